@@ -1,18 +1,24 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
-use rusty_yard::evaluator::eval_str_with_vars_and_ctx;
+use rusty_yard::evaluator::{
+    eval_over, eval_str_fast, eval_str_with_vars_and_ctx, eval_with_vars_and_ctx,
+};
+use rusty_yard::functions::Func;
+use rusty_yard::operators::{binary, BiOp};
+use rusty_yard::parser::{optimize, parse_iter, parse_str};
+use rusty_yard::tokenizer::{tokenize, tokens};
 use rusty_yard::Ctx;
 use std::collections::HashMap;
-use std::iter::{once, repeat};
+use std::iter::{once, repeat_n};
 
 pub fn bench_default_ctx(c: &mut Criterion) {
-    let one_operator = black_box(repeat("1.0").take(1000).collect::<Vec<_>>().join(" + "));
+    let one_operator = black_box(repeat_n("1.0", 1000).collect::<Vec<_>>().join(" + "));
     let two_operators = black_box::<String>(
-        repeat("10.0").take(500).collect::<Vec<_>>().join(" + ")
+        repeat_n("10.0", 500).collect::<Vec<_>>().join(" + ")
             + " * "
-            + &repeat("20.0").take(500).collect::<Vec<_>>().join(" * "),
+            + &repeat_n("20.0", 500).collect::<Vec<_>>().join(" * "),
     );
     let one_operator_right_associative =
-        black_box::<String>(repeat("1.0").take(1000).collect::<Vec<_>>().join(" ^ "));
+        black_box::<String>(repeat_n("1.0", 1000).collect::<Vec<_>>().join(" ^ "));
     let mut vars = HashMap::new();
     let ctx = Ctx::default();
     let mut eval = |s: &str| eval_str_with_vars_and_ctx(s, &mut vars, &ctx);
@@ -30,16 +36,14 @@ pub fn bench_default_ctx(c: &mut Criterion) {
 }
 
 pub fn bench_nested_expression(c: &mut Criterion) {
-    let no_fn = repeat("(")
-        .take(1000)
+    let no_fn = repeat_n("(", 1000)
         .chain(once("10.0"))
-        .chain(repeat(")").take(1000))
+        .chain(repeat_n(")", 1000))
         .collect::<Vec<_>>()
         .join("");
-    let with_fn = repeat("sum(")
-        .take(1000)
+    let with_fn = repeat_n("sum(", 1000)
         .chain(once("10.0"))
-        .chain(repeat(")").take(1000))
+        .chain(repeat_n(")", 1000))
         .collect::<Vec<_>>()
         .join("");
     let mut vars = HashMap::new();
@@ -54,5 +58,102 @@ pub fn bench_nested_expression(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, bench_default_ctx, bench_nested_expression);
+pub fn bench_fast_path(c: &mut Criterion) {
+    let simple = black_box("a * b + c");
+    let mut vars = HashMap::new();
+    vars.insert("a".to_owned(), 1.0);
+    vars.insert("b".to_owned(), 2.0);
+    vars.insert("c".to_owned(), 3.0);
+    let ctx = Ctx::default();
+    let mut g = c.benchmark_group("simple expression");
+    g.bench_function("general pipeline", |b| {
+        b.iter(|| eval_str_with_vars_and_ctx(simple, &mut vars, &ctx));
+    });
+    g.bench_function("fast path", |b| {
+        b.iter(|| eval_str_fast(simple, &mut vars, &ctx));
+    });
+    g.finish()
+}
+
+pub fn bench_large_ctx(c: &mut Criterion) {
+    let mut ctx = Ctx::default();
+    for i in 0..300 {
+        ctx.bi_ops.push(BiOp {
+            token: format!("op{}", i),
+            ..binary::PLUS.clone()
+        });
+    }
+    for i in 0..300 {
+        ctx.fns
+            .push(Func::new(&format!("fn{}", i), Some(1), |args| args[0]));
+    }
+    let input = black_box(
+        repeat_n("a op299 fn299(b)", 500)
+            .collect::<Vec<_>>()
+            .join(" op0 "),
+    );
+    let mut g = c.benchmark_group("large context");
+    g.bench_function("tokenize", |b| {
+        b.iter(|| tokenize(&input, &ctx));
+    });
+    g.bench_function("parse", |b| {
+        b.iter(|| parse_str(&input, &ctx));
+    });
+    g.bench_function("parse lazily", |b| {
+        b.iter(|| parse_iter(tokens(&input, &ctx), &ctx));
+    });
+    g.finish()
+}
+
+pub fn bench_optimize(c: &mut Criterion) {
+    let input = black_box("x * (2 * pi / 360) + 0 - -(1 / 3)");
+    let mut vars = HashMap::new();
+    vars.insert("x".to_owned(), 90.0);
+    let ctx = Ctx::default();
+    let tokens = parse_str(input, &ctx).unwrap();
+    let optimized = optimize(parse_str(input, &ctx).unwrap(), &ctx);
+    let mut g = c.benchmark_group("partially constant expression");
+    g.bench_function("eval", |b| {
+        b.iter(|| eval_with_vars_and_ctx(&tokens, &mut vars, &ctx));
+    });
+    g.bench_function("eval optimized", |b| {
+        b.iter(|| eval_with_vars_and_ctx(&optimized, &mut vars, &ctx));
+    });
+    g.finish()
+}
+
+pub fn bench_batch(c: &mut Criterion) {
+    let ctx = Ctx::default();
+    let tokens = parse_str("a * x ^ 2 + b * x + c", &ctx).unwrap();
+    let xs: Vec<f64> = (0..1000).map(|i| i as f64 / 100.0).collect();
+    let mut vars = HashMap::new();
+    vars.insert("a".to_owned(), 1.0);
+    vars.insert("b".to_owned(), 2.0);
+    vars.insert("c".to_owned(), 3.0);
+    let mut g = c.benchmark_group("1000 values of x");
+    g.bench_function("eval in a loop", |b| {
+        b.iter(|| {
+            xs.iter()
+                .map(|x| {
+                    vars.insert("x".to_owned(), *x);
+                    eval_with_vars_and_ctx(&tokens, &mut vars, &ctx)
+                })
+                .collect::<Vec<_>>()
+        });
+    });
+    g.bench_function("eval over", |b| {
+        b.iter(|| eval_over(&tokens, "x", &xs, &mut vars, &ctx));
+    });
+    g.finish()
+}
+
+criterion_group!(
+    benches,
+    bench_default_ctx,
+    bench_nested_expression,
+    bench_fast_path,
+    bench_large_ctx,
+    bench_optimize,
+    bench_batch
+);
 criterion_main!(benches);
@@ -34,10 +34,7 @@ fn with_context() {
     // default ctx with operators one might expect for shunting yard
     let mut ctx = Ctx::default();
     // add $$$ operator with some action
-    ctx.u_ops.push(UOp {
-        token: "$$$".to_owned(),
-        func: |v| v * 1000.0,
-    });
+    ctx.u_ops.push(UOp::new("$$$", |v| v * 1000.0));
 
     let result = evaluator::eval_str_with_vars_and_ctx("$$$42.0", &mut vars, &ctx).unwrap();
 
@@ -0,0 +1,303 @@
+//! Dependencies between the statements of multi-statement input, see [`dependencies`](dependencies).
+use std::collections::BTreeSet;
+
+use thiserror::Error;
+
+use super::variables_of;
+use crate::parser::ParserToken;
+use crate::Ctx;
+
+/// Represents the error returned by [`Dependencies::order`](Dependencies::order)
+/// when the statements depend on each other.
+#[derive(Debug, Error, Clone, Eq, PartialEq)]
+#[error("Circular dependency between statements {statements:?}")]
+pub struct CycleError {
+    /// Indices of the statements that form the cycle.
+    pub statements: Vec<usize>,
+}
+
+/// The dependency graph of the statements, see [`dependencies`](dependencies).
+///
+/// Statements are identified by their index in the list passed to [`dependencies`](dependencies).
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct Dependencies<'t> {
+    /// The variables each statement assigns.
+    pub assigns: Vec<BTreeSet<&'t str>>,
+    /// The variables each statement reads, including the reads inside the macros, see [`variables_of`](super::variables_of).
+    pub reads: Vec<BTreeSet<String>>,
+    /// The statements each statement depends on, i.e. the statements that assign the variables it reads.
+    pub depends_on: Vec<BTreeSet<usize>>,
+    /// The variables that are read but not assigned by any statement, they have to be provided by the caller.
+    pub inputs: BTreeSet<String>,
+}
+
+impl Dependencies<'_> {
+    /// Returns the order in which the statements can be evaluated,
+    /// every statement comes after the statements it depends on.
+    ///
+    /// The independent statements keep their original order.
+    /// Returns [`CycleError`](CycleError) with the first cycle if the statements depend on each other.
+    pub fn order(&self) -> Result<Vec<usize>, CycleError> {
+        let n = self.depends_on.len();
+        let mut remaining: Vec<usize> = self.depends_on.iter().map(BTreeSet::len).collect();
+        let mut dependents = vec![Vec::new(); n];
+        for (statement, depends_on) in self.depends_on.iter().enumerate() {
+            for dependency in depends_on {
+                dependents[*dependency].push(statement);
+            }
+        }
+        let mut ready: BTreeSet<usize> = (0..n).filter(|s| remaining[*s] == 0).collect();
+        let mut order = Vec::with_capacity(n);
+        while let Some(statement) = ready.iter().next().copied() {
+            ready.remove(&statement);
+            order.push(statement);
+            for dependent in &dependents[statement] {
+                remaining[*dependent] -= 1;
+                if remaining[*dependent] == 0 {
+                    ready.insert(*dependent);
+                }
+            }
+        }
+        if order.len() == n {
+            Ok(order)
+        } else {
+            let statements = self.cycles().into_iter().next().unwrap_or_default();
+            Err(CycleError { statements })
+        }
+    }
+
+    /// Returns the groups of statements that depend on each other, including the statements that read
+    /// the variable they assign.
+    ///
+    /// Each group is sorted, the groups are sorted by their first statement.
+    pub fn cycles(&self) -> Vec<Vec<usize>> {
+        let mut tarjan = Tarjan {
+            graph: &self.depends_on,
+            index: vec![None; self.depends_on.len()],
+            low_link: vec![0; self.depends_on.len()],
+            on_stack: vec![false; self.depends_on.len()],
+            stack: Vec::new(),
+            next_index: 0,
+            components: Vec::new(),
+        };
+        for statement in 0..self.depends_on.len() {
+            if tarjan.index[statement].is_none() {
+                tarjan.visit(statement);
+            }
+        }
+        let mut cycles: Vec<_> = tarjan
+            .components
+            .into_iter()
+            .filter(|c| c.len() > 1 || self.depends_on[c[0]].contains(&c[0]))
+            .map(|mut c| {
+                c.sort_unstable();
+                c
+            })
+            .collect();
+        cycles.sort_unstable();
+        cycles
+    }
+
+    /// Returns the statements that assign variables no other statement reads.
+    ///
+    /// The statements without assignments are never dead, they are the results of the input.
+    pub fn dead_assignments(&self) -> Vec<usize> {
+        (0..self.assigns.len())
+            .filter(|statement| {
+                let assigns = &self.assigns[*statement];
+                !assigns.is_empty()
+                    && !self.reads.iter().enumerate().any(|(other, reads)| {
+                        other != *statement
+                            && reads.iter().any(|var| assigns.contains(var.as_str()))
+                    })
+            })
+            .collect()
+    }
+}
+
+/// Tarjan's strongly connected components algorithm
+struct Tarjan<'g> {
+    graph: &'g [BTreeSet<usize>],
+    index: Vec<Option<usize>>,
+    low_link: Vec<usize>,
+    on_stack: Vec<bool>,
+    stack: Vec<usize>,
+    next_index: usize,
+    components: Vec<Vec<usize>>,
+}
+
+impl Tarjan<'_> {
+    fn visit(&mut self, node: usize) {
+        self.index[node] = Some(self.next_index);
+        self.low_link[node] = self.next_index;
+        self.next_index += 1;
+        self.stack.push(node);
+        self.on_stack[node] = true;
+        for next in self.graph[node].iter().copied() {
+            match self.index[next] {
+                None => {
+                    self.visit(next);
+                    self.low_link[node] = self.low_link[node].min(self.low_link[next]);
+                }
+                Some(index) if self.on_stack[next] => {
+                    self.low_link[node] = self.low_link[node].min(index);
+                }
+                Some(_) => {}
+            }
+        }
+        if Some(self.low_link[node]) == self.index[node] {
+            let mut component = Vec::new();
+            while let Some(top) = self.stack.pop() {
+                self.on_stack[top] = false;
+                component.push(top);
+                if top == node {
+                    break;
+                }
+            }
+            self.components.push(component);
+        }
+    }
+}
+
+/// Builds the dependency graph between the statements of multi-statement input,
+/// e.g. the statements parsed by [`parse_statements`](crate::parser::parse_statements).
+///
+/// The statements are treated as a sheet of formulas, i.e. the statement that reads a variable
+/// depends on every other statement that assigns it, regardless of their order in the input.
+/// The variables are assigned by the macros, see [`ParsedMacro::assigns`](crate::macros::ParsedMacro::assigns),
+/// and read by the statements and the expressions inside their macros, e.g. the branches of `if`.
+///
+/// # Example
+///
+/// ```
+/// use rusty_yard::analysis::dependencies;
+/// use rusty_yard::parser::parse_statements;
+/// use rusty_yard::Ctx;
+///
+/// let ctx = Ctx::default_with_macros();
+/// let statements = parse_statements("area = width * height; width = 2 * height; unused = 1", &ctx).unwrap();
+/// let dependencies = dependencies(&statements, &ctx);
+/// assert_eq!(dependencies.order(), Ok(vec![1, 0, 2]));
+/// assert_eq!(dependencies.inputs.iter().collect::<Vec<_>>(), vec!["height"]);
+/// assert_eq!(dependencies.dead_assignments(), vec![0, 2]);
+/// ```
+pub fn dependencies<'t>(statements: &'t [Vec<ParserToken<'_, '_>>], ctx: &Ctx) -> Dependencies<'t> {
+    let assigns: Vec<BTreeSet<&'t str>> = statements
+        .iter()
+        .map(|tokens| {
+            tokens
+                .iter()
+                .filter_map(|token| match token {
+                    ParserToken::Macro(m) => m.assigns(),
+                    _ => None,
+                })
+                .collect()
+        })
+        .collect();
+    let reads: Vec<BTreeSet<String>> = statements
+        .iter()
+        .map(|tokens| variables_of(tokens, ctx))
+        .collect();
+    let mut inputs = BTreeSet::new();
+    let depends_on = reads
+        .iter()
+        .map(|reads| {
+            let mut depends_on = BTreeSet::new();
+            for var in reads {
+                let assigned_by = assigns
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, a)| a.contains(var.as_str()));
+                let mut is_assigned = false;
+                for (statement, _) in assigned_by {
+                    depends_on.insert(statement);
+                    is_assigned = true;
+                }
+                if !is_assigned {
+                    inputs.insert(var.clone());
+                }
+            }
+            depends_on
+        })
+        .collect();
+    Dependencies {
+        assigns,
+        reads,
+        depends_on,
+        inputs,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_statements;
+    use crate::Ctx;
+
+    #[test]
+    fn test_order() {
+        let input_expected: &[(&str, &[usize])] = &[
+            ("", &[]),
+            ("a = 1; b = 2", &[0, 1]),
+            ("c = a + b; a = 1; b = a * 2", &[1, 2, 0]),
+            ("a + b; b = a; a = 1", &[2, 1, 0]),
+            ("y = x = 1; x + y", &[0, 1]),
+            ("c = if(a > 0, a * b, 0); a = 1; b = 2", &[1, 2, 0]),
+            ("c = let t = a in t * 2; a = 1", &[1, 0]),
+        ];
+        let ctx = Ctx::default_with_macros();
+        for (input, expected) in input_expected {
+            let statements = parse_statements(input, &ctx).unwrap();
+            let order = dependencies(&statements, &ctx).order();
+            assert_eq!(order, Ok(expected.to_vec()), "input was {}", input);
+        }
+    }
+
+    #[test]
+    fn test_cycles() {
+        let input_expected: &[(&str, &[&[usize]])] = &[
+            ("a = 1; b = a", &[]),
+            ("a = a + 1", &[&[0]]),
+            ("a = b + 1; c = 1; b = a", &[&[0, 2]]),
+            ("d = c; a = b; c = d; b = a + c", &[&[0, 2], &[1, 3]]),
+        ];
+        let ctx = Ctx::default_with_macros();
+        for (input, expected) in input_expected {
+            let statements = parse_statements(input, &ctx).unwrap();
+            let dependencies = dependencies(&statements, &ctx);
+            let expected: Vec<Vec<usize>> = expected.iter().map(|c| c.to_vec()).collect();
+            assert_eq!(dependencies.cycles(), expected, "input was {}", input);
+            match expected.first() {
+                Some(cycle) => assert_eq!(
+                    dependencies.order(),
+                    Err(CycleError {
+                        statements: cycle.clone()
+                    }),
+                    "input was {}",
+                    input
+                ),
+                None => assert!(dependencies.order().is_ok(), "input was {}", input),
+            }
+        }
+    }
+
+    #[test]
+    fn test_dead_assignments_and_inputs() {
+        let ctx = Ctx::default_with_macros();
+        let statements = parse_statements("a = x; b = a + y; c = 1; a = a + 1\nb", &ctx).unwrap();
+        let dependencies = dependencies(&statements, &ctx);
+        assert_eq!(dependencies.dead_assignments(), vec![2]);
+        assert_eq!(
+            dependencies.inputs.into_iter().collect::<Vec<_>>(),
+            vec!["x", "y"]
+        );
+        // the reads inside the branches of `if` are inputs and keep the assignments alive
+        let statements = parse_statements("a = 1; if(c, a, d)", &ctx).unwrap();
+        let dependencies = super::dependencies(&statements, &ctx);
+        assert_eq!(dependencies.dead_assignments(), Vec::<usize>::new());
+        assert_eq!(
+            dependencies.inputs.into_iter().collect::<Vec<_>>(),
+            vec!["c", "d"]
+        );
+    }
+}
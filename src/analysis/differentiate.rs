@@ -0,0 +1,592 @@
+//! Symbolic differentiation of the expressions, see [`differentiate`](differentiate).
+use thiserror::Error;
+
+use super::node::{self, Node};
+use crate::expression::Expression;
+use crate::functions::{
+    Func, FN_ABS, FN_ACOS, FN_ASIN, FN_ATAN, FN_ATAN2, FN_CBRT, FN_CEIL, FN_COS, FN_COSH, FN_EXP,
+    FN_FLOOR, FN_HYPOT, FN_LN, FN_LOG, FN_LOG10, FN_LOG2, FN_PROD, FN_ROUND, FN_SIGNUM, FN_SIN,
+    FN_SINH, FN_SQRT, FN_SUB, FN_SUM, FN_TAN, FN_TANH,
+};
+use crate::operators::binary::Builtin;
+use crate::operators::{unary, BiOp, UOp};
+use crate::Ctx;
+
+/// Represents the error that can occur during [`differentiate`](differentiate).
+#[derive(Debug, Error, Clone, PartialEq)]
+pub enum Error {
+    /// The expression can't be converted into the syntax tree.
+    #[error("{0}")]
+    Node(#[from] node::Error),
+    /// The derivative of the operator or function is not known.
+    #[error("Derivative of {0} is not known")]
+    NoDerivative(String),
+    /// The context does not contain the binary operator needed to write the derivative.
+    #[error("Context does not contain {0:?} operator")]
+    MissingOperator(Builtin),
+    /// The context does not contain the function needed to write the derivative, e.g. `cos` for `sin`.
+    #[error("Context does not contain {0} function")]
+    MissingFunction(String),
+}
+
+/// Hook that provides the derivatives of the functions [`differentiate`](differentiate) does not know.
+///
+/// It is called with the function, its arguments and the index of the argument,
+/// and returns the partial derivative of the function with respect to that argument,
+/// or [`None`](std::option::Option::None) if it is not known.
+pub type DerivativeHook<'h, 'a, 'ctx> =
+    dyn Fn(&'ctx Func, &[Node<'a, 'ctx>], usize) -> Option<Node<'a, 'ctx>> + 'h;
+
+/// Returns the derivative of the expression with respect to the variable `var`.
+///
+/// The following are differentiated:
+///
+/// - numbers and variables;
+/// - [`+`](crate::operators::unary::PLUS) and [`-`](crate::operators::unary::NEGATE) unary operators;
+/// - binary operators with [`builtin`](crate::operators::BiOp::builtin) arithmetic,
+///   `u ^ v` needs the [`ln`](crate::functions::FN_LN) function in the context if `v` depends on `var`
+///   and `u` is not a number;
+/// - [`sum`](crate::functions::FN_SUM), [`prod`](crate::functions::FN_PROD) and [`sub`](crate::functions::FN_SUB) functions;
+/// - the other [`default_functions`](crate::functions::default_functions) except `max` and `min`,
+///   using the chain rule, e.g. `sin(u)' = cos(u) * u'`.
+///   The functions used by the derivative must be in the context, e.g. `cos` for `sin`.
+///   `floor`, `ceil`, `round` and `signum` are constant between their jumps, so their derivative is 0.
+///
+/// The functions are compared with [`PartialEq`](crate::functions::Func#impl-PartialEq-for-Func),
+/// so the function replaced in the context, e.g. `sin` taking degrees, is not differentiated.
+/// Use [`differentiate_with`](differentiate_with) to provide the derivatives of other functions.
+///
+/// The result is not simplified, but the subexpressions that don't depend on `var` are not differentiated.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use rusty_yard::analysis::differentiate;
+/// use rusty_yard::expression::Expression;
+/// use rusty_yard::Ctx;
+///
+/// let ctx = Ctx::default();
+/// let expr = Expression::parse("x ^ 3 + 2 * x", &ctx).unwrap();
+/// let derivative = differentiate(&expr, "x").unwrap();
+/// let mut vars = HashMap::new();
+/// vars.insert("x".to_owned(), 2.0);
+/// assert_eq!(derivative.eval(&mut vars), Ok(14.0));
+/// ```
+pub fn differentiate<'a, 'ctx>(
+    expr: &Expression<'a, 'ctx>,
+    var: &str,
+) -> Result<Expression<'a, 'ctx>, Error> {
+    differentiate_with(expr, var, &|_, _, _| None)
+}
+
+/// Returns the derivative of the expression with respect to the variable `var`,
+/// using the `hook` for the functions that are not known.
+///
+/// The hook is called before the built-in rules, so it can override them.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use rusty_yard::analysis::{differentiate_with, Node};
+/// use rusty_yard::expression::Expression;
+/// use rusty_yard::functions::Func;
+/// use rusty_yard::Ctx;
+///
+/// let mut ctx = Ctx::default();
+/// ctx.fns.push(Func::new("exp", Some(1), |args| args[0].exp()));
+/// let expr = Expression::parse("exp(2 * x)", &ctx).unwrap();
+/// // exp'(u) = exp(u)
+/// let derivative = differentiate_with(&expr, "x", &|func, args, _| match func.token.as_str() {
+///     "exp" => Some(Node::Func(func, args.to_vec())),
+///     _ => None,
+/// })
+/// .unwrap();
+/// let mut vars = HashMap::new();
+/// vars.insert("x".to_owned(), 0.0);
+/// assert_eq!(derivative.eval(&mut vars), Ok(2.0));
+/// ```
+pub fn differentiate_with<'a, 'ctx>(
+    expr: &Expression<'a, 'ctx>,
+    var: &str,
+    hook: &DerivativeHook<'_, 'a, 'ctx>,
+) -> Result<Expression<'a, 'ctx>, Error> {
+    let node = Node::from_tokens(expr.tokens())?;
+    let differentiator = Differentiator {
+        ctx: expr.ctx(),
+        var,
+        hook,
+    };
+    let derivative = differentiator.d(&node)?.unwrap_or(Node::Num(0.0));
+    Ok(Expression::from_tokens(derivative.to_tokens(), expr.ctx()))
+}
+
+struct Differentiator<'h, 'a, 'ctx> {
+    ctx: &'ctx Ctx,
+    var: &'h str,
+    hook: &'h DerivativeHook<'h, 'a, 'ctx>,
+}
+
+impl<'h, 'a, 'ctx> Differentiator<'h, 'a, 'ctx> {
+    /// Differentiates the `node`, [`None`](std::option::Option::None) stands for 0.
+    fn d(&self, node: &Node<'a, 'ctx>) -> Result<Option<Node<'a, 'ctx>>, Error> {
+        if !node.contains_var(self.var) {
+            return Ok(None);
+        }
+        let derivative = match node {
+            Node::Num(_) => None,
+            Node::Var(_) => Some(Node::Num(1.0)),
+            Node::UOp(op, operand) => self.d_u_op(op, operand)?,
+            Node::BiOp(op, left, right) => self.d_bi_op(op, left, right)?,
+            Node::Func(func, args) => self.d_func(func, args)?,
+        };
+        Ok(derivative)
+    }
+
+    fn d_u_op(
+        &self,
+        op: &'ctx UOp,
+        operand: &Node<'a, 'ctx>,
+    ) -> Result<Option<Node<'a, 'ctx>>, Error> {
+        let d_operand = self.d(operand)?;
+        if *op == *unary::PLUS {
+            Ok(d_operand)
+        } else if *op == *unary::NEGATE {
+            Ok(d_operand.map(|d| Node::UOp(op, Box::new(d))))
+        } else {
+            Err(Error::NoDerivative(op.token.clone()))
+        }
+    }
+
+    fn d_bi_op(
+        &self,
+        op: &'ctx BiOp,
+        u: &Node<'a, 'ctx>,
+        v: &Node<'a, 'ctx>,
+    ) -> Result<Option<Node<'a, 'ctx>>, Error> {
+        let builtin = op
+            .builtin
+            .ok_or_else(|| Error::NoDerivative(op.token.clone()))?;
+        let du = self.d(u)?;
+        let dv = self.d(v)?;
+        let derivative = match builtin {
+            Builtin::Add => match (du, dv) {
+                (Some(du), Some(dv)) => Some(self.bi_op(Builtin::Add, du, dv)?),
+                (du, dv) => du.or(dv),
+            },
+            Builtin::Subtract => match (du, dv) {
+                (Some(du), Some(dv)) => Some(self.bi_op(Builtin::Subtract, du, dv)?),
+                (du, None) => du,
+                (None, Some(dv)) => Some(self.neg(dv)?),
+            },
+            // (u * v)' = u' * v + u * v'
+            Builtin::Multiply => {
+                let left = du
+                    .map(|du| self.bi_op(Builtin::Multiply, du, v.clone()))
+                    .transpose()?;
+                let right = dv
+                    .map(|dv| self.bi_op(Builtin::Multiply, u.clone(), dv))
+                    .transpose()?;
+                self.sum(left, right)?
+            }
+            // (u / v)' = (u' * v - u * v') / v ^ 2
+            Builtin::Divide => match (du, dv) {
+                (Some(du), None) => Some(self.bi_op(Builtin::Divide, du, v.clone())?),
+                (du, Some(dv)) => {
+                    let u_dv = self.bi_op(Builtin::Multiply, u.clone(), dv)?;
+                    let numerator = match du {
+                        Some(du) => {
+                            let du_v = self.bi_op(Builtin::Multiply, du, v.clone())?;
+                            self.bi_op(Builtin::Subtract, du_v, u_dv)?
+                        }
+                        None => self.neg(u_dv)?,
+                    };
+                    let denominator = self.bi_op(Builtin::Power, v.clone(), Node::Num(2.0))?;
+                    Some(self.bi_op(Builtin::Divide, numerator, denominator)?)
+                }
+                (None, None) => None,
+            },
+            Builtin::Power => match (du, dv) {
+                // (u ^ c)' = c * u ^ (c - 1) * u'
+                (Some(du), None) => {
+                    let exponent = match v {
+                        Node::Num(c) => Node::Num(c - 1.0),
+                        v => self.bi_op(Builtin::Subtract, v.clone(), Node::Num(1.0))?,
+                    };
+                    let power = self.bi_op(Builtin::Power, u.clone(), exponent)?;
+                    let scaled = self.bi_op(Builtin::Multiply, v.clone(), power)?;
+                    Some(self.bi_op(Builtin::Multiply, scaled, du)?)
+                }
+                // (c ^ v)' = c ^ v * ln(c) * v'
+                // (u ^ v)' = u ^ v * ln(u) * v'
+                (None, Some(dv)) => {
+                    let ln_u = match u {
+                        Node::Num(c) => Node::Num(c.ln()),
+                        u => self.func(&FN_LN, vec![u.clone()])?,
+                    };
+                    let power = self.bi_op(Builtin::Power, u.clone(), v.clone())?;
+                    let scaled = self.bi_op(Builtin::Multiply, power, ln_u)?;
+                    Some(self.bi_op(Builtin::Multiply, scaled, dv)?)
+                }
+                // (u ^ v)' = u ^ v * (v' * ln(u) + v * u' / u)
+                (Some(du), Some(dv)) => {
+                    let ln_u = self.func(&FN_LN, vec![u.clone()])?;
+                    let left = self.bi_op(Builtin::Multiply, dv, ln_u)?;
+                    let v_du = self.bi_op(Builtin::Multiply, v.clone(), du)?;
+                    let right = self.bi_op(Builtin::Divide, v_du, u.clone())?;
+                    let sum = self.bi_op(Builtin::Add, left, right)?;
+                    let power = self.bi_op(Builtin::Power, u.clone(), v.clone())?;
+                    Some(self.bi_op(Builtin::Multiply, power, sum)?)
+                }
+                (None, None) => None,
+            },
+        };
+        Ok(derivative)
+    }
+
+    fn d_func(
+        &self,
+        func: &'ctx Func,
+        args: &[Node<'a, 'ctx>],
+    ) -> Result<Option<Node<'a, 'ctx>>, Error> {
+        let d_args = args
+            .iter()
+            .map(|arg| self.d(arg))
+            .collect::<Result<Vec<_>, _>>()?;
+        // chain rule using the partial derivatives provided by the hook or by the built-in rules
+        let mut partials = Vec::new();
+        for (i, _) in d_args
+            .iter()
+            .enumerate()
+            .filter(|(_, d_arg)| d_arg.is_some())
+        {
+            match (self.hook)(func, args, i) {
+                Some(partial) => partials.push(partial),
+                None => match self.partial(func, args, i)? {
+                    Some(partial) => partials.push(partial),
+                    None => break,
+                },
+            }
+        }
+        if partials.len() == d_args.iter().flatten().count() {
+            let mut result = None;
+            let d_args = d_args.into_iter().flatten();
+            for (partial, d_arg) in partials.into_iter().zip(d_args) {
+                if partial != Node::Num(0.0) {
+                    let term = self.bi_op(Builtin::Multiply, partial, d_arg)?;
+                    result = self.sum(result, Some(term))?;
+                }
+            }
+            return Ok(result);
+        }
+        if *func == *FN_SUM {
+            let mut result = None;
+            for d_arg in d_args {
+                result = self.sum(result, d_arg)?;
+            }
+            Ok(result)
+        } else if *func == *FN_SUB {
+            let mut d_args = d_args.into_iter();
+            let (du, dv) = (d_args.next().flatten(), d_args.next().flatten());
+            let dv = dv.unwrap_or(Node::Num(0.0));
+            let du = du.unwrap_or(Node::Num(0.0));
+            Ok(Some(Node::Func(func, vec![du, dv])))
+        } else if *func == *FN_PROD {
+            // (a * b * c)' = a' * b * c + a * b' * c + a * b * c'
+            let mut result = None;
+            for (i, d_arg) in d_args.into_iter().enumerate() {
+                if let Some(d_arg) = d_arg {
+                    let mut factors = args.to_vec();
+                    factors[i] = d_arg;
+                    result = self.sum(result, Some(Node::Func(func, factors)))?;
+                }
+            }
+            Ok(result)
+        } else {
+            Err(Error::NoDerivative(func.token.clone()))
+        }
+    }
+
+    /// Returns the partial derivative of the default function with respect to the argument `i`,
+    /// [`None`](std::option::Option::None) if the function is not known.
+    fn partial(
+        &self,
+        func: &'ctx Func,
+        args: &[Node<'a, 'ctx>],
+        i: usize,
+    ) -> Result<Option<Node<'a, 'ctx>>, Error> {
+        let u = || args[0].clone();
+        let one_over = |node| self.bi_op(Builtin::Divide, Node::Num(1.0), node);
+        let square = |node| self.bi_op(Builtin::Power, node, Node::Num(2.0));
+        let partial = if *func == *FN_SIN {
+            self.func(&FN_COS, vec![u()])?
+        } else if *func == *FN_COS {
+            let sin = self.func(&FN_SIN, vec![u()])?;
+            self.neg(sin)?
+        } else if *func == *FN_TAN {
+            // 1 / cos(u) ^ 2
+            one_over(square(self.func(&FN_COS, vec![u()])?)?)?
+        } else if *func == *FN_ASIN || *func == *FN_ACOS {
+            // ±1 / sqrt(1 - u ^ 2)
+            let difference = self.bi_op(Builtin::Subtract, Node::Num(1.0), square(u())?)?;
+            let partial = one_over(self.func(&FN_SQRT, vec![difference])?)?;
+            if *func == *FN_ASIN {
+                partial
+            } else {
+                self.neg(partial)?
+            }
+        } else if *func == *FN_ATAN {
+            one_over(self.bi_op(Builtin::Add, Node::Num(1.0), square(u())?)?)?
+        } else if *func == *FN_SINH {
+            self.func(&FN_COSH, vec![u()])?
+        } else if *func == *FN_COSH {
+            self.func(&FN_SINH, vec![u()])?
+        } else if *func == *FN_TANH {
+            let tanh = self.func(&FN_TANH, vec![u()])?;
+            self.bi_op(Builtin::Subtract, Node::Num(1.0), square(tanh)?)?
+        } else if *func == *FN_EXP {
+            self.func(&FN_EXP, vec![u()])?
+        } else if *func == *FN_LN {
+            one_over(u())?
+        } else if *func == *FN_LOG10 || *func == *FN_LOG2 {
+            // 1 / (u * ln(base))
+            let ln_base = if *func == *FN_LOG10 {
+                std::f64::consts::LN_10
+            } else {
+                std::f64::consts::LN_2
+            };
+            one_over(self.bi_op(Builtin::Multiply, u(), Node::Num(ln_base))?)?
+        } else if *func == *FN_LOG {
+            // log(b, x) = ln(x) / ln(b)
+            let (base, x) = (args[0].clone(), args[1].clone());
+            let ln_base = self.func(&FN_LN, vec![base.clone()])?;
+            if i == 0 {
+                // -ln(x) / (b * ln(b) ^ 2)
+                let ln_x = self.func(&FN_LN, vec![x])?;
+                let denominator = self.bi_op(Builtin::Multiply, base, square(ln_base)?)?;
+                let partial = self.bi_op(Builtin::Divide, ln_x, denominator)?;
+                self.neg(partial)?
+            } else {
+                one_over(self.bi_op(Builtin::Multiply, x, ln_base)?)?
+            }
+        } else if *func == *FN_SQRT {
+            let sqrt = self.func(&FN_SQRT, vec![u()])?;
+            self.bi_op(Builtin::Divide, Node::Num(0.5), sqrt)?
+        } else if *func == *FN_CBRT {
+            // 1 / (3 * cbrt(u) ^ 2)
+            let cbrt = self.func(&FN_CBRT, vec![u()])?;
+            let scaled = self.bi_op(Builtin::Multiply, Node::Num(3.0), square(cbrt)?)?;
+            one_over(scaled)?
+        } else if *func == *FN_ABS {
+            self.func(&FN_SIGNUM, vec![u()])?
+        } else if *func == *FN_ATAN2 || *func == *FN_HYPOT {
+            let (a, b) = (args[0].clone(), args[1].clone());
+            if *func == *FN_ATAN2 {
+                // atan2(y, x): x / (x ^ 2 + y ^ 2) and -y / (x ^ 2 + y ^ 2)
+                let denominator =
+                    self.bi_op(Builtin::Add, square(b.clone())?, square(a.clone())?)?;
+                if i == 0 {
+                    self.bi_op(Builtin::Divide, b, denominator)?
+                } else {
+                    let partial = self.bi_op(Builtin::Divide, a, denominator)?;
+                    self.neg(partial)?
+                }
+            } else {
+                // hypot(a, b): a / hypot(a, b) and b / hypot(a, b)
+                let hypot = self.func(&FN_HYPOT, vec![a.clone(), b.clone()])?;
+                let numerator = if i == 0 { a } else { b };
+                self.bi_op(Builtin::Divide, numerator, hypot)?
+            }
+        } else if *func == *FN_FLOOR
+            || *func == *FN_CEIL
+            || *func == *FN_ROUND
+            || *func == *FN_SIGNUM
+        {
+            Node::Num(0.0)
+        } else {
+            return Ok(None);
+        };
+        Ok(Some(partial))
+    }
+
+    fn func(&self, func: &Func, args: Vec<Node<'a, 'ctx>>) -> Result<Node<'a, 'ctx>, Error> {
+        let func = self
+            .ctx
+            .fns
+            .iter()
+            .find(|f| **f == *func)
+            .ok_or_else(|| Error::MissingFunction(func.token.clone()))?;
+        Ok(Node::Func(func, args))
+    }
+
+    fn bi_op(
+        &self,
+        builtin: Builtin,
+        left: Node<'a, 'ctx>,
+        right: Node<'a, 'ctx>,
+    ) -> Result<Node<'a, 'ctx>, Error> {
+        let op = self
+            .ctx
+            .bi_ops
+            .iter()
+            .find(|op| op.builtin == Some(builtin))
+            .ok_or(Error::MissingOperator(builtin))?;
+        Ok(Node::BiOp(op, Box::new(left), Box::new(right)))
+    }
+
+    fn neg(&self, node: Node<'a, 'ctx>) -> Result<Node<'a, 'ctx>, Error> {
+        match self.ctx.u_ops.iter().find(|op| **op == *unary::NEGATE) {
+            Some(op) => Ok(Node::UOp(op, Box::new(node))),
+            None => self.bi_op(Builtin::Multiply, Node::Num(-1.0), node),
+        }
+    }
+
+    fn sum(
+        &self,
+        left: Option<Node<'a, 'ctx>>,
+        right: Option<Node<'a, 'ctx>>,
+    ) -> Result<Option<Node<'a, 'ctx>>, Error> {
+        match (left, right) {
+            (Some(left), Some(right)) => Ok(Some(self.bi_op(Builtin::Add, left, right)?)),
+            (left, right) => Ok(left.or(right)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::operators::binary;
+
+    fn eval_derivative(input: &str, x: f64) -> Result<f64, Error> {
+        let ctx = Ctx::default();
+        let expr = Expression::parse(input, &ctx).unwrap();
+        let derivative = differentiate(&expr, "x")?;
+        let mut vars = HashMap::new();
+        vars.insert("x".to_owned(), x);
+        vars.insert("y".to_owned(), 5.0);
+        Ok(derivative.eval(&mut vars).unwrap())
+    }
+
+    #[test]
+    fn test_differentiate() {
+        let input_expected = &[
+            ("1", 0.0),
+            ("y", 0.0),
+            ("x", 1.0),
+            ("-x", -1.0),
+            ("+x + y", 1.0),
+            ("3 * x", 3.0),
+            ("x * x", 4.0),
+            ("x - 3 * y", 1.0),
+            ("y - x", -1.0),
+            ("x / 4", 0.25),
+            ("1 / x", -0.25),
+            ("x / (x + 1)", 1.0 / 9.0),
+            ("x ^ 3", 12.0),
+            ("(2 * x + 1) ^ y", 2.0 * 5.0 * 5f64.powi(4)),
+            ("2 ^ x", 4.0 * 2f64.ln()),
+            ("sum(x, x * x, y)", 5.0),
+            ("sub(y, x ^ 2)", -4.0),
+            ("prod(x, x, y)", 20.0),
+            ("floor(3 * x) + x", 1.0),
+            ("abs(-x)", 1.0),
+        ];
+        for (input, expected) in input_expected {
+            let result = eval_derivative(input, 2.0);
+            assert_eq!(result, Ok(*expected), "input was {}", input);
+        }
+    }
+
+    #[test]
+    fn test_differentiate_functions() {
+        let x = 0.5f64;
+        let input_expected = &[
+            ("sin(2 * x)", 2.0 * (2.0 * x).cos()),
+            ("cos(x ^ 2)", -(x * x).sin() * 2.0 * x),
+            ("tan(x)", 1.0 / x.cos().powi(2)),
+            ("asin(x)", 1.0 / (1.0 - x * x).sqrt()),
+            ("acos(x)", -1.0 / (1.0 - x * x).sqrt()),
+            ("atan(y * x)", 5.0 / (1.0 + 25.0 * x * x)),
+            ("sinh(x)", x.cosh()),
+            ("cosh(x)", x.sinh()),
+            ("tanh(x)", 1.0 - x.tanh().powi(2)),
+            ("exp(3 * x)", 3.0 * (3.0 * x).exp()),
+            ("ln(x ^ 2 + 1)", 2.0 * x / (x * x + 1.0)),
+            ("log10(x)", 1.0 / (x * 10f64.ln())),
+            ("log2(x)", 1.0 / (x * 2f64.ln())),
+            ("log(y, x)", 1.0 / (x * 5f64.ln())),
+            ("log(x, y)", -(5f64.ln()) / (x * x.ln().powi(2))),
+            ("sqrt(x)", 0.5 / x.sqrt()),
+            ("cbrt(x)", 1.0 / (3.0 * x.cbrt().powi(2))),
+            ("abs(x - 1)", -1.0),
+            ("atan2(x, y)", 5.0 / (25.0 + x * x)),
+            ("atan2(y, x)", -5.0 / (25.0 + x * x)),
+            ("hypot(x, y)", x / x.hypot(5.0)),
+            ("round(x, 2) + signum(x) + ceil(x)", 0.0),
+            ("x ^ x", x.powf(x) * (x.ln() + 1.0)),
+            ("y ^ x", 5f64.powf(x) * 5f64.ln()),
+            ("sin(x) ^ 2", 2.0 * x.sin() * x.cos()),
+        ];
+        for (input, expected) in input_expected {
+            let result = eval_derivative(input, x).unwrap();
+            assert!(
+                (result - expected).abs() < 1e-12,
+                "input was {}, result {}, expected {}",
+                input,
+                result,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_differentiate_err() {
+        let input_expected = &[
+            ("max(x, 1)", Error::NoDerivative("max".to_owned())),
+            ("min(1, sin(x))", Error::NoDerivative("min".to_owned())),
+        ];
+        for (input, expected) in input_expected {
+            let result = eval_derivative(input, 2.0);
+            assert_eq!(result, Err(expected.clone()), "input was {}", input);
+        }
+        let mut ctx = Ctx::empty();
+        ctx.bi_ops.push(binary::PLUS.clone());
+        ctx.bi_ops.push(binary::POWER.clone());
+        let expr = Expression::parse("x ^ 2 + 1", &ctx).unwrap();
+        assert_eq!(
+            differentiate(&expr, "x").map(|_| ()),
+            Err(Error::MissingOperator(Builtin::Multiply))
+        );
+        // the derivative of sin is written with cos
+        let mut ctx = Ctx::default();
+        ctx.fns.retain(|func| func.token != "cos");
+        let expr = Expression::parse("sin(x)", &ctx).unwrap();
+        assert_eq!(
+            differentiate(&expr, "x").map(|_| ()),
+            Err(Error::MissingFunction("cos".to_owned()))
+        );
+        // sin replaced with the one taking degrees is not the default sin
+        let mut ctx = Ctx::default();
+        ctx.fns.retain(|func| func.token != "sin");
+        ctx.fns
+            .push(Func::new("sin", Some(1), |args| args[0].to_radians().sin()));
+        let expr = Expression::parse("sin(x)", &ctx).unwrap();
+        assert_eq!(
+            differentiate(&expr, "x").map(|_| ()),
+            Err(Error::NoDerivative("sin".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_constant_parts_are_not_differentiated() {
+        let ctx = Ctx::default();
+        let expr = Expression::parse("max(y, 2) * x + max(y, 3)", &ctx).unwrap();
+        let derivative = differentiate(&expr, "x").unwrap();
+        let node = Node::from_tokens(derivative.tokens()).unwrap();
+        assert_eq!(node.to_string(), "(max(y, 2) * 1)");
+    }
+}
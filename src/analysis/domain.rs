@@ -0,0 +1,538 @@
+//! Static interval analysis of the expressions, see [`estimate`](estimate).
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+use std::ops::Range;
+
+use crate::functions::{Func, FN_MAX, FN_PROD, FN_SUB, FN_SUM};
+use crate::operators::binary::Builtin;
+use crate::operators::{unary, BiOp, UOp};
+use crate::parser::ParserToken;
+
+/// The closed range of values of a variable or a subexpression.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Interval {
+    /// The lower bound, can be negative infinity.
+    pub min: f64,
+    /// The upper bound, can be positive infinity.
+    pub max: f64,
+}
+
+impl Interval {
+    /// The interval of all numbers, used for the variables without a range.
+    pub const UNBOUNDED: Interval = Interval {
+        min: f64::NEG_INFINITY,
+        max: f64::INFINITY,
+    };
+
+    /// Creates the interval, the bounds are swapped if `min > max`.
+    ///
+    /// The interval is [`UNBOUNDED`](Interval::UNBOUNDED) if either bound is `NaN`.
+    pub fn new(min: f64, max: f64) -> Self {
+        if min.is_nan() || max.is_nan() {
+            Self::UNBOUNDED
+        } else {
+            Interval {
+                min: min.min(max),
+                max: min.max(max),
+            }
+        }
+    }
+
+    /// The interval of the single value.
+    pub fn point(value: f64) -> Self {
+        Self::new(value, value)
+    }
+
+    /// Returns `true` if both bounds are finite.
+    pub fn is_bounded(&self) -> bool {
+        self.min.is_finite() && self.max.is_finite()
+    }
+
+    /// Returns `true` if `value` is in the interval.
+    pub fn contains(&self, value: f64) -> bool {
+        self.min <= value && value <= self.max
+    }
+
+    fn from_corners(corners: &[f64]) -> Self {
+        if corners.iter().any(|c| c.is_nan()) {
+            return Self::UNBOUNDED;
+        }
+        let min = corners.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = corners.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        Self::new(min, max)
+    }
+
+    fn add(self, other: Self) -> Self {
+        Self::new(self.min + other.min, self.max + other.max)
+    }
+
+    fn sub(self, other: Self) -> Self {
+        Self::new(self.min - other.max, self.max - other.min)
+    }
+
+    fn neg(self) -> Self {
+        Self::new(-self.max, -self.min)
+    }
+
+    fn mul(self, other: Self) -> Self {
+        // 0 * inf is 0 for the bounds, the product of the values is always finite
+        let mul = |a: f64, b: f64| if a == 0.0 || b == 0.0 { 0.0 } else { a * b };
+        Self::from_corners(&[
+            mul(self.min, other.min),
+            mul(self.min, other.max),
+            mul(self.max, other.min),
+            mul(self.max, other.max),
+        ])
+    }
+
+    fn recip(self) -> Self {
+        Self::new(1.0 / self.max, 1.0 / self.min)
+    }
+
+    fn max(self, other: Self) -> Self {
+        Self::new(self.min.max(other.min), self.max.max(other.max))
+    }
+}
+
+/// The kind of the [`Hazard`](Hazard).
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum HazardKind {
+    /// The divisor can be zero.
+    DivisionByZero,
+    /// The base of the power can be zero while the exponent is negative.
+    ZeroToNegativePower,
+    /// The base of the power can be negative while the exponent is fractional, e.g. `x ^ 0.5`.
+    NegativeBase,
+    /// The result of the operation can be infinite while its operands are finite.
+    Overflow,
+}
+
+impl Display for HazardKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            HazardKind::DivisionByZero => "the divisor can be zero",
+            HazardKind::ZeroToNegativePower => "zero can be raised to a negative power",
+            HazardKind::NegativeBase => "negative number can be raised to a fractional power",
+            HazardKind::Overflow => "the result can overflow",
+        })
+    }
+}
+
+/// The operation that can produce `NaN` or infinity, reported by [`domain_hazards`](domain_hazards).
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Hazard {
+    /// The kind of the hazard.
+    pub kind: HazardKind,
+    /// The index of the operator token in the token stream.
+    pub position: usize,
+    /// The range of the tokens of the operand that causes the hazard, i.e. the divisor or the base.
+    ///
+    /// For [`Overflow`](HazardKind::Overflow) this is the whole operation.
+    pub operand: Range<usize>,
+}
+
+impl Display for Hazard {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at token {}", self.kind, self.position)
+    }
+}
+
+/// The result of [`estimate`](estimate).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Estimate {
+    /// The bounds of the value of the expression.
+    ///
+    /// The value is guaranteed to be in the range unless it is `NaN`,
+    /// which can only happen if there are [`hazards`](Estimate::hazards).
+    pub range: Interval,
+    /// The operations that can produce `NaN` or infinity.
+    pub hazards: Vec<Hazard>,
+}
+
+/// Estimates the range of the value of the expression and finds the operations
+/// that can produce `NaN` or infinity for some values of the variables.
+///
+/// The values of the subexpressions are estimated once with interval arithmetic, the expression is not evaluated.
+/// `ranges` restrict the values of the variables, other variables can have any value.
+/// The estimate is conservative, i.e. the range can be wider than the actual one
+/// and a hazard is reported whenever it can't be ruled out.
+///
+/// Only the operators with [`builtin`](crate::operators::BiOp::builtin) arithmetic, the default unary operators
+/// and the default functions are understood, the results of the other operations and of macros can be any number.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use rusty_yard::analysis::domain::{estimate, HazardKind, Interval};
+/// use rusty_yard::parser::parse_str;
+/// use rusty_yard::Ctx;
+///
+/// let ctx = Ctx::default();
+/// let tokens = parse_str("2 * x ^ 2 - 1", &ctx).unwrap();
+/// let mut ranges = HashMap::new();
+/// ranges.insert("x".to_owned(), Interval::new(-1.0, 3.0));
+/// let result = estimate(&tokens, &ranges);
+/// assert_eq!(result.range, Interval::new(-1.0, 17.0));
+/// assert!(result.hazards.is_empty());
+///
+/// ranges.insert("x".to_owned(), Interval::new(0.0, 1e200));
+/// let result = estimate(&tokens, &ranges);
+/// assert_eq!(result.hazards[0].kind, HazardKind::Overflow);
+/// ```
+pub fn estimate(tokens: &[ParserToken], ranges: &HashMap<String, Interval>) -> Estimate {
+    let mut hazards = Vec::new();
+    // the interval of each operand and the index of its first token
+    let mut stack: Vec<(Interval, usize)> = Vec::new();
+    for (position, token) in tokens.iter().enumerate() {
+        let n_hazards = hazards.len();
+        // the interval of the result and whether the operation can overflow
+        let (interval, start, can_overflow) = match token {
+            ParserToken::Num(n) => (Interval::point(*n), position, false),
+            ParserToken::Id(id) => (
+                ranges.get(*id).copied().unwrap_or(Interval::UNBOUNDED),
+                position,
+                false,
+            ),
+            // fails to evaluate, unless it is the argument of the function
+            ParserToken::Str(_) => (Interval::UNBOUNDED, position, false),
+            ParserToken::UOp(op) => {
+                let (operand, start) = pop(&mut stack, position);
+                (u_op(op, operand), start, false)
+            }
+            ParserToken::BiOp(op) => {
+                let (right, right_start) = pop(&mut stack, position);
+                let (left, start) = pop(&mut stack, right_start);
+                let mut report = |kind, operand| {
+                    hazards.push(Hazard {
+                        kind,
+                        position,
+                        operand,
+                    })
+                };
+                let interval = bi_op(op, left, right, |kind| match kind {
+                    HazardKind::DivisionByZero => report(kind, right_start..position),
+                    _ => report(kind, start..right_start),
+                });
+                let can_overflow = op.builtin.is_some() && left.is_bounded() && right.is_bounded();
+                (interval, start, can_overflow)
+            }
+            ParserToken::Func(func, n_args) => {
+                let mut args = Vec::with_capacity(*n_args);
+                let mut start = position;
+                for _ in 0..*n_args {
+                    let (arg, arg_start) = pop(&mut stack, start);
+                    args.push(arg);
+                    start = arg_start;
+                }
+                args.reverse();
+                let interval = func_call(func, &args);
+                let can_overflow = interval.is_some() && args.iter().all(Interval::is_bounded);
+                (interval.unwrap_or(Interval::UNBOUNDED), start, can_overflow)
+            }
+            ParserToken::Macro(m) => {
+                let mut start = position;
+                for _ in 0..m.operands() {
+                    start = pop(&mut stack, start).1;
+                }
+                (Interval::UNBOUNDED, start, false)
+            }
+        };
+        if can_overflow && hazards.len() == n_hazards && !interval.is_bounded() {
+            hazards.push(Hazard {
+                kind: HazardKind::Overflow,
+                position,
+                operand: start..position + 1,
+            });
+        }
+        stack.push((interval, start));
+    }
+    let range = match (stack.pop(), stack.is_empty()) {
+        (Some((range, _)), true) => range,
+        _ => Interval::UNBOUNDED,
+    };
+    Estimate { range, hazards }
+}
+
+/// Finds the operations that can produce `NaN` or infinity for some values of the variables.
+///
+/// These are the [`hazards`](Estimate::hazards) of the [`estimate`](estimate), see it for the details.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use rusty_yard::analysis::domain::{domain_hazards, HazardKind, Interval};
+/// use rusty_yard::parser::parse_str;
+/// use rusty_yard::Ctx;
+///
+/// let ctx = Ctx::default();
+/// let tokens = parse_str("1 / (x ^ 2 + 1) + 1 / (x - 1)", &ctx).unwrap();
+/// let hazards = domain_hazards(&tokens, &HashMap::new());
+/// assert_eq!(hazards.len(), 1);
+/// assert_eq!(hazards[0].kind, HazardKind::DivisionByZero);
+///
+/// let mut ranges = HashMap::new();
+/// ranges.insert("x".to_owned(), Interval::new(2.0, 10.0));
+/// assert!(domain_hazards(&tokens, &ranges).is_empty());
+/// ```
+#[cfg_attr(tarpaulin, skip)]
+pub fn domain_hazards(tokens: &[ParserToken], ranges: &HashMap<String, Interval>) -> Vec<Hazard> {
+    estimate(tokens, ranges).hazards
+}
+
+/// Pops the operand, the ill formed stream is treated as if the operand can be anything
+fn pop(stack: &mut Vec<(Interval, usize)>, end: usize) -> (Interval, usize) {
+    stack.pop().unwrap_or((Interval::UNBOUNDED, end))
+}
+
+fn u_op(op: &UOp, operand: Interval) -> Interval {
+    if *op == *unary::NEGATE {
+        operand.neg()
+    } else if *op == *unary::PLUS {
+        operand
+    } else {
+        Interval::UNBOUNDED
+    }
+}
+
+fn bi_op(
+    op: &BiOp,
+    left: Interval,
+    right: Interval,
+    mut report: impl FnMut(HazardKind),
+) -> Interval {
+    match op.builtin {
+        Some(Builtin::Add) => left.add(right),
+        Some(Builtin::Subtract) => left.sub(right),
+        Some(Builtin::Multiply) => left.mul(right),
+        Some(Builtin::Divide) if right.contains(0.0) => {
+            report(HazardKind::DivisionByZero);
+            Interval::UNBOUNDED
+        }
+        Some(Builtin::Divide) => left.mul(right.recip()),
+        Some(Builtin::Power) => power(left, right, report),
+        None => Interval::UNBOUNDED,
+    }
+}
+
+fn power(base: Interval, exponent: Interval, mut report: impl FnMut(HazardKind)) -> Interval {
+    let n = exponent.min;
+    if exponent.min == exponent.max && n.is_finite() && n.fract() == 0.0 {
+        if n < 0.0 {
+            if base.contains(0.0) {
+                report(HazardKind::ZeroToNegativePower);
+                return Interval::UNBOUNDED;
+            }
+            return power(base, Interval::point(-n), report).recip();
+        }
+        let (min, max) = (base.min.powf(n), base.max.powf(n));
+        let is_even = n % 2.0 == 0.0;
+        return if is_even && base.contains(0.0) {
+            Interval::new(0.0, min.max(max))
+        } else {
+            Interval::new(min, max)
+        };
+    }
+    if base.min < 0.0 {
+        report(HazardKind::NegativeBase);
+    }
+    if base.contains(0.0) && exponent.min < 0.0 {
+        report(HazardKind::ZeroToNegativePower);
+    }
+    if base.max < 0.0 {
+        return Interval::UNBOUNDED;
+    }
+    // the power of non-negative base is monotonic in both operands
+    let base_min = base.min.max(0.0);
+    Interval::from_corners(&[
+        base_min.powf(exponent.min),
+        base_min.powf(exponent.max),
+        base.max.powf(exponent.min),
+        base.max.powf(exponent.max),
+    ])
+}
+
+fn func_call(func: &Func, args: &[Interval]) -> Option<Interval> {
+    let zero = Interval::point(0.0);
+    if *func == *FN_SUM {
+        Some(args.iter().fold(zero, |acc, arg| acc.add(*arg)))
+    } else if *func == *FN_PROD {
+        Some(
+            args.iter()
+                .fold(Interval::point(1.0), |acc, arg| acc.mul(*arg)),
+        )
+    } else if *func == *FN_SUB {
+        match args.split_first() {
+            Some((first, rest)) => Some(rest.iter().fold(*first, |acc, arg| acc.sub(*arg))),
+            None => Some(zero),
+        }
+    } else if *func == *FN_MAX {
+        let (first, rest) = args.split_first()?;
+        Some(rest.iter().fold(*first, |acc, arg| acc.max(*arg)))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::Node;
+    use crate::parser::parse_str;
+    use crate::Ctx;
+
+    /// The ranges of the variables
+    type Ranges = &'static [(&'static str, f64, f64)];
+
+    /// The input, the ranges of the variables, and the hazards with the operands
+    type Case = (&'static str, Ranges, &'static [(HazardKind, &'static str)]);
+
+    #[test]
+    fn test_domain_hazards() {
+        let input_expected: &[Case] = &[
+            ("1 / x", &[], &[(HazardKind::DivisionByZero, "x")]),
+            ("1 / x", &[("x", 1.0, 2.0)], &[]),
+            (
+                "1 / x",
+                &[("x", -1.0, 2.0)],
+                &[(HazardKind::DivisionByZero, "x")],
+            ),
+            ("1 / (x ^ 2 + 1)", &[], &[]),
+            (
+                "1 / (x * x + 1)",
+                &[],
+                &[(HazardKind::DivisionByZero, "((x * x) + 1)")],
+            ),
+            ("1 / (x - 1)", &[("x", 2.0, 3.0)], &[]),
+            ("1 / -x", &[("x", 2.0, 3.0)], &[]),
+            (
+                "1 / (2 - x)",
+                &[("x", 0.0, 3.0)],
+                &[(HazardKind::DivisionByZero, "(2 - x)")],
+            ),
+            ("1 / sum(x, y)", &[("x", 1.0, 2.0), ("y", 0.0, 1.0)], &[]),
+            ("1 / max(x, 1)", &[], &[]),
+            ("1 / (1 / x)", &[("x", 1.0, 2.0)], &[]),
+            ("x ^ 0.5", &[], &[(HazardKind::NegativeBase, "x")]),
+            ("x ^ 0.5", &[("x", 0.0, 4.0)], &[]),
+            (
+                "(x - 1) ^ 0.5",
+                &[("x", 0.0, 4.0)],
+                &[(HazardKind::NegativeBase, "(x - 1)")],
+            ),
+            ("x ^ -1", &[], &[(HazardKind::ZeroToNegativePower, "x")]),
+            ("x ^ -2", &[("x", 1.0, 2.0)], &[]),
+            ("x ^ 3", &[], &[]),
+            ("2 ^ x", &[], &[]),
+            (
+                "x ^ y",
+                &[],
+                &[
+                    (HazardKind::NegativeBase, "x"),
+                    (HazardKind::ZeroToNegativePower, "x"),
+                ],
+            ),
+            (
+                "1 / x + 1 / y",
+                &[],
+                &[
+                    (HazardKind::DivisionByZero, "x"),
+                    (HazardKind::DivisionByZero, "y"),
+                ],
+            ),
+        ];
+        let ctx = Ctx::default();
+        for (input, ranges, expected) in input_expected {
+            let tokens = parse_str(input, &ctx).unwrap();
+            let ranges = ranges
+                .iter()
+                .map(|(var, min, max)| ((*var).to_owned(), Interval::new(*min, *max)))
+                .collect();
+            let hazards: Vec<_> = domain_hazards(&tokens, &ranges)
+                .into_iter()
+                .map(|h| {
+                    let operand = Node::from_tokens(&tokens[h.operand]).unwrap();
+                    (h.kind, operand.to_string())
+                })
+                .collect();
+            let expected: Vec<_> = expected
+                .iter()
+                .map(|(kind, operand)| (*kind, (*operand).to_owned()))
+                .collect();
+            assert_eq!(hazards, expected, "input was {}", input);
+        }
+    }
+
+    #[test]
+    fn test_estimate() {
+        let input_expected: &[(&str, Ranges, (f64, f64))] = &[
+            ("1", &[], (1.0, 1.0)),
+            ("x", &[], (f64::NEG_INFINITY, f64::INFINITY)),
+            ("x + 1", &[("x", 0.0, 1.0)], (1.0, 2.0)),
+            ("-x", &[("x", 1.0, 2.0)], (-2.0, -1.0)),
+            ("2 * x ^ 2 - 1", &[("x", -1.0, 3.0)], (-1.0, 17.0)),
+            ("x ^ 3", &[("x", -2.0, 1.0)], (-8.0, 1.0)),
+            ("x ^ 0.5", &[("x", 4.0, 9.0)], (2.0, 3.0)),
+            ("1 / x", &[("x", 1.0, 2.0)], (0.5, 1.0)),
+            ("x * y", &[("x", -1.0, 2.0), ("y", -3.0, 1.0)], (-6.0, 3.0)),
+            ("max(x, 0)", &[("x", -1.0, 1.0)], (0.0, 1.0)),
+            ("sum(x, 1, x)", &[("x", 0.0, 1.0)], (1.0, 3.0)),
+            ("sub(x, 1)", &[("x", 0.0, 1.0)], (-1.0, 0.0)),
+            (
+                "1 / x",
+                &[("x", -1.0, 1.0)],
+                (f64::NEG_INFINITY, f64::INFINITY),
+            ),
+        ];
+        let ctx = Ctx::default();
+        for (input, ranges, (min, max)) in input_expected {
+            let tokens = parse_str(input, &ctx).unwrap();
+            let ranges = ranges
+                .iter()
+                .map(|(var, min, max)| ((*var).to_owned(), Interval::new(*min, *max)))
+                .collect();
+            let estimate = estimate(&tokens, &ranges);
+            assert_eq!(
+                estimate.range,
+                Interval::new(*min, *max),
+                "input was {}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_estimate_overflow() {
+        let input_expected = &[
+            ("x * x", Some(0..3)),
+            ("prod(x, x)", Some(0..3)),
+            ("2 ^ (x / 100)", Some(0..5)),
+            ("1 / x", None),
+            ("x + y", None),
+            ("1 / (x * x)", Some(1..4)),
+        ];
+        let ctx = Ctx::default();
+        let mut ranges = HashMap::new();
+        ranges.insert("x".to_owned(), Interval::new(0.0, 1e200));
+        for (input, expected) in input_expected {
+            let tokens = parse_str(input, &ctx).unwrap();
+            let overflow = estimate(&tokens, &ranges)
+                .hazards
+                .into_iter()
+                .find(|h| h.kind == HazardKind::Overflow)
+                .map(|h| h.operand);
+            assert_eq!(overflow, *expected, "input was {}", input);
+        }
+    }
+
+    #[test]
+    fn test_domain_hazards_macro() {
+        let ctx = Ctx::default_with_macros();
+        let tokens = parse_str("a = 1 / x", &ctx).unwrap();
+        let hazards = domain_hazards(&tokens, &HashMap::new());
+        assert_eq!(hazards.len(), 1);
+        assert_eq!(hazards[0].to_string(), "the divisor can be zero at token 2");
+        assert_eq!(hazards[0].operand, 1..2);
+    }
+}
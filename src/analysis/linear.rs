@@ -0,0 +1,267 @@
+//! Extraction of the coefficients of affine expressions, see [`linear_form`](linear_form).
+use thiserror::Error;
+
+use super::node::{self, Node};
+use crate::evaluator;
+use crate::expression::Expression;
+use crate::functions::{FN_SUB, FN_SUM};
+use crate::operators::binary::Builtin;
+use crate::operators::unary;
+use crate::suggest::closest;
+use crate::variables::VariableResolver;
+
+/// Represents the error that can occur during [`linear_form`](linear_form).
+#[derive(Debug, Error, PartialEq)]
+pub enum Error {
+    /// The expression can't be converted into the syntax tree.
+    #[error("{0}")]
+    Node(#[from] node::Error),
+    /// The parameter of the expression is not defined.
+    #[error("{0}")]
+    Eval(#[from] evaluator::Error),
+    /// The expression is not affine in the variables, contains the subexpression that is not.
+    #[error("Expression is not linear: {0}")]
+    NonLinear(String),
+}
+
+/// The affine form `c[0] * vars[0] + c[1] * vars[1] + ... + constant` of the expression.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinearForm {
+    /// The coefficients of the variables, in the order of the variables.
+    pub coefficients: Vec<f64>,
+    /// The constant term.
+    pub constant: f64,
+}
+
+impl LinearForm {
+    fn constant(n_vars: usize, constant: f64) -> Self {
+        Self {
+            coefficients: vec![0.0; n_vars],
+            constant,
+        }
+    }
+
+    /// Returns `true` if the form does not depend on the variables.
+    pub fn is_constant(&self) -> bool {
+        self.coefficients.iter().all(|c| *c == 0.0)
+    }
+
+    /// Returns `true` if the form is linear, i.e. the constant term is 0.
+    pub fn is_linear(&self) -> bool {
+        self.constant == 0.0
+    }
+
+    /// Evaluates the form with the `values` of the variables.
+    pub fn eval(&self, values: &[f64]) -> f64 {
+        self.coefficients
+            .iter()
+            .zip(values)
+            .map(|(c, v)| c * v)
+            .sum::<f64>()
+            + self.constant
+    }
+
+    fn combine(mut self, other: Self, scale: f64) -> Self {
+        for (c, o) in self.coefficients.iter_mut().zip(other.coefficients) {
+            *c += scale * o;
+        }
+        self.constant += scale * other.constant;
+        self
+    }
+
+    fn scale(mut self, scale: f64) -> Self {
+        for c in &mut self.coefficients {
+            *c *= scale;
+        }
+        self.constant *= scale;
+        self
+    }
+}
+
+/// Recognizes the expression that is affine in `vars` and extracts its coefficients and constant term.
+///
+/// Other variables are the parameters of the expression, their values are read from `parameters`.
+/// The expression is affine if `vars` are only added, subtracted, negated,
+/// multiplied by or divided by the subexpressions that don't depend on `vars`,
+/// or passed to [`sum`](crate::functions::FN_SUM) and [`sub`](crate::functions::FN_SUB).
+/// The subexpressions that don't depend on `vars` are evaluated.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use rusty_yard::analysis::linear::{linear_form, Error};
+/// use rusty_yard::expression::Expression;
+/// use rusty_yard::Ctx;
+///
+/// let ctx = Ctx::default();
+/// let mut parameters = HashMap::new();
+/// parameters.insert("price".to_owned(), 2.5);
+/// let expr = Expression::parse("price * (x + 2 * y) - y / 2 + 10", &ctx).unwrap();
+/// let form = linear_form(&expr, &["x", "y"], &parameters).unwrap();
+/// assert_eq!(form.coefficients, vec![2.5, 4.5]);
+/// assert_eq!(form.constant, 10.0);
+///
+/// let expr = Expression::parse("x * y", &ctx).unwrap();
+/// let result = linear_form(&expr, &["x", "y"], &parameters);
+/// assert_eq!(result, Err(Error::NonLinear("(x * y)".to_owned())));
+/// ```
+pub fn linear_form(
+    expr: &Expression,
+    vars: &[&str],
+    parameters: &dyn VariableResolver,
+) -> Result<LinearForm, Error> {
+    let node = Node::from_tokens(expr.tokens())?;
+    affine(&node, vars, parameters)
+}
+
+fn affine(
+    node: &Node,
+    vars: &[&str],
+    parameters: &dyn VariableResolver,
+) -> Result<LinearForm, Error> {
+    let n_vars = vars.len();
+    let non_linear = || Error::NonLinear(node.to_string());
+    let form = match node {
+        Node::Num(n) => LinearForm::constant(n_vars, *n),
+        Node::Var(id) => match vars.iter().position(|var| var == id) {
+            Some(index) => {
+                let mut form = LinearForm::constant(n_vars, 0.0);
+                form.coefficients[index] = 1.0;
+                form
+            }
+            None => {
+                let value = parameters.get(id).ok_or_else(|| {
+                    let names = parameters.names();
+                    let suggestion = closest(id, names.iter().map(String::as_str));
+                    evaluator::Error::VarNotFound((*id).to_owned(), suggestion)
+                })?;
+                LinearForm::constant(n_vars, value)
+            }
+        },
+        Node::UOp(op, operand) => {
+            let operand = affine(operand, vars, parameters)?;
+            if **op == *unary::NEGATE {
+                operand.scale(-1.0)
+            } else if **op == *unary::PLUS {
+                operand
+            } else if operand.is_constant() {
+                LinearForm::constant(n_vars, (op.func)(operand.constant))
+            } else {
+                return Err(non_linear());
+            }
+        }
+        Node::BiOp(op, left, right) => {
+            let left = affine(left, vars, parameters)?;
+            let right = affine(right, vars, parameters)?;
+            match op.builtin {
+                Some(Builtin::Add) => left.combine(right, 1.0),
+                Some(Builtin::Subtract) => left.combine(right, -1.0),
+                Some(Builtin::Multiply) if right.is_constant() => left.scale(right.constant),
+                Some(Builtin::Multiply) if left.is_constant() => right.scale(left.constant),
+                Some(Builtin::Divide) if right.is_constant() => left.scale(1.0 / right.constant),
+                Some(Builtin::Power) if right.is_constant() && right.constant == 1.0 => left,
+                _ if left.is_constant() && right.is_constant() => {
+                    LinearForm::constant(n_vars, op.apply(left.constant, right.constant))
+                }
+                _ => return Err(non_linear()),
+            }
+        }
+        Node::Func(func, args) => {
+            let args = args
+                .iter()
+                .map(|arg| affine(arg, vars, parameters))
+                .collect::<Result<Vec<_>, _>>()?;
+            if **func == *FN_SUM {
+                let zero = LinearForm::constant(n_vars, 0.0);
+                args.into_iter()
+                    .fold(zero, |acc, arg| acc.combine(arg, 1.0))
+            } else if **func == *FN_SUB && args.len() == 2 {
+                let mut args = args.into_iter();
+                let left = args.next().unwrap();
+                left.combine(args.next().unwrap(), -1.0)
+            } else if args.iter().all(LinearForm::is_constant) {
+                let values: Vec<f64> = args.iter().map(|arg| arg.constant).collect();
+                let value = func.call(&values).map_err(|_| non_linear())?;
+                LinearForm::constant(n_vars, value)
+            } else {
+                return Err(non_linear());
+            }
+        }
+    };
+    Ok(form)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::Ctx;
+
+    #[test]
+    fn test_linear_form() {
+        let input_expected: &[(&str, &[f64], f64)] = &[
+            ("0", &[0.0, 0.0], 0.0),
+            ("x", &[1.0, 0.0], 0.0),
+            ("-y + 1", &[0.0, -1.0], 1.0),
+            ("+x - -y", &[1.0, 1.0], 0.0),
+            ("2 * x + y * 3 - 4", &[2.0, 3.0], -4.0),
+            ("(x + y) / 4", &[0.25, 0.25], 0.0),
+            ("a * x + a ^ 2 * y", &[2.0, 4.0], 0.0),
+            ("x ^ 1 + 2 ^ 3", &[1.0, 0.0], 8.0),
+            ("sum(x, y, 1) - sub(x, 2 * y)", &[0.0, 3.0], 1.0),
+            ("max(a, 3) * x", &[3.0, 0.0], 0.0),
+            ("0 * x * y", &[0.0, 0.0], 0.0),
+        ];
+        let ctx = Ctx::default();
+        let mut parameters = HashMap::new();
+        parameters.insert("a".to_owned(), 2.0);
+        for (input, coefficients, constant) in input_expected {
+            let expr = Expression::parse(input, &ctx).unwrap();
+            let result = linear_form(&expr, &["x", "y"], &parameters);
+            let expected = LinearForm {
+                coefficients: coefficients.to_vec(),
+                constant: *constant,
+            };
+            assert_eq!(result, Ok(expected), "input was {}", input);
+        }
+    }
+
+    #[test]
+    fn test_linear_form_err() {
+        let input_expected = &[
+            ("x * y", Error::NonLinear("(x * y)".to_owned())),
+            ("x ^ 2", Error::NonLinear("(x ^ 2)".to_owned())),
+            ("1 / x", Error::NonLinear("(1 / x)".to_owned())),
+            ("max(x, 1) + y", Error::NonLinear("max(x, 1)".to_owned())),
+            // the terms are not combined before the check
+            ("x * y - x * y + x", Error::NonLinear("(x * y)".to_owned())),
+            (
+                "x + b",
+                Error::Eval(evaluator::Error::VarNotFound("b".to_owned(), None)),
+            ),
+        ];
+        let ctx = Ctx::default();
+        for (input, expected) in input_expected {
+            let expr = Expression::parse(input, &ctx).unwrap();
+            let result = linear_form(&expr, &["x", "y"], &HashMap::new());
+            assert_eq!(result.as_ref().err(), Some(expected), "input was {}", input);
+        }
+        let ctx = Ctx::default_with_macros();
+        let expr = Expression::parse("a = x", &ctx).unwrap();
+        let result = linear_form(&expr, &["x"], &HashMap::new());
+        assert_eq!(result, Err(Error::Node(node::Error::Macro("=".to_owned()))));
+    }
+
+    #[test]
+    fn test_linear_form_eval() {
+        let form = LinearForm {
+            coefficients: vec![2.0, -1.0],
+            constant: 0.5,
+        };
+        assert_eq!(form.eval(&[3.0, 1.0]), 5.5);
+        assert!(!form.is_linear());
+        assert!(!form.is_constant());
+    }
+}
@@ -0,0 +1,287 @@
+//! Static analysis of the parsed expressions.
+//!
+//! Functions in this module inspect the RPN token stream produced by the [`parser`](crate::parser)
+//! without evaluating it.
+//!
+//! # Example
+//!
+//! ```
+//! use rusty_yard::analysis::{free_variables, references};
+//! use rusty_yard::parser::parse_str;
+//! use rusty_yard::Ctx;
+//!
+//! let ctx = Ctx::default();
+//! let tokens = parse_str("max(a, b) * -a + 2", &ctx).unwrap();
+//! let variables: Vec<_> = free_variables(&tokens).into_iter().collect();
+//! assert_eq!(variables, vec!["a", "b"]);
+//!
+//! let references = references(&tokens, &ctx);
+//! assert!(references.functions.contains("max"));
+//! assert!(!references.functions.contains("sum"));
+//! ```
+use std::collections::BTreeSet;
+
+pub use dependencies::dependencies;
+pub use differentiate::{differentiate, differentiate_with, DerivativeHook};
+pub use domain::{domain_hazards, estimate};
+pub use linear::linear_form;
+pub use node::Node;
+pub use predicate::check_predicate;
+pub use simplify::simplify;
+
+use crate::macros::ParsedMacro;
+use crate::parser::{parse_str, ParserToken};
+use crate::Ctx;
+
+pub mod dependencies;
+pub mod differentiate;
+pub mod domain;
+pub mod linear;
+pub mod node;
+pub mod predicate;
+pub mod simplify;
+
+/// Returns the names of the variables the expression reads.
+///
+/// # Note
+///
+/// Macros are opaque to this function, i.e. the variable that is assigned by
+/// [`Assign`](crate::macros::default::Assign) macro is not reported,
+/// and neither are the variables used in the arguments of [`UserFn`](crate::macros::user_fn::UserFn).
+pub fn free_variables<'a>(tokens: &[ParserToken<'a, '_>]) -> BTreeSet<&'a str> {
+    tokens
+        .iter()
+        .filter_map(|token| match token {
+            ParserToken::Id(id) => Some(*id),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Returns the names of the variables the caller has to provide to evaluate the expression.
+///
+/// Unlike [`free_variables`](free_variables), this function looks into the macros:
+/// - the variable assigned by the macro, see [`ParsedMacro::assigns`](crate::macros::ParsedMacro::assigns),
+///   is not reported when it is read after the assignment, e.g. `(a = 2) * a` doesn't need `a`;
+/// - the variables read by the expressions the macro evaluates by itself are reported,
+///   except for the variables the macro binds, e.g. the parameters of [`UserFn`](crate::macros::user_fn::UserFn),
+///   see [`ParsedMacro::inner_expressions`](crate::macros::ParsedMacro::inner_expressions).
+///
+/// The names are owned, because the inner expressions of the macros are parsed by this function.
+///
+/// # Example
+///
+/// ```
+/// use rusty_yard::analysis::{functions_of, variables_of};
+/// use rusty_yard::parser::parse_str;
+/// use rusty_yard::Ctx;
+///
+/// let ctx = Ctx::default_with_macros();
+/// let tokens = parse_str("total = if(discount, price * (1 - discount), max(price, 0))", &ctx).unwrap();
+/// let variables: Vec<_> = variables_of(&tokens, &ctx).into_iter().collect();
+/// assert_eq!(variables, vec!["discount", "price"]);
+/// let functions: Vec<_> = functions_of(&tokens, &ctx).into_iter().collect();
+/// assert_eq!(functions, vec!["max"]);
+/// ```
+pub fn variables_of(tokens: &[ParserToken], ctx: &Ctx) -> BTreeSet<String> {
+    let mut usage = Usage::default();
+    usage.collect(tokens, ctx, &BTreeSet::new());
+    usage.variables
+}
+
+/// Returns the identifiers of the [`functions`](crate::functions::Func) the expression calls,
+/// including the calls in the expressions the macros evaluate by themselves, see [`variables_of`](variables_of).
+///
+/// The calls of [`UserFn`](crate::macros::user_fn::UserFn) are not reported, they are macros,
+/// but the functions called in their body are.
+pub fn functions_of(tokens: &[ParserToken], ctx: &Ctx) -> BTreeSet<String> {
+    let mut usage = Usage::default();
+    usage.collect(tokens, ctx, &BTreeSet::new());
+    usage.references.functions
+}
+
+/// The variables and the items of the context used by the expression and the macros in it
+#[derive(Debug, Default)]
+pub(crate) struct Usage {
+    pub(crate) variables: BTreeSet<String>,
+    pub(crate) references: References,
+}
+
+impl Usage {
+    /// Adds the variables that are not `bound` and the functions of the `tokens`
+    pub(crate) fn collect(&mut self, tokens: &[ParserToken], ctx: &Ctx, bound: &BTreeSet<String>) {
+        let mut bound = bound.clone();
+        for token in tokens {
+            match token {
+                ParserToken::Num(_) | ParserToken::Str(_) => {}
+                ParserToken::Id(id) => self.read(id, &bound),
+                ParserToken::UOp(op) => {
+                    self.references.unary_operators.insert(op.token.clone());
+                }
+                ParserToken::BiOp(op) => {
+                    self.references.binary_operators.insert(op.token.clone());
+                }
+                ParserToken::Func(func, _) => {
+                    self.references.functions.insert(func.token.clone());
+                }
+                ParserToken::Macro(m) => self.collect_macro(m.as_ref(), ctx, &mut bound),
+            }
+        }
+    }
+
+    /// Adds the variable `id` if it is not `bound`
+    pub(crate) fn read(&mut self, id: &str, bound: &BTreeSet<String>) {
+        if !bound.contains(id) {
+            self.variables.insert(id.to_owned());
+        }
+    }
+
+    /// Adds the usage of the inner expressions of the macro,
+    /// the variable the macro assigns is `bound` for the rest of the expression
+    pub(crate) fn collect_macro(
+        &mut self,
+        m: &dyn ParsedMacro,
+        ctx: &Ctx,
+        bound: &mut BTreeSet<String>,
+    ) {
+        self.references.macros.insert(m.name().to_owned());
+        for inner in m.inner_expressions() {
+            let mut inner_bound = bound.clone();
+            inner_bound.extend(inner.bound.iter().map(|id| id.to_string()));
+            // the macro has parsed the expression already, it only fails if the ctx is different
+            if let Ok(inner_tokens) = parse_str(inner.input, ctx) {
+                self.collect(&inner_tokens, ctx, &inner_bound);
+            }
+        }
+        if let Some(id) = m.assigns() {
+            bound.insert(id.to_owned());
+        }
+    }
+}
+
+/// The items of the context used by the expression, see [`references`](references).
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct References {
+    /// Identifiers of the functions.
+    pub functions: BTreeSet<String>,
+    /// Identifiers of the binary operators.
+    pub binary_operators: BTreeSet<String>,
+    /// Identifiers of the unary operators.
+    pub unary_operators: BTreeSet<String>,
+    /// Names of the macros, see [`ParsedMacro::name`](crate::macros::ParsedMacro::name).
+    ///
+    /// Calls of [`UserFn`](crate::macros::user_fn::UserFn) are reported here by the name of the function.
+    pub macros: BTreeSet<String>,
+}
+
+/// Returns every function, operator and macro the expression uses,
+/// including the ones used in the expressions the macros evaluate by themselves, see [`variables_of`](variables_of).
+///
+/// This can be used to enforce the policy on the expressions before they are evaluated,
+/// e.g. to reject the expressions that call some function.
+pub fn references(tokens: &[ParserToken], ctx: &Ctx) -> References {
+    let mut usage = Usage::default();
+    usage.collect(tokens, ctx, &BTreeSet::new());
+    usage.references
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::macros::user_fn::UserFn;
+
+    fn user_fn(token: &str, params: &[&str], body: &str) -> Box<UserFn> {
+        Box::new(UserFn {
+            token: token.to_owned(),
+            params: params.iter().map(|param| param.to_string()).collect(),
+            body: body.to_owned(),
+        })
+    }
+
+    #[test]
+    fn test_free_variables() {
+        let input_expected: &[(&str, &[&str])] = &[
+            ("1 + 2", &[]),
+            ("a", &["a"]),
+            ("b + a * b", &["a", "b"]),
+            ("-x ^ y", &["x", "y"]),
+            ("sum(a, max(b, c), 1)", &["a", "b", "c"]),
+            ("a = b + 1", &["b"]),
+        ];
+        let ctx = Ctx::default_with_macros();
+        for (input, expected) in input_expected {
+            let tokens = parse_str(input, &ctx).unwrap();
+            let result: Vec<_> = free_variables(&tokens).into_iter().collect();
+            assert_eq!(result, *expected, "input was {}", input);
+        }
+    }
+
+    #[test]
+    fn test_variables_of() {
+        let input_expected: &[(&str, &[&str])] = &[
+            ("1 + 2", &[]),
+            ("b + a * b", &["a", "b"]),
+            ("a = b + 1", &["b"]),
+            ("(a = 2) * a", &[]),
+            ("a * (a = 2)", &["a"]),
+            ("if(c, x, y + 1)", &["c", "x", "y"]),
+            ("let t = s * 2 in t * t + u", &["s", "u"]),
+            ("let t = t + 1 in t", &["t"]),
+            ("hypot(a, b * c)", &["a", "b", "c"]),
+            ("scaled(x)", &["factor", "x"]),
+        ];
+        let mut ctx = Ctx::default_with_macros();
+        ctx.macros
+            .push(user_fn("hypot", &["a", "b"], "(a^2 + b^2)^0.5"));
+        ctx.macros.push(user_fn("scaled", &["v"], "v * factor"));
+        for (input, expected) in input_expected {
+            let tokens = parse_str(input, &ctx).unwrap();
+            let result: Vec<_> = variables_of(&tokens, &ctx).into_iter().collect();
+            assert_eq!(result, *expected, "input was {}", input);
+        }
+    }
+
+    #[test]
+    fn test_functions_of() {
+        let input_expected: &[(&str, &[&str])] = &[
+            ("1 + a", &[]),
+            ("max(sin(a), 1) + sin(b)", &["max", "sin"]),
+            ("if(a, sqrt(a), 0)", &["sqrt"]),
+            ("norm(a, b)", &["sqrt"]),
+        ];
+        let mut ctx = Ctx::default_with_macros();
+        ctx.macros
+            .push(user_fn("norm", &["a", "b"], "sqrt(a^2 + b^2)"));
+        for (input, expected) in input_expected {
+            let tokens = parse_str(input, &ctx).unwrap();
+            let result: Vec<_> = functions_of(&tokens, &ctx).into_iter().collect();
+            assert_eq!(result, *expected, "input was {}", input);
+        }
+    }
+
+    #[test]
+    fn test_references() {
+        let ctx = Ctx::default_with_macros();
+        let tokens = parse_str("a = -max(b, 2) + sum() * -sum(1) - 3", &ctx).unwrap();
+        let references = references(&tokens, &ctx);
+        fn to_vec(set: &BTreeSet<String>) -> Vec<&str> {
+            set.iter().map(String::as_str).collect()
+        }
+        assert_eq!(to_vec(&references.functions), vec!["max", "sum"]);
+        assert_eq!(to_vec(&references.binary_operators), vec!["*", "+", "-"]);
+        assert_eq!(to_vec(&references.unary_operators), vec!["-"]);
+        assert_eq!(to_vec(&references.macros), vec!["="]);
+        let tokens = parse_str("1", &ctx).unwrap();
+        assert_eq!(super::references(&tokens, &ctx), References::default());
+        // the calls inside the macros are reported too
+        let ctx = ctx.with_random(&crate::random::Rng::new(0));
+        let tokens = parse_str("if(1, rand(), 0)", &ctx).unwrap();
+        let references = super::references(&tokens, &ctx);
+        assert_eq!(to_vec(&references.functions), vec!["rand"]);
+        assert_eq!(to_vec(&references.macros), vec!["if"]);
+        let tokens = parse_str("let t = rand() in -t", &ctx).unwrap();
+        let references = super::references(&tokens, &ctx);
+        assert_eq!(to_vec(&references.functions), vec!["rand"]);
+        assert_eq!(to_vec(&references.unary_operators), vec!["-"]);
+    }
+}
@@ -0,0 +1,183 @@
+//! The owned syntax tree used by the analyses that rewrite the expression.
+use std::fmt::{self, Display, Formatter};
+
+use thiserror::Error;
+
+use crate::functions::Func;
+use crate::operators::{BiOp, UOp};
+use crate::parser::ParserToken;
+use crate::tokenizer::quote_id;
+
+/// Represents the error that can occur when the token stream is converted into [`Node`](Node).
+#[derive(Debug, Error, Clone, Eq, PartialEq)]
+pub enum Error {
+    /// The token stream contains a macro, macros can't be analyzed.
+    #[error("Macro {0} can't be analyzed")]
+    Macro(String),
+    /// The token stream is not a single expression.
+    #[error("Ill formed token stream")]
+    IllFormed,
+    /// The token stream contains a string literal, strings can't be analyzed.
+    #[error("String literal {0:?} can't be analyzed")]
+    Str(String),
+}
+
+/// The owned syntax tree of the expression used to analyze and rewrite it.
+///
+/// Unlike [`parser::Ast`](crate::parser::Ast), the nodes can be created and combined freely,
+/// and the tree is converted back into the token stream with [`to_tokens`](Node::to_tokens).
+/// The identifiers and the items of the context are still borrowed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node<'a, 'ctx> {
+    /// Number literal.
+    Num(f64),
+    /// Variable.
+    Var(&'a str),
+    /// Unary operator applied to the operand.
+    UOp(&'ctx UOp, Box<Node<'a, 'ctx>>),
+    /// Binary operator applied to the operands.
+    BiOp(&'ctx BiOp, Box<Node<'a, 'ctx>>, Box<Node<'a, 'ctx>>),
+    /// Function called with the arguments.
+    Func(&'ctx Func, Vec<Node<'a, 'ctx>>),
+}
+
+impl<'a, 'ctx> Node<'a, 'ctx> {
+    /// Builds the tree from the `tokens` in reverse polish notation.
+    pub fn from_tokens(tokens: &[ParserToken<'a, 'ctx>]) -> Result<Self, Error> {
+        let mut stack = Vec::new();
+        for token in tokens {
+            let node = match *token {
+                ParserToken::Num(n) => Node::Num(n),
+                ParserToken::Id(id) => Node::Var(id),
+                ParserToken::UOp(op) => {
+                    Node::UOp(op, Box::new(stack.pop().ok_or(Error::IllFormed)?))
+                }
+                ParserToken::BiOp(op) => {
+                    let right = stack.pop().ok_or(Error::IllFormed)?;
+                    let left = stack.pop().ok_or(Error::IllFormed)?;
+                    Node::BiOp(op, Box::new(left), Box::new(right))
+                }
+                ParserToken::Func(func, n_args) => {
+                    let start = stack.len().checked_sub(n_args).ok_or(Error::IllFormed)?;
+                    Node::Func(func, stack.split_off(start))
+                }
+                ParserToken::Str(s) => return Err(Error::Str(s.to_owned())),
+                ParserToken::Macro(ref m) => return Err(Error::Macro(m.name().to_owned())),
+            };
+            stack.push(node);
+        }
+        match (stack.pop(), stack.is_empty()) {
+            (Some(node), true) => Ok(node),
+            _ => Err(Error::IllFormed),
+        }
+    }
+
+    /// Converts the tree into the token stream in reverse polish notation.
+    pub fn to_tokens(&self) -> Vec<ParserToken<'a, 'ctx>> {
+        let mut tokens = Vec::new();
+        self.push_tokens(&mut tokens);
+        tokens
+    }
+
+    fn push_tokens(&self, tokens: &mut Vec<ParserToken<'a, 'ctx>>) {
+        match self {
+            Node::Num(n) => tokens.push(ParserToken::Num(*n)),
+            Node::Var(id) => tokens.push(ParserToken::Id(id)),
+            Node::UOp(op, operand) => {
+                operand.push_tokens(tokens);
+                tokens.push(ParserToken::UOp(op));
+            }
+            Node::BiOp(op, left, right) => {
+                left.push_tokens(tokens);
+                right.push_tokens(tokens);
+                tokens.push(ParserToken::BiOp(op));
+            }
+            Node::Func(func, args) => {
+                for arg in args {
+                    arg.push_tokens(tokens);
+                }
+                tokens.push(ParserToken::Func(func, args.len()));
+            }
+        }
+    }
+
+    /// Returns `true` if the variable `var` is used in this tree.
+    pub fn contains_var(&self, var: &str) -> bool {
+        match self {
+            Node::Num(_) => false,
+            Node::Var(id) => *id == var,
+            Node::UOp(_, operand) => operand.contains_var(var),
+            Node::BiOp(_, left, right) => left.contains_var(var) || right.contains_var(var),
+            Node::Func(_, args) => args.iter().any(|arg| arg.contains_var(var)),
+        }
+    }
+}
+
+impl Display for Node<'_, '_> {
+    /// Writes the expression with every operation in parentheses, so that it can be parsed back.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Node::Num(n) if *n < 0.0 => write!(f, "({})", n),
+            Node::Num(n) => write!(f, "{}", n),
+            Node::Var(id) => write!(f, "{}", quote_id(id)),
+            Node::UOp(op, operand) if op.postfix => write!(f, "({}{})", operand, op.token),
+            Node::UOp(op, operand) if op.precedence.is_some() => {
+                write!(f, "({}{})", op.token, operand)
+            }
+            Node::UOp(op, operand) => write!(f, "{}{}", op.token, operand),
+            Node::BiOp(op, left, right) => write!(f, "({} {} {})", left, op.token, right),
+            Node::Func(func, args) => {
+                write!(f, "{}(", func.token)?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", arg)?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_str;
+    use crate::Ctx;
+
+    #[test]
+    fn test_round_trip() {
+        let input_expected = &[
+            ("1", "1"),
+            ("-a", "-a"),
+            ("1 + 2 * 3", "(1 + (2 * 3))"),
+            ("(1 - 2) - 3", "((1 - 2) - 3)"),
+            ("2 ^ x ^ 2", "(2 ^ (x ^ 2))"),
+            ("sum(a, -b, max(1, 2) / 2)", "sum(a, -b, (max(1, 2) / 2))"),
+            ("prod()", "prod()"),
+        ];
+        let ctx = Ctx::default();
+        for (input, expected) in input_expected {
+            let tokens = parse_str(input, &ctx).unwrap();
+            let node = Node::from_tokens(&tokens).unwrap();
+            assert_eq!(node.to_tokens(), tokens, "input was {}", input);
+            assert_eq!(node.to_string(), *expected, "input was {}", input);
+            let reparsed = parse_str(expected, &ctx).unwrap();
+            assert_eq!(reparsed, tokens, "input was {}", input);
+        }
+    }
+
+    #[test]
+    fn test_from_tokens_err() {
+        let ctx = Ctx::default_with_macros();
+        let tokens = parse_str("a = 1", &ctx).unwrap();
+        assert_eq!(
+            Node::from_tokens(&tokens),
+            Err(Error::Macro("=".to_owned()))
+        );
+        assert_eq!(Node::from_tokens(&[]), Err(Error::IllFormed));
+        let tokens = [ParserToken::Num(1.0), ParserToken::Num(2.0)];
+        assert_eq!(Node::from_tokens(&tokens), Err(Error::IllFormed));
+    }
+}
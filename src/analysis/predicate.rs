@@ -0,0 +1,108 @@
+//! Static check of the expressions used as predicates, see [`check_predicate`](check_predicate).
+use std::fmt::{self, Display, Formatter};
+
+use crate::operators::unary;
+use crate::parser::ParserToken;
+
+/// The reason the expression is not likely to be a predicate.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PredicateWarning {
+    /// The expression is the constant that is neither 0 nor 1.
+    NotBoolean(f64),
+    /// The result of the expression is computed by the arithmetic operator, contains the token of the operator.
+    Arithmetic(String),
+}
+
+impl Display for PredicateWarning {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            PredicateWarning::NotBoolean(n) => write!(f, "constant {} is not a truth value", n),
+            PredicateWarning::Arithmetic(op) => {
+                write!(f, "result of `{}` is a number, not a truth value", op)
+            }
+        }
+    }
+}
+
+/// Checks that the expression can be used as a predicate with [`eval_bool`](crate::evaluator::eval_bool).
+///
+/// Only the last operation of the expression is inspected:
+/// the expression is reported if it is a constant other than 0 and 1,
+/// or if its result is computed by the [`builtin`](crate::operators::BiOp::builtin) arithmetic operator
+/// or by [`NEGATE`](crate::operators::unary::NEGATE).
+/// Variables, functions, macros and the operators without the built-in arithmetic are assumed to be truth values.
+///
+/// # Example
+///
+/// ```
+/// use rusty_yard::analysis::check_predicate;
+/// use rusty_yard::analysis::predicate::PredicateWarning;
+/// use rusty_yard::parser::parse_str;
+/// use rusty_yard::Ctx;
+///
+/// let ctx = Ctx::default();
+/// let tokens = parse_str("max(a, b)", &ctx).unwrap();
+/// assert_eq!(check_predicate(&tokens), None);
+/// let tokens = parse_str("a - 0.5", &ctx).unwrap();
+/// assert_eq!(check_predicate(&tokens), Some(PredicateWarning::Arithmetic("-".to_owned())));
+/// ```
+pub fn check_predicate(tokens: &[ParserToken]) -> Option<PredicateWarning> {
+    match tokens.last()? {
+        ParserToken::Num(n) if *n != 0.0 && *n != 1.0 => Some(PredicateWarning::NotBoolean(*n)),
+        ParserToken::BiOp(op) if op.builtin.is_some() => {
+            Some(PredicateWarning::Arithmetic(op.token.clone()))
+        }
+        ParserToken::UOp(op) if **op == *unary::NEGATE => {
+            Some(PredicateWarning::Arithmetic(op.token.clone()))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operators::binary::{Associativity, BiOp};
+    use crate::parser::parse_str;
+    use crate::Ctx;
+
+    #[test]
+    fn test_check_predicate() {
+        let input_expected = &[
+            ("1", None),
+            ("0", None),
+            ("2", Some(PredicateWarning::NotBoolean(2.0))),
+            ("a", None),
+            ("+a", None),
+            ("-a", Some(PredicateWarning::Arithmetic("-".to_owned()))),
+            ("a * b", Some(PredicateWarning::Arithmetic("*".to_owned()))),
+            (
+                "(a ^ 2)",
+                Some(PredicateWarning::Arithmetic("^".to_owned())),
+            ),
+            ("max(a, b)", None),
+            ("a = 2", None),
+            ("", None),
+        ];
+        let ctx = Ctx::default_with_macros();
+        for (input, expected) in input_expected {
+            let tokens = parse_str(input, &ctx).unwrap();
+            assert_eq!(check_predicate(&tokens), *expected, "input was {}", input);
+        }
+    }
+
+    #[test]
+    fn test_check_predicate_custom_operator() {
+        let mut ctx = Ctx::default();
+        ctx.bi_ops
+            .push(BiOp::new("gt", 0, Associativity::LEFT, |a, b| {
+                if a > b {
+                    1.0
+                } else {
+                    0.0
+                }
+            }));
+        let tokens = parse_str("a + 1 gt b", &ctx).unwrap();
+        assert_eq!(check_predicate(&tokens), None);
+    }
+}
@@ -0,0 +1,406 @@
+//! Algebraic simplification of the expressions, see [`simplify`](simplify).
+use super::node::{self, Node};
+use crate::expression::Expression;
+use crate::functions::{Func, FN_MAX, FN_PROD, FN_SUB, FN_SUM};
+use crate::operators::binary::Builtin;
+use crate::operators::{unary, BiOp, UOp};
+use crate::Ctx;
+
+/// The maximum number of times the rules are applied to the whole expression.
+const MAX_PASSES: usize = 32;
+
+/// Options of [`simplify`](simplify).
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub struct Options {
+    /// Assume that every subexpression is finite and that every divisor is not zero.
+    ///
+    /// This enables the rules that change the result of the expression when it is not the case,
+    /// i.e. `x / x = 1`, `0 * x = 0`, `0 / x = 0` and `x - x = 0`.
+    pub assume_defined: bool,
+}
+
+/// The rewrite rule applied by [`simplify`](simplify).
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Rule {
+    /// Operation on numbers is replaced with its result, e.g. `2 * 3 = 6`.
+    FoldConstants,
+    /// `x + 0 = x`, `x - 0 = x`, `0 - x = -x`.
+    AddZero,
+    /// `x * 1 = x`, `x / 1 = x`, `-1 * x = -x`.
+    MultiplyByOne,
+    /// `0 * x = 0`, `0 / x = 0`, requires [`assume_defined`](Options::assume_defined).
+    MultiplyByZero,
+    /// `x ^ 1 = x`, `x ^ 0 = 1`, `1 ^ x = 1`.
+    PowerIdentity,
+    /// `--x = x`, `+x = x`.
+    UnaryIdentity,
+    /// `2 * x + x = 3 * x`, the terms that cancel out require [`assume_defined`](Options::assume_defined).
+    CombineLikeTerms,
+    /// `x / x = 1`, requires [`assume_defined`](Options::assume_defined).
+    CancelDivision,
+}
+
+/// The result of [`simplify`](simplify).
+#[derive(Debug)]
+pub struct Simplified<'a, 'ctx> {
+    /// The simplified expression.
+    pub expression: Expression<'a, 'ctx>,
+    /// The rules in the order they were applied.
+    pub applied: Vec<Rule>,
+}
+
+/// Simplifies the expression by applying algebraic rewrite rules.
+///
+/// Only the operators with [`builtin`](crate::operators::BiOp::builtin) arithmetic, the default unary operators
+/// and the default functions are rewritten, because other functions may have side effects.
+/// Operations on numbers are not folded if the result is not finite.
+///
+/// # Example
+///
+/// ```
+/// use rusty_yard::analysis::simplify::{simplify, Options, Rule};
+/// use rusty_yard::analysis::Node;
+/// use rusty_yard::expression::Expression;
+/// use rusty_yard::Ctx;
+///
+/// let ctx = Ctx::default();
+/// let expr = Expression::parse("2 * x * 1 + x + (3 - 1) ^ 2", &ctx).unwrap();
+/// let simplified = simplify(&expr, Options::default()).unwrap();
+/// let node = Node::from_tokens(simplified.expression.tokens()).unwrap();
+/// assert_eq!(node.to_string(), "((3 * x) + 4)");
+/// assert!(simplified.applied.contains(&Rule::CombineLikeTerms));
+/// ```
+pub fn simplify<'a, 'ctx>(
+    expr: &Expression<'a, 'ctx>,
+    options: Options,
+) -> Result<Simplified<'a, 'ctx>, node::Error> {
+    let mut simplifier = Simplifier {
+        ctx: expr.ctx(),
+        options,
+        applied: Vec::new(),
+    };
+    let mut node = Node::from_tokens(expr.tokens())?;
+    for _ in 0..MAX_PASSES {
+        let applied = simplifier.applied.len();
+        node = simplifier.simplify(node);
+        if simplifier.applied.len() == applied {
+            break;
+        }
+    }
+    Ok(Simplified {
+        expression: Expression::from_tokens(node.to_tokens(), expr.ctx()),
+        applied: simplifier.applied,
+    })
+}
+
+struct Simplifier<'ctx> {
+    ctx: &'ctx Ctx,
+    options: Options,
+    applied: Vec<Rule>,
+}
+
+/// The term of the sum: coefficient and the rest of the term, `None` for the constant
+type Term<'a, 'ctx> = (f64, Option<Node<'a, 'ctx>>);
+
+impl<'ctx> Simplifier<'ctx> {
+    fn simplify<'a>(&mut self, node: Node<'a, 'ctx>) -> Node<'a, 'ctx> {
+        match node {
+            Node::UOp(op, operand) => {
+                let operand = self.simplify(*operand);
+                self.u_op(op, operand)
+            }
+            Node::BiOp(op, left, right) => {
+                let left = self.simplify(*left);
+                let right = self.simplify(*right);
+                match op.builtin {
+                    Some(builtin) => self.bi_op(op, builtin, left, right),
+                    None => Node::BiOp(op, Box::new(left), Box::new(right)),
+                }
+            }
+            Node::Func(func, args) => {
+                let args: Vec<_> = args.into_iter().map(|arg| self.simplify(arg)).collect();
+                self.func(func, args)
+            }
+            leaf => leaf,
+        }
+    }
+
+    fn apply<'a>(&mut self, rule: Rule, node: Node<'a, 'ctx>) -> Node<'a, 'ctx> {
+        self.applied.push(rule);
+        node
+    }
+
+    fn u_op<'a>(&mut self, op: &'ctx UOp, operand: Node<'a, 'ctx>) -> Node<'a, 'ctx> {
+        let is_negate = *op == *unary::NEGATE;
+        if *op == *unary::PLUS {
+            return self.apply(Rule::UnaryIdentity, operand);
+        }
+        match operand {
+            Node::Num(n) if is_negate => self.apply(Rule::FoldConstants, Node::Num(-n)),
+            Node::UOp(inner, operand) if is_negate && *inner == *unary::NEGATE => {
+                self.apply(Rule::UnaryIdentity, *operand)
+            }
+            operand => Node::UOp(op, Box::new(operand)),
+        }
+    }
+
+    fn bi_op<'a>(
+        &mut self,
+        op: &'ctx BiOp,
+        builtin: Builtin,
+        left: Node<'a, 'ctx>,
+        right: Node<'a, 'ctx>,
+    ) -> Node<'a, 'ctx> {
+        let assume_defined = self.options.assume_defined;
+        if let (Node::Num(l), Node::Num(r)) = (&left, &right) {
+            let result = op.apply(*l, *r);
+            if result.is_finite() {
+                return self.apply(Rule::FoldConstants, Node::Num(result));
+            }
+        }
+        match (builtin, left, right) {
+            (Builtin::Add, x, Node::Num(n))
+            | (Builtin::Add, Node::Num(n), x)
+            | (Builtin::Subtract, x, Node::Num(n))
+                if n == 0.0 =>
+            {
+                self.apply(Rule::AddZero, x)
+            }
+            (Builtin::Subtract, Node::Num(n), x) if n == 0.0 => match self.negate(x) {
+                Ok(negated) => self.apply(Rule::AddZero, negated),
+                Err(x) => Node::BiOp(op, Box::new(Node::Num(n)), Box::new(x)),
+            },
+            (Builtin::Add, left, right) | (Builtin::Subtract, left, right) => {
+                self.sum(Node::BiOp(op, Box::new(left), Box::new(right)))
+            }
+            (Builtin::Multiply, x, Node::Num(n)) | (Builtin::Multiply, Node::Num(n), x)
+                if n == 1.0 =>
+            {
+                self.apply(Rule::MultiplyByOne, x)
+            }
+            (Builtin::Divide, x, Node::Num(1.0)) => self.apply(Rule::MultiplyByOne, x),
+            (Builtin::Multiply, Node::Num(n), x) | (Builtin::Multiply, x, Node::Num(n))
+                if n == -1.0 =>
+            {
+                match self.negate(x) {
+                    Ok(negated) => self.apply(Rule::MultiplyByOne, negated),
+                    Err(x) => Node::BiOp(op, Box::new(Node::Num(n)), Box::new(x)),
+                }
+            }
+            (Builtin::Multiply, _, Node::Num(n))
+            | (Builtin::Multiply, Node::Num(n), _)
+            | (Builtin::Divide, Node::Num(n), _)
+                if n == 0.0 && assume_defined =>
+            {
+                self.apply(Rule::MultiplyByZero, Node::Num(0.0))
+            }
+            (Builtin::Divide, left, right) if left == right && assume_defined => {
+                self.apply(Rule::CancelDivision, Node::Num(1.0))
+            }
+            (Builtin::Power, x, Node::Num(1.0)) => self.apply(Rule::PowerIdentity, x),
+            (Builtin::Power, _, Node::Num(0.0)) => self.apply(Rule::PowerIdentity, Node::Num(1.0)),
+            (Builtin::Power, Node::Num(1.0), _) => self.apply(Rule::PowerIdentity, Node::Num(1.0)),
+            (_, left, right) => Node::BiOp(op, Box::new(left), Box::new(right)),
+        }
+    }
+
+    fn func<'a>(&mut self, func: &'ctx Func, args: Vec<Node<'a, 'ctx>>) -> Node<'a, 'ctx> {
+        let is_pure = [&*FN_MAX, &*FN_SUM, &*FN_PROD, &*FN_SUB].contains(&func);
+        let values: Option<Vec<f64>> = args
+            .iter()
+            .map(|arg| match arg {
+                Node::Num(n) => Some(*n),
+                _ => None,
+            })
+            .collect();
+        if let (true, Some(values)) = (is_pure, values) {
+            if let Ok(result) = func.call(&values) {
+                if result.is_finite() {
+                    return self.apply(Rule::FoldConstants, Node::Num(result));
+                }
+            }
+        }
+        Node::Func(func, args)
+    }
+
+    /// Negates the `node` using the unary minus from the context, returns the `node` back if there is none.
+    fn negate<'a>(&self, node: Node<'a, 'ctx>) -> Result<Node<'a, 'ctx>, Node<'a, 'ctx>> {
+        match node {
+            Node::Num(n) => Ok(Node::Num(-n)),
+            Node::UOp(op, operand) if *op == *unary::NEGATE => Ok(*operand),
+            node => match self.ctx.u_ops.iter().find(|op| **op == *unary::NEGATE) {
+                Some(op) => Ok(Node::UOp(op, Box::new(node))),
+                None => Err(node),
+            },
+        }
+    }
+
+    fn find_bi_op(&self, builtin: Builtin) -> Option<&'ctx BiOp> {
+        self.ctx
+            .bi_ops
+            .iter()
+            .find(|op| op.builtin == Some(builtin))
+    }
+
+    /// Combines the like terms of the sum.
+    fn sum<'a>(&mut self, sum: Node<'a, 'ctx>) -> Node<'a, 'ctx> {
+        let mut terms = Vec::new();
+        collect_terms(sum.clone(), 1.0, &mut terms);
+        let n_terms = terms.len();
+        let mut constant = None;
+        let mut groups: Vec<Vec<Term<'a, 'ctx>>> = Vec::new();
+        for (coefficient, rest) in terms {
+            match rest {
+                None => *constant.get_or_insert(0.0) += coefficient,
+                Some(rest) => match groups
+                    .iter_mut()
+                    .find(|group| group[0].1.as_ref() == Some(&rest))
+                {
+                    Some(group) => group.push((coefficient, Some(rest))),
+                    None => groups.push(vec![(coefficient, Some(rest))]),
+                },
+            }
+        }
+        let mut combined: Vec<Term<'a, 'ctx>> = Vec::new();
+        for group in groups {
+            let coefficient: f64 = group.iter().map(|(c, _)| c).sum();
+            if coefficient == 0.0 && !self.options.assume_defined {
+                // x - x is not 0 if x is not finite
+                combined.extend(group);
+            } else if coefficient != 0.0 {
+                combined.push((coefficient, group.into_iter().next().unwrap().1));
+            }
+        }
+        if let Some(constant) = constant.filter(|c| *c != 0.0) {
+            combined.push((constant, None));
+        }
+        if combined.len() >= n_terms {
+            return sum;
+        }
+        match self.build_sum(combined) {
+            Some(result) => self.apply(Rule::CombineLikeTerms, result),
+            None => sum,
+        }
+    }
+
+    fn build_sum<'a>(&self, terms: Vec<Term<'a, 'ctx>>) -> Option<Node<'a, 'ctx>> {
+        let add = self.find_bi_op(Builtin::Add)?;
+        let subtract = self.find_bi_op(Builtin::Subtract)?;
+        let multiply = self.find_bi_op(Builtin::Multiply)?;
+        let term = |coefficient: f64, rest: Option<Node<'a, 'ctx>>| match rest {
+            None => Node::Num(coefficient),
+            Some(rest) if coefficient == 1.0 => rest,
+            Some(rest) => Node::BiOp(multiply, Box::new(Node::Num(coefficient)), Box::new(rest)),
+        };
+        let mut result: Option<Node<'a, 'ctx>> = None;
+        for (coefficient, rest) in terms {
+            result = Some(match result {
+                None => term(coefficient, rest),
+                Some(acc) if coefficient < 0.0 => {
+                    Node::BiOp(subtract, Box::new(acc), Box::new(term(-coefficient, rest)))
+                }
+                Some(acc) => Node::BiOp(add, Box::new(acc), Box::new(term(coefficient, rest))),
+            });
+        }
+        Some(result.unwrap_or(Node::Num(0.0)))
+    }
+}
+
+/// Flattens the sum into the list of terms multiplied by `sign`
+fn collect_terms<'a, 'ctx>(node: Node<'a, 'ctx>, sign: f64, terms: &mut Vec<Term<'a, 'ctx>>) {
+    match node {
+        Node::BiOp(op, left, right) if op.builtin == Some(Builtin::Add) => {
+            collect_terms(*left, sign, terms);
+            collect_terms(*right, sign, terms);
+        }
+        Node::BiOp(op, left, right) if op.builtin == Some(Builtin::Subtract) => {
+            collect_terms(*left, sign, terms);
+            collect_terms(*right, -sign, terms);
+        }
+        Node::UOp(op, operand) if *op == *unary::NEGATE => collect_terms(*operand, -sign, terms),
+        Node::Num(n) => terms.push((sign * n, None)),
+        Node::BiOp(op, left, right) if op.builtin == Some(Builtin::Multiply) => {
+            match (*left, *right) {
+                (Node::Num(c), rest) | (rest, Node::Num(c)) => terms.push((sign * c, Some(rest))),
+                (left, right) => {
+                    terms.push((sign, Some(Node::BiOp(op, Box::new(left), Box::new(right)))))
+                }
+            }
+        }
+        node => terms.push((sign, Some(node))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::differentiate;
+
+    fn simplify_str(input: &str, assume_defined: bool) -> String {
+        let ctx = Ctx::default();
+        let expr = Expression::parse(input, &ctx).unwrap();
+        let simplified = simplify(&expr, Options { assume_defined }).unwrap();
+        let node = Node::from_tokens(simplified.expression.tokens()).unwrap();
+        node.to_string()
+    }
+
+    #[test]
+    fn test_simplify() {
+        let input_expected = &[
+            ("x", "x"),
+            ("1 + 2 * 3", "7"),
+            ("x + 0", "x"),
+            ("0 + x * 1", "x"),
+            ("0 - x", "-x"),
+            ("x / 1 - 0", "x"),
+            ("--x", "x"),
+            ("+x", "x"),
+            ("-1 * x", "-x"),
+            ("x ^ 1", "x"),
+            ("x ^ 0", "1"),
+            ("1 ^ x", "1"),
+            ("x + x", "(2 * x)"),
+            ("2 * x - x + y - 3 * y", "(x - (2 * y))"),
+            ("x + 1 + y + 2", "((x + y) + 3)"),
+            ("sum(1, 2) * max(x, y)", "(3 * max(x, y))"),
+            ("x - x", "(x - x)"),
+            ("x / x", "(x / x)"),
+            ("0 * x", "(0 * x)"),
+            ("1 / 0", "(1 / 0)"),
+            ("x * y + y * x", "((x * y) + (y * x))"),
+        ];
+        for (input, expected) in input_expected {
+            let result = simplify_str(input, false);
+            assert_eq!(result, *expected, "input was {}", input);
+        }
+    }
+
+    #[test]
+    fn test_simplify_assume_defined() {
+        let input_expected = &[
+            ("x - x", "0"),
+            ("x / x", "1"),
+            ("0 * x", "0"),
+            ("0 / (x + y)", "0"),
+            ("(x + 1) / (x + 1) + y - y", "1"),
+        ];
+        for (input, expected) in input_expected {
+            let result = simplify_str(input, true);
+            assert_eq!(result, *expected, "input was {}", input);
+        }
+    }
+
+    #[test]
+    fn test_simplify_derivative() {
+        let ctx = Ctx::default();
+        let expr = Expression::parse("x ^ 3 + 2 * x", &ctx).unwrap();
+        let derivative = differentiate(&expr, "x").unwrap();
+        let simplified = simplify(&derivative, Options::default()).unwrap();
+        let node = Node::from_tokens(simplified.expression.tokens()).unwrap();
+        assert_eq!(node.to_string(), "((3 * (x ^ 2)) + 2)");
+        assert_eq!(
+            simplified.applied,
+            vec![Rule::MultiplyByOne, Rule::FoldConstants]
+        );
+    }
+}
@@ -0,0 +1,372 @@
+//! Evaluation of the expressions whose variables and functions are resolved asynchronously.
+//!
+//! This module is available with the `async` feature.
+//! It allows to evaluate the formulas that reference the values fetched from a database or a remote service
+//! without blocking the runtime. It does not depend on any particular runtime.
+//!
+//! Variables are read through [`AsyncVariableResolver`](AsyncVariableResolver)
+//! and functions are implemented by [`AsyncFunc`](AsyncFunc).
+//!
+//! # Example
+//!
+//! ```
+//! # fn block_on<F: std::future::Future>(future: F) -> F::Output {
+//! #     let mut future = Box::pin(future);
+//! #     let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+//! #     loop {
+//! #         if let std::task::Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+//! #             return output;
+//! #         }
+//! #     }
+//! # }
+//! use rusty_yard::asynchronous::{eval_str_async, AsyncFunc};
+//! use rusty_yard::Ctx;
+//! use std::collections::HashMap;
+//!
+//! let price = AsyncFunc::new("price", Some(1), |args| Box::pin(async move {
+//!     // fetch the price of the item args[0] from the database
+//!     args[0] * 10.0
+//! }));
+//! let mut ctx = Ctx::default();
+//! ctx.fns.push(price.declaration());
+//!
+//! let mut vars = HashMap::new();
+//! vars.insert("count".to_owned(), 3.0);
+//! let result = block_on(eval_str_async("price(2) * count", &mut vars, &ctx, &[price]));
+//! assert_eq!(result, Ok(60.0));
+//! ```
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Formatter};
+use std::future::Future;
+use std::pin::Pin;
+
+use crate::analysis::{functions_of, variables_of};
+use crate::evaluator::{self, eval_token, Error};
+use crate::functions::Func;
+use crate::parser::{parse, ParserToken};
+use crate::tokenizer::{apply_passes, tokenize};
+use crate::variables::VariableResolver;
+use crate::Ctx;
+
+/// The boxed future returned by the asynchronous resolvers and functions.
+pub type BoxFuture<'f, T> = Pin<Box<dyn Future<Output = T> + Send + 'f>>;
+
+/// The storage of variables that are read and written asynchronously.
+///
+/// It is the asynchronous counterpart of [`VariableResolver`](crate::variables::VariableResolver).
+pub trait AsyncVariableResolver: Send {
+    /// Get the value of variable `name`.
+    ///
+    /// Resolves to [`None`](std::option::Option::None) if the variable is not defined.
+    fn get<'r>(&'r self, name: &'r str) -> BoxFuture<'r, Option<f64>>;
+
+    /// Set the value of variable `name`, defining it if it did not exist.
+    fn set<'r>(&'r mut self, name: &'r str, value: f64) -> BoxFuture<'r, ()>;
+}
+
+impl AsyncVariableResolver for HashMap<String, f64> {
+    fn get<'r>(&'r self, name: &'r str) -> BoxFuture<'r, Option<f64>> {
+        let value = VariableResolver::get(self, name);
+        Box::pin(async move { value })
+    }
+
+    fn set<'r>(&'r mut self, name: &'r str, value: f64) -> BoxFuture<'r, ()> {
+        VariableResolver::set(self, name, value);
+        Box::pin(async {})
+    }
+}
+
+/// The implementation of [`AsyncFunc`](AsyncFunc), receives the arguments of the call.
+pub type AsyncFnImpl = dyn Fn(Vec<f64>) -> BoxFuture<'static, f64> + Send + Sync;
+
+/// Represents the function that is evaluated asynchronously.
+///
+/// The parser only knows about the functions of the [`Ctx`](crate::Ctx),
+/// so the [`declaration`](AsyncFunc::declaration) of the function should be added to [`Ctx::fns`](crate::Ctx::fns).
+pub struct AsyncFunc {
+    /// Identifier of the function.
+    pub token: String,
+    /// Arity of the function, `None` if the function is variadic.
+    pub arity: Option<usize>,
+    /// The implementation of the function.
+    pub func: Box<AsyncFnImpl>,
+}
+
+impl AsyncFunc {
+    /// Creates the function with the identifier `token`.
+    pub fn new(
+        token: &str,
+        arity: Option<usize>,
+        func: impl Fn(Vec<f64>) -> BoxFuture<'static, f64> + Send + Sync + 'static,
+    ) -> Self {
+        AsyncFunc {
+            token: token.to_owned(),
+            arity,
+            func: Box::new(func),
+        }
+    }
+
+    /// The synchronous declaration of this function that allows to parse its calls.
+    ///
+    /// The synchronous evaluator evaluates the declaration to `NaN`.
+    pub fn declaration(&self) -> Func {
+        Func::new(&self.token, self.arity, |_| f64::NAN)
+    }
+}
+
+impl Debug for AsyncFunc {
+    #[cfg_attr(tarpaulin, skip)]
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AsyncFunc")
+            .field("token", &self.token)
+            .field("arity", &self.arity)
+            .finish()
+    }
+}
+
+/// Variables of the macros, records the variables that were assigned.
+struct Scope {
+    values: HashMap<String, f64>,
+    assigned: Vec<String>,
+}
+
+impl VariableResolver for Scope {
+    fn get(&self, name: &str) -> Option<f64> {
+        self.values.get(name).copied()
+    }
+
+    fn set(&mut self, name: &str, value: f64) {
+        if !self.assigned.iter().any(|assigned| assigned == name) {
+            self.assigned.push(name.to_owned());
+        }
+        VariableResolver::set(&mut self.values, name, value);
+    }
+
+    fn names(&self) -> Vec<String> {
+        self.values.names()
+    }
+}
+
+/// Evaluates the token stream, resolving the variables and the functions in `fns` asynchronously.
+///
+/// The variables the expression reads, including the reads inside the macros (see [`variables_of`](crate::analysis::variables_of)),
+/// are resolved before the evaluation starts, the functions from `fns` are awaited when they are called,
+/// the other functions are called synchronously.
+///
+/// Macros are evaluated synchronously and only see the variables the expression reads.
+/// The variables they assign are written to the `variables` after the evaluation.
+/// The functions from `fns` can't be awaited inside the macros, e.g. in the branches of `if`,
+/// such calls are reported as [`FunctionError`](crate::evaluator::Error::FunctionError) before the evaluation starts.
+pub async fn eval_async(
+    tokens: &[ParserToken<'_, '_>],
+    variables: &mut dyn AsyncVariableResolver,
+    ctx: &Ctx,
+    fns: &[AsyncFunc],
+) -> evaluator::Result {
+    let mut scope = Scope {
+        values: HashMap::new(),
+        assigned: Vec::new(),
+    };
+    for token in tokens {
+        if let ParserToken::Macro(m) = token {
+            let called = functions_of(std::slice::from_ref(token), ctx);
+            if let Some(func) = fns.iter().find(|func| called.contains(&func.token)) {
+                return Err(Error::FunctionError {
+                    id: func.token.clone(),
+                    message: format!("asynchronous function can't be called inside {}", m.name()),
+                });
+            }
+        }
+    }
+    for name in variables_of(tokens, ctx) {
+        if let Some(value) = variables.get(&name).await {
+            scope.values.insert(name, value);
+        }
+    }
+    let mut eval_stack = Vec::new();
+    for (position, token) in tokens.iter().enumerate() {
+        let async_func = match *token {
+            ParserToken::Func(func, _) => fns.iter().find(|f| f.token == func.token),
+            _ => None,
+        };
+        match (token, async_func) {
+            (ParserToken::Func(_, call_args), Some(async_func)) => {
+                let call_args = *call_args;
+                if let Some(arity) = async_func.arity {
+                    if arity != call_args {
+                        return Err(Error::ArityMismatch {
+                            id: async_func.token.clone(),
+                            expected: arity.into(),
+                            actual: call_args,
+                        });
+                    }
+                }
+                if eval_stack.len() < call_args {
+                    return Err(Error::EmptyEvalStack);
+                }
+                let args = eval_stack.split_off(eval_stack.len() - call_args);
+                eval_stack.push((async_func.func)(args).await);
+            }
+            _ => eval_token(token, position, &mut scope, ctx, &mut eval_stack)?,
+        }
+    }
+    let result = eval_stack.pop().ok_or(Error::Other)?;
+    for name in &scope.assigned {
+        variables.set(name, scope.values[name]).await;
+    }
+    Ok(result)
+}
+
+/// Parses the `input` using the `ctx` and evaluates it with [`eval_async`](eval_async).
+pub async fn eval_str_async(
+    input: &str,
+    variables: &mut dyn AsyncVariableResolver,
+    ctx: &Ctx,
+    fns: &[AsyncFunc],
+) -> evaluator::Result {
+    let tokens = apply_passes(tokenize(input, ctx), ctx);
+    let parsed = parse(&tokens, ctx)?;
+    eval_async(&parsed, variables, ctx, fns).await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::task::{Context, Poll, Waker};
+
+    use super::*;
+    use crate::functions::FN_MAX;
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = Box::pin(future);
+        let mut cx = Context::from_waker(Waker::noop());
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+        }
+    }
+
+    /// The future that is pending once before it resolves, like the one waiting for the network.
+    struct YieldOnce(bool);
+
+    impl Future for YieldOnce {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            if self.0 {
+                Poll::Ready(())
+            } else {
+                self.0 = true;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+    }
+
+    /// Variables stored remotely.
+    #[derive(Debug, Default)]
+    struct Remote(HashMap<String, f64>);
+
+    impl AsyncVariableResolver for Remote {
+        fn get<'r>(&'r self, name: &'r str) -> BoxFuture<'r, Option<f64>> {
+            Box::pin(async move {
+                YieldOnce(false).await;
+                self.0.get(name).copied()
+            })
+        }
+
+        fn set<'r>(&'r mut self, name: &'r str, value: f64) -> BoxFuture<'r, ()> {
+            Box::pin(async move {
+                YieldOnce(false).await;
+                self.0.insert(name.to_owned(), value);
+            })
+        }
+    }
+
+    fn get_fns() -> Vec<AsyncFunc> {
+        vec![
+            AsyncFunc::new("double", Some(1), |args| {
+                Box::pin(async move {
+                    YieldOnce(false).await;
+                    args[0] * 2.0
+                })
+            }),
+            AsyncFunc::new("count", None, |args| {
+                Box::pin(async move { args.len() as f64 })
+            }),
+        ]
+    }
+
+    fn get_ctx(fns: &[AsyncFunc]) -> Ctx {
+        let mut ctx = Ctx::default_with_macros();
+        ctx.fns.extend(fns.iter().map(AsyncFunc::declaration));
+        ctx
+    }
+
+    #[test]
+    fn test_eval_str_async() {
+        let input_expected = &[
+            ("1 + 2", Ok(3.0)),
+            ("a * b", Ok(6.0)),
+            ("double(a) + 1", Ok(5.0)),
+            ("double(double(b))", Ok(12.0)),
+            ("max(double(a), b) - count(1, 2, 3)", Ok(1.0)),
+            ("count()", Ok(0.0)),
+            ("c", Err(Error::VarNotFound("c".to_owned(), None))),
+            ("if(b > 2, a, 0)", Ok(2.0)),
+            ("let t = a in t * b", Ok(6.0)),
+            (
+                "if(1, double(2), 0)",
+                Err(Error::FunctionError {
+                    id: "double".to_owned(),
+                    message: "asynchronous function can't be called inside if".to_owned(),
+                }),
+            ),
+        ];
+        let fns = get_fns();
+        let ctx = get_ctx(&fns);
+        let mut vars = Remote::default();
+        vars.0.insert("a".to_owned(), 2.0);
+        vars.0.insert("b".to_owned(), 3.0);
+        for (input, expected) in input_expected {
+            let result = block_on(eval_str_async(input, &mut vars, &ctx, &fns));
+            assert_eq!(result, *expected, "input was {}", input);
+        }
+    }
+
+    #[test]
+    fn test_eval_async_assign() {
+        let fns = get_fns();
+        let ctx = get_ctx(&fns);
+        let mut vars = Remote::default();
+        vars.0.insert("a".to_owned(), 2.0);
+        let result = block_on(eval_str_async("b = a = double(a)", &mut vars, &ctx, &fns));
+        assert_eq!(result, Ok(4.0));
+        assert_eq!(vars.0["a"], 4.0);
+        assert_eq!(vars.0["b"], 4.0);
+    }
+
+    #[test]
+    fn test_eval_async_arity_mismatch() {
+        let ctx = Ctx::default();
+        let tokens = vec![ParserToken::Num(1.0), ParserToken::Func(&FN_MAX, 1)];
+        let fns = vec![AsyncFunc::new("max", Some(2), |_| Box::pin(async { 0.0 }))];
+        let result = block_on(eval_async(&tokens, &mut HashMap::new(), &ctx, &fns));
+        assert_eq!(
+            result,
+            Err(Error::ArityMismatch {
+                id: "max".to_owned(),
+                expected: 2.into(),
+                actual: 1,
+            })
+        );
+    }
+
+    #[test]
+    fn test_eval_async_is_send() {
+        fn assert_send<T: Send>(_: T) {}
+        let ctx = Ctx::default();
+        assert_send(eval_str_async("1", &mut HashMap::new(), &ctx, &[]));
+    }
+}
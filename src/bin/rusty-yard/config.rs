@@ -0,0 +1,168 @@
+//! Reading of the configuration file.
+//!
+//! The file is toml, read with `config` feature. The values are numbers, booleans or strings,
+//! the keys before the first section are the options of `:set`.
+//!
+//! ```toml
+//! # result format, same options as :set
+//! precision = 6
+//! separator = ","
+//! angle = "deg"
+//!
+//! [variables]
+//! g = 9.81
+//! tau = "2 * 3.141592653589793"
+//!
+//! [functions]
+//! hypot = "(a, b) = (a^2 + b^2)^0.5"
+//! ```
+use std::fmt::{self, Display, Formatter};
+
+/// The value of the configuration entry
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(not(feature = "config"), allow(dead_code))]
+pub enum Value {
+    Number(f64),
+    Bool(bool),
+    Str(String),
+}
+
+/// Single `key = value` pair of the configuration file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    /// The section the entry is in, `None` before the first section header
+    pub section: Option<String>,
+    pub key: String,
+    pub value: Value,
+}
+
+/// Parses the configuration file, the error message of the invalid toml has the line number
+///
+/// The entries are returned in the order of the file, the keys before the first section come first.
+#[cfg(feature = "config")]
+pub fn parse(input: &str) -> Result<Vec<Entry>, String> {
+    let table: toml::Table = input.parse().map_err(|e: toml::de::Error| e.to_string())?;
+    let mut entries = Vec::new();
+    for (name, value) in table {
+        match value {
+            toml::Value::Table(section) => {
+                for (key, value) in section {
+                    entries.push(Entry::new(Some(name.clone()), key, value)?);
+                }
+            }
+            value => entries.push(Entry::new(None, name, value)?),
+        }
+    }
+    Ok(entries)
+}
+
+/// Reports that the configuration can't be read without `config` feature
+#[cfg(not(feature = "config"))]
+pub fn parse(_input: &str) -> Result<Vec<Entry>, String> {
+    Err("the configuration file is read with `config` feature".to_owned())
+}
+
+#[cfg(feature = "config")]
+impl Entry {
+    fn new(section: Option<String>, key: String, value: toml::Value) -> Result<Self, String> {
+        let value = match value {
+            toml::Value::Integer(n) => Some(Value::Number(n as f64)),
+            toml::Value::Float(n) => Some(Value::Number(n)),
+            toml::Value::Boolean(b) => Some(Value::Bool(b)),
+            toml::Value::String(s) => Some(Value::Str(s)),
+            _ => None,
+        };
+        let mut entry = Entry {
+            section,
+            key,
+            value: Value::Bool(false),
+        };
+        match value {
+            Some(value) => {
+                entry.value = value;
+                Ok(entry)
+            }
+            None => Err(format!("{}: expected number, boolean or string", entry)),
+        }
+    }
+}
+
+impl Display for Entry {
+    /// Writes the key of the entry, `[section] key` for the keys in the sections
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.section {
+            Some(section) => write!(f, "[{}] {}", section, self.key),
+            None => write!(f, "{}", self.key),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "config"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let input = "
+# comment
+precision = 6 # trailing comment
+rational = true
+
+[variables]
+g = 9.81
+big = 1_000
+tau = \"2 * pi # not a comment\"
+quote = 'a \\ b'
+
+[functions]
+sq = \"(x) = x^2\"
+";
+        let entries = parse(input).unwrap();
+        let entries: Vec<_> = entries
+            .iter()
+            .map(|e| (e.section.as_deref(), e.key.as_str(), e.value.clone()))
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                (None, "precision", Value::Number(6.0)),
+                (None, "rational", Value::Bool(true)),
+                (Some("variables"), "g", Value::Number(9.81)),
+                (Some("variables"), "big", Value::Number(1000.0)),
+                (
+                    Some("variables"),
+                    "tau",
+                    Value::Str("2 * pi # not a comment".to_owned())
+                ),
+                (Some("variables"), "quote", Value::Str("a \\ b".to_owned())),
+                (Some("functions"), "sq", Value::Str("(x) = x^2".to_owned())),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_err() {
+        let input_expected = &[
+            ("[variables", "line 1"),
+            ("\n\nprecision", "line 3"),
+            ("= 1", "line 1"),
+            ("a = \"unterminated", "line 1"),
+            ("a = b", "line 1"),
+            ("a = 1\na = 2", "line 2"),
+            ("a = [1, 2]", "a: expected number, boolean or string"),
+            (
+                "[variables]\nx = { y = 1 }",
+                "[variables] x: expected number, boolean or string",
+            ),
+        ];
+        for (input, expected) in input_expected {
+            let result = parse(input).unwrap_err();
+            assert!(
+                result.contains(expected),
+                "input was {}, error {}",
+                input,
+                result
+            );
+        }
+    }
+}
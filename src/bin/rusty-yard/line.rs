@@ -0,0 +1,86 @@
+//! Reads the lines of the interactive calculator
+//!
+//! With `readline` feature the lines can be edited, and the history is kept in the history file between the sessions.
+//! Without it the lines are read from stdin as they are.
+use std::error::Error;
+use std::path::PathBuf;
+
+#[cfg(feature = "readline")]
+use rustyline::{error::ReadlineError, DefaultEditor};
+
+/// Reads the lines with the prompt
+pub struct LineReader {
+    #[cfg(feature = "readline")]
+    editor: DefaultEditor,
+    /// The file the history is loaded from and saved to
+    #[cfg_attr(not(feature = "readline"), allow(dead_code))]
+    history: Option<PathBuf>,
+}
+
+#[cfg(feature = "readline")]
+impl LineReader {
+    #[cfg_attr(tarpaulin, skip)]
+    pub fn new(history: Option<PathBuf>) -> Result<Self, Box<dyn Error>> {
+        let mut editor = DefaultEditor::new()?;
+        if let Some(path) = &history {
+            // there is no history before the first session
+            let _ = editor.load_history(path);
+        }
+        Ok(Self { editor, history })
+    }
+
+    /// Reads the next line, returns `None` at the end of the input
+    ///
+    /// Ctrl-C discards the line that is being edited.
+    #[cfg_attr(tarpaulin, skip)]
+    pub fn read_line(&mut self, prompt: &str) -> Result<Option<String>, Box<dyn Error>> {
+        match self.editor.readline(prompt) {
+            Ok(line) => {
+                if !line.trim().is_empty() {
+                    self.editor.add_history_entry(line.as_str())?;
+                }
+                Ok(Some(line))
+            }
+            Err(ReadlineError::Interrupted) => Ok(Some(String::new())),
+            Err(ReadlineError::Eof) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Saves the history to the history file
+    #[cfg_attr(tarpaulin, skip)]
+    pub fn save_history(&mut self) -> Result<(), Box<dyn Error>> {
+        if let Some(path) = &self.history {
+            self.editor.save_history(path)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "readline"))]
+impl LineReader {
+    #[cfg_attr(tarpaulin, skip)]
+    pub fn new(history: Option<PathBuf>) -> Result<Self, Box<dyn Error>> {
+        Ok(Self { history })
+    }
+
+    /// Reads the next line, returns `None` at the end of the input
+    #[cfg_attr(tarpaulin, skip)]
+    pub fn read_line(&mut self, prompt: &str) -> Result<Option<String>, Box<dyn Error>> {
+        use std::io::{stdin, stdout, Write};
+
+        print!("{}", prompt);
+        stdout().flush()?;
+        let mut line = String::new();
+        if stdin().read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        Ok(Some(line))
+    }
+
+    /// The history is not kept without `readline` feature
+    #[cfg_attr(tarpaulin, skip)]
+    pub fn save_history(&mut self) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+}
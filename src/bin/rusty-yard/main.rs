@@ -0,0 +1,924 @@
+use std::collections::HashMap;
+use std::io::{stdin, IsTerminal, Read};
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+use rusty_yard::diagnostics::Diagnostic;
+use rusty_yard::fmt::{Notation, NumberFormat};
+use rusty_yard::functions::packs::trig_functions;
+use rusty_yard::functions::{default_functions, Func};
+use rusty_yard::macros::user_fn::UserFn;
+use rusty_yard::operators::binary::Associativity;
+use rusty_yard::operators::unary::PercentMode;
+use rusty_yard::operators::{default_aliases, BiOp};
+use rusty_yard::parser::{
+    parse_all_errors, parse_str, parse_str_with_spans, split_statements_with_comments, Ast,
+    ParserToken, SpannedError,
+};
+use rusty_yard::printer::to_infix;
+use rusty_yard::random::Rng;
+use rusty_yard::tokenizer::{
+    apply_passes, apply_passes_with_spans, match_id, tokenize, tokenize_with_spans, Match, Token,
+};
+use rusty_yard::{evaluator, Ctx};
+
+mod config;
+mod line;
+
+const USAGE: &str = "\
+Usage: rusty-yard [options] [script]
+
+Without arguments starts the interactive calculator, unless the input is piped.
+With a script file, or with piped input, evaluates each statement and prints the results.
+Statements are separated by new lines and ';'.
+
+Options:
+  -e, --eval <expr>          evaluate the expression, print the result and exit
+      --var <name>=<expr>    define the variable before the evaluation, can be repeated
+      --init <file>          read the configuration from the file instead of ~/.rusty-yard.toml
+  -h, --help                 print this message
+
+Exit status is 0 on success, 1 if any evaluation failed and 2 on invalid arguments.";
+
+/// The configuration file in the home directory, see `config` module for the format
+const CONFIG_FILE: &str = ".rusty-yard.toml";
+
+/// The history of the interactive calculator in the home directory
+const HISTORY_FILE: &str = ".rusty-yard_history";
+
+/// Variables that hold the result of the last successful evaluation
+const RESULT_VARIABLES: &[&str] = &["ans", "_"];
+
+/// The trigonometric functions of the angle, see [`set_angle`](set_angle)
+const ANGLE_ARGUMENT_FNS: &[&str] = &["sin", "cos", "tan", "sec", "csc", "cot"];
+
+/// The inverse trigonometric functions that return the angle, see [`set_angle`](set_angle)
+const ANGLE_RESULT_FNS: &[&str] = &["asin", "acos", "atan", "atan2"];
+
+const HELP: &str = "\
+Enter an expression to evaluate it, the result is stored in `ans` and `_` variables.
+Or enter one of the commands:
+  :help     show this message
+  :quit     exit the calculator, also :q, :exit or Ctrl-D
+  :vars     list defined variables
+  :del      remove the variables: :del <name>...
+  :fns      list functions, also :funcs
+  :consts   list constants
+  :ops      list operators
+  :macros   list macros
+  :clear    remove all variables
+  :tokens   print the tokens of the expression: :tokens <expr>
+  :rpn      print the parsed expression in reverse polish notation: :rpn <expr>
+  :ast      print the syntax tree of the expression: :ast <expr>
+  :fmt      print the expression with the minimal parentheses: :fmt <expr>
+  :deffn    define a function: :deffn hypot(a, b) = (a^2 + b^2)^0.5
+  :defop    define a binary operator from an existing one, or from a function of 2 arguments:
+              :defop <token> <precedence> <left|right> = <op|fn>
+  :time     print the duration of tokenize, parse and eval: :time on|off
+  :set      change result format:
+              :set precision <digits>    significant digits
+              :set decimals <digits>     fixed decimal places
+              :set scientific <digits>   scientific notation
+              :set rational              fractions when possible
+              :set auto                  shortest exact representation
+              :set separator <char|off>  thousands separator
+              :set percent <calculator|plain>
+                                         200 + 10% is 220 or 200.1
+              :set angle <deg|rad>       the angles of sin, asin and others";
+
+/// The error of [`Session::eval`](Session::eval) and [`Session::command`](Session::command)
+#[derive(Debug)]
+enum Failure {
+    /// Every error of the expression, there is at least one
+    Parse(Vec<SpannedError>),
+    Eval(evaluator::Error),
+    Command(String),
+}
+
+impl Failure {
+    /// Writes the error to stderr, with the diagnostics of the parser errors of the `input`
+    fn report(&self, input: &str, ctx: &Ctx) {
+        match self {
+            Failure::Parse(errors) => {
+                for e in errors {
+                    report_parse_error(e, input, ctx);
+                }
+            }
+            Failure::Eval(e) => eprintln!("Error: {}", e),
+            Failure::Command(e) => eprintln!("Error: {}", e),
+        }
+    }
+}
+
+/// Writes the diagnostic of the parser error of the `input` to stderr, colored if it is the terminal
+fn report_parse_error(e: &SpannedError, input: &str, ctx: &Ctx) {
+    let diagnostic = Diagnostic::new(e, input, ctx);
+    eprint!(
+        "{}",
+        diagnostic
+            .render(input)
+            .colored(std::io::stderr().is_terminal())
+    );
+}
+
+/// Replaces the trigonometric functions of the `ctx` with the ones that take and return the angles
+/// in degrees if `degrees` is set, or in radians otherwise
+fn set_angle(ctx: &mut Ctx, degrees: bool) {
+    for func in default_functions().into_iter().chain(trig_functions()) {
+        let token = func.token.clone();
+        let func = if !degrees {
+            func
+        } else if ANGLE_ARGUMENT_FNS.contains(&token.as_str()) {
+            Func::new(&token, func.arity, move |args| {
+                func.call(&[args[0].to_radians()]).unwrap_or(f64::NAN)
+            })
+        } else if ANGLE_RESULT_FNS.contains(&token.as_str()) {
+            Func::new(&token, func.arity, move |args| {
+                func.call(args).map_or(f64::NAN, f64::to_degrees)
+            })
+        } else {
+            continue;
+        };
+        if let Some(existing) = ctx.fns.iter_mut().find(|existing| existing.token == token) {
+            *existing = func;
+        }
+    }
+}
+
+/// Whether the `input` opens more parens than it closes
+fn has_unclosed_parens(input: &str, ctx: &Ctx) -> bool {
+    let depth = tokenize(input, ctx)
+        .iter()
+        .fold(0i64, |depth, token| match token {
+            Token::OpenParen => depth + 1,
+            Token::ClosedParen => depth - 1,
+            _ => depth,
+        });
+    depth > 0
+}
+
+/// Whether the session continues after the command
+#[derive(Debug, PartialEq, Eq)]
+enum Flow {
+    Continue,
+    Quit,
+}
+
+/// The state of the read, eval, print loop
+struct Session {
+    vars: HashMap<String, f64>,
+    ctx: Ctx,
+    /// The functions defined by `:deffn`, they are also the macros of the `ctx`
+    user_fns: HashMap<String, UserFn>,
+    format: NumberFormat,
+    /// Print the duration of each phase of the evaluation
+    timing: bool,
+}
+
+impl Session {
+    fn new() -> Self {
+        Self {
+            vars: HashMap::new(),
+            ctx: Self::base_ctx(),
+            user_fns: HashMap::new(),
+            format: NumberFormat::default(),
+            timing: false,
+        }
+    }
+
+    /// The context of the new session
+    fn base_ctx() -> Ctx {
+        Ctx {
+            percent_mode: PercentMode::Calculator,
+            comments: true,
+            aliases: default_aliases(),
+            ..Ctx::default_with_macros()
+                .with_trig()
+                .with_statistics()
+                .with_finance()
+                .with_random(&Rng::default())
+        }
+    }
+
+    /// The copy of the current context, with the operators, the functions and the constants defined so far
+    fn snapshot_ctx(&self) -> Ctx {
+        let mut ctx = Ctx {
+            bi_ops: self.ctx.bi_ops.clone(),
+            u_ops: self.ctx.u_ops.clone(),
+            fns: self.ctx.fns.clone(),
+            consts: self.ctx.consts.clone(),
+            percent_mode: self.ctx.percent_mode,
+            ..Self::base_ctx()
+        };
+        for func in self.user_fns.values() {
+            ctx.macros.insert(0, Box::new(func.clone()));
+        }
+        ctx
+    }
+
+    /// Evaluates the `input`, binding the result to `ans` and `_` if evaluation succeeded
+    fn eval(&mut self, input: &str) -> Result<f64, Failure> {
+        let start = Instant::now();
+        let (tokens, spans) = tokenize_with_spans(input, &self.ctx);
+        let (tokens, spans) = apply_passes_with_spans(tokens, spans, &self.ctx);
+        let tokenized = Instant::now();
+        let parsed = parse_all_errors(&tokens, &spans, &self.ctx);
+        let parsed_at = Instant::now();
+        let res = match parsed {
+            Ok(parsed) => evaluator::eval_with_vars_and_ctx(&parsed, &mut self.vars, &self.ctx)
+                .map_err(Failure::Eval),
+            Err(e) => Err(Failure::Parse(e)),
+        };
+        if self.timing {
+            let evaluated = Instant::now();
+            eprintln!(
+                "tokenize: {}, parse: {}, eval: {}",
+                fmt_duration(tokenized - start),
+                fmt_duration(parsed_at - tokenized),
+                fmt_duration(evaluated - parsed_at)
+            );
+        }
+        let res = res?;
+        for name in RESULT_VARIABLES {
+            self.vars.insert((*name).to_owned(), res);
+        }
+        Ok(res)
+    }
+
+    /// Evaluates each statement of the `input`, returns `true` if all of them succeeded
+    fn eval_batch(&mut self, input: &str) -> bool {
+        let mut succeeded = true;
+        for statement in split_statements_with_comments(input, &self.ctx) {
+            if let Some(command) = statement.text.trim().strip_prefix(':') {
+                let command = command.trim();
+                match self.command(command) {
+                    Ok(Flow::Continue) => continue,
+                    Ok(Flow::Quit) => break,
+                    Err(e) => {
+                        eprint!("line {}: ", statement.line);
+                        e.report(command, &self.ctx);
+                        succeeded = false;
+                        continue;
+                    }
+                }
+            }
+            match self.eval(statement.text) {
+                Ok(res) => println!("{}", self.format.format(res)),
+                Err(e) => {
+                    eprint!("line {}: ", statement.line);
+                    e.report(statement.text, &self.ctx);
+                    succeeded = false;
+                }
+            }
+        }
+        succeeded
+    }
+
+    /// Runs the `command` without the leading `:`, the spans of the parser errors are in the `command`
+    fn command(&mut self, command: &str) -> Result<Flow, Failure> {
+        let command = command.trim();
+        let (name, rest) = command
+            .split_once(char::is_whitespace)
+            .unwrap_or((command, ""));
+        let mut args = rest.split_whitespace();
+        match name {
+            "help" => println!("{}", HELP),
+            "quit" | "q" | "exit" => return Ok(Flow::Quit),
+            "vars" => {
+                let mut vars: Vec<_> = self.vars.iter().collect();
+                vars.sort_by(|a, b| a.0.cmp(b.0));
+                for (name, value) in vars {
+                    println!("{} = {}", name, value);
+                }
+            }
+            "del" => {
+                let mut names = args.peekable();
+                if names.peek().is_none() {
+                    return Err(Failure::Command(
+                        "expected variable names: :del <name>...".to_owned(),
+                    ));
+                }
+                let undefined: Vec<_> = names
+                    .filter(|name| self.vars.remove(*name).is_none())
+                    .collect();
+                match undefined.as_slice() {
+                    [] => {}
+                    [name] => {
+                        return Err(Failure::Command(format!(
+                            "variable {} is not defined",
+                            name
+                        )))
+                    }
+                    names => {
+                        return Err(Failure::Command(format!(
+                            "variables {} are not defined",
+                            names.join(", ")
+                        )))
+                    }
+                }
+            }
+            "fns" | "funcs" => {
+                for func in &self.ctx.fns {
+                    let arity = func.arity_range();
+                    match (arity.min, arity.max) {
+                        (0, None) => println!("{} (variadic)", func.token),
+                        (1, Some(1)) => println!("{} (1 argument)", func.token),
+                        _ => println!("{} ({} arguments)", func.token, arity),
+                    }
+                }
+            }
+            "consts" => {
+                for constant in &self.ctx.consts {
+                    println!("{} = {}", constant.token, constant.value);
+                }
+            }
+            "ops" => {
+                for op in &self.ctx.bi_ops {
+                    let associativity = match op.associativity {
+                        Associativity::LEFT => "left",
+                        Associativity::RIGHT => "right",
+                    };
+                    println!(
+                        "a {} b (precedence {}, {} associative)",
+                        op.token, op.precedence, associativity
+                    );
+                }
+                for op in &self.ctx.u_ops {
+                    if op.postfix {
+                        println!("a{}", op.token);
+                        continue;
+                    }
+                    match op.precedence {
+                        Some(precedence) => println!("{}a (precedence {})", op.token, precedence),
+                        None => println!("{}a", op.token),
+                    }
+                }
+            }
+            "macros" => {
+                for m in &self.ctx.macros {
+                    println!("{:?}", m);
+                }
+            }
+            "clear" => self.vars.clear(),
+            "tokens" => {
+                let tokens = apply_passes(tokenize(rest, &self.ctx), &self.ctx);
+                let texts: Vec<_> = tokens.iter().map(|t| t.token_text()).collect();
+                println!("{}", texts.join(" "));
+            }
+            "rpn" => {
+                for token in &self.parse_argument(command, rest)? {
+                    let kind = match token {
+                        ParserToken::Num(_) => "number",
+                        ParserToken::Id(_) => "variable",
+                        ParserToken::Str(_) => "string",
+                        ParserToken::UOp(_) => "unary operator",
+                        ParserToken::BiOp(_) => "binary operator",
+                        ParserToken::Func(..) => "function",
+                        ParserToken::Macro(_) => "macro",
+                    };
+                    println!("{:<16} {}", kind, token);
+                }
+            }
+            "ast" => match Ast::from_rpn(&self.parse_argument(command, rest)?) {
+                Some(ast) => print!("{:#}", ast),
+                None => return Err(Failure::Command("not a single expression".to_owned())),
+            },
+            "fmt" => match to_infix(&self.parse_argument(command, rest)?) {
+                Ok(infix) => println!("{}", infix),
+                Err(e) => return Err(Failure::Command(e.to_string())),
+            },
+            "time" => match args.next() {
+                Some("on") => self.timing = true,
+                Some("off") => self.timing = false,
+                None => println!("time {}", if self.timing { "on" } else { "off" }),
+                Some(other) => {
+                    return Err(Failure::Command(format!(
+                        "expected on or off, got {:?}",
+                        other
+                    )))
+                }
+            },
+            "deffn" => self.define_fn(rest).map_err(Failure::Command)?,
+            "defop" => self.define_op(rest).map_err(Failure::Command)?,
+            "set" => self
+                .set(args.next(), args.next())
+                .map_err(Failure::Command)?,
+            other => {
+                return Err(Failure::Command(format!(
+                    "unknown command :{}, see :help",
+                    other
+                )))
+            }
+        }
+        Ok(Flow::Continue)
+    }
+
+    /// Parses the `argument` of the `command`, the spans of the errors are moved to be in the whole `command`
+    fn parse_argument<'a>(
+        &self,
+        command: &str,
+        argument: &'a str,
+    ) -> Result<Vec<ParserToken<'a, '_>>, Failure> {
+        parse_str_with_spans(argument, &self.ctx).map_err(|mut e| {
+            let offset = command.len() - argument.len();
+            e.span.start += offset;
+            e.span.end += offset;
+            Failure::Parse(vec![e])
+        })
+    }
+
+    /// Applies the configuration file
+    ///
+    /// Top level keys are the options of `:set`, `true` stands for the options without a value.
+    /// `[variables]` are evaluated in order and `[functions]` are defined as `:deffn <name><definition>`.
+    fn load_config(&mut self, input: &str) -> Result<(), String> {
+        for entry in config::parse(input)? {
+            let value = match &entry.value {
+                config::Value::Number(n) => Some(n.to_string()),
+                config::Value::Str(s) => Some(s.clone()),
+                config::Value::Bool(true) => None,
+                config::Value::Bool(false) => continue,
+            };
+            let res = match (entry.section.as_deref(), value) {
+                (None, value) => self.set(Some(&entry.key), value.as_deref()),
+                (Some("variables"), Some(expr)) => {
+                    evaluator::eval_str_with_vars_and_ctx(&expr, &mut self.vars, &self.ctx)
+                        .map(|value| {
+                            self.vars.insert(entry.key.clone(), value);
+                        })
+                        .map_err(|e| e.to_string())
+                }
+                (Some("functions"), Some(definition)) => {
+                    self.define_fn(&format!("{}{}", entry.key, definition))
+                }
+                (Some(section @ "variables"), None) | (Some(section @ "functions"), None) => {
+                    Err(format!("expected string in [{}]", section))
+                }
+                (Some(section), _) => Err(format!("unknown section [{}]", section)),
+            };
+            res.map_err(|e| format!("{}: {}", entry, e))?;
+        }
+        Ok(())
+    }
+
+    /// Defines the function from `<name>(<params>) = <body>`
+    fn define_fn(&mut self, definition: &str) -> Result<(), String> {
+        let (head, body) = definition
+            .split_once('=')
+            .ok_or("expected <name>(<params>) = <body>")?;
+        let (token, params) = head
+            .trim()
+            .strip_suffix(')')
+            .and_then(|head| head.split_once('('))
+            .ok_or("expected <name>(<params>) before =")?;
+        let token = token.trim();
+        let params: Vec<String> = match params.trim() {
+            "" => Vec::new(),
+            params => params.split(',').map(|p| p.trim().to_owned()).collect(),
+        };
+        for name in std::iter::once(token).chain(params.iter().map(String::as_str)) {
+            let is_id = match_id(name, &self.ctx).map(|Match(id, _)| id == name);
+            if is_id != Some(true) {
+                return Err(format!("invalid identifier {:?}", name));
+            }
+        }
+        parse_str(body, &self.ctx).map_err(|e| format!("invalid body: {}", e))?;
+        let func = UserFn {
+            token: token.to_owned(),
+            params,
+            body: body.trim().to_owned(),
+        };
+        // macros are matched in order, so the latest definition wins
+        self.ctx.macros.insert(0, Box::new(func.clone()));
+        self.user_fns.insert(func.token.clone(), func);
+        Ok(())
+    }
+
+    /// Defines the binary operator from `<token> <precedence> <left|right> = <op>`
+    ///
+    /// `<op>` is the existing binary operator, or the function of two arguments, built-in or defined by `:deffn`
+    fn define_op(&mut self, definition: &str) -> Result<(), String> {
+        const EXPECTED: &str = "expected <token> <precedence> <left|right> = <op>";
+        let (head, op) = definition.split_once('=').ok_or(EXPECTED)?;
+        let head: Vec<_> = head.split_whitespace().collect();
+        let (token, precedence, associativity) = match head.as_slice() {
+            [token, precedence, associativity] => (token, precedence, associativity),
+            _ => return Err(EXPECTED.to_owned()),
+        };
+        let precedence = precedence
+            .parse()
+            .map_err(|_| format!("invalid precedence {}", precedence))?;
+        let associativity = match *associativity {
+            "left" => Associativity::LEFT,
+            "right" => Associativity::RIGHT,
+            other => return Err(format!("expected left or right, got {}", other)),
+        };
+        let name = op.trim();
+        let op = if let Some(op) = self.ctx.bi_op(name) {
+            BiOp {
+                token: (*token).to_owned(),
+                precedence,
+                associativity,
+                ..op.clone()
+            }
+        } else if let Some(func) = self.user_fns.get(name) {
+            if func.params.len() != 2 {
+                return Err(format!(
+                    "{} is not a function of 2 arguments, it takes {}",
+                    name,
+                    func.params.len()
+                ));
+            }
+            // the body sees only the parameters, the variables of the session can change after the definition
+            let ctx = self.snapshot_ctx();
+            let func = func.clone();
+            BiOp::new(token, precedence, associativity, move |a, b| {
+                let mut vars: HashMap<_, _> = func.params.iter().cloned().zip(vec![a, b]).collect();
+                evaluator::eval_str_with_vars_and_ctx(&func.body, &mut vars, &ctx)
+                    .unwrap_or(f64::NAN)
+            })
+        } else if let Some(func) = self.ctx.func(name) {
+            if !func.accepts(2) {
+                return Err(format!(
+                    "{} is not a function of 2 arguments, it takes {}",
+                    name,
+                    func.arity_range()
+                ));
+            }
+            let func = func.clone();
+            BiOp::new(token, precedence, associativity, move |a, b| {
+                func.call(&[a, b]).unwrap_or(f64::NAN)
+            })
+        } else {
+            return Err(format!(
+                "{} is not a binary operator or a function of 2 arguments",
+                name
+            ));
+        };
+        self.ctx
+            .bi_ops
+            .retain(|existing| existing.token != op.token);
+        self.ctx.bi_ops.push(op);
+        Ok(())
+    }
+
+    fn set(&mut self, option: Option<&str>, value: Option<&str>) -> Result<(), String> {
+        let digits = || -> Result<usize, String> {
+            let value = value.ok_or("expected number of digits")?;
+            value
+                .parse()
+                .map_err(|_| format!("invalid number of digits: {}", value))
+        };
+        match option.unwrap_or_default() {
+            "precision" => match digits()? {
+                0 => return Err("precision should be at least 1".to_owned()),
+                digits => self.format.notation = Notation::Significant(digits),
+            },
+            "decimals" => self.format.notation = Notation::Fixed(digits()?),
+            "scientific" => self.format.notation = Notation::Scientific(digits()?),
+            "rational" => self.format.notation = Notation::Rational,
+            "auto" => self.format.notation = Notation::Auto,
+            "separator" => {
+                self.format.thousands_separator = match value {
+                    Some("off") => None,
+                    Some(separator) if separator.chars().count() == 1 => separator.chars().next(),
+                    _ => return Err("expected a single character or off".to_owned()),
+                }
+            }
+            "percent" => {
+                self.ctx.percent_mode = match value {
+                    Some("calculator") => PercentMode::Calculator,
+                    Some("plain") => PercentMode::Plain,
+                    _ => return Err("expected calculator or plain".to_owned()),
+                }
+            }
+            "angle" => match value {
+                Some("deg") => set_angle(&mut self.ctx, true),
+                Some("rad") => set_angle(&mut self.ctx, false),
+                _ => return Err("expected deg or rad".to_owned()),
+            },
+            other => return Err(format!("unknown option {:?}, see :help", other)),
+        }
+        Ok(())
+    }
+}
+
+/// Formats the `duration` in the most fitting unit
+fn fmt_duration(duration: Duration) -> String {
+    let nanos = duration.as_nanos();
+    if nanos < 1_000 {
+        format!("{}ns", nanos)
+    } else if nanos < 1_000_000 {
+        format!("{:.2}µs", nanos as f64 / 1e3)
+    } else if nanos < 1_000_000_000 {
+        format!("{:.2}ms", nanos as f64 / 1e6)
+    } else {
+        format!("{:.2}s", duration.as_secs_f64())
+    }
+}
+
+#[cfg_attr(tarpaulin, skip)]
+/// Simple read, eval, print loop
+fn repl(session: &mut Session) -> Result<(), Box<dyn std::error::Error>> {
+    let history = home_file(HISTORY_FILE);
+    let mut reader = line::LineReader::new(history)?;
+    while let Some(mut input) = reader.read_line(">> ")? {
+        if input.trim().is_empty() {
+            continue;
+        }
+        // the expression continues on the next lines until the parens are closed, or the line is empty
+        while !input.trim_start().starts_with(':') && has_unclosed_parens(&input, &session.ctx) {
+            match reader.read_line(".. ")? {
+                Some(line) if !line.trim().is_empty() => {
+                    input.push('\n');
+                    input.push_str(&line);
+                }
+                _ => break,
+            }
+        }
+        match input.trim().strip_prefix(':') {
+            Some(command) => match session.command(command.trim()) {
+                Ok(Flow::Continue) => {}
+                Ok(Flow::Quit) => break,
+                Err(e) => e.report(command.trim(), &session.ctx),
+            },
+            None => match session.eval(&input) {
+                Ok(res) => println!("{}", session.format.format(res)),
+                Err(e) => {
+                    e.report(&input, &session.ctx);
+                }
+            },
+        }
+    }
+    reader.save_history()
+}
+
+/// The path of the `file` in the home directory
+fn home_file(file: &str) -> Option<std::path::PathBuf> {
+    std::env::var_os("HOME").map(|home| std::path::Path::new(&home).join(file))
+}
+
+/// What the binary was asked to do by the command line arguments
+enum Mode {
+    Help,
+    Repl,
+    Stdin,
+    Script(String),
+    Eval(String),
+}
+
+/// Parsed command line arguments
+struct Args {
+    mode: Mode,
+    /// The configuration file passed with `--init`
+    init: Option<String>,
+    /// `--var` definitions in the order they were passed
+    vars: Vec<(String, String)>,
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Args, String> {
+    let mut mode = None;
+    let mut set_mode = |new| match mode.replace(new) {
+        Some(_) => Err("expected only one script or expression".to_owned()),
+        None => Ok(()),
+    };
+    let mut init = None;
+    let mut vars = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => set_mode(Mode::Help)?,
+            "-e" | "--eval" => {
+                let expr = args.next().ok_or("expected expression after -e")?;
+                set_mode(Mode::Eval(expr))?;
+            }
+            "--var" => {
+                let var = args.next().ok_or("expected <name>=<expr> after --var")?;
+                let (name, expr) = var
+                    .split_once('=')
+                    .ok_or_else(|| format!("expected <name>=<expr>, got {:?}", var))?;
+                vars.push((name.trim().to_owned(), expr.to_owned()));
+            }
+            "--init" => init = Some(args.next().ok_or("expected file after --init")?),
+            option if option.starts_with('-') => return Err(format!("unknown option {}", option)),
+            path => set_mode(Mode::Script(path.to_owned()))?,
+        }
+    }
+    let mode = mode.unwrap_or(if stdin().is_terminal() {
+        Mode::Repl
+    } else {
+        Mode::Stdin
+    });
+    Ok(Args { mode, init, vars })
+}
+
+/// Applies the configuration and the variables from the `args` to the `session`
+fn init_session(session: &mut Session, args: &Args) -> Result<(), String> {
+    let config = match &args.init {
+        Some(path) => Some(std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?),
+        // the file in the home directory is skipped when it can't be read
+        None if cfg!(feature = "config") => {
+            home_file(CONFIG_FILE).and_then(|path| std::fs::read_to_string(path).ok())
+        }
+        None => None,
+    };
+    if let Some(config) = config {
+        session.load_config(&config)?;
+    }
+    for (name, expr) in &args.vars {
+        let value = evaluator::eval_str_with_vars_and_ctx(expr, &mut session.vars, &session.ctx)
+            .map_err(|e| format!("invalid value of {}: {}", name, e))?;
+        session.vars.insert(name.clone(), value);
+    }
+    Ok(())
+}
+
+#[cfg_attr(tarpaulin, skip)]
+fn main() -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let mut session = Session::new();
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("Error: {}\n\n{}", e, USAGE);
+            return Ok(ExitCode::from(2));
+        }
+    };
+    if let Mode::Help = args.mode {
+        println!("{}", USAGE);
+        return Ok(ExitCode::SUCCESS);
+    }
+    if let Err(e) = init_session(&mut session, &args) {
+        eprintln!("Error: {}", e);
+        return Ok(ExitCode::from(2));
+    }
+    let input = match args.mode {
+        Mode::Help => unreachable!("help is handled before the session is initialized"),
+        Mode::Repl => {
+            repl(&mut session)?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        Mode::Stdin => {
+            let mut input = String::new();
+            stdin().read_to_string(&mut input)?;
+            input
+        }
+        Mode::Script(path) => std::fs::read_to_string(path)?,
+        Mode::Eval(expr) => expr,
+    };
+    if session.eval_batch(&input) {
+        Ok(ExitCode::SUCCESS)
+    } else {
+        Ok(ExitCode::FAILURE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eval_batch_commands() {
+        let input_expected = &[
+            (":set precision 3\n1 + 1", true),
+            ("1 + 1\n:time on", true),
+            (":quit\n1 +", true),
+            (":set precision x\n1 + 1", false),
+            (":set nope", false),
+            (":nope", false),
+            (":del x", false),
+            (":rpn 1 +", false),
+            (":deffn f(a) = a +", false),
+            (":defop ~ 1 left = sin", false),
+        ];
+        for (input, expected) in input_expected {
+            let mut session = Session::new();
+            assert_eq!(session.eval_batch(input), *expected, "input was {}", input);
+        }
+    }
+
+    #[test]
+    fn test_command_parse_error_span() {
+        use rusty_yard::tokenizer::Span;
+
+        let mut session = Session::new();
+        match session.command("rpn 1 + )") {
+            Err(Failure::Parse(errors)) => assert_eq!(errors[0].span, Span { start: 8, end: 9 }),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[cfg(feature = "config")]
+    #[test]
+    fn test_load_config() {
+        let mut session = Session::new();
+        let config = "
+angle = \"deg\"
+precision = 3
+
+[variables]
+g = 9.81
+half_g = \"g / 2\"
+
+[functions]
+sq = \"(x) = x ^ 2\"
+";
+        session.load_config(config).unwrap();
+        let result = session.eval("sq(half_g) + sin(90)").unwrap();
+        assert!((result - 25.059_025).abs() < 1e-9, "result was {}", result);
+        let input_expected = &[
+            ("[variables]\nx = \"1 +\"", "[variables] x: "),
+            ("[units]\nm = 1", "[units] m: unknown section [units]"),
+            ("angle = \"grad\"", "angle: expected deg or rad"),
+            ("angle = ", "line 1"),
+        ];
+        for (input, expected) in input_expected {
+            let result = session.load_config(input).unwrap_err();
+            assert!(
+                result.contains(expected),
+                "input was {}, error {}",
+                input,
+                result
+            );
+        }
+    }
+
+    #[test]
+    fn test_set_angle() {
+        let mut session = Session::new();
+        session.set(Some("angle"), Some("deg")).unwrap();
+        let input_expected = &[
+            ("sin(90)", 1.0),
+            ("cos(180)", -1.0),
+            ("cot(45)", 1.0),
+            ("asin(1)", 90.0),
+            ("atan2(1, 1)", 45.0),
+            ("sqrt(16)", 4.0),
+        ];
+        for (input, expected) in input_expected {
+            let result = session.eval(input).unwrap();
+            assert!((result - expected).abs() < 1e-9, "input was {}", input);
+        }
+        session.set(Some("angle"), Some("rad")).unwrap();
+        assert_eq!(
+            session.eval("asin(1)").ok(),
+            Some(std::f64::consts::FRAC_PI_2)
+        );
+        assert_eq!(
+            session.set(Some("angle"), Some("grad")),
+            Err("expected deg or rad".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_define_op() {
+        let mut session = Session::new();
+        session.define_fn("avg(a, b) = (a + b) / 2").unwrap();
+        session.define_fn("twice(a) = 2 * a").unwrap();
+        let definitions = &["** 5 right = ^", "~ 1 left = max", "<> 2 left = avg"];
+        for definition in definitions {
+            assert_eq!(
+                session.define_op(definition),
+                Ok(()),
+                "input was {}",
+                definition
+            );
+        }
+        let input_expected = &[
+            ("2 ** 3 ** 2", 512.0),
+            ("1 ~ 5 ~ 3", 5.0),
+            ("1 + 2 ~ 4", 4.0),
+            ("2 <> 4 * 2", 5.0),
+        ];
+        for (input, expected) in input_expected {
+            assert_eq!(
+                session.eval(input).ok(),
+                Some(*expected),
+                "input was {}",
+                input
+            );
+        }
+
+        let input_expected = &[
+            (
+                "@ 1 left = sin",
+                "sin is not a function of 2 arguments, it takes 1",
+            ),
+            (
+                "@ 1 left = twice",
+                "twice is not a function of 2 arguments, it takes 1",
+            ),
+            (
+                "@ 1 left = nope",
+                "nope is not a binary operator or a function of 2 arguments",
+            ),
+        ];
+        for (input, expected) in input_expected {
+            assert_eq!(
+                session.define_op(input),
+                Err((*expected).to_owned()),
+                "input was {}",
+                input
+            );
+        }
+    }
+}
@@ -0,0 +1,695 @@
+//! Provides [`CtxBuilder`](CtxBuilder) that builds the [`Ctx`](crate::Ctx) and checks that it is consistent.
+//!
+//! The fields of [`Ctx`](crate::Ctx) can be changed freely, so nothing stops the context from having
+//! two operators with the same token, or a function that the tokenizer splits in two.
+//! Such contexts don't fail, they silently produce the parses no one expects.
+//! [`CtxBuilder::build`](CtxBuilder::build) and [`Ctx::validate`](crate::Ctx::validate) report them as [`CtxError`](CtxError).
+//!
+//! # Example
+//!
+//! ```
+//! use rusty_yard::builder::{CtxBuilder, CtxError, ItemKind};
+//! use rusty_yard::constants::Const;
+//! use rusty_yard::functions::Func;
+//!
+//! let ctx = CtxBuilder::with_defaults()
+//!     .func(Func::new("double", Some(1), |args| args[0] * 2.0))
+//!     .build()
+//!     .unwrap();
+//! assert!(ctx.func("double").is_some());
+//!
+//! let error = CtxBuilder::with_defaults()
+//!     .constant(Const { token: "max".to_owned(), value: 1.0 })
+//!     .build()
+//!     .unwrap_err();
+//! assert_eq!(error, CtxError::FunctionConstantClash { token: "max".to_owned() });
+//! ```
+//!
+//! The contexts can be composed with [`Ctx::merge`](crate::Ctx::merge) and the `extend_*` methods of the [`Ctx`](crate::Ctx),
+//! they report the items with the same token as [`CtxConflict`](CtxConflict) instead of shadowing one of them.
+use std::fmt::{self, Display, Formatter};
+
+use thiserror::Error;
+
+use crate::constants::Const;
+use crate::functions::Func;
+use crate::macros::{default::default_macros, Macro};
+use crate::operators::{BiOp, UOp};
+use crate::tokenizer::{id_candidate, strict_id_candidate, TokenPass};
+use crate::Ctx;
+
+/// The kind of the item of the context, used by [`CtxError`](CtxError)
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ItemKind {
+    /// [`BiOp`](crate::operators::BiOp)
+    BinaryOperator,
+    /// [`UOp`](crate::operators::UOp)
+    UnaryOperator,
+    /// [`UOp`](crate::operators::UOp) that is [`postfix`](crate::operators::UOp::postfix)
+    PostfixOperator,
+    /// [`Func`](crate::functions::Func)
+    Function,
+    /// [`Const`](crate::constants::Const)
+    Constant,
+}
+
+impl Display for ItemKind {
+    #[cfg_attr(tarpaulin, skip)]
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ItemKind::BinaryOperator => "binary operator",
+            ItemKind::UnaryOperator => "unary operator",
+            ItemKind::PostfixOperator => "postfix operator",
+            ItemKind::Function => "function",
+            ItemKind::Constant => "constant",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Represents the inconsistency of the context
+#[derive(Error, Debug, PartialEq, Eq, Clone)]
+pub enum CtxError {
+    /// The item has an empty token
+    #[error("The {kind} has an empty token")]
+    EmptyToken {
+        /// The kind of the item
+        kind: ItemKind,
+    },
+
+    /// Two items of the same kind have the same token, only the first one is ever used
+    #[error("The {kind} {token:?} is defined more than once")]
+    DuplicateToken {
+        /// The kind of the items
+        kind: ItemKind,
+        /// The token of the items
+        token: String,
+    },
+
+    /// The function and the constant have the same token, the constant is never used
+    #[error("{token:?} is both a function and a constant")]
+    FunctionConstantClash {
+        /// The token of the function and the constant
+        token: String,
+    },
+
+    /// The token can't be produced by the tokenizer
+    ///
+    /// The operators can't contain whitespace, parens and commas, or start with a digit or a quote,
+    /// the functions and the constants have to be [identifiers](crate::tokenizer::match_id).
+    #[error("The {kind} {token:?} can't be tokenized")]
+    InvalidToken {
+        /// The kind of the item
+        kind: ItemKind,
+        /// The token of the item
+        token: String,
+    },
+
+    /// The token of the function or the constant contains an operator,
+    /// so the tokenizer splits it on the operator
+    #[error("The {kind} {token:?} contains the operator {operator:?}")]
+    ContainsOperator {
+        /// The kind of the item
+        kind: ItemKind,
+        /// The token of the item
+        token: String,
+        /// The token of the operator
+        operator: String,
+    },
+
+    /// The unary operator starts with the binary operator,
+    /// the tokenizer matches the binary operators first, so the unary one is never matched
+    #[error("The unary operator {unary:?} is shadowed by the binary operator {binary:?}")]
+    ShadowedUnaryOperator {
+        /// The token of the unary operator
+        unary: String,
+        /// The token of the binary operator
+        binary: String,
+    },
+
+    /// The [alias](crate::Ctx::aliases) stands for the token that is not the token of any item of the context
+    #[error("The alias {alias:?} stands for {token:?} that is not in the context")]
+    UnknownAlias {
+        /// The alias
+        alias: String,
+        /// The token the alias stands for
+        token: String,
+    },
+
+    /// The [alias](crate::Ctx::aliases) is the token of the item or of the other alias, so it is never used
+    #[error("The alias {alias:?} is already used by the context")]
+    AliasClash {
+        /// The alias
+        alias: String,
+    },
+}
+
+/// Represents the item that has the same token as the item of the context it is added to,
+/// see [`Ctx::merge`](crate::Ctx::merge)
+///
+/// The functions and the constants share the tokens, the operators of each kind have their own.
+#[derive(Error, Debug, PartialEq, Eq, Clone)]
+#[error("The {kind} {token:?} conflicts with the item of the context")]
+pub struct CtxConflict {
+    /// The kind of the added item
+    pub kind: ItemKind,
+    /// The token of the item
+    pub token: String,
+}
+
+/// Builds the [`Ctx`](crate::Ctx) item by item and validates it in [`build`](CtxBuilder::build).
+#[derive(Debug)]
+pub struct CtxBuilder {
+    ctx: Ctx,
+}
+
+impl CtxBuilder {
+    /// Creates the builder of the empty context.
+    pub fn new() -> Self {
+        CtxBuilder { ctx: Ctx::empty() }
+    }
+
+    /// Creates the builder that starts with the items of [`Ctx::default`](crate::Ctx::default).
+    pub fn with_defaults() -> Self {
+        CtxBuilder {
+            ctx: Ctx::default(),
+        }
+    }
+
+    /// Adds the binary operator.
+    pub fn bi_op(mut self, op: BiOp) -> Self {
+        self.ctx.bi_ops.push(op);
+        self
+    }
+
+    /// Adds the unary operator.
+    pub fn u_op(mut self, op: UOp) -> Self {
+        self.ctx.u_ops.push(op);
+        self
+    }
+
+    /// Adds the function.
+    pub fn func(mut self, func: Func) -> Self {
+        self.ctx.fns.push(func);
+        self
+    }
+
+    /// Adds the named constant.
+    pub fn constant(mut self, constant: Const) -> Self {
+        self.ctx.consts.push(constant);
+        self
+    }
+
+    /// Adds the macro.
+    pub fn macro_(mut self, m: Box<dyn Macro>) -> Self {
+        self.ctx.macros.push(m);
+        self
+    }
+
+    /// Adds the [`default_macros`](crate::macros::default::default_macros).
+    pub fn default_macros(mut self) -> Self {
+        self.ctx.macros.extend(default_macros());
+        self
+    }
+
+    /// Adds the `alias` of the `token`, see [`Ctx::aliases`](crate::Ctx::aliases).
+    pub fn alias(mut self, alias: &str, token: &str) -> Self {
+        self.ctx.aliases.push((alias.to_owned(), token.to_owned()));
+        self
+    }
+
+    /// Adds the token pass.
+    pub fn pass(mut self, pass: Box<dyn TokenPass>) -> Self {
+        self.ctx.passes.push(pass);
+        self
+    }
+
+    /// Validates the context and returns it, see [`Ctx::validate`](crate::Ctx::validate).
+    pub fn build(self) -> Result<Ctx, CtxError> {
+        self.ctx.validate()?;
+        Ok(self.ctx)
+    }
+}
+
+impl Default for CtxBuilder {
+    /// Same as [`CtxBuilder::new`](CtxBuilder::new).
+    fn default() -> Self {
+        CtxBuilder::new()
+    }
+}
+
+/// The kinds and the tokens of the operators, the functions and the constants of the `ctx`
+pub(crate) fn items(ctx: &Ctx) -> Vec<(ItemKind, &str)> {
+    let bi_ops = ctx.bi_ops.iter().map(bi_op_item);
+    let u_ops = ctx.u_ops.iter().map(u_op_item);
+    let fns = ctx.fns.iter().map(func_item);
+    let consts = ctx.consts.iter().map(const_item);
+    bi_ops.chain(u_ops).chain(fns).chain(consts).collect()
+}
+
+pub(crate) fn bi_op_item(op: &BiOp) -> (ItemKind, &str) {
+    (ItemKind::BinaryOperator, &op.token)
+}
+
+pub(crate) fn u_op_item(op: &UOp) -> (ItemKind, &str) {
+    let kind = if op.postfix {
+        ItemKind::PostfixOperator
+    } else {
+        ItemKind::UnaryOperator
+    };
+    (kind, &op.token)
+}
+
+pub(crate) fn func_item(func: &Func) -> (ItemKind, &str) {
+    (ItemKind::Function, &func.token)
+}
+
+pub(crate) fn const_item(constant: &Const) -> (ItemKind, &str) {
+    (ItemKind::Constant, &constant.token)
+}
+
+/// Checks that the `added` items don't have the same tokens as the `existing` ones, or as each other
+pub(crate) fn check_conflicts(
+    existing: &[(ItemKind, &str)],
+    added: &[(ItemKind, &str)],
+) -> Result<(), CtxConflict> {
+    let is_identifier = |kind| matches!(kind, ItemKind::Function | ItemKind::Constant);
+    for (i, (kind, token)) in added.iter().enumerate() {
+        let conflicts = existing
+            .iter()
+            .chain(&added[..i])
+            .any(|(other_kind, other)| {
+                other == token
+                    && (other_kind == kind || is_identifier(*kind) && is_identifier(*other_kind))
+            });
+        if conflicts {
+            return Err(CtxConflict {
+                kind: *kind,
+                token: token.to_string(),
+            });
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn validate(ctx: &Ctx) -> Result<(), CtxError> {
+    let bi_ops: Vec<_> = ctx.bi_ops.iter().map(|op| op.token.as_str()).collect();
+    let unary_tokens = |postfix: bool| -> Vec<_> {
+        let ops = ctx.u_ops.iter().filter(|op| op.postfix == postfix);
+        ops.map(|op| op.token.as_str()).collect()
+    };
+    let u_ops = unary_tokens(false);
+    let postfix_ops = unary_tokens(true);
+    let fns: Vec<_> = ctx.fns.iter().map(|f| f.token.as_str()).collect();
+    let consts: Vec<_> = ctx.consts.iter().map(|c| c.token.as_str()).collect();
+    let operators = || bi_ops.iter().chain(&u_ops).chain(&postfix_ops);
+
+    for (kind, tokens) in &[
+        (ItemKind::BinaryOperator, &bi_ops),
+        (ItemKind::UnaryOperator, &u_ops),
+        (ItemKind::PostfixOperator, &postfix_ops),
+    ] {
+        check_tokens(*kind, tokens, is_valid_operator)?;
+    }
+    for (kind, tokens) in &[(ItemKind::Function, &fns), (ItemKind::Constant, &consts)] {
+        check_tokens(*kind, tokens, |token| {
+            let candidate = if ctx.strict_ids {
+                strict_id_candidate(token)
+            } else {
+                id_candidate(token)
+            };
+            candidate == Some(token)
+        })?;
+        for token in tokens.iter() {
+            if let Some(operator) = operators().find(|op| token.contains(*op)) {
+                return Err(CtxError::ContainsOperator {
+                    kind: *kind,
+                    token: token.to_string(),
+                    operator: operator.to_string(),
+                });
+            }
+        }
+    }
+    if let Some(token) = fns.iter().find(|f| consts.contains(f)) {
+        return Err(CtxError::FunctionConstantClash {
+            token: token.to_string(),
+        });
+    }
+    for unary in u_ops.iter().chain(&postfix_ops) {
+        let shadowing = bi_ops
+            .iter()
+            .find(|binary| binary.len() < unary.len() && unary.starts_with(*binary));
+        if let Some(binary) = shadowing {
+            return Err(CtxError::ShadowedUnaryOperator {
+                unary: unary.to_string(),
+                binary: binary.to_string(),
+            });
+        }
+    }
+    validate_aliases(ctx)
+}
+
+fn validate_aliases(ctx: &Ctx) -> Result<(), CtxError> {
+    let items = items(ctx);
+    for (i, (alias, token)) in ctx.aliases.iter().enumerate() {
+        let clashes = items.iter().any(|(_, other)| other == alias)
+            || ctx.aliases[..i].iter().any(|(other, _)| other == alias);
+        if clashes {
+            return Err(CtxError::AliasClash {
+                alias: alias.clone(),
+            });
+        }
+        let (kind, _) = items
+            .iter()
+            .find(|(_, other)| other == token)
+            .ok_or_else(|| CtxError::UnknownAlias {
+                alias: alias.clone(),
+                token: token.clone(),
+            })?;
+        let is_operator = !matches!(kind, ItemKind::Function | ItemKind::Constant);
+        let is_valid = if is_operator {
+            is_valid_operator(alias)
+        } else {
+            id_candidate(alias) == Some(alias.as_str())
+        };
+        if alias.is_empty() || !is_valid {
+            return Err(CtxError::InvalidToken {
+                kind: *kind,
+                token: alias.clone(),
+            });
+        }
+    }
+    Ok(())
+}
+
+fn check_tokens(
+    kind: ItemKind,
+    tokens: &[&str],
+    is_valid: impl Fn(&str) -> bool,
+) -> Result<(), CtxError> {
+    for (i, token) in tokens.iter().enumerate() {
+        if token.is_empty() {
+            return Err(CtxError::EmptyToken { kind });
+        }
+        if tokens[..i].contains(token) {
+            return Err(CtxError::DuplicateToken {
+                kind,
+                token: token.to_string(),
+            });
+        }
+        if !is_valid(token) {
+            return Err(CtxError::InvalidToken {
+                kind,
+                token: token.to_string(),
+            });
+        }
+    }
+    Ok(())
+}
+
+fn is_valid_operator(token: &str) -> bool {
+    const DISALLOWED_CHARS: &[char] = &['(', ')', ','];
+    !token.starts_with(|ch: char| ch.is_ascii_digit() || ch == '`' || ch == '[')
+        && !token
+            .chars()
+            .any(|ch| ch.is_whitespace() || DISALLOWED_CHARS.contains(&ch))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operators::{binary, unary};
+
+    fn func(token: &str) -> Func {
+        Func::new(token, Some(1), |args| args[0])
+    }
+
+    fn constant(token: &str) -> Const {
+        Const {
+            token: token.to_owned(),
+            value: 1.0,
+        }
+    }
+
+    fn bi_op(token: &str) -> BiOp {
+        BiOp {
+            token: token.to_owned(),
+            ..binary::PLUS.clone()
+        }
+    }
+
+    fn u_op(token: &str) -> UOp {
+        UOp {
+            token: token.to_owned(),
+            ..unary::NEGATE.clone()
+        }
+    }
+
+    #[test]
+    fn test_default_ctx_is_valid() {
+        assert!(Ctx::default().validate().is_ok());
+        assert!(Ctx::default_with_macros().validate().is_ok());
+        let aliased = Ctx {
+            aliases: crate::operators::default_aliases(),
+            ..Ctx::default()
+        };
+        assert!(aliased.validate().is_ok());
+        assert!(CtxBuilder::new().build().is_ok());
+    }
+
+    #[test]
+    fn test_validate_strict_ids() {
+        let mut ctx = Ctx {
+            strict_ids: true,
+            ..Ctx::default()
+        };
+        assert!(ctx.validate().is_ok());
+        ctx.consts.push(constant("π"));
+        assert_eq!(
+            ctx.validate(),
+            Err(CtxError::InvalidToken {
+                kind: ItemKind::Constant,
+                token: "π".to_owned(),
+            })
+        );
+        ctx.strict_ids = false;
+        assert!(ctx.validate().is_ok());
+    }
+
+    #[test]
+    fn test_build() {
+        let input_expected = vec![
+            (CtxBuilder::with_defaults().func(func("double")), Ok(())),
+            (
+                CtxBuilder::new().bi_op(bi_op("mod")).u_op(u_op("-")),
+                Ok(()),
+            ),
+            (
+                CtxBuilder::with_defaults().bi_op(bi_op("")),
+                Err(CtxError::EmptyToken {
+                    kind: ItemKind::BinaryOperator,
+                }),
+            ),
+            (
+                CtxBuilder::with_defaults().constant(constant("")),
+                Err(CtxError::EmptyToken {
+                    kind: ItemKind::Constant,
+                }),
+            ),
+            (
+                CtxBuilder::with_defaults().bi_op(bi_op("+")),
+                Err(CtxError::DuplicateToken {
+                    kind: ItemKind::BinaryOperator,
+                    token: "+".to_owned(),
+                }),
+            ),
+            (
+                CtxBuilder::with_defaults().func(func("max")),
+                Err(CtxError::DuplicateToken {
+                    kind: ItemKind::Function,
+                    token: "max".to_owned(),
+                }),
+            ),
+            (
+                CtxBuilder::with_defaults().constant(constant("sqrt")),
+                Err(CtxError::FunctionConstantClash {
+                    token: "sqrt".to_owned(),
+                }),
+            ),
+            (
+                CtxBuilder::new().bi_op(bi_op("a b")),
+                Err(CtxError::InvalidToken {
+                    kind: ItemKind::BinaryOperator,
+                    token: "a b".to_owned(),
+                }),
+            ),
+            (
+                CtxBuilder::new().u_op(u_op("2x")),
+                Err(CtxError::InvalidToken {
+                    kind: ItemKind::UnaryOperator,
+                    token: "2x".to_owned(),
+                }),
+            ),
+            (
+                CtxBuilder::new().func(func("f(x)")),
+                Err(CtxError::InvalidToken {
+                    kind: ItemKind::Function,
+                    token: "f(x)".to_owned(),
+                }),
+            ),
+            (
+                CtxBuilder::new().bi_op(bi_op("mod")).func(func("model")),
+                Err(CtxError::ContainsOperator {
+                    kind: ItemKind::Function,
+                    token: "model".to_owned(),
+                    operator: "mod".to_owned(),
+                }),
+            ),
+            (
+                CtxBuilder::with_defaults().constant(constant("e-1")),
+                Err(CtxError::ContainsOperator {
+                    kind: ItemKind::Constant,
+                    token: "e-1".to_owned(),
+                    operator: "-".to_owned(),
+                }),
+            ),
+            (
+                CtxBuilder::with_defaults().u_op(UOp::postfix("%", |a| a)),
+                Err(CtxError::DuplicateToken {
+                    kind: ItemKind::PostfixOperator,
+                    token: "%".to_owned(),
+                }),
+            ),
+            (
+                CtxBuilder::with_defaults().u_op(UOp::postfix("!", |a| a)),
+                Ok(()),
+            ),
+            (
+                CtxBuilder::with_defaults().u_op(UOp::postfix("*", |a| a)),
+                Ok(()),
+            ),
+            (
+                CtxBuilder::with_defaults().u_op(u_op("--")),
+                Err(CtxError::ShadowedUnaryOperator {
+                    unary: "--".to_owned(),
+                    binary: "-".to_owned(),
+                }),
+            ),
+            (
+                CtxBuilder::with_defaults()
+                    .alias("×", "*")
+                    .alias("mod", "+")
+                    .alias("maximum", "max"),
+                Ok(()),
+            ),
+            (
+                CtxBuilder::with_defaults().alias("×", "mul"),
+                Err(CtxError::UnknownAlias {
+                    alias: "×".to_owned(),
+                    token: "mul".to_owned(),
+                }),
+            ),
+            (
+                CtxBuilder::with_defaults().alias("sqrt", "max"),
+                Err(CtxError::AliasClash {
+                    alias: "sqrt".to_owned(),
+                }),
+            ),
+            (
+                CtxBuilder::with_defaults().alias("×", "*").alias("×", "/"),
+                Err(CtxError::AliasClash {
+                    alias: "×".to_owned(),
+                }),
+            ),
+            (
+                CtxBuilder::with_defaults().alias("√", "sqrt"),
+                Err(CtxError::InvalidToken {
+                    kind: ItemKind::Function,
+                    token: "√".to_owned(),
+                }),
+            ),
+        ];
+        for (i, (builder, expected)) in input_expected.into_iter().enumerate() {
+            let result = builder.build().map(|_| ());
+            assert_eq!(result, expected, "input was #{}", i);
+        }
+    }
+
+    #[test]
+    fn test_merge() {
+        let pack = |builder: CtxBuilder| builder.build().unwrap();
+        let input_expected = vec![
+            (pack(CtxBuilder::new().func(func("double"))), Ok(())),
+            (
+                pack(CtxBuilder::new().bi_op(bi_op("mod")).u_op(u_op("~"))),
+                Ok(()),
+            ),
+            (
+                pack(CtxBuilder::new().u_op(UOp::postfix("-", |a| a))),
+                Ok(()),
+            ),
+            (
+                pack(CtxBuilder::new().bi_op(bi_op("*"))),
+                Err(CtxConflict {
+                    kind: ItemKind::BinaryOperator,
+                    token: "*".to_owned(),
+                }),
+            ),
+            (
+                pack(CtxBuilder::new().u_op(u_op("-"))),
+                Err(CtxConflict {
+                    kind: ItemKind::UnaryOperator,
+                    token: "-".to_owned(),
+                }),
+            ),
+            (
+                pack(CtxBuilder::new().func(func("sin"))),
+                Err(CtxConflict {
+                    kind: ItemKind::Function,
+                    token: "sin".to_owned(),
+                }),
+            ),
+            (
+                pack(CtxBuilder::new().constant(constant("max"))),
+                Err(CtxConflict {
+                    kind: ItemKind::Constant,
+                    token: "max".to_owned(),
+                }),
+            ),
+        ];
+        for (i, (other, expected)) in input_expected.into_iter().enumerate() {
+            let result = Ctx::default().merge(other).map(|_| ());
+            assert_eq!(result, expected, "input was #{}", i);
+        }
+        let merged = Ctx::default()
+            .merge(CtxBuilder::new().default_macros().build().unwrap())
+            .unwrap();
+        assert_eq!(merged.macros.len(), default_macros().len());
+        assert!(merged.validate().is_ok());
+    }
+
+    #[test]
+    fn test_extend() {
+        let mut ctx = Ctx::empty();
+        assert_eq!(ctx.extend_fns(vec![func("f"), func("g")]), Ok(()));
+        // the conflict within the added items
+        assert_eq!(
+            ctx.extend_consts(vec![constant("c"), constant("c")]),
+            Err(CtxConflict {
+                kind: ItemKind::Constant,
+                token: "c".to_owned(),
+            })
+        );
+        // nothing is added on the conflict
+        assert_eq!(
+            ctx.extend_consts(vec![constant("c"), constant("f")]),
+            Err(CtxConflict {
+                kind: ItemKind::Constant,
+                token: "f".to_owned(),
+            })
+        );
+        assert!(ctx.consts.is_empty());
+        assert_eq!(ctx.extend_bi_ops(vec![bi_op("f")]), Ok(()));
+        assert_eq!(ctx.extend_u_ops(vec![u_op("f")]), Ok(()));
+    }
+}
@@ -0,0 +1,111 @@
+//! The module that deals with named constants.
+//!
+//! The main type in this module is [`Const`](Const).
+//! Constants are resolved by the [`parser`](crate::parser), so the identifier of the constant
+//! is replaced with its value and never reaches the variables.
+//! The constants take precedence over the variables with the same name,
+//! use the [quoted identifier](crate::tokenizer::match_quoted_id) (`` `pi` ``) to read such variable.
+//!
+//! # Example
+//! ```
+//! # use std::collections::HashMap;
+//! use rusty_yard::{Ctx, constants::Const, evaluator::eval_str_with_vars_and_ctx};
+//!
+//! let mut ctx = Ctx::default();
+//! ctx.consts.push(Const {
+//!     token: "g".to_owned(),
+//!     value: 9.81,
+//! });
+//! let mut vars = HashMap::new();
+//! assert_eq!(eval_str_with_vars_and_ctx("g * 2", &mut vars, &ctx), Ok(19.62));
+//! assert_eq!(eval_str_with_vars_and_ctx("tau / pi", &mut vars, &ctx), Ok(2.0));
+//! ```
+#![deny(missing_docs)]
+
+use std::f64::consts;
+
+use lazy_static::lazy_static;
+
+/// Represents a named constant
+#[derive(Debug, Clone, PartialEq)]
+pub struct Const {
+    /// Identifier of the constant.
+    pub token: String,
+    /// The value the identifier is replaced with.
+    pub value: f64,
+}
+
+lazy_static! {
+    /// pi constant.
+    ///
+    /// # Implementation
+    ///
+    /// ```text
+    /// std::f64::consts::PI
+    /// ```
+    pub static ref CONST_PI: Const = Const {
+        token: "pi".to_owned(),
+        value: consts::PI,
+    };
+
+    /// e constant, the base of the natural logarithm.
+    ///
+    /// # Implementation
+    ///
+    /// ```text
+    /// std::f64::consts::E
+    /// ```
+    pub static ref CONST_E: Const = Const {
+        token: "e".to_owned(),
+        value: consts::E,
+    };
+
+    /// tau constant, the full circle in radians.
+    ///
+    /// # Implementation
+    ///
+    /// ```text
+    /// std::f64::consts::TAU
+    /// ```
+    pub static ref CONST_TAU: Const = Const {
+        token: "tau".to_owned(),
+        value: consts::TAU,
+    };
+
+    /// inf constant.
+    ///
+    /// # Implementation
+    ///
+    /// ```text
+    /// f64::INFINITY
+    /// ```
+    pub static ref CONST_INF: Const = Const {
+        token: "inf".to_owned(),
+        value: f64::INFINITY,
+    };
+
+    /// nan constant.
+    ///
+    /// # Implementation
+    ///
+    /// ```text
+    /// f64::NAN
+    /// ```
+    pub static ref CONST_NAN: Const = Const {
+        token: "nan".to_owned(),
+        value: f64::NAN,
+    };
+}
+
+/// Get the default constants list.
+///
+/// This includes all constants from [`constants`](self) module.
+pub fn default_constants() -> Vec<Const> {
+    vec![
+        CONST_PI.clone(),
+        CONST_E.clone(),
+        CONST_TAU.clone(),
+        CONST_INF.clone(),
+        CONST_NAN.clone(),
+    ]
+}
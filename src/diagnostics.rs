@@ -0,0 +1,402 @@
+//! The reports of the parser errors for the humans, in the style of the compiler errors.
+//!
+//! The [`Diagnostic`](Diagnostic) is built from the [`SpannedError`](crate::parser::SpannedError)
+//! and the source it was parsed from. It has the [code](crate::parser::Error::code) of the error
+//! and the hints on how to fix it, and it is [rendered](Diagnostic::render) against the source,
+//! optionally with the ANSI colors.
+//!
+//! With the `codespan` feature the diagnostic is converted to the one of the
+//! [`codespan-reporting`](https://docs.rs/codespan-reporting) crate, see [`to_codespan`](Diagnostic::to_codespan).
+//!
+//! # Example
+//!
+//! ```
+//! use rusty_yard::diagnostics::Diagnostic;
+//! use rusty_yard::parser::parse_str_with_spans;
+//! use rusty_yard::Ctx;
+//!
+//! let ctx = Ctx::default();
+//! let source = "1 + max * 2";
+//! let error = parse_str_with_spans(source, &ctx).unwrap_err();
+//! let diagnostic = Diagnostic::new(&error, source, &ctx);
+//! assert_eq!(
+//!     diagnostic.render(source).to_string(),
+//!     "\
+//! error[E001]: Expected left paren after function id
+//!  --> 1:5
+//!   |
+//! 1 | 1 + max * 2
+//!   |     ^^^
+//!   = help: did you mean `max(…)`?
+//! "
+//! );
+//! ```
+use std::fmt::{self, Display, Formatter};
+
+use crate::functions::ArityRange;
+use crate::parser::{Error, SpannedError};
+use crate::tokenizer::{BadTokenReason, Span};
+use crate::Ctx;
+
+/// The report of the parser error.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    /// The [code](crate::parser::Error::code) of the error.
+    pub code: &'static str,
+    /// The message of the error.
+    pub message: String,
+    /// The span of the error in the source.
+    pub span: Span,
+    /// The suggestions on how to fix the error, can be empty.
+    pub hints: Vec<String>,
+}
+
+impl Diagnostic {
+    /// Creates the diagnostic of the `error` of the `source`, parsed with the `ctx`.
+    ///
+    /// The context is used to suggest the fixes, e.g. the parameters of the function.
+    pub fn new(error: &SpannedError, source: &str, ctx: &Ctx) -> Self {
+        let text = source.get(error.span.start..error.span.end).unwrap_or("");
+        Diagnostic {
+            code: error.error.code(),
+            message: error.error.to_string(),
+            span: error.span,
+            hints: hint(&error.error, text, ctx).into_iter().collect(),
+        }
+    }
+
+    /// Returns the renderer of the diagnostic against the `source`, without the colors.
+    ///
+    /// The `source` must be the one the diagnostic was created with.
+    pub fn render<'a>(&'a self, source: &'a str) -> Render<'a> {
+        Render {
+            diagnostic: self,
+            source,
+            colors: false,
+        }
+    }
+
+    /// Converts the diagnostic to the one of the `codespan-reporting` crate, with the source of the `file_id`.
+    #[cfg(feature = "codespan")]
+    pub fn to_codespan<FileId>(
+        &self,
+        file_id: FileId,
+    ) -> codespan_reporting::diagnostic::Diagnostic<FileId> {
+        use codespan_reporting::diagnostic::{Diagnostic, Label};
+
+        Diagnostic::error()
+            .with_code(self.code)
+            .with_message(&self.message)
+            .with_labels(vec![Label::primary(
+                file_id,
+                self.span.start..self.span.end,
+            )])
+            .with_notes(
+                self.hints
+                    .iter()
+                    .map(|hint| format!("help: {}", hint))
+                    .collect(),
+            )
+    }
+}
+
+/// Renders the [`Diagnostic`](Diagnostic) with its [`Display`](std::fmt::Display) implementation,
+/// created by [`Diagnostic::render`](Diagnostic::render).
+#[derive(Debug, Clone, Copy)]
+pub struct Render<'a> {
+    diagnostic: &'a Diagnostic,
+    source: &'a str,
+    colors: bool,
+}
+
+impl Render<'_> {
+    /// Sets whether the report uses the ANSI colors, e.g. when it is written to the terminal.
+    pub fn colored(mut self, colors: bool) -> Self {
+        self.colors = colors;
+        self
+    }
+}
+
+/// The ANSI escape codes of the report
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+impl Display for Render<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let style = |code| if self.colors { code } else { "" };
+        let (red, blue, bold, reset) = (style(RED), style(BLUE), style(BOLD), style(RESET));
+        let Location {
+            number,
+            line,
+            column,
+            width,
+        } = locate(self.source, self.diagnostic.span);
+        let gutter = " ".repeat(number.to_string().len());
+        writeln!(
+            f,
+            "{}error[{}]{}{}: {}{}",
+            red, self.diagnostic.code, reset, bold, self.diagnostic.message, reset
+        )?;
+        writeln!(
+            f,
+            "{}{}-->{} {}:{}",
+            gutter,
+            blue,
+            reset,
+            number,
+            column + 1
+        )?;
+        writeln!(f, "{} {}|{}", gutter, blue, reset)?;
+        writeln!(f, "{}{} |{} {}", blue, number, reset, line)?;
+        writeln!(
+            f,
+            "{} {}|{} {}{}{}{}",
+            gutter,
+            blue,
+            reset,
+            " ".repeat(column),
+            red,
+            "^".repeat(width),
+            reset
+        )?;
+        for hint in &self.diagnostic.hints {
+            writeln!(
+                f,
+                "{} {}={} {}help{}: {}",
+                gutter, blue, reset, bold, reset, hint
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The suggestion on how to fix the `error`, `text` is the source of its span
+fn hint(error: &Error, text: &str, ctx: &Ctx) -> Option<String> {
+    let signature = |id: &str| {
+        let func = ctx.fns.iter().find(|func| func.token == id)?;
+        if func.params.is_empty() {
+            return None;
+        }
+        let params: Vec<_> = func
+            .params
+            .iter()
+            .map(|param| match param.default {
+                Some(default) => format!("{} = {}", param.name, default),
+                None => param.name.clone(),
+            })
+            .collect();
+        Some(format!("{}({})", id, params.join(", ")))
+    };
+    let hint = match error {
+        Error::NoLeftParenAfterFnId => format!(
+            "did you mean `{}`?",
+            signature(text).unwrap_or_else(|| format!("{}(…)", text))
+        ),
+        Error::BadToken(_, BadTokenReason::MalformedNumber) => {
+            "the number has at most one dot, and the digits after the exponent, e.g. `1.5e-3`"
+                .to_owned()
+        }
+        Error::BadToken(_, BadTokenReason::UnterminatedQuote) => {
+            "close the quoted identifier with the matching quote".to_owned()
+        }
+        Error::BadToken(_, BadTokenReason::UnterminatedComment) => {
+            "close the comment with `*/`".to_owned()
+        }
+        Error::BadToken(_, BadTokenReason::UnterminatedString) => {
+            "close the string with `\"`".to_owned()
+        }
+        Error::BadToken(token, BadTokenReason::InvalidCharacter) => {
+            let similar = match token.as_str() {
+                "×" | "·" => "*",
+                "÷" => "/",
+                "−" | "–" => "-",
+                _ => "",
+            };
+            if !similar.is_empty() && ctx.bi_ops.iter().any(|op| op.token == similar) {
+                format!("did you mean `{}`?", similar)
+            } else {
+                "the character is not a part of a number, an identifier or an operator of the context"
+                    .to_owned()
+            }
+        }
+        Error::OperatorAtTheEnd => "add the operand after the operator".to_owned(),
+        Error::MismatchedLeftParen => "the paren is never closed, add `)`".to_owned(),
+        Error::MismatchedRightParen => "the paren closes nothing, remove it".to_owned(),
+        Error::ArityMismatch { id, expected, .. } => match signature(id) {
+            Some(signature) => format!("the function is declared as `{}`", signature),
+            None if *expected == ArityRange::exact(1) => format!("`{}` takes 1 argument", id),
+            None => format!("`{}` takes {} arguments", id, expected),
+        },
+        Error::ExpectedOperator => "add the operator before the operand, e.g. `*`".to_owned(),
+        Error::ExpectedExpression => {
+            "add the operand before the operator, or remove the operator".to_owned()
+        }
+        Error::CommaOutsideFn => {
+            "the commas separate the arguments of the function calls".to_owned()
+        }
+        Error::UnknownArgument(_) => {
+            "the name must be one of the parameters of the function".to_owned()
+        }
+        Error::DuplicateArgument(_) => "remove one of the arguments".to_owned(),
+        Error::MissingArgument(name) => format!("add the argument `{} = …`", name),
+        Error::PositionalAfterNamed => {
+            "pass the positional arguments before the named ones".to_owned()
+        }
+        // the message has the suggestion
+        Error::UnknownFunction(_, Some(_)) => return None,
+        Error::UnknownFunction(_, None) => "the function is not defined in the context".to_owned(),
+        Error::ExpectedVariable(_) => {
+            "the argument must be the name of the variable, e.g. `i`".to_owned()
+        }
+        Error::TooManyTokens(_) | Error::TooDeep(_) => {
+            "the limits are set by `Ctx::limits`".to_owned()
+        }
+    };
+    Some(hint)
+}
+
+/// The line of the source that contains the span
+pub(crate) struct Location<'a> {
+    /// The number of the line, starting from 1
+    pub(crate) number: usize,
+    /// The text of the line, without the line break
+    pub(crate) line: &'a str,
+    /// The number of the characters of the line before the span
+    pub(crate) column: usize,
+    /// The number of the characters of the span on the line, at least 1
+    pub(crate) width: usize,
+}
+
+/// Finds the line of the `source` that contains the `span`
+pub(crate) fn locate(source: &str, span: Span) -> Location<'_> {
+    let start = span.start.min(source.len());
+    let end = span.end.clamp(start, source.len());
+    let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = source[start..]
+        .find('\n')
+        .map_or(source.len(), |i| start + i);
+    Location {
+        number: source[..start].matches('\n').count() + 1,
+        line: source[line_start..line_end].trim_end_matches('\r'),
+        column: source[line_start..start].chars().count(),
+        width: source[start..end.min(line_end)].chars().count().max(1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::functions::{Func, Param};
+    use crate::parser::parse_str_with_spans;
+
+    #[test]
+    fn test_diagnostic_hints() {
+        let mut ctx = Ctx::default();
+        ctx.fns.push(
+            Func::new("clamp", Some(3), |args| args[0].max(args[1]).min(args[2])).with_params(
+                vec![
+                    Param::required("x"),
+                    Param::optional("lo", 0.0),
+                    Param::optional("hi", 1.0),
+                ],
+            ),
+        );
+        let input_expected = &[
+            ("2 * sin", "E001", "did you mean `sin(…)`?"),
+            (
+                "2 * clamp",
+                "E001",
+                "did you mean `clamp(x, lo = 0, hi = 1)`?",
+            ),
+            ("2 × 3", "E002", "did you mean `*`?"),
+            (
+                "1.2.3 * 2",
+                "E002",
+                "the number has at most one dot, and the digits after the exponent, e.g. `1.5e-3`",
+            ),
+            (
+                "clamp(1, 2, 3, 4)",
+                "E006",
+                "the function is declared as `clamp(x, lo = 0, hi = 1)`",
+            ),
+            ("(1 + 2", "E004", "the paren is never closed, add `)`"),
+            (
+                "1 2",
+                "E007",
+                "add the operator before the operand, e.g. `*`",
+            ),
+            ("clamp(lo = 1)", "E012", "add the argument `x = …`"),
+        ];
+        for (input, code, hint) in input_expected {
+            let error = parse_str_with_spans(input, &ctx).unwrap_err();
+            let diagnostic = Diagnostic::new(&error, input, &ctx);
+            assert_eq!(diagnostic.code, *code, "input was {}", input);
+            assert_eq!(diagnostic.hints, vec![*hint], "input was {}", input);
+        }
+        // the function without the params is described by its arity
+        let error = parse_str_with_spans("max(1, 2, 3)", &ctx).unwrap_err();
+        assert_eq!(
+            Diagnostic::new(&error, "max(1, 2, 3)", &ctx).hints,
+            vec!["`max` takes 2 arguments"]
+        );
+        let error = parse_str_with_spans("sin(1, 2)", &ctx).unwrap_err();
+        assert_eq!(
+            Diagnostic::new(&error, "sin(1, 2)", &ctx).hints,
+            vec!["`sin` takes 1 argument"]
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let ctx = Ctx::default();
+        let source = "1 +\n(2 +\n  2 3)";
+        let error = parse_str_with_spans(source, &ctx).unwrap_err();
+        assert_eq!(
+            Diagnostic::new(&error, source, &ctx)
+                .render(source)
+                .to_string(),
+            "\
+error[E007]: Expected Operator, found expression
+ --> 3:5
+  |
+3 |   2 3)
+  |     ^
+  = help: add the operator before the operand, e.g. `*`
+"
+        );
+        let error = parse_str_with_spans("1)", &ctx).unwrap_err();
+        assert_eq!(
+            Diagnostic::new(&error, "1)", &ctx)
+                .render("1)")
+                .colored(true)
+                .to_string(),
+            "\
+\x1b[1;31merror[E005]\x1b[0m\x1b[1m: Mismatched right paren in the token stream\x1b[0m
+ \x1b[1;34m-->\x1b[0m 1:2
+  \x1b[1;34m|\x1b[0m
+\x1b[1;34m1 |\x1b[0m 1)
+  \x1b[1;34m|\x1b[0m  \x1b[1;31m^\x1b[0m
+  \x1b[1;34m=\x1b[0m \x1b[1mhelp\x1b[0m: the paren closes nothing, remove it
+"
+        );
+    }
+
+    #[cfg(feature = "codespan")]
+    #[test]
+    fn test_to_codespan() {
+        use codespan_reporting::diagnostic::{Label, Severity};
+
+        let ctx = Ctx::default();
+        let error = parse_str_with_spans("1 + * 2", &ctx).unwrap_err();
+        let diagnostic = Diagnostic::new(&error, "1 + * 2", &ctx).to_codespan(());
+        assert_eq!(diagnostic.severity, Severity::Error);
+        assert_eq!(diagnostic.code.as_deref(), Some("E008"));
+        assert_eq!(diagnostic.labels, vec![Label::primary((), 4..5)]);
+        assert_eq!(
+            diagnostic.notes,
+            vec!["help: add the operand before the operator, or remove the operator"]
+        );
+    }
+}
@@ -15,20 +15,42 @@
 //!
 
 #![deny(missing_docs)]
+use std::cell::Cell;
 use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
 use thiserror::Error;
 
-use super::parser::{self, parse, ParserToken};
-use super::tokenizer::tokenize;
+use super::functions::{self, ArityRange};
+use super::limits::Limits;
+use super::operators::binary::Associativity;
+use super::operators::unary::PercentMode;
+use super::operators::{BiOp, UOp};
+use super::parser::{self, parse, ParseState, ParserToken};
+use super::sample::Bound;
+use super::suggest::{closest, did_you_mean};
+use super::tokenizer::{
+    apply_passes, match_id, match_number, match_op, skip_whitespace, tokenize, Match,
+    TokenizeOptions, INDEX_OPERATOR,
+};
+use super::variables::{ReadOnly, VariableResolver};
 use super::Ctx;
 
 /// Represents the Error that can occur during the evaluation of the expression
 #[derive(Debug, Error, PartialEq)]
 pub enum Error {
-    /// Signifies that variable was not found in variable map
-    #[error("Variable not found: {0}")]
-    VarNotFound(String),
+    /// Signifies that variable was not found in variable map.
+    ///
+    /// The second field is the closest name of the variables, the constants and the functions,
+    /// if the variable looks like the misspelling of it.
+    #[error("Variable not found: {0}{}", did_you_mean(.1))]
+    VarNotFound(String, Option<String>),
+    /// The [string literal](crate::tokenizer#string-literals) is used where the number is expected,
+    /// only the [functions with strings](crate::functions::Func::strings) accept them
+    #[error("String literal \"{0}\" can't be used as a number")]
+    UnexpectedString(String),
     /// Signifies that evaluation stack has empty when a value was expected
     #[error("Eval stack is empty during processing")]
     EmptyEvalStack,
@@ -46,16 +68,122 @@ pub enum Error {
     /// # Note
     ///
     /// This error is likely picked up in ParserError case, however it still can occur if you pass the tokens manually to one of `eval` functions.
-    #[error("Arity of function {id} mismatched during evaluation: expected: {expected:?}, actual: {actual}")]
+    #[error("Arity of function {id} mismatched during evaluation: expected: {expected}, actual: {actual}")]
     ArityMismatch {
         /// Identifier of the mismatched function
         id: String,
-        /// Expected number of parameters to the function
-        expected: usize,
+        /// Expected numbers of parameters to the function, see [`Func::arity_range`](crate::functions::Func::arity_range)
+        expected: ArityRange,
         /// Actual number of parameters passed to the function
         actual: usize,
     },
 
+    /// Signifies that a [`Fallible`](crate::functions::FuncKind::Fallible) function failed
+    #[error("Function {id} failed: {message}")]
+    FunctionError {
+        /// Identifier of the function
+        id: String,
+        /// The error message of the function
+        message: String,
+    },
+
+    /// Signifies that the operator, the function or the macro is not implemented for the [`Value`](crate::value::Value) type
+    #[error("{0} is not implemented for the value type")]
+    Unsupported(String),
+
+    /// Signifies that the result of the predicate is not a truth value under [`Truthiness::Strict`](Truthiness::Strict)
+    #[error("Result is not a boolean: {0}")]
+    NotBoolean(f64),
+
+    /// Signifies that an operator or a function produced an infinite result from the finite arguments,
+    /// one of which is zero, e.g. `1 / 0` or `ln(0)`.
+    ///
+    /// Only reported by [`strict_math`](EvalOptions::strict_math) evaluation, and by the [`Value`](crate::value::Value) evaluation.
+    #[error("Division by zero in {func} at token {position}")]
+    DivisionByZero {
+        /// Identifier of the operator or the function
+        func: String,
+        /// The index of the operator or the function in the token stream
+        position: usize,
+    },
+
+    /// Signifies that an operator or a function produced `NaN` from the arguments that are not `NaN`,
+    /// e.g. `sqrt(-2)` or `0 / 0`.
+    ///
+    /// Only reported by [`strict_math`](EvalOptions::strict_math) evaluation, and by the [`Value`](crate::value::Value) evaluation.
+    #[error("Arguments {args:?} are outside of the domain of {func} at token {position}")]
+    DomainError {
+        /// Identifier of the operator or the function
+        func: String,
+        /// The arguments the operator or the function was applied to,
+        /// empty when the [`Value`](crate::value::Value) evaluation reports it
+        args: Vec<f64>,
+        /// The index of the operator or the function in the token stream
+        position: usize,
+    },
+
+    /// Signifies that an operator or a function produced an infinite result from the finite non-zero arguments,
+    /// e.g. `10 ^ 400`.
+    ///
+    /// Only reported by [`strict_math`](EvalOptions::strict_math) evaluation, and by the [`Value`](crate::value::Value) evaluation.
+    #[error("Overflow in {func} at token {position}")]
+    Overflow {
+        /// Identifier of the operator or the function
+        func: String,
+        /// The index of the operator or the function in the token stream
+        position: usize,
+    },
+
+    /// Signifies that an element-wise operator was applied to the arrays of different lengths,
+    /// see [`Array`](crate::value::array::Array).
+    #[error("Arrays of different lengths in {func} at token {position}")]
+    LengthMismatch {
+        /// Identifier of the operator
+        func: String,
+        /// The index of the operator in the token stream
+        position: usize,
+    },
+
+    /// Signifies that an operator was applied to the quantities of incompatible dimensions, e.g. `1 m + 1 s`,
+    /// see [`Quantity`](crate::value::units::Quantity).
+    #[error("Incompatible units in {func} at token {position}")]
+    IncompatibleUnits {
+        /// Identifier of the operator
+        func: String,
+        /// The index of the operator in the token stream
+        position: usize,
+    },
+
+    /// Signifies that the index is not an integer from 0 to the number of the elements, e.g. `[1, 2][2]`,
+    /// see [indexing](crate::tokenizer#indexing).
+    #[error("Index out of bounds at token {position}")]
+    IndexOutOfBounds {
+        /// The index of the indexing operator in the token stream
+        position: usize,
+    },
+
+    /// Signifies that the expression evaluated with [`eval_readonly`](eval_readonly) contains the macro
+    /// that writes the variables, see [`ParsedMacro::writes_variables`](crate::macros::ParsedMacro::writes_variables).
+    #[error("Macro {0} writes the variables, but they are read-only")]
+    ReadOnlyVariables(String),
+
+    /// Signifies that the evaluation stack grew over [`max_eval_stack`](crate::limits::Limits::max_eval_stack)
+    #[error("Evaluation stack is deeper than the limit of {0}")]
+    EvalStackOverflow(usize),
+
+    /// Signifies that the evaluation took more steps than [`max_steps`](crate::limits::Limits::max_steps) allows
+    #[error("Evaluation is longer than the limit of {0} steps")]
+    StepLimitExceeded(usize),
+
+    /// Signifies that the macros evaluated the expressions nested deeper than
+    /// [`max_nesting`](crate::limits::Limits::max_nesting) allows, e.g. the recursive function
+    #[error("Evaluation is nested deeper than the limit of {0}")]
+    NestingLimitExceeded(usize),
+
+    /// Signifies that the evaluation was stopped by the [`CancelToken`](CancelToken) of the [`EvalOptions`](EvalOptions)
+    #[error("Evaluation cancelled")]
+    Cancelled,
+
     /// Catch-all case when something unexpected happened
     #[error("Ill formed token steam")]
     Other,
@@ -64,61 +192,302 @@ pub enum Error {
 /// Result type of this module with [`evaluator::Error`](Error) as Error type
 pub type Result = std::result::Result<f64, Error>;
 
-/// The main evaluation logic
 fn eval_internal(
     tokens: &[ParserToken],
-    variables: &mut HashMap<String, f64>,
+    variables: &mut dyn VariableResolver,
     ctx: &Ctx,
 ) -> Result {
-    let mut eval_stack: Vec<f64> = Vec::new();
-    for token in tokens {
-        match *token {
-            ParserToken::Num(n) => {
-                eval_stack.push(n);
+    eval_with_stack(tokens, variables, ctx, &mut Vec::new())
+}
+
+/// The main evaluation logic, `eval_stack` is cleared and reused to avoid allocations
+pub(crate) fn eval_with_stack(
+    tokens: &[ParserToken],
+    variables: &mut dyn VariableResolver,
+    ctx: &Ctx,
+    eval_stack: &mut Vec<f64>,
+) -> Result {
+    eval_stack.clear();
+    if ctx.limits == Limits::default() {
+        for (position, token) in tokens.iter().enumerate() {
+            eval_token(token, position, variables, ctx, eval_stack)?;
+        }
+        return eval_stack.pop().ok_or(Error::Other);
+    }
+    let _guard = LimitsGuard::enter(&ctx.limits, tokens.len())?;
+    for (position, token) in tokens.iter().enumerate() {
+        eval_token(token, position, variables, ctx, eval_stack)?;
+        check_eval_stack(&ctx.limits, eval_stack)?;
+    }
+    eval_stack.pop().ok_or(Error::Other)
+}
+
+thread_local! {
+    /// The nesting depth and the steps of the evaluation running on this thread,
+    /// the expressions evaluated by the macros are counted into the evaluation of the outer expression
+    static EVALUATION: Cell<(usize, usize)> = const { Cell::new((0, 0)) };
+}
+
+/// Counts the evaluation against the [`Limits`](Limits) of the context while it is alive
+pub(crate) struct LimitsGuard;
+
+impl LimitsGuard {
+    /// Enters the evaluation of `steps` tokens
+    pub(crate) fn enter(limits: &Limits, steps: usize) -> std::result::Result<Self, Error> {
+        EVALUATION.with(|evaluation| {
+            let (depth, used) = evaluation.get();
+            // the outermost evaluation starts counting the steps from zero
+            let used = if depth == 0 { steps } else { used + steps };
+            if let Some(max) = limits.max_nesting.filter(|max| depth > *max) {
+                return Err(Error::NestingLimitExceeded(max));
             }
-            ParserToken::Id(id) => {
-                let value = variables
-                    .get(id)
-                    .ok_or_else(|| Error::VarNotFound(id.into()))?;
-                eval_stack.push(*value);
+            if let Some(max) = limits.max_steps.filter(|max| used > *max) {
+                return Err(Error::StepLimitExceeded(max));
             }
-            ParserToken::UOp(op) => {
-                let operand = eval_stack.pop().ok_or(Error::EmptyEvalStack)?;
-                let func = op.func;
-                eval_stack.push(func(operand));
+            evaluation.set((depth + 1, used));
+            Ok(LimitsGuard)
+        })
+    }
+}
+
+impl Drop for LimitsGuard {
+    fn drop(&mut self) {
+        EVALUATION.with(|evaluation| {
+            let (depth, used) = evaluation.get();
+            evaluation.set((depth - 1, used));
+        });
+    }
+}
+
+pub(crate) fn check_eval_stack(
+    limits: &Limits,
+    eval_stack: &[f64],
+) -> std::result::Result<(), Error> {
+    match limits.max_eval_stack {
+        Some(max) if eval_stack.len() > max => Err(Error::EvalStackOverflow(max)),
+        _ => Ok(()),
+    }
+}
+
+/// The options of [`eval_with_options`](eval_with_options).
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct EvalOptions {
+    /// Report [`DivisionByZero`](Error::DivisionByZero), [`DomainError`](Error::DomainError)
+    /// and [`Overflow`](Error::Overflow) instead of producing infinities and `NaN`.
+    ///
+    /// The check wraps every operator and function, the macros that evaluate the expressions by themselves,
+    /// e.g. `if`, check them too, see [`ParsedMacro::eval_with_options`](crate::macros::ParsedMacro::eval_with_options).
+    /// The arguments that are already infinite or `NaN` (e.g. the `inf` constant) are propagated without errors.
+    pub strict_math: bool,
+    /// Stop the evaluation with [`Cancelled`](Error::Cancelled) once the token is cancelled.
+    ///
+    /// The token is checked every [`CANCEL_CHECK_INTERVAL`](CANCEL_CHECK_INTERVAL) tokens of the expression,
+    /// and of the expressions the macros like `if` evaluate with the options.
+    pub cancel: Option<CancelToken>,
+}
+
+/// The number of the tokens evaluated between the checks of the [`CancelToken`](CancelToken).
+pub const CANCEL_CHECK_INTERVAL: usize = 64;
+
+/// The flag that cancels the evaluations that were given its clones, e.g. from the other thread.
+///
+/// # Example
+///
+/// ```
+/// use rusty_yard::evaluator::{eval_str_with_options, CancelToken, Error, EvalOptions};
+/// use rusty_yard::Ctx;
+/// use std::collections::HashMap;
+///
+/// let cancel = CancelToken::new();
+/// let options = EvalOptions {
+///     cancel: Some(cancel.clone()),
+///     ..EvalOptions::default()
+/// };
+/// let ctx = Ctx::default();
+/// assert_eq!(eval_str_with_options("1 + 2", &mut HashMap::new(), &ctx, options.clone()), Ok(3.0));
+/// cancel.cancel();
+/// assert_eq!(eval_str_with_options("1 + 2", &mut HashMap::new(), &ctx, options), Err(Error::Cancelled));
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    /// Creates the token that is not cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels the evaluations that use this token or its clones.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns `true` if the token was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+impl PartialEq for CancelToken {
+    /// The tokens are equal if they are the clones of the same token.
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// Same as [`eval_with_stack`](eval_with_stack), but applies the `options`
+fn eval_with_options_and_stack(
+    tokens: &[ParserToken],
+    variables: &mut dyn VariableResolver,
+    ctx: &Ctx,
+    options: EvalOptions,
+    eval_stack: &mut Vec<f64>,
+) -> Result {
+    if !options.strict_math && options.cancel.is_none() {
+        return eval_with_stack(tokens, variables, ctx, eval_stack);
+    }
+    eval_stack.clear();
+    let _guard = LimitsGuard::enter(&ctx.limits, tokens.len())?;
+    for (position, token) in tokens.iter().enumerate() {
+        eval_token_with_options(token, position, variables, ctx, &options, eval_stack)?;
+    }
+    eval_stack.pop().ok_or(Error::Other)
+}
+
+/// Same as [`eval_token`](eval_token), but applies the `options`,
+/// the macros evaluate their inner expressions with them, see [`ParsedMacro::eval_with_options`](crate::macros::ParsedMacro::eval_with_options)
+pub(crate) fn eval_token_with_options(
+    token: &ParserToken,
+    position: usize,
+    variables: &mut dyn VariableResolver,
+    ctx: &Ctx,
+    options: &EvalOptions,
+    eval_stack: &mut Vec<f64>,
+) -> std::result::Result<(), Error> {
+    if let Some(cancel) = &options.cancel {
+        if position.is_multiple_of(CANCEL_CHECK_INTERVAL) && cancel.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+    }
+    let (func, arg_count) = match *token {
+        ParserToken::UOp(op) if options.strict_math => (&op.token, 1),
+        ParserToken::BiOp(op) if options.strict_math => (&op.token, 2),
+        ParserToken::Func(func, call_args) if options.strict_math => (&func.token, call_args),
+        ParserToken::Macro(ref m) => {
+            m.eval_with_options(eval_stack, variables, ctx, options)?;
+            return check_eval_stack(&ctx.limits, eval_stack);
+        }
+        _ => {
+            eval_token(token, position, variables, ctx, eval_stack)?;
+            return check_eval_stack(&ctx.limits, eval_stack);
+        }
+    };
+    let args = eval_stack[eval_stack.len().saturating_sub(arg_count)..].to_vec();
+    eval_token(token, position, variables, ctx, eval_stack)?;
+    check_eval_stack(&ctx.limits, eval_stack)?;
+    let result = *eval_stack.last().ok_or(Error::EmptyEvalStack)?;
+    check_math(func, args, result, position)
+}
+
+pub(crate) fn check_math(
+    func: &str,
+    args: Vec<f64>,
+    result: f64,
+    position: usize,
+) -> std::result::Result<(), Error> {
+    if result.is_nan() && !args.iter().any(|arg| arg.is_nan()) {
+        return Err(Error::DomainError {
+            func: func.to_owned(),
+            args,
+            position,
+        });
+    }
+    if result.is_infinite() && args.iter().all(|arg| arg.is_finite()) {
+        let func = func.to_owned();
+        return Err(if args.contains(&0.0) {
+            Error::DivisionByZero { func, position }
+        } else {
+            Error::Overflow { func, position }
+        });
+    }
+    Ok(())
+}
+
+/// The [`VarNotFound`](Error::VarNotFound) error of the `id`,
+/// suggesting the closest of the `variables` and of the constants and the functions of the `ctx`
+pub(crate) fn var_not_found(id: &str, variables: &dyn VariableResolver, ctx: &Ctx) -> Error {
+    let names = variables.names();
+    let candidates = names
+        .iter()
+        .map(String::as_str)
+        .chain(ctx.consts.iter().map(|constant| constant.token.as_str()))
+        .chain(ctx.fns.iter().map(|func| func.token.as_str()));
+    Error::VarNotFound(id.to_owned(), closest(id, candidates))
+}
+
+/// Evaluates a single token on top of the `eval_stack`, `position` is the index of the token in the stream
+pub(crate) fn eval_token(
+    token: &ParserToken,
+    position: usize,
+    variables: &mut dyn VariableResolver,
+    ctx: &Ctx,
+    eval_stack: &mut Vec<f64>,
+) -> std::result::Result<(), Error> {
+    match *token {
+        ParserToken::Num(n) => {
+            eval_stack.push(n);
+        }
+        ParserToken::Id(id) => {
+            let value = variables
+                .get(id)
+                .ok_or_else(|| var_not_found(id, variables, ctx))?;
+            eval_stack.push(value);
+        }
+        ParserToken::Str(s) => return Err(Error::UnexpectedString(s.to_owned())),
+        ParserToken::UOp(op) => {
+            let operand = eval_stack.pop().ok_or(Error::EmptyEvalStack)?;
+            eval_stack.push((op.func)(operand));
+        }
+        ParserToken::BiOp(op) => {
+            let right = eval_stack.pop().ok_or(Error::EmptyEvalStack)?;
+            let left = eval_stack.pop().ok_or(Error::EmptyEvalStack)?;
+            // the number is the array of one element, see `Value::index`
+            if op.builtin.is_none() && op.token == INDEX_OPERATOR && right != 0.0 {
+                return Err(Error::IndexOutOfBounds { position });
             }
-            ParserToken::BiOp(op) => {
-                let right = eval_stack.pop().ok_or(Error::EmptyEvalStack)?;
-                let left = eval_stack.pop().ok_or(Error::EmptyEvalStack)?;
-                let func = op.func;
-                let eval = func(left, right);
-                eval_stack.push(eval);
+            eval_stack.push(op.apply(left, right));
+        }
+        ParserToken::Func(func, call_args) => {
+            if !func.accepts(call_args) {
+                return Err(Error::ArityMismatch {
+                    id: func.token.clone(),
+                    expected: func.arity_range(),
+                    actual: call_args,
+                });
             }
-            ParserToken::Func(func, call_args) => {
-                if let Some(arity) = func.arity {
-                    if arity != call_args {
-                        return Err(Error::ArityMismatch {
-                            id: func.token.clone(),
-                            expected: arity,
-                            actual: call_args,
-                        });
-                    }
-                }
-                let temp = &eval_stack[(eval_stack.len() - call_args)..];
-                let eval = func.call(temp).expect(
-                    "Number of actual arguments matches the number of params to the function",
-                );
-                for _ in 0..call_args {
-                    eval_stack.pop();
+            let temp = &eval_stack[(eval_stack.len() - call_args)..];
+            let eval = match func.call(temp) {
+                Ok(eval) => eval,
+                Err(functions::Error::Failed(message)) => {
+                    return Err(Error::FunctionError {
+                        id: func.token.clone(),
+                        message,
+                    })
                 }
-                eval_stack.push(eval);
-            }
-            ParserToken::Macro(ref m) => {
-                m.eval(&mut eval_stack, variables, ctx)?;
+                Err(functions::Error::ArityMismatch { .. }) => unreachable!(
+                    "Number of actual arguments matches the number of params to the function"
+                ),
+            };
+            for _ in 0..call_args {
+                eval_stack.pop();
             }
+            eval_stack.push(eval);
+        }
+        ParserToken::Macro(ref m) => {
+            m.eval(eval_stack, variables, ctx)?;
         }
     }
-    eval_stack.pop().ok_or(Error::Other)
+    Ok(())
 }
 
 /// Evaluate the input token stream and return the result of the evaluation.
@@ -218,6 +587,101 @@ pub fn eval_with_vars_and_ctx(
     eval_internal(tokens, variables, ctx)
 }
 
+/// Evaluate the input token stream with variables stored in any [`VariableResolver`](crate::variables::VariableResolver)
+/// and custom [context](crate::Ctx).
+///
+/// Tokens can be produced by [`parse`](crate::parser::parse) or [`parse_str`](crate::parser::parse_str) function.
+///
+/// # Note
+///
+/// Tokens need to be in [reverse polish notation](https://en.wikipedia.org/wiki/Reverse_Polish_notation).
+///
+/// # Example
+///
+/// ```
+/// use rusty_yard::evaluator::eval_with;
+/// use rusty_yard::parser::ParserToken;
+/// use rusty_yard::operators::binary::PLUS;
+/// use rusty_yard::variables::{SnapshotVariables, VariableResolver};
+/// use rusty_yard::Ctx;
+///
+/// let mut vars = SnapshotVariables::new();
+/// vars.set("a", 3.0);
+/// let result = eval_with(&[ParserToken::Id("a"), ParserToken::Num(4.0), ParserToken::BiOp(&PLUS)], &mut vars, &Ctx::default());
+/// assert_eq!(result, Ok(7.0));
+/// ```
+#[cfg_attr(tarpaulin, skip)]
+#[inline]
+pub fn eval_with(
+    tokens: &[ParserToken],
+    variables: &mut dyn VariableResolver,
+    ctx: &Ctx,
+) -> Result {
+    eval_internal(tokens, variables, ctx)
+}
+
+/// Evaluate the input token stream without changing the `variables`,
+/// so many threads can evaluate the expressions against the same shared variables.
+///
+/// Returns [`ReadOnlyVariables`](Error::ReadOnlyVariables) error if the expression contains the macro that writes the variables
+/// of the caller, e.g. the assignment, including the assignments in the expressions the macros evaluate by themselves.
+/// The assignments to the variables the macro binds, e.g. to the parameters of the [`UserFn`](crate::macros::user_fn::UserFn),
+/// are allowed.
+///
+/// # Example
+///
+/// ```
+/// use rusty_yard::evaluator::{eval_readonly, Error};
+/// use rusty_yard::parser::parse_str;
+/// use rusty_yard::Ctx;
+/// use std::collections::HashMap;
+///
+/// let ctx = Ctx::default_with_macros();
+/// let mut vars = HashMap::new();
+/// vars.insert("a".to_owned(), 2.0);
+/// let tokens = parse_str("a * 3", &ctx).unwrap();
+/// assert_eq!(eval_readonly(&tokens, &vars, &ctx), Ok(6.0));
+/// let tokens = parse_str("a = 3", &ctx).unwrap();
+/// assert_eq!(eval_readonly(&tokens, &vars, &ctx), Err(Error::ReadOnlyVariables("=".to_owned())));
+/// ```
+pub fn eval_readonly(
+    tokens: &[ParserToken],
+    variables: &dyn VariableResolver,
+    ctx: &Ctx,
+) -> Result {
+    if let Some(name) = writing_macro(tokens, ctx, &[]) {
+        return Err(Error::ReadOnlyVariables(name));
+    }
+    eval_internal(tokens, &mut ReadOnly::new(variables), ctx)
+}
+
+/// Returns the name of the macro that writes the variables except the `bound` ones, if any
+fn writing_macro(tokens: &[ParserToken], ctx: &Ctx, bound: &[String]) -> Option<String> {
+    for token in tokens {
+        let m = match token {
+            ParserToken::Macro(m) => m,
+            _ => continue,
+        };
+        let assigns_bound = m
+            .assigns()
+            .is_some_and(|id| bound.iter().any(|var| var == id));
+        if m.writes_variables() && !assigns_bound {
+            return Some(m.name().to_owned());
+        }
+        for inner in m.inner_expressions() {
+            let mut inner_bound = bound.to_vec();
+            inner_bound.extend(inner.bound.iter().map(|var| var.to_string()));
+            // the macro has parsed the expression already, it only fails if the ctx is different
+            if let Ok(inner_tokens) = parser::parse_str(inner.input, ctx) {
+                if let Some(name) = writing_macro(&inner_tokens, ctx, &inner_bound) {
+                    return Some(name);
+                }
+            }
+        }
+    }
+    None
+}
+
 /// Evaluate the string with the expression inside
 ///
 /// This uses the default context from `Ctx::default`
@@ -286,15 +750,586 @@ pub fn eval_str_with_vars_and_ctx(
     variables: &mut HashMap<String, f64>,
     ctx: &Ctx,
 ) -> Result {
-    let tokens = tokenize(input, ctx);
+    let tokens = apply_passes(tokenize(input, ctx), ctx);
     let parsed = parse(&tokens, ctx)?;
     eval_internal(&parsed, variables, ctx)
 }
 
+/// Evaluate the string with the expression inside with variables stored in any [`VariableResolver`](crate::variables::VariableResolver)
+/// and custom [context](crate::Ctx).
+///
+/// # Example
+///
+/// ```
+/// use rusty_yard::evaluator::eval_str_with;
+/// use rusty_yard::variables::SnapshotVariables;
+/// use rusty_yard::Ctx;
+///
+/// let ctx = Ctx::default_with_macros();
+/// let mut vars = SnapshotVariables::new();
+/// assert_eq!(eval_str_with("a = 7.0", &mut vars, &ctx), Ok(7.0));
+/// assert_eq!(vars.get("a"), Some(7.0));
+/// ```
+#[cfg_attr(tarpaulin, skip)]
+pub fn eval_str_with(input: &str, variables: &mut dyn VariableResolver, ctx: &Ctx) -> Result {
+    let tokens = apply_passes(tokenize(input, ctx), ctx);
+    let parsed = parse(&tokens, ctx)?;
+    eval_internal(&parsed, variables, ctx)
+}
+
+/// Evaluate the input token stream with the `options`.
+///
+/// Tokens can be produced by [`parse`](crate::parser::parse) or [`parse_str`](crate::parser::parse_str) function.
+///
+/// # Example
+///
+/// ```
+/// use rusty_yard::evaluator::{eval_with_options, Error, EvalOptions};
+/// use rusty_yard::parser::parse_str;
+/// use rusty_yard::Ctx;
+/// use std::collections::HashMap;
+///
+/// let ctx = Ctx::default();
+/// let tokens = parse_str("1 / a", &ctx).unwrap();
+/// let mut vars = HashMap::new();
+/// vars.insert("a".to_owned(), 0.0);
+/// let options = EvalOptions {
+///     strict_math: true,
+///     ..EvalOptions::default()
+/// };
+/// assert_eq!(
+///     eval_with_options(&tokens, &mut vars, &ctx, options),
+///     Err(Error::DivisionByZero { func: "/".to_owned(), position: 2 })
+/// );
+/// ```
+pub fn eval_with_options(
+    tokens: &[ParserToken],
+    variables: &mut dyn VariableResolver,
+    ctx: &Ctx,
+    options: EvalOptions,
+) -> Result {
+    eval_with_options_and_stack(tokens, variables, ctx, options, &mut Vec::new())
+}
+
+/// Evaluate the string with the expression inside with the `options`.
+///
+/// See [`eval_with_options`](eval_with_options).
+pub fn eval_str_with_options(
+    input: &str,
+    variables: &mut dyn VariableResolver,
+    ctx: &Ctx,
+    options: EvalOptions,
+) -> Result {
+    let tokens = apply_passes(tokenize(input, ctx), ctx);
+    let parsed = parse(&tokens, ctx)?;
+    eval_with_options(&parsed, variables, ctx, options)
+}
+
+/// The step of the evaluation recorded by [`eval_traced`](eval_traced): the token applied to the evaluation stack.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+    /// The index of the token in the evaluated tokens.
+    pub position: usize,
+    /// The number, the variable, the token of the operator or the function, or the [name](crate::macros::ParsedMacro::name) of the macro.
+    pub token: String,
+    /// The values the token took from the top of the evaluation stack, the deepest first.
+    ///
+    /// The operands of the macro are the ones it [declares](crate::macros::ParsedMacro::operands).
+    pub operands: Vec<f64>,
+    /// The value on the top of the evaluation stack after the token is applied.
+    pub result: f64,
+}
+
+impl Display for Step {
+    /// Writes the step as `x -> 2` or `+(3, 4) -> 7`.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.token)?;
+        if !self.operands.is_empty() {
+            let operands: Vec<_> = self.operands.iter().map(f64::to_string).collect();
+            write!(f, "({})", operands.join(", "))?;
+        }
+        write!(f, " -> {}", self.result)
+    }
+}
+
+/// Receives the [`Steps`](Step) of [`eval_traced`](eval_traced), as soon as they are evaluated.
+///
+/// It is implemented for `Vec<Step>`, that collects them.
+pub trait TraceSink {
+    /// Records the step that has been evaluated.
+    fn record(&mut self, step: Step);
+}
+
+impl TraceSink for Vec<Step> {
+    fn record(&mut self, step: Step) {
+        self.push(step);
+    }
+}
+
+/// Same as [`eval_with`](eval_with), but records each [`Step`](Step) of the evaluation into the `trace`,
+/// e.g. to show how the result has been computed.
+///
+/// The steps evaluated before the error are recorded too. The expressions the macros evaluate by themselves,
+/// e.g. the body of the [user function](crate::macros::user_fn), are the single step of the macro.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use rusty_yard::evaluator::{eval_traced, Step};
+/// use rusty_yard::parser::parse_str;
+/// use rusty_yard::Ctx;
+///
+/// let ctx = Ctx::default();
+/// let tokens = parse_str("x * (1 + 2)", &ctx).unwrap();
+/// let mut vars = HashMap::new();
+/// vars.insert("x".to_owned(), 2.0);
+/// let mut trace: Vec<Step> = Vec::new();
+/// assert_eq!(eval_traced(&tokens, &mut vars, &ctx, &mut trace), Ok(6.0));
+/// let steps: Vec<_> = trace.iter().map(Step::to_string).collect();
+/// assert_eq!(steps, vec!["x -> 2", "1 -> 1", "2 -> 2", "+(1, 2) -> 3", "*(2, 3) -> 6"]);
+/// ```
+pub fn eval_traced(
+    tokens: &[ParserToken],
+    variables: &mut dyn VariableResolver,
+    ctx: &Ctx,
+    trace: &mut dyn TraceSink,
+) -> Result {
+    let _guard = LimitsGuard::enter(&ctx.limits, tokens.len())?;
+    let mut eval_stack = Vec::new();
+    for (position, token) in tokens.iter().enumerate() {
+        let (name, n_operands) = match token {
+            ParserToken::Num(n) => (n.to_string(), 0),
+            ParserToken::Id(id) => ((*id).to_owned(), 0),
+            ParserToken::Str(s) => ((*s).to_owned(), 0),
+            ParserToken::UOp(op) => (op.token.clone(), 1),
+            ParserToken::BiOp(op) => (op.token.clone(), 2),
+            ParserToken::Func(func, call_args) => (func.token.clone(), *call_args),
+            ParserToken::Macro(m) => (m.name().to_owned(), m.operands()),
+        };
+        let start = eval_stack.len().saturating_sub(n_operands);
+        let operands = eval_stack[start..].to_vec();
+        eval_token(token, position, variables, ctx, &mut eval_stack)?;
+        check_eval_stack(&ctx.limits, &eval_stack)?;
+        trace.record(Step {
+            position,
+            token: name,
+            operands,
+            result: *eval_stack.last().ok_or(Error::EmptyEvalStack)?,
+        });
+    }
+    eval_stack.pop().ok_or(Error::Other)
+}
+
+/// The rule that converts the result of the predicate to `bool`, see [`eval_bool`](eval_bool).
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Truthiness {
+    /// Every number except 0 is `true`, `NaN` is `false`.
+    NonZero,
+    /// Only 1 is `true` and only 0 is `false`, other results are [`NotBoolean`](Error::NotBoolean) errors.
+    Strict,
+    /// Numbers greater than or equal to the threshold are `true`, `NaN` is `false`.
+    Threshold(f64),
+}
+
+impl Default for Truthiness {
+    /// Creates [`NonZero`](Truthiness::NonZero) rule.
+    fn default() -> Self {
+        Truthiness::NonZero
+    }
+}
+
+impl Truthiness {
+    /// Converts the number to `bool` using this rule.
+    pub fn apply(self, value: f64) -> std::result::Result<bool, Error> {
+        match self {
+            Truthiness::NonZero => Ok(value != 0.0 && !value.is_nan()),
+            Truthiness::Strict if value == 1.0 => Ok(true),
+            Truthiness::Strict if value == 0.0 => Ok(false),
+            Truthiness::Strict => Err(Error::NotBoolean(value)),
+            Truthiness::Threshold(threshold) => Ok(value >= threshold),
+        }
+    }
+}
+
+/// Evaluate the input token stream as a predicate, converting the result with the `truthiness` rule.
+///
+/// Tokens can be produced by [`parse`](crate::parser::parse) or [`parse_str`](crate::parser::parse_str) function.
+/// See [`check_predicate`](crate::analysis::check_predicate) for the static check of the predicate.
+///
+/// # Note
+///
+/// Tokens need to be in [reverse polish notation](https://en.wikipedia.org/wiki/Reverse_Polish_notation).
+///
+/// # Example
+///
+/// ```
+/// use rusty_yard::evaluator::{eval_bool, Truthiness};
+/// use rusty_yard::parser::parse_str;
+/// use rusty_yard::Ctx;
+/// use std::collections::HashMap;
+///
+/// let ctx = Ctx::default();
+/// let tokens = parse_str("a - 1", &ctx).unwrap();
+/// let mut vars = HashMap::new();
+/// vars.insert("a".to_owned(), 1.5);
+/// assert_eq!(eval_bool(&tokens, &mut vars, &ctx, Truthiness::NonZero), Ok(true));
+/// assert_eq!(eval_bool(&tokens, &mut vars, &ctx, Truthiness::Threshold(1.0)), Ok(false));
+/// ```
+pub fn eval_bool(
+    tokens: &[ParserToken],
+    variables: &mut dyn VariableResolver,
+    ctx: &Ctx,
+    truthiness: Truthiness,
+) -> std::result::Result<bool, Error> {
+    truthiness.apply(eval_internal(tokens, variables, ctx)?)
+}
+
+/// Evaluate the string with the expression inside as a predicate, converting the result with the `truthiness` rule.
+///
+/// # Example
+///
+/// ```
+/// use rusty_yard::evaluator::{eval_str_bool, Error, Truthiness};
+/// use rusty_yard::Ctx;
+/// use std::collections::HashMap;
+///
+/// let ctx = Ctx::default();
+/// let mut vars = HashMap::new();
+/// vars.insert("a".to_owned(), 0.0);
+/// assert_eq!(eval_str_bool("a", &mut vars, &ctx, Truthiness::Strict), Ok(false));
+/// assert_eq!(eval_str_bool("a + 2", &mut vars, &ctx, Truthiness::Strict), Err(Error::NotBoolean(2.0)));
+/// ```
+pub fn eval_str_bool(
+    input: &str,
+    variables: &mut dyn VariableResolver,
+    ctx: &Ctx,
+    truthiness: Truthiness,
+) -> std::result::Result<bool, Error> {
+    truthiness.apply(eval_str_with(input, variables, ctx)?)
+}
+
+/// Evaluate the input token stream once for every set of variables.
+///
+/// This is the same as calling [`eval_with`](eval_with) for every item of `variables`,
+/// but the evaluation stack is allocated once and reused.
+/// The items can be any [`VariableResolver`](VariableResolver), including `&mut` references to them.
+///
+/// # Example
+///
+/// ```
+/// use rusty_yard::evaluator::eval_batch;
+/// use rusty_yard::parser::parse_str;
+/// use rusty_yard::Ctx;
+/// use std::collections::HashMap;
+///
+/// let ctx = Ctx::default();
+/// let tokens = parse_str("price * quantity", &ctx).unwrap();
+/// let rows = vec![(2.0, 3.0), (1.5, 2.0)].into_iter().map(|(price, quantity)| {
+///     let mut vars = HashMap::new();
+///     vars.insert("price".to_owned(), price);
+///     vars.insert("quantity".to_owned(), quantity);
+///     vars
+/// });
+/// assert_eq!(eval_batch(&tokens, rows, &ctx), vec![Ok(6.0), Ok(3.0)]);
+/// ```
+pub fn eval_batch<R: VariableResolver>(
+    tokens: &[ParserToken],
+    variables: impl IntoIterator<Item = R>,
+    ctx: &Ctx,
+) -> Vec<Result> {
+    let mut eval_stack = Vec::new();
+    variables
+        .into_iter()
+        .map(|mut variables| eval_with_stack(tokens, &mut variables, ctx, &mut eval_stack))
+        .collect()
+}
+
+/// Same as [`eval_batch`](eval_batch), but the sets of variables are evaluated in parallel.
+///
+/// Every thread reuses its own evaluation stack.
+/// The token stream and the context are shared between the threads,
+/// it is possible because [`ParsedMacro`](crate::macros::ParsedMacro), [`Func`](crate::functions::Func)
+/// and the operators are [`Send`](std::marker::Send) and [`Sync`](std::marker::Sync).
+/// The results are in the order of the `variables`.
+///
+/// Requires `rayon` feature.
+///
+/// # Example
+///
+/// ```
+/// use rusty_yard::evaluator::eval_batch_par;
+/// use rusty_yard::parser::parse_str;
+/// use rusty_yard::Ctx;
+/// use std::collections::HashMap;
+///
+/// let ctx = Ctx::default();
+/// let tokens = parse_str("x ^ 2", &ctx).unwrap();
+/// let rows: Vec<_> = (0..1000)
+///     .map(|x| HashMap::from([("x".to_owned(), x as f64)]))
+///     .collect();
+/// let results = eval_batch_par(&tokens, rows, &ctx);
+/// assert_eq!(results[999], Ok(998001.0));
+/// ```
+#[cfg(feature = "rayon")]
+pub fn eval_batch_par<R: VariableResolver + Send>(
+    tokens: &[ParserToken],
+    variables: impl rayon::iter::IntoParallelIterator<Item = R>,
+    ctx: &Ctx,
+) -> Vec<Result> {
+    use rayon::prelude::*;
+    variables
+        .into_par_iter()
+        .map_init(Vec::new, |eval_stack, mut variables| {
+            eval_with_stack(tokens, &mut variables, ctx, eval_stack)
+        })
+        .collect()
+}
+
+/// Evaluate the input token stream for each of the `values` of the variable `var`, e.g. to plot the expression.
+///
+/// Other variables are read from `variables`, `var` shadows the variable with the same name,
+/// and the assignments to `var` don't change `variables`.
+/// The evaluation stack is allocated once and reused, see also [`sample`](crate::sample::sample).
+///
+/// # Example
+///
+/// ```
+/// use rusty_yard::evaluator::eval_over;
+/// use rusty_yard::parser::parse_str;
+/// use rusty_yard::Ctx;
+/// use std::collections::HashMap;
+///
+/// let ctx = Ctx::default();
+/// let tokens = parse_str("a * x ^ 2", &ctx).unwrap();
+/// let mut vars = HashMap::new();
+/// vars.insert("a".to_owned(), 2.0);
+/// let results = eval_over(&tokens, "x", &[0.0, 1.0, 2.0], &mut vars, &ctx);
+/// assert_eq!(results, vec![Ok(0.0), Ok(2.0), Ok(8.0)]);
+/// ```
+pub fn eval_over(
+    tokens: &[ParserToken],
+    var: &str,
+    values: &[f64],
+    variables: &mut dyn VariableResolver,
+    ctx: &Ctx,
+) -> Vec<Result> {
+    let mut eval_stack = Vec::new();
+    let mut bound = Bound {
+        vars: [(var, 0.0)],
+        variables,
+    };
+    values
+        .iter()
+        .map(|value| {
+            bound.vars[0].1 = *value;
+            eval_with_stack(tokens, &mut bound, ctx, &mut eval_stack)
+        })
+        .collect()
+}
+
+/// Evaluate the string with the expression inside in a single pass when it is possible.
+///
+/// Simple expressions that only consist of numbers, variables, parentheses and operators from the context
+/// (the common `a * b + c` case) are evaluated while the input is being read,
+/// without building the intermediate [`Token`](crate::tokenizer::Token) and [`ParserToken`](crate::parser::ParserToken) streams
+/// and without any allocations.
+///
+/// Everything else (functions, macros, deeply nested expressions, erroneous input)
+/// falls back to [`eval_str_with_vars_and_ctx`](eval_str_with_vars_and_ctx),
+/// so the result is always the same as the result of that function.
+///
+/// # Example
+///
+/// ```
+/// use rusty_yard::evaluator::eval_str_fast;
+/// use rusty_yard::Ctx;
+/// use std::collections::HashMap;
+///
+/// let ctx = Ctx::default();
+/// let mut vars = HashMap::new();
+/// vars.insert("a".to_owned(), 3.0);
+/// vars.insert("b".to_owned(), 4.0);
+/// assert_eq!(eval_str_fast("a * b + 1", &mut vars, &ctx), Ok(13.0));
+/// // functions are evaluated by the general pipeline
+/// assert_eq!(eval_str_fast("max(a, b)", &mut vars, &ctx), Ok(4.0));
+/// ```
+pub fn eval_str_fast(input: &str, variables: &mut HashMap<String, f64>, ctx: &Ctx) -> Result {
+    match eval_single_pass(input, variables, ctx) {
+        Some(result) => Ok(result),
+        None => eval_str_with_vars_and_ctx(input, variables, ctx),
+    }
+}
+
+/// The maximum depth of the stacks used by [`eval_single_pass`](eval_single_pass).
+const SINGLE_PASS_STACK_SIZE: usize = 64;
+
+#[derive(Debug, Clone, Copy)]
+enum SinglePassOp<'ctx> {
+    LeftParen,
+    BiOp(&'ctx BiOp),
+    UOp(&'ctx UOp),
+}
+
+/// Stack that lives on the stack.
+struct FixedStack<T, const N: usize> {
+    items: [Option<T>; N],
+    len: usize,
+}
+
+impl<T: Copy, const N: usize> FixedStack<T, N> {
+    fn new() -> Self {
+        Self {
+            items: [None; N],
+            len: 0,
+        }
+    }
+
+    /// Returns [`None`](std::option::Option::None) if the stack is full.
+    fn push(&mut self, item: T) -> Option<()> {
+        *self.items.get_mut(self.len)? = Some(item);
+        self.len += 1;
+        Some(())
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        self.len = self.len.checked_sub(1)?;
+        self.items[self.len].take()
+    }
+
+    fn last(&self) -> Option<T> {
+        self.items[self.len.checked_sub(1)?]
+    }
+}
+
+/// Tokenizes, parses and evaluates the input at the same time.
+///
+/// Returns [`None`](std::option::Option::None) when the input cannot be handled by this function,
+/// this includes all erroneous inputs, so that the errors are produced by the general pipeline.
+fn eval_single_pass(input: &str, variables: &HashMap<String, f64>, ctx: &Ctx) -> Option<f64> {
+    use ParseState::*;
+    // macros can match anything, passes can rewrite anything, limits are checked by the general pipeline
+    if !ctx.macros.is_empty() || !ctx.passes.is_empty() || ctx.limits != Limits::default() {
+        return None;
+    }
+    // the percent depends on what follows it and on the operator before it
+    if ctx.tokenize_options != TokenizeOptions::default()
+        || ctx.percent_mode != PercentMode::default()
+    {
+        return None;
+    }
+    if ctx.comments && (input.contains('#') || input.contains("/*")) {
+        return None;
+    }
+    let mut values = FixedStack::<f64, SINGLE_PASS_STACK_SIZE>::new();
+    let mut operators = FixedStack::<SinglePassOp, SINGLE_PASS_STACK_SIZE>::new();
+    let mut parse_state = Expression;
+    let mut text = &input[skip_whitespace(input)..];
+    while !text.is_empty() {
+        let consumed = if text.starts_with('(') {
+            if parse_state != Expression {
+                return None;
+            }
+            operators.push(SinglePassOp::LeftParen)?;
+            '('.len_utf8()
+        } else if text.starts_with(')') {
+            if parse_state != Operator {
+                return None;
+            }
+            loop {
+                match operators.pop()? {
+                    SinglePassOp::LeftParen => break,
+                    op => apply_single_pass_op(&mut values, op)?,
+                }
+            }
+            ')'.len_utf8()
+        } else if let Some(Match(num, c)) = match_number(text) {
+            if parse_state != Expression {
+                return None;
+            }
+            parse_state = Operator;
+            values.push(num)?;
+            c
+        } else if text.starts_with(['`', '[', '"']) {
+            // quoted identifiers and strings are left to the general pipeline
+            return None;
+        } else {
+            let Match(id, c) = match_op(text, ctx).or_else(|| match_id(text, ctx))?;
+            if ctx.postfix_op(id).is_some() {
+                return None;
+            }
+            let u_op = ctx.u_op(id);
+            if let (Expression, Some(u_op)) = (parse_state, u_op) {
+                operators.push(SinglePassOp::UOp(u_op))?;
+            } else if let Some(bi_op) = ctx.bi_op(id) {
+                if parse_state != Operator {
+                    return None;
+                }
+                while let Some(top_of_stack) = operators.last() {
+                    match top_of_stack {
+                        // same as the parser: the unary operator extends over the binary operators with the higher precedence
+                        SinglePassOp::UOp(u_op)
+                            if u_op
+                                .precedence
+                                .is_none_or(|precedence| precedence >= bi_op.precedence) => {}
+                        SinglePassOp::BiOp(op)
+                            if op.precedence > bi_op.precedence
+                                || (op.precedence == bi_op.precedence
+                                    && op.associativity == Associativity::LEFT) => {}
+                        _ => break,
+                    }
+                    apply_single_pass_op(&mut values, operators.pop()?)?;
+                }
+                parse_state = Expression;
+                operators.push(SinglePassOp::BiOp(bi_op))?;
+            } else if parse_state == Expression && ctx.func(id).is_none() {
+                parse_state = Operator;
+                let value = match ctx.constant(id) {
+                    Some(constant) => constant.value,
+                    None => *variables.get(id)?,
+                };
+                values.push(value)?;
+            } else {
+                return None;
+            }
+            c
+        };
+        text = &text[consumed..];
+        text = &text[skip_whitespace(text)..];
+    }
+    if parse_state != Operator {
+        return None;
+    }
+    while let Some(op) = operators.pop() {
+        apply_single_pass_op(&mut values, op)?;
+    }
+    values.pop()
+}
+
+fn apply_single_pass_op<const N: usize>(
+    values: &mut FixedStack<f64, N>,
+    op: SinglePassOp,
+) -> Option<()> {
+    match op {
+        SinglePassOp::LeftParen => None,
+        SinglePassOp::UOp(op) => {
+            let operand = values.pop()?;
+            values.push((op.func)(operand))
+        }
+        SinglePassOp::BiOp(op) => {
+            let right = values.pop()?;
+            let left = values.pop()?;
+            values.push(op.apply(left, right))
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::functions::{FN_SUB, FN_SUM};
-    use crate::operators::{binary::PLUS as B_PLUS, unary::PLUS as U_PLUS};
+    use crate::operators::binary::{self, PLUS as B_PLUS};
+    use crate::operators::unary::PLUS as U_PLUS;
+    use crate::parser::parse_str;
 
     use super::ParserToken::*;
     use super::*;
@@ -321,15 +1356,509 @@ mod tests {
                 vec![Num(2.0), Num(1.0), Func(&FN_SUB, 1)],
                 Err(Error::ArityMismatch {
                     id: "sub".to_owned(),
-                    expected: 2,
+                    expected: 2.into(),
                     actual: 1,
                 }),
             ),
         ];
 
         for (input, expected) in input_expected {
-            let result = eval_with_vars(&input, &mut vars);
+            let result = eval_with_vars(input, &mut vars);
             assert_eq!(result, *expected, "input {:?}", input);
         }
     }
+
+    #[test]
+    fn test_eval_str_fast() {
+        let mut vars = HashMap::new();
+        vars.insert("a".into(), 2.0);
+        vars.insert("b".into(), 3.0);
+        vars.insert("π".into(), 4.0);
+        let ctx = Ctx::default();
+        let deeply_nested = "(".repeat(100) + "1" + &")".repeat(100);
+        let inputs = &[
+            "a * b + 1",
+            "-a ^ 2",
+            "2 ^ 3 ^ 2",
+            "(a + b) * (a - b)",
+            "--a - -b",
+            "max(a, b) + 1",
+            deeply_nested.as_str(),
+            "",
+            "()",
+            "a +",
+            "a b",
+            "(a + b",
+            "a + b)",
+            "c + 1",
+            "sub(1)",
+            "π * a",
+            "a € b",
+            "1e3 * a + 2.5E-1",
+            "2e + a",
+            "pi * a + e",
+            "π * pi",
+        ];
+        for input in inputs {
+            let expected = eval_str_with_vars_and_ctx(input, &mut vars, &ctx);
+            let result = eval_str_fast(input, &mut vars, &ctx);
+            assert_eq!(result, expected, "input {:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_eval_str_fast_unary_precedence() {
+        let mut vars = HashMap::new();
+        vars.insert("a".into(), 3.0);
+        let mut ctx = Ctx::default();
+        for op in ctx.u_ops.iter_mut().filter(|op| op.token == "-") {
+            op.precedence = Some(binary::MULTIPLY.precedence);
+        }
+        let inputs = &[
+            "-2 ^ 2",
+            "-a ^ 2 + 1",
+            "-a * 2",
+            "2 ^ -a",
+            "--a ^ 2",
+            "-(2 ^ 2) - -a",
+        ];
+        for input in inputs {
+            let expected = eval_str_with_vars_and_ctx(input, &mut vars, &ctx);
+            let result = eval_str_fast(input, &mut vars, &ctx);
+            assert_eq!(result, expected, "input {:?}", input);
+        }
+        assert_eq!(eval_str_fast("-2 ^ 2", &mut vars, &ctx), Ok(-4.0));
+    }
+
+    #[test]
+    fn test_eval_str_fast_percent() {
+        let mut vars = HashMap::new();
+        vars.insert("a".into(), 200.0);
+        let inputs = &["10% + 1", "a + 10%", "7 % 3", "a % -3", "(10%) * a"];
+        for mode in &[PercentMode::Plain, PercentMode::Calculator] {
+            let ctx = Ctx {
+                percent_mode: *mode,
+                ..Ctx::default()
+            };
+            for input in inputs {
+                let expected = eval_str_with_vars_and_ctx(input, &mut vars, &ctx);
+                let result = eval_str_fast(input, &mut vars, &ctx);
+                assert_eq!(result, expected, "input {:?} in {:?} mode", input, mode);
+            }
+        }
+    }
+
+    #[test]
+    fn test_truthiness() {
+        let nan = f64::NAN;
+        let input_expected = &[
+            (Truthiness::NonZero, 1.0, Ok(true)),
+            (Truthiness::NonZero, -0.5, Ok(true)),
+            (Truthiness::NonZero, 0.0, Ok(false)),
+            (Truthiness::NonZero, -0.0, Ok(false)),
+            (Truthiness::NonZero, nan, Ok(false)),
+            (Truthiness::Strict, 1.0, Ok(true)),
+            (Truthiness::Strict, 0.0, Ok(false)),
+            (Truthiness::Strict, 0.5, Err(Error::NotBoolean(0.5))),
+            (Truthiness::Threshold(0.5), 0.5, Ok(true)),
+            (Truthiness::Threshold(0.5), 0.4, Ok(false)),
+            (Truthiness::Threshold(0.5), nan, Ok(false)),
+        ];
+        for (truthiness, value, expected) in input_expected {
+            let result = truthiness.apply(*value);
+            assert_eq!(result, *expected, "input was {:?} {}", truthiness, value);
+        }
+    }
+
+    #[test]
+    fn test_eval_str_bool() {
+        let ctx = Ctx::default();
+        let mut vars = HashMap::new();
+        vars.insert("a".to_owned(), 2.0);
+        let truthiness = Truthiness::default();
+        assert_eq!(
+            eval_str_bool("a - 2", &mut vars, &ctx, truthiness),
+            Ok(false)
+        );
+        assert_eq!(
+            eval_str_bool("a * 2", &mut vars, &ctx, truthiness),
+            Ok(true)
+        );
+        assert_eq!(
+            eval_str_bool("b", &mut vars, &ctx, truthiness),
+            Err(Error::VarNotFound("b".to_owned(), None))
+        );
+    }
+
+    #[test]
+    fn test_eval_str_strict_math() {
+        let domain = |func: &str, args: &[f64], position| Error::DomainError {
+            func: func.to_owned(),
+            args: args.to_vec(),
+            position,
+        };
+        let input_expected = &[
+            ("1 / 2", Ok(0.5)),
+            (
+                "1 / 0",
+                Err(Error::DivisionByZero {
+                    func: "/".to_owned(),
+                    position: 2,
+                }),
+            ),
+            (
+                "ln(0)",
+                Err(Error::DivisionByZero {
+                    func: "ln".to_owned(),
+                    position: 1,
+                }),
+            ),
+            (
+                "0 ^ -1",
+                Err(Error::DivisionByZero {
+                    func: "^".to_owned(),
+                    position: 3,
+                }),
+            ),
+            ("0 / 0", Err(domain("/", &[0.0, 0.0], 2))),
+            ("1 + sqrt(-2)", Err(domain("sqrt", &[-2.0], 3))),
+            ("ln(-1)", Err(domain("ln", &[-1.0], 2))),
+            (
+                "10 ^ 400",
+                Err(Error::Overflow {
+                    func: "^".to_owned(),
+                    position: 2,
+                }),
+            ),
+            (
+                "exp(1000)",
+                Err(Error::Overflow {
+                    func: "exp".to_owned(),
+                    position: 1,
+                }),
+            ),
+            ("inf - 1", Ok(f64::INFINITY)),
+            ("1 / inf", Ok(0.0)),
+        ];
+        let ctx = Ctx::default();
+        let options = EvalOptions {
+            strict_math: true,
+            ..EvalOptions::default()
+        };
+        for (input, expected) in input_expected {
+            let result = eval_str_with_options(input, &mut HashMap::new(), &ctx, options.clone());
+            assert_eq!(result, *expected, "input was {}", input);
+        }
+        // nan is propagated
+        let result = eval_str_with_options("nan + 1", &mut HashMap::new(), &ctx, options);
+        assert!(result.unwrap().is_nan());
+        // the check is opt-in
+        let result =
+            eval_str_with_options("1 / 0", &mut HashMap::new(), &ctx, EvalOptions::default());
+        assert_eq!(result, Ok(f64::INFINITY));
+    }
+
+    #[test]
+    fn test_eval_cancelled() {
+        let cancel = CancelToken::new();
+        let mut ctx = Ctx::default();
+        let stop = cancel.clone();
+        ctx.fns
+            .push(crate::functions::Func::new("stop", Some(0), move |_| {
+                stop.cancel();
+                1.0
+            }));
+        let terms = vec!["1"; CANCEL_CHECK_INTERVAL].join(" + ");
+        let options = EvalOptions {
+            cancel: Some(cancel.clone()),
+            ..EvalOptions::default()
+        };
+        let input_expected = &[
+            (terms.clone(), Ok(CANCEL_CHECK_INTERVAL as f64)),
+            // the rest of the expression is not evaluated
+            (format!("stop() + {}", terms), Err(Error::Cancelled)),
+            (terms.clone(), Err(Error::Cancelled)),
+        ];
+        for (input, expected) in input_expected {
+            let result = eval_str_with_options(input, &mut HashMap::new(), &ctx, options.clone());
+            assert_eq!(result, *expected, "input was {}", input);
+        }
+        assert!(cancel.is_cancelled());
+        assert_ne!(cancel, CancelToken::new());
+    }
+
+    #[test]
+    fn test_var_not_found_suggestions() {
+        let ctx = Ctx::default();
+        let mut vars = HashMap::new();
+        vars.insert("radius".to_owned(), 2.0);
+        let not_found = |id: &str, suggestion: Option<&str>| {
+            Err(Error::VarNotFound(
+                id.to_owned(),
+                suggestion.map(str::to_owned),
+            ))
+        };
+        let input_expected = &[
+            ("2 * pie", not_found("pie", Some("pi"))),
+            ("raduis ^ 2", not_found("raduis", Some("radius"))),
+            ("sqr * 2", not_found("sqr", Some("sqrt"))),
+            ("r * 2", not_found("r", None)),
+            ("height", not_found("height", None)),
+            (
+                "sinn(1)",
+                Err(Error::ParserError(parser::Error::UnknownFunction(
+                    "sinn".to_owned(),
+                    Some("sin".to_owned()),
+                ))),
+            ),
+        ];
+        for (input, expected) in input_expected {
+            let result = eval_str_with_vars_and_ctx(input, &mut vars, &ctx);
+            assert_eq!(result, *expected, "input was {}", input);
+        }
+        assert_eq!(
+            not_found("pie", Some("pi")).unwrap_err().to_string(),
+            "Variable not found: pie. Did you mean pi?"
+        );
+        assert_eq!(
+            not_found("r", None).unwrap_err().to_string(),
+            "Variable not found: r"
+        );
+    }
+
+    #[test]
+    fn test_eval_single_pass_falls_back() {
+        let vars = HashMap::new();
+        let ctx = Ctx::default();
+        assert_eq!(eval_single_pass("1 + 2 * 3", &vars, &ctx), Some(7.0));
+        assert_eq!(eval_single_pass("sum(1, 2)", &vars, &ctx), None);
+        assert_eq!(eval_single_pass("a", &vars, &ctx), None);
+        let macro_ctx = Ctx::default_with_macros();
+        assert_eq!(eval_single_pass("1 + 2", &vars, &macro_ctx), None);
+    }
+
+    #[test]
+    fn test_eval_traced() {
+        let ctx = Ctx::default_with_macros();
+        let mut vars = HashMap::new();
+        let input_expected = &[
+            (
+                "-2 ^ 2",
+                vec!["2 -> 2", "-(2) -> -2", "2 -> 2", "^(-2, 2) -> 4"],
+            ),
+            (
+                "max(1, a = 3)",
+                vec!["1 -> 1", "3 -> 3", "=(3) -> 3", "max(1, 3) -> 3"],
+            ),
+            ("a + b", vec!["a -> 3"]),
+        ];
+        for (input, expected) in input_expected {
+            let tokens = parse_str(input, &ctx).unwrap();
+            let mut trace: Vec<Step> = Vec::new();
+            let _ = eval_traced(&tokens, &mut vars, &ctx, &mut trace);
+            let steps: Vec<_> = trace.iter().map(Step::to_string).collect();
+            assert_eq!(steps, *expected, "input was {}", input);
+        }
+        let tokens = parse_str("2 * 3", &ctx).unwrap();
+        let mut trace = Vec::new();
+        let result = eval_traced(&tokens, &mut vars, &ctx, &mut trace);
+        assert_eq!(result, eval_with(&tokens, &mut vars, &ctx));
+        assert_eq!(
+            trace.last(),
+            Some(&Step {
+                position: 2,
+                token: "*".to_owned(),
+                operands: vec![2.0, 3.0],
+                result: 6.0,
+            })
+        );
+    }
+
+    #[test]
+    fn test_eval_tokenize_options() {
+        use crate::tokenizer::DecimalSeparator;
+
+        let mut ctx = Ctx {
+            tokenize_options: TokenizeOptions {
+                decimal_separator: DecimalSeparator::Comma,
+                case_insensitive: true,
+            },
+            ..Ctx::default_with_macros()
+        };
+        ctx.consts.push(crate::constants::Const {
+            token: "E".to_owned(),
+            value: 10.0,
+        });
+        let mut vars = HashMap::new();
+        vars.insert("X".to_owned(), 2.0);
+        let input_expected = &[
+            ("MAX(1,5; 2,5)", Ok(2.5)),
+            ("Sqrt(X * 2) + 0,5", Ok(2.5)),
+            ("E + e", Ok(10.0 + std::f64::consts::E)),
+            ("if(1 > 0; 1,5; 2)", Ok(1.5)),
+            ("let y = 0,5 in y * X", Ok(1.0)),
+            ("x", Err(Error::VarNotFound("x".to_owned(), None))),
+        ];
+        for (input, expected) in input_expected {
+            let result = eval_str_with_vars_and_ctx(input, &mut vars, &ctx);
+            assert_eq!(result, *expected, "input was {}", input);
+        }
+        // the long inputs find the tokens the same way
+        let long = vec!["PI"; 40].join(" + ");
+        let result = eval_str_with_vars_and_ctx(&long, &mut vars, &ctx).unwrap();
+        assert!((result - 40.0 * std::f64::consts::PI).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_eval_strings() {
+        use crate::functions::Func;
+
+        let mut ctx = Ctx {
+            strings: true,
+            ..Ctx::default()
+        };
+        ctx.fns.push(Func::strings(
+            "lookup",
+            Some(2),
+            |strings, args| match strings {
+                ["steel"] => Ok(7850.0 * args[0]),
+                [material] => Err(format!("unknown material {}", material)),
+                _ => Err("the material must be the string".to_owned()),
+            },
+        ));
+        let input_expected = &[
+            (r#"lookup("steel", 2) + 1"#, Ok(15701.0)),
+            (r#"lookup("steel", 1 + 1)"#, Ok(15700.0)),
+            (
+                r#"lookup("wood", 1)"#,
+                Err(Error::FunctionError {
+                    id: "lookup".to_owned(),
+                    message: "unknown material wood".to_owned(),
+                }),
+            ),
+            (
+                "lookup(1, 1)",
+                Err(Error::FunctionError {
+                    id: "lookup".to_owned(),
+                    message: "the material must be the string".to_owned(),
+                }),
+            ),
+            (
+                r#"lookup("steel" + 1, 1)"#,
+                Err(Error::UnexpectedString("steel".to_owned())),
+            ),
+            (
+                r#"max("a", 1)"#,
+                Err(Error::UnexpectedString("a".to_owned())),
+            ),
+        ];
+        let mut vars = HashMap::new();
+        for (input, expected) in input_expected {
+            let result = eval_str_with_vars_and_ctx(input, &mut vars, &ctx);
+            assert_eq!(result, *expected, "input was {}", input);
+        }
+        assert_eq!(eval_single_pass(r#"lookup("steel", 1)"#, &vars, &ctx), None);
+    }
+
+    #[test]
+    fn test_eval_batch() {
+        let ctx = Ctx::default_with_macros();
+        let tokens = parse_str("b = a * 2", &ctx).unwrap();
+        let mut rows: Vec<HashMap<String, f64>> = [1.0, 2.5]
+            .iter()
+            .map(|a| HashMap::from([("a".to_owned(), *a)]))
+            .collect();
+        rows.push(HashMap::new());
+        let results = eval_batch(&tokens, &mut rows, &ctx);
+        let expected = vec![
+            Ok(2.0),
+            Ok(5.0),
+            Err(Error::VarNotFound("a".to_owned(), None)),
+        ];
+        assert_eq!(results, expected);
+        // the rows are borrowed, so the assignments are visible
+        assert_eq!(rows[1].get("b"), Some(&5.0));
+        assert_eq!(
+            eval_batch(&tokens, Vec::<HashMap<_, _>>::new(), &ctx),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_tokens_are_thread_safe() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<ParserToken>();
+        assert_send_sync::<Ctx>();
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_eval_batch_par() {
+        use rayon::prelude::*;
+
+        let ctx = Ctx::default_with_macros();
+        let tokens = parse_str("b = a * 2", &ctx).unwrap();
+        let mut rows: Vec<HashMap<String, f64>> = (0..100)
+            .map(|a| HashMap::from([("a".to_owned(), a as f64)]))
+            .collect();
+        rows.push(HashMap::new());
+        let results = eval_batch_par(&tokens, rows.par_iter_mut(), &ctx);
+        assert_eq!(results, eval_batch(&tokens, rows.clone(), &ctx));
+        assert_eq!(results[50], Ok(100.0));
+        assert_eq!(results[100], Err(Error::VarNotFound("a".to_owned(), None)));
+        assert_eq!(rows[50].get("b"), Some(&100.0));
+    }
+
+    #[test]
+    fn test_eval_readonly() {
+        let mut ctx = Ctx::default_with_macros();
+        ctx.macros.push(Box::new(crate::macros::user_fn::UserFn {
+            token: "inc".to_owned(),
+            params: vec!["v".to_owned()],
+            body: "v = v + 1".to_owned(),
+        }));
+        let vars = HashMap::from([("a".to_owned(), 2.0)]);
+        let read_only = |name: &str| Err(Error::ReadOnlyVariables(name.to_owned()));
+        let input_expected = &[
+            ("a * 3", Ok(6.0)),
+            ("if(a, a, 0)", Ok(2.0)),
+            ("let t = a in t = t * 2", Ok(4.0)),
+            ("inc(a)", Ok(3.0)),
+            ("b", Err(Error::VarNotFound("b".to_owned(), None))),
+            ("a = 1", read_only("=")),
+            ("if(a, b = 1, 0)", read_only("=")),
+            ("let t = a in a = t", read_only("=")),
+        ];
+        std::thread::scope(|scope| {
+            for (input, expected) in input_expected {
+                let (vars, ctx) = (&vars, &ctx);
+                scope.spawn(move || {
+                    let tokens = parse_str(input, ctx).unwrap();
+                    let result = eval_readonly(&tokens, vars, ctx);
+                    assert_eq!(result, *expected, "input was {}", input);
+                });
+            }
+        });
+        assert_eq!(vars.len(), 1);
+    }
+
+    #[test]
+    fn test_eval_over() {
+        let ctx = Ctx::default_with_macros();
+        let mut vars = HashMap::new();
+        vars.insert("x".to_owned(), 100.0);
+        vars.insert("k".to_owned(), 3.0);
+        let input_expected = &[
+            ("x * k", vec![Ok(0.0), Ok(-3.0), Ok(1.5)]),
+            ("k", vec![Ok(3.0), Ok(3.0), Ok(3.0)]),
+            ("x = x + 1", vec![Ok(1.0), Ok(0.0), Ok(1.5)]),
+            ("x - k", vec![Ok(-3.0), Ok(-4.0), Ok(-2.5)]),
+        ];
+        for (input, expected) in input_expected {
+            let tokens = parse_str(input, &ctx).unwrap();
+            let results = eval_over(&tokens, "x", &[0.0, -1.0, 0.5], &mut vars, &ctx);
+            assert_eq!(results, *expected, "input was {}", input);
+        }
+        assert_eq!(vars.get("x"), Some(&100.0));
+    }
 }
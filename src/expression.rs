@@ -0,0 +1,345 @@
+//! Provides [`Expression`](Expression), the parsed expression together with its [context](crate::Ctx).
+//!
+//! The expression is parsed once and can be evaluated many times with different variables.
+//!
+//! # Example
+//!
+//! ```
+//! use std::collections::HashMap;
+//! use rusty_yard::expression::Expression;
+//! use rusty_yard::Ctx;
+//!
+//! let ctx = Ctx::default();
+//! let expr = Expression::parse("a * b + 1", &ctx).unwrap();
+//! assert_eq!(expr.variables().into_iter().collect::<Vec<_>>(), vec!["a", "b"]);
+//!
+//! let mut vars = HashMap::new();
+//! vars.insert("a".to_owned(), 2.0);
+//! vars.insert("b".to_owned(), 3.0);
+//! assert_eq!(expr.eval(&mut vars), Ok(7.0));
+//! ```
+//!
+//! [`CompiledExpr`](CompiledExpr) is the same, but it owns the parsed expression,
+//! so it can outlive the input and be stored in caches.
+use std::collections::BTreeSet;
+use std::hash::{Hash, Hasher};
+
+use thiserror::Error;
+
+use crate::analysis::{self, References, Usage};
+use crate::evaluator::{self, eval_token, eval_with};
+use crate::functions::Func;
+use crate::macros::ParsedMacro;
+use crate::operators::{BiOp, UOp};
+use crate::parser::{self, parse_str, ParserToken};
+use crate::variables::VariableResolver;
+use crate::Ctx;
+
+/// The parsed expression.
+///
+/// It borrows the input it was parsed from and the context.
+///
+/// Expressions are compared and hashed structurally by their [`tokens`](Expression::tokens),
+/// see the [`PartialEq`](ParserToken#impl-PartialEq-for-ParserToken<'_,+'_>) of the parser token,
+/// so they can be deduplicated and used as keys of maps.
+#[derive(Debug)]
+pub struct Expression<'a, 'ctx> {
+    tokens: Vec<ParserToken<'a, 'ctx>>,
+    ctx: &'ctx Ctx,
+}
+
+impl<'a, 'ctx> Expression<'a, 'ctx> {
+    /// Parses the `input` using the `ctx`.
+    pub fn parse(input: &'a str, ctx: &'ctx Ctx) -> Result<Self, parser::Error> {
+        let tokens = parse_str(input, ctx)?;
+        Ok(Self { tokens, ctx })
+    }
+
+    /// Creates the expression from the `tokens` in reverse polish notation.
+    ///
+    /// The tokens should be parsed using the same `ctx`.
+    pub fn from_tokens(tokens: Vec<ParserToken<'a, 'ctx>>, ctx: &'ctx Ctx) -> Self {
+        Self { tokens, ctx }
+    }
+
+    /// The tokens of the expression in reverse polish notation.
+    pub fn tokens(&self) -> &[ParserToken<'a, 'ctx>] {
+        &self.tokens
+    }
+
+    /// The context of the expression.
+    pub fn ctx(&self) -> &'ctx Ctx {
+        self.ctx
+    }
+
+    /// Evaluates the expression with the `variables`.
+    pub fn eval(&self, variables: &mut dyn VariableResolver) -> evaluator::Result {
+        eval_with(&self.tokens, variables, self.ctx)
+    }
+
+    /// Returns the names of the variables the expression reads, including the reads inside the macros.
+    ///
+    /// See [`analysis::variables_of`](crate::analysis::variables_of).
+    pub fn variables(&self) -> BTreeSet<String> {
+        analysis::variables_of(&self.tokens, self.ctx)
+    }
+
+    /// Returns every function, operator and macro the expression uses.
+    ///
+    /// See [`analysis::references`](crate::analysis::references).
+    pub fn references(&self) -> References {
+        analysis::references(&self.tokens, self.ctx)
+    }
+
+    /// Copies the expression into [`CompiledExpr`](CompiledExpr) that doesn't borrow the input.
+    ///
+    /// Fails with [`Error::NotOwned`](Error::NotOwned) if one of the macros of the expression
+    /// doesn't implement [`to_owned_macro`](crate::macros::ParsedMacro::to_owned_macro).
+    pub fn to_compiled(&self) -> Result<CompiledExpr<'ctx>, Error> {
+        let tokens = self
+            .tokens
+            .iter()
+            .map(|token| match *token {
+                ParserToken::Num(n) => Ok(CompiledToken::Num(n)),
+                ParserToken::Id(id) => Ok(CompiledToken::Id(id.to_owned())),
+                ParserToken::Str(s) => Ok(CompiledToken::Str(s.to_owned())),
+                ParserToken::UOp(op) => Ok(CompiledToken::UOp(op)),
+                ParserToken::BiOp(op) => Ok(CompiledToken::BiOp(op)),
+                ParserToken::Func(func, n_args) => Ok(CompiledToken::Func(func, n_args)),
+                ParserToken::Macro(ref m) => m
+                    .to_owned_macro()
+                    .map(CompiledToken::Macro)
+                    .ok_or_else(|| Error::NotOwned(m.name().to_owned())),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(CompiledExpr {
+            tokens,
+            ctx: self.ctx,
+        })
+    }
+}
+
+impl PartialEq for Expression<'_, '_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.tokens == other.tokens
+    }
+}
+
+impl Eq for Expression<'_, '_> {}
+
+impl Hash for Expression<'_, '_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.tokens.hash(state);
+    }
+}
+
+/// Represents the error that can occur when compiling the expression.
+#[derive(Debug, Error, PartialEq)]
+pub enum Error {
+    /// The input could not be parsed.
+    #[error("Parser: {0}")]
+    Parser(#[from] parser::Error),
+    /// The macro can't be copied out of the input, contains the [`name`](crate::macros::ParsedMacro::name) of the macro.
+    #[error("Macro {0} borrows the input")]
+    NotOwned(String),
+}
+
+#[derive(Debug)]
+enum CompiledToken<'ctx> {
+    Num(f64),
+    Id(String),
+    Str(String),
+    UOp(&'ctx UOp),
+    BiOp(&'ctx BiOp),
+    Func(&'ctx Func, usize),
+    Macro(Box<dyn ParsedMacro>),
+}
+
+/// The compiled expression.
+///
+/// Unlike [`Expression`](Expression) it owns its tokens and only borrows the context,
+/// so it can be evaluated many times without keeping the input around,
+/// e.g. when plotting the function or running the simulation.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use rusty_yard::expression::CompiledExpr;
+/// use rusty_yard::Ctx;
+///
+/// let ctx = Ctx::default();
+/// let expr = CompiledExpr::compile(&format!("x ^ {}", 2), &ctx).unwrap();
+/// let mut vars = HashMap::new();
+/// let points = (0..4)
+///     .map(|x| {
+///         vars.insert("x".to_owned(), x as f64);
+///         expr.eval(&mut vars).unwrap()
+///     })
+///     .collect::<Vec<_>>();
+/// assert_eq!(points, vec![0.0, 1.0, 4.0, 9.0]);
+/// ```
+#[derive(Debug)]
+pub struct CompiledExpr<'ctx> {
+    tokens: Vec<CompiledToken<'ctx>>,
+    ctx: &'ctx Ctx,
+}
+
+impl<'ctx> CompiledExpr<'ctx> {
+    /// Parses the `input` using the `ctx` and compiles it.
+    ///
+    /// See [`Expression::to_compiled`](Expression::to_compiled).
+    pub fn compile(input: &str, ctx: &'ctx Ctx) -> Result<Self, Error> {
+        Expression::parse(input, ctx)?.to_compiled()
+    }
+
+    /// The context of the expression.
+    pub fn ctx(&self) -> &'ctx Ctx {
+        self.ctx
+    }
+
+    /// Evaluates the expression with the `variables`.
+    pub fn eval(&self, variables: &mut dyn VariableResolver) -> evaluator::Result {
+        let mut eval_stack = Vec::new();
+        for (position, token) in self.tokens.iter().enumerate() {
+            let token = match *token {
+                CompiledToken::Num(n) => ParserToken::Num(n),
+                CompiledToken::Id(ref id) => ParserToken::Id(id),
+                CompiledToken::Str(ref s) => ParserToken::Str(s),
+                CompiledToken::UOp(op) => ParserToken::UOp(op),
+                CompiledToken::BiOp(op) => ParserToken::BiOp(op),
+                CompiledToken::Func(func, n_args) => ParserToken::Func(func, n_args),
+                CompiledToken::Macro(ref m) => {
+                    m.eval(&mut eval_stack, variables, self.ctx)?;
+                    continue;
+                }
+            };
+            eval_token(&token, position, variables, self.ctx, &mut eval_stack)?;
+        }
+        eval_stack.pop().ok_or(evaluator::Error::Other)
+    }
+
+    /// Returns the names of the variables the expression reads, including the reads inside the macros.
+    ///
+    /// See [`analysis::variables_of`](crate::analysis::variables_of).
+    pub fn variables(&self) -> BTreeSet<String> {
+        let mut usage = Usage::default();
+        let mut bound = BTreeSet::new();
+        for token in &self.tokens {
+            match token {
+                CompiledToken::Id(id) => usage.read(id, &bound),
+                CompiledToken::Macro(m) => usage.collect_macro(m.as_ref(), self.ctx, &mut bound),
+                _ => {}
+            }
+        }
+        usage.variables
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::macros::user_fn::UserFn;
+
+    fn get_ctx() -> Ctx {
+        let mut ctx = Ctx::default_with_macros();
+        ctx.macros.push(Box::new(UserFn {
+            token: "sq".to_owned(),
+            params: vec!["x".to_owned()],
+            body: "x * x".to_owned(),
+        }));
+        ctx
+    }
+
+    #[test]
+    fn test_compiled_expr_eval() {
+        let input_expected = &[
+            ("1 + 2 * 3", Ok(7.0)),
+            ("-a + max(a, b)", Ok(1.0)),
+            ("sq(a + 1) - b", Ok(6.0)),
+            ("c = a * b", Ok(6.0)),
+            (
+                "d",
+                Err(evaluator::Error::VarNotFound("d".to_owned(), None)),
+            ),
+        ];
+        let ctx = get_ctx();
+        for (input, expected) in input_expected {
+            // the compiled expression outlives its input
+            let compiled = {
+                let input = input.to_string();
+                CompiledExpr::compile(&input, &ctx).unwrap()
+            };
+            let mut vars = HashMap::new();
+            vars.insert("a".to_owned(), 2.0);
+            vars.insert("b".to_owned(), 3.0);
+            let mut expected_vars = vars.clone();
+            let expected_result = eval_with(
+                Expression::parse(input, &ctx).unwrap().tokens(),
+                &mut expected_vars,
+                &ctx,
+            );
+            assert_eq!(expected_result, *expected, "input was {}", input);
+            assert_eq!(compiled.eval(&mut vars), *expected, "input was {}", input);
+            assert_eq!(vars, expected_vars, "input was {}", input);
+        }
+    }
+
+    #[test]
+    fn test_compiled_expr_variables() {
+        let ctx = get_ctx();
+        let input_expected: &[(&str, &[&str])] = &[
+            ("c = a * b + a", &["a", "b"]),
+            ("if(c, a, b + 1)", &["a", "b", "c"]),
+            ("let t = a * 2 in t + b", &["a", "b"]),
+            ("(d = 2) * d + sq(g)", &["g"]),
+        ];
+        for (input, expected) in input_expected {
+            let compiled = CompiledExpr::compile(input, &ctx).unwrap();
+            let expression = Expression::parse(input, &ctx).unwrap();
+            let result: Vec<_> = compiled.variables().into_iter().collect();
+            assert_eq!(result, *expected, "input was {}", input);
+            assert_eq!(
+                expression.variables(),
+                compiled.variables(),
+                "input was {}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_compile_errors() {
+        #[derive(Debug)]
+        struct Borrowed;
+
+        impl ParsedMacro for Borrowed {
+            fn eval(
+                &self,
+                eval_stack: &mut Vec<f64>,
+                _variables: &mut dyn VariableResolver,
+                _ctx: &Ctx,
+            ) -> Result<(), evaluator::Error> {
+                eval_stack.push(0.0);
+                Ok(())
+            }
+
+            fn name(&self) -> &str {
+                "borrowed"
+            }
+        }
+
+        let ctx = get_ctx();
+        let expr = Expression::from_tokens(vec![ParserToken::Macro(Box::new(Borrowed))], &ctx);
+        assert_eq!(
+            expr.to_compiled().unwrap_err(),
+            Error::NotOwned("borrowed".to_owned())
+        );
+        assert!(matches!(
+            CompiledExpr::compile("1 +", &ctx),
+            Err(Error::Parser(_))
+        ));
+    }
+}
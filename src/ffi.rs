@@ -0,0 +1,260 @@
+//! C interface of the crate, enabled with the `ffi` feature.
+//!
+//! The C programs create the [context](rusty_yard_ctx_new), [compile](rusty_yard_compile) the expressions once,
+//! set their variables and evaluate them. The functions that can fail return `0` on success and `-1` on failure,
+//! or the null pointer, the message of the error is returned by [`rusty_yard_last_error`](rusty_yard_last_error).
+//!
+//! The header is generated with `cbindgen --config cbindgen.toml --output rusty_yard.h`,
+//! and the library is built with `cargo rustc --release --features ffi --crate-type cdylib`
+//! (or `staticlib`).
+//!
+//! ```c
+//! RustyYardCtx *ctx = rusty_yard_ctx_new();
+//! RustyYardExpr *expr = rusty_yard_compile(ctx, "x ^ 2 + 1");
+//! double result;
+//! rusty_yard_set_variable(expr, "x", 3.0);
+//! if (rusty_yard_eval(expr, &result) != 0) {
+//!     fprintf(stderr, "%s\n", rusty_yard_last_error());
+//! }
+//! rusty_yard_expr_free(expr);
+//! rusty_yard_ctx_free(ctx);
+//! ```
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+
+use crate::evaluator::eval_str_with_vars_and_ctx;
+use crate::expression::CompiledExpr;
+use crate::Ctx;
+
+/// The context of the expressions, see [`Ctx`](crate::Ctx).
+pub struct RustyYardCtx {
+    ctx: Ctx,
+}
+
+/// The compiled expression together with the values of its variables.
+pub struct RustyYardExpr {
+    // borrows the context, that must outlive the expression
+    expr: CompiledExpr<'static>,
+    variables: HashMap<String, f64>,
+}
+
+thread_local! {
+    /// The message of the last error on this thread
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: String) {
+    // the messages don't contain the nul, except the ones quoting the input
+    let message = CString::new(message.replace('\0', "\\0")).unwrap_or_default();
+    LAST_ERROR.with(|error| *error.borrow_mut() = Some(message));
+}
+
+/// Runs `f`, storing its error and turning the panics into errors
+fn status(f: impl FnOnce() -> Result<(), String>) -> c_int {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => 0,
+        Ok(Err(message)) => {
+            set_last_error(message);
+            -1
+        }
+        Err(_) => {
+            set_last_error("panic during the evaluation".to_owned());
+            -1
+        }
+    }
+}
+
+/// Reads the nul terminated string
+unsafe fn read_str<'a>(s: *const c_char) -> Result<&'a str, String> {
+    if s.is_null() {
+        return Err("null string".to_owned());
+    }
+    CStr::from_ptr(s)
+        .to_str()
+        .map_err(|_| "string is not valid UTF-8".to_owned())
+}
+
+/// Creates the [default context with macros](crate::Ctx::default_with_macros).
+///
+/// It must be freed with [`rusty_yard_ctx_free`](rusty_yard_ctx_free).
+#[no_mangle]
+pub extern "C" fn rusty_yard_ctx_new() -> *mut RustyYardCtx {
+    Box::into_raw(Box::new(RustyYardCtx {
+        ctx: Ctx::default_with_macros(),
+    }))
+}
+
+/// Frees the context.
+///
+/// # Safety
+///
+/// `ctx` must be created by [`rusty_yard_ctx_new`](rusty_yard_ctx_new) or be null,
+/// the expressions compiled with it must be freed before.
+#[no_mangle]
+pub unsafe extern "C" fn rusty_yard_ctx_free(ctx: *mut RustyYardCtx) {
+    if !ctx.is_null() {
+        drop(Box::from_raw(ctx));
+    }
+}
+
+/// Compiles the `input`, returns the null pointer if it fails.
+///
+/// The expression must be freed with [`rusty_yard_expr_free`](rusty_yard_expr_free).
+///
+/// # Safety
+///
+/// `ctx` must be created by [`rusty_yard_ctx_new`](rusty_yard_ctx_new) and outlive the expression,
+/// `input` must be the nul terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn rusty_yard_compile(
+    ctx: *const RustyYardCtx,
+    input: *const c_char,
+) -> *mut RustyYardExpr {
+    let mut compiled = None;
+    let status = status(|| {
+        let ctx = &ctx.as_ref().ok_or("null context")?.ctx;
+        let expr = CompiledExpr::compile(read_str(input)?, ctx).map_err(|e| e.to_string())?;
+        compiled = Some(RustyYardExpr {
+            expr,
+            variables: HashMap::new(),
+        });
+        Ok(())
+    });
+    match compiled {
+        Some(expr) if status == 0 => Box::into_raw(Box::new(expr)),
+        _ => ptr::null_mut(),
+    }
+}
+
+/// Frees the expression.
+///
+/// # Safety
+///
+/// `expr` must be created by [`rusty_yard_compile`](rusty_yard_compile) or be null.
+#[no_mangle]
+pub unsafe extern "C" fn rusty_yard_expr_free(expr: *mut RustyYardExpr) {
+    if !expr.is_null() {
+        drop(Box::from_raw(expr));
+    }
+}
+
+/// Sets the variable `name` of the expression to the `value`.
+///
+/// # Safety
+///
+/// `expr` must be created by [`rusty_yard_compile`](rusty_yard_compile),
+/// `name` must be the nul terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn rusty_yard_set_variable(
+    expr: *mut RustyYardExpr,
+    name: *const c_char,
+    value: f64,
+) -> c_int {
+    status(|| {
+        let expr = expr.as_mut().ok_or("null expression")?;
+        expr.variables.insert(read_str(name)?.to_owned(), value);
+        Ok(())
+    })
+}
+
+/// Evaluates the expression with its variables, and writes the value to the `result`.
+///
+/// The macros like `=` write their variables to the variables of the expression.
+///
+/// # Safety
+///
+/// `expr` must be created by [`rusty_yard_compile`](rusty_yard_compile),
+/// `result` must point to the writable `double`.
+#[no_mangle]
+pub unsafe extern "C" fn rusty_yard_eval(expr: *mut RustyYardExpr, result: *mut f64) -> c_int {
+    status(|| {
+        let expr = expr.as_mut().ok_or("null expression")?;
+        let result = result.as_mut().ok_or("null result")?;
+        *result = expr
+            .expr
+            .eval(&mut expr.variables)
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    })
+}
+
+/// Evaluates the `input` without the variables, and writes the value to the `result`.
+///
+/// # Safety
+///
+/// `ctx` must be created by [`rusty_yard_ctx_new`](rusty_yard_ctx_new),
+/// `input` must be the nul terminated string, `result` must point to the writable `double`.
+#[no_mangle]
+pub unsafe extern "C" fn rusty_yard_eval_str(
+    ctx: *const RustyYardCtx,
+    input: *const c_char,
+    result: *mut f64,
+) -> c_int {
+    status(|| {
+        let ctx = &ctx.as_ref().ok_or("null context")?.ctx;
+        let result = result.as_mut().ok_or("null result")?;
+        *result = eval_str_with_vars_and_ctx(read_str(input)?, &mut HashMap::new(), ctx)
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    })
+}
+
+/// Returns the message of the last error on the calling thread, or the null pointer if there was none.
+///
+/// The message is valid until the next call that fails on the same thread.
+#[no_mangle]
+pub extern "C" fn rusty_yard_last_error() -> *const c_char {
+    LAST_ERROR.with(|error| {
+        error
+            .borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn last_error() -> String {
+        let message = rusty_yard_last_error();
+        assert!(!message.is_null());
+        unsafe { CStr::from_ptr(message) }
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[test]
+    fn test_ffi() {
+        let ctx = rusty_yard_ctx_new();
+        let input = CString::new("x ^ 2 + y").unwrap();
+        let (x, y) = (CString::new("x").unwrap(), CString::new("y").unwrap());
+        let mut result = 0.0;
+        unsafe {
+            let expr = rusty_yard_compile(ctx, input.as_ptr());
+            assert!(!expr.is_null());
+            assert_eq!(rusty_yard_set_variable(expr, x.as_ptr(), 3.0), 0);
+            assert_eq!(rusty_yard_eval(expr, &mut result), -1);
+            assert_eq!(last_error(), "Variable not found: y");
+            assert_eq!(rusty_yard_set_variable(expr, y.as_ptr(), 1.0), 0);
+            assert_eq!(rusty_yard_eval(expr, &mut result), 0);
+            assert_eq!(result, 10.0);
+            rusty_yard_expr_free(expr);
+
+            let input = CString::new("1 + * 2").unwrap();
+            assert!(rusty_yard_compile(ctx, input.as_ptr()).is_null());
+            assert_eq!(last_error(), "Parser: Expected expression, found operator");
+
+            let input = CString::new("max(1, 2) * 2").unwrap();
+            assert_eq!(rusty_yard_eval_str(ctx, input.as_ptr(), &mut result), 0);
+            assert_eq!(result, 4.0);
+            assert_eq!(rusty_yard_eval_str(ctx, ptr::null(), &mut result), -1);
+            assert_eq!(last_error(), "null string");
+            rusty_yard_ctx_free(ctx);
+        }
+    }
+}
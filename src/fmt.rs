@@ -0,0 +1,213 @@
+//! Provides the helpers to format the results of the evaluation.
+//!
+//! The main type in this module is [`NumberFormat`](NumberFormat).
+//!
+//! # Example
+//!
+//! ```
+//! use rusty_yard::fmt::{NumberFormat, Notation};
+//!
+//! let format = NumberFormat {
+//!     notation: Notation::Fixed(2),
+//!     thousands_separator: Some(','),
+//! };
+//! assert_eq!(format.format(1234567.891), "1,234,567.89");
+//! assert_eq!(NumberFormat::new(Notation::Significant(3)).format(3.14159), "3.14");
+//! assert_eq!(NumberFormat::new(Notation::Scientific(2)).format(1234.5), "1.23e3");
+//! assert_eq!(NumberFormat::new(Notation::Rational).format(0.75), "3/4");
+//! ```
+
+/// The way the number is written.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Notation {
+    /// The shortest representation that is parsed back into the same number, same as `{}`.
+    Auto,
+    /// Fixed number of decimal places.
+    Fixed(usize),
+    /// Number of significant digits, must be greater than 0.
+    Significant(usize),
+    /// Scientific notation with fixed number of decimal places in the mantissa.
+    Scientific(usize),
+    /// Fraction `numerator/denominator` if the number is close enough to one
+    /// with the denominator of at most [`MAX_DENOMINATOR`](MAX_DENOMINATOR), [`Auto`](Notation::Auto) otherwise.
+    Rational,
+}
+
+/// The largest denominator used by [`Notation::Rational`](Notation::Rational).
+pub const MAX_DENOMINATOR: u64 = 1_000_000;
+
+/// Describes how the numbers are formatted.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct NumberFormat {
+    /// The notation of the number.
+    pub notation: Notation,
+    /// The character inserted between the groups of thousands of the integer part, if any.
+    pub thousands_separator: Option<char>,
+}
+
+impl Default for NumberFormat {
+    /// [`Auto`](Notation::Auto) notation without thousands separator.
+    fn default() -> Self {
+        Self::new(Notation::Auto)
+    }
+}
+
+impl NumberFormat {
+    /// Creates new format with `notation` and without thousands separator.
+    pub fn new(notation: Notation) -> Self {
+        Self {
+            notation,
+            thousands_separator: None,
+        }
+    }
+
+    /// Formats the `value`.
+    pub fn format(&self, value: f64) -> String {
+        if !value.is_finite() {
+            return value.to_string();
+        }
+        let formatted = match self.notation {
+            Notation::Auto => value.to_string(),
+            Notation::Fixed(decimals) => format!("{:.*}", decimals, value),
+            Notation::Significant(digits) => format_significant(value, digits),
+            Notation::Scientific(decimals) => return format!("{:.*e}", decimals, value),
+            Notation::Rational => match to_fraction(value) {
+                Some((numerator, denominator)) if denominator != 1 => {
+                    return format!(
+                        "{}/{}",
+                        self.group_thousands(&numerator.to_string()),
+                        denominator
+                    )
+                }
+                _ => value.to_string(),
+            },
+        };
+        self.group_thousands(&formatted)
+    }
+
+    fn group_thousands(&self, number: &str) -> String {
+        let separator = match self.thousands_separator {
+            Some(separator) => separator,
+            None => return number.to_owned(),
+        };
+        let (sign, unsigned) = match number.strip_prefix('-') {
+            Some(unsigned) => ("-", unsigned),
+            None => ("", number),
+        };
+        let int_len = unsigned.find('.').unwrap_or(unsigned.len());
+        let (int, rest) = unsigned.split_at(int_len);
+        let mut result = String::from(sign);
+        for (i, ch) in int.chars().enumerate() {
+            if i != 0 && (int_len - i) % 3 == 0 {
+                result.push(separator);
+            }
+            result.push(ch);
+        }
+        result.push_str(rest);
+        result
+    }
+}
+
+fn format_significant(value: f64, digits: usize) -> String {
+    let digits = digits.max(1);
+    if value == 0.0 {
+        return format!("{:.*}", digits - 1, value);
+    }
+    // let rust do the rounding, then use the exponent of the rounded value
+    let scientific = format!("{:.*e}", digits - 1, value);
+    // unwrap: scientific format always contains the exponent
+    let exponent: i64 = scientific[scientific.find('e').unwrap() + 1..]
+        .parse()
+        .unwrap();
+    let decimals = (digits as i64 - 1 - exponent).max(0) as usize;
+    let rounded: f64 = scientific.parse().unwrap_or(value);
+    format!("{:.*}", decimals, rounded)
+}
+
+/// Finds the fraction closest to the `value` using continued fractions.
+fn to_fraction(value: f64) -> Option<(i64, u64)> {
+    // allow for the rounding errors of a few operations, `0.1 + 0.2` is still `3/10`
+    const EPSILON: f64 = 4.0 * f64::EPSILON;
+    let sign = if value < 0.0 { -1 } else { 1 };
+    let value = value.abs();
+    if value > i64::MAX as f64 {
+        return None;
+    }
+    let (mut p0, mut q0, mut p1, mut q1) = (0u64, 1u64, 1u64, 0u64);
+    let mut x = value;
+    loop {
+        let a = x.floor();
+        let p = (a as u64).checked_mul(p1)?.checked_add(p0)?;
+        let q = (a as u64).checked_mul(q1)?.checked_add(q0)?;
+        if q > MAX_DENOMINATOR {
+            return None;
+        }
+        if (value - p as f64 / q as f64).abs() <= EPSILON * value.max(1.0) {
+            return Some((sign * p as i64, q));
+        }
+        let fraction = x - a;
+        if fraction == 0.0 {
+            return None;
+        }
+        x = 1.0 / fraction;
+        p0 = p1;
+        q0 = q1;
+        p1 = p;
+        q1 = q;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format() {
+        let input_expected = &[
+            (Notation::Auto, 0.1 + 0.2, "0.30000000000000004"),
+            (Notation::Fixed(3), 2.0 / 3.0, "0.667"),
+            (Notation::Fixed(0), -2.5, "-2"),
+            (Notation::Significant(4), 0.1 + 0.2, "0.3000"),
+            (Notation::Significant(2), 9.99, "10"),
+            (Notation::Significant(3), 123456.0, "123000"),
+            (Notation::Significant(3), 0.000123456, "0.000123"),
+            (Notation::Significant(3), 0.0, "0.00"),
+            (Notation::Scientific(3), -0.00123456, "-1.235e-3"),
+            (Notation::Rational, 1.0 / 3.0, "1/3"),
+            (Notation::Rational, -1.25, "-5/4"),
+            (Notation::Rational, 2.0, "2"),
+            (Notation::Rational, 0.1 + 0.2, "3/10"),
+            (
+                Notation::Rational,
+                std::f64::consts::PI,
+                "3.141592653589793",
+            ),
+            (Notation::Auto, f64::NAN, "NaN"),
+            (Notation::Fixed(2), f64::NEG_INFINITY, "-inf"),
+        ];
+        for (notation, value, expected) in input_expected {
+            let result = NumberFormat::new(*notation).format(*value);
+            assert_eq!(
+                result, *expected,
+                "notation {:?}, value {}",
+                notation, value
+            );
+        }
+    }
+
+    #[test]
+    fn test_thousands_separator() {
+        let format = |notation, value| {
+            NumberFormat {
+                notation,
+                thousands_separator: Some(' '),
+            }
+            .format(value)
+        };
+        assert_eq!(format(Notation::Auto, 1234567.5), "1 234 567.5");
+        assert_eq!(format(Notation::Fixed(1), -123456.0), "-123 456.0");
+        assert_eq!(format(Notation::Auto, 123.0), "123");
+        assert_eq!(format(Notation::Rational, 4000.5), "8 001/2");
+        assert_eq!(format(Notation::Scientific(1), 12345.0), "1.2e4");
+    }
+}
@@ -8,29 +8,72 @@
 //! # use std::f64;
 //! use rusty_yard::{Ctx,functions::Func, evaluator::eval_str_with_vars_and_ctx};
 //!
-//! let exp = Func {
-//!    token: "exp".to_owned(),
-//!    arity: 1.into(),
-//!    func: |args| args[0].exp()
-//! };
+//! let exp = Func::new("exp", Some(1), |args| args[0].exp());
 //! let mut vars = HashMap::new();
 //! let mut ctx = Ctx::empty();
 //! ctx.fns.push(exp);
 //! assert_eq!(eval_str_with_vars_and_ctx("exp(1.0)", &mut vars, &ctx), Ok(f64::consts::E));
 //! ```
 //!
+//! The functions are closures, so they can capture the state:
+//! ```
+//! # use std::collections::HashMap;
+//! use rusty_yard::{Ctx,functions::Func, evaluator::eval_str_with_vars_and_ctx};
+//!
+//! let rates: HashMap<usize, f64> = vec![(1, 0.1), (2, 0.25)].into_iter().collect();
+//! let tax = Func::new("tax", Some(2), move |args| args[0] * rates[&(args[1] as usize)]);
+//! let mut vars = HashMap::new();
+//! let mut ctx = Ctx::empty();
+//! ctx.fns.push(tax);
+//! assert_eq!(eval_str_with_vars_and_ctx("tax(100, 2)", &mut vars, &ctx), Ok(25.0));
+//! ```
+//!
+//! The functions that can fail are [`Func::fallible`](Func::fallible),
+//! their errors are reported as [`FunctionError`](crate::evaluator::Error::FunctionError):
+//! ```
+//! # use std::collections::HashMap;
+//! use rusty_yard::{Ctx,functions::Func, evaluator::{eval_str_with_vars_and_ctx, Error}};
+//!
+//! let nth_prime = Func::fallible("nth_prime", Some(1), |args| match args[0] as usize {
+//!     1 => Ok(2.0),
+//!     2 => Ok(3.0),
+//!     3 => Ok(5.0),
+//!     _ => Err(format!("prime #{} is not in the table", args[0])),
+//! });
+//! let mut vars = HashMap::new();
+//! let mut ctx = Ctx::empty();
+//! ctx.fns.push(nth_prime);
+//! assert_eq!(eval_str_with_vars_and_ctx("nth_prime(3)", &mut vars, &ctx), Ok(5.0));
+//! assert_eq!(
+//!     eval_str_with_vars_and_ctx("nth_prime(4)", &mut vars, &ctx),
+//!     Err(Error::FunctionError {
+//!         id: "nth_prime".to_owned(),
+//!         message: "prime #4 is not in the table".to_owned(),
+//!     })
+//! );
+//! ```
+//!
 //! # Note
 //!
 //! A lot of functions are missing from [`default_functions`](default_functions) list.
-//! Feel free to implement more of them.
+//! Some of them are in the optional [`packs`](packs), feel free to implement more of them.
 #![deny(missing_docs)]
 
-use std::fmt::{Debug, Formatter};
+use std::borrow::Cow;
+use std::fmt::{Debug, Display, Formatter};
 use std::hash::{Hash, Hasher};
+use std::ops::{Bound, RangeBounds};
+use std::sync::Arc;
 
 use lazy_static::lazy_static;
 use thiserror::Error;
 
+use crate::macros::ParsedMacro;
+use crate::variables::VariableResolver;
+use crate::{evaluator, Ctx};
+
+pub mod packs;
+
 /// Represents a function
 #[derive(Clone)]
 pub struct Func {
@@ -38,8 +81,17 @@ pub struct Func {
     pub token: String,
     /// Arity of the function.
     ///
-    /// Set to 0 to make the function variadic.
+    /// Set to `None` to make the function variadic.
+    /// The functions of arity 0 can be referenced without the parentheses, `2 * rand` is the same as `2 * rand()`.
     pub arity: Option<usize>,
+    /// The least number of the arguments, when the function takes less than the [`arity`](Func::arity),
+    /// see [`with_arity_range`](Func::with_arity_range).
+    ///
+    /// Set to `None` to take exactly `arity` arguments, less the optional [`params`](Func::params).
+    pub min_arity: Option<usize>,
+
+    /// The parameters of the function, empty if they are not described, see [`with_params`](Func::with_params).
+    pub params: Vec<Param>,
 
     /// The pointer to the function that implements the behaviour of the function.
     ///
@@ -48,36 +100,415 @@ pub struct Func {
     /// [`evaluator`](crate::evaluator) will never pass any other number of parameters to the function other than arity.
     /// However, if the function is variadic `arity == None` then any number of parameters,
     /// **including** 0 might be passed to the function by the evaluator.
-    pub func: fn(&[f64]) -> f64,
+    pub func: FuncKind,
+}
+
+/// The parameter of the [`Func`](Func).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param {
+    /// The name of the parameter.
+    pub name: String,
+    /// The value of the parameter when the call doesn't pass it, `None` if the parameter is required.
+    pub default: Option<f64>,
+}
+
+impl Param {
+    /// Creates the parameter that must be passed.
+    pub fn required(name: &str) -> Self {
+        Param {
+            name: name.to_owned(),
+            default: None,
+        }
+    }
+
+    /// Creates the parameter that is `default` when the call doesn't pass it.
+    pub fn optional(name: &str, default: f64) -> Self {
+        Param {
+            name: name.to_owned(),
+            default: Some(default),
+        }
+    }
+}
+
+/// The numbers of the arguments the [`Func`](Func) can be called with, see [`Func::arity_range`](Func::arity_range).
+///
+/// It is displayed like the range of the numbers, `2`, `1..=3` or `1..`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct ArityRange {
+    /// The least number of the arguments.
+    pub min: usize,
+    /// The greatest number of the arguments, `None` if the function is variadic.
+    pub max: Option<usize>,
+}
+
+impl ArityRange {
+    /// The range of the function that takes exactly `arity` arguments.
+    pub fn exact(arity: usize) -> Self {
+        ArityRange {
+            min: arity,
+            max: Some(arity),
+        }
+    }
+
+    /// Returns `true` if `n_args` is in the range.
+    pub fn contains(&self, n_args: usize) -> bool {
+        n_args >= self.min && self.max.is_none_or(|max| n_args <= max)
+    }
+}
+
+impl From<usize> for ArityRange {
+    fn from(arity: usize) -> Self {
+        ArityRange::exact(arity)
+    }
+}
+
+impl Display for ArityRange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.max {
+            Some(max) if max == self.min => write!(f, "{}", max),
+            Some(max) => write!(f, "{}..={}", self.min, max),
+            None => write!(f, "{}..", self.min),
+        }
+    }
+}
+
+/// The implementation of the [`Infallible`](FuncKind::Infallible) function, receives the arguments of the call.
+pub type FuncFn = dyn Fn(&[f64]) -> f64 + Send + Sync;
+
+/// The implementation of the [`Fallible`](FuncKind::Fallible) function, receives the arguments of the call.
+pub type FallibleFuncFn = dyn Fn(&[f64]) -> Result<f64, String> + Send + Sync;
+
+/// The implementation of the [`Strings`](FuncKind::Strings) function,
+/// receives the string arguments and the other arguments of the call.
+pub type StringsFuncFn = dyn Fn(&[&str], &[f64]) -> Result<f64, String> + Send + Sync;
+
+/// The implementation of [`Func`](Func).
+///
+/// The closures can capture the state, e.g. the configuration or the lookup tables.
+#[derive(Clone)]
+pub enum FuncKind {
+    /// The function that always produces a value.
+    Infallible(Arc<FuncFn>),
+    /// The function that can fail with the error message,
+    /// e.g. when the value is not found in the lookup table.
+    Fallible(Arc<FallibleFuncFn>),
+    /// The function that receives the [string literals](crate::tokenizer#string-literals), see [`Func::strings`](Func::strings).
+    Strings(Arc<StringsFuncFn>),
+}
+
+impl FuncKind {
+    fn address(&self) -> *const () {
+        match self {
+            FuncKind::Infallible(func) => Arc::as_ptr(func).cast(),
+            FuncKind::Fallible(func) => Arc::as_ptr(func).cast(),
+            FuncKind::Strings(func) => Arc::as_ptr(func).cast(),
+        }
+    }
+}
+
+impl Debug for FuncKind {
+    #[cfg_attr(tarpaulin, skip)]
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        match self {
+            FuncKind::Infallible(_) => write!(f, "Infallible"),
+            FuncKind::Fallible(_) => write!(f, "Fallible"),
+            FuncKind::Strings(_) => write!(f, "Strings"),
+        }
+    }
 }
 
 /// Represents an error that can occur when calling [`Func::call`](Func::call).
 #[derive(Debug, Error, PartialEq)]
-#[error("Mismatched number of parameters when calling the function, expected: {expected}, actual: {actual}")]
-pub struct Error {
-    /// Expected number of parameters to the function.
-    expected: usize,
-    /// Actual number of parameters passed to the function.
-    actual: usize,
+pub enum Error {
+    /// The number of the parameters is not in the [`arity_range`](Func::arity_range) of the function.
+    #[error("Mismatched number of parameters when calling the function, expected: {expected}, actual: {actual}")]
+    ArityMismatch {
+        /// Expected numbers of parameters to the function.
+        expected: ArityRange,
+        /// Actual number of parameters passed to the function.
+        actual: usize,
+    },
+    /// The [`Fallible`](FuncKind::Fallible) function failed, contains the error message.
+    #[error("{0}")]
+    Failed(String),
 }
 
 impl Func {
+    /// Creates the function that always produces a value, `func` can be a plain `fn` or a closure.
+    pub fn new(
+        token: &str,
+        arity: Option<usize>,
+        func: impl Fn(&[f64]) -> f64 + Send + Sync + 'static,
+    ) -> Self {
+        Func {
+            token: token.to_owned(),
+            arity,
+            min_arity: None,
+            params: Vec::new(),
+            func: FuncKind::Infallible(Arc::new(func)),
+        }
+    }
+
+    /// Creates the function that can fail, `func` can be a plain `fn` or a closure.
+    pub fn fallible(
+        token: &str,
+        arity: Option<usize>,
+        func: impl Fn(&[f64]) -> Result<f64, String> + Send + Sync + 'static,
+    ) -> Self {
+        Func {
+            token: token.to_owned(),
+            arity,
+            min_arity: None,
+            params: Vec::new(),
+            func: FuncKind::Fallible(Arc::new(func)),
+        }
+    }
+
+    /// Creates the function that receives the [string literals](crate::tokenizer#string-literals)
+    /// passed to it, e.g. the table function `lookup("steel", t)`, see [`Ctx::strings`](crate::Ctx::strings).
+    ///
+    /// `func` receives the string arguments and the other arguments separately, both in the order of the call,
+    /// the `arity` counts both. Only the literals are the string arguments, `lookup("a" + 1)` is rejected by the evaluator.
+    ///
+    /// ```
+    /// use rusty_yard::evaluator::eval_str_with_vars_and_ctx;
+    /// use rusty_yard::functions::Func;
+    /// use rusty_yard::Ctx;
+    /// use std::collections::HashMap;
+    ///
+    /// let mut ctx = Ctx {
+    ///     strings: true,
+    ///     ..Ctx::default()
+    /// };
+    /// ctx.fns.push(Func::strings("density", Some(1), |strings, _| match strings {
+    ///     ["steel"] => Ok(7850.0),
+    ///     ["water"] => Ok(1000.0),
+    ///     _ => Err(format!("unknown material {:?}", strings)),
+    /// }));
+    /// let result = eval_str_with_vars_and_ctx("2 * density(\"water\")", &mut HashMap::new(), &ctx);
+    /// assert_eq!(result, Ok(2000.0));
+    /// ```
+    pub fn strings(
+        token: &str,
+        arity: Option<usize>,
+        func: impl Fn(&[&str], &[f64]) -> Result<f64, String> + Send + Sync + 'static,
+    ) -> Self {
+        Func {
+            token: token.to_owned(),
+            arity,
+            min_arity: None,
+            params: Vec::new(),
+            func: FuncKind::Strings(Arc::new(func)),
+        }
+    }
+
+    /// Describes the parameters of the function, and sets its arity to their number.
+    ///
+    /// The parameters can be passed by their names, `round(x, digits = 2)`,
+    /// see [`named arguments`](crate::tokenizer#named-arguments).
+    /// The optional parameters can be omitted by the call, e.g. `round(x)` is `round(x, 0)` with
+    ///
+    /// ```
+    /// use rusty_yard::functions::{Func, Param};
+    ///
+    /// let round = Func::new("round", None, |args| {
+    ///     let scale = 10f64.powf(args[1]);
+    ///     (args[0] * scale).round() / scale
+    /// })
+    /// .with_params(vec![Param::required("x"), Param::optional("digits", 0.0)]);
+    /// assert_eq!(round.call(&[2.345]), Ok(2.0));
+    /// assert_eq!(round.call(&[2.345, 2.0]), Ok(2.35));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the required parameter follows the optional one.
+    pub fn with_params(mut self, params: Vec<Param>) -> Self {
+        let first_optional = params.iter().position(|param| param.default.is_some());
+        if let Some(first_optional) = first_optional {
+            assert!(
+                params[first_optional..]
+                    .iter()
+                    .all(|param| param.default.is_some()),
+                "the required parameter of {} follows the optional one",
+                self.token
+            );
+        }
+        self.arity = Some(params.len());
+        self.params = params;
+        self
+    }
+
+    /// Sets the numbers of the arguments the function takes,
+    /// e.g. `1..=2` for `round(x)` and `round(x, digits)`, or `1..` for the variadic function that needs an argument.
+    ///
+    /// Unlike the optional [`params`](Func::params), the omitted arguments are not passed to the function,
+    /// so it receives as many arguments as the call has.
+    ///
+    /// ```
+    /// use rusty_yard::functions::Func;
+    ///
+    /// let round = Func::new("round", None, |args| {
+    ///     let scale = 10f64.powf(args.get(1).copied().unwrap_or(0.0));
+    ///     (args[0] * scale).round() / scale
+    /// })
+    /// .with_arity_range(1..=2);
+    /// assert_eq!(round.call(&[2.345]), Ok(2.0));
+    /// assert_eq!(round.call(&[2.345, 2.0]), Ok(2.35));
+    /// assert_eq!(round.arity_range().to_string(), "1..=2");
+    /// assert!(round.call(&[]).is_err());
+    /// ```
+    pub fn with_arity_range(mut self, range: impl RangeBounds<usize>) -> Self {
+        self.min_arity = Some(match range.start_bound() {
+            Bound::Included(min) => *min,
+            Bound::Excluded(min) => min + 1,
+            Bound::Unbounded => 0,
+        });
+        self.arity = match range.end_bound() {
+            Bound::Included(max) => Some(*max),
+            Bound::Excluded(max) => Some(max.saturating_sub(1)),
+            Bound::Unbounded => None,
+        };
+        self
+    }
+
+    /// The numbers of the arguments the function can be called with,
+    /// the optional [`params`](Func::params) can be omitted.
+    pub fn arity_range(&self) -> ArityRange {
+        let min = match (self.min_arity, self.arity) {
+            (Some(min), _) => min,
+            (None, Some(arity)) => arity - self.optional_params(),
+            (None, None) => 0,
+        };
+        ArityRange {
+            min,
+            max: self.arity,
+        }
+    }
+
+    /// Returns `true` if the function can be called with `n_args` arguments, see [`arity_range`](Func::arity_range).
+    pub fn accepts(&self, n_args: usize) -> bool {
+        self.arity_range().contains(n_args)
+    }
+
+    /// The default values of the parameters after the first `n_args` ones
+    pub fn defaults(&self, n_args: usize) -> impl Iterator<Item = f64> + '_ {
+        self.params
+            .iter()
+            .skip(n_args)
+            .filter_map(|param| param.default)
+    }
+
+    fn optional_params(&self) -> usize {
+        self.params
+            .iter()
+            .filter(|param| param.default.is_some())
+            .count()
+    }
+
     /// Call the function with the specified parameters.
     ///
-    /// If number of parameters is equal to function arity, or function is variadic.
-    /// returns [`Ok`](std::result::Result::Ok),
+    /// If the function [`accepts`](Func::accepts) the number of parameters,
+    /// returns the result of the function called with the defaults of the omitted parameters,
     /// otherwise [`Err`](std::result::Result::Err) with [`function::Error`](Error) type is returned.
     pub fn call(&self, args: &[f64]) -> Result<f64, Error> {
-        if let Some(arity) = self.arity {
-            if args.len() != arity {
-                return Err(Error {
-                    expected: arity,
-                    actual: args.len(),
-                });
-            }
+        self.call_with_strings(&[], args)
+    }
+
+    /// Same as [`call`](Func::call), the `strings` are the string arguments of the [`Strings`](FuncKind::Strings) function,
+    /// they are counted into the number of the parameters
+    pub(crate) fn call_with_strings(&self, strings: &[&str], args: &[f64]) -> Result<f64, Error> {
+        let n_args = strings.len() + args.len();
+        if !self.accepts(n_args) {
+            return Err(Error::ArityMismatch {
+                expected: self.arity_range(),
+                actual: n_args,
+            });
+        }
+        if self.defaults(n_args).next().is_some() {
+            let args: Vec<f64> = args.iter().copied().chain(self.defaults(n_args)).collect();
+            return self.call_with_strings(strings, &args);
+        }
+        match &self.func {
+            FuncKind::Infallible(func) => Ok(func(args)),
+            FuncKind::Fallible(func) => func(args).map_err(Error::Failed),
+            FuncKind::Strings(func) => func(strings, args).map_err(Error::Failed),
         }
-        let func = self.func;
-        Ok(func(args))
+    }
+}
+
+/// The call of the [`Strings`](FuncKind::Strings) function with the string literals,
+/// the parser replaces the function token with it, the string arguments are not on the evaluation stack
+pub(crate) struct StringsCall<'a> {
+    pub(crate) func: Func,
+    pub(crate) strings: Vec<Cow<'a, str>>,
+    /// The number of the arguments of the call, including the strings
+    pub(crate) n_args: usize,
+}
+
+impl Debug for StringsCall<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        f.debug_struct("StringsCall")
+            .field("func", &self.func.token)
+            .field("strings", &self.strings)
+            .field("n_args", &self.n_args)
+            .finish()
+    }
+}
+
+impl ParsedMacro for StringsCall<'_> {
+    fn eval(
+        &self,
+        eval_stack: &mut Vec<f64>,
+        _variables: &mut dyn VariableResolver,
+        _ctx: &Ctx,
+    ) -> Result<(), evaluator::Error> {
+        let start = eval_stack
+            .len()
+            .checked_sub(self.operands())
+            .ok_or(evaluator::Error::EmptyEvalStack)?;
+        let strings: Vec<&str> = self.strings.iter().map(|s| s.as_ref()).collect();
+        let value = self
+            .func
+            .call_with_strings(&strings, &eval_stack[start..])
+            .map_err(|error| match error {
+                Error::Failed(message) => evaluator::Error::FunctionError {
+                    id: self.func.token.clone(),
+                    message,
+                },
+                Error::ArityMismatch { expected, actual } => evaluator::Error::ArityMismatch {
+                    id: self.func.token.clone(),
+                    expected,
+                    actual,
+                },
+            })?;
+        eval_stack.truncate(start);
+        eval_stack.push(value);
+        Ok(())
+    }
+
+    fn operands(&self) -> usize {
+        self.n_args - self.strings.len()
+    }
+
+    fn name(&self) -> &str {
+        &self.func.token
+    }
+
+    fn structural_key(&self) -> Option<String> {
+        Some(format!("{}{:?}", self.n_args, self.strings))
+    }
+
+    fn to_owned_macro(&self) -> Option<Box<dyn ParsedMacro>> {
+        Some(Box::new(StringsCall {
+            func: self.func.clone(),
+            strings: self
+                .strings
+                .iter()
+                .map(|s| Cow::Owned(s.to_string()))
+                .collect(),
+            n_args: self.n_args,
+        }))
     }
 }
 
@@ -87,7 +518,9 @@ impl PartialEq for Func {
     fn eq(&self, other: &Self) -> bool {
         self.token.eq(&other.token)
             && self.arity.eq(&other.arity)
-            && self.func as usize == other.func as usize
+            && self.min_arity.eq(&other.min_arity)
+            && self.params.eq(&other.params)
+            && self.func.address() == other.func.address()
     }
 }
 
@@ -96,7 +529,12 @@ impl Hash for Func {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.token.hash(state);
         self.arity.hash(state);
-        (self.func as usize).hash(state)
+        self.min_arity.hash(state);
+        for param in &self.params {
+            param.name.hash(state);
+            param.default.map(f64::to_bits).hash(state);
+        }
+        self.func.address().hash(state)
     }
 }
 
@@ -107,6 +545,8 @@ impl Debug for Func {
         f.debug_struct("Func")
             .field("token", &self.token)
             .field("arity", &self.arity)
+            .field("min_arity", &self.min_arity)
+            .field("params", &self.params)
             .finish()
     }
 }
@@ -125,6 +565,18 @@ pub fn to_args_2(func: fn(f64, f64) -> f64) -> impl Fn(&[f64]) -> f64 {
     move |args| func(args[0], args[1])
 }
 
+/// Rounds `x` to `digits` decimal places like `FN_ROUND`
+pub(crate) fn round_to(x: f64, digits: f64) -> f64 {
+    let scale = 10f64.powi(digits as i32);
+    let scaled = x * scale;
+    // beyond the precision of f64 the number is already rounded
+    if scaled.is_finite() {
+        scaled.round() / scale
+    } else {
+        x
+    }
+}
+
 lazy_static! {
     /// max(a, b) function.
     ///
@@ -136,11 +588,13 @@ lazy_static! {
     pub static ref FN_MAX: Func = Func {
         token: "max".to_owned(),
         arity: 2.into(),
-        func: |args| {
+        min_arity: None,
+        params: Vec::new(),
+        func: FuncKind::Infallible(Arc::new(|args| {
             let arg1 = args[0];
             let arg2 = args[1];
             arg1.max(arg2)
-        },
+        })),
     };
 
     /// sum(..args) function.
@@ -153,7 +607,9 @@ lazy_static! {
     pub static ref FN_SUM: Func = Func {
         token: "sum".to_owned(),
         arity: None,
-        func: |args| args.iter().sum(),
+        min_arity: None,
+        params: Vec::new(),
+        func: FuncKind::Infallible(Arc::new(|args| args.iter().sum())),
     };
 
     /// prod(..args) function.
@@ -166,7 +622,9 @@ lazy_static! {
     pub static ref FN_PROD: Func = Func {
         token: "prod".to_owned(),
         arity: None,
-        func: |args| args.iter().product(),
+        min_arity: None,
+        params: Vec::new(),
+        func: FuncKind::Infallible(Arc::new(|args| args.iter().product())),
     };
 
     /// sub(a, b) function.
@@ -179,11 +637,392 @@ lazy_static! {
     pub static ref FN_SUB: Func = Func {
         token: "sub".to_owned(),
         arity: 2.into(),
-        func: |args| {
+        min_arity: None,
+        params: Vec::new(),
+        func: FuncKind::Infallible(Arc::new(|args| {
             let arg1 = args[0];
             let arg2 = args[1];
             arg1 - arg2
-        },
+        })),
+    };
+
+    /// sin(x) function, sine of x in radians.
+    ///
+    /// # Implementation
+    ///
+    /// ```text
+    /// x.sin()
+    /// ```
+    pub static ref FN_SIN: Func = Func {
+        token: "sin".to_owned(),
+        arity: 1.into(),
+        min_arity: None,
+        params: Vec::new(),
+        func: FuncKind::Infallible(Arc::new(|args| args[0].sin())),
+    };
+
+    /// cos(x) function, cosine of x in radians.
+    ///
+    /// # Implementation
+    ///
+    /// ```text
+    /// x.cos()
+    /// ```
+    pub static ref FN_COS: Func = Func {
+        token: "cos".to_owned(),
+        arity: 1.into(),
+        min_arity: None,
+        params: Vec::new(),
+        func: FuncKind::Infallible(Arc::new(|args| args[0].cos())),
+    };
+
+    /// tan(x) function, tangent of x in radians.
+    ///
+    /// # Implementation
+    ///
+    /// ```text
+    /// x.tan()
+    /// ```
+    pub static ref FN_TAN: Func = Func {
+        token: "tan".to_owned(),
+        arity: 1.into(),
+        min_arity: None,
+        params: Vec::new(),
+        func: FuncKind::Infallible(Arc::new(|args| args[0].tan())),
+    };
+
+    /// asin(x) function, arcsine of x in radians.
+    ///
+    /// # Implementation
+    ///
+    /// ```text
+    /// x.asin()
+    /// ```
+    pub static ref FN_ASIN: Func = Func {
+        token: "asin".to_owned(),
+        arity: 1.into(),
+        min_arity: None,
+        params: Vec::new(),
+        func: FuncKind::Infallible(Arc::new(|args| args[0].asin())),
+    };
+
+    /// acos(x) function, arccosine of x in radians.
+    ///
+    /// # Implementation
+    ///
+    /// ```text
+    /// x.acos()
+    /// ```
+    pub static ref FN_ACOS: Func = Func {
+        token: "acos".to_owned(),
+        arity: 1.into(),
+        min_arity: None,
+        params: Vec::new(),
+        func: FuncKind::Infallible(Arc::new(|args| args[0].acos())),
+    };
+
+    /// atan(x) function, arctangent of x in radians.
+    ///
+    /// # Implementation
+    ///
+    /// ```text
+    /// x.atan()
+    /// ```
+    pub static ref FN_ATAN: Func = Func {
+        token: "atan".to_owned(),
+        arity: 1.into(),
+        min_arity: None,
+        params: Vec::new(),
+        func: FuncKind::Infallible(Arc::new(|args| args[0].atan())),
+    };
+
+    /// sinh(x) function, hyperbolic sine.
+    ///
+    /// # Implementation
+    ///
+    /// ```text
+    /// x.sinh()
+    /// ```
+    pub static ref FN_SINH: Func = Func {
+        token: "sinh".to_owned(),
+        arity: 1.into(),
+        min_arity: None,
+        params: Vec::new(),
+        func: FuncKind::Infallible(Arc::new(|args| args[0].sinh())),
+    };
+
+    /// cosh(x) function, hyperbolic cosine.
+    ///
+    /// # Implementation
+    ///
+    /// ```text
+    /// x.cosh()
+    /// ```
+    pub static ref FN_COSH: Func = Func {
+        token: "cosh".to_owned(),
+        arity: 1.into(),
+        min_arity: None,
+        params: Vec::new(),
+        func: FuncKind::Infallible(Arc::new(|args| args[0].cosh())),
+    };
+
+    /// tanh(x) function, hyperbolic tangent.
+    ///
+    /// # Implementation
+    ///
+    /// ```text
+    /// x.tanh()
+    /// ```
+    pub static ref FN_TANH: Func = Func {
+        token: "tanh".to_owned(),
+        arity: 1.into(),
+        min_arity: None,
+        params: Vec::new(),
+        func: FuncKind::Infallible(Arc::new(|args| args[0].tanh())),
+    };
+
+    /// ln(x) function, natural logarithm.
+    ///
+    /// # Implementation
+    ///
+    /// ```text
+    /// x.ln()
+    /// ```
+    pub static ref FN_LN: Func = Func {
+        token: "ln".to_owned(),
+        arity: 1.into(),
+        min_arity: None,
+        params: Vec::new(),
+        func: FuncKind::Infallible(Arc::new(|args| args[0].ln())),
+    };
+
+    /// log10(x) function, base 10 logarithm.
+    ///
+    /// # Implementation
+    ///
+    /// ```text
+    /// x.log10()
+    /// ```
+    pub static ref FN_LOG10: Func = Func {
+        token: "log10".to_owned(),
+        arity: 1.into(),
+        min_arity: None,
+        params: Vec::new(),
+        func: FuncKind::Infallible(Arc::new(|args| args[0].log10())),
+    };
+
+    /// log2(x) function, base 2 logarithm.
+    ///
+    /// # Implementation
+    ///
+    /// ```text
+    /// x.log2()
+    /// ```
+    pub static ref FN_LOG2: Func = Func {
+        token: "log2".to_owned(),
+        arity: 1.into(),
+        min_arity: None,
+        params: Vec::new(),
+        func: FuncKind::Infallible(Arc::new(|args| args[0].log2())),
+    };
+
+    /// exp(x) function, e to the power of x.
+    ///
+    /// # Implementation
+    ///
+    /// ```text
+    /// x.exp()
+    /// ```
+    pub static ref FN_EXP: Func = Func {
+        token: "exp".to_owned(),
+        arity: 1.into(),
+        min_arity: None,
+        params: Vec::new(),
+        func: FuncKind::Infallible(Arc::new(|args| args[0].exp())),
+    };
+
+    /// sqrt(x) function, square root.
+    ///
+    /// # Implementation
+    ///
+    /// ```text
+    /// x.sqrt()
+    /// ```
+    pub static ref FN_SQRT: Func = Func {
+        token: "sqrt".to_owned(),
+        arity: 1.into(),
+        min_arity: None,
+        params: Vec::new(),
+        func: FuncKind::Infallible(Arc::new(|args| args[0].sqrt())),
+    };
+
+    /// cbrt(x) function, cube root.
+    ///
+    /// # Implementation
+    ///
+    /// ```text
+    /// x.cbrt()
+    /// ```
+    pub static ref FN_CBRT: Func = Func {
+        token: "cbrt".to_owned(),
+        arity: 1.into(),
+        min_arity: None,
+        params: Vec::new(),
+        func: FuncKind::Infallible(Arc::new(|args| args[0].cbrt())),
+    };
+
+    /// abs(x) function, absolute value.
+    ///
+    /// # Implementation
+    ///
+    /// ```text
+    /// x.abs()
+    /// ```
+    pub static ref FN_ABS: Func = Func {
+        token: "abs".to_owned(),
+        arity: 1.into(),
+        min_arity: None,
+        params: Vec::new(),
+        func: FuncKind::Infallible(Arc::new(|args| args[0].abs())),
+    };
+
+    /// floor(x) function, the largest integer less than or equal to x.
+    ///
+    /// # Implementation
+    ///
+    /// ```text
+    /// x.floor()
+    /// ```
+    pub static ref FN_FLOOR: Func = Func {
+        token: "floor".to_owned(),
+        arity: 1.into(),
+        min_arity: None,
+        params: Vec::new(),
+        func: FuncKind::Infallible(Arc::new(|args| args[0].floor())),
+    };
+
+    /// ceil(x) function, the smallest integer greater than or equal to x.
+    ///
+    /// # Implementation
+    ///
+    /// ```text
+    /// x.ceil()
+    /// ```
+    pub static ref FN_CEIL: Func = Func {
+        token: "ceil".to_owned(),
+        arity: 1.into(),
+        min_arity: None,
+        params: Vec::new(),
+        func: FuncKind::Infallible(Arc::new(|args| args[0].ceil())),
+    };
+
+    /// round(x, digits = 0) function, x rounded to `digits` decimal places, rounds half-way cases away from 0.
+    ///
+    /// `round(x)` is the nearest integer, the negative `digits` round to the tens, hundreds and so on.
+    ///
+    /// # Implementation
+    ///
+    /// ```text
+    /// let scale = 10f64.powi(digits as i32);
+    /// (x * scale).round() / scale
+    /// ```
+    pub static ref FN_ROUND: Func = Func {
+        token: "round".to_owned(),
+        arity: 2.into(),
+        min_arity: None,
+        params: vec![Param::required("x"), Param::optional("digits", 0.0)],
+        func: FuncKind::Infallible(Arc::new(|args| round_to(args[0], args[1]))),
+    };
+
+    /// signum(x) function, the sign of x.
+    ///
+    /// # Implementation
+    ///
+    /// ```text
+    /// x.signum()
+    /// ```
+    pub static ref FN_SIGNUM: Func = Func {
+        token: "signum".to_owned(),
+        arity: 1.into(),
+        min_arity: None,
+        params: Vec::new(),
+        func: FuncKind::Infallible(Arc::new(|args| args[0].signum())),
+    };
+
+    /// min(a, b) function.
+    ///
+    /// # Implementation
+    ///
+    /// ```text
+    /// a.min(b)
+    /// ```
+    pub static ref FN_MIN: Func = Func {
+        token: "min".to_owned(),
+        arity: 2.into(),
+        min_arity: None,
+        params: Vec::new(),
+        func: FuncKind::Infallible(Arc::new(|args| {
+            let a = args[0];
+            let b = args[1];
+            a.min(b)
+        })),
+    };
+
+    /// atan2(y, x) function, four quadrant arctangent of y / x in radians.
+    ///
+    /// # Implementation
+    ///
+    /// ```text
+    /// y.atan2(x)
+    /// ```
+    pub static ref FN_ATAN2: Func = Func {
+        token: "atan2".to_owned(),
+        arity: 2.into(),
+        min_arity: None,
+        params: Vec::new(),
+        func: FuncKind::Infallible(Arc::new(|args| {
+            let y = args[0];
+            let x = args[1];
+            y.atan2(x)
+        })),
+    };
+
+    /// hypot(a, b) function, the length of the hypotenuse of the right triangle with the legs a and b.
+    ///
+    /// # Implementation
+    ///
+    /// ```text
+    /// a.hypot(b)
+    /// ```
+    pub static ref FN_HYPOT: Func = Func {
+        token: "hypot".to_owned(),
+        arity: 2.into(),
+        min_arity: None,
+        params: Vec::new(),
+        func: FuncKind::Infallible(Arc::new(|args| {
+            let a = args[0];
+            let b = args[1];
+            a.hypot(b)
+        })),
+    };
+
+    /// log(base, x) function, logarithm of x with respect to the base.
+    ///
+    /// # Implementation
+    ///
+    /// ```text
+    /// x.log(base)
+    /// ```
+    pub static ref FN_LOG: Func = Func {
+        token: "log".to_owned(),
+        arity: 2.into(),
+        min_arity: None,
+        params: Vec::new(),
+        func: FuncKind::Infallible(Arc::new(|args| {
+            let base = args[0];
+            let x = args[1];
+            x.log(base)
+        })),
     };
 }
 
@@ -196,6 +1035,30 @@ pub fn default_functions() -> Vec<Func> {
         FN_SUM.clone(),
         FN_SUB.clone(),
         FN_PROD.clone(),
+        FN_MIN.clone(),
+        FN_SIN.clone(),
+        FN_COS.clone(),
+        FN_TAN.clone(),
+        FN_ASIN.clone(),
+        FN_ACOS.clone(),
+        FN_ATAN.clone(),
+        FN_ATAN2.clone(),
+        FN_SINH.clone(),
+        FN_COSH.clone(),
+        FN_TANH.clone(),
+        FN_LN.clone(),
+        FN_LOG10.clone(),
+        FN_LOG2.clone(),
+        FN_LOG.clone(),
+        FN_EXP.clone(),
+        FN_SQRT.clone(),
+        FN_CBRT.clone(),
+        FN_ABS.clone(),
+        FN_FLOOR.clone(),
+        FN_CEIL.clone(),
+        FN_ROUND.clone(),
+        FN_HYPOT.clone(),
+        FN_SIGNUM.clone(),
     ]
 }
 
@@ -208,7 +1071,9 @@ mod tests {
         let func = Func {
             token: "#".to_owned(),
             arity: 0.into(),
-            func: |_| 0.0,
+            min_arity: None,
+            params: Vec::new(),
+            func: FuncKind::Infallible(Arc::new(|_| 0.0)),
         };
         let dbg = format!("{:?}", func);
         assert!(dbg.contains("Func"));
@@ -223,20 +1088,22 @@ mod tests {
         let func = Func {
             token: "#".to_owned(),
             arity: 1.into(),
-            func: |_| 0.0,
+            min_arity: None,
+            params: Vec::new(),
+            func: FuncKind::Infallible(Arc::new(|_| 0.0)),
         };
         assert_eq!(func.call(&[1.0]), Ok(0.0));
         assert_eq!(
             func.call(&[1.0, 1.0]),
-            Err(Error {
-                expected: 1,
+            Err(Error::ArityMismatch {
+                expected: 1.into(),
                 actual: 2
             })
         );
         assert_eq!(
             func.call(&[]),
-            Err(Error {
-                expected: 1,
+            Err(Error::ArityMismatch {
+                expected: 1.into(),
                 actual: 0
             })
         );
@@ -246,11 +1113,195 @@ mod tests {
         let func = Func {
             token: "#".to_owned(),
             arity: None,
-            func: |_| 0.0,
+            min_arity: None,
+            params: Vec::new(),
+            func: FuncKind::Infallible(Arc::new(|_| 0.0)),
         };
         assert_eq!(func.call(&[]), Ok(0.0));
         assert_eq!(func.call(&[1.0]), Ok(0.0));
         assert_eq!(func.call(&[1.0, 1.0]), Ok(0.0));
         assert_eq!(func.call(&[1.0, 1.0, 1.0]), Ok(0.0));
     }
+
+    #[test]
+    fn test_call_fallible() {
+        let func = Func {
+            token: "#".to_owned(),
+            arity: 1.into(),
+            min_arity: None,
+            params: Vec::new(),
+            func: FuncKind::Fallible(Arc::new(|args| {
+                if args[0] > 0.0 {
+                    Ok(args[0])
+                } else {
+                    Err("not positive".to_owned())
+                }
+            })),
+        };
+        assert_eq!(func.call(&[1.0]), Ok(1.0));
+        assert_eq!(
+            func.call(&[-1.0]),
+            Err(Error::Failed("not positive".to_owned()))
+        );
+        assert_eq!(
+            func.call(&[]),
+            Err(Error::ArityMismatch {
+                expected: 1.into(),
+                actual: 0
+            })
+        );
+    }
+
+    #[test]
+    fn test_call_with_defaults() {
+        let func =
+            Func::new("clamp", None, |args| args[0].max(args[1]).min(args[2])).with_params(vec![
+                Param::required("x"),
+                Param::optional("low", 0.0),
+                Param::optional("high", 1.0),
+            ]);
+        let input_expected = &[
+            (vec![0.5], Ok(0.5)),
+            (vec![-1.0], Ok(0.0)),
+            (vec![-1.0, -2.0], Ok(-1.0)),
+            (vec![3.0, 0.0, 2.0], Ok(2.0)),
+            (
+                vec![],
+                Err(Error::ArityMismatch {
+                    expected: ArityRange {
+                        min: 1,
+                        max: Some(3),
+                    },
+                    actual: 0,
+                }),
+            ),
+            (
+                vec![1.0; 4],
+                Err(Error::ArityMismatch {
+                    expected: ArityRange {
+                        min: 1,
+                        max: Some(3),
+                    },
+                    actual: 4,
+                }),
+            ),
+        ];
+        for (input, expected) in input_expected {
+            assert_eq!(func.call(input), *expected, "input was {:?}", input);
+        }
+
+        let mut ctx = crate::Ctx::default();
+        ctx.fns.push(func);
+        let mut vars = std::collections::HashMap::new();
+        let input_expected = &[
+            ("clamp(5)", Ok(1.0)),
+            ("clamp(5, 0, 10) + clamp(-5, -1)", Ok(4.0)),
+            (
+                "clamp()",
+                Err(crate::evaluator::Error::ParserError(
+                    crate::parser::Error::ArityMismatch {
+                        id: "clamp".to_owned(),
+                        expected: ArityRange {
+                            min: 1,
+                            max: Some(3),
+                        },
+                        actual: 0,
+                    },
+                )),
+            ),
+        ];
+        for (input, expected) in input_expected {
+            let result = crate::evaluator::eval_str_with_vars_and_ctx(input, &mut vars, &ctx);
+            assert_eq!(result, *expected, "input was {}", input);
+        }
+    }
+
+    #[test]
+    fn test_arity_range() {
+        let first = |args: &[f64]| args.first().copied().unwrap_or(0.0);
+        let input_expected = vec![
+            (Func::new("f", Some(2), first), "2"),
+            (Func::new("f", None, first), "0.."),
+            (Func::new("f", None, first).with_arity_range(1..=2), "1..=2"),
+            (
+                Func::new("f", Some(0), first).with_arity_range(1..3),
+                "1..=2",
+            ),
+            (Func::new("f", None, first).with_arity_range(1..), "1.."),
+            (
+                Func::new("f", None, first)
+                    .with_params(vec![Param::required("x"), Param::optional("y", 0.0)]),
+                "1..=2",
+            ),
+        ];
+        for (func, expected) in &input_expected {
+            assert_eq!(
+                func.arity_range().to_string(),
+                *expected,
+                "input was {:?}",
+                func
+            );
+        }
+
+        let clamp = Func::new("clamp", None, |args| match args {
+            [x] => x.clamp(0.0, 1.0),
+            [x, lo, hi] => x.max(*lo).min(*hi),
+            _ => f64::NAN,
+        })
+        .with_arity_range(1..=3);
+        assert_eq!(clamp.call(&[2.0]), Ok(1.0));
+        assert_eq!(clamp.call(&[2.0, 0.0, 5.0]), Ok(2.0));
+        let error = clamp.call(&[1.0; 4]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Mismatched number of parameters when calling the function, expected: 1..=3, actual: 4"
+        );
+
+        let mut ctx = crate::Ctx::default();
+        ctx.fns.push(clamp);
+        let mut vars = std::collections::HashMap::new();
+        let input_expected = &[
+            ("clamp(-1) + clamp(7, 0, 10)", Ok(7.0)),
+            (
+                "clamp()",
+                Err(crate::evaluator::Error::ParserError(
+                    crate::parser::Error::ArityMismatch {
+                        id: "clamp".to_owned(),
+                        expected: ArityRange {
+                            min: 1,
+                            max: Some(3),
+                        },
+                        actual: 0,
+                    },
+                )),
+            ),
+        ];
+        for (input, expected) in input_expected {
+            let result = crate::evaluator::eval_str_with_vars_and_ctx(input, &mut vars, &ctx);
+            assert_eq!(result, *expected, "input was {}", input);
+        }
+    }
+
+    #[test]
+    fn test_round() {
+        let input_expected = &[
+            ("round(3.14159, 2)", Ok(314.0 / 100.0)),
+            ("round(2.5)", Ok(3.0)),
+            ("round(-2.5)", Ok(-3.0)),
+            ("round(1250, -2)", Ok(1300.0)),
+            ("round(x = 2.345, digits = 1)", Ok(2.3)),
+            ("round(1e300, 20)", Ok(1e300)),
+        ];
+        for (input, expected) in input_expected {
+            let result = crate::evaluator::eval_str(input);
+            assert_eq!(result, *expected, "input was {}", input);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_required_param_after_optional() {
+        Func::new("f", None, |_| 0.0)
+            .with_params(vec![Param::optional("a", 0.0), Param::required("b")]);
+    }
 }
@@ -0,0 +1,289 @@
+//! The optional packs of the domain functions, that are not the part of the [`default_functions`](super::default_functions).
+//!
+//! Each pack is the list of the functions, the [`Ctx`](crate::Ctx) adds them with
+//! [`with_trig`](crate::Ctx::with_trig), [`with_statistics`](crate::Ctx::with_statistics)
+//! and [`with_finance`](crate::Ctx::with_finance), so the packs can be picked à la carte.
+//!
+//! # Example
+//!
+//! ```
+//! use rusty_yard::evaluator::eval_str_with_vars_and_ctx;
+//! use rusty_yard::Ctx;
+//! use std::collections::HashMap;
+//!
+//! let ctx = Ctx::default().with_statistics().with_finance();
+//! let mut vars = HashMap::new();
+//! assert_eq!(eval_str_with_vars_and_ctx("median(3, 1, 2)", &mut vars, &ctx), Ok(2.0));
+//! assert_eq!(eval_str_with_vars_and_ctx("npv(0, 1, 2, 3)", &mut vars, &ctx), Ok(6.0));
+//! ```
+use lazy_static::lazy_static;
+
+use super::{Func, Param};
+
+lazy_static! {
+    /// sec(x) function, secant of x in radians.
+    pub static ref FN_SEC: Func = Func::new("sec", Some(1), |args| 1.0 / args[0].cos());
+
+    /// csc(x) function, cosecant of x in radians.
+    pub static ref FN_CSC: Func = Func::new("csc", Some(1), |args| 1.0 / args[0].sin());
+
+    /// cot(x) function, cotangent of x in radians.
+    pub static ref FN_COT: Func = Func::new("cot", Some(1), |args| 1.0 / args[0].tan());
+
+    /// asinh(x) function, inverse hyperbolic sine.
+    pub static ref FN_ASINH: Func = Func::new("asinh", Some(1), |args| args[0].asinh());
+
+    /// acosh(x) function, inverse hyperbolic cosine.
+    pub static ref FN_ACOSH: Func = Func::new("acosh", Some(1), |args| args[0].acosh());
+
+    /// atanh(x) function, inverse hyperbolic tangent.
+    pub static ref FN_ATANH: Func = Func::new("atanh", Some(1), |args| args[0].atanh());
+
+    /// deg(x) function, converts x radians to degrees.
+    pub static ref FN_DEG: Func = Func::new("deg", Some(1), |args| args[0].to_degrees());
+
+    /// rad(x) function, converts x degrees to radians.
+    pub static ref FN_RAD: Func = Func::new("rad", Some(1), |args| args[0].to_radians());
+
+    /// mean(..args) function, the arithmetic mean, fails without the arguments.
+    pub static ref FN_MEAN: Func = Func::fallible("mean", None, |args| {
+        non_empty("mean", args)?;
+        Ok(mean(args))
+    });
+
+    /// median(..args) function, the middle value, or the mean of the two middle ones, fails without the arguments.
+    pub static ref FN_MEDIAN: Func = Func::fallible("median", None, |args| {
+        non_empty("median", args)?;
+        Ok(percentile(&sorted(args), 50.0))
+    });
+
+    /// variance(..args) function, the sample variance, fails with less than two arguments.
+    pub static ref FN_VARIANCE: Func = Func::fallible("variance", None, variance);
+
+    /// stddev(..args) function, the sample standard deviation, fails with less than two arguments.
+    pub static ref FN_STDDEV: Func =
+        Func::fallible("stddev", None, |args| variance(args).map(f64::sqrt));
+
+    /// percentile(p, ..args) function, the `p`th percentile of the arguments, `p` is from 0 to 100.
+    ///
+    /// The percentile between the two values is interpolated linearly, `percentile(50, ..)` is the median.
+    pub static ref FN_PERCENTILE: Func = Func::fallible("percentile", None, |args| {
+        let (p, values) = args
+            .split_first()
+            .ok_or("the percentile is expected as the first argument")?;
+        if !(0.0..=100.0).contains(p) {
+            return Err(format!("the percentile {} is not from 0 to 100", p));
+        }
+        non_empty("percentile", values)?;
+        Ok(percentile(&sorted(values), *p))
+    });
+
+    /// pmt(rate, nper, pv, fv = 0) function, the payment per period of the loan `pv` with the `rate` per period,
+    /// that leaves `fv` after `nper` periods.
+    ///
+    /// The payments are paid at the end of the period, the money paid is negative like in the spreadsheets,
+    /// `pmt(0, 10, 1000)` is `-100`.
+    pub static ref FN_PMT: Func = Func::new("pmt", None, |args| {
+        let (rate, nper, pv, fv) = (args[0], args[1], args[2], args[3]);
+        if rate == 0.0 {
+            -(pv + fv) / nper
+        } else {
+            let growth = (1.0 + rate).powf(nper);
+            -(pv * growth + fv) * rate / (growth - 1.0)
+        }
+    })
+    .with_params(vec![
+        Param::required("rate"),
+        Param::required("nper"),
+        Param::required("pv"),
+        Param::optional("fv", 0.0),
+    ]);
+
+    /// npv(rate, ..values) function, the net present value of the cash flows at the end of each period.
+    pub static ref FN_NPV: Func = Func::fallible("npv", None, |args| {
+        let (rate, values) = args
+            .split_first()
+            .ok_or("the rate is expected as the first argument")?;
+        Ok(present_value(*rate, values) / (1.0 + rate))
+    });
+
+    /// irr(..values) function, the internal rate of return, the rate at which the net present value
+    /// of the cash flows is zero, the first cash flow is at the start.
+    ///
+    /// Fails if the cash flows are not both positive and negative, or the rate is not found.
+    pub static ref FN_IRR: Func = Func::fallible("irr", None, irr);
+}
+
+/// Get the functions of the trigonometry pack: `sec`, `csc`, `cot`, `asinh`, `acosh`, `atanh`, `deg` and `rad`.
+pub fn trig_functions() -> Vec<Func> {
+    vec![
+        FN_SEC.clone(),
+        FN_CSC.clone(),
+        FN_COT.clone(),
+        FN_ASINH.clone(),
+        FN_ACOSH.clone(),
+        FN_ATANH.clone(),
+        FN_DEG.clone(),
+        FN_RAD.clone(),
+    ]
+}
+
+/// Get the functions of the statistics pack: `mean`, `median`, `variance`, `stddev` and `percentile`.
+pub fn statistics_functions() -> Vec<Func> {
+    vec![
+        FN_MEAN.clone(),
+        FN_MEDIAN.clone(),
+        FN_VARIANCE.clone(),
+        FN_STDDEV.clone(),
+        FN_PERCENTILE.clone(),
+    ]
+}
+
+/// Get the functions of the finance pack: `pmt`, `npv` and `irr`.
+pub fn finance_functions() -> Vec<Func> {
+    vec![FN_PMT.clone(), FN_NPV.clone(), FN_IRR.clone()]
+}
+
+fn non_empty(token: &str, args: &[f64]) -> Result<(), String> {
+    if args.is_empty() {
+        Err(format!("{} of no values", token))
+    } else {
+        Ok(())
+    }
+}
+
+fn mean(args: &[f64]) -> f64 {
+    args.iter().sum::<f64>() / args.len() as f64
+}
+
+fn sorted(args: &[f64]) -> Vec<f64> {
+    let mut sorted = args.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    sorted
+}
+
+/// The `p`th percentile of the non-empty `sorted` values
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let rank = p / 100.0 * (sorted.len() - 1) as f64;
+    let (below, above) = (rank.floor() as usize, rank.ceil() as usize);
+    sorted[below] + (sorted[above] - sorted[below]) * (rank - below as f64)
+}
+
+fn variance(args: &[f64]) -> Result<f64, String> {
+    if args.len() < 2 {
+        return Err("variance of less than two values".to_owned());
+    }
+    let mean = mean(args);
+    let squares: f64 = args.iter().map(|x| (x - mean).powi(2)).sum();
+    Ok(squares / (args.len() - 1) as f64)
+}
+
+/// The value of the cash flows at the time of the first one
+fn present_value(rate: f64, values: &[f64]) -> f64 {
+    values
+        .iter()
+        .rev()
+        .fold(0.0, |value, cash_flow| value / (1.0 + rate) + cash_flow)
+}
+
+fn irr(values: &[f64]) -> Result<f64, String> {
+    if !values.iter().any(|v| *v > 0.0) || !values.iter().any(|v| *v < 0.0) {
+        return Err("the cash flows must be both positive and negative".to_owned());
+    }
+    // the derivative of the present value by the rate
+    let slope = |rate: f64| -> f64 {
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, v)| -(i as f64) * v / (1.0 + rate).powi(i as i32 + 1))
+            .sum()
+    };
+    let mut rate = 0.1;
+    for _ in 0..100 {
+        let step = present_value(rate, values) / slope(rate);
+        rate -= step;
+        if !rate.is_finite() || rate <= -1.0 {
+            break;
+        }
+        if step.abs() < 1e-12 {
+            return Ok(rate);
+        }
+    }
+    Err("the rate is not found".to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::evaluator::{eval_str_with_vars_and_ctx, Error};
+    use crate::Ctx;
+
+    #[test]
+    fn test_packs() {
+        let ctx = Ctx::default().with_trig().with_statistics().with_finance();
+        let failed = |id: &str, message: &str| {
+            Err(Error::FunctionError {
+                id: id.to_owned(),
+                message: message.to_owned(),
+            })
+        };
+        let input_expected = &[
+            ("sec(0) + cot(pi / 4)", Ok(2.0)),
+            ("deg(pi) + rad(180) / pi", Ok(181.0)),
+            ("mean(1, 2, 3, 6)", Ok(3.0)),
+            ("mean()", failed("mean", "mean of no values")),
+            ("median(5, 1, 3)", Ok(3.0)),
+            ("median(4, 1, 3, 2)", Ok(2.5)),
+            ("variance(2, 4, 4, 4, 5, 5, 7, 9)", Ok(32.0 / 7.0)),
+            ("stddev(1, 3)", Ok(2f64.sqrt())),
+            (
+                "stddev(1)",
+                failed("stddev", "variance of less than two values"),
+            ),
+            ("percentile(25, 1, 2, 3, 4, 5)", Ok(2.0)),
+            ("percentile(90, 10, 20)", Ok(19.0)),
+            ("percentile(100, 3, 1, 2)", Ok(3.0)),
+            (
+                "percentile(101, 1)",
+                failed("percentile", "the percentile 101 is not from 0 to 100"),
+            ),
+            ("pmt(0, 10, 1000)", Ok(-100.0)),
+            ("pmt(0.1, 2, 0, fv = -210)", Ok(100.0)),
+            ("npv(0.1, 110, 121)", Ok(200.0)),
+            ("npv(0.1)", Ok(0.0)),
+            (
+                "irr(1, 2)",
+                failed("irr", "the cash flows must be both positive and negative"),
+            ),
+        ];
+        let mut vars = HashMap::new();
+        for (input, expected) in input_expected {
+            let result = eval_str_with_vars_and_ctx(input, &mut vars, &ctx);
+            match (result, expected) {
+                (Ok(result), Ok(expected)) => {
+                    assert!((result - expected).abs() < 1e-9, "input was {}", input)
+                }
+                (result, expected) => assert_eq!(result, *expected, "input was {}", input),
+            }
+        }
+        let pmt = eval_str_with_vars_and_ctx("pmt(0.05 / 12, 360, 100000)", &mut vars, &ctx);
+        assert!((pmt.unwrap() + 536.82).abs() < 0.01);
+        let irr = eval_str_with_vars_and_ctx("irr(-100, 60, 60)", &mut vars, &ctx);
+        assert!((irr.unwrap() - 0.130_662_386_291_808).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_packs_are_idempotent() {
+        let ctx = Ctx::default().with_statistics();
+        let twice = Ctx::default().with_statistics().with_statistics();
+        assert_eq!(ctx.fns.len(), twice.fns.len());
+        assert!(twice.validate().is_ok());
+        assert!(Ctx::default()
+            .with_trig()
+            .with_statistics()
+            .with_finance()
+            .validate()
+            .is_ok());
+    }
+}
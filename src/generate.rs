@@ -0,0 +1,352 @@
+//! Random generation of valid expressions over the items of the [`context`](crate::Ctx).
+//!
+//! [`Generator`](Generator) is deterministic for the given seed and doesn't depend on any testing framework,
+//! so it can be used to fuzz custom contexts or to produce large inputs for load testing.
+//! See [`testing`](crate::testing) for [`proptest`](https://docs.rs/proptest) strategies with shrinking.
+//!
+//! # Example
+//!
+//! ```
+//! use rusty_yard::generate::{Generator, Options};
+//! use rusty_yard::parser::parse_str;
+//! use rusty_yard::Ctx;
+//!
+//! let ctx = Ctx::default();
+//! let options = Options {
+//!     variables: vec!["x".to_owned()],
+//!     ..Options::default()
+//! };
+//! let mut generator = Generator::new(&ctx, options, 42);
+//! for _ in 0..10 {
+//!     let tree = generator.tree();
+//!     let input = tree.to_string();
+//!     assert_eq!(parse_str(&input, &ctx).ok(), tree.to_tokens(&ctx));
+//! }
+//! ```
+use std::fmt::{self, Display, Formatter};
+
+use crate::parser::ParserToken;
+use crate::random::XorShift;
+use crate::tokenizer::quote_id;
+use crate::Ctx;
+
+/// Generated expression tree.
+///
+/// [`Display`](std::fmt::Display) implementation produces the expression string
+/// with all tokens separated by spaces.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprTree {
+    /// Number literal.
+    Num(f64),
+    /// Variable.
+    Var(String),
+    /// Expression in parentheses.
+    Paren(Box<ExprTree>),
+    /// Unary operator applied to the expression.
+    UOp(String, Box<ExprTree>),
+    /// Binary operator applied to the expressions.
+    BiOp(String, Box<ExprTree>, Box<ExprTree>),
+    /// Function called with the arguments.
+    Func(String, Vec<ExprTree>),
+}
+
+impl ExprTree {
+    /// Converts the tree into the token stream in reverse polish notation.
+    ///
+    /// The tree is expected to have the parentheses wherever the precedence of the operators requires them,
+    /// as the trees produced by [`Generator`](Generator) do.
+    /// Returns `None` if the tree uses an item that is not in the `ctx`.
+    pub fn to_tokens<'t, 'ctx>(&'t self, ctx: &'ctx Ctx) -> Option<Vec<ParserToken<'t, 'ctx>>> {
+        let mut tokens = Vec::new();
+        self.push_tokens(ctx, &mut tokens)?;
+        Some(tokens)
+    }
+
+    fn push_tokens<'t, 'ctx>(
+        &'t self,
+        ctx: &'ctx Ctx,
+        tokens: &mut Vec<ParserToken<'t, 'ctx>>,
+    ) -> Option<()> {
+        match self {
+            ExprTree::Num(n) => tokens.push(ParserToken::Num(*n)),
+            ExprTree::Var(id) => tokens.push(ParserToken::Id(id)),
+            ExprTree::Paren(e) => e.push_tokens(ctx, tokens)?,
+            ExprTree::UOp(op, e) => {
+                e.push_tokens(ctx, tokens)?;
+                tokens.push(ParserToken::UOp(ctx.u_op(op)?));
+            }
+            ExprTree::BiOp(op, l, r) => {
+                l.push_tokens(ctx, tokens)?;
+                r.push_tokens(ctx, tokens)?;
+                tokens.push(ParserToken::BiOp(ctx.bi_op(op)?));
+            }
+            ExprTree::Func(id, args) => {
+                for arg in args {
+                    arg.push_tokens(ctx, tokens)?;
+                }
+                tokens.push(ParserToken::Func(ctx.func(id)?, args.len()));
+            }
+        }
+        Some(())
+    }
+}
+
+impl Display for ExprTree {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ExprTree::Num(n) => write!(f, "{}", n),
+            ExprTree::Var(id) => write!(f, "{}", quote_id(id)),
+            ExprTree::Paren(e) => write!(f, "( {} )", e),
+            ExprTree::UOp(op, e) => write!(f, "{} {}", op, e),
+            ExprTree::BiOp(op, l, r) => write!(f, "{} {} {}", l, op, r),
+            ExprTree::Func(id, args) => {
+                write!(f, "{}(", id)?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, " {}", arg)?;
+                }
+                write!(f, " )")
+            }
+        }
+    }
+}
+
+/// Constraints of the expressions produced by [`Generator`](Generator).
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Options {
+    /// The maximum depth of the tree, not counting the parentheses.
+    pub max_depth: usize,
+    /// The maximum number of arguments of the variadic functions.
+    pub max_args: usize,
+    /// The variables the expressions can use, the variables must not clash with the items of the context.
+    pub variables: Vec<String>,
+    /// Use unary operators.
+    pub unary_operators: bool,
+    /// Use binary operators.
+    pub binary_operators: bool,
+    /// Use functions.
+    pub functions: bool,
+    /// Put redundant parentheses around some subexpressions.
+    pub parentheses: bool,
+}
+
+impl Default for Options {
+    /// Creates the options that use every item of the context without variables, with the depth of 4.
+    fn default() -> Self {
+        Self {
+            max_depth: 4,
+            max_args: 4,
+            variables: Vec::new(),
+            unary_operators: true,
+            binary_operators: true,
+            functions: true,
+            parentheses: true,
+        }
+    }
+}
+
+/// Generator of random expressions over the items of the context.
+///
+/// The expressions are valid, i.e. they are always parsed by [`parser`](crate::parser),
+/// but their evaluation can still fail, e.g. in a custom function.
+#[derive(Debug)]
+pub struct Generator<'ctx> {
+    ctx: &'ctx Ctx,
+    options: Options,
+    rng: XorShift,
+}
+
+#[derive(Copy, Clone)]
+enum Branch {
+    UOp,
+    BiOp,
+    Func,
+}
+
+impl<'ctx> Generator<'ctx> {
+    /// Creates the generator, the same `seed` produces the same sequence of expressions.
+    pub fn new(ctx: &'ctx Ctx, options: Options, seed: u64) -> Self {
+        Self {
+            ctx,
+            options,
+            rng: XorShift::new(seed),
+        }
+    }
+
+    /// Generates the expression tree.
+    pub fn tree(&mut self) -> ExprTree {
+        self.node(self.options.max_depth)
+    }
+
+    /// Generates the expression string.
+    pub fn expression(&mut self) -> String {
+        self.tree().to_string()
+    }
+
+    fn node(&mut self, depth: usize) -> ExprTree {
+        let mut branches = Vec::with_capacity(3);
+        if self.options.unary_operators && self.ctx.u_ops.iter().any(|op| !op.postfix) {
+            branches.push(Branch::UOp);
+        }
+        if self.options.binary_operators && !self.ctx.bi_ops.is_empty() {
+            branches.push(Branch::BiOp);
+        }
+        if self.options.functions && !self.ctx.fns.is_empty() {
+            branches.push(Branch::Func);
+        }
+        // leaves get more likely closer to the max depth
+        if depth == 0 || branches.is_empty() || self.rng.below(self.options.max_depth + 1) >= depth
+        {
+            return self.leaf();
+        }
+        let node = match branches[self.rng.below(branches.len())] {
+            Branch::UOp => {
+                let prefix: Vec<_> = self.ctx.u_ops.iter().filter(|op| !op.postfix).collect();
+                let op = prefix[self.rng.below(prefix.len())];
+                let operand = self.operand(depth - 1);
+                ExprTree::UOp(op.token.clone(), Box::new(operand))
+            }
+            Branch::BiOp => {
+                let op = &self.ctx.bi_ops[self.rng.below(self.ctx.bi_ops.len())];
+                let left = self.operand(depth - 1);
+                let right = match self.operand(depth - 1) {
+                    // the operator that is also postfix, e.g. `%`, is binary only before the operand
+                    node @ ExprTree::UOp(..) if self.ctx.postfix_op(&op.token).is_some() => {
+                        ExprTree::Paren(Box::new(node))
+                    }
+                    node => node,
+                };
+                ExprTree::BiOp(op.token.clone(), Box::new(left), Box::new(right))
+            }
+            Branch::Func => {
+                let func = &self.ctx.fns[self.rng.below(self.ctx.fns.len())];
+                let n_args = match func.arity {
+                    Some(arity) => arity,
+                    None => self.rng.below(self.options.max_args + 1),
+                };
+                let args = (0..n_args).map(|_| self.node(depth - 1)).collect();
+                ExprTree::Func(func.token.clone(), args)
+            }
+        };
+        if self.options.parentheses && self.rng.below(8) == 0 {
+            ExprTree::Paren(Box::new(node))
+        } else {
+            node
+        }
+    }
+
+    /// The operand of an operator, it is put into parentheses if it is a binary operation,
+    /// or a unary operation with the [precedence](crate::operators::UOp::precedence)
+    fn operand(&mut self, depth: usize) -> ExprTree {
+        match self.node(depth) {
+            node @ ExprTree::BiOp(..) => ExprTree::Paren(Box::new(node)),
+            ExprTree::UOp(op, operand)
+                if self.ctx.u_op(&op).is_some_and(|op| op.precedence.is_some()) =>
+            {
+                ExprTree::Paren(Box::new(ExprTree::UOp(op, operand)))
+            }
+            node => node,
+        }
+    }
+
+    fn leaf(&mut self) -> ExprTree {
+        let variables = &self.options.variables;
+        if !variables.is_empty() && self.rng.below(2) == 0 {
+            return ExprTree::Var(variables[self.rng.below(variables.len())].clone());
+        }
+        // numbers with at most one decimal digit, so that they are easy to read
+        ExprTree::Num(self.rng.below(1000) as f64 / 10.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_str;
+
+    fn options() -> Options {
+        Options {
+            variables: vec!["a".to_owned(), "b".to_owned()],
+            ..Options::default()
+        }
+    }
+
+    #[test]
+    fn test_generated_parse() {
+        // the unary operators with the precedence are put into parentheses to be the operands
+        let mut unary_precedence = Ctx::default();
+        for op in &mut unary_precedence.u_ops {
+            op.precedence = Some(5);
+        }
+        for ctx in &[Ctx::default(), unary_precedence] {
+            let mut generator = Generator::new(ctx, options(), 0);
+            for _ in 0..500 {
+                let tree = generator.tree();
+                let input = tree.to_string();
+                assert_eq!(
+                    parse_str(&input, ctx).ok(),
+                    tree.to_tokens(ctx),
+                    "input was {}",
+                    input
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_deterministic() {
+        let ctx = Ctx::default();
+        let first: Vec<_> = {
+            let mut generator = Generator::new(&ctx, options(), 7);
+            (0..20).map(|_| generator.expression()).collect()
+        };
+        let mut generator = Generator::new(&ctx, options(), 7);
+        let second: Vec<_> = (0..20).map(|_| generator.expression()).collect();
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_options() {
+        fn max_depth(tree: &ExprTree) -> usize {
+            match tree {
+                ExprTree::Num(_) | ExprTree::Var(_) => 0,
+                ExprTree::Paren(e) => max_depth(e),
+                ExprTree::UOp(_, e) => 1 + max_depth(e),
+                ExprTree::BiOp(_, l, r) => 1 + max_depth(l).max(max_depth(r)),
+                ExprTree::Func(_, args) => 1 + args.iter().map(max_depth).max().unwrap_or(0),
+            }
+        }
+        let ctx = Ctx::default();
+        let options = Options {
+            max_depth: 3,
+            unary_operators: false,
+            functions: false,
+            parentheses: false,
+            ..Options::default()
+        };
+        let mut generator = Generator::new(&ctx, options, 1);
+        for _ in 0..100 {
+            let tree = generator.tree();
+            assert!(max_depth(&tree) <= 3, "tree was {}", tree);
+            let tokens = tree.to_tokens(&ctx).unwrap();
+            assert!(
+                tokens
+                    .iter()
+                    .all(|t| matches!(t, ParserToken::Num(_) | ParserToken::BiOp(_))),
+                "tree was {}",
+                tree
+            );
+        }
+        let empty = Ctx::empty();
+        let mut generator = Generator::new(&empty, Options::default(), 1);
+        assert!(matches!(generator.tree(), ExprTree::Num(_)));
+    }
+
+    #[test]
+    fn test_to_tokens_unknown() {
+        let tree = ExprTree::Func("unknown".to_owned(), vec![]);
+        assert_eq!(tree.to_tokens(&Ctx::default()), None);
+    }
+}
@@ -0,0 +1,228 @@
+//! Exports the structure of the parsed expressions as the graphs, to see how the expression was parsed.
+//!
+//! [`to_dot`](to_dot) writes the syntax tree in the [Graphviz](https://graphviz.org) DOT language
+//! and [`to_mermaid`](to_mermaid) writes it as the [Mermaid](https://mermaid.js.org) flowchart.
+//! Every node is labeled with its token, the binary operators also with their precedence and associativity,
+//! the unary operators with their precedence or position, and the functions with the number of the arguments,
+//! the operands of the node are in the order they were written.
+//!
+//! # Example
+//!
+//! ```
+//! use rusty_yard::graph::to_dot;
+//! use rusty_yard::parser::parse_str;
+//! use rusty_yard::Ctx;
+//!
+//! let ctx = Ctx::default();
+//! let tokens = parse_str("1 + max(a, 2)", &ctx).unwrap();
+//! assert_eq!(to_dot(&tokens).unwrap(), "\
+//! digraph expression {
+//!     ordering=out;
+//!     n0 [label=\"+\\nprecedence 4, left\"];
+//!     n1 [label=\"1\"];
+//!     n0 -> n1;
+//!     n2 [label=\"max\\n2 arguments\"];
+//!     n0 -> n2;
+//!     n3 [label=\"a\"];
+//!     n2 -> n3;
+//!     n4 [label=\"2\"];
+//!     n2 -> n4;
+//! }
+//! ");
+//! ```
+use std::fmt::Write;
+
+use crate::operators::binary::Associativity;
+use crate::parser::{Ast, ParserToken};
+use crate::printer::Error;
+
+/// Writes the expression in reverse polish notation `tokens` as the Graphviz DOT digraph.
+///
+/// The nodes are named `n0`, `n1` and so on in the depth-first order, the root is `n0`.
+/// Returns [`IllFormed`](crate::printer::Error::IllFormed) error if the tokens are not a single expression.
+pub fn to_dot(tokens: &[ParserToken]) -> Result<String, Error> {
+    let ast = Ast::from_rpn(tokens).ok_or(Error::IllFormed)?;
+    let mut output = String::from("digraph expression {\n    ordering=out;\n");
+    for node in nodes(&ast) {
+        let label = label(node.ast).replace('\\', "\\\\").replace('"', "\\\"");
+        // unwrap: writing to String never fails
+        writeln!(
+            output,
+            "    n{} [label=\"{}\"];",
+            node.id,
+            label.replace('\n', "\\n")
+        )
+        .unwrap();
+        if let Some(parent) = node.parent {
+            writeln!(output, "    n{} -> n{};", parent, node.id).unwrap();
+        }
+    }
+    output.push_str("}\n");
+    Ok(output)
+}
+
+/// Writes the expression in reverse polish notation `tokens` as the Mermaid flowchart from the top down.
+///
+/// The nodes are named like in [`to_dot`](to_dot).
+/// Returns [`IllFormed`](crate::printer::Error::IllFormed) error if the tokens are not a single expression.
+///
+/// # Example
+///
+/// ```
+/// use rusty_yard::graph::to_mermaid;
+/// use rusty_yard::parser::parse_str;
+/// use rusty_yard::Ctx;
+///
+/// let ctx = Ctx::default();
+/// let tokens = parse_str("-a", &ctx).unwrap();
+/// assert_eq!(to_mermaid(&tokens).unwrap(), "flowchart TD\n    n0[\"-<br/>prefix\"]\n    n1[\"a\"]\n    n0 --> n1\n");
+/// ```
+pub fn to_mermaid(tokens: &[ParserToken]) -> Result<String, Error> {
+    let ast = Ast::from_rpn(tokens).ok_or(Error::IllFormed)?;
+    let mut output = String::from("flowchart TD\n");
+    for node in nodes(&ast) {
+        let label = label(node.ast).replace('"', "#quot;");
+        // unwrap: writing to String never fails
+        writeln!(
+            output,
+            "    n{}[\"{}\"]",
+            node.id,
+            label.replace('\n', "<br/>")
+        )
+        .unwrap();
+        if let Some(parent) = node.parent {
+            writeln!(output, "    n{} --> n{}", parent, node.id).unwrap();
+        }
+    }
+    Ok(output)
+}
+
+/// The node of the tree with its number and the number of its parent
+struct Node<'n, 't, 'a, 'ctx> {
+    id: usize,
+    parent: Option<usize>,
+    ast: &'n Ast<'t, 'a, 'ctx>,
+}
+
+/// Numbers the nodes of the tree in the depth-first order
+fn nodes<'n, 't, 'a, 'ctx>(ast: &'n Ast<'t, 'a, 'ctx>) -> Vec<Node<'n, 't, 'a, 'ctx>> {
+    let mut nodes = Vec::new();
+    let mut stack = vec![(ast, None)];
+    while let Some((ast, parent)) = stack.pop() {
+        let id = nodes.len();
+        nodes.push(Node { id, parent, ast });
+        stack.extend(ast.operands.iter().rev().map(|operand| (operand, Some(id))));
+    }
+    nodes
+}
+
+/// The label of the node, the second line describes how the operator or the function is applied
+fn label(ast: &Ast) -> String {
+    match ast.token {
+        ParserToken::BiOp(op) => {
+            let associativity = match op.associativity {
+                Associativity::LEFT => "left",
+                Associativity::RIGHT => "right",
+            };
+            format!(
+                "{}\nprecedence {}, {}",
+                op.token, op.precedence, associativity
+            )
+        }
+        ParserToken::UOp(op) if op.postfix => format!("{}\npostfix", op.token),
+        ParserToken::UOp(op) => match op.precedence {
+            Some(precedence) => format!("{}\nprecedence {}", op.token, precedence),
+            None => format!("{}\nprefix", op.token),
+        },
+        ParserToken::Func(func, 1) => format!("{}\n1 argument", func.token),
+        ParserToken::Func(func, n_args) => format!("{}\n{} arguments", func.token, n_args),
+        ParserToken::Macro(m) => match m.assigns() {
+            Some(id) => format!("{}\nassigns {}", m.name(), id),
+            None => m.name().to_owned(),
+        },
+        token => token.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_str;
+    use crate::Ctx;
+
+    #[test]
+    fn test_to_dot() {
+        let ctx = Ctx {
+            strings: true,
+            ..Ctx::default_with_macros()
+        };
+        let input_expected = &[
+            (
+                "2 ^ 3 ^ 2",
+                vec![
+                    "n0 [label=\"^\\nprecedence 6, right\"];",
+                    "n1 [label=\"2\"];",
+                    "n0 -> n1;",
+                    "n2 [label=\"^\\nprecedence 6, right\"];",
+                    "n0 -> n2;",
+                    "n3 [label=\"3\"];",
+                    "n2 -> n3;",
+                    "n4 [label=\"2\"];",
+                    "n2 -> n4;",
+                ],
+            ),
+            (
+                "x = sin(\"a\\b\")",
+                vec![
+                    "n0 [label=\"=\\nassigns x\"];",
+                    "n1 [label=\"sin\\n1 argument\"];",
+                    "n0 -> n1;",
+                    "n2 [label=\"\\\"a\\\\b\\\"\"];",
+                    "n1 -> n2;",
+                ],
+            ),
+            (
+                "10%",
+                vec![
+                    "n0 [label=\"%\\npostfix\"];",
+                    "n1 [label=\"10\"];",
+                    "n0 -> n1;",
+                ],
+            ),
+        ];
+        for (input, expected) in input_expected {
+            let tokens = parse_str(input, &ctx).unwrap();
+            let dot = to_dot(&tokens).unwrap();
+            let lines: Vec<_> = dot.lines().map(str::trim).collect();
+            assert_eq!(
+                lines[..2],
+                ["digraph expression {", "ordering=out;"],
+                "input was {}",
+                input
+            );
+            assert_eq!(
+                lines[2..lines.len() - 1],
+                expected[..],
+                "input was {}",
+                input
+            );
+            assert_eq!(lines.last(), Some(&"}"), "input was {}", input);
+        }
+    }
+
+    #[test]
+    fn test_to_mermaid() {
+        let ctx = Ctx {
+            strings: true,
+            ..Ctx::default()
+        };
+        let tokens = parse_str("\"q\" + x", &ctx).unwrap();
+        assert_eq!(
+            to_mermaid(&tokens).unwrap(),
+            "flowchart TD\n    n0[\"+<br/>precedence 4, left\"]\n    n1[\"#quot;q#quot;\"]\n    n0 --> n1\n    n2[\"x\"]\n    n0 --> n2\n"
+        );
+        let tokens = vec![ParserToken::Num(1.0), ParserToken::Num(2.0)];
+        assert_eq!(to_mermaid(&tokens), Err(Error::IllFormed));
+        assert_eq!(to_dot(&tokens), Err(Error::IllFormed));
+    }
+}
@@ -0,0 +1,545 @@
+//! Reads the formulas written in LaTeX, e.g. copied from the documents.
+//!
+//! [`lower`](lower) rewrites the LaTeX math into the expression of this crate, that is then
+//! [tokenized](crate::tokenizer) and evaluated as usual, e.g. `\frac{1}{2} \cdot x^{2}` is `(1/2) * x^2`.
+//! The subset of LaTeX covers what [`to_latex`](crate::printer::to_latex) writes, except the string literals:
+//!
+//! * `{…}` groups, `\frac{a}{b}`, `\sqrt{x}`, `\sqrt[n]{x}`, `x^{2}` and `\left(…\right)`,
+//! * `\cdot`, `\times`, `\div`, `\bmod`, the comparisons like `\leq` and the logic like `\land` and `\lnot`,
+//! * the functions like `\sin` and `\ln`, `\log_{10}`, `\log_{b}(x)`, `\operatorname{name}`, `|x|` and `\lfloor x \rfloor`,
+//! * the greek letters like `\pi`, `\infty`, and the names in `\mathrm{…}`, `\mathit{…}` or `\text{…}`,
+//! * the subscripts of the variables, `x_{1}` is the variable `x_1`.
+//!
+//! As in the math, every letter is the variable, so `mv^2` is `m v^2`, add the
+//! [`ImplicitMultiplication`](crate::tokenizer::passes::ImplicitMultiplication) pass to the context to multiply them.
+//! The functions need their arguments in the parentheses or braces, `\sin{x}`, unless
+//! [`implicit_calls`](crate::Ctx::implicit_calls) are on.
+//!
+//! # Example
+//!
+//! ```
+//! use std::collections::HashMap;
+//! use rusty_yard::evaluator::eval_str_with;
+//! use rusty_yard::latex::lower;
+//! use rusty_yard::tokenizer::passes::ImplicitMultiplication;
+//! use rusty_yard::Ctx;
+//!
+//! let mut ctx = Ctx::default();
+//! ctx.passes.push(Box::new(ImplicitMultiplication));
+//! let expression = lower(r"\frac{1}{2} m v_{0}^{2}").unwrap();
+//! assert_eq!(expression, "(1/2) m v_0^2");
+//! let mut vars = HashMap::new();
+//! vars.insert("m".to_owned(), 4.0);
+//! vars.insert("v_0".to_owned(), 3.0);
+//! assert_eq!(eval_str_with(&expression, &mut vars, &ctx), Ok(18.0));
+//! ```
+use thiserror::Error;
+
+/// The LaTeX functions that are the functions of the context with the same name.
+const FUNCTIONS: &[&str] = &[
+    "sin", "cos", "tan", "sinh", "cosh", "tanh", "ln", "exp", "max", "min",
+];
+
+/// The greek letters that are the variables or the constants with the same name, e.g. `\pi` is `pi`.
+const GREEK: &[&str] = &[
+    "alpha", "beta", "gamma", "delta", "epsilon", "zeta", "eta", "theta", "iota", "kappa",
+    "lambda", "mu", "nu", "xi", "pi", "rho", "sigma", "tau", "upsilon", "phi", "chi", "psi",
+    "omega", "Gamma", "Delta", "Theta", "Lambda", "Xi", "Pi", "Sigma", "Upsilon", "Phi", "Psi",
+    "Omega",
+];
+
+/// Represents the error that can occur when the LaTeX is rewritten into the expression.
+#[derive(Debug, Error, Clone, Eq, PartialEq)]
+pub enum Error {
+    /// The command is not supported, contains the name of the command without `\`.
+    #[error("Unsupported command \\{0}")]
+    UnsupportedCommand(String),
+    /// The command or `^` is not followed by its argument, contains the command.
+    #[error("Expected the argument of {0}")]
+    ExpectedArgument(String),
+    /// The braces or the parentheses of the argument are not closed.
+    #[error("Unbalanced braces")]
+    Unbalanced,
+    /// The subscript is not the part of the variable name, contains the subscript.
+    #[error("Subscript {0} is not supported")]
+    Subscript(String),
+}
+
+/// Rewrites the LaTeX math `input` into the expression of this crate, see [the module docs](self).
+pub fn lower(input: &str) -> Result<String, Error> {
+    Ok(Lowering::new(input).run()?.trim().to_owned())
+}
+
+struct Lowering<'a> {
+    rest: &'a str,
+    out: String,
+    /// The number of `|` that are opened and not closed yet
+    open_bars: usize,
+}
+
+fn is_word(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_' || ch == '.' || ch == '`'
+}
+
+/// Puts the operand into the parentheses unless it is a number or a name
+fn operand(text: String) -> String {
+    if !text.is_empty() && text.chars().all(is_word) {
+        text
+    } else {
+        format!("({})", text)
+    }
+}
+
+impl<'a> Lowering<'a> {
+    fn new(input: &'a str) -> Self {
+        Lowering {
+            rest: input,
+            out: String::new(),
+            open_bars: 0,
+        }
+    }
+
+    fn run(mut self) -> Result<String, Error> {
+        while let Some(ch) = self.rest.chars().next() {
+            match ch {
+                '\\' => {
+                    self.advance(1);
+                    let name = self.command_name();
+                    self.command(name)?;
+                }
+                '{' => {
+                    let group = lower(self.group()?)?;
+                    self.push(&format!("({})", group));
+                }
+                '}' => return Err(Error::Unbalanced),
+                '^' => {
+                    self.advance(1);
+                    let exponent = self.arg("^")?;
+                    self.push("^");
+                    self.push(&operand(exponent));
+                }
+                '_' => {
+                    self.advance(1);
+                    self.subscript()?;
+                }
+                '|' => {
+                    self.advance(1);
+                    let ends_operand = self
+                        .out
+                        .trim_end()
+                        .ends_with(|ch: char| is_word(ch) || ch == ')');
+                    if self.open_bars > 0 && ends_operand {
+                        self.open_bars -= 1;
+                        self.push(")");
+                    } else {
+                        self.open_bars += 1;
+                        self.push("abs(");
+                    }
+                }
+                '[' | ']' => {
+                    self.advance(1);
+                    self.push(if ch == '[' { "(" } else { ")" });
+                }
+                ch if ch.is_ascii_digit() || ch == '.' => {
+                    let len = self
+                        .rest
+                        .find(|ch: char| !ch.is_ascii_digit() && ch != '.')
+                        .unwrap_or(self.rest.len());
+                    let number = &self.rest[..len];
+                    self.advance(len);
+                    self.push(number);
+                }
+                ch if ch.is_whitespace() => {
+                    self.advance(ch.len_utf8());
+                    self.space();
+                }
+                ch => {
+                    // every letter is the variable, the other characters are the operators
+                    self.advance(ch.len_utf8());
+                    self.push(ch.encode_utf8(&mut [0; 4]));
+                }
+            }
+        }
+        Ok(self.out)
+    }
+
+    fn advance(&mut self, len: usize) {
+        self.rest = &self.rest[len..];
+    }
+
+    /// Appends the `piece` to the output, separated by the space if both of them are the names or the numbers
+    fn push(&mut self, piece: &str) {
+        if piece.starts_with(')') {
+            self.out.truncate(self.out.trim_end().len());
+        }
+        let separate = self.out.ends_with(is_word) && piece.starts_with(is_word);
+        if separate {
+            self.out.push(' ');
+        }
+        self.out.push_str(piece);
+    }
+
+    fn space(&mut self) {
+        if !self.out.is_empty() && !self.out.ends_with([' ', '(']) {
+            self.out.push(' ');
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.rest = self.rest.trim_start();
+    }
+
+    /// Reads the name of the command after `\`, the letters or the single character
+    fn command_name(&mut self) -> &'a str {
+        let len = match self.rest.find(|ch: char| !ch.is_ascii_alphabetic()) {
+            Some(0) => self.rest.chars().next().map_or(0, char::len_utf8),
+            Some(len) => len,
+            None => self.rest.len(),
+        };
+        let name = &self.rest[..len];
+        self.advance(len);
+        name
+    }
+
+    fn command(&mut self, name: &str) -> Result<(), Error> {
+        let piece = match name {
+            "cdot" | "times" | "ast" => "*",
+            "div" => "/",
+            "bmod" | "mod" | "%" => "%",
+            "le" | "leq" => "<=",
+            "ge" | "geq" => ">=",
+            "ne" | "neq" => "!=",
+            "land" | "wedge" => "&&",
+            "lor" | "vee" => "||",
+            "lnot" | "neg" => "!",
+            "infty" => "inf",
+            "arcsin" => "asin",
+            "arccos" => "acos",
+            "arctan" => "atan",
+            "lfloor" => "floor(",
+            "lceil" => "ceil(",
+            "rfloor" | "rceil" | "}" => ")",
+            "{" => "(",
+            "," | ";" | ":" | "!" | " " | "quad" | "qquad" => {
+                self.space();
+                return Ok(());
+            }
+            "frac" | "dfrac" | "tfrac" => {
+                let numerator = operand(self.arg(name)?);
+                let denominator = operand(self.arg(name)?);
+                self.push(&format!("({}/{})", numerator, denominator));
+                return Ok(());
+            }
+            "sqrt" => {
+                let index = match self.rest.strip_prefix('[') {
+                    Some(rest) => {
+                        let end = rest.find(']').ok_or(Error::Unbalanced)?;
+                        self.rest = &rest[end + 1..];
+                        Some(lower(&rest[..end])?)
+                    }
+                    None => None,
+                };
+                let radicand = self.arg(name)?;
+                match index {
+                    Some(index) => {
+                        self.push(&format!("{}^(1/{})", operand(radicand), operand(index)))
+                    }
+                    None => self.push(&format!("sqrt({})", radicand)),
+                }
+                return Ok(());
+            }
+            "log" => return self.log(),
+            "left" => return self.left(),
+            "right" => return self.right(),
+            "operatorname" | "mathrm" | "mathit" | "text" => {
+                self.skip_whitespace();
+                if !self.rest.starts_with('{') {
+                    return Err(Error::ExpectedArgument(format!("\\{}", name)));
+                }
+                let name = self.group()?.replace("\\_", "_").replace("\\ ", " ");
+                if name.chars().all(|ch| ch.is_alphanumeric() || ch == '_') {
+                    self.push(&name);
+                } else {
+                    self.push(&format!("`{}`", name));
+                }
+                return Ok(());
+            }
+            name if FUNCTIONS.contains(&name) || GREEK.contains(&name) => name,
+            name => return Err(Error::UnsupportedCommand(name.to_owned())),
+        };
+        self.push(piece);
+        Ok(())
+    }
+
+    /// Reads the group in braces and returns its content
+    fn group(&mut self) -> Result<&'a str, Error> {
+        let mut depth = 0;
+        let mut escaped = false;
+        for (i, ch) in self.rest.char_indices() {
+            match ch {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        let content = &self.rest[1..i];
+                        self.advance(i + 1);
+                        return Ok(content);
+                    }
+                }
+                _ => {}
+            }
+        }
+        Err(Error::Unbalanced)
+    }
+
+    /// Reads the raw argument of the command: the group, the command or the single character
+    fn raw_arg(&mut self, command: &str) -> Result<&'a str, Error> {
+        self.skip_whitespace();
+        match self.rest.chars().next() {
+            Some('{') => self.group(),
+            Some('\\') => {
+                let start = self.rest;
+                self.advance(1);
+                let name = self.command_name();
+                Ok(&start[..name.len() + 1])
+            }
+            Some(ch) if ch != '}' => {
+                let arg = &self.rest[..ch.len_utf8()];
+                self.advance(ch.len_utf8());
+                Ok(arg)
+            }
+            _ => Err(Error::ExpectedArgument(command_text(command))),
+        }
+    }
+
+    /// Reads the argument of the command and rewrites it
+    fn arg(&mut self, command: &str) -> Result<String, Error> {
+        lower(self.raw_arg(command)?)
+    }
+
+    fn subscript(&mut self) -> Result<(), Error> {
+        let subscript = self.raw_arg("_")?;
+        let name_start = self
+            .out
+            .trim_end_matches(|ch: char| ch.is_alphanumeric() || ch == '_')
+            .len();
+        let is_name = self.out[name_start..].starts_with(char::is_alphabetic)
+            && !subscript.is_empty()
+            && subscript.chars().all(char::is_alphanumeric);
+        if !is_name {
+            return Err(Error::Subscript(subscript.to_owned()));
+        }
+        self.out.push('_');
+        self.out.push_str(subscript);
+        Ok(())
+    }
+
+    /// `\log`, `\log_{10}` and `\log_{b}(x)`, that is `log(b, x)`
+    fn log(&mut self) -> Result<(), Error> {
+        let rest = match self.rest.strip_prefix('_') {
+            Some(rest) => rest,
+            None => {
+                self.push("log");
+                return Ok(());
+            }
+        };
+        self.rest = rest;
+        match self.raw_arg("_")? {
+            "10" => self.push("log10"),
+            "2" => self.push("log2"),
+            base => {
+                let base = lower(base)?;
+                let x = self.call_arg()?;
+                self.push(&format!("log({}, {})", base, x));
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the argument of the function in the braces or the parentheses
+    fn call_arg(&mut self) -> Result<String, Error> {
+        self.skip_whitespace();
+        if self.rest.starts_with('{') {
+            return lower(self.group()?);
+        }
+        if let Some(rest) = self.rest.strip_prefix("\\left") {
+            self.rest = rest.trim_start();
+        }
+        if !self.rest.starts_with('(') {
+            return Err(Error::ExpectedArgument("\\log".to_owned()));
+        }
+        let mut depth = 0;
+        for (i, ch) in self.rest.char_indices() {
+            match ch {
+                '(' => depth += 1,
+                ')' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        let content = &self.rest[1..i];
+                        self.advance(i + 1);
+                        let content = content.trim_end();
+                        return lower(content.strip_suffix("\\right").unwrap_or(content));
+                    }
+                }
+                _ => {}
+            }
+        }
+        Err(Error::Unbalanced)
+    }
+
+    /// `\left` followed by the delimiter
+    fn left(&mut self) -> Result<(), Error> {
+        self.skip_whitespace();
+        let (piece, len) = match self.rest.chars().next() {
+            Some('(') | Some('[') => ("(", 1),
+            Some('|') => ("abs(", 1),
+            Some('.') => ("", 1),
+            _ if self.rest.starts_with("\\{") => ("(", 2),
+            // the commands like `\lfloor` open by themselves
+            _ => return Ok(()),
+        };
+        self.advance(len);
+        self.push(piece);
+        Ok(())
+    }
+
+    /// `\right` followed by the delimiter
+    fn right(&mut self) -> Result<(), Error> {
+        self.skip_whitespace();
+        let (piece, len) = match self.rest.chars().next() {
+            Some(')') | Some(']') | Some('|') => (")", 1),
+            Some('.') => ("", 1),
+            _ if self.rest.starts_with("\\}") => (")", 2),
+            _ => return Ok(()),
+        };
+        self.advance(len);
+        self.push(piece);
+        Ok(())
+    }
+}
+
+/// The command as it is written in the input, for the errors
+fn command_text(command: &str) -> String {
+    match command {
+        "^" | "_" => command.to_owned(),
+        command => format!("\\{}", command),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::evaluator::eval_str_with;
+    use crate::parser::parse_str;
+    use crate::printer::to_latex;
+    use crate::tokenizer::passes::ImplicitMultiplication;
+    use crate::Ctx;
+
+    #[test]
+    fn test_lower() {
+        let input_expected = &[
+            (r"1 + 2", "1 + 2"),
+            (r"\frac{1}{2}", "(1/2)"),
+            (r"\frac{a + 1}{b_{2}}", "((a + 1)/b_2)"),
+            (r"\frac12", "(1/2)"),
+            (r"2 \cdot x^{2}", "2 * x^2"),
+            (r"x^{n+1}", "x^(n+1)"),
+            (r"e^\pi", "e^pi"),
+            (r"\sin(x)^2 + \cos{x}^2", "sin(x)^2 + cos(x)^2"),
+            (r"\sqrt{a^2 + b^2}", "sqrt(a^2 + b^2)"),
+            (r"\sqrt[3]{27}", "27^(1/3)"),
+            (r"\left(1 + x\right) \times 3", "(1 + x) * 3"),
+            (r"\left| x - 1 \right| + |y|", "abs(x - 1) + abs(y)"),
+            (r"\lfloor 2.5 \rfloor", "floor(2.5)"),
+            (r"\log_{10}(100) + \log_2 8", "log10(100) + log2 8"),
+            (r"\log_{b}\left(x\right)", "log(b, x)"),
+            (r"a \leq b \land \lnot c", "a <= b && ! c"),
+            (r"2\pi r", "2 pi r"),
+            (r"mv", "m v"),
+            (
+                r"\mathrm{price} \cdot \text{unit\ count}",
+                "price * `unit count`",
+            ),
+            (r"\mathit{total\_1} \div \infty", "total_1 / inf"),
+            (r"x = \frac{1}{2}\,y", "x = (1/2) y"),
+        ];
+        for (input, expected) in input_expected {
+            assert_eq!(
+                lower(input).as_deref(),
+                Ok(*expected),
+                "input was {}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_lower_errors() {
+        let input_expected = &[
+            (r"\int x", Error::UnsupportedCommand("int".to_owned())),
+            (r"\frac{1}", Error::ExpectedArgument("\\frac".to_owned())),
+            (r"x^", Error::ExpectedArgument("^".to_owned())),
+            (r"\frac{1}{2", Error::Unbalanced),
+            (r"x}", Error::Unbalanced),
+            (r"x_{i+1}", Error::Subscript("i+1".to_owned())),
+            (r"2_1", Error::Subscript("1".to_owned())),
+            (r"\log_3 x", Error::ExpectedArgument("\\log".to_owned())),
+        ];
+        for (input, expected) in input_expected {
+            assert_eq!(lower(input).as_ref(), Err(expected), "input was {}", input);
+        }
+    }
+
+    #[test]
+    fn test_lower_eval() {
+        let mut ctx = Ctx::default();
+        ctx.passes.push(Box::new(ImplicitMultiplication));
+        let mut vars = HashMap::new();
+        vars.insert("x".to_owned(), 3.0);
+        vars.insert("x_1".to_owned(), 4.0);
+        let input_expected = &[
+            (r"\frac{x^{2} + 1}{2}", 5.0),
+            (r"\sqrt{x^2 + x_{1}^2}", 5.0),
+            (r"2x \cdot \left(x - 1\right)", 12.0),
+            (r"\log_{x}{27} + \lfloor \frac{x}{2} \rfloor", 4.0),
+            (r"|1 - x| \times \max(x, 1)", 6.0),
+        ];
+        for (input, expected) in input_expected {
+            let expression = lower(input).unwrap();
+            let result = eval_str_with(&expression, &mut vars, &ctx).unwrap();
+            assert!((result - expected).abs() < 1e-9, "input was {}", input);
+        }
+    }
+
+    #[test]
+    fn test_lower_to_latex() {
+        let ctx = Ctx::default();
+        let mut vars = HashMap::new();
+        vars.insert("a".to_owned(), 5.0);
+        vars.insert("x".to_owned(), 27.0);
+        vars.insert("unit price".to_owned(), 3.0);
+        vars.insert("alpha".to_owned(), 1.0);
+        let inputs = &[
+            "(a + 1) / 2 * sqrt(x) ^ 2",
+            "-(2 ^ 3) % 5 // hypot(`unit price`, 4)",
+            "abs(log(2, 8) - cbrt(x)) <= floor(10%) && !(alpha > inf)",
+        ];
+        for input in inputs {
+            let tokens = parse_str(input, &ctx).unwrap();
+            let latex = to_latex(&tokens).unwrap();
+            let expression = lower(&latex).unwrap();
+            // `//` is written as the floor of the fraction, so only the results are the same
+            let expected = eval_str_with(input, &mut vars, &ctx).unwrap();
+            assert_eq!(
+                eval_str_with(&expression, &mut vars, &ctx),
+                Ok(expected),
+                "input was {}",
+                input
+            );
+        }
+    }
+}
@@ -5,23 +5,58 @@
 //!
 //! See [evaluator](crate::evaluator) documentation to get started with high level api that allows you to evaluate strings directly.
 #![deny(missing_docs)]
+use builder::{CtxBuilder, CtxConflict, CtxError};
+use constants::Const;
 use functions::Func;
+use limits::Limits;
 use macros::{default::default_macros, Macro};
+use operators::unary::PercentMode;
 use operators::{binary, unary, BiOp, UOp};
+use random::Rng;
+use tokenizer::{TokenPass, TokenizeOptions};
+use value::units::Units;
 
+pub mod analysis;
+#[cfg(feature = "async")]
+pub mod asynchronous;
+pub mod builder;
+pub mod constants;
+pub mod diagnostics;
 // reason api not stable
 #[allow(clippy::implicit_hasher)]
 pub mod evaluator;
+pub mod expression;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod fmt;
 pub mod functions;
+pub mod generate;
+pub mod graph;
+#[cfg(feature = "latex")]
+pub mod latex;
+pub mod limits;
 pub mod macros;
+pub mod monte_carlo;
+pub mod numeric;
 pub mod operators;
 pub mod parser;
+pub mod printer;
+pub mod program;
+pub mod random;
+pub mod sample;
+pub mod session;
+mod suggest;
+#[cfg(any(test, feature = "proptest"))]
+pub mod testing;
 pub mod tokenizer;
+pub mod value;
+pub mod variables;
 
 /// The context of the expression
 ///
 /// It is used to make [tokenization](crate::tokenizer) more resalable form human perspective and
 /// to actually parse the expression into a steam of tokens that can be executed by [`evaluator`](crate::evaluator).
+#[derive(Debug)]
 pub struct Ctx {
     /// Binary operators
     pub bi_ops: Vec<BiOp>,
@@ -29,8 +64,44 @@ pub struct Ctx {
     pub u_ops: Vec<UOp>,
     /// Functions that this context contains
     pub fns: Vec<Func>,
+    /// Named constants that this context contains, see [`constants`](crate::constants)
+    pub consts: Vec<Const>,
     /// Macros that this context contains
     pub macros: Vec<Box<dyn Macro>>,
+    /// Token passes that run between tokenization and parsing, see [`passes`](crate::tokenizer::passes)
+    pub passes: Vec<Box<dyn TokenPass>>,
+    /// How the parser treats the percents after `+` and `-`, see [`PercentMode`](crate::operators::unary::PercentMode)
+    pub percent_mode: PercentMode,
+    /// Allows calling the functions of one argument without the parentheses, e.g. `sin x` or `sqrt 2`.
+    ///
+    /// The function is applied to the next operand before any binary operator, so `sin x ^ 2` is `sin(x) ^ 2`.
+    pub implicit_calls: bool,
+    /// Tokenizes the text in double quotes as the [string literal](crate::tokenizer#string-literals), e.g. `lookup("steel", t)`.
+    ///
+    /// Off by default, `"` is then the part of the identifiers.
+    pub strings: bool,
+    /// Skips the [comments](crate::tokenizer#comments), `# …` to the end of the line and `/* … */`.
+    ///
+    /// Off by default, `#` can then be the operator or the part of the identifiers.
+    pub comments: bool,
+    /// Restricts the identifiers to `[A-Za-z_][A-Za-z0-9_]*`, see [strict identifiers](crate::tokenizer#strict-identifiers).
+    ///
+    /// Off by default, the identifiers can then contain any characters except the whitespace, the parens, the commas
+    /// and the operators, so `a$b` is one identifier.
+    pub strict_ids: bool,
+    /// The decimal separator and the case sensitivity of the tokens, see [`TokenizeOptions`](crate::tokenizer::TokenizeOptions).
+    pub tokenize_options: TokenizeOptions,
+    /// The alternative tokens of the operators, the functions and the constants, the pairs of the alias and the token,
+    /// e.g. `("×", "*")`, see [`default_aliases`](crate::operators::default_aliases).
+    ///
+    /// The alias is parsed as the item with the token, so the [printer](crate::printer) writes the token.
+    /// The aliases made of the letters, the digits and `_`, e.g. `mod`, are tokenized as the identifiers,
+    /// so they don't split the identifiers they are part of.
+    pub aliases: Vec<(String, String)>,
+    /// The limits of the parsing and the evaluation, see [`limits`](crate::limits)
+    pub limits: Limits,
+    /// The registry of the units of the [unit-aware calculations](crate::value::units), empty by default.
+    pub units: Units,
 }
 
 impl Ctx {
@@ -40,7 +111,18 @@ impl Ctx {
             bi_ops,
             u_ops,
             fns,
+            consts: Vec::new(),
             macros: Vec::new(),
+            passes: Vec::new(),
+            percent_mode: PercentMode::Plain,
+            implicit_calls: false,
+            strings: false,
+            comments: false,
+            strict_ids: false,
+            tokenize_options: TokenizeOptions::default(),
+            aliases: Vec::new(),
+            limits: Limits::default(),
+            units: Units::new(),
         }
     }
 
@@ -50,8 +132,221 @@ impl Ctx {
             bi_ops: Vec::new(),
             u_ops: Vec::new(),
             fns: Vec::new(),
+            consts: Vec::new(),
             macros: Vec::new(),
+            passes: Vec::new(),
+            percent_mode: PercentMode::Plain,
+            implicit_calls: false,
+            strings: false,
+            comments: false,
+            strict_ids: false,
+            tokenize_options: TokenizeOptions::default(),
+            aliases: Vec::new(),
+            limits: Limits::default(),
+            units: Units::new(),
+        }
+    }
+
+    /// Creates the builder of the context that validates it, see [`builder`](crate::builder).
+    pub fn builder() -> CtxBuilder {
+        CtxBuilder::new()
+    }
+
+    /// Checks that the items of the context don't conflict with each other.
+    ///
+    /// Returns the first [`CtxError`](crate::builder::CtxError) found.
+    pub fn validate(&self) -> Result<(), CtxError> {
+        builder::validate(self)
+    }
+
+    /// Adds the items of the `other` context to this one, and returns the merged context.
+    ///
+    /// Fails if an operator, a function or a constant of the `other` context has the same token as the item of this one,
+    /// so the domain-specific items can't silently shadow the base ones, see [`CtxConflict`](crate::builder::CtxConflict).
+    /// The macros, the passes and the aliases of the `other` context are added after the ones of this context,
+    /// its [`units`](Ctx::units) replace the ones of the same symbols, the settings like [`percent_mode`](Ctx::percent_mode) and the [`limits`](Ctx::limits) are the ones of this context.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rusty_yard::builder::{CtxConflict, ItemKind};
+    /// use rusty_yard::functions::Func;
+    /// use rusty_yard::Ctx;
+    ///
+    /// let finance = Ctx::new(vec![], vec![], vec![Func::new("pv", Some(3), |args| {
+    ///     args[2] / (1.0 + args[0]).powf(args[1])
+    /// })]);
+    /// let ctx = Ctx::default().merge(finance).unwrap();
+    /// assert!(ctx.func("pv").is_some() && ctx.func("max").is_some());
+    ///
+    /// let clashing = Ctx::new(vec![], vec![], vec![Func::new("max", Some(1), |args| args[0])]);
+    /// assert_eq!(
+    ///     Ctx::default().merge(clashing).unwrap_err(),
+    ///     CtxConflict { kind: ItemKind::Function, token: "max".to_owned() }
+    /// );
+    /// ```
+    pub fn merge(mut self, other: Ctx) -> Result<Ctx, CtxConflict> {
+        self.extend_bi_ops(other.bi_ops)?;
+        self.extend_u_ops(other.u_ops)?;
+        self.extend_fns(other.fns)?;
+        self.extend_consts(other.consts)?;
+        self.macros.extend(other.macros);
+        self.passes.extend(other.passes);
+        self.aliases.extend(other.aliases);
+        self.units.units.extend(other.units.units);
+        Ok(self)
+    }
+
+    /// Adds the binary operators, see [`merge`](Ctx::merge) for the conflicts.
+    ///
+    /// Nothing is added if any of the operators conflicts.
+    pub fn extend_bi_ops(
+        &mut self,
+        ops: impl IntoIterator<Item = BiOp>,
+    ) -> Result<(), CtxConflict> {
+        let ops: Vec<_> = ops.into_iter().collect();
+        let added: Vec<_> = ops.iter().map(builder::bi_op_item).collect();
+        builder::check_conflicts(&builder::items(self), &added)?;
+        self.bi_ops.extend(ops);
+        Ok(())
+    }
+
+    /// Adds the unary operators, see [`merge`](Ctx::merge) for the conflicts.
+    ///
+    /// Nothing is added if any of the operators conflicts.
+    pub fn extend_u_ops(&mut self, ops: impl IntoIterator<Item = UOp>) -> Result<(), CtxConflict> {
+        let ops: Vec<_> = ops.into_iter().collect();
+        let added: Vec<_> = ops.iter().map(builder::u_op_item).collect();
+        builder::check_conflicts(&builder::items(self), &added)?;
+        self.u_ops.extend(ops);
+        Ok(())
+    }
+
+    /// Adds the functions, see [`merge`](Ctx::merge) for the conflicts.
+    ///
+    /// Nothing is added if any of the functions conflicts.
+    pub fn extend_fns(&mut self, fns: impl IntoIterator<Item = Func>) -> Result<(), CtxConflict> {
+        let fns: Vec<_> = fns.into_iter().collect();
+        let added: Vec<_> = fns.iter().map(builder::func_item).collect();
+        builder::check_conflicts(&builder::items(self), &added)?;
+        self.fns.extend(fns);
+        Ok(())
+    }
+
+    /// Adds the constants, see [`merge`](Ctx::merge) for the conflicts.
+    ///
+    /// Nothing is added if any of the constants conflicts.
+    pub fn extend_consts(
+        &mut self,
+        consts: impl IntoIterator<Item = Const>,
+    ) -> Result<(), CtxConflict> {
+        let consts: Vec<_> = consts.into_iter().collect();
+        let added: Vec<_> = consts.iter().map(builder::const_item).collect();
+        builder::check_conflicts(&builder::items(self), &added)?;
+        self.consts.extend(consts);
+        Ok(())
+    }
+
+    /// Adds the [trigonometry pack](crate::functions::packs::trig_functions) of the functions.
+    ///
+    /// The functions the context already has are kept, so the packs can be added in any order and more than once.
+    pub fn with_trig(self) -> Self {
+        self.with_pack(functions::packs::trig_functions())
+    }
+
+    /// Adds the [statistics pack](crate::functions::packs::statistics_functions) of the functions,
+    /// see [`with_trig`](Ctx::with_trig).
+    pub fn with_statistics(self) -> Self {
+        self.with_pack(functions::packs::statistics_functions())
+    }
+
+    /// Adds the [finance pack](crate::functions::packs::finance_functions) of the functions,
+    /// see [`with_trig`](Ctx::with_trig).
+    pub fn with_finance(self) -> Self {
+        self.with_pack(functions::packs::finance_functions())
+    }
+
+    /// Adds the random functions of the `rng`, see [`Rng::functions`](crate::random::Rng::functions).
+    ///
+    /// The functions of the context with the same tokens are kept.
+    pub fn with_random(self, rng: &Rng) -> Self {
+        self.with_pack(rng.functions())
+    }
+
+    fn with_pack(mut self, pack: Vec<Func>) -> Self {
+        for func in pack {
+            if self.func(&func.token).is_none() && self.constant(&func.token).is_none() {
+                self.fns.push(func);
+            }
         }
+        self
+    }
+
+    /// Finds the binary operator with identifier `token`, or with the token the `token` is the [alias](Ctx::aliases) of.
+    pub fn bi_op(&self, token: &str) -> Option<&BiOp> {
+        let find = |token: &str| self.bi_ops.iter().find(|op| op.token == token);
+        find(token).or_else(|| find(self.aliased(token)?))
+    }
+
+    /// Finds the prefix unary operator with identifier `token`, or with the token the `token` is the [alias](Ctx::aliases) of.
+    pub fn u_op(&self, token: &str) -> Option<&UOp> {
+        let find = |token: &str| {
+            self.u_ops
+                .iter()
+                .find(|op| op.token == token && !op.postfix)
+        };
+        find(token).or_else(|| find(self.aliased(token)?))
+    }
+
+    /// Finds the [postfix](crate::operators::UOp::postfix) unary operator with identifier `token`,
+    /// or with the token the `token` is the [alias](Ctx::aliases) of.
+    pub fn postfix_op(&self, token: &str) -> Option<&UOp> {
+        let find = |token: &str| self.u_ops.iter().find(|op| op.token == token && op.postfix);
+        find(token).or_else(|| find(self.aliased(token)?))
+    }
+
+    /// Finds the function with identifier `token`, or with the token the `token` is the [alias](Ctx::aliases) of.
+    ///
+    /// With [`case_insensitive`](crate::tokenizer::TokenizeOptions::case_insensitive) tokens
+    /// the function with the token of the other case is found if there is no exact match.
+    pub fn func(&self, token: &str) -> Option<&Func> {
+        let find = |token: &str| self.fns.iter().find(|f| f.token == token);
+        find(token)
+            .or_else(|| find(self.aliased(token)?))
+            .or_else(|| {
+                self.fns
+                    .iter()
+                    .find(|f| self.same_ignoring_case(&f.token, token))
+            })
+    }
+
+    /// Finds the constant with identifier `token`, or with the token the `token` is the [alias](Ctx::aliases) of.
+    ///
+    /// The constants are found regardless of the case like the [functions](Ctx::func).
+    pub fn constant(&self, token: &str) -> Option<&Const> {
+        let find = |token: &str| self.consts.iter().find(|c| c.token == token);
+        find(token)
+            .or_else(|| find(self.aliased(token)?))
+            .or_else(|| {
+                let same = |c: &&Const| self.same_ignoring_case(&c.token, token);
+                self.consts.iter().find(same)
+            })
+    }
+
+    /// Whether the tokens differ only in case and the tokens are [case-insensitive](TokenizeOptions::case_insensitive)
+    fn same_ignoring_case(&self, a: &str, b: &str) -> bool {
+        self.tokenize_options.case_insensitive
+            && a.chars()
+                .flat_map(char::to_lowercase)
+                .eq(b.chars().flat_map(char::to_lowercase))
+    }
+
+    /// Returns the token the `alias` stands for, see [`aliases`](Ctx::aliases).
+    pub fn aliased(&self, alias: &str) -> Option<&str> {
+        self.aliases
+            .iter()
+            .find(|(other, _)| other == alias)
+            .map(|(_, token)| token.as_str())
     }
 
     /// Creates new default context that is similar to the one produced by [`default`](std::default::Default::default) but also has default macros enabled.
@@ -72,13 +367,25 @@ impl Default for Ctx {
     ///
     /// - [binary::default_operators](crate::operators::binary::default_operators) to populate binary operators;
     /// - [unary::default_operators](crate::operators::unary::default_operators) to populate binary operators;
-    /// - [functions::default_functions](crate::functions::default_functions) to populate functions.
+    /// - [functions::default_functions](crate::functions::default_functions) to populate functions;
+    /// - [constants::default_constants](crate::constants::default_constants) to populate constants.
     fn default() -> Self {
         Self {
             bi_ops: binary::default_operators(),
             u_ops: unary::default_operators(),
             fns: functions::default_functions(),
+            consts: constants::default_constants(),
             macros: Vec::new(),
+            passes: Vec::new(),
+            percent_mode: PercentMode::Plain,
+            implicit_calls: false,
+            strings: false,
+            comments: false,
+            strict_ids: false,
+            tokenize_options: TokenizeOptions::default(),
+            aliases: Vec::new(),
+            limits: Limits::default(),
+            units: Units::new(),
         }
     }
 }
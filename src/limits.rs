@@ -0,0 +1,146 @@
+//! The limits that protect the services evaluating the untrusted expressions from the memory and cpu blowups.
+//!
+//! The limits are the part of the [`Ctx`](crate::Ctx), so every expression parsed and evaluated with the context
+//! is checked against them. By default there are no limits.
+//! The limits of the evaluation are checked by the functions of the [`evaluator`](crate::evaluator),
+//! the [`Value`](crate::value::Value) evaluation and the [`CompiledExpr`](crate::expression::CompiledExpr) check the parser limits only.
+//!
+//! # Example
+//!
+//! ```
+//! use rusty_yard::evaluator::{eval_str_with_vars_and_ctx, Error};
+//! use rusty_yard::limits::Limits;
+//! use rusty_yard::{parser, Ctx};
+//! use std::collections::HashMap;
+//!
+//! let ctx = Ctx {
+//!     limits: Limits {
+//!         max_tokens: Some(5),
+//!         ..Limits::untrusted()
+//!     },
+//!     ..Ctx::default()
+//! };
+//! let mut vars = HashMap::new();
+//! assert_eq!(eval_str_with_vars_and_ctx("1 + 2", &mut vars, &ctx), Ok(3.0));
+//! assert_eq!(
+//!     eval_str_with_vars_and_ctx("1 + 2 + 3 + 4", &mut vars, &ctx),
+//!     Err(Error::ParserError(parser::Error::TooManyTokens(5)))
+//! );
+//! ```
+
+/// The limits of the parsing and the evaluation, `None` is no limit.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Limits {
+    /// The maximum number of the tokens of the expression, reported as [`TooManyTokens`](crate::parser::Error::TooManyTokens).
+    pub max_tokens: Option<usize>,
+    /// The maximum depth of the operator stack of the parser, i.e. of the nested parentheses, function calls
+    /// and the right associative operators like `2 ^ 2 ^ 2`,
+    /// reported as [`TooDeep`](crate::parser::Error::TooDeep).
+    pub max_depth: Option<usize>,
+    /// The maximum number of values on the evaluation stack,
+    /// reported as [`EvalStackOverflow`](crate::evaluator::Error::EvalStackOverflow).
+    pub max_eval_stack: Option<usize>,
+    /// The maximum number of the tokens evaluated, including the tokens of the expressions the macros evaluate,
+    /// e.g. the body of the [`UserFn`](crate::macros::user_fn::UserFn),
+    /// reported as [`StepLimitExceeded`](crate::evaluator::Error::StepLimitExceeded).
+    pub max_steps: Option<usize>,
+    /// The maximum depth of the expressions evaluated by the macros inside of each other,
+    /// e.g. of the recursive calls of the [`UserFn`](crate::macros::user_fn::UserFn),
+    /// reported as [`NestingLimitExceeded`](crate::evaluator::Error::NestingLimitExceeded).
+    pub max_nesting: Option<usize>,
+}
+
+impl Limits {
+    /// The limits that are generous for the expressions written by hand,
+    /// but stop the malicious ones early.
+    pub fn untrusted() -> Self {
+        Limits {
+            max_tokens: Some(10_000),
+            max_depth: Some(256),
+            max_eval_stack: Some(1024),
+            max_steps: Some(1_000_000),
+            max_nesting: Some(64),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::evaluator::{eval_str_with_vars_and_ctx, eval_with_options, Error, EvalOptions};
+    use crate::macros::user_fn::UserFn;
+    use crate::parser::{self, parse_str};
+    use crate::Ctx;
+
+    #[test]
+    fn test_parser_limits() {
+        let ctx = Ctx {
+            limits: Limits {
+                max_tokens: Some(7),
+                max_depth: Some(3),
+                ..Limits::default()
+            },
+            ..Ctx::default()
+        };
+        let input_expected = &[
+            ("1 + 2 * 3", Ok(())),
+            ("1 + 2 + 3 + 4 + 5", Err(parser::Error::TooManyTokens(7))),
+            ("(((1)))", Ok(())),
+            ("((((1))))", Err(parser::Error::TooDeep(3))),
+            ("2 ^ 2 ^ 2", Ok(())),
+            ("max(1, 2)", Ok(())),
+            ("-(-(-1))", Err(parser::Error::TooDeep(3))),
+        ];
+        for (input, expected) in input_expected {
+            let result = parse_str(input, &ctx).map(|_| ());
+            assert_eq!(result, *expected, "input was {}", input);
+        }
+    }
+
+    #[test]
+    fn test_eval_limits() {
+        let mut ctx = Ctx {
+            limits: Limits {
+                max_eval_stack: Some(3),
+                max_steps: Some(40),
+                max_nesting: Some(4),
+                ..Limits::default()
+            },
+            ..Ctx::default_with_macros()
+        };
+        ctx.macros.push(Box::new(UserFn {
+            token: "f".to_owned(),
+            params: vec!["x".to_owned()],
+            body: "f(x)".to_owned(),
+        }));
+        ctx.macros.push(Box::new(UserFn {
+            token: "g".to_owned(),
+            params: vec!["x".to_owned()],
+            body: "x + x + x + x + x + x + x + x + x + x".to_owned(),
+        }));
+        let mut vars = HashMap::new();
+        let input_expected = &[
+            ("1 + 2 * 3", Ok(7.0)),
+            ("1 + (2 * (3 + 4))", Err(Error::EvalStackOverflow(3))),
+            ("f(1)", Err(Error::NestingLimitExceeded(4))),
+            ("g(1)", Ok(10.0)),
+            ("g(1) + g(2)", Err(Error::StepLimitExceeded(40))),
+            ("g(1)", Ok(10.0)),
+        ];
+        for (input, expected) in input_expected {
+            let result = eval_str_with_vars_and_ctx(input, &mut vars, &ctx);
+            assert_eq!(result, *expected, "input was {}", input);
+        }
+        let tokens = parse_str("1 + (2 * (3 + 4))", &ctx).unwrap();
+        let options = EvalOptions {
+            strict_math: true,
+            ..EvalOptions::default()
+        };
+        assert_eq!(
+            eval_with_options(&tokens, &mut vars, &ctx, options),
+            Err(Error::EvalStackOverflow(3))
+        );
+    }
+}
@@ -0,0 +1,397 @@
+//! Provides the helpers for the macros that are called like the functions, e.g. `series(i, 1, 10, i ^ 2)`.
+//!
+//! The arguments of such macros are not evaluated before the call like the arguments of the [`Func`](crate::functions::Func),
+//! the macro parses them by itself: [`call_args`](call_args) splits the matched call into the arguments,
+//! and [`SubExpr`](SubExpr) parses the argument as the expression, so the syntax errors are reported by the parser,
+//! and evaluates it later with the variables the macro binds, e.g. the index of the sum.
+//! [`ParsedArg`](ParsedArg) keeps the parsed tokens of the argument that is evaluated with the variables of the caller,
+//! e.g. the branch of `if`, and evaluates it with the [`EvalOptions`](crate::evaluator::EvalOptions) of the caller.
+//!
+//! # Example
+//!
+//! ```
+//! use std::collections::HashMap;
+//! use rusty_yard::macros::args::SubExpr;
+//! use rusty_yard::Ctx;
+//!
+//! let ctx = Ctx::default();
+//! let body = SubExpr::parse("i ^ 2 + a", &["i"], &ctx).unwrap();
+//! let mut vars = HashMap::new();
+//! vars.insert("a".to_owned(), 1.0);
+//! assert_eq!(body.eval(&[3.0], &mut vars, &ctx), Ok(10.0));
+//!
+//! // the expression is parsed once for many values of the bound variables
+//! let mut eval = body.evaluator(&mut vars, &ctx).unwrap();
+//! let squares: Result<Vec<f64>, _> = (1..=3).map(|i| eval.eval(&[i as f64])).collect();
+//! assert_eq!(squares, Ok(vec![2.0, 5.0, 10.0]));
+//! ```
+use std::borrow::Cow;
+
+use crate::evaluator::{
+    self, check_eval_stack, eval_token_with_options, eval_with_stack, EvalOptions, LimitsGuard,
+};
+use crate::functions::Func;
+use crate::macros::{InnerExpression, ParsedMacro};
+use crate::operators::{BiOp, UOp};
+use crate::parser::{self, parse_str, ParserToken};
+use crate::sample::Bound;
+use crate::tokenizer::{match_id, skip_whitespace, Match};
+use crate::variables::VariableResolver;
+use crate::Ctx;
+
+/// Matches the whole call `{token}<spaces>(<arguments>)` at the start of the `input`.
+///
+/// The arguments can contain the nested parentheses, the match ends at the paren that closes the call.
+pub fn match_call(input: &str, token: &str, ctx: &Ctx) -> Option<Match<()>> {
+    let Match(id, c) = match_id(input, ctx)?;
+    if id != token {
+        return None;
+    }
+    let start = c + skip_whitespace(&input[c..]);
+    let call = &input[start..];
+    if !call.starts_with('(') {
+        return None;
+    }
+    let mut depth = 0;
+    for (i, ch) in call.char_indices() {
+        match ch {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(Match((), start + i + ')'.len_utf8()));
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Splits the arguments on the `separator`s that are not inside of parentheses.
+///
+/// The separator is `,`, or `;` with the [decimal comma](crate::tokenizer::DecimalSeparator::Comma),
+/// see [`argument_separator`](crate::tokenizer::DecimalSeparator::argument_separator).
+pub fn split_args(args: &str, separator: char) -> Vec<&str> {
+    if args.trim().is_empty() {
+        return Vec::new();
+    }
+    let mut result = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, ch) in args.char_indices() {
+        match ch {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ch if ch == separator && depth == 0 => {
+                result.push(args[start..i].trim());
+                start = i + separator.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    result.push(args[start..].trim());
+    result
+}
+
+/// Returns the arguments of the `call` matched by [`match_call`](match_call).
+///
+/// Returns [`ArityMismatch`](crate::parser::Error::ArityMismatch) error of the `token` if the number of the arguments is not `expected`.
+pub fn call_args<'a>(
+    call: &'a str,
+    token: &str,
+    expected: usize,
+    ctx: &Ctx,
+) -> Result<Vec<&'a str>, parser::Error> {
+    // unwrap: the call has been matched, so it contains the parens
+    let args = &call[call.find('(').unwrap() + 1..call.len() - 1];
+    let separator = ctx.tokenize_options.decimal_separator.argument_separator();
+    let args = split_args(args, separator);
+    if args.len() != expected {
+        return Err(parser::Error::ArityMismatch {
+            id: token.to_owned(),
+            expected: expected.into(),
+            actual: args.len(),
+        });
+    }
+    Ok(args)
+}
+
+/// Checks that the argument is the name of the variable, e.g. the index of the sum.
+///
+/// Returns [`ExpectedVariable`](crate::parser::Error::ExpectedVariable) error otherwise.
+pub fn variable_arg<'a>(arg: &'a str, ctx: &Ctx) -> Result<&'a str, parser::Error> {
+    match match_id(arg, ctx) {
+        Some(Match(id, len)) if len == arg.len() => Ok(id),
+        _ => Err(parser::Error::ExpectedVariable(arg.to_owned())),
+    }
+}
+
+/// The argument of the macro that is parsed as the expression and evaluated by the macro.
+///
+/// The bound variables shadow the variables of the caller while the expression is evaluated,
+/// the assignments to them are not seen by the caller.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubExpr<'a> {
+    input: Cow<'a, str>,
+    bound: Vec<String>,
+}
+
+impl<'a> SubExpr<'a> {
+    /// Parses the `input` to check that it is the valid expression of the `ctx`,
+    /// `bound` are the names of the variables the macro binds.
+    pub fn parse(input: &'a str, bound: &[&str], ctx: &Ctx) -> Result<Self, parser::Error> {
+        parse_str(input, ctx)?;
+        Ok(SubExpr {
+            input: Cow::Borrowed(input),
+            bound: bound.iter().map(|var| (*var).to_owned()).collect(),
+        })
+    }
+
+    /// The input of the expression.
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    /// The names of the bound variables.
+    pub fn bound(&self) -> &[String] {
+        &self.bound
+    }
+
+    /// Evaluates the expression with the `values` of the bound variables, in the order they were passed to [`parse`](SubExpr::parse).
+    pub fn eval(
+        &self,
+        values: &[f64],
+        variables: &mut dyn VariableResolver,
+        ctx: &Ctx,
+    ) -> evaluator::Result {
+        self.evaluator(variables, ctx)?.eval(values)
+    }
+
+    /// Parses the expression once to evaluate it for many values of the bound variables, see [`SubExprEval`](SubExprEval).
+    pub fn evaluator<'s>(
+        &'s self,
+        variables: &'s mut dyn VariableResolver,
+        ctx: &'s Ctx,
+    ) -> Result<SubExprEval<'s>, evaluator::Error> {
+        Ok(SubExprEval {
+            tokens: parse_str(&self.input, ctx)?,
+            bound: Bound {
+                vars: self.bound.iter().map(|var| (var.as_str(), 0.0)).collect(),
+                variables,
+            },
+            ctx,
+            stack: Vec::new(),
+        })
+    }
+
+    /// The expression for the [`inner_expressions`](crate::macros::ParsedMacro::inner_expressions) of the macro.
+    pub fn inner_expression(&self) -> InnerExpression<'_> {
+        InnerExpression {
+            input: &self.input,
+            bound: self.bound.iter().map(String::as_str).collect(),
+        }
+    }
+
+    /// The copy of the expression that doesn't borrow the input,
+    /// for the [`to_owned_macro`](crate::macros::ParsedMacro::to_owned_macro) of the macro.
+    pub fn to_owned_expr(&self) -> SubExpr<'static> {
+        SubExpr {
+            input: Cow::Owned(self.input.to_string()),
+            bound: self.bound.clone(),
+        }
+    }
+}
+
+/// The parsed [`SubExpr`](SubExpr) that reuses the tokens and the evaluation stack,
+/// created by [`SubExpr::evaluator`](SubExpr::evaluator).
+pub struct SubExprEval<'s> {
+    tokens: Vec<ParserToken<'s, 's>>,
+    bound: Bound<'s, Vec<(&'s str, f64)>>,
+    ctx: &'s Ctx,
+    stack: Vec<f64>,
+}
+
+impl SubExprEval<'_> {
+    /// Evaluates the expression with the `values` of the bound variables.
+    ///
+    /// The missing values are 0, the extra values are ignored.
+    pub fn eval(&mut self, values: &[f64]) -> evaluator::Result {
+        for (var, value) in self.bound.vars.iter_mut().zip(values) {
+            var.1 = *value;
+        }
+        eval_with_stack(&self.tokens, &mut self.bound, self.ctx, &mut self.stack)
+    }
+}
+
+/// The argument of the macro that is parsed once, when the macro is parsed,
+/// and evaluated with the variables and the [`EvalOptions`](EvalOptions) of the caller.
+///
+/// The parsed tokens don't borrow the context, the operators and the functions are copied from it,
+/// so the argument can be kept by the parsed macro.
+/// It is evaluated with the context it was parsed with.
+#[derive(Debug)]
+pub struct ParsedArg<'a> {
+    input: Cow<'a, str>,
+    tokens: Vec<ArgToken<'a>>,
+}
+
+/// The [`ParserToken`](ParserToken) that owns the items of the context
+#[derive(Debug)]
+enum ArgToken<'a> {
+    Num(f64),
+    Id(Cow<'a, str>),
+    Str(Cow<'a, str>),
+    UOp(UOp),
+    BiOp(BiOp),
+    Func(Func, usize),
+    Macro(Box<dyn ParsedMacro + 'a>),
+}
+
+impl<'a> ParsedArg<'a> {
+    /// Parses the `input` as the expression of the `ctx`.
+    pub fn parse(input: &'a str, ctx: &Ctx) -> Result<Self, parser::Error> {
+        let tokens = parse_str(input, ctx)?
+            .into_iter()
+            .map(|token| match token {
+                ParserToken::Num(n) => ArgToken::Num(n),
+                ParserToken::Id(id) => ArgToken::Id(Cow::Borrowed(id)),
+                ParserToken::Str(s) => ArgToken::Str(Cow::Borrowed(s)),
+                ParserToken::UOp(op) => ArgToken::UOp(op.clone()),
+                ParserToken::BiOp(op) => ArgToken::BiOp(op.clone()),
+                ParserToken::Func(func, n_args) => ArgToken::Func(func.clone(), n_args),
+                ParserToken::Macro(m) => ArgToken::Macro(m),
+            })
+            .collect();
+        Ok(ParsedArg {
+            input: Cow::Borrowed(input),
+            tokens,
+        })
+    }
+
+    /// The input of the expression.
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    /// Evaluates the expression with the `options`, the same way as [`eval_with_options`](crate::evaluator::eval_with_options).
+    ///
+    /// The tokens are counted into the [`Limits`](crate::limits::Limits) of the evaluation of the caller.
+    pub fn eval(
+        &self,
+        variables: &mut dyn VariableResolver,
+        ctx: &Ctx,
+        options: &EvalOptions,
+    ) -> evaluator::Result {
+        let _guard = LimitsGuard::enter(&ctx.limits, self.tokens.len())?;
+        let mut eval_stack = Vec::new();
+        for (position, token) in self.tokens.iter().enumerate() {
+            let token = match token {
+                ArgToken::Num(n) => ParserToken::Num(*n),
+                ArgToken::Id(id) => ParserToken::Id(id),
+                ArgToken::Str(s) => ParserToken::Str(s),
+                ArgToken::UOp(op) => ParserToken::UOp(op),
+                ArgToken::BiOp(op) => ParserToken::BiOp(op),
+                ArgToken::Func(func, n_args) => ParserToken::Func(func, *n_args),
+                ArgToken::Macro(m) => {
+                    m.eval_with_options(&mut eval_stack, variables, ctx, options)?;
+                    check_eval_stack(&ctx.limits, &eval_stack)?;
+                    continue;
+                }
+            };
+            eval_token_with_options(&token, position, variables, ctx, options, &mut eval_stack)?;
+        }
+        eval_stack.pop().ok_or(evaluator::Error::Other)
+    }
+
+    /// The copy of the argument that doesn't borrow the input,
+    /// for the [`to_owned_macro`](crate::macros::ParsedMacro::to_owned_macro) of the macro.
+    ///
+    /// Returns `None` if one of the macros of the argument can't be copied.
+    pub fn to_owned_arg(&self) -> Option<ParsedArg<'static>> {
+        let tokens = self
+            .tokens
+            .iter()
+            .map(|token| {
+                Some(match token {
+                    ArgToken::Num(n) => ArgToken::Num(*n),
+                    ArgToken::Id(id) => ArgToken::Id(Cow::Owned(id.to_string())),
+                    ArgToken::Str(s) => ArgToken::Str(Cow::Owned(s.to_string())),
+                    ArgToken::UOp(op) => ArgToken::UOp(op.clone()),
+                    ArgToken::BiOp(op) => ArgToken::BiOp(op.clone()),
+                    ArgToken::Func(func, n_args) => ArgToken::Func(func.clone(), *n_args),
+                    ArgToken::Macro(m) => ArgToken::Macro(m.to_owned_macro()?),
+                })
+            })
+            .collect::<Option<_>>()?;
+        Some(ParsedArg {
+            input: Cow::Owned(self.input.to_string()),
+            tokens,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn test_call_args() {
+        let input_expected = &[
+            ("f(1, 2)", Ok(vec!["1", "2"])),
+            ("f (max(1, 2), (3))", Ok(vec!["max(1, 2)", "(3)"])),
+            (
+                "f()",
+                Err(parser::Error::ArityMismatch {
+                    id: "f".to_owned(),
+                    expected: 2.into(),
+                    actual: 0,
+                }),
+            ),
+        ];
+        for (input, expected) in input_expected {
+            let result = call_args(input, "f", 2, &Ctx::default());
+            assert_eq!(result, *expected, "input was {}", input);
+        }
+    }
+
+    #[test]
+    fn test_variable_arg() {
+        let ctx = Ctx::default();
+        assert_eq!(variable_arg("i", &ctx), Ok("i"));
+        assert_eq!(
+            variable_arg("i + 1", &ctx),
+            Err(parser::Error::ExpectedVariable("i + 1".to_owned()))
+        );
+        assert_eq!(
+            variable_arg("2", &ctx),
+            Err(parser::Error::ExpectedVariable("2".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_sub_expr() {
+        let ctx = Ctx::default_with_macros();
+        assert_eq!(
+            SubExpr::parse("i +", &["i"], &ctx),
+            Err(parser::Error::OperatorAtTheEnd)
+        );
+        let expr = SubExpr::parse("i = i * a", &["i"], &ctx).unwrap();
+        let mut vars = HashMap::new();
+        vars.insert("a".to_owned(), 2.0);
+        vars.insert("i".to_owned(), -1.0);
+        assert_eq!(expr.eval(&[3.0], &mut vars, &ctx), Ok(6.0));
+        // the bound variable shadows the variable of the caller and the assignment stays local
+        assert_eq!(vars.get("i"), Some(&-1.0));
+        assert_eq!(
+            expr.inner_expression(),
+            InnerExpression {
+                input: "i = i * a",
+                bound: vec!["i"],
+            }
+        );
+        assert_eq!(expr.to_owned_expr(), expr);
+    }
+}
@@ -0,0 +1,336 @@
+//! Provides the calculus macros, that parse their arguments with [`macros::args`](crate::macros::args).
+//!
+//! The macros are not the part of the [`default_macros`](crate::macros::default::default_macros),
+//! add them with [`calculus_macros`](calculus_macros).
+//!
+//! # Example
+//!
+//! ```
+//! use std::collections::HashMap;
+//! use rusty_yard::evaluator::eval_str_with_vars_and_ctx;
+//! use rusty_yard::macros::calculus::calculus_macros;
+//! use rusty_yard::Ctx;
+//!
+//! let mut ctx = Ctx::default();
+//! ctx.macros.extend(calculus_macros());
+//! let mut vars = HashMap::new();
+//! assert_eq!(eval_str_with_vars_and_ctx("series(i, 1, 10, i ^ 2)", &mut vars, &ctx), Ok(385.0));
+//! vars.insert("x".to_owned(), 3.0);
+//! let slope = eval_str_with_vars_and_ctx("derive(x ^ 2, x)", &mut vars, &ctx).unwrap();
+//! assert!((slope - 6.0).abs() < 1e-9);
+//! ```
+use crate::evaluator::{self, var_not_found};
+use crate::macros::args::{call_args, match_call, variable_arg, SubExpr};
+use crate::macros::{InnerExpression, Macro, MacroParse, ParsedMacro};
+use crate::numeric::derivative::five_point;
+use crate::parser::{self, ParseState};
+use crate::tokenizer::Match;
+use crate::variables::VariableResolver;
+use crate::Ctx;
+
+/// Get the list of the calculus macros: [`Series`](Series) and [`Derive`](Derive).
+pub fn calculus_macros() -> Vec<Box<dyn Macro>> {
+    vec![Box::new(Series), Box::new(Derive)]
+}
+
+/// The sum of the series macro.
+///
+/// # Matching
+///
+/// This macro matches the whole call:
+/// ```text
+/// series<spaces>(<index>, <from>, <to>, <body>)
+/// ```
+///
+/// # Evaluation
+///
+/// The bounds are evaluated first, then the body is evaluated with `{index}` bound to
+/// `from`, `from + 1` and so on while it is not greater than `to`, the results are summed.
+/// The sum of no terms is 0, the bounds that are not finite fail with the [`FunctionError`](crate::evaluator::Error::FunctionError).
+#[derive(Debug)]
+pub struct Series;
+
+impl Macro for Series {
+    fn match_input(&self, input: &str, ctx: &Ctx) -> Option<Match<()>> {
+        match_call(input, "series", ctx)
+    }
+
+    fn parse<'a>(
+        &self,
+        input: &'a str,
+        ctx: &Ctx,
+        current_state: ParseState,
+    ) -> Result<MacroParse<'a>, parser::Error> {
+        if let ParseState::Operator = current_state {
+            return Err(parser::Error::ExpectedOperator);
+        }
+        let args = call_args(input, "series", 4, ctx)?;
+        let index = variable_arg(args[0], ctx)?;
+        Ok(MacroParse::before(
+            SeriesParsed {
+                from: SubExpr::parse(args[1], &[], ctx)?,
+                to: SubExpr::parse(args[2], &[], ctx)?,
+                body: SubExpr::parse(args[3], &[index], ctx)?,
+            },
+            ParseState::Operator,
+        ))
+    }
+}
+
+/// Parsed [`Series`](Series) macro
+#[derive(Debug)]
+pub struct SeriesParsed<'a> {
+    from: SubExpr<'a>,
+    to: SubExpr<'a>,
+    body: SubExpr<'a>,
+}
+
+impl ParsedMacro for SeriesParsed<'_> {
+    fn eval(
+        &self,
+        eval_stack: &mut Vec<f64>,
+        variables: &mut dyn VariableResolver,
+        ctx: &Ctx,
+    ) -> Result<(), evaluator::Error> {
+        let from = self.from.eval(&[], variables, ctx)?;
+        let to = self.to.eval(&[], variables, ctx)?;
+        if !from.is_finite() || !to.is_finite() {
+            return Err(evaluator::Error::FunctionError {
+                id: "series".to_owned(),
+                message: format!("the bounds {} and {} are not finite", from, to),
+            });
+        }
+        let terms = if to >= from {
+            (to - from).floor() as u64 + 1
+        } else {
+            0
+        };
+        let mut body = self.body.evaluator(variables, ctx)?;
+        let mut sum = 0.0;
+        for k in 0..terms {
+            sum += body.eval(&[from + k as f64])?;
+        }
+        eval_stack.push(sum);
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "series"
+    }
+
+    fn inner_expressions(&self) -> Vec<InnerExpression<'_>> {
+        vec![
+            self.from.inner_expression(),
+            self.to.inner_expression(),
+            self.body.inner_expression(),
+        ]
+    }
+
+    fn structural_key(&self) -> Option<String> {
+        Some(format!(
+            "{}, {}, {}, {}",
+            self.body.bound()[0],
+            self.from.input(),
+            self.to.input(),
+            self.body.input()
+        ))
+    }
+
+    fn to_owned_macro(&self) -> Option<Box<dyn ParsedMacro>> {
+        Some(Box::new(SeriesParsed {
+            from: self.from.to_owned_expr(),
+            to: self.to.to_owned_expr(),
+            body: self.body.to_owned_expr(),
+        }))
+    }
+}
+
+/// The numerical derivative macro.
+///
+/// # Matching
+///
+/// This macro matches the whole call:
+/// ```text
+/// derive<spaces>(<body>, <variable>)
+/// ```
+///
+/// # Evaluation
+///
+/// The derivative of the body by `{variable}` at the current value of the variable,
+/// found with the [five-point central difference](crate::numeric::derivative::derivative_at).
+/// The variable must be set, the body is evaluated with the variable bound near its value,
+/// so the variable itself is never changed.
+#[derive(Debug)]
+pub struct Derive;
+
+impl Macro for Derive {
+    fn match_input(&self, input: &str, ctx: &Ctx) -> Option<Match<()>> {
+        match_call(input, "derive", ctx)
+    }
+
+    fn parse<'a>(
+        &self,
+        input: &'a str,
+        ctx: &Ctx,
+        current_state: ParseState,
+    ) -> Result<MacroParse<'a>, parser::Error> {
+        if let ParseState::Operator = current_state {
+            return Err(parser::Error::ExpectedOperator);
+        }
+        let args = call_args(input, "derive", 2, ctx)?;
+        let var = variable_arg(args[1], ctx)?;
+        Ok(MacroParse::before(
+            DeriveParsed {
+                body: SubExpr::parse(args[0], &[var], ctx)?,
+            },
+            ParseState::Operator,
+        ))
+    }
+}
+
+/// Parsed [`Derive`](Derive) macro
+#[derive(Debug)]
+pub struct DeriveParsed<'a> {
+    body: SubExpr<'a>,
+}
+
+impl DeriveParsed<'_> {
+    fn var(&self) -> &str {
+        &self.body.bound()[0]
+    }
+}
+
+impl ParsedMacro for DeriveParsed<'_> {
+    fn eval(
+        &self,
+        eval_stack: &mut Vec<f64>,
+        variables: &mut dyn VariableResolver,
+        ctx: &Ctx,
+    ) -> Result<(), evaluator::Error> {
+        let x = match variables.get(self.var()) {
+            Some(x) => x,
+            None => return Err(var_not_found(self.var(), variables, ctx)),
+        };
+        let mut body = self.body.evaluator(variables, ctx)?;
+        eval_stack.push(five_point(|x| body.eval(&[x]), x)?);
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "derive"
+    }
+
+    fn inner_expressions(&self) -> Vec<InnerExpression<'_>> {
+        // the body reads the variable of the caller, it is bound only near its value
+        vec![
+            InnerExpression::new(self.body.input()),
+            InnerExpression::new(self.var()),
+        ]
+    }
+
+    fn structural_key(&self) -> Option<String> {
+        Some(format!("{}, {}", self.body.input(), self.var()))
+    }
+
+    fn to_owned_macro(&self) -> Option<Box<dyn ParsedMacro>> {
+        Some(Box::new(DeriveParsed {
+            body: self.body.to_owned_expr(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::analysis::variables_of;
+    use crate::evaluator::eval_str_with_vars_and_ctx;
+    use crate::parser::parse_str;
+
+    fn get_ctx() -> Ctx {
+        let mut ctx = Ctx::default_with_macros();
+        ctx.macros.extend(calculus_macros());
+        ctx
+    }
+
+    #[test]
+    fn test_series() {
+        let ctx = get_ctx();
+        let mut vars = HashMap::new();
+        vars.insert("n".to_owned(), 4.0);
+        vars.insert("i".to_owned(), -1.0);
+        let input_expected = &[
+            ("series(i, 1, 10, i)", Ok(55.0)),
+            ("series(i, 1, n, i ^ 2) + 1", Ok(31.0)),
+            ("series(k, 0, 2, series(j, 0, k, 1))", Ok(6.0)),
+            ("series(i, 1.5, 3, i)", Ok(4.0)),
+            ("series(i, 1, 0, i)", Ok(0.0)),
+            ("i", Ok(-1.0)),
+            (
+                "series(i, 0, 1 / 0, i)",
+                Err(evaluator::Error::FunctionError {
+                    id: "series".to_owned(),
+                    message: "the bounds 0 and inf are not finite".to_owned(),
+                }),
+            ),
+            (
+                "series(2, 1, 10, i)",
+                Err(evaluator::Error::ParserError(
+                    parser::Error::ExpectedVariable("2".to_owned()),
+                )),
+            ),
+            (
+                "series(i, 1, 10)",
+                Err(evaluator::Error::ParserError(
+                    parser::Error::ArityMismatch {
+                        id: "series".to_owned(),
+                        expected: 4.into(),
+                        actual: 3,
+                    },
+                )),
+            ),
+            (
+                "series(i, 1, 10, i *)",
+                Err(evaluator::Error::ParserError(
+                    parser::Error::OperatorAtTheEnd,
+                )),
+            ),
+        ];
+        for (input, expected) in input_expected {
+            let result = eval_str_with_vars_and_ctx(input, &mut vars, &ctx);
+            assert_eq!(result, *expected, "input was {}", input);
+        }
+    }
+
+    #[test]
+    fn test_derive() {
+        let ctx = get_ctx();
+        let mut vars = HashMap::new();
+        vars.insert("x".to_owned(), 2.0);
+        vars.insert("a".to_owned(), 3.0);
+        let input_expected = &[
+            ("derive(x ^ 3, x)", 12.0),
+            ("derive(a * sin(x), x)", 3.0 * 2f64.cos()),
+            ("derive(x * a, a)", 2.0),
+            ("derive(series(i, 1, 3, x ^ i), x)", 1.0 + 4.0 + 12.0),
+        ];
+        for (input, expected) in input_expected {
+            let result = eval_str_with_vars_and_ctx(input, &mut vars, &ctx).unwrap();
+            assert!((result - expected).abs() < 1e-6, "input was {}", input);
+        }
+        assert_eq!(
+            eval_str_with_vars_and_ctx("derive(y ^ 2, y)", &mut vars, &ctx),
+            Err(evaluator::Error::VarNotFound("y".to_owned(), None))
+        );
+        assert_eq!(vars.get("x"), Some(&2.0));
+    }
+
+    #[test]
+    fn test_analysis() {
+        let ctx = get_ctx();
+        let parsed = parse_str("series(i, 1, n, i * a) + derive(x ^ 2, x)", &ctx).unwrap();
+        let vars: Vec<_> = variables_of(&parsed, &ctx).into_iter().collect();
+        assert_eq!(vars, vec!["a", "n", "x"]);
+    }
+}
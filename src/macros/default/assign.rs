@@ -1,8 +1,9 @@
-use std::collections::HashMap;
+use std::borrow::Cow;
 
 use crate::macros::{Macro, MacroParse, ParsedMacro};
 use crate::parser::ParseState;
-use crate::tokenizer::{match_id, match_str, skip_whitespace, Match};
+use crate::tokenizer::{match_id, match_op, match_quoted_id, match_str, skip_whitespace, Match};
+use crate::variables::VariableResolver;
 use crate::{evaluator, parser, Ctx};
 
 /// The assign macro.
@@ -14,15 +15,26 @@ use crate::{evaluator, parser, Ctx};
 /// {id}<spaces>=
 /// ```
 ///
+/// where `{id}` can be a quoted identifier, e.g. `` `unit price` = 1 ``.
+/// The operators of the context that start with `=`, e.g. `==`, are not matched.
+///
 /// # Evaluation
 ///
 /// This macro assigns the matched identifier the result of expression on the left of `=`
 /// and returns that expression.
+///
+/// The assignment is the expression itself, so the assignments can be chained,
+/// `a = b = 3` assigns 3 to both variables.
 #[derive(Debug)]
 pub struct Assign;
 
 impl Macro for Assign {
     fn match_input(&self, input: &str, ctx: &Ctx) -> Option<Match<()>> {
+        if let Some(Match(_, c)) = match_quoted_id(input) {
+            let whitespace = skip_whitespace(&input[c..]);
+            let eq_len = match_assign_eq(&input[(c + whitespace)..], ctx)?;
+            return Some(Match((), c + whitespace + eq_len));
+        }
         let Match(id, c) = match_id(input, ctx)?;
         if id == "=" {
             None
@@ -32,7 +44,7 @@ impl Macro for Assign {
             Some(Match((), c))
         } else {
             let whitespace = skip_whitespace(&input[c..]);
-            let Match(_, eq_len) = match_str(&input[(c + whitespace)..], "=")?;
+            let eq_len = match_assign_eq(&input[(c + whitespace)..], ctx)?;
             Some(Match((), c + whitespace + eq_len))
         }
     }
@@ -46,20 +58,35 @@ impl Macro for Assign {
         if let ParseState::Operator = current_state {
             Err(parser::Error::ExpectedExpression)
         } else {
+            if let Some(Match(id, _)) = match_quoted_id(input) {
+                return Ok(MacroParse::after(
+                    AssignParsed::new(id),
+                    ParseState::Expression,
+                ));
+            }
             let Match(id, len) = match_id(input, ctx).unwrap();
             let len = id.find('=').unwrap_or(len);
             Ok(MacroParse::after(
-                AssignParsed { id: &id[..len] },
+                AssignParsed::new(&id[..len]),
                 ParseState::Expression,
             ))
         }
     }
 }
 
+/// Matches `=` unless it starts a longer operator, e.g. `==`
+fn match_assign_eq(input: &str, ctx: &Ctx) -> Option<usize> {
+    let Match(_, eq_len) = match_str(input, "=")?;
+    match match_op(input, ctx) {
+        Some(Match(_, op_len)) if op_len > eq_len => None,
+        _ => Some(eq_len),
+    }
+}
+
 /// Parsed assign macro
 #[derive(Debug)]
 pub struct AssignParsed<'a> {
-    id: &'a str,
+    id: Cow<'a, str>,
 }
 
 impl<'a> AssignParsed<'a> {
@@ -73,7 +100,7 @@ impl<'a> AssignParsed<'a> {
     /// the expression which value will be assigned to macros variable.
     #[cfg_attr(tarpaulin, skip)]
     pub fn new(id: &'a str) -> Self {
-        Self { id }
+        Self { id: id.into() }
     }
 }
 
@@ -81,13 +108,35 @@ impl<'a> ParsedMacro for AssignParsed<'a> {
     fn eval(
         &self,
         eval_stack: &mut Vec<f64>,
-        variables: &mut HashMap<String, f64>,
+        variables: &mut dyn VariableResolver,
         _ctx: &Ctx,
     ) -> Result<(), evaluator::Error> {
         let expr = *eval_stack.last().ok_or(evaluator::Error::EmptyEvalStack)?;
-        variables.insert(self.id.into(), expr);
+        variables.set(&self.id, expr);
         Ok(())
     }
+
+    fn operands(&self) -> usize {
+        1
+    }
+
+    fn name(&self) -> &str {
+        "="
+    }
+
+    fn assigns(&self) -> Option<&str> {
+        Some(&self.id)
+    }
+
+    fn structural_key(&self) -> Option<String> {
+        Some(self.id.to_string())
+    }
+
+    fn to_owned_macro(&self) -> Option<Box<dyn ParsedMacro>> {
+        Some(Box::new(AssignParsed {
+            id: Cow::Owned(self.id.to_string()),
+        }))
+    }
 }
 
 #[cfg(test)]
@@ -112,6 +161,9 @@ mod tests {
             ("10= ", None),
             ("a", None),
             ("=", None),
+            ("`a b` = 10", Some(7)),
+            ("[a]=", Some(4)),
+            ("`a b`", None),
         ];
         let ctx = &Ctx::empty();
         for (input, expected) in input_expected {
@@ -120,6 +172,23 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_match_input_comparison() {
+        let input_expected = &[
+            ("a == 10", None),
+            ("a==10", None),
+            ("`a b` == 10", None),
+            ("a <= 10", None),
+            ("a = 10", Some(3usize)),
+            ("a = =", Some(3)),
+        ];
+        let ctx = &Ctx::default();
+        for (input, expected) in input_expected {
+            let result = Assign.match_input(input, ctx).map(|m| m.1);
+            assert_eq!(result, *expected, "input was {}", input);
+        }
+    }
+
     #[test]
     fn test_parse_ok() {
         let input = &["a = ", "a="];
@@ -127,7 +196,7 @@ mod tests {
         let expected_binding = ApplyMode::After;
         let ctx = &Ctx::empty();
         for input in input {
-            let result = Assign.parse(input, &ctx, ParseState::Expression);
+            let result = Assign.parse(input, ctx, ParseState::Expression);
             assert!(result.is_ok(), "input = {}", input);
             let MacroParse {
                 result: _,
@@ -0,0 +1,263 @@
+use std::fmt::{self, Debug, Formatter};
+
+use crate::evaluator::{self, EvalOptions};
+use crate::macros::args::{call_args, match_call, ParsedArg};
+use crate::macros::{InnerExpression, Macro, MacroParse, ParsedMacro};
+use crate::operators::binary::is_true;
+use crate::parser::{self, ParseState};
+use crate::tokenizer::Match;
+use crate::variables::VariableResolver;
+use crate::Ctx;
+
+/// The conditional macro.
+///
+/// # Matching
+///
+/// This macro matches the whole call:
+/// ```text
+/// if<spaces>(<condition>, <then>, <else>)
+/// ```
+///
+/// # Evaluation
+///
+/// The condition is evaluated first, every number except 0 and `NaN` is `true`.
+/// Then only one of the branches is evaluated, so `if(x != 0, 1 / x, 0)` never divides by zero
+/// and the variables assigned in the other branch are left unchanged.
+/// The condition and the branches are parsed together with the macro
+/// and evaluated with the [`EvalOptions`](crate::evaluator::EvalOptions) of the caller.
+#[derive(Debug)]
+pub struct If;
+
+impl Macro for If {
+    fn match_input(&self, input: &str, ctx: &Ctx) -> Option<Match<()>> {
+        match_call(input, "if", ctx)
+    }
+
+    fn parse<'a>(
+        &self,
+        input: &'a str,
+        ctx: &Ctx,
+        current_state: ParseState,
+    ) -> Result<MacroParse<'a>, parser::Error> {
+        if let ParseState::Operator = current_state {
+            return Err(parser::Error::ExpectedOperator);
+        }
+        let args = call_args(input, "if", 3, ctx)?;
+        Ok(MacroParse::before(
+            IfParsed {
+                condition: ParsedArg::parse(args[0], ctx)?,
+                then: ParsedArg::parse(args[1], ctx)?,
+                otherwise: ParsedArg::parse(args[2], ctx)?,
+            },
+            ParseState::Operator,
+        ))
+    }
+}
+
+/// Parsed [`If`](If) macro
+pub struct IfParsed<'a> {
+    condition: ParsedArg<'a>,
+    then: ParsedArg<'a>,
+    otherwise: ParsedArg<'a>,
+}
+
+impl Debug for IfParsed<'_> {
+    /// Writes the call as it was written in the input, so the token displays as source text
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "if({}, {}, {})",
+            self.condition.input(),
+            self.then.input(),
+            self.otherwise.input()
+        )
+    }
+}
+
+impl ParsedMacro for IfParsed<'_> {
+    fn eval(
+        &self,
+        eval_stack: &mut Vec<f64>,
+        variables: &mut dyn VariableResolver,
+        ctx: &Ctx,
+    ) -> Result<(), evaluator::Error> {
+        self.eval_with_options(eval_stack, variables, ctx, &EvalOptions::default())
+    }
+
+    fn eval_with_options(
+        &self,
+        eval_stack: &mut Vec<f64>,
+        variables: &mut dyn VariableResolver,
+        ctx: &Ctx,
+        options: &EvalOptions,
+    ) -> Result<(), evaluator::Error> {
+        let condition = self.condition.eval(variables, ctx, options)?;
+        let branch = if is_true(condition) {
+            &self.then
+        } else {
+            &self.otherwise
+        };
+        eval_stack.push(branch.eval(variables, ctx, options)?);
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "if"
+    }
+
+    fn inner_expressions(&self) -> Vec<InnerExpression<'_>> {
+        vec![
+            InnerExpression::new(self.condition.input()),
+            InnerExpression::new(self.then.input()),
+            InnerExpression::new(self.otherwise.input()),
+        ]
+    }
+
+    fn structural_key(&self) -> Option<String> {
+        Some(format!(
+            "{}, {}, {}",
+            self.condition.input(),
+            self.then.input(),
+            self.otherwise.input()
+        ))
+    }
+
+    fn to_owned_macro(&self) -> Option<Box<dyn ParsedMacro>> {
+        Some(Box::new(IfParsed {
+            condition: self.condition.to_owned_arg()?,
+            then: self.then.to_owned_arg()?,
+            otherwise: self.otherwise.to_owned_arg()?,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::evaluator::{eval_str_with_vars_and_ctx, Error, EvalOptions};
+
+    #[test]
+    fn test_match_input() {
+        let ctx = Ctx::default();
+        let input_expected = &[
+            ("if(a, b, c)", Some(11usize)),
+            ("if (a > 0, max(a, 1), 0) + 1", Some(24)),
+            ("if(a, b, c", None),
+            ("if", None),
+            ("iffy(a, b, c)", None),
+        ];
+        for (input, expected) in input_expected {
+            let result = If.match_input(input, &ctx).map(|m| m.1);
+            assert_eq!(result, *expected, "input was {}", input);
+        }
+    }
+
+    #[test]
+    fn test_eval() {
+        let ctx = Ctx::default_with_macros();
+        let mut vars = HashMap::new();
+        vars.insert("x".to_owned(), 0.0);
+        vars.insert("y".to_owned(), 4.0);
+        let input_expected = &[
+            ("if(x != 0, 1 / x, 0)", Ok(0.0)),
+            ("if(y != 0, 1 / y, 0)", Ok(0.25)),
+            ("2 * if(y > 2, if(y > 3, 3, 2), 1) + 1", Ok(7.0)),
+            ("if(x, missing, y)", Ok(4.0)),
+            (
+                "if(y, missing, y)",
+                Err(Error::VarNotFound("missing".to_owned(), None)),
+            ),
+            (
+                "if(x, 1)",
+                Err(Error::ParserError(parser::Error::ArityMismatch {
+                    id: "if".to_owned(),
+                    expected: 3.into(),
+                    actual: 2,
+                })),
+            ),
+            (
+                "1 if(x, 1, 2)",
+                Err(Error::ParserError(parser::Error::ExpectedOperator)),
+            ),
+            (
+                "if(x, 1, 2 +)",
+                Err(Error::ParserError(parser::Error::OperatorAtTheEnd)),
+            ),
+        ];
+        for (input, expected) in input_expected {
+            let result = eval_str_with_vars_and_ctx(input, &mut vars, &ctx);
+            assert_eq!(result, *expected, "input was {}", input);
+        }
+    }
+
+    #[test]
+    fn test_eval_untaken_branch() {
+        let ctx = Ctx::default_with_macros();
+        let mut vars = HashMap::new();
+        vars.insert("x".to_owned(), 1.0);
+        let result = eval_str_with_vars_and_ctx("if(x > 0, a = 1, b = 2)", &mut vars, &ctx);
+        assert_eq!(result, Ok(1.0));
+        assert_eq!(vars.get("a"), Some(&1.0));
+        assert_eq!(vars.get("b"), None);
+        // the division in the untaken branch is not evaluated
+        let options = EvalOptions {
+            strict_math: true,
+            ..EvalOptions::default()
+        };
+        let result = evaluator::eval_str_with_options(
+            "if(x < 0, 1 / 0, 2)",
+            &mut vars,
+            &ctx,
+            options.clone(),
+        );
+        assert_eq!(result, Ok(2.0));
+    }
+
+    #[test]
+    fn test_eval_with_options() {
+        let ctx = Ctx::default_with_macros();
+        let mut vars = HashMap::new();
+        vars.insert("x".to_owned(), 1.0);
+        let options = EvalOptions {
+            strict_math: true,
+            ..EvalOptions::default()
+        };
+        // the taken branch is checked like the rest of the expression
+        let input_expected = &[
+            (
+                "if(x > 0, 1 / 0, 2)",
+                Err(Error::DivisionByZero {
+                    func: "/".to_owned(),
+                    position: 2,
+                }),
+            ),
+            (
+                "1 + if(x > 0, if(x, sqrt(-x), 0), 2)",
+                Err(Error::DomainError {
+                    func: "sqrt".to_owned(),
+                    args: vec![-1.0],
+                    position: 2,
+                }),
+            ),
+            ("if(x > 0, 1 / x, 1 / 0)", Ok(1.0)),
+        ];
+        for (input, expected) in input_expected {
+            let result = evaluator::eval_str_with_options(input, &mut vars, &ctx, options.clone());
+            assert_eq!(result, *expected, "input was {}", input);
+        }
+        // the compiled expression keeps the parsed branches
+        let compiled =
+            crate::expression::CompiledExpr::compile("if(x > 0, 1 / 0, 2)", &ctx).unwrap();
+        assert_eq!(compiled.eval(&mut vars), Ok(f64::INFINITY));
+    }
+
+    #[test]
+    fn test_display() {
+        let ctx = Ctx::default_with_macros();
+        let tokens = crate::parser::parse_str("2 * if(a > 0, max(a, 1), 0)", &ctx).unwrap();
+        let rpn: Vec<_> = tokens.iter().map(ToString::to_string).collect();
+        assert_eq!(rpn, vec!["2", "if(a > 0, max(a, 1), 0)", "*"]);
+    }
+}
@@ -0,0 +1,226 @@
+use std::borrow::Cow;
+
+use crate::evaluator::{self, var_not_found};
+use crate::macros::{Macro, MacroParse, ParsedMacro};
+use crate::parser::{self, ParseState};
+use crate::tokenizer::{match_id, match_quoted_id, skip_whitespace, Match};
+use crate::variables::VariableResolver;
+use crate::Ctx;
+
+/// The postfix increment macro.
+///
+/// # Matching
+///
+/// This macro matches the following input:
+/// ```text
+/// {id}<spaces>++
+/// ```
+///
+/// where `{id}` can be a quoted identifier, e.g. `` `unit count`++ ``.
+/// The input is not matched if an operand follows `++`, so `a++b` is still `a + (+b)`.
+///
+/// # Evaluation
+///
+/// This macro adds 1 to the variable and returns the value it had before, like the postfix `++` in C.
+/// The variable must be set.
+#[derive(Debug)]
+pub struct Increment;
+
+/// The postfix decrement macro.
+///
+/// Like [`Increment`](Increment), but matches `{id}<spaces>--` and subtracts 1 from the variable.
+#[derive(Debug)]
+pub struct Decrement;
+
+impl Macro for Increment {
+    fn match_input(&self, input: &str, ctx: &Ctx) -> Option<Match<()>> {
+        let (_, len) = match_step(input, "++", ctx)?;
+        Some(Match((), len))
+    }
+
+    fn parse<'a>(
+        &self,
+        input: &'a str,
+        ctx: &Ctx,
+        current_state: ParseState,
+    ) -> Result<MacroParse<'a>, parser::Error> {
+        parse_step(input, "++", 1.0, ctx, current_state)
+    }
+}
+
+impl Macro for Decrement {
+    fn match_input(&self, input: &str, ctx: &Ctx) -> Option<Match<()>> {
+        let (_, len) = match_step(input, "--", ctx)?;
+        Some(Match((), len))
+    }
+
+    fn parse<'a>(
+        &self,
+        input: &'a str,
+        ctx: &Ctx,
+        current_state: ParseState,
+    ) -> Result<MacroParse<'a>, parser::Error> {
+        parse_step(input, "--", -1.0, ctx, current_state)
+    }
+}
+
+/// Matches `{id}<spaces>{op}` that is not followed by an operand,
+/// returns the identifier and the length of the match
+fn match_step<'a>(input: &'a str, op: &str, ctx: &Ctx) -> Option<(&'a str, usize)> {
+    let (id, c) = match match_quoted_id(input) {
+        Some(Match(id, c)) => (id, c),
+        None => {
+            let Match(id, c) = match_id(input, ctx)?;
+            // without the operators in the context the identifier includes the op
+            match id.find(op) {
+                Some(0) => return None,
+                Some(i) => (&id[..i], i),
+                None => (id, c),
+            }
+        }
+    };
+    let op_start = c + skip_whitespace(&input[c..]);
+    if !input[op_start..].starts_with(op) {
+        return None;
+    }
+    let end = op_start + op.len();
+    let rest = &input[end..];
+    let next = rest[skip_whitespace(rest)..].chars().next();
+    match next {
+        Some(ch) if ch.is_alphanumeric() || "_.(`[".contains(ch) => None,
+        _ => Some((id, end)),
+    }
+}
+
+fn parse_step<'a>(
+    input: &'a str,
+    op: &'static str,
+    delta: f64,
+    ctx: &Ctx,
+    current_state: ParseState,
+) -> Result<MacroParse<'a>, parser::Error> {
+    if let ParseState::Operator = current_state {
+        return Err(parser::Error::ExpectedOperator);
+    }
+    // unwrap: the input has been matched
+    let (id, _) = match_step(input, op, ctx).unwrap();
+    Ok(MacroParse::before(
+        IncrementParsed {
+            id: Cow::Borrowed(id),
+            op,
+            delta,
+        },
+        ParseState::Operator,
+    ))
+}
+
+/// Parsed [`Increment`](Increment) or [`Decrement`](Decrement) macro
+#[derive(Debug)]
+pub struct IncrementParsed<'a> {
+    id: Cow<'a, str>,
+    op: &'static str,
+    delta: f64,
+}
+
+impl ParsedMacro for IncrementParsed<'_> {
+    fn eval(
+        &self,
+        eval_stack: &mut Vec<f64>,
+        variables: &mut dyn VariableResolver,
+        ctx: &Ctx,
+    ) -> Result<(), evaluator::Error> {
+        let value = match variables.get(&self.id) {
+            Some(value) => value,
+            None => return Err(var_not_found(&self.id, variables, ctx)),
+        };
+        variables.set(&self.id, value + self.delta);
+        eval_stack.push(value);
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        self.op
+    }
+
+    fn assigns(&self) -> Option<&str> {
+        Some(&self.id)
+    }
+
+    fn structural_key(&self) -> Option<String> {
+        Some(self.id.to_string())
+    }
+
+    fn to_owned_macro(&self) -> Option<Box<dyn ParsedMacro>> {
+        Some(Box::new(IncrementParsed {
+            id: Cow::Owned(self.id.to_string()),
+            op: self.op,
+            delta: self.delta,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::evaluator::eval_str_with_vars_and_ctx;
+
+    #[test]
+    fn test_match_input() {
+        let input_expected = &[
+            ("a++", Some(3usize)),
+            ("a ++ + 1", Some(4)),
+            ("`a b`++", Some(7)),
+            ("a++)", Some(3)),
+            ("a++b", None),
+            ("a++ (1)", None),
+            ("a+", None),
+            ("++a", None),
+            ("10++", None),
+        ];
+        for ctx in &[Ctx::empty(), Ctx::default()] {
+            for (input, expected) in input_expected {
+                let result = Increment.match_input(input, ctx).map(|m| m.1);
+                assert_eq!(result, *expected, "input was {}", input);
+            }
+        }
+        assert_eq!(
+            Decrement.match_input("a--", &Ctx::default()).map(|m| m.1),
+            Some(3)
+        );
+        assert!(Decrement.match_input("a--1", &Ctx::default()).is_none());
+    }
+
+    #[test]
+    fn test_eval() {
+        let ctx = Ctx::default_with_macros();
+        let mut vars = HashMap::new();
+        let input_expected = &[
+            ("a = b = 3", Ok(3.0)),
+            ("a + b", Ok(6.0)),
+            ("a = 1 + b = 2", Ok(3.0)),
+            ("a++", Ok(3.0)),
+            ("a", Ok(4.0)),
+            ("a++ + b--", Ok(6.0)),
+            ("a * 10 + b", Ok(51.0)),
+            ("max(a--, 0)", Ok(5.0)),
+            ("a--1", Ok(5.0)),
+            ("a++b", Ok(5.0)),
+            (
+                "c++",
+                Err(evaluator::Error::VarNotFound("c".to_owned(), None)),
+            ),
+            (
+                "1 a++",
+                Err(evaluator::Error::ParserError(
+                    parser::Error::ExpectedOperator,
+                )),
+            ),
+        ];
+        for (input, expected) in input_expected {
+            let result = eval_str_with_vars_and_ctx(input, &mut vars, &ctx);
+            assert_eq!(result, *expected, "input was {}", input);
+        }
+    }
+}
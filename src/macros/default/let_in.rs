@@ -0,0 +1,238 @@
+use std::borrow::Cow;
+
+use crate::evaluator::{self, eval_str_with};
+use crate::macros::user_fn::Scope;
+use crate::macros::{InnerExpression, Macro, MacroParse, ParsedMacro};
+use crate::parser::{self, parse_str, ParseState};
+use crate::tokenizer::{match_id, skip_whitespace, Match};
+use crate::variables::VariableResolver;
+use crate::Ctx;
+
+/// The local binding macro.
+///
+/// # Matching
+///
+/// This macro matches the binding together with the expression it is visible in:
+/// ```text
+/// let<spaces>{id}<spaces>=<value><spaces>in<spaces><body>
+/// ```
+///
+/// The body extends as far right as possible, up to the end of the input,
+/// or to the comma or the closing paren that belongs to the enclosing expression,
+/// so `(let t = 2 in t * t) + 1` binds `t` only inside of the parens.
+///
+/// # Evaluation
+///
+/// The value is evaluated first, then the body is evaluated with `{id}` bound to it.
+/// The binding shadows the variable with the same name, and is never visible to the caller,
+/// so neither the binding nor the assignments to it change the variables.
+#[derive(Debug)]
+pub struct Let;
+
+impl Macro for Let {
+    fn match_input(&self, input: &str, ctx: &Ctx) -> Option<Match<()>> {
+        let (_, len) = match_let(input, ctx)?;
+        Some(Match((), len))
+    }
+
+    fn parse<'a>(
+        &self,
+        input: &'a str,
+        ctx: &Ctx,
+        current_state: ParseState,
+    ) -> Result<MacroParse<'a>, parser::Error> {
+        if let ParseState::Operator = current_state {
+            return Err(parser::Error::ExpectedOperator);
+        }
+        // unwrap: the input has been matched
+        let (parsed, _) = match_let(input, ctx).unwrap();
+        parse_str(&parsed.value, ctx)?;
+        parse_str(&parsed.body, ctx)?;
+        Ok(MacroParse::before(parsed, ParseState::Operator))
+    }
+}
+
+/// Matches `let {id} = <value> in <body>` at the start of the `input`
+fn match_let<'a>(input: &'a str, ctx: &Ctx) -> Option<(LetParsed<'a>, usize)> {
+    if !input.starts_with("let") {
+        return None;
+    }
+    let Match(keyword, c) = match_id(input, ctx)?;
+    let name_start = c + skip_whitespace(&input[c..]);
+    if keyword != "let" || name_start == c {
+        return None;
+    }
+    let Match(id, _) = match_id(&input[name_start..], ctx)?;
+    let name = &id[..id.find('=').unwrap_or(id.len())];
+    let name_end = name_start + name.len();
+    let eq = name_end + skip_whitespace(&input[name_end..]);
+    if name.is_empty() || !input[eq..].starts_with('=') || input[eq..].starts_with("==") {
+        return None;
+    }
+    let value_start = eq + '='.len_utf8();
+    let separator = ctx.tokenize_options.decimal_separator.argument_separator();
+    let value_end = value_start + expression_len(&input[value_start..], separator, true);
+    let in_start = value_end + skip_whitespace(&input[value_end..]);
+    if !starts_with_in(&input[in_start..]) {
+        return None;
+    }
+    let body_start = in_start + "in".len();
+    let body_end = body_start + expression_len(&input[body_start..], separator, false);
+    let value = input[value_start..value_end].trim();
+    let body = input[body_start..body_end].trim();
+    if value.is_empty() || body.is_empty() {
+        return None;
+    }
+    let len = body_start + input[body_start..body_end].trim_end().len();
+    let parsed = LetParsed {
+        name: Cow::Borrowed(name),
+        value: Cow::Borrowed(value),
+        body: Cow::Borrowed(body),
+    };
+    Some((parsed, len))
+}
+
+/// The length of the expression at the start of `text`,
+/// it ends at the argument `separator` or the closing paren that is not nested, or at the ` in ` if `stop_at_in`
+fn expression_len(text: &str, separator: char, stop_at_in: bool) -> usize {
+    let mut depth = 0;
+    for (i, ch) in text.char_indices() {
+        match ch {
+            '(' => depth += 1,
+            ')' if depth == 0 => return i,
+            ch if ch == separator && depth == 0 => return i,
+            ')' => depth -= 1,
+            ch if stop_at_in
+                && depth == 0
+                && ch.is_whitespace()
+                && starts_with_in(&text[i + ch.len_utf8()..]) =>
+            {
+                return i
+            }
+            _ => {}
+        }
+    }
+    text.len()
+}
+
+fn starts_with_in(text: &str) -> bool {
+    text.strip_prefix("in")
+        .is_some_and(|rest| rest.starts_with(char::is_whitespace))
+}
+
+/// Parsed [`Let`](Let) macro
+#[derive(Debug)]
+pub struct LetParsed<'a> {
+    name: Cow<'a, str>,
+    value: Cow<'a, str>,
+    body: Cow<'a, str>,
+}
+
+impl ParsedMacro for LetParsed<'_> {
+    fn eval(
+        &self,
+        eval_stack: &mut Vec<f64>,
+        variables: &mut dyn VariableResolver,
+        ctx: &Ctx,
+    ) -> Result<(), evaluator::Error> {
+        let value = eval_str_with(&self.value, variables, ctx)?;
+        let params = [self.name.to_string()];
+        let mut scope = Scope {
+            params: &params,
+            values: vec![value],
+            outer: variables,
+        };
+        eval_stack.push(eval_str_with(&self.body, &mut scope, ctx)?);
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "let"
+    }
+
+    fn inner_expressions(&self) -> Vec<InnerExpression<'_>> {
+        vec![
+            InnerExpression::new(&self.value),
+            InnerExpression {
+                input: &self.body,
+                bound: vec![&self.name],
+            },
+        ]
+    }
+
+    fn structural_key(&self) -> Option<String> {
+        Some(format!("{} = {} in {}", self.name, self.value, self.body))
+    }
+
+    fn to_owned_macro(&self) -> Option<Box<dyn ParsedMacro>> {
+        Some(Box::new(LetParsed {
+            name: Cow::Owned(self.name.to_string()),
+            value: Cow::Owned(self.value.to_string()),
+            body: Cow::Owned(self.body.to_string()),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::evaluator::{eval_str_with_vars_and_ctx, Error};
+
+    #[test]
+    fn test_match_input() {
+        let ctx = Ctx::default();
+        let input_expected = &[
+            ("let t = 2 in t", Some(14usize)),
+            ("let t=x*2 in t + t", Some(18)),
+            ("let t = 2 in t * t) + 1", Some(18)),
+            ("let t = 2 in max(t, 1), 3", Some(22)),
+            ("let t = (1, 2) in t", Some(19)),
+            ("let t = 2", None),
+            ("let t == 2 in t", None),
+            ("let t = in t", None),
+            ("let t = 2 in ", None),
+            ("lettuce = 2 in t", None),
+            ("let = 2 in t", None),
+        ];
+        for (input, expected) in input_expected {
+            let result = Let.match_input(input, &ctx).map(|m| m.1);
+            assert_eq!(result, *expected, "input was {}", input);
+        }
+    }
+
+    #[test]
+    fn test_eval() {
+        let ctx = Ctx::default_with_macros();
+        let mut vars = HashMap::new();
+        vars.insert("x".to_owned(), 3.0);
+        let input_expected = &[
+            ("let t = x * 2 in t + t", Ok(12.0)),
+            ("(let t = 2 in t * t) + 1", Ok(5.0)),
+            ("max(let t = 2 in t ^ 3, 5)", Ok(8.0)),
+            ("let x = x + 1 in x * 10", Ok(40.0)),
+            ("let t = 1 in (let u = t + 1 in t + u)", Ok(3.0)),
+            ("let t = 1 in t = 5", Ok(5.0)),
+            (
+                "(let t = 1 in t) + t",
+                Err(Error::VarNotFound("t".to_owned(), None)),
+            ),
+            (
+                "1 let t = 1 in t",
+                Err(Error::ParserError(parser::Error::ExpectedOperator)),
+            ),
+            (
+                "let t = 1 + in t",
+                Err(Error::ParserError(parser::Error::OperatorAtTheEnd)),
+            ),
+        ];
+        for (input, expected) in input_expected {
+            let result = eval_str_with_vars_and_ctx(input, &mut vars, &ctx);
+            assert_eq!(result, *expected, "input was {}", input);
+        }
+        // the bindings never reach the variables of the caller
+        assert_eq!(vars.get("x"), Some(&3.0));
+        assert_eq!(vars.get("t"), None);
+    }
+}
@@ -1,14 +1,29 @@
 //! Provides some default macros, and their parsed variants.
 pub use assign::Assign;
+pub use if_else::If;
+pub use increment::{Decrement, Increment};
+pub use let_in::Let;
 // TODO v0.3: move to mod parsed
 use crate::macros::Macro;
 pub use assign::AssignParsed;
+pub use if_else::IfParsed;
+pub use increment::IncrementParsed;
+pub use let_in::LetParsed;
 
 mod assign;
+mod if_else;
+mod increment;
+mod let_in;
 
 /// Get the list of default macros
 ///
 /// This includes all macros from [`macros::default`](self) module
 pub fn default_macros() -> Vec<Box<dyn Macro>> {
-    vec![Box::new(Assign)]
+    vec![
+        Box::new(Assign),
+        Box::new(If),
+        Box::new(Let),
+        Box::new(Increment),
+        Box::new(Decrement),
+    ]
 }
@@ -1,15 +1,19 @@
 //! This module contains the necessary types to implement your own macros.
-use std::collections::HashMap;
 use std::fmt::Debug;
 
+use crate::evaluator::EvalOptions;
 use crate::macros::ApplyMode::Before;
 use crate::parser::ParseState;
+use crate::variables::VariableResolver;
 use crate::{evaluator, parser};
 
 use super::tokenizer::Match;
 use super::Ctx;
 
+pub mod args;
+pub mod calculus;
 pub mod default;
+pub mod user_fn;
 
 /// Specifies how the macro should be parsed in relation to other tokens.
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
@@ -81,7 +85,10 @@ impl<'a> MacroParse<'a> {
 }
 
 /// Implement this trait (+ [`Debug`](std::fmt::Debug) to create your own macro).
-pub trait Macro: Debug {
+///
+/// Macros are required to be [`Send`](std::marker::Send) and [`Sync`](std::marker::Sync),
+/// so that [`Ctx`](crate::Ctx) can be shared between threads.
+pub trait Macro: Debug + Send + Sync {
     /// Match the start of the `input` with this macro.
     ///
     /// Returns [`Some(length of the match)`](std::option::Option::Some) if the start of the `input` matched this macro
@@ -101,19 +108,120 @@ pub trait Macro: Debug {
     ) -> Result<MacroParse<'a>, parser::Error>;
 }
 
+/// The expression a parsed macro evaluates by itself, see [`ParsedMacro::inner_expressions`](ParsedMacro::inner_expressions).
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct InnerExpression<'s> {
+    /// The input of the expression.
+    pub input: &'s str,
+    /// The variables that are bound only while the expression is evaluated, e.g. the parameters of the function.
+    pub bound: Vec<&'s str>,
+}
+
+impl<'s> InnerExpression<'s> {
+    /// Creates the expression that doesn't bind any variables.
+    pub fn new(input: &'s str) -> Self {
+        InnerExpression {
+            input,
+            bound: Vec::new(),
+        }
+    }
+}
+
 /// Represents the Parsed macro.
 ///
 /// Types implementing this trait should contain all the information necessary to evaluate this macro.
 ///
 /// Don't forget to derive or implement [`Debug`](std::fmt::Debug).
-pub trait ParsedMacro: Debug {
+///
+/// Parsed macros are required to be [`Send`](std::marker::Send) and [`Sync`](std::marker::Sync),
+/// so that parsed token streams can be shared between threads.
+pub trait ParsedMacro: Debug + Send + Sync {
     /// Evaluate this parsed macro
     ///
     /// Arguments contain the current state of the evaluator.
     fn eval(
         &self,
         eval_stack: &mut Vec<f64>,
-        variables: &mut HashMap<String, f64>,
+        variables: &mut dyn VariableResolver,
         ctx: &Ctx,
     ) -> Result<(), evaluator::Error>;
+
+    /// Evaluate this parsed macro with the `options` of the caller, see [`eval_with_options`](crate::evaluator::eval_with_options).
+    ///
+    /// The macros that evaluate the expressions by themselves, e.g. the branches of `if`,
+    /// should evaluate them with the `options`, see [`ParsedArg`](crate::macros::args::ParsedArg).
+    ///
+    /// The default ignores the options and calls [`eval`](ParsedMacro::eval).
+    fn eval_with_options(
+        &self,
+        eval_stack: &mut Vec<f64>,
+        variables: &mut dyn VariableResolver,
+        ctx: &Ctx,
+        options: &EvalOptions,
+    ) -> Result<(), evaluator::Error> {
+        let _ = options;
+        self.eval(eval_stack, variables, ctx)
+    }
+
+    /// The number of values from the top of the evaluation stack this macro uses as its operands.
+    ///
+    /// It is used to reconstruct the [`syntax tree`](crate::parser::Ast) from the token stream,
+    /// the macro is expected to replace its operands with a single value.
+    ///
+    /// The default is 0, i.e. the macro only pushes a value onto the stack.
+    fn operands(&self) -> usize {
+        0
+    }
+
+    /// The name of the macro reported by [`analysis::references`](crate::analysis::references).
+    ///
+    /// The default is the name of the type.
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+
+    /// The variable this macro assigns, used by [`analysis::dependencies`](crate::analysis::dependencies).
+    ///
+    /// The default is `None`, i.e. the macro doesn't assign any variables.
+    fn assigns(&self) -> Option<&str> {
+        None
+    }
+
+    /// Whether the macro writes the variables of the caller, used by [`eval_readonly`](crate::evaluator::eval_readonly)
+    /// to reject the expressions that can't be evaluated without the mutable variables.
+    ///
+    /// The writes of the [`inner_expressions`](ParsedMacro::inner_expressions) are found by parsing them,
+    /// they don't have to be reported here.
+    ///
+    /// The default is `true` if the macro [`assigns`](ParsedMacro::assigns) a variable.
+    fn writes_variables(&self) -> bool {
+        self.assigns().is_some()
+    }
+
+    /// The expressions this macro parses and evaluates by itself, e.g. the branches of `if`,
+    /// used by [`analysis::variables_of`](crate::analysis::variables_of) and [`analysis::functions_of`](crate::analysis::functions_of).
+    ///
+    /// The default is empty, i.e. the macro doesn't evaluate any expressions.
+    fn inner_expressions(&self) -> Vec<InnerExpression<'_>> {
+        Vec::new()
+    }
+
+    /// The key that identifies the parsed macro for the structural [`PartialEq`](std::cmp::PartialEq) and [`Hash`](std::hash::Hash)
+    /// of the [`ParserToken`](crate::parser::ParserToken).
+    ///
+    /// Two parsed macros with the same [`name`](ParsedMacro::name) and equal keys are considered equal.
+    /// The key should contain everything that affects the evaluation of the macro.
+    ///
+    /// The default is `None`, i.e. the parsed macro is only equal to itself.
+    fn structural_key(&self) -> Option<String> {
+        None
+    }
+
+    /// The copy of the parsed macro that doesn't borrow the input,
+    /// used by [`CompiledExpr`](crate::expression::CompiledExpr).
+    ///
+    /// The default is `None`, i.e. the expressions with this macro can't be compiled.
+    fn to_owned_macro(&self) -> Option<Box<dyn ParsedMacro>> {
+        None
+    }
 }
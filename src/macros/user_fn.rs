@@ -0,0 +1,269 @@
+//! Provides the macro that implements the functions defined by an expression.
+//!
+//! Unlike [`Func`](crate::functions::Func) that is implemented in rust,
+//! [`UserFn`](UserFn) is defined at runtime using the parameter names and the expression of its body.
+//!
+//! # Example
+//!
+//! ```
+//! use std::collections::HashMap;
+//! use rusty_yard::evaluator::eval_str_with_vars_and_ctx;
+//! use rusty_yard::macros::user_fn::UserFn;
+//! use rusty_yard::Ctx;
+//!
+//! let mut ctx = Ctx::default();
+//! ctx.macros.push(Box::new(UserFn {
+//!     token: "hypot".to_owned(),
+//!     params: vec!["a".to_owned(), "b".to_owned()],
+//!     body: "(a ^ 2 + b ^ 2) ^ 0.5".to_owned(),
+//! }));
+//! let mut vars = HashMap::new();
+//! assert_eq!(eval_str_with_vars_and_ctx("hypot(3, 4) * 2", &mut vars, &ctx), Ok(10.0));
+//! ```
+use std::borrow::Cow;
+
+use crate::evaluator::{self, eval_str_with};
+use crate::macros::args::{call_args, match_call};
+use crate::macros::{InnerExpression, Macro, MacroParse, ParsedMacro};
+use crate::parser::{self, parse_str, ParseState};
+use crate::tokenizer::Match;
+use crate::variables::VariableResolver;
+use crate::Ctx;
+
+/// The function defined by an expression.
+///
+/// # Matching
+///
+/// This macro matches the whole call of the function:
+/// ```text
+/// {token}<spaces>(<arguments>)
+/// ```
+///
+/// # Evaluation
+///
+/// The arguments are evaluated first, then the body is evaluated with the parameters bound to their values.
+/// Other variables used in the body are resolved from the variables of the caller.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserFn {
+    /// Identifier of the function.
+    pub token: String,
+    /// Names of the parameters.
+    pub params: Vec<String>,
+    /// The expression that is evaluated when the function is called.
+    pub body: String,
+}
+
+impl Macro for UserFn {
+    fn match_input(&self, input: &str, ctx: &Ctx) -> Option<Match<()>> {
+        match_call(input, &self.token, ctx)
+    }
+
+    fn parse<'a>(
+        &self,
+        input: &'a str,
+        ctx: &Ctx,
+        current_state: ParseState,
+    ) -> Result<MacroParse<'a>, parser::Error> {
+        if let ParseState::Operator = current_state {
+            return Err(parser::Error::ExpectedOperator);
+        }
+        let args = call_args(input, &self.token, self.params.len(), ctx)?;
+        for arg in &args {
+            parse_str(arg, ctx)?;
+        }
+        Ok(MacroParse::before(
+            UserFnParsed {
+                func: self.clone(),
+                args: args.into_iter().map(Cow::Borrowed).collect(),
+            },
+            ParseState::Operator,
+        ))
+    }
+}
+
+/// Parsed call of the [`UserFn`](UserFn)
+#[derive(Debug)]
+pub struct UserFnParsed<'a> {
+    func: UserFn,
+    args: Vec<Cow<'a, str>>,
+}
+
+impl ParsedMacro for UserFnParsed<'_> {
+    fn eval(
+        &self,
+        eval_stack: &mut Vec<f64>,
+        variables: &mut dyn VariableResolver,
+        ctx: &Ctx,
+    ) -> Result<(), evaluator::Error> {
+        let values = self
+            .args
+            .iter()
+            .map(|arg| eval_str_with(arg, variables, ctx))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut scope = Scope {
+            params: &self.func.params,
+            values,
+            outer: variables,
+        };
+        let result = eval_str_with(&self.func.body, &mut scope, ctx)?;
+        eval_stack.push(result);
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        &self.func.token
+    }
+
+    fn inner_expressions(&self) -> Vec<InnerExpression<'_>> {
+        let body = InnerExpression {
+            input: &self.func.body,
+            bound: self.func.params.iter().map(String::as_str).collect(),
+        };
+        let args = self.args.iter().map(|arg| InnerExpression::new(arg));
+        args.chain(std::iter::once(body)).collect()
+    }
+
+    fn structural_key(&self) -> Option<String> {
+        Some(format!(
+            "({}) = {}; ({})",
+            self.func.params.join(", "),
+            self.func.body,
+            self.args.join(", ")
+        ))
+    }
+
+    fn to_owned_macro(&self) -> Option<Box<dyn ParsedMacro>> {
+        Some(Box::new(UserFnParsed {
+            func: self.func.clone(),
+            args: self
+                .args
+                .iter()
+                .map(|arg| Cow::Owned(arg.to_string()))
+                .collect(),
+        }))
+    }
+}
+
+/// Variables of the function body, the parameters shadow the variables of the caller.
+pub(crate) struct Scope<'s> {
+    pub(crate) params: &'s [String],
+    pub(crate) values: Vec<f64>,
+    pub(crate) outer: &'s mut dyn VariableResolver,
+}
+
+impl Scope<'_> {
+    fn param(&self, name: &str) -> Option<usize> {
+        self.params.iter().position(|param| param == name)
+    }
+}
+
+impl VariableResolver for Scope<'_> {
+    fn get(&self, name: &str) -> Option<f64> {
+        match self.param(name) {
+            Some(i) => Some(self.values[i]),
+            None => self.outer.get(name),
+        }
+    }
+
+    fn set(&mut self, name: &str, value: f64) {
+        match self.param(name) {
+            Some(i) => self.values[i] = value,
+            None => self.outer.set(name, value),
+        }
+    }
+
+    fn names(&self) -> Vec<String> {
+        let mut names = self.outer.names();
+        names.extend(self.params.iter().cloned());
+        names
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::evaluator::eval_str_with_vars_and_ctx;
+
+    fn get_ctx() -> Ctx {
+        let mut ctx = Ctx::default_with_macros();
+        ctx.macros.push(Box::new(UserFn {
+            token: "hypot".to_owned(),
+            params: vec!["a".to_owned(), "b".to_owned()],
+            body: "(a ^ 2 + b ^ 2) ^ 0.5".to_owned(),
+        }));
+        ctx.macros.push(Box::new(UserFn {
+            token: "scaled".to_owned(),
+            params: vec!["a".to_owned()],
+            body: "a * scale".to_owned(),
+        }));
+        ctx.macros.push(Box::new(UserFn {
+            token: "one".to_owned(),
+            params: vec![],
+            body: "1".to_owned(),
+        }));
+        ctx
+    }
+
+    #[test]
+    fn test_match_input() {
+        let ctx = get_ctx();
+        let hypot = &ctx.macros[ctx.macros.len() - 3];
+        let input_expected = &[
+            ("hypot(3, 4)", Some(11usize)),
+            ("hypot (3, 4) + 1", Some(12)),
+            ("hypot((1 + 2), max(3, 4))", Some(25)),
+            ("hypot(3, 4", None),
+            ("hypot", None),
+            ("hypotenuse(3, 4)", None),
+            ("a + hypot(3, 4)", None),
+        ];
+        for (input, expected) in input_expected {
+            let result = hypot.match_input(input, &ctx).map(|m| m.1);
+            assert_eq!(result, *expected, "input was {}", input);
+        }
+    }
+
+    #[test]
+    fn test_eval() {
+        let ctx = get_ctx();
+        let mut vars = HashMap::new();
+        vars.insert("scale".to_owned(), 10.0);
+        vars.insert("a".to_owned(), -1.0);
+        let input_expected = &[
+            ("hypot(3, 4)", Ok(5.0)),
+            ("1 + hypot(3, 4) * 2", Ok(11.0)),
+            ("hypot(hypot(3, 4), 12)", Ok(13.0)),
+            ("scaled(a + 3)", Ok(20.0)),
+            ("a", Ok(-1.0)),
+            ("one() + one( )", Ok(2.0)),
+            ("b = scaled(2)", Ok(20.0)),
+            (
+                "scaled(c)",
+                Err(evaluator::Error::VarNotFound("c".to_owned(), None)),
+            ),
+            (
+                "hypot(1)",
+                Err(evaluator::Error::ParserError(
+                    parser::Error::ArityMismatch {
+                        id: "hypot".to_owned(),
+                        expected: 2.into(),
+                        actual: 1,
+                    },
+                )),
+            ),
+            (
+                "1 hypot(3, 4)",
+                Err(evaluator::Error::ParserError(
+                    parser::Error::ExpectedOperator,
+                )),
+            ),
+        ];
+        for (input, expected) in input_expected {
+            let result = eval_str_with_vars_and_ctx(input, &mut vars, &ctx);
+            assert_eq!(result, *expected, "input was {}", input);
+        }
+        assert_eq!(vars.get("b"), Some(&20.0));
+    }
+}
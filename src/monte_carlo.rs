@@ -0,0 +1,419 @@
+//! Monte Carlo evaluation of the expression with random inputs, e.g. for risk analysis.
+//!
+//! The variables are bound to the [`distributions`](Distribution) instead of the point values,
+//! the expression is evaluated with the random values drawn from them,
+//! and the results are summarized in [`Summary`](Summary).
+//!
+//! # Example
+//!
+//! ```
+//! use std::collections::HashMap;
+//! use rusty_yard::expression::Expression;
+//! use rusty_yard::monte_carlo::{Distribution, MonteCarlo};
+//! use rusty_yard::Ctx;
+//!
+//! let ctx = Ctx::default();
+//! let expr = Expression::parse("units * price - cost", &ctx).unwrap();
+//! let mut vars = HashMap::new();
+//! vars.insert("cost".to_owned(), 1000.0);
+//!
+//! let mut monte_carlo = MonteCarlo::new(42);
+//! monte_carlo
+//!     .bind("units", Distribution::Triangular { min: 50.0, mode: 100.0, max: 200.0 })
+//!     .bind("price", Distribution::Uniform { min: 10.0, max: 15.0 });
+//! let summary = monte_carlo.simulate(&expr, 10_000, &mut vars).unwrap();
+//! // the mean of units is 350 / 3 and the mean of price is 12.5
+//! assert!((summary.mean - 458.33).abs() < 20.0);
+//! assert!(summary.percentile(5.0) < summary.percentile(95.0));
+//! ```
+use crate::evaluator::{self, eval_with_stack};
+use crate::expression::Expression;
+use crate::random::XorShift;
+use crate::sample::Bound;
+use crate::variables::VariableResolver;
+
+/// The probability distribution of the variable.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Distribution {
+    /// The value is always the same.
+    Constant(f64),
+    /// Every value in `[min, max)` is equally likely.
+    Uniform {
+        /// The lower bound.
+        min: f64,
+        /// The upper bound.
+        max: f64,
+    },
+    /// The normal (Gaussian) distribution.
+    Normal {
+        /// The mean of the distribution.
+        mean: f64,
+        /// The standard deviation of the distribution.
+        stddev: f64,
+    },
+    /// The triangular distribution, the density rises linearly from `min` to `mode` and falls to `max`.
+    ///
+    /// `min <= mode <= max` should hold.
+    Triangular {
+        /// The lower bound.
+        min: f64,
+        /// The most likely value.
+        mode: f64,
+        /// The upper bound.
+        max: f64,
+    },
+}
+
+impl Distribution {
+    fn sample(&self, rng: &mut XorShift) -> f64 {
+        match *self {
+            Distribution::Constant(value) => value,
+            Distribution::Uniform { min, max } => min + (max - min) * rng.unit(),
+            Distribution::Normal { mean, stddev } => mean + stddev * rng.normal(),
+            Distribution::Triangular { min, mode, max } => {
+                if max <= min {
+                    return min;
+                }
+                // the inverse of the cumulative distribution function
+                let u = rng.unit();
+                let width = max - min;
+                if u < (mode - min) / width {
+                    min + (u * width * (mode - min)).sqrt()
+                } else {
+                    max - ((1.0 - u) * width * (max - mode)).sqrt()
+                }
+            }
+        }
+    }
+}
+
+/// The summary statistics of the results of [`MonteCarlo::simulate`](MonteCarlo::simulate).
+///
+/// The results that are `NaN` or infinite are only counted in `non_finite`,
+/// the statistics are computed over the rest of the results.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Summary {
+    /// The finite results in ascending order.
+    pub samples: Vec<f64>,
+    /// The number of the results that are `NaN` or infinite.
+    pub non_finite: usize,
+    /// The mean of the results, `NaN` if there are none.
+    pub mean: f64,
+    /// The sample standard deviation of the results, 0 if there is one result, `NaN` if there are none.
+    pub stddev: f64,
+}
+
+impl Summary {
+    /// Computes the summary of the results.
+    pub fn new(results: impl IntoIterator<Item = f64>) -> Self {
+        let mut non_finite = 0;
+        let mut samples: Vec<f64> = results
+            .into_iter()
+            .filter(|result| {
+                let finite = result.is_finite();
+                if !finite {
+                    non_finite += 1;
+                }
+                finite
+            })
+            .collect();
+        samples.sort_by(f64::total_cmp);
+        let n = samples.len() as f64;
+        let mean = samples.iter().sum::<f64>() / n;
+        let stddev = match samples.len() {
+            0 => f64::NAN,
+            1 => 0.0,
+            _ => {
+                let squares: f64 = samples.iter().map(|s| (s - mean).powi(2)).sum();
+                (squares / (n - 1.0)).sqrt()
+            }
+        };
+        Self {
+            samples,
+            non_finite,
+            mean,
+            stddev,
+        }
+    }
+
+    /// The smallest result, `NaN` if there are none.
+    pub fn min(&self) -> f64 {
+        self.samples.first().copied().unwrap_or(f64::NAN)
+    }
+
+    /// The largest result, `NaN` if there are none.
+    pub fn max(&self) -> f64 {
+        self.samples.last().copied().unwrap_or(f64::NAN)
+    }
+
+    /// Returns the `p`-th percentile of the results, `p` is clamped to `[0, 100]`.
+    ///
+    /// The percentile is linearly interpolated between the closest results, `NaN` if there are none.
+    pub fn percentile(&self, p: f64) -> f64 {
+        if self.samples.is_empty() {
+            return f64::NAN;
+        }
+        let position = p.clamp(0.0, 100.0) / 100.0 * (self.samples.len() - 1) as f64;
+        let lower = position.floor() as usize;
+        let upper = position.ceil() as usize;
+        let fraction = position - lower as f64;
+        self.samples[lower] + (self.samples[upper] - self.samples[lower]) * fraction
+    }
+}
+
+/// Evaluates the expression many times with the variables drawn from their distributions.
+///
+/// The same `seed` and the same calls produce the same results.
+/// The bound variables shadow the variables with the same names in the resolver,
+/// other variables are read from it.
+#[derive(Debug, Clone)]
+pub struct MonteCarlo<'v> {
+    inputs: Vec<(&'v str, Distribution)>,
+    rng: XorShift,
+}
+
+impl<'v> MonteCarlo<'v> {
+    /// Creates the simulation without the bound variables.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            inputs: Vec::new(),
+            rng: XorShift::new(seed),
+        }
+    }
+
+    /// Binds `var` to the `distribution`, replacing the previous distribution of `var`.
+    pub fn bind(&mut self, var: &'v str, distribution: Distribution) -> &mut Self {
+        match self.inputs.iter_mut().find(|(input, _)| *input == var) {
+            Some((_, old)) => *old = distribution,
+            None => self.inputs.push((var, distribution)),
+        }
+        self
+    }
+
+    /// The bound variables and their distributions.
+    pub fn inputs(&self) -> &[(&'v str, Distribution)] {
+        &self.inputs
+    }
+
+    /// Evaluates the expression `n` times and summarizes the results.
+    ///
+    /// The evaluation stops at the first [`Error`](crate::evaluator::Error).
+    pub fn simulate(
+        &mut self,
+        expr: &Expression,
+        n: usize,
+        variables: &mut dyn VariableResolver,
+    ) -> Result<Summary, evaluator::Error> {
+        let mut bound = Bound {
+            vars: self
+                .inputs
+                .iter()
+                .map(|(var, _)| (*var, 0.0))
+                .collect::<Vec<_>>(),
+            variables,
+        };
+        let mut stack = Vec::new();
+        let mut results = Vec::with_capacity(n);
+        for _ in 0..n {
+            for ((_, distribution), (_, value)) in self.inputs.iter().zip(&mut bound.vars) {
+                *value = distribution.sample(&mut self.rng);
+            }
+            results.push(eval_with_stack(
+                expr.tokens(),
+                &mut bound,
+                expr.ctx(),
+                &mut stack,
+            )?);
+        }
+        Ok(Summary::new(results))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::Ctx;
+
+    #[test]
+    fn test_distributions() {
+        // mean and standard deviation
+        let input_expected = &[
+            (Distribution::Constant(3.0), 3.0, 0.0),
+            (
+                Distribution::Uniform { min: 0.0, max: 1.0 },
+                0.5,
+                12f64.recip().sqrt(),
+            ),
+            (
+                Distribution::Uniform {
+                    min: -4.0,
+                    max: 2.0,
+                },
+                -1.0,
+                3f64.sqrt(),
+            ),
+            (
+                Distribution::Normal {
+                    mean: 10.0,
+                    stddev: 2.0,
+                },
+                10.0,
+                2.0,
+            ),
+            (
+                Distribution::Triangular {
+                    min: 0.0,
+                    mode: 0.0,
+                    max: 3.0,
+                },
+                1.0,
+                0.5f64.sqrt(),
+            ),
+            (
+                Distribution::Triangular {
+                    min: 1.0,
+                    mode: 2.0,
+                    max: 3.0,
+                },
+                2.0,
+                6f64.recip().sqrt(),
+            ),
+            (
+                Distribution::Triangular {
+                    min: 1.0,
+                    mode: 1.0,
+                    max: 1.0,
+                },
+                1.0,
+                0.0,
+            ),
+        ];
+        let ctx = Ctx::default();
+        let expr = Expression::parse("x", &ctx).unwrap();
+        for (distribution, mean, stddev) in input_expected {
+            let mut monte_carlo = MonteCarlo::new(7);
+            monte_carlo.bind("x", *distribution);
+            let summary = monte_carlo
+                .simulate(&expr, 20_000, &mut HashMap::new())
+                .unwrap();
+            assert!(
+                (summary.mean - mean).abs() < 0.05 * stddev.max(1.0),
+                "input was {:?}, mean {}",
+                distribution,
+                summary.mean
+            );
+            assert!(
+                (summary.stddev - stddev).abs() < 0.05 * stddev.max(0.1),
+                "input was {:?}, stddev {}",
+                distribution,
+                summary.stddev
+            );
+        }
+    }
+
+    #[test]
+    fn test_simulate() {
+        let ctx = Ctx::default();
+        let expr = Expression::parse("x + y", &ctx).unwrap();
+        let mut vars = HashMap::new();
+        vars.insert("x".to_owned(), 100.0);
+        vars.insert("y".to_owned(), 1.0);
+
+        let mut monte_carlo = MonteCarlo::new(1);
+        monte_carlo
+            .bind(
+                "x",
+                Distribution::Normal {
+                    mean: 0.0,
+                    stddev: 1.0,
+                },
+            )
+            .bind("x", Distribution::Uniform { min: 0.0, max: 1.0 });
+        assert_eq!(monte_carlo.inputs().len(), 1);
+        let summary = monte_carlo.simulate(&expr, 1000, &mut vars).unwrap();
+        // the bound variable shadows the outer one
+        assert!(summary.min() >= 1.0 && summary.max() < 2.0);
+        assert_eq!(vars.get("x"), Some(&100.0));
+
+        // the same seed produces the same results
+        let mut other = MonteCarlo::new(1);
+        other.bind("x", Distribution::Uniform { min: 0.0, max: 1.0 });
+        assert_eq!(other.simulate(&expr, 1000, &mut vars), Ok(summary));
+
+        vars.remove("y");
+        let result = monte_carlo.simulate(&expr, 10, &mut vars);
+        assert_eq!(
+            result,
+            Err(evaluator::Error::VarNotFound("y".to_owned(), None))
+        );
+    }
+
+    #[test]
+    fn test_simulate_non_finite() {
+        let ctx = Ctx::default();
+        let expr = Expression::parse("x ^ 0.5", &ctx).unwrap();
+        let mut monte_carlo = MonteCarlo::new(3);
+        monte_carlo.bind(
+            "x",
+            Distribution::Uniform {
+                min: -1.0,
+                max: 1.0,
+            },
+        );
+        let summary = monte_carlo
+            .simulate(&expr, 1000, &mut HashMap::new())
+            .unwrap();
+        assert_eq!(summary.samples.len() + summary.non_finite, 1000);
+        assert!(summary.non_finite > 400 && summary.non_finite < 600);
+        assert!(summary.min() >= 0.0);
+    }
+
+    #[test]
+    fn test_summary() {
+        type Case = (&'static [f64], usize, f64, f64, &'static [(f64, f64)]);
+        let input_expected: &[Case] = &[
+            (&[], 0, f64::NAN, f64::NAN, &[(50.0, f64::NAN)]),
+            (&[f64::NAN, f64::INFINITY], 2, f64::NAN, f64::NAN, &[]),
+            (&[5.0], 0, 5.0, 0.0, &[(0.0, 5.0), (100.0, 5.0)]),
+            (
+                &[4.0, 1.0, 3.0, 2.0, f64::NEG_INFINITY],
+                1,
+                2.5,
+                (5.0f64 / 3.0).sqrt(),
+                &[
+                    (0.0, 1.0),
+                    (50.0, 2.5),
+                    (100.0, 4.0),
+                    (-10.0, 1.0),
+                    (150.0, 4.0),
+                ],
+            ),
+            (
+                &[0.0, 10.0, 20.0, 30.0, 40.0],
+                0,
+                20.0,
+                250f64.sqrt(),
+                &[(25.0, 10.0), (30.0, 12.0), (95.0, 38.0)],
+            ),
+        ];
+        let same = |a: f64, b: f64| (a.is_nan() && b.is_nan()) || (a - b).abs() < 1e-12;
+        for (results, non_finite, mean, stddev, percentiles) in input_expected {
+            let summary = Summary::new(results.iter().copied());
+            assert_eq!(summary.non_finite, *non_finite, "input was {:?}", results);
+            assert!(same(summary.mean, *mean), "input was {:?}", results);
+            assert!(same(summary.stddev, *stddev), "input was {:?}", results);
+            for (p, expected) in *percentiles {
+                let result = summary.percentile(*p);
+                assert!(
+                    same(result, *expected),
+                    "input was {:?} {}, result {}",
+                    results,
+                    p,
+                    result
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,83 @@
+//! Numerical differentiation, see [`derivative_at`](derivative_at) and [`Gradient`](crate::numeric::Gradient).
+use crate::evaluator;
+use crate::expression::Expression;
+use crate::sample::Sampler;
+use crate::variables::VariableResolver;
+
+/// How the derivative was computed.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Derivative {
+    /// The derivative expression from [`differentiate`](crate::analysis::differentiate).
+    Symbolic,
+    /// The finite difference, see [`derivative_at`](derivative_at).
+    Numeric,
+}
+
+/// Computes the derivative of the expression with respect to `var` at `x` with finite differences.
+///
+/// This uses the five-point central difference, the step is scaled with `x`.
+/// Other variables are read from `variables`, `var` shadows the variable with the same name.
+/// See [`differentiate`](crate::analysis::differentiate) for the exact derivative.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use rusty_yard::expression::Expression;
+/// use rusty_yard::numeric::derivative_at;
+/// use rusty_yard::Ctx;
+///
+/// let ctx = Ctx::default();
+/// let expr = Expression::parse("x ^ 3", &ctx).unwrap();
+/// let slope = derivative_at(&expr, "x", 2.0, &mut HashMap::new()).unwrap();
+/// assert!((slope - 12.0).abs() < 1e-9);
+/// ```
+pub fn derivative_at(
+    expr: &Expression,
+    var: &str,
+    x: f64,
+    variables: &mut dyn VariableResolver,
+) -> evaluator::Result {
+    let mut sampler = Sampler::new(expr, [var], variables);
+    five_point(|x| sampler.eval([x]), x)
+}
+
+/// The five-point central difference of `f` at `x`
+pub(crate) fn five_point(mut f: impl FnMut(f64) -> evaluator::Result, x: f64) -> evaluator::Result {
+    // the optimal step for the error of order h^4
+    let h = f64::EPSILON.powf(0.2) * x.abs().max(1.0);
+    let difference = f(x - 2.0 * h)? - 8.0 * f(x - h)? + 8.0 * f(x + h)? - f(x + 2.0 * h)?;
+    Ok(difference / (12.0 * h))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::Ctx;
+
+    #[test]
+    fn test_derivative_at() {
+        let input_expected = &[
+            ("1", 0.0, 0.0),
+            ("x", 5.0, 1.0),
+            ("x ^ 2", -3.0, -6.0),
+            ("x ^ 3 - 2 * x", 1.0, 1.0),
+            ("1 / x", 2.0, -0.25),
+            ("x ^ 0.5", 1e6, 0.0005),
+            ("max(x, 2 * x)", 1.0, 2.0),
+        ];
+        let ctx = Ctx::default();
+        for (input, x, expected) in input_expected {
+            let expr = Expression::parse(input, &ctx).unwrap();
+            let result = derivative_at(&expr, "x", *x, &mut HashMap::new()).unwrap();
+            assert!(
+                (result - expected).abs() < 1e-8,
+                "input was {}, result {}",
+                input,
+                result
+            );
+        }
+    }
+}
@@ -0,0 +1,194 @@
+//! The value and the gradient of the expression, see [`Gradient`](Gradient).
+use super::derivative::{five_point, Derivative};
+use crate::analysis::{differentiate, simplify};
+use crate::evaluator::{self, eval_with_stack, var_not_found};
+use crate::expression::Expression;
+use crate::sample::Sampler;
+use crate::variables::VariableResolver;
+
+/// Evaluates the expression together with its partial derivatives with respect to the variables.
+///
+/// The partial derivatives are prepared once, when the gradient is created:
+/// [`differentiate`](crate::analysis::differentiate) and [`simplify`](crate::analysis::simplify) are used
+/// where possible, and the finite differences otherwise.
+/// Then the gradient can be evaluated for many values of the variables, sharing the evaluation stack.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use rusty_yard::expression::Expression;
+/// use rusty_yard::numeric::Gradient;
+/// use rusty_yard::Ctx;
+///
+/// let ctx = Ctx::default();
+/// let expr = Expression::parse("x ^ 2 * y + y", &ctx).unwrap();
+/// let mut gradient = Gradient::new(&expr, &["x", "y"]);
+///
+/// let mut vars = HashMap::new();
+/// vars.insert("x".to_owned(), 3.0);
+/// vars.insert("y".to_owned(), 2.0);
+/// assert_eq!(gradient.eval(&mut vars), Ok((20.0, vec![12.0, 10.0])));
+/// ```
+#[derive(Debug)]
+pub struct Gradient<'e, 'a, 'ctx, 'v> {
+    expr: &'e Expression<'a, 'ctx>,
+    partials: Vec<(&'v str, Option<Expression<'a, 'ctx>>)>,
+    stack: Vec<f64>,
+}
+
+impl<'e, 'a, 'ctx, 'v> Gradient<'e, 'a, 'ctx, 'v> {
+    /// Prepares the partial derivatives of the expression with respect to `vars`.
+    pub fn new(expr: &'e Expression<'a, 'ctx>, vars: &[&'v str]) -> Self {
+        let partials = vars
+            .iter()
+            .map(|var| {
+                let derivative = differentiate(expr, var).ok().map(|derivative| {
+                    match simplify(&derivative, simplify::Options::default()) {
+                        Ok(simplified) => simplified.expression,
+                        Err(_) => derivative,
+                    }
+                });
+                (*var, derivative)
+            })
+            .collect();
+        Self {
+            expr,
+            partials,
+            stack: Vec::new(),
+        }
+    }
+
+    /// The variables of the gradient in the order of the partial derivatives.
+    pub fn vars(&self) -> impl Iterator<Item = &'v str> + '_ {
+        self.partials.iter().map(|(var, _)| *var)
+    }
+
+    /// How the partial derivative with respect to each variable is computed.
+    pub fn derivatives(&self) -> impl Iterator<Item = Derivative> + '_ {
+        self.partials.iter().map(|(_, partial)| match partial {
+            Some(_) => Derivative::Symbolic,
+            None => Derivative::Numeric,
+        })
+    }
+
+    /// Evaluates the expression and its gradient with the `variables`.
+    ///
+    /// Returns the value of the expression and the partial derivatives in the order of the variables.
+    pub fn eval(
+        &mut self,
+        variables: &mut dyn VariableResolver,
+    ) -> Result<(f64, Vec<f64>), evaluator::Error> {
+        let mut gradient = vec![0.0; self.partials.len()];
+        let value = self.eval_into(variables, &mut gradient)?;
+        Ok((value, gradient))
+    }
+
+    /// Evaluates the expression and writes its gradient into `gradient`, returns the value of the expression.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of `gradient` is not the number of the variables.
+    pub fn eval_into(
+        &mut self,
+        variables: &mut dyn VariableResolver,
+        gradient: &mut [f64],
+    ) -> evaluator::Result {
+        assert_eq!(
+            gradient.len(),
+            self.partials.len(),
+            "Gradient has {} variables",
+            self.partials.len()
+        );
+        let expr = self.expr;
+        let value = eval_with_stack(expr.tokens(), variables, expr.ctx(), &mut self.stack)?;
+        for ((var, partial), result) in self.partials.iter().zip(gradient) {
+            *result = match partial {
+                Some(partial) => {
+                    eval_with_stack(partial.tokens(), variables, partial.ctx(), &mut self.stack)?
+                }
+                None => {
+                    let x = variables
+                        .get(var)
+                        .ok_or_else(|| var_not_found(var, variables, expr.ctx()))?;
+                    let mut sampler = Sampler::new(expr, [*var], variables);
+                    five_point(|x| sampler.eval([x]), x)?
+                }
+            };
+        }
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::Ctx;
+
+    #[test]
+    fn test_gradient() {
+        let input_expected: &[(&str, &[f64], &[Derivative])] = &[
+            (
+                "1",
+                &[0.0, 0.0],
+                &[Derivative::Symbolic, Derivative::Symbolic],
+            ),
+            (
+                "x * y",
+                &[3.0, 2.0],
+                &[Derivative::Symbolic, Derivative::Symbolic],
+            ),
+            (
+                "x ^ 2 + c",
+                &[4.0, 0.0],
+                &[Derivative::Symbolic, Derivative::Symbolic],
+            ),
+            (
+                "max(x, y)",
+                &[0.0, 1.0],
+                &[Derivative::Numeric, Derivative::Numeric],
+            ),
+            (
+                "max(x, 0) + y",
+                &[1.0, 1.0],
+                &[Derivative::Numeric, Derivative::Symbolic],
+            ),
+        ];
+        let ctx = Ctx::default();
+        let mut vars = HashMap::new();
+        vars.insert("x".to_owned(), 2.0);
+        vars.insert("y".to_owned(), 3.0);
+        vars.insert("c".to_owned(), 1.0);
+        for (input, expected, derivatives) in input_expected {
+            let expr = Expression::parse(input, &ctx).unwrap();
+            let mut gradient = Gradient::new(&expr, &["x", "y"]);
+            assert_eq!(gradient.derivatives().collect::<Vec<_>>(), *derivatives);
+            let (value, result) = gradient.eval(&mut vars).unwrap();
+            assert_eq!(Ok(value), expr.eval(&mut vars), "input was {}", input);
+            let close = result
+                .iter()
+                .zip(*expected)
+                .all(|(r, e)| (r - e).abs() < 1e-8);
+            assert!(close, "input was {}, result {:?}", input, result);
+        }
+    }
+
+    #[test]
+    fn test_gradient_unused_and_missing() {
+        let ctx = Ctx::default();
+        let expr = Expression::parse("max(x, y)", &ctx).unwrap();
+        let mut gradient = Gradient::new(&expr, &["x", "z"]);
+        assert_eq!(gradient.vars().collect::<Vec<_>>(), vec!["x", "z"]);
+        let mut vars = HashMap::new();
+        vars.insert("y".to_owned(), 3.0);
+        assert_eq!(
+            gradient.eval(&mut vars),
+            Err(evaluator::Error::VarNotFound("x".to_owned(), None))
+        );
+        vars.insert("x".to_owned(), 2.0);
+        // z is not used, so the derivative is 0 even though z is not defined
+        assert_eq!(gradient.eval(&mut vars), Ok((3.0, vec![0.0, 0.0])));
+    }
+}
@@ -0,0 +1,153 @@
+//! Numerical integration, see [`integrate`](integrate).
+use crate::evaluator;
+use crate::expression::Expression;
+use crate::sample::Sampler;
+use crate::variables::VariableResolver;
+
+/// The nodes and weights of 5-point Gauss-Legendre rule on `[-1, 1]`.
+const GAUSS_LEGENDRE_5: [(f64, f64); 5] = [
+    (0.0, 0.568_888_888_888_888_9),
+    (-0.538_469_310_105_683_1, 0.478_628_670_499_366_5),
+    (0.538_469_310_105_683_1, 0.478_628_670_499_366_5),
+    (-0.906_179_845_938_664, 0.236_926_885_056_189_1),
+    (0.906_179_845_938_664, 0.236_926_885_056_189_1),
+];
+
+/// The quadrature rule used by [`integrate`](integrate).
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Method {
+    /// Composite Simpson's rule.
+    Simpson {
+        /// The number of subintervals, it is rounded up to an even number.
+        intervals: usize,
+    },
+    /// Composite 5-point Gauss-Legendre rule, exact for polynomials up to the degree of 9 on each subinterval.
+    ///
+    /// The expression is not evaluated at the ends of the interval,
+    /// so this also works for some integrable singularities at the ends.
+    Gauss {
+        /// The number of subintervals.
+        intervals: usize,
+    },
+}
+
+impl Default for Method {
+    /// Creates 5-point Gauss-Legendre rule with 16 subintervals.
+    fn default() -> Self {
+        Method::Gauss { intervals: 16 }
+    }
+}
+
+/// Computes the definite integral of the expression over `var` from `a` to `b`.
+///
+/// Other variables are read from `variables`, `var` shadows the variable with the same name.
+/// The result is negative if `b < a`. At least one subinterval is used.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use rusty_yard::expression::Expression;
+/// use rusty_yard::numeric::integrate::{integrate, Method};
+/// use rusty_yard::Ctx;
+///
+/// let ctx = Ctx::default();
+/// let expr = Expression::parse("3 * x ^ 2", &ctx).unwrap();
+/// let method = Method::Simpson { intervals: 4 };
+/// let area = integrate(&expr, "x", 0.0, 2.0, method, &mut HashMap::new()).unwrap();
+/// assert!((area - 8.0).abs() < 1e-12);
+/// ```
+pub fn integrate(
+    expr: &Expression,
+    var: &str,
+    a: f64,
+    b: f64,
+    method: Method,
+    variables: &mut dyn VariableResolver,
+) -> evaluator::Result {
+    let mut sampler = Sampler::new(expr, [var], variables);
+    match method {
+        Method::Simpson { intervals } => {
+            let n = intervals.max(1).div_ceil(2) * 2;
+            let h = (b - a) / n as f64;
+            let mut sum = sampler.eval([a])? + sampler.eval([b])?;
+            for i in 1..n {
+                let weight = if i % 2 == 1 { 4.0 } else { 2.0 };
+                sum += weight * sampler.eval([a + h * i as f64])?;
+            }
+            Ok(sum * h / 3.0)
+        }
+        Method::Gauss { intervals } => {
+            let n = intervals.max(1);
+            let h = (b - a) / n as f64;
+            let mut sum = 0.0;
+            for i in 0..n {
+                let mid = a + h * (i as f64 + 0.5);
+                for (node, weight) in GAUSS_LEGENDRE_5.iter() {
+                    sum += weight * sampler.eval([mid + node * h / 2.0])?;
+                }
+            }
+            Ok(sum * h / 2.0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::Ctx;
+
+    #[test]
+    fn test_integrate() {
+        let methods = &[
+            Method::Simpson { intervals: 64 },
+            Method::Simpson { intervals: 63 },
+            Method::Gauss { intervals: 4 },
+            Method::default(),
+        ];
+        let input_expected = &[
+            ("1", 0.0, 2.0, 2.0),
+            ("x", -1.0, 1.0, 0.0),
+            ("x ^ 3 - 2 * x", 0.0, 3.0, 11.25),
+            ("1 / x", 1.0, 2.0, 2f64.ln()),
+            ("x ^ 2", 3.0, 0.0, -9.0),
+            ("x", 1.0, 1.0, 0.0),
+        ];
+        let ctx = Ctx::default();
+        for method in methods {
+            for (input, a, b, expected) in input_expected {
+                let expr = Expression::parse(input, &ctx).unwrap();
+                let result = integrate(&expr, "x", *a, *b, *method, &mut HashMap::new()).unwrap();
+                assert!(
+                    (result - expected).abs() < 1e-8,
+                    "input was {} with {:?}, result {}",
+                    input,
+                    method,
+                    result
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_integrate_gauss_singularity() {
+        let ctx = Ctx::default();
+        let expr = Expression::parse("1 / x ^ 0.5", &ctx).unwrap();
+        let method = Method::Gauss { intervals: 1000 };
+        let result = integrate(&expr, "x", 0.0, 1.0, method, &mut HashMap::new()).unwrap();
+        assert!((result - 2.0).abs() < 1e-2, "result {}", result);
+    }
+
+    #[test]
+    fn test_integrate_err() {
+        let ctx = Ctx::default();
+        let expr = Expression::parse("x * y", &ctx).unwrap();
+        let result = integrate(&expr, "x", 0.0, 1.0, Method::default(), &mut HashMap::new());
+        assert_eq!(
+            result,
+            Err(evaluator::Error::VarNotFound("y".to_owned(), None))
+        );
+    }
+}
@@ -0,0 +1,28 @@
+//! Numerical methods over the parsed [`expressions`](crate::expression::Expression).
+//!
+//! The expression is parsed once and evaluated at as many points as the method needs,
+//! the variable the method works with shadows the variable with the same name in the resolver.
+//!
+//! # Example
+//!
+//! ```
+//! use std::collections::HashMap;
+//! use rusty_yard::expression::Expression;
+//! use rusty_yard::numeric::solve::{solve, Method, Options};
+//! use rusty_yard::Ctx;
+//!
+//! let ctx = Ctx::default();
+//! let expr = Expression::parse("x ^ 2 - 2", &ctx).unwrap();
+//! let method = Method::Bisection { bracket: 0.0..=2.0 };
+//! let solution = solve(&expr, "x", method, Options::default(), &mut HashMap::new()).unwrap();
+//! assert!((solution.root - 2f64.sqrt()).abs() < 1e-9);
+//! ```
+pub use derivative::derivative_at;
+pub use gradient::Gradient;
+pub use integrate::integrate;
+pub use solve::solve;
+
+pub mod derivative;
+pub mod gradient;
+pub mod integrate;
+pub mod solve;
@@ -0,0 +1,361 @@
+//! Root finding, see [`solve`](solve).
+use std::ops::RangeInclusive;
+
+use thiserror::Error;
+
+use super::derivative::{five_point, Derivative};
+use crate::analysis::differentiate;
+use crate::evaluator;
+use crate::expression::Expression;
+use crate::sample::Sampler;
+use crate::variables::VariableResolver;
+
+/// Represents the error that can occur during [`solve`](solve).
+#[derive(Debug, Error, PartialEq)]
+pub enum Error {
+    /// The evaluation of the expression failed.
+    #[error("{0}")]
+    Eval(#[from] evaluator::Error),
+    /// The values of the expression at the ends of the bracket have the same sign.
+    #[error("Expression has the same sign at {a} and {b}")]
+    NoSignChange {
+        /// The start of the bracket.
+        a: f64,
+        /// The end of the bracket.
+        b: f64,
+    },
+    /// The value of the expression or of its derivative is not finite.
+    #[error("Expression is not finite at {x}")]
+    NotFinite {
+        /// The point.
+        x: f64,
+    },
+    /// The derivative of the expression is zero, Newton's method can't make a step.
+    #[error("Derivative is zero at {x}")]
+    ZeroDerivative {
+        /// The point.
+        x: f64,
+    },
+    /// The method did not converge within [`max_iterations`](Options::max_iterations).
+    #[error("Did not converge after {iterations} iterations, last estimate is {x}")]
+    NotConverged {
+        /// The last estimate of the root.
+        x: f64,
+        /// The number of iterations made.
+        iterations: usize,
+    },
+}
+
+/// The root finding method used by [`solve`](solve).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Method {
+    /// Bisection of the bracket the expression changes its sign in.
+    ///
+    /// Always converges if the expression is continuous in the bracket.
+    Bisection {
+        /// The interval that contains the root.
+        bracket: RangeInclusive<f64>,
+    },
+    /// Newton's method starting at the initial guess.
+    ///
+    /// The derivative is found with [`differentiate`](crate::analysis::differentiate),
+    /// the finite difference is used if the expression can't be differentiated.
+    Newton {
+        /// The initial estimate of the root.
+        initial_guess: f64,
+    },
+}
+
+/// Options of [`solve`](solve).
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Options {
+    /// The maximum number of iterations.
+    pub max_iterations: usize,
+    /// The method stops when the value of the expression or the step is not greater than the tolerance,
+    /// the step is relative to the root if it is greater than 1.
+    pub tolerance: f64,
+}
+
+impl Default for Options {
+    /// Creates the options with 100 iterations and the tolerance of `1e-12`.
+    fn default() -> Self {
+        Self {
+            max_iterations: 100,
+            tolerance: 1e-12,
+        }
+    }
+}
+
+/// The root found by [`solve`](solve) together with the convergence diagnostics.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Solution {
+    /// The point where the expression is zero.
+    pub root: f64,
+    /// The value of the expression at the root.
+    pub residual: f64,
+    /// The number of iterations made.
+    pub iterations: usize,
+    /// How the derivative was computed, `None` for the [`Bisection`](Method::Bisection).
+    pub derivative: Option<Derivative>,
+}
+
+/// Finds the value of `var` the expression equals zero at.
+///
+/// Other variables are read from `variables`, `var` shadows the variable with the same name.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use rusty_yard::expression::Expression;
+/// use rusty_yard::numeric::derivative::Derivative;
+/// use rusty_yard::numeric::solve::{solve, Method, Options};
+/// use rusty_yard::Ctx;
+///
+/// let ctx = Ctx::default();
+/// let expr = Expression::parse("x ^ 3 - a", &ctx).unwrap();
+/// let mut vars = HashMap::new();
+/// vars.insert("a".to_owned(), 27.0);
+/// let method = Method::Newton { initial_guess: 1.0 };
+/// let solution = solve(&expr, "x", method, Options::default(), &mut vars).unwrap();
+/// assert!((solution.root - 3.0).abs() < 1e-12);
+/// assert_eq!(solution.derivative, Some(Derivative::Symbolic));
+/// ```
+pub fn solve(
+    expr: &Expression,
+    var: &str,
+    method: Method,
+    options: Options,
+    variables: &mut dyn VariableResolver,
+) -> Result<Solution, Error> {
+    match method {
+        Method::Bisection { bracket } => bisection(expr, var, bracket, options, variables),
+        Method::Newton { initial_guess } => newton(expr, var, initial_guess, options, variables),
+    }
+}
+
+fn finite(x: f64, value: f64) -> Result<f64, Error> {
+    if value.is_finite() {
+        Ok(value)
+    } else {
+        Err(Error::NotFinite { x })
+    }
+}
+
+fn is_converged(options: Options, step: f64, x: f64) -> bool {
+    step.abs() <= options.tolerance * x.abs().max(1.0)
+}
+
+fn bisection(
+    expr: &Expression,
+    var: &str,
+    bracket: RangeInclusive<f64>,
+    options: Options,
+    variables: &mut dyn VariableResolver,
+) -> Result<Solution, Error> {
+    let mut sampler = Sampler::new(expr, [var], variables);
+    let (mut a, mut b) = bracket.into_inner();
+    let mut fa = finite(a, sampler.eval([a])?)?;
+    let fb = finite(b, sampler.eval([b])?)?;
+    let solution = |root, residual, iterations| Solution {
+        root,
+        residual,
+        iterations,
+        derivative: None,
+    };
+    if fa == 0.0 {
+        return Ok(solution(a, fa, 0));
+    }
+    if fb == 0.0 {
+        return Ok(solution(b, fb, 0));
+    }
+    if fa.signum() == fb.signum() {
+        return Err(Error::NoSignChange { a, b });
+    }
+    for iteration in 1..=options.max_iterations {
+        let mid = a + (b - a) / 2.0;
+        let f_mid = sampler.eval([mid])?;
+        if f_mid.abs() <= options.tolerance || is_converged(options, (b - a) / 2.0, mid) {
+            return Ok(solution(mid, f_mid, iteration));
+        }
+        if f_mid.signum() == fa.signum() {
+            a = mid;
+            fa = f_mid;
+        } else {
+            b = mid;
+        }
+    }
+    Err(Error::NotConverged {
+        x: a + (b - a) / 2.0,
+        iterations: options.max_iterations,
+    })
+}
+
+fn newton(
+    expr: &Expression,
+    var: &str,
+    initial_guess: f64,
+    options: Options,
+    variables: &mut dyn VariableResolver,
+) -> Result<Solution, Error> {
+    let derivative_expr = differentiate(expr, var).ok();
+    let derivative = match derivative_expr {
+        Some(_) => Derivative::Symbolic,
+        None => Derivative::Numeric,
+    };
+    let mut x = initial_guess;
+    for iteration in 1..=options.max_iterations {
+        let f = finite(x, Sampler::new(expr, [var], variables).eval([x])?)?;
+        if f.abs() <= options.tolerance {
+            return Ok(Solution {
+                root: x,
+                residual: f,
+                iterations: iteration - 1,
+                derivative: Some(derivative),
+            });
+        }
+        let df = match &derivative_expr {
+            Some(derivative_expr) => Sampler::new(derivative_expr, [var], variables).eval([x])?,
+            None => {
+                let mut sampler = Sampler::new(expr, [var], variables);
+                five_point(|x| sampler.eval([x]), x)?
+            }
+        };
+        let df = finite(x, df)?;
+        if df == 0.0 {
+            return Err(Error::ZeroDerivative { x });
+        }
+        let step = f / df;
+        x -= step;
+        if is_converged(options, step, x) {
+            let residual = finite(x, Sampler::new(expr, [var], variables).eval([x])?)?;
+            return Ok(Solution {
+                root: x,
+                residual,
+                iterations: iteration,
+                derivative: Some(derivative),
+            });
+        }
+    }
+    Err(Error::NotConverged {
+        x,
+        iterations: options.max_iterations,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::Ctx;
+
+    fn solve_str(input: &str, method: Method) -> Result<Solution, Error> {
+        let ctx = Ctx::default();
+        let expr = Expression::parse(input, &ctx).unwrap();
+        solve(&expr, "x", method, Options::default(), &mut HashMap::new())
+    }
+
+    #[test]
+    fn test_bisection() {
+        let input_expected = &[
+            ("x ^ 2 - 2", 0.0..=2.0, 2f64.sqrt()),
+            ("x - 1", 1.0..=5.0, 1.0),
+            ("x - 5", 1.0..=5.0, 5.0),
+            ("3 - x", 0.0..=10.0, 3.0),
+            ("x ^ 3 + x - 1", -10.0..=10.0, 0.682_327_803_828_019_3),
+        ];
+        for (input, bracket, expected) in input_expected {
+            let method = Method::Bisection {
+                bracket: bracket.clone(),
+            };
+            let solution = solve_str(input, method).unwrap();
+            assert!(
+                (solution.root - expected).abs() < 1e-10,
+                "input was {}, solution {:?}",
+                input,
+                solution
+            );
+            assert_eq!(solution.derivative, None);
+        }
+    }
+
+    #[test]
+    fn test_newton() {
+        let input_expected = &[
+            ("x ^ 2 - 2", 1.0, 2f64.sqrt(), Derivative::Symbolic),
+            (
+                "x ^ 3 + x - 1",
+                0.0,
+                0.682_327_803_828_019_3,
+                Derivative::Symbolic,
+            ),
+            ("max(x, 2 * x) - 4", 1.0, 2.0, Derivative::Numeric),
+            ("x - 1", 1.0, 1.0, Derivative::Symbolic),
+            (
+                "cos(x) - x",
+                1.0,
+                0.739_085_133_215_160_6,
+                Derivative::Symbolic,
+            ),
+            ("exp(x) - 2", 1.0, 2f64.ln(), Derivative::Symbolic),
+        ];
+        for (input, initial_guess, expected, derivative) in input_expected {
+            let method = Method::Newton {
+                initial_guess: *initial_guess,
+            };
+            let solution = solve_str(input, method).unwrap();
+            assert!(
+                (solution.root - expected).abs() < 1e-10,
+                "input was {}, solution {:?}",
+                input,
+                solution
+            );
+            assert_eq!(
+                solution.derivative,
+                Some(*derivative),
+                "input was {}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_solve_err() {
+        let bisection = |a, b| Method::Bisection { bracket: a..=b };
+        let newton = |initial_guess| Method::Newton { initial_guess };
+        let input_expected = &[
+            (
+                "x ^ 2 + 1",
+                bisection(-1.0, 1.0),
+                Error::NoSignChange { a: -1.0, b: 1.0 },
+            ),
+            ("1 / x", bisection(0.0, 1.0), Error::NotFinite { x: 0.0 }),
+            ("x ^ 2 + 1", newton(0.0), Error::ZeroDerivative { x: 0.0 }),
+            (
+                "x + y",
+                newton(0.0),
+                Error::Eval(evaluator::Error::VarNotFound("y".to_owned(), None)),
+            ),
+        ];
+        for (input, method, expected) in input_expected {
+            let result = solve_str(input, method.clone());
+            assert_eq!(result.err().as_ref(), Some(expected), "input was {}", input);
+        }
+        let ctx = Ctx::default();
+        let expr = Expression::parse("x ^ 2 - 2", &ctx).unwrap();
+        let options = Options {
+            max_iterations: 3,
+            ..Options::default()
+        };
+        let method = bisection(0.0, 2.0);
+        let result = solve(&expr, "x", method, options, &mut HashMap::new());
+        assert_eq!(
+            result,
+            Err(Error::NotConverged {
+                x: 1.375,
+                iterations: 3
+            })
+        );
+    }
+}
@@ -1,12 +1,19 @@
 //! Provides definitions of types used in defining what a binary operator is.
 //!
 //! It also provides default operators that one might expect.
+//!
+//! The comparison and the logical operators use 0 for `false` and 1 for `true`,
+//! the logical operators treat every number except 0 and `NaN` as `true`.
+//! From the lowest to the highest precedence the default operators are:
+//! `||`, `&&`, `==` `!=`, `<` `<=` `>` `>=`, `+` `-`, `*` `/`, `^`.
 use std::fmt::{self, Debug, Formatter};
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
 
 use lazy_static::lazy_static;
 
 /// Represent the binary operator.
-#[derive(Clone, Eq, PartialEq, Hash)]
+#[derive(Clone)]
 pub struct BiOp {
     /// operator's identifier.
     pub token: String,
@@ -18,7 +25,68 @@ pub struct BiOp {
     pub associativity: Associativity,
 
     /// the function that is invoked by [`evaluator`](crate::evaluator) when evaluating this operator.
-    pub func: fn(f64, f64) -> f64,
+    pub func: Arc<BiOpFn>,
+
+    /// the built-in arithmetic this operator performs, if any.
+    ///
+    /// When this is set, [`evaluator`](crate::evaluator) computes the operation inline instead of calling `func`.
+    /// Set it only if `func` does exactly the same thing, use `None` for your own operators.
+    pub builtin: Option<Builtin>,
+}
+
+/// The implementation of [`BiOp`](BiOp), receives the left and the right operands.
+///
+/// The closures can capture the state, e.g. the configuration or the lookup tables.
+pub type BiOpFn = dyn Fn(f64, f64) -> f64 + Send + Sync;
+
+/// The built-in arithmetic operation, see [`BiOp::builtin`](BiOp::builtin).
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Builtin {
+    /// `a + b`
+    Add,
+    /// `a - b`
+    Subtract,
+    /// `a * b`
+    Multiply,
+    /// `a / b`
+    Divide,
+    /// `a.powf(b)`
+    Power,
+}
+
+impl BiOp {
+    /// Creates the operator without the [`builtin`](BiOp::builtin) arithmetic.
+    ///
+    /// `func` can be a plain `fn` or a closure.
+    pub fn new(
+        token: &str,
+        precedence: u32,
+        associativity: Associativity,
+        func: impl Fn(f64, f64) -> f64 + Send + Sync + 'static,
+    ) -> Self {
+        BiOp {
+            token: token.to_owned(),
+            precedence,
+            associativity,
+            func: Arc::new(func),
+            builtin: None,
+        }
+    }
+
+    /// Apply this operator to the operands.
+    ///
+    /// This uses [`builtin`](BiOp::builtin) arithmetic if it is set and calls [`func`](BiOp::func) otherwise.
+    #[inline]
+    pub fn apply(&self, left: f64, right: f64) -> f64 {
+        match self.builtin {
+            Some(Builtin::Add) => left + right,
+            Some(Builtin::Subtract) => left - right,
+            Some(Builtin::Multiply) => left * right,
+            Some(Builtin::Divide) => left / right,
+            Some(Builtin::Power) => left.powf(right),
+            None => (self.func)(left, right),
+        }
+    }
 }
 
 /// The associativity of the operator.
@@ -32,6 +100,31 @@ pub enum Associativity {
     RIGHT,
 }
 
+// Because func is magic we need to implement all markers our self
+impl PartialEq for BiOp {
+    #[cfg_attr(tarpaulin, skip)]
+    fn eq(&self, other: &Self) -> bool {
+        self.token.eq(&other.token)
+            && self.precedence.eq(&other.precedence)
+            && self.associativity.eq(&other.associativity)
+            && Arc::ptr_eq(&self.func, &other.func)
+            && self.builtin.eq(&other.builtin)
+    }
+}
+
+impl Hash for BiOp {
+    #[cfg_attr(tarpaulin, skip)]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.token.hash(state);
+        self.precedence.hash(state);
+        self.associativity.hash(state);
+        Arc::as_ptr(&self.func).cast::<()>().hash(state);
+        self.builtin.hash(state)
+    }
+}
+
+impl Eq for BiOp {}
+
 impl Debug for BiOp {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         f.debug_struct("BiOp")
@@ -53,9 +146,10 @@ lazy_static! {
     /// ```
     pub static ref PLUS: BiOp = BiOp {
         token: "+".to_owned(),
-        precedence: 0,
+        precedence: 4,
         associativity: Associativity::LEFT,
-        func: |e1, e2| e1 + e2,
+        func: Arc::new(|e1, e2| e1 + e2),
+        builtin: Some(Builtin::Add),
     };
 
     /// `a - b` operator.
@@ -67,9 +161,10 @@ lazy_static! {
     /// ```
     pub static ref MINUS: BiOp = BiOp {
         token: "-".to_owned(),
-        precedence: 0,
+        precedence: 4,
         associativity: Associativity::LEFT,
-        func: |e1, e2| e1 - e2,
+        func: Arc::new(|e1, e2| e1 - e2),
+        builtin: Some(Builtin::Subtract),
     };
 
     /// `a * b` operator.
@@ -81,9 +176,10 @@ lazy_static! {
     /// ```
     pub static ref MULTIPLY: BiOp = BiOp {
         token: "*".to_owned(),
-        precedence: 1,
+        precedence: 5,
         associativity: Associativity::LEFT,
-        func: |e1, e2| e1 * e2,
+        func: Arc::new(|e1, e2| e1 * e2),
+        builtin: Some(Builtin::Multiply),
     };
 
     /// `a / b` operator.
@@ -95,9 +191,44 @@ lazy_static! {
     /// ```
     pub static ref DIVIDE: BiOp = BiOp {
         token: "/".to_owned(),
-        precedence: 1,
+        precedence: 5,
         associativity: Associativity::LEFT,
-        func: |e1, e2| e1 / e2,
+        func: Arc::new(|e1, e2| e1 / e2),
+        builtin: Some(Builtin::Divide),
+    };
+
+    /// `a % b ("remainder")` operator, the remainder has the sign of `a` like `fmod` in C.
+    ///
+    /// The token is also the postfix [percent](crate::operators::unary::PERCENT) operator,
+    /// `%` is binary when it is followed by the operand, so `7 % 3` is 1 and `7% * 3` is 0.21.
+    /// `%` followed by `+` or `-` is the percent, so the negative divisor has to be in parentheses, `7 % (-3)`.
+    ///
+    /// # Implementation
+    ///
+    /// ```text
+    /// a % b
+    /// ```
+    pub static ref REMAINDER: BiOp = BiOp {
+        token: "%".to_owned(),
+        precedence: 5,
+        associativity: Associativity::LEFT,
+        func: Arc::new(|e1, e2| e1 % e2),
+        builtin: None,
+    };
+
+    /// `a // b ("floor division")` operator, the quotient rounded towards negative infinity.
+    ///
+    /// # Implementation
+    ///
+    /// ```text
+    /// (a / b).floor()
+    /// ```
+    pub static ref FLOOR_DIVIDE: BiOp = BiOp {
+        token: "//".to_owned(),
+        precedence: 5,
+        associativity: Associativity::LEFT,
+        func: Arc::new(|e1, e2| (e1 / e2).floor()),
+        builtin: None,
     };
 
     /// `a ^ b ("power")` operator.
@@ -109,9 +240,130 @@ lazy_static! {
     /// ```
     pub static ref POWER: BiOp = BiOp {
         token: "^".to_owned(),
-        precedence: 2,
+        precedence: 6,
         associativity: Associativity::RIGHT,
-        func: |e1, e2| e1.powf(e2),
+        func: Arc::new(|e1, e2| e1.powf(e2)),
+        builtin: Some(Builtin::Power),
+    };
+
+    /// `a == b` operator.
+    ///
+    /// # Implementation
+    ///
+    /// ```text
+    /// from_bool(a == b)
+    /// ```
+    pub static ref EQUAL: BiOp = BiOp {
+        token: "==".to_owned(),
+        precedence: 2,
+        associativity: Associativity::LEFT,
+        func: Arc::new(|e1, e2| from_bool(e1 == e2)),
+        builtin: None,
+    };
+
+    /// `a != b` operator.
+    ///
+    /// # Implementation
+    ///
+    /// ```text
+    /// from_bool(a != b)
+    /// ```
+    pub static ref NOT_EQUAL: BiOp = BiOp {
+        token: "!=".to_owned(),
+        precedence: 2,
+        associativity: Associativity::LEFT,
+        func: Arc::new(|e1, e2| from_bool(e1 != e2)),
+        builtin: None,
+    };
+
+    /// `a < b` operator.
+    ///
+    /// # Implementation
+    ///
+    /// ```text
+    /// from_bool(a < b)
+    /// ```
+    pub static ref LESS: BiOp = BiOp {
+        token: "<".to_owned(),
+        precedence: 3,
+        associativity: Associativity::LEFT,
+        func: Arc::new(|e1, e2| from_bool(e1 < e2)),
+        builtin: None,
+    };
+
+    /// `a <= b` operator.
+    ///
+    /// # Implementation
+    ///
+    /// ```text
+    /// from_bool(a <= b)
+    /// ```
+    pub static ref LESS_OR_EQUAL: BiOp = BiOp {
+        token: "<=".to_owned(),
+        precedence: 3,
+        associativity: Associativity::LEFT,
+        func: Arc::new(|e1, e2| from_bool(e1 <= e2)),
+        builtin: None,
+    };
+
+    /// `a > b` operator.
+    ///
+    /// # Implementation
+    ///
+    /// ```text
+    /// from_bool(a > b)
+    /// ```
+    pub static ref GREATER: BiOp = BiOp {
+        token: ">".to_owned(),
+        precedence: 3,
+        associativity: Associativity::LEFT,
+        func: Arc::new(|e1, e2| from_bool(e1 > e2)),
+        builtin: None,
+    };
+
+    /// `a >= b` operator.
+    ///
+    /// # Implementation
+    ///
+    /// ```text
+    /// from_bool(a >= b)
+    /// ```
+    pub static ref GREATER_OR_EQUAL: BiOp = BiOp {
+        token: ">=".to_owned(),
+        precedence: 3,
+        associativity: Associativity::LEFT,
+        func: Arc::new(|e1, e2| from_bool(e1 >= e2)),
+        builtin: None,
+    };
+
+    /// `a && b ("logical and")` operator.
+    ///
+    /// # Implementation
+    ///
+    /// ```text
+    /// from_bool(is_true(a) && is_true(b))
+    /// ```
+    pub static ref AND: BiOp = BiOp {
+        token: "&&".to_owned(),
+        precedence: 1,
+        associativity: Associativity::LEFT,
+        func: Arc::new(|e1, e2| from_bool(is_true(e1) && is_true(e2))),
+        builtin: None,
+    };
+
+    /// `a || b ("logical or")` operator.
+    ///
+    /// # Implementation
+    ///
+    /// ```text
+    /// from_bool(is_true(a) || is_true(b))
+    /// ```
+    pub static ref OR: BiOp = BiOp {
+        token: "||".to_owned(),
+        precedence: 0,
+        associativity: Associativity::LEFT,
+        func: Arc::new(|e1, e2| from_bool(is_true(e1) || is_true(e2))),
+        builtin: None,
     };
 }
 
@@ -124,10 +376,34 @@ pub fn default_operators() -> Vec<BiOp> {
         MINUS.clone(),
         MULTIPLY.clone(),
         DIVIDE.clone(),
+        REMAINDER.clone(),
+        FLOOR_DIVIDE.clone(),
         POWER.clone(),
+        EQUAL.clone(),
+        NOT_EQUAL.clone(),
+        LESS.clone(),
+        LESS_OR_EQUAL.clone(),
+        GREATER.clone(),
+        GREATER_OR_EQUAL.clone(),
+        AND.clone(),
+        OR.clone(),
     ]
 }
 
+/// Converts `bool` to the number, `true` is 1 and `false` is 0.
+pub fn from_bool(value: bool) -> f64 {
+    if value {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+/// Converts the number to `bool` the way the logical operators do: 0 and `NaN` are `false`.
+pub fn is_true(value: f64) -> bool {
+    value != 0.0 && !value.is_nan()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -138,7 +414,8 @@ mod tests {
             token: "#".to_owned(),
             precedence: 0,
             associativity: Associativity::LEFT,
-            func: |_, _| 0.0,
+            func: Arc::new(|_, _| 0.0),
+            builtin: None,
         };
         let dbg = format!("{:?}", op);
         assert!(dbg.contains("BiOp"));
@@ -148,4 +425,65 @@ mod tests {
         assert!(dbg.contains("associativity"));
         assert!(dbg.contains(&format!("{:?}", Associativity::LEFT)));
     }
+
+    #[test]
+    fn test_builtin_matches_func() {
+        let operands = &[(1.5, 2.0), (-3.0, 0.5), (0.0, 0.0), (10.0, -2.0)];
+        let arithmetic = [&*PLUS, &*MINUS, &*MULTIPLY, &*DIVIDE, &*POWER];
+        for op in arithmetic {
+            assert!(op.builtin.is_some(), "op {:?}", op);
+            for &(left, right) in operands {
+                let expected = (op.func)(left, right);
+                let actual = op.apply(left, right);
+                assert!(
+                    expected == actual || (expected.is_nan() && actual.is_nan()),
+                    "op {:?}, operands {} {}",
+                    op,
+                    left,
+                    right
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_closure() {
+        let scale = 10.0;
+        let op = BiOp::new("#", 0, Associativity::LEFT, move |a, b| (a + b) * scale);
+        assert_eq!(op.apply(1.0, 2.0), 30.0);
+        assert_eq!(op, op.clone());
+        let other = BiOp::new("#", 0, Associativity::LEFT, move |a, b| (a + b) * scale);
+        assert_ne!(op, other);
+    }
+
+    #[test]
+    fn test_comparison_and_logical() {
+        let nan = f64::NAN;
+        let input_expected = &[
+            (&*EQUAL, 1.0, 1.0, 1.0),
+            (&*EQUAL, 1.0, 2.0, 0.0),
+            (&*EQUAL, nan, nan, 0.0),
+            (&*NOT_EQUAL, 1.0, 2.0, 1.0),
+            (&*NOT_EQUAL, nan, nan, 1.0),
+            (&*LESS, 1.0, 2.0, 1.0),
+            (&*LESS, 2.0, 2.0, 0.0),
+            (&*LESS_OR_EQUAL, 2.0, 2.0, 1.0),
+            (&*GREATER, 3.0, 2.0, 1.0),
+            (&*GREATER, nan, 2.0, 0.0),
+            (&*GREATER_OR_EQUAL, 1.0, 2.0, 0.0),
+            (&*AND, 2.0, -1.0, 1.0),
+            (&*AND, 1.0, 0.0, 0.0),
+            (&*AND, 1.0, nan, 0.0),
+            (&*OR, 0.0, 0.5, 1.0),
+            (&*OR, 0.0, nan, 0.0),
+        ];
+        for (op, left, right, expected) in input_expected {
+            let result = op.apply(*left, *right);
+            assert_eq!(
+                result, *expected,
+                "input was {} {} {}",
+                left, op.token, right
+            );
+        }
+    }
 }
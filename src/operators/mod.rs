@@ -13,18 +13,15 @@
 //! let mut ctx = Ctx::empty();
 //! let mut vars = HashMap::new();
 //! // add new u_op to context
-//! ctx.u_ops.push(UOp {
-//!     token: "$$$".to_owned(),
-//!     func: |a| 1000.0 * a,
-//! });
+//! ctx.u_ops.push(UOp::new("$$$", |a| 1000.0 * a));
 //! // add new bi_op to context
-//! ctx.bi_ops.push(BiOp {
-//!     token: "crazy".to_owned(),
-//!     precedence: 0,
+//! ctx.bi_ops.push(BiOp::new(
+//!     "crazy",
+//!     0,
 //!     // use right associativity because why not?
-//!     associativity: Associativity::RIGHT,
-//!     func: |a, b| (a.powi(2) + b.powi(2)).sqrt()
-//! });
+//!     Associativity::RIGHT,
+//!     |a, b| (a.powi(2) + b.powi(2)).sqrt(),
+//! ));
 //! assert_eq!(eval_str_with_vars_and_ctx("$$$(12 crazy 3 crazy 4)", &mut vars, &ctx), Ok(13_000.0))
 //! //                                     ^      ^       ^ 1. 'crazy' is right associative (3 crazy 4) = 5 is first;
 //! //                                     |      | 2. next this will be evaluated 12 crazy 5;
@@ -36,3 +33,12 @@ pub use unary::UOp;
 
 pub mod binary;
 pub mod unary;
+
+/// Get the aliases of the [default operators](binary::default_operators) for the [`Ctx::aliases`](crate::Ctx::aliases):
+/// `×` and `·` for `*`, `÷` for `/` and `**` for `^`.
+pub fn default_aliases() -> Vec<(String, String)> {
+    [("×", "*"), ("·", "*"), ("÷", "/"), ("**", "^")]
+        .iter()
+        .map(|(alias, token)| ((*alias).to_owned(), (*token).to_owned()))
+        .collect()
+}
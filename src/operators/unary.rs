@@ -1,23 +1,109 @@
 //! Provides definition on unary operator type.
 //!
 //! It also provides default operators that one might expect.
+//!
+//! The unary operators are prefix (`-a`) unless they are [`postfix`](UOp::postfix) (`a%`).
+//! The postfix operators are applied to the operand right before them, before any other operator.
 use std::fmt::{self, Debug, Formatter};
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
 
 use lazy_static::lazy_static;
 
+use super::binary::{from_bool, is_true};
+
 /// Represents the unary operator.
-#[derive(Clone, Hash, Eq, PartialEq)]
+#[derive(Clone)]
 pub struct UOp {
     /// operator's identifier.
     pub token: String,
 
+    /// operator's precedence relative to the [binary operators](crate::operators::binary).
+    ///
+    /// With `None` the operator is applied before any binary operator, so `-a ^ b` is `(-a) ^ b`.
+    /// With `Some(precedence)` it is applied before the binary operators with the same or lower precedence only,
+    /// e.g. `-` with the precedence of `*` makes `-a ^ b` to be `-(a ^ b)`, as in the math notation.
+    pub precedence: Option<u32>,
+
+    /// the operator is written after its operand, e.g. `10%`.
+    ///
+    /// The postfix operators are applied before any other operator, so they ignore [`precedence`](UOp::precedence).
+    pub postfix: bool,
+
     /// the function that is invoked by [`evaluator`](crate::evaluator) when evaluating this operator.
-    pub func: fn(f64) -> f64,
+    pub func: Arc<UOpFn>,
+}
+
+/// The implementation of [`UOp`](UOp), receives the operand.
+///
+/// The closures can capture the state, e.g. the configuration or the lookup tables.
+pub type UOpFn = dyn Fn(f64) -> f64 + Send + Sync;
+
+impl UOp {
+    /// Creates the operator that is applied before any binary operator.
+    ///
+    /// `func` can be a plain `fn` or a closure.
+    pub fn new(token: &str, func: impl Fn(f64) -> f64 + Send + Sync + 'static) -> Self {
+        UOp {
+            token: token.to_owned(),
+            precedence: None,
+            postfix: false,
+            func: Arc::new(func),
+        }
+    }
+
+    /// Creates the [`postfix`](UOp::postfix) operator.
+    ///
+    /// `func` can be a plain `fn` or a closure.
+    pub fn postfix(token: &str, func: impl Fn(f64) -> f64 + Send + Sync + 'static) -> Self {
+        UOp {
+            postfix: true,
+            ..UOp::new(token, func)
+        }
+    }
+}
+
+/// How [`PERCENT`](PERCENT) works as the right operand of `+` and `-`, see [`Ctx::percent_mode`](crate::Ctx::percent_mode).
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Default)]
+pub enum PercentMode {
+    /// `%` divides by 100 everywhere, so `200 + 10%` is `200.1`.
+    #[default]
+    Plain,
+    /// `%` after `+` and `-` is relative to the left operand like on a calculator,
+    /// so `200 + 10%` is `200 * (1 + 10%)`, i.e. `220`, and `200 - 10%` is `180`.
+    Calculator,
+}
+
+// Because func is magic we need to implement all markers our self
+impl PartialEq for UOp {
+    #[cfg_attr(tarpaulin, skip)]
+    fn eq(&self, other: &Self) -> bool {
+        self.token.eq(&other.token)
+            && self.precedence.eq(&other.precedence)
+            && self.postfix.eq(&other.postfix)
+            && Arc::ptr_eq(&self.func, &other.func)
+    }
 }
 
+impl Hash for UOp {
+    #[cfg_attr(tarpaulin, skip)]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.token.hash(state);
+        self.precedence.hash(state);
+        self.postfix.hash(state);
+        Arc::as_ptr(&self.func).cast::<()>().hash(state)
+    }
+}
+
+impl Eq for UOp {}
+
 impl Debug for UOp {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        f.debug_struct("UOp").field("token", &self.token).finish()
+        f.debug_struct("UOp")
+            .field("token", &self.token)
+            .field("precedence", &self.precedence)
+            .field("postfix", &self.postfix)
+            .finish()
     }
 }
 lazy_static! {
@@ -31,7 +117,9 @@ lazy_static! {
     /// ```
     pub static ref NEGATE: UOp = UOp {
         token: "-".to_owned(),
-        func: |v| -v,
+        precedence: None,
+        postfix: false,
+        func: Arc::new(|v| -v),
     };
 
     /// `+a ("unary plus")` operator.
@@ -43,7 +131,37 @@ lazy_static! {
     /// ```
     pub static ref PLUS: UOp = UOp {
         token: "+".to_owned(),
-        func: |v| v,
+        precedence: None,
+        postfix: false,
+        func: Arc::new(|v| v),
+    };
+
+    /// `!a ("logical not")` operator.
+    ///
+    /// # Implementation
+    ///
+    /// ```text
+    /// return from_bool(!is_true(a))
+    /// ```
+    pub static ref NOT: UOp = UOp {
+        token: "!".to_owned(),
+        precedence: None,
+        postfix: false,
+        func: Arc::new(|v| from_bool(!is_true(v))),
+    };
+
+    /// `a% ("percent")` postfix operator.
+    ///
+    /// # Implementation
+    ///
+    /// ```text
+    /// return a / 100
+    /// ```
+    pub static ref PERCENT: UOp = UOp {
+        token: "%".to_owned(),
+        precedence: None,
+        postfix: true,
+        func: Arc::new(|v| v / 100.0),
     };
 }
 
@@ -51,7 +169,7 @@ lazy_static! {
 ///
 /// This includes all operators from [`this`](self) module.
 pub fn default_operators() -> Vec<UOp> {
-    vec![PLUS.clone(), NEGATE.clone()]
+    vec![PLUS.clone(), NEGATE.clone(), NOT.clone(), PERCENT.clone()]
 }
 
 #[cfg(test)]
@@ -61,7 +179,9 @@ mod tests {
     fn test_debug() {
         let op = UOp {
             token: "#".to_owned(),
-            func: |_| 0.0,
+            precedence: None,
+            postfix: false,
+            func: Arc::new(|_| 0.0),
         };
         let dbg = format!("{:?}", op);
         assert!(dbg.contains("UOp"));
@@ -0,0 +1,135 @@
+use std::fmt::{self, Display, Formatter};
+
+use super::ParserToken;
+
+/// Represents the syntax tree of the expression.
+///
+/// The tree is reconstructed from the RPN token stream using [`from_rpn`](Ast::from_rpn)
+/// and borrows the tokens of the stream.
+///
+/// [`Display`](std::fmt::Display) implementation writes the tree as an s-expression, e.g. `(+ 1 (* 2 3))`,
+/// the alternate form (`{:#}`) writes one node per line indented by its depth.
+///
+/// # Example
+///
+/// ```
+/// use rusty_yard::parser::{parse_str, Ast};
+/// use rusty_yard::Ctx;
+///
+/// let ctx = Ctx::default();
+/// let tokens = parse_str("1 + 2 * max(a, 3)", &ctx).unwrap();
+/// let ast = Ast::from_rpn(&tokens).unwrap();
+/// assert_eq!(ast.to_string(), "(+ 1 (* 2 (max a 3)))");
+/// ```
+#[derive(Debug, PartialEq)]
+pub struct Ast<'t, 'a, 'ctx> {
+    /// The token of this node.
+    pub token: &'t ParserToken<'a, 'ctx>,
+    /// The operands of the token in the order they were written.
+    pub operands: Vec<Ast<'t, 'a, 'ctx>>,
+}
+
+impl<'t, 'a, 'ctx> Ast<'t, 'a, 'ctx> {
+    /// Builds the tree from the `tokens` in reverse polish notation.
+    ///
+    /// Returns [`None`](std::option::Option::None) if the tokens do not form exactly one expression.
+    pub fn from_rpn(tokens: &'t [ParserToken<'a, 'ctx>]) -> Option<Self> {
+        let mut stack: Vec<Self> = Vec::new();
+        for token in tokens {
+            let n_operands = match token {
+                ParserToken::Num(_) | ParserToken::Id(_) | ParserToken::Str(_) => 0,
+                ParserToken::UOp(_) => 1,
+                ParserToken::BiOp(_) => 2,
+                ParserToken::Func(_, n_args) => *n_args,
+                ParserToken::Macro(m) => m.operands(),
+            };
+            let operands = stack.split_off(stack.len().checked_sub(n_operands)?);
+            stack.push(Ast { token, operands });
+        }
+        match stack.len() {
+            1 => stack.pop(),
+            _ => None,
+        }
+    }
+
+    fn fmt_indented(&self, f: &mut Formatter<'_>, depth: usize) -> fmt::Result {
+        writeln!(f, "{:indent$}{}", "", self.token, indent = depth * 2)?;
+        for operand in &self.operands {
+            operand.fmt_indented(f, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Ast<'_, '_, '_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            return self.fmt_indented(f, 0);
+        }
+        if self.operands.is_empty() {
+            return write!(f, "{}", self.token);
+        }
+        write!(f, "(")?;
+        match self.token {
+            // arguments are already the part of the tree
+            ParserToken::Func(func, _) => write!(f, "{}", func.token)?,
+            token => write!(f, "{}", token)?,
+        }
+        for operand in &self.operands {
+            write!(f, " {}", operand)?;
+        }
+        write!(f, ")")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_str;
+    use crate::Ctx;
+
+    #[test]
+    fn test_from_rpn() {
+        let input_expected = &[
+            ("1", "1"),
+            ("-a", "(- a)"),
+            ("1 - 2 - 3", "(- (- 1 2) 3)"),
+            ("2 ^ 3 ^ 2", "(^ 2 (^ 3 2))"),
+            ("(1 + 2) * 3", "(* (+ 1 2) 3)"),
+            ("sum(1, 2 + 3, a) / 2", "(/ (sum 1 (+ 2 3) a) 2)"),
+            ("sum()", "sum/0"),
+            (
+                "a = b = 1 + 2",
+                "(AssignParsed { id: \"a\" } (AssignParsed { id: \"b\" } (+ 1 2)))",
+            ),
+        ];
+        let ctx = Ctx::default_with_macros();
+        for (input, expected) in input_expected {
+            let tokens = parse_str(input, &ctx).unwrap();
+            let ast = Ast::from_rpn(&tokens).unwrap();
+            assert_eq!(ast.to_string(), *expected, "input was {}", input);
+        }
+    }
+
+    #[test]
+    fn test_from_rpn_ill_formed() {
+        let ctx = Ctx::default();
+        let plus = ctx.bi_op("+").unwrap();
+        let input = &[
+            vec![],
+            vec![ParserToken::Num(1.0), ParserToken::Num(2.0)],
+            vec![ParserToken::Num(1.0), ParserToken::BiOp(plus)],
+        ];
+        for tokens in input {
+            assert_eq!(Ast::from_rpn(tokens), None, "tokens were {:?}", tokens);
+        }
+    }
+
+    #[test]
+    fn test_display_alternate() {
+        let ctx = Ctx::default();
+        let tokens = parse_str("1 + max(2, a)", &ctx).unwrap();
+        let ast = Ast::from_rpn(&tokens).unwrap();
+        assert_eq!(format!("{:#}", ast), "+\n  1\n  max/2\n    2\n    a\n");
+    }
+}
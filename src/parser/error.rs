@@ -1,14 +1,21 @@
+use std::io::{self, Write};
+
 use thiserror::Error;
 
+use crate::diagnostics::{locate, Location};
+use crate::functions::ArityRange;
+use crate::suggest::did_you_mean;
+use crate::tokenizer::{BadTokenReason, Position, Span};
+
 /// Represents the error that a parser can output
-#[derive(Error, Debug, PartialEq)]
+#[derive(Error, Debug, PartialEq, Clone)]
 pub enum Error {
     /// left paren has not been found after identifier that represents a function
     #[error("Expected left paren after function id")]
     NoLeftParenAfterFnId,
-    /// Bad token found in input
-    #[error("Bad token {0:?}")]
-    BadToken(String),
+    /// Bad token found in input, with the reason it could not be tokenized
+    #[error("Bad token {0:?}: {1}")]
+    BadToken(String, BadTokenReason),
 
     /// Operator at the end of the expression
     #[error("Operator at the end of the token stream")]
@@ -27,8 +34,8 @@ pub enum Error {
     ArityMismatch {
         /// Identifier of the mismatched function
         id: String,
-        /// Expected number of parameters to the function
-        expected: usize,
+        /// Expected numbers of parameters to the function, see [`Func::arity_range`](crate::functions::Func::arity_range)
+        expected: ArityRange,
         /// Actual number of parameters passed to the function
         actual: usize,
     },
@@ -58,4 +65,135 @@ pub enum Error {
     /// Parser found a comma outside function
     #[error("Comma can only be used in functions, arity stack is empty")]
     CommaOutsideFn,
+
+    /// The named argument is not the parameter of the function it is passed to,
+    /// see [`named arguments`](crate::tokenizer#named-arguments)
+    #[error("Unknown argument {0}")]
+    UnknownArgument(String),
+
+    /// The parameter is passed twice, by the position and by the name or by the name twice
+    #[error("Argument {0} is passed more than once")]
+    DuplicateArgument(String),
+
+    /// The required parameter is not passed to the call with the named arguments
+    #[error("Missing argument {0}")]
+    MissingArgument(String),
+
+    /// The positional argument follows the named one
+    #[error("Positional argument follows the named argument")]
+    PositionalAfterNamed,
+
+    /// The expression has more tokens than [`max_tokens`](crate::limits::Limits::max_tokens) allows
+    #[error("Expression is longer than the limit of {0} tokens")]
+    TooManyTokens(usize),
+
+    /// The expression is nested deeper than [`max_depth`](crate::limits::Limits::max_depth) allows
+    #[error("Expression is nested deeper than the limit of {0}")]
+    TooDeep(usize),
+
+    /// The identifier followed by the left paren is not the function of the context.
+    ///
+    /// The second field is the closest function, if the identifier looks like the misspelling of it.
+    #[error("Unknown function {0}{}", did_you_mean(.1))]
+    UnknownFunction(String, Option<String>),
+
+    /// The argument of the macro must be the name of the variable, e.g. the index of the sum,
+    /// see [`variable_arg`](crate::macros::args::variable_arg)
+    #[error("Expected the variable name, found {0:?}")]
+    ExpectedVariable(String),
+}
+
+impl Error {
+    /// The stable code of the error, e.g. `E007` for [`ExpectedOperator`](Error::ExpectedOperator).
+    ///
+    /// The codes don't change between the versions, so the tools can match them instead of the messages,
+    /// see [`diagnostics`](crate::diagnostics).
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::NoLeftParenAfterFnId => "E001",
+            Error::BadToken(..) => "E002",
+            Error::OperatorAtTheEnd => "E003",
+            Error::MismatchedLeftParen => "E004",
+            Error::MismatchedRightParen => "E005",
+            Error::ArityMismatch { .. } => "E006",
+            Error::ExpectedOperator => "E007",
+            Error::ExpectedExpression => "E008",
+            Error::CommaOutsideFn => "E009",
+            Error::UnknownArgument(_) => "E010",
+            Error::DuplicateArgument(_) => "E011",
+            Error::MissingArgument(_) => "E012",
+            Error::PositionalAfterNamed => "E013",
+            Error::TooManyTokens(_) => "E014",
+            Error::TooDeep(_) => "E015",
+            Error::UnknownFunction(..) => "E016",
+            Error::ExpectedVariable(_) => "E017",
+        }
+    }
+}
+
+/// Represents the parser [`Error`](Error) together with the [`Span`](crate::tokenizer::Span) of the token that caused it.
+///
+/// Produced by [`parse_with_spans`](crate::parser::parse_with_spans) and [`parse_str_with_spans`](crate::parser::parse_str_with_spans).
+#[derive(Error, Debug, PartialEq)]
+#[error("{error} at {}..{}", span.start, span.end)]
+pub struct SpannedError {
+    /// The error.
+    pub error: Error,
+    /// The span of the token that caused the error in the input.
+    pub span: Span,
+}
+
+impl SpannedError {
+    /// The [`Position`](Position) of the token that caused the error in the `input`.
+    ///
+    /// `input` should be the string the tokens were tokenized from.
+    pub fn position(&self, input: &str) -> Position {
+        self.span.position(input)
+    }
+
+    /// The error message with the line and the column of the error in the `input`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rusty_yard::parser::parse_str_with_spans;
+    /// use rusty_yard::Ctx;
+    ///
+    /// let input = "max(\n  1,\n  2 3\n)";
+    /// let error = parse_str_with_spans(input, &Ctx::default()).unwrap_err();
+    /// assert_eq!(
+    ///     error.message_in(input),
+    ///     "Expected Operator, found expression at line 3, col 5"
+    /// );
+    /// ```
+    pub fn message_in(&self, input: &str) -> String {
+        format!("{} at {}", self.error, self.position(input))
+    }
+
+    /// Writes the line of the `input` that contains the error and underlines the span of the error.
+    ///
+    /// `input` should be the string the tokens were tokenized from.
+    /// See [`Diagnostic`](crate::diagnostics::Diagnostic) for the report with the error code, the hints and the colors.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rusty_yard::parser::parse_str_with_spans;
+    /// use rusty_yard::Ctx;
+    ///
+    /// let error = parse_str_with_spans("max(1, 2 3)", &Ctx::default()).unwrap_err();
+    /// let mut report = Vec::new();
+    /// error.report_to("max(1, 2 3)", &mut report).unwrap();
+    /// assert_eq!(String::from_utf8(report).unwrap(), "max(1, 2 3)\n         ^\n");
+    /// ```
+    pub fn report_to(&self, input: &str, out: &mut dyn Write) -> io::Result<()> {
+        let Location {
+            line,
+            column,
+            width,
+            ..
+        } = locate(input, self.span);
+        writeln!(out, "{}", line)?;
+        writeln!(out, "{}{}", " ".repeat(column), "^".repeat(width))
+    }
 }
@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+
+use crate::constants::Const;
+use crate::functions::Func;
+use crate::operators::{BiOp, UOp};
+use crate::Ctx;
+
+/// The number of tokens from which building the lookup tables is faster than the linear search
+const MIN_TOKENS: usize = 32;
+
+/// Finds the items of the context by their tokens.
+///
+/// The parser builds it once per input, the long inputs use the hash maps,
+/// the short ones use the linear search of [`Ctx`](crate::Ctx) that doesn't need to build anything.
+/// The first item with the token wins in both cases.
+pub(super) enum CtxLookup<'ctx> {
+    Linear(&'ctx Ctx),
+    Maps(Box<Maps<'ctx>>),
+}
+
+/// The items of the context by their tokens
+pub(super) struct Maps<'ctx> {
+    bi_ops: HashMap<&'ctx str, &'ctx BiOp>,
+    u_ops: HashMap<&'ctx str, &'ctx UOp>,
+    postfix_ops: HashMap<&'ctx str, &'ctx UOp>,
+    fns: HashMap<&'ctx str, &'ctx Func>,
+    consts: HashMap<&'ctx str, &'ctx Const>,
+}
+
+fn to_map<'ctx, T>(
+    items: impl Iterator<Item = &'ctx T>,
+    token: impl Fn(&'ctx T) -> &'ctx str,
+) -> HashMap<&'ctx str, &'ctx T> {
+    let mut map = HashMap::new();
+    for item in items {
+        map.entry(token(item)).or_insert(item);
+    }
+    map
+}
+
+fn add_alias<'ctx, T>(map: &mut HashMap<&'ctx str, &'ctx T>, alias: &'ctx str, token: &str) {
+    if let Some(item) = map.get(token).copied() {
+        map.entry(alias).or_insert(item);
+    }
+}
+
+impl<'ctx> CtxLookup<'ctx> {
+    pub(super) fn new(ctx: &'ctx Ctx, n_tokens: usize) -> Self {
+        // the maps find only the exact tokens
+        if n_tokens < MIN_TOKENS || ctx.tokenize_options.case_insensitive {
+            return CtxLookup::Linear(ctx);
+        }
+        let mut maps = Box::new(Maps {
+            bi_ops: to_map(ctx.bi_ops.iter(), |op| &op.token),
+            u_ops: to_map(ctx.u_ops.iter().filter(|op| !op.postfix), |op| &op.token),
+            postfix_ops: to_map(ctx.u_ops.iter().filter(|op| op.postfix), |op| &op.token),
+            fns: to_map(ctx.fns.iter(), |f| &f.token),
+            consts: to_map(ctx.consts.iter(), |c| &c.token),
+        });
+        // the items win over the aliases with the same token, like in the linear search
+        for (alias, token) in &ctx.aliases {
+            add_alias(&mut maps.bi_ops, alias, token);
+            add_alias(&mut maps.u_ops, alias, token);
+            add_alias(&mut maps.postfix_ops, alias, token);
+            add_alias(&mut maps.fns, alias, token);
+            add_alias(&mut maps.consts, alias, token);
+        }
+        CtxLookup::Maps(maps)
+    }
+
+    pub(super) fn bi_op(&self, token: &str) -> Option<&'ctx BiOp> {
+        match self {
+            CtxLookup::Linear(ctx) => ctx.bi_op(token),
+            CtxLookup::Maps(maps) => maps.bi_ops.get(token).copied(),
+        }
+    }
+
+    pub(super) fn u_op(&self, token: &str) -> Option<&'ctx UOp> {
+        match self {
+            CtxLookup::Linear(ctx) => ctx.u_op(token),
+            CtxLookup::Maps(maps) => maps.u_ops.get(token).copied(),
+        }
+    }
+
+    pub(super) fn postfix_op(&self, token: &str) -> Option<&'ctx UOp> {
+        match self {
+            CtxLookup::Linear(ctx) => ctx.postfix_op(token),
+            CtxLookup::Maps(maps) => maps.postfix_ops.get(token).copied(),
+        }
+    }
+
+    pub(super) fn func(&self, token: &str) -> Option<&'ctx Func> {
+        match self {
+            CtxLookup::Linear(ctx) => ctx.func(token),
+            CtxLookup::Maps(maps) => maps.fns.get(token).copied(),
+        }
+    }
+
+    pub(super) fn constant(&self, token: &str) -> Option<&'ctx Const> {
+        match self {
+            CtxLookup::Linear(ctx) => ctx.constant(token),
+            CtxLookup::Maps(maps) => maps.consts.get(token).copied(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operators::binary;
+
+    #[test]
+    fn test_maps_match_linear_search() {
+        let mut ctx = Ctx::default();
+        // the first operator with the token wins
+        ctx.bi_ops.push(BiOp {
+            precedence: 100,
+            ..binary::PLUS.clone()
+        });
+        let linear = CtxLookup::new(&ctx, 0);
+        let maps = CtxLookup::new(&ctx, MIN_TOKENS);
+        assert!(matches!(maps, CtxLookup::Maps(_)));
+        for token in &["+", "-", "!", "%", "max", "sin", "pi", "e", "x", ""] {
+            assert_eq!(
+                linear.bi_op(token),
+                maps.bi_op(token),
+                "input was {}",
+                token
+            );
+            assert_eq!(linear.u_op(token), maps.u_op(token), "input was {}", token);
+            assert_eq!(
+                linear.postfix_op(token),
+                maps.postfix_op(token),
+                "input was {}",
+                token
+            );
+            assert_eq!(linear.func(token), maps.func(token), "input was {}", token);
+            assert_eq!(
+                linear.constant(token),
+                maps.constant(token),
+                "input was {}",
+                token
+            );
+        }
+        assert_eq!(maps.bi_op("+").map(|op| op.precedence), Some(4));
+    }
+}
@@ -4,20 +4,38 @@
 //! into the stream of [`ParserTokens`](ParserToken) in [reverse polish notation](https://en.wikipedia.org/wiki/Reverse_Polish_notation).
 //!
 //! The parser implementation uses the [`context`](crate::Ctx) to categorize input tokens of [`Token::Id`](crate::tokenizer::Token::Id) into VariableId, Function, Binary Operator and others.
-pub use error::Error;
+pub use ast::Ast;
+pub use error::{Error, SpannedError};
+pub use optimize::optimize;
+pub use recovery::{parse_all_errors, parse_str_all_errors};
+pub use statements::{
+    parse_statements, split_statements, split_statements_with_comments, Statement, StatementError,
+};
+use std::borrow::Cow;
 pub use token::ParserToken;
+pub use trace::{parse_with_trace, ParseStep};
+
 use ParseState::*;
 
-use super::functions::Func;
+use super::functions::{Func, FuncKind, StringsCall};
 use super::macros::{ApplyMode, ParsedMacro};
-use super::operators::binary::Associativity;
+use super::operators::binary::{Associativity, Builtin, DIVIDE, MULTIPLY};
+use super::operators::unary::{PercentMode, PERCENT};
 use super::operators::{BiOp, UOp};
-use super::tokenizer::{self, Token};
+use super::suggest::closest;
+use super::tokenizer::{self, Span, Token};
 use super::Ctx;
 use crate::macros::MacroParse;
+use lookup::CtxLookup;
 
+mod ast;
 mod error;
+mod lookup;
+mod optimize;
+mod recovery;
+mod statements;
 mod token;
+mod trace;
 
 #[derive(Debug)]
 enum OperatorStackValue<'a, 'ctx> {
@@ -28,6 +46,16 @@ enum OperatorStackValue<'a, 'ctx> {
     Macro(Box<dyn ParsedMacro + 'a>),
 }
 
+/// The arguments of the call of the function with the [`params`](crate::functions::Func::params),
+/// see [`named arguments`](crate::tokenizer#named-arguments)
+#[derive(Debug)]
+struct NamedCall<'a> {
+    /// The index in the output queue where each argument starts
+    starts: Vec<usize>,
+    /// The names of the arguments by the index of the argument
+    names: Vec<(usize, &'a str)>,
+}
+
 fn to_parser_token<'a, 'ctx>(
     sv: OperatorStackValue<'a, 'ctx>,
 ) -> Result<ParserToken<'a, 'ctx>, &'static str> {
@@ -67,42 +95,209 @@ pub fn parse<'a, 'ctx>(
     tokens: &[Token<'a, 'ctx>],
     ctx: &'ctx Ctx,
 ) -> Result<Vec<ParserToken<'a, 'ctx>>, Error> {
-    if tokens.is_empty() {
+    parse_tracking(tokens.iter().cloned(), ctx, &mut 0, None)
+}
+
+/// Same as [`parse`](parse), but takes the tokens from any iterator, e.g. from the lazy [`tokens`](tokenizer::tokens).
+///
+/// The tokens are parsed as they come, so they don't have to be collected first.
+///
+/// # Example
+///
+/// ```
+/// use rusty_yard::parser::{parse_iter, ParserToken};
+/// use rusty_yard::tokenizer::tokens;
+/// use rusty_yard::Ctx;
+///
+/// let ctx = Ctx::default();
+/// let rpn = parse_iter(tokens("1 + 2", &ctx), &ctx).unwrap();
+/// assert_eq!(rpn.len(), 3);
+/// assert!(matches!(rpn[2], ParserToken::BiOp(_)));
+/// ```
+pub fn parse_iter<'a, 'ctx>(
+    tokens: impl IntoIterator<Item = Token<'a, 'ctx>>,
+    ctx: &'ctx Ctx,
+) -> Result<Vec<ParserToken<'a, 'ctx>>, Error> {
+    parse_tracking(tokens, ctx, &mut 0, None)
+}
+
+/// Same as [`parse`](parse), but the error contains the span of the token that caused it.
+///
+/// `spans` are the spans of the `tokens`,
+/// as returned by [`tokenize_with_spans`](crate::tokenizer::tokenize_with_spans).
+pub fn parse_with_spans<'a, 'ctx>(
+    tokens: &[Token<'a, 'ctx>],
+    spans: &[Span],
+    ctx: &'ctx Ctx,
+) -> Result<Vec<ParserToken<'a, 'ctx>>, SpannedError> {
+    let mut position = 0;
+    parse_tracking(tokens.iter().cloned(), ctx, &mut position, None).map_err(|error| {
+        if error == Error::MismatchedLeftParen {
+            position = unclosed_left_paren(tokens).unwrap_or(position);
+        }
+        let end = spans.last().map_or(0, |span| span.end);
+        let span = spans
+            .get(position)
+            .copied()
+            .unwrap_or(Span { start: end, end });
+        SpannedError { error, span }
+    })
+}
+
+/// Same as [`parse_str`](parse_str), but the error contains the span of the token that caused it.
+///
+/// # Example
+///
+/// ```
+/// use rusty_yard::parser::{parse_str_with_spans, Error};
+/// use rusty_yard::tokenizer::Span;
+/// use rusty_yard::Ctx;
+///
+/// let error = parse_str_with_spans("1 + * 2", &Ctx::default()).unwrap_err();
+/// assert_eq!(error.error, Error::ExpectedExpression);
+/// assert_eq!(error.span, Span { start: 4, end: 5 });
+/// ```
+pub fn parse_str_with_spans<'a, 'ctx>(
+    input: &'a str,
+    ctx: &'ctx Ctx,
+) -> Result<Vec<ParserToken<'a, 'ctx>>, SpannedError> {
+    let (tokens, spans) = tokenizer::tokenize_with_spans(input, ctx);
+    let (tokens, spans) = tokenizer::apply_passes_with_spans(tokens, spans, ctx);
+    parse_with_spans(&tokens, &spans, ctx)
+}
+
+/// The index of the left paren that is not closed
+fn unclosed_left_paren(tokens: &[Token]) -> Option<usize> {
+    let mut open = Vec::new();
+    for (i, token) in tokens.iter().enumerate() {
+        match token {
+            Token::OpenParen => open.push(i),
+            Token::ClosedParen => {
+                open.pop();
+            }
+            _ => {}
+        }
+    }
+    open.pop()
+}
+
+/// The parser, `position` is set to the index of the token that is being parsed,
+/// the state after each token is recorded into the `trace`, see [`parse_with_trace`](parse_with_trace)
+fn parse_tracking<'a, 'ctx>(
+    tokens: impl IntoIterator<Item = Token<'a, 'ctx>>,
+    ctx: &'ctx Ctx,
+    position: &mut usize,
+    mut trace: Option<&mut Vec<ParseStep>>,
+) -> Result<Vec<ParserToken<'a, 'ctx>>, Error> {
+    let mut iter = tokens.into_iter().enumerate().peekable();
+    if iter.peek().is_none() {
         return Ok(Vec::new());
     }
+    let (min_tokens, max_tokens) = iter.size_hint();
+    let lookup = CtxLookup::new(ctx, max_tokens.unwrap_or(min_tokens));
     let mut queue = Vec::new();
     let mut operator_stack: Vec<OperatorStackValue> = Vec::new();
+    let mut named_calls: Vec<NamedCall> = Vec::new();
+    // the string arguments of the open calls of the functions of the `Strings` kind
+    let mut string_calls: Vec<Vec<&'a str>> = Vec::new();
     let mut parse_state: ParseState = Expression;
-    let mut iter = tokens.iter().peekable();
-    while let Some(current_token) = iter.next() {
-        match &*current_token {
+    let limits = &ctx.limits;
+    while let Some((i, current_token)) = iter.next() {
+        *position = i;
+        if let Some(max) = limits.max_tokens.filter(|max| i >= *max) {
+            return Err(Error::TooManyTokens(max));
+        }
+        if let Some(max) = limits.max_depth.filter(|max| operator_stack.len() > *max) {
+            return Err(Error::TooDeep(max));
+        }
+        let described = trace
+            .as_ref()
+            .map(|_| trace::describe_token(&current_token));
+        match current_token {
             Token::Num(num) => {
                 parse_state.expect(Expression)?;
                 parse_state = Operator;
-                queue.push(ParserToken::Num(*num));
+                queue.push(ParserToken::Num(num));
             }
             Token::Id(id) => {
-                if let Some(u_op) = find_uop(ctx, id, parse_state) {
+                if let Some(u_op) = find_uop(&lookup, id, parse_state) {
                     operator_stack.push(OperatorStackValue::UOp(u_op));
-                } else if let Some(bi_op) = find_biop(ctx, id) {
+                } else if let Some(op) = find_postfix_op(&lookup, id, parse_state)
+                    .filter(|_| lookup.bi_op(id).is_none() || !starts_operand(iter.peek(), &lookup))
+                {
+                    // the operand is already in the output
+                    queue.push(ParserToken::UOp(op));
+                } else if let Some(bi_op) = lookup.bi_op(id) {
                     parse_state.expect(Operator)?;
                     push_to_output(&mut queue, &mut operator_stack, bi_op);
                     parse_state = Expression;
                     operator_stack.push(OperatorStackValue::BiOp(bi_op));
-                } else if let Some(func) = find_func(ctx, id, parse_state) {
-                    if let Some(Token::OpenParen) = iter.peek() {
+                } else if lookup.postfix_op(id).is_some() {
+                    // the postfix operator without the operand
+                    return Err(Error::ExpectedExpression);
+                } else if let Some(func) = find_func(&lookup, id, parse_state) {
+                    if let Some((_, Token::OpenParen)) = iter.peek() {
+                        if !func.params.is_empty() {
+                            named_calls.push(NamedCall {
+                                starts: vec![queue.len()],
+                                names: Vec::new(),
+                            });
+                        }
+                        if let FuncKind::Strings(_) = func.func {
+                            string_calls.push(Vec::new());
+                        }
                         operator_stack.push(OperatorStackValue::Func(func, 0usize))
+                    } else if func.arity == Some(0) {
+                        // the function without the arguments is referenced like the constant
+                        parse_state = Operator;
+                        queue.push(ParserToken::Func(func, 0));
+                    } else if ctx.implicit_calls && func.arity == Some(1) {
+                        // the call without the parentheses, the argument is the next operand
+                        operator_stack.push(OperatorStackValue::Func(func, 1))
                     } else {
-                        // TODO v0.3: might be better to match id, to that fn(), and fn are different
                         return Err(Error::NoLeftParenAfterFnId);
                     }
+                } else if let Some(constant) = lookup.constant(id) {
+                    parse_state.expect(Expression)?;
+                    parse_state = Operator;
+                    queue.push(ParserToken::Num(constant.value));
                 } else {
                     // variable
                     parse_state.expect(Expression)?;
+                    if let Some((_, Token::OpenParen)) = iter.peek() {
+                        let functions = ctx.fns.iter().map(|func| func.token.as_str());
+                        return Err(Error::UnknownFunction(
+                            id.to_owned(),
+                            closest(id, functions),
+                        ));
+                    }
                     parse_state = Operator;
                     queue.push(ParserToken::Id(id));
                 }
             }
+            Token::QuotedId(id) => {
+                parse_state.expect(Expression)?;
+                parse_state = Operator;
+                queue.push(ParserToken::Id(id));
+            }
+            Token::Str(s) => {
+                parse_state.expect(Expression)?;
+                parse_state = Operator;
+                // the whole argument of the function that takes strings is bound to the call,
+                // the string anywhere else is left to the evaluator to reject
+                let is_argument = matches!(
+                    operator_stack.as_slice(),
+                    [.., OperatorStackValue::Func(func, _), OperatorStackValue::LeftParen]
+                        if matches!(func.func, FuncKind::Strings(_))
+                ) && matches!(
+                    iter.peek(),
+                    Some((_, Token::Comma)) | Some((_, Token::ClosedParen))
+                );
+                match string_calls.last_mut() {
+                    Some(strings) if is_argument => strings.push(s),
+                    _ => queue.push(ParserToken::Str(s)),
+                }
+            }
             Token::OpenParen => {
                 parse_state.expect(Expression)?;
                 operator_stack.push(OperatorStackValue::LeftParen);
@@ -130,6 +325,41 @@ pub fn parse<'a, 'ctx>(
                         *n_args += 1;
                     }
                 }
+                // the call is complete, so the function goes to the output
+                // before any operator that follows it
+                if let Some(OperatorStackValue::Func(..)) = operator_stack.last() {
+                    // unwrap: the stack is not empty
+                    let mut token = to_parser_token(operator_stack.pop().unwrap()).unwrap();
+                    if let ParserToken::Func(func, n_args) = &mut token {
+                        if !func.params.is_empty() {
+                            // unwrap: the call is pushed together with the function
+                            let call = named_calls.pop().unwrap();
+                            *n_args = apply_named_args(func, *n_args, call, &mut queue)?;
+                        }
+                    }
+                    check_arity(&token)?;
+                    if let ParserToken::Func(func, n_args) = token {
+                        if let FuncKind::Strings(_) = func.func {
+                            // unwrap: the call is pushed together with the function
+                            let strings = string_calls.pop().unwrap();
+                            if !strings.is_empty() {
+                                token = ParserToken::Macro(Box::new(StringsCall {
+                                    func: func.clone(),
+                                    strings: strings.into_iter().map(Cow::Borrowed).collect(),
+                                    n_args,
+                                }));
+                            }
+                        }
+                    }
+                    queue.push(token);
+                } else if matches!(
+                    operator_stack.last(),
+                    Some(OperatorStackValue::BiOp(op)) if op.token == tokenizer::INDEX_OPERATOR
+                ) {
+                    // the index is complete, so it binds before any postfix operator that follows it
+                    // unwrap: the stack is not empty
+                    queue.push(to_parser_token(operator_stack.pop().unwrap()).unwrap());
+                }
                 parse_state = Operator;
             }
             Token::Comma => {
@@ -137,8 +367,13 @@ pub fn parse<'a, 'ctx>(
                 parse_state = Expression;
                 let found_left_paren = pop_operator_stack(&mut operator_stack, &mut queue)?;
                 match operator_stack.last_mut() {
-                    Some(OperatorStackValue::Func(_, n_args)) if found_left_paren => {
+                    Some(OperatorStackValue::Func(func, n_args)) if found_left_paren => {
                         *n_args += 1;
+                        if !func.params.is_empty() {
+                            if let Some(call) = named_calls.last_mut() {
+                                call.starts.push(queue.len());
+                            }
+                        }
                         // return left paren into the stack
                         operator_stack.push(OperatorStackValue::LeftParen);
                     }
@@ -147,6 +382,18 @@ pub fn parse<'a, 'ctx>(
                     }
                 }
             }
+            Token::ArgName(name) => {
+                parse_state.expect(Expression)?;
+                let is_param = matches!(
+                    operator_stack.as_slice(),
+                    [.., OperatorStackValue::Func(func, _), OperatorStackValue::LeftParen]
+                        if func.params.iter().any(|param| param.name == name)
+                );
+                match named_calls.last_mut() {
+                    Some(call) if is_param => call.names.push((call.starts.len() - 1, name)),
+                    _ => return Err(Error::UnknownArgument(name.to_owned())),
+                }
+            }
             Token::Macro(m) => {
                 let MacroParse {
                     result,
@@ -159,22 +406,62 @@ pub fn parse<'a, 'ctx>(
                     ApplyMode::After => operator_stack.push(OperatorStackValue::Macro(result)),
                 };
             }
-            Token::BadToken(token) => {
-                return Err(Error::BadToken(String::from(*token)));
+            Token::BadToken(token, reason) => {
+                return Err(Error::BadToken(String::from(token), reason));
             }
         }
+        trace::record(&mut trace, described, &operator_stack, &queue);
     }
     if let Expression = parse_state {
         return Err(Error::OperatorAtTheEnd);
     }
     let found_left_paren = pop_operator_stack(&mut operator_stack, &mut queue)?;
+    trace::record(&mut trace, None, &operator_stack, &queue);
     if found_left_paren {
         Err(Error::MismatchedLeftParen)
+    } else if ctx.percent_mode == PercentMode::Calculator {
+        Ok(relative_percents(queue))
     } else {
         Ok(queue)
     }
 }
 
+/// Rewrites `a + b%` into `a * (100 + b) / 100`, and the same for `-`, see [`PercentMode`](PercentMode)
+fn relative_percents<'a, 'ctx>(queue: Vec<ParserToken<'a, 'ctx>>) -> Vec<ParserToken<'a, 'ctx>> {
+    let mut output = Vec::with_capacity(queue.len());
+    // the index in the output where each operand on the evaluation stack starts
+    let mut starts: Vec<usize> = Vec::new();
+    for token in queue {
+        let n_operands = match &token {
+            ParserToken::Num(_) | ParserToken::Id(_) | ParserToken::Str(_) => 0,
+            ParserToken::UOp(_) => 1,
+            ParserToken::BiOp(_) => 2,
+            ParserToken::Func(_, n_args) => *n_args,
+            ParserToken::Macro(m) => m.operands(),
+        };
+        let operands = starts.split_off(starts.len().saturating_sub(n_operands));
+        let start = operands.first().copied().unwrap_or(output.len());
+        let is_additive = matches!(
+            token,
+            ParserToken::BiOp(op) if matches!(op.builtin, Some(Builtin::Add) | Some(Builtin::Subtract))
+        );
+        let is_percent = matches!(output.last(), Some(ParserToken::UOp(op)) if **op == *PERCENT);
+        if let (true, true, [_, right]) = (is_additive, is_percent, operands.as_slice()) {
+            // `a * (100 + b) / 100` is exact where `a * (1 + b / 100)` is not, e.g. for `200 + 10%`
+            output.pop();
+            output.insert(*right, ParserToken::Num(100.0));
+            output.push(token);
+            output.push(ParserToken::BiOp(&MULTIPLY));
+            output.push(ParserToken::Num(100.0));
+            output.push(ParserToken::BiOp(&DIVIDE));
+        } else {
+            output.push(token);
+        }
+        starts.push(start);
+    }
+    output
+}
+
 fn push_to_output<'a, 'ctx>(
     queue: &mut Vec<ParserToken<'a, 'ctx>>,
     operator_stack: &mut Vec<OperatorStackValue<'a, 'ctx>>,
@@ -182,10 +469,20 @@ fn push_to_output<'a, 'ctx>(
 ) {
     while let Some(top_of_stack) = operator_stack.last() {
         match *top_of_stack {
-            OperatorStackValue::UOp(op) => {
+            // the unary operator is applied first, unless its precedence is lower
+            OperatorStackValue::UOp(op)
+                if op
+                    .precedence
+                    .is_none_or(|precedence| precedence >= b_op.precedence) =>
+            {
                 queue.push(ParserToken::UOp(op));
                 operator_stack.pop();
             }
+            // the call without the parentheses, the explicit calls are always under the left paren
+            OperatorStackValue::Func(func, n_args) => {
+                queue.push(ParserToken::Func(func, n_args));
+                operator_stack.pop();
+            }
             OperatorStackValue::BiOp(op)
                 if op.precedence > b_op.precedence
                     || (op.precedence == b_op.precedence
@@ -213,22 +510,78 @@ pub fn parse_str<'a, 'ctx>(
     input: &'a str,
     ctx: &'ctx Ctx,
 ) -> Result<Vec<ParserToken<'a, 'ctx>>, Error> {
-    let tokens = tokenizer::tokenize(input, ctx);
+    let tokens = tokenizer::apply_passes(tokenizer::tokenize(input, ctx), ctx);
     parse(&tokens, ctx)
 }
 
-fn check_arity(token: &ParserToken) -> Result<(), Error> {
-    if let ParserToken::Func(func, n_args) = token {
-        if let Some(arity) = func.arity {
-            if arity != *n_args {
+/// Puts the arguments of the `call` in the order of the params of the `func`, and adds the defaults of the omitted ones.
+///
+/// The arguments are the last ones in the `queue`, returns their new number.
+fn apply_named_args<'a, 'ctx>(
+    func: &Func,
+    n_args: usize,
+    call: NamedCall<'a>,
+    queue: &mut Vec<ParserToken<'a, 'ctx>>,
+) -> Result<usize, Error> {
+    if call.names.last().is_some_and(|&(index, _)| index >= n_args) {
+        // the name without the value, `f(x, a =)`
+        return Err(Error::ExpectedExpression);
+    }
+    let first_named = match call.names.first() {
+        Some(&(index, _)) => index,
+        None => return Ok(n_args),
+    };
+    let mut args = Vec::with_capacity(n_args);
+    for start in call.starts[..n_args].iter().rev() {
+        args.push(queue.split_off(*start));
+    }
+    args.reverse();
+    let mut slots: Vec<Option<Vec<ParserToken>>> = func.params.iter().map(|_| None).collect();
+    for (i, arg) in args.into_iter().enumerate() {
+        let slot = match call.names.iter().find(|(index, _)| *index == i) {
+            // the tokenizer and the parser check that the param exists
+            Some((_, name)) => func
+                .params
+                .iter()
+                .position(|param| param.name == *name)
+                .ok_or_else(|| Error::UnknownArgument(name.to_string()))?,
+            None if i > first_named => return Err(Error::PositionalAfterNamed),
+            None => i,
+        };
+        match slots.get_mut(slot) {
+            Some(Some(_)) => return Err(Error::DuplicateArgument(func.params[slot].name.clone())),
+            Some(empty) => *empty = Some(arg),
+            None => {
                 return Err(Error::ArityMismatch {
-                    id: func.token.to_owned(),
-                    expected: arity,
-                    actual: *n_args,
-                });
+                    id: func.token.clone(),
+                    expected: func.arity_range(),
+                    actual: n_args,
+                })
             }
         }
     }
+    // unwrap: the named argument is in its slot
+    let passed = slots.iter().rposition(Option::is_some).unwrap() + 1;
+    for (param, slot) in func.params.iter().zip(slots).take(passed) {
+        match (slot, param.default) {
+            (Some(arg), _) => queue.extend(arg),
+            (None, Some(default)) => queue.push(ParserToken::Num(default)),
+            (None, None) => return Err(Error::MissingArgument(param.name.clone())),
+        }
+    }
+    Ok(passed)
+}
+
+fn check_arity(token: &ParserToken) -> Result<(), Error> {
+    if let ParserToken::Func(func, n_args) = token {
+        if !func.accepts(*n_args) {
+            return Err(Error::ArityMismatch {
+                id: func.token.to_owned(),
+                expected: func.arity_range(),
+                actual: *n_args,
+            });
+        }
+    }
     Ok(())
 }
 
@@ -249,22 +602,47 @@ fn pop_operator_stack<'a, 'ctx>(
 }
 
 #[inline]
-fn find_biop<'a>(ctx: &'a Ctx, id: &str) -> Option<&'a BiOp> {
-    ctx.bi_ops.iter().find(|op| op.token == id)
+fn find_uop<'ctx>(
+    lookup: &CtxLookup<'ctx>,
+    id: &str,
+    parse_state: ParseState,
+) -> Option<&'ctx UOp> {
+    let u_op = lookup.u_op(id)?;
+    match parse_state {
+        Expression => Some(u_op),
+        Operator => None,
+    }
 }
 
 #[inline]
-fn find_uop<'a>(ctx: &'a Ctx, id: &str, parse_state: ParseState) -> Option<&'a UOp> {
-    let u_op = ctx.u_ops.iter().find(|op| op.token == id)?;
+fn find_postfix_op<'ctx>(
+    lookup: &CtxLookup<'ctx>,
+    id: &str,
+    parse_state: ParseState,
+) -> Option<&'ctx UOp> {
+    let op = lookup.postfix_op(id)?;
     match parse_state {
-        Expression => Some(u_op),
-        Operator => None,
+        Expression => None,
+        Operator => Some(op),
+    }
+}
+
+/// Whether the next token starts the operand, so the operator that is both postfix and binary, e.g. `%`, is binary
+fn starts_operand(next: Option<&(usize, Token)>, lookup: &CtxLookup) -> bool {
+    match next {
+        Some((_, Token::Num(_) | Token::OpenParen | Token::QuotedId(_) | Token::Str(_))) => true,
+        Some((_, Token::Id(id))) => lookup.bi_op(id).is_none() && lookup.postfix_op(id).is_none(),
+        _ => false,
     }
 }
 
 #[inline]
-fn find_func<'a>(ctx: &'a Ctx, id: &str, parse_state: ParseState) -> Option<&'a Func> {
-    let func = ctx.fns.iter().find(|op| op.token == id)?;
+fn find_func<'ctx>(
+    lookup: &CtxLookup<'ctx>,
+    id: &str,
+    parse_state: ParseState,
+) -> Option<&'ctx Func> {
+    let func = lookup.func(id)?;
     match parse_state {
         Expression => Some(func),
         Operator => None, // does this make sense?
@@ -273,25 +651,21 @@ fn find_func<'a>(ctx: &'a Ctx, id: &str, parse_state: ParseState) -> Option<&'a
 
 #[cfg(test)]
 mod tests {
-    use crate::operators;
+    use proptest::prelude::*;
+
+    use crate::testing::expression;
+    use crate::{generate, operators};
 
     use super::ParserToken::*;
     use super::*;
+    use crate::tokenizer::BadTokenReason::*;
 
     fn get_biop() -> operators::BiOp {
-        operators::BiOp {
-            token: "bi_op".to_owned(),
-            precedence: 0,
-            associativity: Associativity::LEFT,
-            func: |_1, _2| 0.0,
-        }
+        operators::BiOp::new("bi_op", 0, Associativity::LEFT, |_1, _2| 0.0)
     }
 
     fn get_uop() -> operators::UOp {
-        operators::UOp {
-            token: "u_op".to_owned(),
-            func: |_arg| 0.0,
-        }
+        operators::UOp::new("u_op", |_arg| 0.0)
     }
     fn get_ctx() -> Ctx {
         let mut ctx = Ctx::empty();
@@ -300,6 +674,27 @@ mod tests {
         ctx
     }
 
+    proptest! {
+        #[test]
+        fn test_parse_generated(input in expression(&Ctx::default(), &["a".to_owned(), "b".to_owned()])) {
+            let ctx = Ctx::default();
+            let parsed = parse_str(&input, &ctx);
+            prop_assert!(parsed.is_ok(), "input: {}, result: {:?}", input, parsed);
+        }
+
+        #[test]
+        fn test_parse_generated_rpn(seed in any::<u64>()) {
+            let ctx = Ctx::default();
+            let options = generate::Options {
+                variables: vec!["a".to_owned(), "b".to_owned()],
+                ..generate::Options::default()
+            };
+            let tree = generate::Generator::new(&ctx, options, seed).tree();
+            let input = tree.to_string();
+            prop_assert_eq!(parse_str(&input, &ctx).ok(), tree.to_tokens(&ctx), "input: {}", input);
+        }
+    }
+
     // TODO: more tests cases
     #[test]
     fn test_parse() -> Result<(), Error> {
@@ -325,22 +720,492 @@ mod tests {
                 ],
                 vec![Id("a"), Id("b"), BiOp(&bi_op), Id("c"), BiOp(&bi_op)],
             ),
+            (
+                vec![
+                    Token::QuotedId("u_op"),
+                    Token::Id("bi_op"),
+                    Token::QuotedId("bi_op"),
+                ],
+                vec![Id("u_op"), Id("bi_op"), BiOp(&bi_op)],
+            ),
         ];
         for (input, expected) in input_expected {
-            let actual = parse(&input, &ctx).expect("Parse succeeded");
+            let actual = parse(input, &ctx).expect("Parse succeeded");
             assert_eq!(actual, *expected, "input was, {:?}", input);
         }
         Ok(())
     }
 
+    #[test]
+    fn test_parse_unary_precedence() {
+        let mut ctx = Ctx::default();
+        ctx.u_ops.retain(|op| op.token != "-");
+        // `-` has the precedence of `*`
+        ctx.u_ops.push(operators::UOp {
+            precedence: Some(5),
+            ..operators::unary::NEGATE.clone()
+        });
+        let mut vars = std::collections::HashMap::new();
+        vars.insert("a".to_owned(), 3.0);
+        let input_expected = &[
+            ("-2 ^ 2", -4.0),
+            ("(-2) ^ 2", 4.0),
+            ("-2 * 3 ^ 2", -18.0),
+            ("2 ^ -1", 0.5),
+            ("2 ^ -1 ^ 2", 0.5),
+            ("1 - -a ^ 2 + 1", 11.0),
+            ("--a ^ 2", 9.0),
+            ("-a ^ 2 * 2", -18.0),
+        ];
+        for (input, expected) in input_expected {
+            let result = parse_str(input, &ctx)
+                .map(|tokens| crate::evaluator::eval_with(&tokens, &mut vars, &ctx).unwrap());
+            assert_eq!(result, Ok(*expected), "input was {}", input);
+        }
+        // without the precedence the unary operator is applied first
+        let ctx = Ctx::default();
+        let result = parse_str("-2 ^ 2", &ctx)
+            .map(|tokens| crate::evaluator::eval_with(&tokens, &mut vars, &ctx).unwrap());
+        assert_eq!(result, Ok(4.0));
+    }
+
+    #[test]
+    fn test_parse_percent() {
+        let mut ctx = Ctx::default();
+        let mut vars = std::collections::HashMap::new();
+        vars.insert("a".to_owned(), 50.0);
+        let input_expected = &[
+            ("200 + 10%", 200.1, 220.0),
+            ("200 - 10%", 199.9, 180.0),
+            ("200 * 10%", 20.0, 20.0),
+            ("100 + 50% + 50%", 101.0, 225.0),
+            ("200 + a%", 200.5, 300.0),
+            ("200 + (10 + 10)%", 200.2, 240.0),
+            ("10% + 200", 200.1, 200.1),
+            ("-10%", -0.1, -0.1),
+            ("a%%", 0.005, 0.005),
+            ("2 ^ 200%", 4.0, 4.0),
+            ("max(100 + 10%, 1)", 100.1, 110.0),
+        ];
+        for (input, plain, calculator) in input_expected {
+            for (mode, expected) in &[
+                (PercentMode::Plain, plain),
+                (PercentMode::Calculator, calculator),
+            ] {
+                ctx.percent_mode = *mode;
+                let result = parse_str(input, &ctx)
+                    .map(|tokens| crate::evaluator::eval_with(&tokens, &mut vars, &ctx).unwrap());
+                assert_eq!(
+                    result,
+                    Ok(**expected),
+                    "input was {} in {:?} mode",
+                    input,
+                    mode
+                );
+            }
+        }
+        let input_expected = &[
+            ("%", Error::ExpectedExpression),
+            ("1 + %", Error::ExpectedExpression),
+        ];
+        for (input, expected) in input_expected {
+            assert_eq!(
+                parse_str(input, &ctx),
+                Err(expected.clone()),
+                "input was {}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_remainder() {
+        let ctx = Ctx::default();
+        let mut vars = std::collections::HashMap::new();
+        vars.insert("a".to_owned(), 7.0);
+        let input_expected = &[
+            ("7 % 3", 1.0),
+            ("-7 % 3", -1.0),
+            ("7 % (-3)", 1.0),
+            ("a % (-a)", 0.0),
+            ("10 % !0", 0.0),
+            ("-(7 % 3)", -1.0),
+            ("7.5 % 2", 1.5),
+            ("a%3", 1.0),
+            ("2 * a % 4", 2.0),
+            ("a % 4 * 2", 6.0),
+            ("1 + a % 4", 4.0),
+            ("7 // 2", 3.0),
+            ("-7 // 2", -4.0),
+            ("7 // -2", -4.0),
+            ("-7 // -2", 3.0),
+            ("a // 2 * 2 + a % 2", 7.0),
+            // `%` without the operand after it is the percent
+            ("(a + 3)% * 10", 1.0),
+            ("(a + 3) % - 1", -0.9),
+            ("10% + 5", 5.1),
+            ("(a)%", 0.07),
+        ];
+        for (input, expected) in input_expected {
+            let result = parse_str(input, &ctx)
+                .map(|tokens| crate::evaluator::eval_with(&tokens, &mut vars, &ctx).unwrap());
+            assert_eq!(result, Ok(*expected), "input was {}", input);
+        }
+    }
+
+    #[test]
+    fn test_parse_named_args() {
+        use crate::functions::{Func, Param};
+
+        let mut ctx = Ctx::default_with_macros();
+        ctx.fns.push(
+            Func::new("digits", None, |args| {
+                args[0] * 100.0 + args[1] * 10.0 + args[2]
+            })
+            .with_params(vec![
+                Param::required("x"),
+                Param::optional("mean", 0.0),
+                Param::optional("sd", 1.0),
+            ]),
+        );
+        let mut vars = std::collections::HashMap::new();
+        vars.insert("x".to_owned(), 5.0);
+        let input_expected = &[
+            ("digits(x)", Ok(501.0)),
+            ("digits(x, 2)", Ok(521.0)),
+            ("digits(x, sd = 3)", Ok(503.0)),
+            ("digits(x, sd = 3, mean = 2)", Ok(523.0)),
+            ("digits(sd = 3, x = 1 + 1)", Ok(203.0)),
+            ("digits(x, mean = x - 1) * 2", Ok(1082.0)),
+            ("digits(digits(1, sd = 0) / 100, sd = 0)", Ok(100.0)),
+            ("digits(x, sd = (y = 4)) + y", Ok(508.0)),
+            (
+                "digits(mean = 1)",
+                Err(Error::MissingArgument("x".to_owned())),
+            ),
+            (
+                "digits(x, x = 1)",
+                Err(Error::DuplicateArgument("x".to_owned())),
+            ),
+            (
+                "digits(sd = 1, sd = 2)",
+                Err(Error::DuplicateArgument("sd".to_owned())),
+            ),
+            ("digits(sd = 1, 2)", Err(Error::PositionalAfterNamed)),
+            ("digits(x, sd =)", Err(Error::ExpectedExpression)),
+            (
+                "digits(1, 2, 3, 4, sd = 1)",
+                Err(Error::ArityMismatch {
+                    id: "digits".to_owned(),
+                    expected: crate::functions::ArityRange {
+                        min: 1,
+                        max: Some(3),
+                    },
+                    actual: 5,
+                }),
+            ),
+        ];
+        for (input, expected) in input_expected {
+            let result = parse_str(input, &ctx)
+                .map(|tokens| crate::evaluator::eval_with(&tokens, &mut vars, &ctx).unwrap());
+            assert_eq!(result, *expected, "input was {}", input);
+        }
+        let tokens = [Token::ArgName("sd"), Token::Num(1.0)];
+        assert_eq!(
+            parse(&tokens, &ctx),
+            Err(Error::UnknownArgument("sd".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_parse_zero_arity_function() {
+        let mut ctx = Ctx::default();
+        ctx.fns
+            .push(crate::functions::Func::new("answer", Some(0), |_| 42.0));
+        let mut vars = std::collections::HashMap::new();
+        let input_expected = &[
+            ("answer", Ok(42.0)),
+            ("answer()", Ok(42.0)),
+            ("2 * answer + 1", Ok(85.0)),
+            ("-answer", Ok(-42.0)),
+            ("max(answer, answer())", Ok(42.0)),
+            ("answer answer", Err(Error::ExpectedOperator)),
+            (
+                "answer(1)",
+                Err(Error::ArityMismatch {
+                    id: "answer".to_owned(),
+                    expected: 0.into(),
+                    actual: 1,
+                }),
+            ),
+        ];
+        for (input, expected) in input_expected {
+            let result = parse_str(input, &ctx)
+                .map(|tokens| crate::evaluator::eval_with(&tokens, &mut vars, &ctx).unwrap());
+            assert_eq!(result, *expected, "input was {}", input);
+        }
+    }
+
+    #[test]
+    fn test_parse_implicit_calls() {
+        let mut ctx = Ctx::default();
+        let mut vars = std::collections::HashMap::new();
+        vars.insert("x".to_owned(), 4.0);
+        let input_expected = &[
+            ("sqrt x", Ok(2.0)),
+            ("sqrt 16 + 1", Ok(5.0)),
+            ("sqrt x ^ 3", Ok(8.0)),
+            ("2 * sqrt x * 3", Ok(12.0)),
+            ("sqrt sqrt 16", Ok(2.0)),
+            ("sqrt (x + 5)", Ok(3.0)),
+            ("-sqrt x", Ok(-2.0)),
+            ("sqrt -x", Ok(f64::NAN)),
+            ("max(sqrt x, 1)", Ok(2.0)),
+            ("(1 + sqrt x)", Ok(3.0)),
+            ("max 1", Err(Error::NoLeftParenAfterFnId)),
+            ("sqrt", Err(Error::OperatorAtTheEnd)),
+            ("sqrt x x", Err(Error::ExpectedOperator)),
+            ("sqrt * x", Err(Error::ExpectedExpression)),
+        ];
+        ctx.implicit_calls = true;
+        for (input, expected) in input_expected {
+            let result = parse_str(input, &ctx)
+                .map(|tokens| crate::evaluator::eval_with(&tokens, &mut vars, &ctx).unwrap());
+            match (result, expected) {
+                (Ok(result), Ok(expected)) if expected.is_nan() => {
+                    assert!(result.is_nan(), "input was {}", input)
+                }
+                (result, expected) => assert_eq!(result, *expected, "input was {}", input),
+            }
+        }
+        ctx.implicit_calls = false;
+        assert_eq!(parse_str("sqrt x", &ctx), Err(Error::NoLeftParenAfterFnId));
+    }
+
+    #[test]
+    fn test_parse_iter() {
+        let ctx = Ctx::default_with_macros();
+        let inputs = &[
+            "1 + 2 * a",
+            "max(1, 2)",
+            "-(a + 1)%",
+            "a = 1",
+            "1 +",
+            ")",
+            "",
+        ];
+        for input in inputs {
+            let lazy = parse_iter(tokenizer::tokens(input, &ctx), &ctx);
+            assert_eq!(
+                lazy,
+                parse(&tokenizer::tokenize(input, &ctx), &ctx),
+                "input was {}",
+                input
+            );
+        }
+        // the tokens can come from anywhere
+        let tokens = vec![Token::Num(1.0), Token::Id("+"), Token::Id("a")];
+        let expected = vec![Num(1.0), Id("a"), BiOp(&operators::binary::PLUS)];
+        assert_eq!(parse_iter(tokens, &ctx), Ok(expected));
+    }
+
+    #[test]
+    fn test_parse_quoted_id() {
+        let ctx = Ctx::default_with_macros();
+        let mut vars = std::collections::HashMap::new();
+        vars.insert("unit price".to_owned(), 2.5);
+        vars.insert("max".to_owned(), 4.0);
+        let input_expected = &[
+            ("`unit price` * [max]", Ok(10.0)),
+            ("[qty (pcs)] = 3", Ok(3.0)),
+            ("`unit price` * [qty (pcs)]", Ok(7.5)),
+            ("`unit price` `max`", Err(Error::ExpectedOperator)),
+        ];
+        for (input, expected) in input_expected {
+            let result = parse_str(input, &ctx)
+                .map(|tokens| crate::evaluator::eval_with(&tokens, &mut vars, &ctx).unwrap());
+            assert_eq!(result, *expected, "input was {}", input);
+        }
+    }
+
+    #[test]
+    fn test_parse_index() {
+        let ctx = crate::value::array::array_ctx();
+        let input_expected = &[
+            ("prices[i]", Ok("prices i []")),
+            ("prices[i + 1] * 2", Ok("prices i 1 + [] 2 *")),
+            ("2 ^ a[0] ^ 2", Ok("2 a 0 [] 2 ^ ^")),
+            ("-a[1]%", Ok("a - 1 [] %")),
+            ("m[i][j]", Ok("m i [] j []")),
+            ("(a + b)[0]", Ok("a b + 0 []")),
+            ("[1, 2][x]", Ok("1 2 array/2 x []")),
+            ("a [0]", Err(Error::ExpectedOperator)),
+            ("a[0", Err(Error::MismatchedLeftParen)),
+        ];
+        for (input, expected) in input_expected {
+            let result = parse_str(input, &ctx).map(|tokens| {
+                let texts: Vec<_> = tokens.iter().map(ToString::to_string).collect();
+                texts.join(" ")
+            });
+            assert_eq!(
+                result.as_deref(),
+                expected.as_ref().copied(),
+                "input was {}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_constants() {
+        let ctx = Ctx::default();
+        let mut vars = std::collections::HashMap::new();
+        vars.insert("pi".to_owned(), 3.0);
+        let input_expected = &[
+            ("2 * pi", Ok(std::f64::consts::TAU)),
+            ("`pi` * 2", Ok(6.0)),
+            ("tau - e", Ok(std::f64::consts::TAU - std::f64::consts::E)),
+            ("-inf", Ok(f64::NEG_INFINITY)),
+            ("pi pi", Err(Error::ExpectedOperator)),
+        ];
+        for (input, expected) in input_expected {
+            let result = parse_str(input, &ctx)
+                .map(|tokens| crate::evaluator::eval_with(&tokens, &mut vars, &ctx).unwrap());
+            assert_eq!(result, *expected, "input was {}", input);
+        }
+        let tokens = parse_str("nan", &ctx).unwrap();
+        assert!(matches!(tokens.as_slice(), [Num(n)] if n.is_nan()));
+    }
+
+    #[test]
+    fn test_parse_scientific_notation() {
+        let ctx = Ctx::default();
+        let input_expected = &[
+            (
+                "1e3 + 2.5E-1",
+                Ok(vec![Num(1e3), Num(0.25), BiOp(&operators::binary::PLUS)]),
+            ),
+            ("2e", Err(Error::BadToken("2e".to_owned(), MalformedNumber))),
+            (
+                "1 + 2e+",
+                Err(Error::BadToken("2e+".to_owned(), MalformedNumber)),
+            ),
+        ];
+        for (input, expected) in input_expected {
+            assert_eq!(parse_str(input, &ctx), *expected, "input was {}", input);
+        }
+    }
+
+    #[test]
+    fn test_parse_str_with_spans() {
+        let input_expected = &[
+            ("1 + * 2", Error::ExpectedExpression, (4, 5)),
+            ("1 10 + 10", Error::ExpectedOperator, (2, 4)),
+            ("a + ", Error::OperatorAtTheEnd, (2, 3)),
+            ("(1 + (2)", Error::MismatchedLeftParen, (0, 1)),
+            ("1 + 2)", Error::MismatchedRightParen, (5, 6)),
+            ("π * * 1", Error::ExpectedExpression, (5, 6)),
+            (
+                "1 + € 1",
+                Error::BadToken("€".to_owned(), InvalidCharacter),
+                (4, 7),
+            ),
+            ("a, b", Error::CommaOutsideFn, (1, 2)),
+            ("max + 1", Error::NoLeftParenAfterFnId, (0, 3)),
+            (
+                "2 * maxx(1, 2)",
+                Error::UnknownFunction("maxx".to_owned(), Some("max".to_owned())),
+                (4, 8),
+            ),
+            (
+                "foo(1)",
+                Error::UnknownFunction("foo".to_owned(), None),
+                (0, 3),
+            ),
+        ];
+        let ctx = Ctx::default();
+        for (input, error, (start, end)) in input_expected {
+            let expected = SpannedError {
+                error: error.clone(),
+                span: Span {
+                    start: *start,
+                    end: *end,
+                },
+            };
+            assert_eq!(
+                parse_str_with_spans(input, &ctx).unwrap_err(),
+                expected,
+                "input was {}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_report_to() {
+        let input_expected = &[
+            ("1 + * 2", "1 + * 2\n    ^\n"),
+            ("résultat 2", "résultat 2\n         ^\n"),
+            ("1 +\n2 )\n", "2 )\n  ^\n"),
+            ("1 +", "1 +\n  ^\n"),
+        ];
+        let ctx = Ctx::default();
+        for (input, expected) in input_expected {
+            let error = parse_str_with_spans(input, &ctx).unwrap_err();
+            let mut report = Vec::new();
+            error.report_to(input, &mut report).unwrap();
+            assert_eq!(
+                String::from_utf8(report).unwrap(),
+                *expected,
+                "input was {}",
+                input
+            );
+        }
+    }
+
     #[test]
     fn test_parse_bad_token() {
         let s = "\x00".to_owned();
         let ctx = &get_ctx();
-        let result = parse(&[Token::BadToken(&s)], &ctx).unwrap_err();
-        assert_eq!(
-            std::mem::discriminant(&result),
-            std::mem::discriminant(&Error::BadToken(s))
-        );
+        let result = parse(&[Token::BadToken(&s, InvalidCharacter)], ctx).unwrap_err();
+        assert_eq!(result, Error::BadToken(s, InvalidCharacter));
+    }
+
+    #[test]
+    fn test_structural_eq() {
+        let input_expected = &[
+            ("a + 1", "a + 1", true),
+            ("a + 1", "(a) + 1.0", true),
+            ("a + 1", "1 + a", false),
+            ("max(a, b)", "max(a, b)", true),
+            ("sum(a, b)", "sum(a, b, c)", false),
+            ("a = 1", "a = 1", true),
+            ("a = 1", "b = 1", false),
+        ];
+        let ctx = Ctx::default_with_macros();
+        for (left, right, expected) in input_expected {
+            let left = parse_str(left, &ctx).unwrap();
+            let right = parse_str(right, &ctx).unwrap();
+            assert_eq!(left == right, *expected, "input was {:?} {:?}", left, right);
+        }
+    }
+
+    #[test]
+    fn test_structural_eq_nan() {
+        assert_eq!(Num(f64::NAN), Num(f64::NAN));
+        assert_ne!(Num(0.0), Num(-0.0));
+    }
+
+    #[test]
+    fn test_structural_hash() {
+        use std::collections::HashSet;
+
+        let ctx = Ctx::default_with_macros();
+        let inputs = ["a = b + 1", "a = (b) + 1", "a = b + 2", "NaN", "NaN"];
+        let parsed = inputs
+            .iter()
+            .map(|input| parse_str(input, &ctx).unwrap())
+            .collect::<HashSet<_>>();
+        assert_eq!(parsed.len(), 3);
     }
 }
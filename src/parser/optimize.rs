@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+
+use super::ParserToken;
+use crate::evaluator::{check_math, eval_token};
+use crate::functions::{default_functions, Func};
+use crate::operators::binary::Builtin;
+use crate::operators::unary::{NEGATE, PLUS};
+use crate::Ctx;
+
+/// Simplifies the parsed `tokens`, so the repeated evaluations of the expression do less work.
+///
+/// - the operators and the [`default functions`](crate::functions::default_functions) with the constant operands
+///   are evaluated, `2 * 3 + x` becomes `6 + x`;
+/// - the identity operations are removed: `x + 0`, `0 + x`, `x - 0`, `x * 1`, `1 * x`, `x / 1`, `x ^ 1` and `+x`;
+/// - the double negation `- -x` becomes `x`.
+///
+/// The folding is skipped when the evaluation would fail or produce the infinity or `NaN`
+/// out of the finite operands, so the errors are still reported when the expression is evaluated,
+/// also with [`strict_math`](crate::evaluator::EvalOptions::strict_math).
+/// The identities are recognized for the operators with the [`builtin`](crate::operators::BiOp::builtin) arithmetic only,
+/// the macros are never folded, but their operands are.
+///
+/// Other functions are not folded, because they may have side effects or depend on the state.
+/// The constants are folded as `f64`, so the [`Value`](crate::value::Value) evaluation of the optimized tokens
+/// may differ from the original ones.
+/// For the algebraic rewrites of the expressions without macros see [`analysis::simplify`](crate::analysis::simplify()).
+/// The positions in the evaluation errors refer to the optimized tokens.
+///
+/// # Example
+///
+/// ```
+/// use rusty_yard::parser::{optimize, parse_str, Ast};
+/// use rusty_yard::Ctx;
+///
+/// let ctx = Ctx::default();
+/// let tokens = optimize(parse_str("2 * 3 + x * 1 - -(-y)", &ctx).unwrap(), &ctx);
+/// assert_eq!(Ast::from_rpn(&tokens).unwrap().to_string(), "(- (+ 6 x) y)");
+/// ```
+pub fn optimize<'a, 'ctx>(
+    tokens: Vec<ParserToken<'a, 'ctx>>,
+    ctx: &'ctx Ctx,
+) -> Vec<ParserToken<'a, 'ctx>> {
+    // the tokens of each subexpression on the stack
+    let mut stack: Vec<Vec<ParserToken<'a, 'ctx>>> = Vec::new();
+    let original = tokens.len();
+    let pure_functions = default_functions();
+    for token in tokens {
+        let n_operands = match &token {
+            ParserToken::Num(_) | ParserToken::Id(_) | ParserToken::Str(_) => 0,
+            ParserToken::UOp(_) => 1,
+            ParserToken::BiOp(_) => 2,
+            ParserToken::Func(_, n_args) => *n_args,
+            ParserToken::Macro(m) => m.operands(),
+        };
+        let operands = match stack.len().checked_sub(n_operands) {
+            Some(start) => stack.split_off(start),
+            // the ill-formed expression, it fails to evaluate the same way
+            None => std::mem::take(&mut stack),
+        };
+        stack.push(simplify(token, operands, &pure_functions, ctx));
+    }
+    let mut optimized = Vec::with_capacity(original);
+    stack
+        .into_iter()
+        .for_each(|tokens| optimized.extend(tokens));
+    optimized
+}
+
+/// Returns the tokens of the subexpression of the `token` applied to the `operands`
+fn simplify<'a, 'ctx>(
+    token: ParserToken<'a, 'ctx>,
+    mut operands: Vec<Vec<ParserToken<'a, 'ctx>>>,
+    pure_functions: &[Func],
+    ctx: &'ctx Ctx,
+) -> Vec<ParserToken<'a, 'ctx>> {
+    let constant = |operand: &[ParserToken]| match operand {
+        [ParserToken::Num(n)] => Some(*n),
+        _ => None,
+    };
+    let args: Option<Vec<f64>> = operands.iter().map(|operand| constant(operand)).collect();
+    let is_pure = match &token {
+        ParserToken::UOp(_) | ParserToken::BiOp(_) => true,
+        ParserToken::Func(func, _) => pure_functions.contains(func),
+        _ => false,
+    };
+    if let (Some(args), true) = (args, is_pure) {
+        if let Some(value) = fold(&token, args, ctx) {
+            return vec![ParserToken::Num(value)];
+        }
+    }
+    match (&token, operands.as_slice()) {
+        (ParserToken::UOp(op), [_]) if **op == *PLUS => return operands.remove(0),
+        (ParserToken::UOp(op), [operand])
+            if **op == *NEGATE
+                && matches!(operand.last(), Some(ParserToken::UOp(inner)) if **inner == *NEGATE) =>
+        {
+            let mut operand = operands.remove(0);
+            operand.pop();
+            return operand;
+        }
+        (ParserToken::BiOp(op), [left, right]) => {
+            let (left, right) = (constant(left), constant(right));
+            let identity = match op.builtin {
+                Some(Builtin::Add) if left == Some(0.0) => Some(1),
+                Some(Builtin::Add | Builtin::Subtract) if right == Some(0.0) => Some(0),
+                Some(Builtin::Multiply) if left == Some(1.0) => Some(1),
+                Some(Builtin::Multiply | Builtin::Divide | Builtin::Power)
+                    if right == Some(1.0) =>
+                {
+                    Some(0)
+                }
+                _ => None,
+            };
+            if let Some(kept) = identity {
+                return operands.swap_remove(kept);
+            }
+        }
+        _ => {}
+    }
+    let mut tokens: Vec<_> = operands.into_iter().flatten().collect();
+    tokens.push(token);
+    tokens
+}
+
+/// Evaluates the `token` with the constant `args`, if it does not fail
+fn fold(token: &ParserToken, args: Vec<f64>, ctx: &Ctx) -> Option<f64> {
+    let func = match token {
+        ParserToken::UOp(op) => &op.token,
+        ParserToken::BiOp(op) => &op.token,
+        ParserToken::Func(func, _) => &func.token,
+        _ => return None,
+    };
+    let mut eval_stack = args.clone();
+    eval_token(token, 0, &mut HashMap::new(), ctx, &mut eval_stack).ok()?;
+    let value = eval_stack.pop()?;
+    check_math(func, args, value, 0).ok()?;
+    Some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluator::eval_with_vars_and_ctx;
+    use crate::parser::{parse_str, Ast};
+
+    #[test]
+    fn test_optimize() {
+        let input_expected = &[
+            ("2 * 3 + x", "(+ 6 x)"),
+            ("x + 2 * 3", "(+ x 6)"),
+            ("2 * pi * r", "(* 6.283185307179586 r)"),
+            ("max(1, 2 + 3) - x", "(- 5 x)"),
+            ("x * 1 + 0", "x"),
+            ("0 + 1 * x / 1 ^ 1", "x"),
+            ("x - 0", "x"),
+            ("x ^ (3 - 2)", "x"),
+            ("0 - x", "(- 0 x)"),
+            ("x * 0", "(* x 0)"),
+            ("- -x", "x"),
+            ("-(-(-x))", "(- x)"),
+            ("+x", "x"),
+            ("!!x", "(! (! x))"),
+            ("-(2 + 3)", "-5"),
+            ("1 / 0 + x", "(+ (/ 1 0) x)"),
+            ("sqrt(-1)", "(sqrt -1)"),
+            ("ln(0) * x", "(* (ln 0) x)"),
+            ("a = 1 + 2", "(AssignParsed { id: \"a\" } 3)"),
+            ("double(2) + 1", "(+ (double 2) 1)"),
+        ];
+        let mut ctx = Ctx::default_with_macros();
+        ctx.fns
+            .push(Func::new("double", Some(1), |args| args[0] * 2.0));
+        for (input, expected) in input_expected {
+            let tokens = optimize(parse_str(input, &ctx).unwrap(), &ctx);
+            let ast = Ast::from_rpn(&tokens).unwrap();
+            assert_eq!(ast.to_string(), *expected, "input was {}", input);
+        }
+    }
+
+    #[test]
+    fn test_optimize_evaluates_the_same() {
+        let ctx = Ctx::default();
+        let mut vars = HashMap::new();
+        vars.insert("x".to_owned(), 3.0);
+        let inputs = &[
+            "2 ^ 3 ^ 2 * x",
+            "-(-x) / (1 + 1) ^ 1",
+            "sin(pi / 2) + x - 0",
+            "+-+x * 1",
+            "x / (2 - 2)",
+            "y + 1 * 2",
+        ];
+        for input in inputs {
+            let tokens = parse_str(input, &ctx).unwrap();
+            let expected = eval_with_vars_and_ctx(&tokens, &mut vars, &ctx);
+            let optimized = optimize(tokens, &ctx);
+            let result = eval_with_vars_and_ctx(&optimized, &mut vars, &ctx);
+            assert_eq!(result, expected, "input was {}", input);
+        }
+    }
+}
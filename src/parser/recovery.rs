@@ -0,0 +1,330 @@
+use super::{parse_tracking, unclosed_left_paren, Error, ParserToken, SpannedError};
+use crate::tokenizer::{self, Span, Token};
+use crate::Ctx;
+
+/// Same as [`parse_with_spans`](super::parse_with_spans), but returns every error of the expression.
+///
+/// After the error the parser recovers and continues to parse the rest of the expression:
+///
+/// - the argument or the parenthesized part of the expression that contains the error,
+///   i.e. the tokens up to the nearest comma or paren, is replaced by the placeholder operand,
+///   the unknown token or the function without the parentheses is replaced alone;
+/// - the unexpected operand is removed, `1 10 + 10` is parsed as `1 + 10`;
+/// - the missing operand is inserted, `1 + * 2` is parsed as `1 + _ * 2`;
+/// - the unmatched paren is removed;
+/// - the comma outside the function separates the independent expressions;
+/// - the call with the wrong arguments is replaced as a whole.
+///
+/// The recovery can produce the errors that are caused by the previous ones, the first error is always the same
+/// as the one of [`parse_with_spans`](super::parse_with_spans).
+/// The [`TooManyTokens`](Error::TooManyTokens) and [`TooDeep`](Error::TooDeep) errors stop the parsing.
+pub fn parse_all_errors<'a, 'ctx>(
+    tokens: &[Token<'a, 'ctx>],
+    spans: &[Span],
+    ctx: &'ctx Ctx,
+) -> Result<Vec<ParserToken<'a, 'ctx>>, Vec<SpannedError>> {
+    let mut position = 0;
+    match parse_tracking(tokens.iter().cloned(), ctx, &mut position, None) {
+        Ok(parsed) => Ok(parsed),
+        Err(_) => {
+            let mut errors = Vec::new();
+            collect_errors(tokens.to_vec(), spans.to_vec(), ctx, &mut errors);
+            Err(errors)
+        }
+    }
+}
+
+/// Same as [`parse_all_errors`](parse_all_errors), but tokenizes the `input` first.
+///
+/// # Example
+///
+/// ```
+/// use rusty_yard::parser::{parse_str_all_errors, Error};
+/// use rusty_yard::Ctx;
+///
+/// let errors = parse_str_all_errors("max(1 +, 2) * (3 4) + 5)", &Ctx::default()).unwrap_err();
+/// let errors: Vec<_> = errors.into_iter().map(|e| (e.error, e.span.start)).collect();
+/// assert_eq!(
+///     errors,
+///     vec![
+///         (Error::ExpectedExpression, 7),
+///         (Error::ExpectedOperator, 17),
+///         (Error::MismatchedRightParen, 23),
+///     ]
+/// );
+/// ```
+pub fn parse_str_all_errors<'a, 'ctx>(
+    input: &'a str,
+    ctx: &'ctx Ctx,
+) -> Result<Vec<ParserToken<'a, 'ctx>>, Vec<SpannedError>> {
+    let (tokens, spans) = tokenizer::tokenize_with_spans(input, ctx);
+    let (tokens, spans) = tokenizer::apply_passes_with_spans(tokens, spans, ctx);
+    parse_all_errors(&tokens, &spans, ctx)
+}
+
+/// The operand that replaces the tokens with the error
+const PLACEHOLDER: Token<'static, 'static> = Token::Num(0.0);
+
+/// Parses the `tokens` again and again, fixing the error each time, until there are no errors
+fn collect_errors<'a, 'ctx>(
+    mut tokens: Vec<Token<'a, 'ctx>>,
+    mut spans: Vec<Span>,
+    ctx: &'ctx Ctx,
+    errors: &mut Vec<SpannedError>,
+) {
+    // every fix removes the tokens or inserts the missing operand, that can't fail the same way,
+    // the limit is the guard against the parsers of the macros
+    for _ in 0..=2 * tokens.len() + 1 {
+        let mut position = 0;
+        let error = match parse_tracking(tokens.iter().cloned(), ctx, &mut position, None) {
+            Ok(_) => return,
+            Err(error) => error,
+        };
+        if error == Error::MismatchedLeftParen {
+            position = unclosed_left_paren(&tokens).unwrap_or(position);
+        }
+        let end = spans.last().map_or(0, |span| span.end);
+        let span = spans
+            .get(position)
+            .copied()
+            .unwrap_or(Span { start: end, end });
+        errors.push(SpannedError {
+            error: error.clone(),
+            span,
+        });
+        let at_paren = matches!(tokens.get(position), Some(Token::ClosedParen));
+        let (start, end, replacement) = match error {
+            Error::TooManyTokens(_) | Error::TooDeep(_) => return,
+            Error::MismatchedLeftParen | Error::MismatchedRightParen => {
+                (position, position + 1, None)
+            }
+            Error::ExpectedExpression => (position, position, Some(span)),
+            Error::ExpectedOperator => (position, operand_end(&tokens, position), None),
+            Error::UnknownFunction(..) => {
+                let end = operand_end(&tokens, position);
+                (position, end, Some(span_of(&spans[position..end])))
+            }
+            Error::NoLeftParenAfterFnId | Error::BadToken(..) => {
+                (position, position + 1, Some(span))
+            }
+            Error::OperatorAtTheEnd if at_paren => (position, position, Some(span)),
+            Error::OperatorAtTheEnd => (tokens.len(), tokens.len(), Some(span)),
+            Error::CommaOutsideFn => {
+                // the rest is the independent expression
+                let (_, end) = segment(&tokens, position + 1);
+                collect_errors(
+                    tokens[position + 1..end].to_vec(),
+                    spans[position + 1..end].to_vec(),
+                    ctx,
+                    errors,
+                );
+                (position, end, None)
+            }
+            _ if at_paren => {
+                let start = call_start(&tokens, position);
+                (start, position + 1, Some(span_of(&spans[start..=position])))
+            }
+            _ => {
+                let (start, end) = segment(&tokens, position);
+                (start, end, Some(span_of(&spans[start..end.max(start + 1)])))
+            }
+        };
+        match replacement {
+            Some(span) => {
+                tokens.splice(start..end, Some(PLACEHOLDER));
+                spans.splice(start..end, Some(span));
+            }
+            None => {
+                tokens.drain(start..end);
+                spans.drain(start..end);
+            }
+        }
+    }
+}
+
+/// The range of the tokens around the `position` up to the nearest comma or paren of the same depth
+fn segment(tokens: &[Token], position: usize) -> (usize, usize) {
+    let mut depth = 0;
+    let mut start = 0;
+    for i in (0..position.min(tokens.len())).rev() {
+        match tokens[i] {
+            Token::ClosedParen => depth += 1,
+            Token::OpenParen if depth > 0 => depth -= 1,
+            Token::OpenParen | Token::Comma if depth == 0 => {
+                start = i + 1;
+                break;
+            }
+            _ => {}
+        }
+    }
+    let mut depth = 0;
+    let mut end = tokens.len();
+    for (i, token) in tokens.iter().enumerate().skip(position) {
+        match token {
+            Token::OpenParen => depth += 1,
+            Token::ClosedParen if depth > 0 => depth -= 1,
+            Token::ClosedParen | Token::Comma if depth == 0 => {
+                end = i;
+                break;
+            }
+            _ => {}
+        }
+    }
+    (start, end)
+}
+
+/// The index after the operand that starts at the `position`, with the arguments of the call or the parenthesized expression
+fn operand_end(tokens: &[Token], position: usize) -> usize {
+    let mut depth = 0;
+    for (i, token) in tokens.iter().enumerate().skip(position) {
+        match token {
+            Token::OpenParen => depth += 1,
+            Token::ClosedParen if depth > 0 => depth -= 1,
+            // the function id
+            Token::Id(_) if matches!(tokens.get(i + 1), Some(Token::OpenParen)) => continue,
+            _ => {}
+        }
+        if depth == 0 {
+            return i + 1;
+        }
+    }
+    tokens.len()
+}
+
+/// The index of the function of the call that ends with the paren at the `position`, or of its left paren
+fn call_start(tokens: &[Token], position: usize) -> usize {
+    let mut depth = 0;
+    for i in (0..position).rev() {
+        match tokens[i] {
+            Token::ClosedParen => depth += 1,
+            Token::OpenParen if depth > 0 => depth -= 1,
+            Token::OpenParen => {
+                return match i.checked_sub(1).map(|id| &tokens[id]) {
+                    Some(Token::Id(_)) => i - 1,
+                    _ => i,
+                };
+            }
+            _ => {}
+        }
+    }
+    position
+}
+
+/// The span that covers the `spans`
+fn span_of(spans: &[Span]) -> Span {
+    match (spans.first(), spans.last()) {
+        (Some(first), Some(last)) => Span {
+            start: first.start,
+            end: last.end,
+        },
+        _ => Span::default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::parser::parse_str_with_spans;
+    use crate::tokenizer::BadTokenReason;
+
+    proptest! {
+        #[test]
+        fn test_parse_all_errors_any_input(s in r"[a-c1-3+*\-(),€ ]{0,24}") {
+            let ctx = Ctx::default();
+            match (parse_str_all_errors(&s, &ctx), parse_str_with_spans(&s, &ctx)) {
+                (Ok(all), Ok(first)) => prop_assert_eq!(all, first),
+                (Err(all), Err(first)) => prop_assert_eq!(&all[0], &first),
+                (all, first) => prop_assert!(false, "{:?} != {:?}", all, first),
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_all_errors() {
+        let input_expected: &[(&str, &[(Error, usize)])] = &[
+            ("1 + * 2", &[(Error::ExpectedExpression, 4)]),
+            (
+                "1 + * 2 - * 3",
+                &[
+                    (Error::ExpectedExpression, 4),
+                    (Error::ExpectedExpression, 10),
+                ],
+            ),
+            ("1 10 + 10", &[(Error::ExpectedOperator, 2)]),
+            ("a +", &[(Error::OperatorAtTheEnd, 2)]),
+            (
+                "(a + ) * (b -)",
+                &[(Error::OperatorAtTheEnd, 5), (Error::OperatorAtTheEnd, 13)],
+            ),
+            ("((1 + 2) * 3", &[(Error::MismatchedLeftParen, 0)]),
+            (
+                "1) + 2)",
+                &[
+                    (Error::MismatchedRightParen, 1),
+                    (Error::MismatchedRightParen, 6),
+                ],
+            ),
+            (
+                "a, b c, d",
+                &[
+                    (Error::CommaOutsideFn, 1),
+                    (Error::ExpectedOperator, 5),
+                    (Error::CommaOutsideFn, 6),
+                ],
+            ),
+            (
+                "max(1, 2, 3) + sin(€ + 1)",
+                &[
+                    (
+                        Error::ArityMismatch {
+                            id: "max".to_owned(),
+                            expected: 2.into(),
+                            actual: 3,
+                        },
+                        11,
+                    ),
+                    (
+                        Error::BadToken("€".to_owned(), BadTokenReason::InvalidCharacter),
+                        19,
+                    ),
+                ],
+            ),
+            (
+                "sinn(1 +) + (2 2)",
+                &[
+                    (
+                        Error::UnknownFunction("sinn".to_owned(), Some("sin".to_owned())),
+                        0,
+                    ),
+                    (Error::ExpectedOperator, 15),
+                ],
+            ),
+            (
+                "max + 1 ^ (2 2)",
+                &[
+                    (Error::NoLeftParenAfterFnId, 0),
+                    (Error::ExpectedOperator, 13),
+                ],
+            ),
+        ];
+        let ctx = Ctx::default();
+        for (input, expected) in input_expected {
+            let errors = parse_str_all_errors(input, &ctx).unwrap_err();
+            let errors: Vec<_> = errors
+                .into_iter()
+                .map(|e| (e.error, e.span.start))
+                .collect();
+            assert_eq!(errors, *expected, "input was {}", input);
+            // the first error is the one of the parser
+            let first = parse_str_with_spans(input, &ctx).unwrap_err();
+            assert_eq!(first.error, expected[0].0, "input was {}", input);
+            assert_eq!(first.span.start, expected[0].1, "input was {}", input);
+        }
+        assert_eq!(
+            parse_str_all_errors("max(1, 2) * 3", &ctx).map(|tokens| tokens.len()),
+            Ok(5)
+        );
+    }
+}
@@ -0,0 +1,342 @@
+use thiserror::Error;
+
+use super::{parse_str, Error, ParserToken};
+use crate::tokenizer::{match_string, skip_whitespace_and_comments, DecimalSeparator, Match};
+use crate::Ctx;
+
+/// Represents a single statement of multi-statement input.
+///
+/// Statements are separated by new lines and `;`, see [`split_statements`](split_statements).
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Statement<'a> {
+    /// The line (starting from 1) the statement is on.
+    pub line: usize,
+    /// The text of the statement.
+    pub text: &'a str,
+}
+
+/// Represents the error that occurred while parsing one of the statements of multi-statement input.
+#[derive(Error, Debug, PartialEq)]
+#[error("Statement {statement} (line {line}): {error}")]
+pub struct StatementError {
+    /// Index of the statement in the list returned by [`split_statements`](split_statements),
+    /// or by [`split_statements_with_comments`](split_statements_with_comments) if the context has the comments.
+    pub statement: usize,
+    /// The line (starting from 1) the statement is on.
+    pub line: usize,
+    /// The error itself.
+    pub error: Error,
+}
+
+/// Splits multi-statement input into the statements.
+///
+/// Statements are separated by new lines and `;`. Empty statements are skipped.
+/// The new lines and `;` inside the [string literals](crate::tokenizer#string-literals) of the context
+/// with [`strings`](crate::Ctx::strings) don't separate the statements.
+///
+/// When the [decimal separator](crate::tokenizer::DecimalSeparator) of the context is the comma,
+/// the `;` inside the parentheses separates the arguments of the functions, not the statements.
+///
+/// # Example
+///
+/// ```
+/// use rusty_yard::parser::split_statements;
+/// use rusty_yard::tokenizer::{DecimalSeparator, TokenizeOptions};
+/// use rusty_yard::Ctx;
+///
+/// let ctx = Ctx {
+///     tokenize_options: TokenizeOptions {
+///         decimal_separator: DecimalSeparator::Comma,
+///         ..TokenizeOptions::default()
+///     },
+///     ..Ctx::default()
+/// };
+/// let statements: Vec<_> = split_statements("a = max(1,5; 2); b = 2", &ctx).map(|s| s.text).collect();
+/// assert_eq!(statements, vec!["a = max(1,5; 2)", " b = 2"]);
+/// ```
+pub fn split_statements<'a>(input: &'a str, ctx: &Ctx) -> impl Iterator<Item = Statement<'a>> {
+    split(input, ctx, false)
+        .into_iter()
+        .filter(|statement| !statement.text.trim().is_empty())
+}
+
+/// Same as [`split_statements`](split_statements), but the new lines and `;` inside the [comments](crate::tokenizer#comments)
+/// don't separate the statements, and the statements of only the comments are skipped.
+/// The `#` and `/*` inside the string literals don't start the comments.
+///
+/// The statements keep their comments, the tokenizer of the context with [`comments`](crate::Ctx::comments) skips them.
+///
+/// # Example
+///
+/// ```
+/// use rusty_yard::parser::split_statements_with_comments;
+/// use rusty_yard::Ctx;
+///
+/// let input = "# the area; in m2\narea = w * h /* w; h\n are in m */\nw = 2";
+/// let statements: Vec<_> = split_statements_with_comments(input, &Ctx::default()).map(|s| s.line).collect();
+/// assert_eq!(statements, vec![2, 4]);
+/// ```
+pub fn split_statements_with_comments<'a>(
+    input: &'a str,
+    ctx: &Ctx,
+) -> impl Iterator<Item = Statement<'a>> {
+    split(input, ctx, true)
+        .into_iter()
+        .filter(|statement| skip_whitespace_and_comments(statement.text) < statement.text.len())
+}
+
+/// Splits the input at the new lines and `;`, skips over the [string literals](crate::tokenizer#string-literals)
+/// of the context with the strings and over the comments if `comments` is set
+fn split<'a>(input: &'a str, ctx: &Ctx, comments: bool) -> Vec<Statement<'a>> {
+    let argument_semicolon = ctx.tokenize_options.decimal_separator == DecimalSeparator::Comma;
+    let mut statements = Vec::new();
+    let (mut start, mut start_line, mut line) = (0, 1, 1);
+    // the depth of the parentheses on the current line
+    let mut depth = 0usize;
+    let mut i = 0;
+    while let Some(ch) = input[i..].chars().next() {
+        let rest = &input[i..];
+        // the string that is not closed is left to the tokenizer to report
+        if let Some(Match(_, len)) = match_string(rest).filter(|_| ctx.strings) {
+            line += rest[..len].matches('\n').count();
+            i += len;
+            continue;
+        }
+        if comments && ch == '#' {
+            i += rest.find('\n').unwrap_or(rest.len());
+            continue;
+        }
+        if comments && rest.starts_with("/*") {
+            // the comment that is not closed lasts to the end of the input
+            let end = rest["/*".len()..]
+                .find("*/")
+                .map_or(rest.len(), |end| "/*".len() + end + "*/".len());
+            line += rest[..end].matches('\n').count();
+            i += end;
+            continue;
+        }
+        match ch {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ';' if argument_semicolon && depth > 0 => {}
+            '\n' | ';' => {
+                let text = &input[start..i];
+                statements.push(Statement {
+                    line: start_line,
+                    text: text.strip_suffix('\r').unwrap_or(text),
+                });
+                start = i + 1;
+                if ch == '\n' {
+                    line += 1;
+                    depth = 0;
+                }
+                start_line = line;
+            }
+            _ => {}
+        }
+        i += ch.len_utf8();
+    }
+    statements.push(Statement {
+        line: start_line,
+        text: &input[start..],
+    });
+    statements
+}
+
+/// Parses each statement of multi-statement input into its own stream of [`ParserTokens`](ParserToken).
+///
+/// The statements are split by [`split_statements_with_comments`](split_statements_with_comments)
+/// if the context has the [`comments`](crate::Ctx::comments).
+///
+/// Statements are independent of each other, so with `rayon` feature enabled they are parsed in parallel.
+///
+/// Unlike [`parse`](super::parse), this does not stop at the first error.
+/// All statements are parsed and the errors of every one of them are returned together.
+///
+/// # Example
+///
+/// ```
+/// use rusty_yard::parser::parse_statements;
+/// use rusty_yard::Ctx;
+///
+/// let ctx = Ctx::default_with_macros();
+/// let parsed = parse_statements("a = 1; b = 2\nc = a + b", &ctx).unwrap();
+/// assert_eq!(parsed.len(), 3);
+///
+/// let errors = parse_statements("1 +\n2\n3 4", &ctx).unwrap_err();
+/// assert_eq!(errors.iter().map(|e| e.line).collect::<Vec<_>>(), vec![1, 3]);
+/// ```
+pub fn parse_statements<'a, 'ctx>(
+    input: &'a str,
+    ctx: &'ctx Ctx,
+) -> Result<Vec<Vec<ParserToken<'a, 'ctx>>>, Vec<StatementError>> {
+    let statements: Vec<_> = if ctx.comments {
+        split_statements_with_comments(input, ctx).collect()
+    } else {
+        split_statements(input, ctx).collect()
+    };
+    #[cfg(feature = "rayon")]
+    let results: Vec<_> = {
+        use rayon::prelude::*;
+        statements
+            .par_iter()
+            .map(|statement| parse_str(statement.text, ctx))
+            .collect()
+    };
+    #[cfg(not(feature = "rayon"))]
+    let results: Vec<_> = statements
+        .iter()
+        .map(|statement| parse_str(statement.text, ctx))
+        .collect();
+
+    let mut parsed = Vec::with_capacity(results.len());
+    let mut errors = Vec::new();
+    for (index, (statement, result)) in statements.iter().zip(results).enumerate() {
+        match result {
+            Ok(tokens) => parsed.push(tokens),
+            Err(error) => errors.push(StatementError {
+                statement: index,
+                line: statement.line,
+                error,
+            }),
+        }
+    }
+    if errors.is_empty() {
+        Ok(parsed)
+    } else {
+        Err(errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluator::eval;
+    use crate::tokenizer::TokenizeOptions;
+
+    #[test]
+    fn test_split_statements() {
+        let statements: Vec<_> =
+            split_statements("a = 1;b = 2\n\n c ; \n", &Ctx::default()).collect();
+        assert_eq!(
+            statements,
+            vec![
+                Statement {
+                    line: 1,
+                    text: "a = 1"
+                },
+                Statement {
+                    line: 1,
+                    text: "b = 2"
+                },
+                Statement {
+                    line: 3,
+                    text: " c "
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_statements_with_comments() {
+        let input = "a = 1 # one; two\n/* b = 2;\n */ c # ;\n# d\n/**/ ; e /* \n";
+        let statements: Vec<_> = split_statements_with_comments(input, &Ctx::default())
+            .map(|statement| (statement.line, statement.text))
+            .collect();
+        assert_eq!(
+            statements,
+            vec![
+                (1, "a = 1 # one; two"),
+                (2, "/* b = 2;\n */ c # ;"),
+                (5, " e /* \n"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_statements_strings() {
+        let ctx = Ctx {
+            strings: true,
+            ..Ctx::default()
+        };
+        let input_expected: &[(&str, &[(usize, &str)])] = &[
+            ("a = \"x;y\"; b", &[(1, "a = \"x;y\""), (1, " b")]),
+            ("\"x\ny\"\nb", &[(1, "\"x\ny\""), (3, "b")]),
+            // the string that is not closed doesn't hide the separators
+            ("a = \"x; b", &[(1, "a = \"x"), (1, " b")]),
+        ];
+        for (input, expected) in input_expected {
+            let statements: Vec<_> = split_statements(input, &ctx)
+                .map(|statement| (statement.line, statement.text))
+                .collect();
+            assert_eq!(statements, *expected, "input was {}", input);
+        }
+        let statements: Vec<_> = split_statements("a = \"x;y\"", &Ctx::default())
+            .map(|statement| statement.text)
+            .collect();
+        assert_eq!(statements, vec!["a = \"x", "y\""]);
+    }
+
+    #[test]
+    fn test_split_statements_with_comments_strings() {
+        let ctx = Ctx {
+            strings: true,
+            comments: true,
+            ..Ctx::default()
+        };
+        let input = "a = \"#1; /*\" # b; c\nd = \"*/\" /* \" */ ; e";
+        let statements: Vec<_> = split_statements_with_comments(input, &ctx)
+            .map(|statement| (statement.line, statement.text))
+            .collect();
+        assert_eq!(
+            statements,
+            vec![
+                (1, "a = \"#1; /*\" # b; c"),
+                (2, "d = \"*/\" /* \" */ "),
+                (2, " e"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_statements_decimal_comma() {
+        let ctx = Ctx {
+            tokenize_options: TokenizeOptions {
+                decimal_separator: DecimalSeparator::Comma,
+                ..TokenizeOptions::default()
+            },
+            ..Ctx::default()
+        };
+        let input_expected: &[(&str, &[f64])] = &[
+            ("max(1,5; 2,5)", &[2.5]),
+            ("max(1,5; 2,5); 3", &[2.5, 3.0]),
+            ("min(1; max(2; 3))\n4;5", &[1.0, 4.0, 5.0]),
+        ];
+        for (input, expected) in input_expected {
+            let parsed = parse_statements(input, &ctx).unwrap();
+            let results: Vec<_> = parsed.iter().map(|tokens| eval(tokens).unwrap()).collect();
+            assert_eq!(results, *expected, "input was {}", input);
+        }
+    }
+
+    #[test]
+    fn test_parse_statements_errors() {
+        let ctx = Ctx::default();
+        let errors = parse_statements("1 +; 2\n(3", &ctx).unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                StatementError {
+                    statement: 0,
+                    line: 1,
+                    error: Error::OperatorAtTheEnd
+                },
+                StatementError {
+                    statement: 2,
+                    line: 2,
+                    error: Error::MismatchedLeftParen
+                },
+            ]
+        );
+    }
+}
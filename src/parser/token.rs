@@ -1,6 +1,10 @@
+use std::fmt::{self, Display, Formatter};
+use std::hash::{Hash, Hasher};
+
 use crate::functions::Func;
 use crate::macros::ParsedMacro;
 use crate::operators::{BiOp, UOp};
+use crate::tokenizer::quote_id;
 
 /// Represents the parser token.
 ///
@@ -12,6 +16,11 @@ pub enum ParserToken<'a, 'ctx> {
     Num(f64),
     /// Represents a variable identifier.
     Id(&'a str),
+    /// Represents the [string literal](crate::tokenizer#string-literals), without the quotes.
+    ///
+    /// The string arguments of the [functions with strings](crate::functions::Func::strings) are passed
+    /// to them by the parser, the evaluator rejects the others with [`UnexpectedString`](crate::evaluator::Error::UnexpectedString).
+    Str(&'a str),
     /// Represents a [`Unary operator`](crate::operators::UOp).
     UOp(&'ctx UOp),
     /// Represents a [`Binary operator`](crate::operators::BiOp).
@@ -27,6 +36,24 @@ pub enum ParserToken<'a, 'ctx> {
     Macro(Box<dyn ParsedMacro + 'a>),
 }
 
+impl Display for ParserToken<'_, '_> {
+    /// Writes the token as it appears in the input.
+    ///
+    /// Functions are followed by the number of arguments they were called with (`max/2`)
+    /// and macros are written using their [`Debug`](std::fmt::Debug) implementation.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ParserToken::Num(n) => write!(f, "{}", n),
+            ParserToken::Id(id) => write!(f, "{}", quote_id(id)),
+            ParserToken::Str(s) => write!(f, "\"{}\"", s),
+            ParserToken::UOp(op) => write!(f, "{}", op.token),
+            ParserToken::BiOp(op) => write!(f, "{}", op.token),
+            ParserToken::Func(func, n_args) => write!(f, "{}/{}", func.token, n_args),
+            ParserToken::Macro(m) => write!(f, "{:?}", m),
+        }
+    }
+}
+
 impl<'a> From<&'a BiOp> for ParserToken<'_, 'a> {
     #[inline]
     #[cfg_attr(tarpaulin, skip)]
@@ -39,7 +66,7 @@ impl<'a> From<&'a UOp> for ParserToken<'_, 'a> {
     #[inline]
     #[cfg_attr(tarpaulin, skip)]
     fn from(op: &'a UOp) -> Self {
-        ParserToken::UOp(&op)
+        ParserToken::UOp(op)
     }
 }
 
@@ -51,21 +78,51 @@ impl<'a> From<(&'a Func, usize)> for ParserToken<'_, 'a> {
     }
 }
 
+/// Structural equality of the tokens.
+///
+/// Numbers are compared bitwise, so `NaN` is equal to itself and `0.0` is not equal to `-0.0`.
+/// Operators and functions are compared by their tokens, functions also by the number of arguments.
+/// Macros are compared by their [`name`](crate::macros::ParsedMacro::name)
+/// and [`structural_key`](crate::macros::ParsedMacro::structural_key),
+/// the macro without the key is only equal to itself.
 impl PartialEq for ParserToken<'_, '_> {
-    #[cfg_attr(tarpaulin, skip)]
     fn eq(&self, other: &Self) -> bool {
         use ParserToken::*;
-        if std::mem::discriminant(self) != std::mem::discriminant(other) {
-            return false;
-        }
         match (self, other) {
-            (Num(n1), Num(n2)) => n1 == n2,
+            (Num(n1), Num(n2)) => n1.to_bits() == n2.to_bits(),
             (Id(id1), Id(id2)) => id1 == id2,
-            (UOp(op1), UOp(op2)) => op1 == op2,
-            (BiOp(op1), BiOp(op2)) => op1 == op2,
-            (Func(f1, s1), Func(f2, s2)) => f1 == f2 && s1 == s2,
-            (Macro(_), Macro(_)) => unimplemented!(),
+            (Str(s1), Str(s2)) => s1 == s2,
+            (UOp(op1), UOp(op2)) => op1.token == op2.token,
+            (BiOp(op1), BiOp(op2)) => op1.token == op2.token,
+            (Func(f1, s1), Func(f2, s2)) => f1.token == f2.token && s1 == s2,
+            (Macro(m1), Macro(m2)) => match (m1.structural_key(), m2.structural_key()) {
+                (Some(k1), Some(k2)) => m1.name() == m2.name() && k1 == k2,
+                _ => std::ptr::addr_eq(m1.as_ref(), m2.as_ref()),
+            },
             _ => false,
         }
     }
 }
+
+impl Eq for ParserToken<'_, '_> {}
+
+/// Structural hash of the token, consistent with its [`PartialEq`](std::cmp::PartialEq) implementation.
+impl Hash for ParserToken<'_, '_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            ParserToken::Num(n) => n.to_bits().hash(state),
+            ParserToken::Id(id) | ParserToken::Str(id) => id.hash(state),
+            ParserToken::UOp(op) => op.token.hash(state),
+            ParserToken::BiOp(op) => op.token.hash(state),
+            ParserToken::Func(func, n_args) => {
+                func.token.hash(state);
+                n_args.hash(state);
+            }
+            ParserToken::Macro(m) => {
+                m.name().hash(state);
+                m.structural_key().hash(state);
+            }
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use std::fmt::{self, Display, Formatter};
+
+use super::{parse_tracking, Error, OperatorStackValue, ParserToken};
+use crate::tokenizer::{quote_id, Token};
+use crate::Ctx;
+
+/// The state of the shunting yard after the input token is parsed, recorded by [`parse_with_trace`](parse_with_trace).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseStep {
+    /// The input token as it is written in the input,
+    /// `None` for the last step, that moves the operators left on the stack to the output.
+    pub token: Option<String>,
+    /// The operator stack, the bottom first: the operators, the left parens, the macros
+    /// and the functions with the number of the arguments they have so far, e.g. `max/1`.
+    pub operator_stack: Vec<String>,
+    /// The output queue in the reverse polish notation, the tokens are written like the [`ParserTokens`](ParserToken).
+    pub output: Vec<String>,
+}
+
+impl ParseStep {
+    fn new(
+        token: Option<String>,
+        operator_stack: &[OperatorStackValue],
+        output: &[ParserToken],
+    ) -> Self {
+        ParseStep {
+            token,
+            operator_stack: operator_stack.iter().map(describe_operator).collect(),
+            output: output.iter().map(ParserToken::to_string).collect(),
+        }
+    }
+}
+
+impl Display for ParseStep {
+    /// Writes the step as `+ | stack: ( + | output: 1`, the last step has `end` instead of the token.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} | stack:", self.token.as_deref().unwrap_or("end"))?;
+        for operator in &self.operator_stack {
+            write!(f, " {}", operator)?;
+        }
+        write!(f, " | output:")?;
+        for token in &self.output {
+            write!(f, " {}", token)?;
+        }
+        Ok(())
+    }
+}
+
+/// Same as [`parse`](super::parse), but also returns the state of the operator stack and the output queue
+/// after each of the `tokens`, e.g. to show how the shunting yard algorithm works,
+/// or to find out why the custom operator is applied in the unexpected order.
+///
+/// The steps are returned even if the parsing fails, they end before the token that caused the error.
+///
+/// # Example
+///
+/// ```
+/// use rusty_yard::parser::parse_with_trace;
+/// use rusty_yard::tokenizer::tokenize;
+/// use rusty_yard::Ctx;
+///
+/// let ctx = Ctx::default();
+/// let (result, steps) = parse_with_trace(&tokenize("1 + 2 * 3", &ctx), &ctx);
+/// assert!(result.is_ok());
+/// let steps: Vec<_> = steps.iter().map(|step| step.to_string()).collect();
+/// assert_eq!(steps, vec![
+///     "1 | stack: | output: 1",
+///     "+ | stack: + | output: 1",
+///     "2 | stack: + | output: 1 2",
+///     "* | stack: + * | output: 1 2",
+///     "3 | stack: + * | output: 1 2 3",
+///     "end | stack: | output: 1 2 3 * +",
+/// ]);
+/// ```
+pub fn parse_with_trace<'a, 'ctx>(
+    tokens: &[Token<'a, 'ctx>],
+    ctx: &'ctx Ctx,
+) -> (Result<Vec<ParserToken<'a, 'ctx>>, Error>, Vec<ParseStep>) {
+    let mut steps = Vec::new();
+    let result = parse_tracking(tokens.iter().cloned(), ctx, &mut 0, Some(&mut steps));
+    (result, steps)
+}
+
+/// Records the step of the `token` into the `trace`, if the parsing is traced
+pub(super) fn record(
+    trace: &mut Option<&mut Vec<ParseStep>>,
+    token: Option<String>,
+    operator_stack: &[OperatorStackValue],
+    output: &[ParserToken],
+) {
+    if let Some(trace) = trace {
+        trace.push(ParseStep::new(token, operator_stack, output));
+    }
+}
+
+/// Writes the input `token` as it is written in the input
+pub(super) fn describe_token(token: &Token) -> String {
+    match token {
+        Token::OpenParen => "(".to_owned(),
+        Token::ClosedParen => ")".to_owned(),
+        Token::Comma => ",".to_owned(),
+        Token::Id(id) => (*id).to_owned(),
+        Token::QuotedId(id) => quote_id(id).into_owned(),
+        Token::Num(num) => num.to_string(),
+        Token::Str(s) => format!("\"{}\"", s),
+        Token::BadToken(text, _) => (*text).to_owned(),
+        Token::ArgName(name) => format!("{} =", name),
+        Token::Macro(m) => m.text.to_owned(),
+    }
+}
+
+fn describe_operator(value: &OperatorStackValue) -> String {
+    match value {
+        OperatorStackValue::LeftParen => "(".to_owned(),
+        OperatorStackValue::BiOp(op) => op.token.clone(),
+        OperatorStackValue::UOp(op) => op.token.clone(),
+        OperatorStackValue::Func(func, n_args) => format!("{}/{}", func.token, n_args),
+        OperatorStackValue::Macro(m) => m.name().to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::tokenize;
+
+    #[test]
+    fn test_parse_with_trace() {
+        let ctx = Ctx::default();
+        let input_expected = &[
+            (
+                "max(1, -a)",
+                vec![
+                    "max | stack: max/0 | output:",
+                    "( | stack: max/0 ( | output:",
+                    "1 | stack: max/0 ( | output: 1",
+                    ", | stack: max/1 ( | output: 1",
+                    "- | stack: max/1 ( - | output: 1",
+                    "a | stack: max/1 ( - | output: 1 a",
+                    ") | stack: | output: 1 a - max/2",
+                    "end | stack: | output: 1 a - max/2",
+                ],
+            ),
+            (
+                "2 ^ 3 ^ 2",
+                vec![
+                    "2 | stack: | output: 2",
+                    "^ | stack: ^ | output: 2",
+                    "3 | stack: ^ | output: 2 3",
+                    "^ | stack: ^ ^ | output: 2 3",
+                    "2 | stack: ^ ^ | output: 2 3 2",
+                    "end | stack: | output: 2 3 2 ^ ^",
+                ],
+            ),
+            (
+                "1 + * 2",
+                vec!["1 | stack: | output: 1", "+ | stack: + | output: 1"],
+            ),
+        ];
+        for (input, expected) in input_expected {
+            let tokens = tokenize(input, &ctx);
+            let (result, steps) = parse_with_trace(&tokens, &ctx);
+            assert_eq!(result.is_ok(), input != &"1 + * 2", "input was {}", input);
+            let steps: Vec<_> = steps.iter().map(ParseStep::to_string).collect();
+            assert_eq!(steps, *expected, "input was {}", input);
+        }
+        let tokens = tokenize("`unit price` * 2", &ctx);
+        let (_, steps) = parse_with_trace(&tokens, &ctx);
+        assert_eq!(steps[0].token.as_deref(), Some("`unit price`"));
+    }
+}
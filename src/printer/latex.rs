@@ -0,0 +1,378 @@
+use std::collections::HashMap;
+
+use super::{needs_parens, Error, Side};
+use crate::parser::{Ast, ParserToken};
+
+/// The names that LaTeX writes upright with the `\name` command, e.g. `\sin`.
+const COMMANDS: &[&str] = &[
+    "arccos", "arcsin", "arctan", "cos", "cosh", "cot", "coth", "csc", "det", "exp", "gcd", "lg",
+    "ln", "log", "max", "min", "sec", "sin", "sinh", "tan", "tanh",
+];
+
+/// The names of the variables that are written as the greek letters, e.g. `\alpha`.
+const GREEK: &[&str] = &[
+    "alpha", "beta", "gamma", "delta", "epsilon", "zeta", "eta", "theta", "iota", "kappa",
+    "lambda", "mu", "nu", "xi", "pi", "rho", "sigma", "tau", "upsilon", "phi", "chi", "psi",
+    "omega", "Gamma", "Delta", "Theta", "Lambda", "Xi", "Pi", "Sigma", "Upsilon", "Phi", "Psi",
+    "Omega",
+];
+
+/// Writes the parsed expressions as the LaTeX math, e.g. `\frac{a + 1}{2} \cdot \sqrt{x}`.
+///
+/// The operators and the functions are written with the templates, where `#1`, `#2` and so on are replaced with the operands,
+/// e.g. `\frac{#1}{#2}` for `/`. The templates are looked up by the token, so they can be replaced or added for the custom
+/// operators and functions. The operand is put into `\left(` and `\right)` if the [precedence](crate::operators::BiOp::precedence)
+/// of the operators requires it, same as in [`to_infix`](super::to_infix), unless its placeholder is in braces, like `{#1}`.
+/// The arguments of the functions are never put in parentheses.
+///
+/// The operators without the template are written with their token, the functions without the template as
+/// `\operatorname{name}\left(a, b\right)`, or `\sin\left(x\right)` for the names LaTeX knows.
+/// The function template is only used when the function has all the arguments of the template, otherwise it is written as the others.
+///
+/// # Example
+///
+/// ```
+/// use rusty_yard::parser::parse_str;
+/// use rusty_yard::printer::{to_latex, Latex};
+/// use rusty_yard::Ctx;
+///
+/// let ctx = Ctx::default();
+/// let tokens = parse_str("(a + 1) / 2 * sqrt(x) ^ 2", &ctx).unwrap();
+/// assert_eq!(to_latex(&tokens).unwrap(), r"\frac{a + 1}{2} \cdot \sqrt{x}^{2}");
+///
+/// let mut latex = Latex::default();
+/// latex.binary.insert("*".to_owned(), r"#1 \times #2".to_owned());
+/// assert_eq!(latex.render(&tokens).unwrap(), r"\frac{a + 1}{2} \times \sqrt{x}^{2}");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Latex {
+    /// The templates of the binary operators by their tokens.
+    pub binary: HashMap<String, String>,
+    /// The templates of the unary operators by their tokens, both prefix and postfix.
+    pub unary: HashMap<String, String>,
+    /// The templates of the functions by their tokens.
+    pub functions: HashMap<String, String>,
+}
+
+impl Default for Latex {
+    /// The templates of the [default operators](crate::operators) and [functions](crate::functions::default_functions).
+    fn default() -> Self {
+        let templates = |templates: &[(&str, &str)]| {
+            templates
+                .iter()
+                .map(|(token, template)| ((*token).to_owned(), (*template).to_owned()))
+                .collect()
+        };
+        Latex {
+            binary: templates(&[
+                ("+", "#1 + #2"),
+                ("-", "#1 - #2"),
+                ("*", r"#1 \cdot #2"),
+                ("/", r"\frac{#1}{#2}"),
+                ("%", r"#1 \bmod #2"),
+                ("//", r"\left\lfloor \frac{#1}{#2} \right\rfloor"),
+                ("^", "#1^{#2}"),
+                ("[]", "#1_{#2}"),
+                ("==", "#1 = #2"),
+                ("!=", r"#1 \neq #2"),
+                ("<", "#1 < #2"),
+                ("<=", r"#1 \leq #2"),
+                (">", "#1 > #2"),
+                (">=", r"#1 \geq #2"),
+                ("&&", r"#1 \land #2"),
+                ("||", r"#1 \lor #2"),
+            ]),
+            unary: templates(&[
+                ("-", "-#1"),
+                ("+", "+#1"),
+                ("!", r"\lnot #1"),
+                ("%", r"#1\%"),
+            ]),
+            functions: templates(&[
+                ("sqrt", r"\sqrt{#1}"),
+                ("cbrt", r"\sqrt[3]{#1}"),
+                ("abs", r"\left|#1\right|"),
+                ("floor", r"\left\lfloor #1 \right\rfloor"),
+                ("ceil", r"\left\lceil #1 \right\rceil"),
+                ("asin", r"\arcsin\left(#1\right)"),
+                ("acos", r"\arccos\left(#1\right)"),
+                ("atan", r"\arctan\left(#1\right)"),
+                ("log", r"\log_{#1}\left(#2\right)"),
+                ("log10", r"\log_{10}\left(#1\right)"),
+                ("log2", r"\log_{2}\left(#1\right)"),
+            ]),
+        }
+    }
+}
+
+impl Latex {
+    /// Writes the expression in reverse polish notation `tokens` as the LaTeX math.
+    ///
+    /// The macros that [assign](crate::macros::ParsedMacro::assigns) the variable are written as `id = expression`,
+    /// other macros can't be written.
+    pub fn render(&self, tokens: &[ParserToken]) -> Result<String, Error> {
+        let ast = Ast::from_rpn(tokens).ok_or(Error::IllFormed)?;
+        let mut output = String::new();
+        self.write_node(&mut output, &ast)?;
+        Ok(output)
+    }
+
+    fn write_node(&self, out: &mut String, ast: &Ast) -> Result<(), Error> {
+        match *ast.token {
+            ParserToken::Num(n) if n.is_nan() => out.push_str(r"\mathrm{NaN}"),
+            ParserToken::Num(n) if n.is_infinite() => {
+                out.push_str(if n < 0.0 { r"-\infty" } else { r"\infty" })
+            }
+            ParserToken::Num(n) => out.push_str(&n.to_string()),
+            ParserToken::Id(id) => write_id(out, id),
+            ParserToken::Str(s) => {
+                out.push_str(r#"\text{""#);
+                out.push_str(&escape(s));
+                out.push_str(r#""}"#);
+            }
+            ParserToken::BiOp(op) => {
+                let default = format!("#1 {} #2", escape(&op.token));
+                let template = self.binary.get(&op.token).unwrap_or(&default);
+                self.write_template(out, template, ast, |i| match i {
+                    0 => Some(Side::Left(op)),
+                    _ => Some(Side::Right(op)),
+                })?;
+            }
+            ParserToken::UOp(op) => {
+                let default = if op.postfix {
+                    format!("#1{}", escape(&op.token))
+                } else {
+                    format!("{}#1", escape(&op.token))
+                };
+                let template = self.unary.get(&op.token).unwrap_or(&default);
+                let side = if op.postfix {
+                    Side::Postfix
+                } else {
+                    Side::Unary(op)
+                };
+                self.write_template(out, template, ast, |_| Some(side))?;
+            }
+            ParserToken::Func(func, n_args) => match self.functions.get(&func.token) {
+                Some(template) if max_placeholder(template) <= n_args => {
+                    self.write_template(out, template, ast, |_| None)?;
+                }
+                _ => {
+                    if COMMANDS.contains(&func.token.as_str()) {
+                        out.push('\\');
+                        out.push_str(&func.token);
+                    } else {
+                        out.push_str(r"\operatorname{");
+                        out.push_str(&escape(&func.token));
+                        out.push('}');
+                    }
+                    out.push_str(r"\left(");
+                    for (i, arg) in ast.operands.iter().enumerate() {
+                        if i != 0 {
+                            out.push_str(", ");
+                        }
+                        self.write_node(out, arg)?;
+                    }
+                    out.push_str(r"\right)");
+                }
+            },
+            ParserToken::Macro(ref m) => match m.assigns() {
+                Some(id) if ast.operands.len() == 1 => {
+                    write_id(out, id);
+                    out.push_str(" = ");
+                    self.write_node(out, &ast.operands[0])?;
+                }
+                _ => return Err(Error::Macro(m.name().to_owned())),
+            },
+        }
+        Ok(())
+    }
+
+    /// Writes the `template` with the operands of the `ast` in place of the placeholders,
+    /// `side` returns how the operand with the index is bound to the parent, `None` if it never needs the parentheses
+    fn write_template<'ctx>(
+        &self,
+        out: &mut String,
+        template: &str,
+        ast: &Ast<'_, '_, 'ctx>,
+        side: impl Fn(usize) -> Option<Side<'ctx>>,
+    ) -> Result<(), Error> {
+        let mut rest = template;
+        while let Some(start) = rest.find('#') {
+            let index = rest[start + 1..]
+                .chars()
+                .next()
+                .and_then(|ch| ch.to_digit(10))
+                .filter(|&digit| digit != 0);
+            let index = match index {
+                Some(index) => index as usize - 1,
+                None => {
+                    out.push_str(&rest[..=start]);
+                    rest = &rest[start + 1..];
+                    continue;
+                }
+            };
+            let operand = ast.operands.get(index).ok_or(Error::IllFormed)?;
+            out.push_str(&rest[..start]);
+            rest = &rest[start + 2..];
+            let braced = out.ends_with('{') && rest.starts_with('}');
+            // the superscript and the subscript are only applied to the last symbol of the operand
+            let scripted = rest.starts_with('^') || rest.starts_with('_');
+            let parens = match side(index) {
+                Some(_) if braced => false,
+                Some(side) => needs_parens(operand, side) || (scripted && !is_atom(operand)),
+                None => false,
+            };
+            if parens {
+                out.push_str(r"\left(");
+                self.write_node(out, operand)?;
+                out.push_str(r"\right)");
+            } else {
+                self.write_node(out, operand)?;
+            }
+        }
+        out.push_str(rest);
+        Ok(())
+    }
+}
+
+/// Writes the expression in reverse polish notation `tokens` as the LaTeX math with the [default templates](Latex::default).
+///
+/// See [`Latex`](Latex) for the details.
+pub fn to_latex(tokens: &[ParserToken]) -> Result<String, Error> {
+    Latex::default().render(tokens)
+}
+
+/// The largest placeholder of the template, e.g. 2 for `\log_{#1}\left(#2\right)`
+fn max_placeholder(template: &str) -> usize {
+    template
+        .split('#')
+        .skip(1)
+        .filter_map(|part| part.chars().next()?.to_digit(10))
+        .max()
+        .unwrap_or(0) as usize
+}
+
+/// Checks that the superscript of the operand is applied to the whole operand
+fn is_atom(ast: &Ast) -> bool {
+    match ast.token {
+        ParserToken::Num(n) => *n >= 0.0,
+        ParserToken::Id(_) | ParserToken::Str(_) | ParserToken::Func(_, _) => true,
+        _ => false,
+    }
+}
+
+/// Writes the single letter variables as is, the greek letters as the commands, and the others in italics
+fn write_id(out: &mut String, id: &str) {
+    let mut chars = id.chars();
+    match (chars.next(), chars.next()) {
+        (Some(ch), None) if ch.is_ascii_alphabetic() => out.push(ch),
+        _ if GREEK.contains(&id) => {
+            out.push('\\');
+            out.push_str(id);
+        }
+        _ => {
+            out.push_str(r"\mathit{");
+            out.push_str(&escape(id));
+            out.push('}');
+        }
+    }
+}
+
+/// Escapes the characters that have a special meaning in LaTeX
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '#' | '$' | '%' | '&' | '_' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(ch);
+            }
+            '\\' => escaped.push_str(r"\backslash "),
+            '^' => escaped.push_str(r"\hat{}"),
+            '~' => escaped.push_str(r"\sim "),
+            ' ' => escaped.push_str(r"\ "),
+            ch => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::functions::Func;
+    use crate::parser::parse_str;
+    use crate::Ctx;
+
+    #[test]
+    fn test_to_latex() {
+        let input_expected = &[
+            ("1 + 2 * 3", r"1 + 2 \cdot 3"),
+            ("(1 + 2) * 3", r"\left(1 + 2\right) \cdot 3"),
+            ("(a + b) / (c - 1)", r"\frac{a + b}{c - 1}"),
+            ("2 ^ 3 ^ 2", "2^{3^{2}}"),
+            ("(2 ^ 3) ^ 2", r"\left(2^{3}\right)^{2}"),
+            ("(a + 1) ^ 2", r"\left(a + 1\right)^{2}"),
+            ("(-a) ^ 2", r"\left(-a\right)^{2}"),
+            ("a - (b - c)", r"a - \left(b - c\right)"),
+            ("10 // 3", r"\left\lfloor \frac{10}{3} \right\rfloor"),
+            ("a <= b && !c", r"a \leq b \land \lnot c"),
+            ("50%", r"50\%"),
+            (
+                "sin(x) ^ 2 + cos(x) ^ 2",
+                r"\sin\left(x\right)^{2} + \cos\left(x\right)^{2}",
+            ),
+            ("sqrt(a ^ 2 + b ^ 2)", r"\sqrt{a^{2} + b^{2}}"),
+            ("abs(x - 1)", r"\left|x - 1\right|"),
+            ("log(2, 8)", r"\log_{2}\left(8\right)"),
+            ("max(1, 2 + 3)", r"\max\left(1, 2 + 3\right)"),
+            ("hypot(3, 4)", r"\operatorname{hypot}\left(3, 4\right)"),
+            ("2 * pi * alpha", r"2 \cdot 3.141592653589793 \cdot \alpha"),
+            (
+                "`unit price` * total_1",
+                r"\mathit{unit\ price} \cdot \mathit{total\_1}",
+            ),
+            ("x = 1 / 0", r"x = \frac{1}{0}"),
+        ];
+        let ctx = Ctx::default_with_macros();
+        for (input, expected) in input_expected {
+            let tokens = parse_str(input, &ctx).unwrap();
+            assert_eq!(to_latex(&tokens).unwrap(), *expected, "input was {}", input);
+        }
+    }
+
+    #[test]
+    fn test_render_templates() {
+        let mut ctx = Ctx::default();
+        ctx.fns
+            .push(Func::new("norm", None, |args| args.iter().sum()));
+        let mut latex = Latex::default();
+        latex
+            .functions
+            .insert("norm".to_owned(), r"\lVert #1 \rVert_{#2}".to_owned());
+        latex.binary.insert("*".to_owned(), "#1 #2".to_owned());
+        let input_expected = &[
+            ("2 * norm(v, 2)", r"2 \lVert v \rVert_{2}"),
+            // the template needs two arguments
+            ("norm(v)", r"\operatorname{norm}\left(v\right)"),
+            ("(a + b) * c", r"\left(a + b\right) c"),
+        ];
+        for (input, expected) in input_expected {
+            let tokens = parse_str(input, &ctx).unwrap();
+            assert_eq!(
+                latex.render(&tokens).unwrap(),
+                *expected,
+                "input was {}",
+                input
+            );
+        }
+        let tokens = vec![
+            ParserToken::Num(f64::INFINITY),
+            ParserToken::Num(f64::NAN),
+            ParserToken::BiOp(&crate::operators::binary::MINUS),
+        ];
+        assert_eq!(to_latex(&tokens).unwrap(), r"\infty - \mathrm{NaN}");
+        let tokens = vec![ParserToken::Num(1.0), ParserToken::Num(2.0)];
+        assert_eq!(to_latex(&tokens), Err(Error::IllFormed));
+    }
+}
@@ -0,0 +1,370 @@
+//! Converts the parsed expressions back to the infix notation.
+//!
+//! The main function in this module is [`to_infix`](to_infix).
+//! It writes the canonical form of the expression: the operators are separated by spaces,
+//! the arguments of the functions by `", "` and the parentheses are only written where
+//! the [precedence](crate::operators::BiOp::precedence) and the [associativity](crate::operators::BiOp::associativity)
+//! of the operators require them.
+//!
+//! # Example
+//!
+//! ```
+//! use rusty_yard::parser::parse_str;
+//! use rusty_yard::printer::to_infix;
+//! use rusty_yard::Ctx;
+//!
+//! let ctx = Ctx::default_with_macros();
+//! let tokens = parse_str("x=((a+b))*(c)-max( 1,(2) )", &ctx).unwrap();
+//! assert_eq!(to_infix(&tokens).unwrap(), "x = (a + b) * c - max(1, 2)");
+//! ```
+//!
+//! [`to_latex`](to_latex) writes the expression as the LaTeX math in the same way, see [`Latex`](Latex).
+use std::fmt::{self, Write};
+
+use thiserror::Error;
+
+use crate::operators::binary::Associativity;
+use crate::operators::{BiOp, UOp};
+use crate::parser::{Ast, ParserToken};
+use crate::tokenizer::{quote_id, INDEX_OPERATOR};
+
+pub use latex::{to_latex, Latex};
+
+mod latex;
+
+/// Represents the error that can occur when the token stream is converted to the infix notation.
+#[derive(Debug, Error, Clone, Eq, PartialEq)]
+pub enum Error {
+    /// The token stream contains a macro that is not an assignment, contains the name of the macro.
+    #[error("Macro {0} can't be printed")]
+    Macro(String),
+    /// The token stream is not a single expression.
+    #[error("Ill formed token stream")]
+    IllFormed,
+}
+
+/// Writes the expression in reverse polish notation `tokens` in the infix notation with the minimal parentheses.
+///
+/// The macros that [assign](crate::macros::ParsedMacro::assigns) the variable are written as `id = expression`,
+/// other macros can't be printed.
+pub fn to_infix(tokens: &[ParserToken]) -> Result<String, Error> {
+    let ast = Ast::from_rpn(tokens).ok_or(Error::IllFormed)?;
+    check_macros(&ast)?;
+    let mut output = String::new();
+    // unwrap: writing to String never fails
+    write_node(&mut output, &ast).unwrap();
+    Ok(output)
+}
+
+fn check_macros(ast: &Ast) -> Result<(), Error> {
+    if let ParserToken::Macro(m) = ast.token {
+        if m.assigns().is_none() || ast.operands.len() != 1 {
+            return Err(Error::Macro(m.name().to_owned()));
+        }
+    }
+    ast.operands.iter().try_for_each(check_macros)
+}
+
+/// How the operand is bound to its parent
+#[derive(Clone, Copy)]
+enum Side<'ctx> {
+    Left(&'ctx BiOp),
+    Right(&'ctx BiOp),
+    Unary(&'ctx UOp),
+    Postfix,
+}
+
+fn needs_parens(operand: &Ast, side: Side) -> bool {
+    match (operand.token, side) {
+        (ParserToken::Macro(_), _) => true,
+        // the postfix operators are applied first, so only the other operators need the parentheses
+        (ParserToken::UOp(op), _) if op.postfix => false,
+        // the index is applied as soon as its bracket is closed
+        (ParserToken::BiOp(op), Side::Postfix) if op.token == INDEX_OPERATOR => false,
+        (ParserToken::BiOp(_), Side::Postfix) | (ParserToken::UOp(_), Side::Postfix) => true,
+        // the operand of the unary operator extends over the binary operators with the higher precedence
+        (ParserToken::BiOp(op), Side::Unary(parent)) => parent
+            .precedence
+            .is_none_or(|precedence| op.precedence <= precedence),
+        // the operand of the unary operator with the lower precedence would extend over the parent
+        (ParserToken::UOp(op), Side::Left(parent))
+        | (ParserToken::UOp(op), Side::Right(parent)) => op
+            .precedence
+            .is_some_and(|precedence| precedence < parent.precedence),
+        (ParserToken::UOp(op), Side::Unary(parent)) => match (op.precedence, parent.precedence) {
+            (Some(precedence), Some(parent)) => precedence < parent,
+            (Some(_), None) => true,
+            (None, _) => false,
+        },
+        // same as the parser: the left operator is applied first
+        // if its precedence is higher, or it is the same and the left operator is left associative
+        (ParserToken::BiOp(op), Side::Left(parent)) => {
+            op.precedence < parent.precedence
+                || (op.precedence == parent.precedence && op.associativity != Associativity::LEFT)
+        }
+        (ParserToken::BiOp(op), Side::Right(parent)) => {
+            op.precedence < parent.precedence
+                || (op.precedence == parent.precedence
+                    && parent.associativity != Associativity::RIGHT)
+        }
+        _ => false,
+    }
+}
+
+fn write_operand(out: &mut String, operand: &Ast, side: Side) -> fmt::Result {
+    if needs_parens(operand, side) {
+        out.push('(');
+        write_node(out, operand)?;
+        out.push(')');
+        Ok(())
+    } else {
+        write_node(out, operand)
+    }
+}
+
+fn write_node(out: &mut String, ast: &Ast) -> fmt::Result {
+    match *ast.token {
+        ParserToken::Num(n) if n.is_nan() => write!(out, "nan"),
+        ParserToken::Num(n) if n.is_infinite() => {
+            write!(out, "{}inf", if n < 0.0 { "-" } else { "" })
+        }
+        ParserToken::Num(n) => write!(out, "{}", n),
+        ParserToken::Id(id) => write!(out, "{}", quote_id(id)),
+        ParserToken::Str(s) => write!(out, "\"{}\"", s),
+        ParserToken::UOp(op) if op.postfix => {
+            write_operand(out, &ast.operands[0], Side::Postfix)?;
+            write!(out, "{}", op.token)
+        }
+        ParserToken::UOp(op) => {
+            write!(out, "{}", op.token)?;
+            write_operand(out, &ast.operands[0], Side::Unary(op))
+        }
+        ParserToken::BiOp(op) if op.token == INDEX_OPERATOR => {
+            let indexed = &ast.operands[0];
+            // only the identifiers and the closing parens are followed by the index,
+            // the prefix operators without the precedence are applied before it
+            match indexed.token {
+                ParserToken::Id(_) | ParserToken::Func(..) => write_node(out, indexed)?,
+                ParserToken::BiOp(inner) if inner.token == INDEX_OPERATOR => {
+                    write_node(out, indexed)?
+                }
+                ParserToken::UOp(inner) if !inner.postfix && inner.precedence.is_none() => {
+                    write_node(out, indexed)?
+                }
+                _ => {
+                    out.push('(');
+                    write_node(out, indexed)?;
+                    out.push(')');
+                }
+            }
+            write!(out, "[")?;
+            write_node(out, &ast.operands[1])?;
+            write!(out, "]")
+        }
+        ParserToken::BiOp(op) => {
+            write_operand(out, &ast.operands[0], Side::Left(op))?;
+            write!(out, " {} ", op.token)?;
+            write_operand(out, &ast.operands[1], Side::Right(op))
+        }
+        ParserToken::Func(func, _) => {
+            write!(out, "{}(", func.token)?;
+            for (i, arg) in ast.operands.iter().enumerate() {
+                if i != 0 {
+                    write!(out, ", ")?;
+                }
+                write_node(out, arg)?;
+            }
+            write!(out, ")")
+        }
+        ParserToken::Macro(ref m) => {
+            // checked by `check_macros`
+            let id = m.assigns().expect("only assignments are printed");
+            write!(out, "{} = ", quote_id(id))?;
+            write_node(out, &ast.operands[0])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operators::{binary, unary};
+    use crate::parser::parse_str;
+    use crate::Ctx;
+
+    #[test]
+    fn test_to_infix() {
+        let input_expected = &[
+            ("1", "1"),
+            ("((a))", "a"),
+            ("1+2*3", "1 + 2 * 3"),
+            ("(1 + 2) * 3", "(1 + 2) * 3"),
+            ("1 - (2 - 3)", "1 - (2 - 3)"),
+            ("(1 - 2) - 3", "1 - 2 - 3"),
+            ("2 ^ (3 ^ 2)", "2 ^ 3 ^ 2"),
+            ("(2 ^ 3) ^ 2", "(2 ^ 3) ^ 2"),
+            ("-(a + b)", "-(a + b)"),
+            ("(-a) ^ 2", "-a ^ 2"),
+            ("a * -b", "a * -b"),
+            ("max((a), sum(1, 2 + 3))", "max(a, sum(1, 2 + 3))"),
+            ("prod()", "prod()"),
+            ("`unit price` * 2", "`unit price` * 2"),
+            ("a = b = (1 + 2)", "a = b = 1 + 2"),
+            ("(a = 1) + 2", "(a = 1) + 2"),
+            ("((10)%) * 2", "10% * 2"),
+            ("-(a%)", "-a%"),
+            ("(-a)%", "(-a)%"),
+            ("(a + 1)%", "(a + 1)%"),
+        ];
+        let ctx = Ctx::default_with_macros();
+        for (input, expected) in input_expected {
+            let tokens = parse_str(input, &ctx).unwrap();
+            let infix = to_infix(&tokens).unwrap();
+            assert_eq!(infix, *expected, "input was {}", input);
+            // the printed expression is parsed back into the same tokens
+            assert_eq!(
+                parse_str(&infix, &ctx).unwrap(),
+                tokens,
+                "input was {}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_to_infix_same_precedence() {
+        // `+` is left associative, `-+` is right associative with the same precedence
+        let mut ctx = Ctx::default();
+        ctx.bi_ops.push(BiOp {
+            token: "-+".to_owned(),
+            associativity: Associativity::RIGHT,
+            ..binary::PLUS.clone()
+        });
+        let inputs = &[
+            "a + b -+ c",
+            "(a + b) -+ c",
+            "a + (b -+ c)",
+            "a -+ b + c",
+            "(a -+ b) + c",
+            "a -+ (b + c)",
+        ];
+        for input in inputs {
+            let tokens = parse_str(input, &ctx).unwrap();
+            let infix = to_infix(&tokens).unwrap();
+            assert_eq!(
+                parse_str(&infix, &ctx).unwrap(),
+                tokens,
+                "input was {}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_to_infix_unary_precedence() {
+        // `-` binds looser than `^`, `~` binds looser than `+`
+        let mut ctx = Ctx::default();
+        ctx.u_ops.retain(|op| op.token != "-");
+        ctx.u_ops.push(UOp {
+            precedence: Some(5),
+            ..unary::NEGATE.clone()
+        });
+        ctx.u_ops.push(UOp {
+            token: "~".to_owned(),
+            precedence: Some(3),
+            ..unary::NEGATE.clone()
+        });
+        let input_expected = &[
+            ("-a ^ 2", "-a ^ 2"),
+            ("(-a) ^ 2", "(-a) ^ 2"),
+            ("-(a * b)", "-(a * b)"),
+            ("-a * b", "-a * b"),
+            ("a * (~b) + c", "a * (~b) + c"),
+            ("a * ~b + c", "a * (~b + c)"),
+            ("(-~a) * b", "-(~a) * b"),
+            ("-(~a * b)", "-(~a * b)"),
+            ("!(-a ^ 2)", "!(-a ^ 2)"),
+        ];
+        for (input, expected) in input_expected {
+            let tokens = parse_str(input, &ctx).unwrap();
+            let infix = to_infix(&tokens).unwrap();
+            assert_eq!(infix, *expected, "input was {}", input);
+            assert_eq!(
+                parse_str(&infix, &ctx).unwrap(),
+                tokens,
+                "input was {}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_to_infix_special_numbers() {
+        let ctx = Ctx::default();
+        let tokens = vec![
+            ParserToken::Num(f64::NAN),
+            ParserToken::Num(f64::NEG_INFINITY),
+            ParserToken::BiOp(&binary::PLUS),
+        ];
+        assert_eq!(to_infix(&tokens).unwrap(), "nan + -inf");
+        let parsed = parse_str("nan + -inf", &ctx).unwrap();
+        assert_eq!(parsed.len(), 4);
+    }
+
+    #[test]
+    fn test_to_infix_aliases() {
+        let mut ctx = Ctx {
+            aliases: crate::operators::default_aliases(),
+            ..Ctx::default_with_macros()
+        };
+        ctx.aliases.push(("div".to_owned(), "//".to_owned()));
+        ctx.aliases.push(("maximum".to_owned(), "max".to_owned()));
+        // the aliases are printed as the tokens they stand for
+        let input_expected = &[
+            ("2 × 3 ** 2 ÷ a", "2 * 3 ^ 2 / a"),
+            ("2·x**-1", "2 * x ^ -1"),
+            ("7 div 2 + model", "7 // 2 + model"),
+            ("maximum(a, b)", "max(a, b)"),
+        ];
+        for (input, expected) in input_expected {
+            let tokens = parse_str(input, &ctx).unwrap();
+            assert_eq!(to_infix(&tokens).unwrap(), *expected, "input was {}", input);
+            assert_eq!(
+                parse_str(expected, &ctx).unwrap(),
+                tokens,
+                "input was {}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_to_infix_index() {
+        let ctx = crate::value::array::array_ctx();
+        let input_expected = &[
+            ("prices[i+1]*2", "prices[i + 1] * 2"),
+            ("m[i][j]", "m[i][j]"),
+            ("(a + b)[0]", "(a + b)[0]"),
+            ("[unit price][0]", "`unit price`[0]"),
+            ("-a[1]%", "-a[1]%"),
+            ("-(a + b)[0]", "-(a + b)[0]"),
+            ("(a%)[0]", "(a%)[0]"),
+        ];
+        for (input, expected) in input_expected {
+            let tokens = parse_str(input, &ctx).unwrap();
+            assert_eq!(to_infix(&tokens).unwrap(), *expected, "input was {}", input);
+            assert_eq!(
+                parse_str(expected, &ctx).unwrap(),
+                tokens,
+                "input was {}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_to_infix_errors() {
+        let tokens = vec![ParserToken::Num(1.0), ParserToken::Num(2.0)];
+        assert_eq!(to_infix(&tokens), Err(Error::IllFormed));
+    }
+}
@@ -0,0 +1,417 @@
+//! Provides [`Program`](Program), the set of named expressions that are evaluated together.
+//!
+//! The expressions can read the results of each other, like the formulas of a spreadsheet.
+//! They are evaluated in the order of their [`dependencies`](crate::analysis::dependencies),
+//! and after the inputs change only the affected expressions are evaluated again.
+//!
+//! # Example
+//!
+//! ```
+//! use std::collections::HashMap;
+//! use rusty_yard::program::Program;
+//! use rusty_yard::Ctx;
+//!
+//! let ctx = Ctx::default_with_macros();
+//! let mut program = Program::parse("cost = area * rate\narea = k * r ^ 2", &ctx).unwrap();
+//! assert_eq!(program.names().collect::<Vec<_>>(), vec!["area", "cost"]);
+//!
+//! let mut vars = HashMap::new();
+//! vars.insert("k".to_owned(), 3.0);
+//! vars.insert("r".to_owned(), 2.0);
+//! vars.insert("rate".to_owned(), 10.0);
+//! program.eval(&mut vars).unwrap();
+//! assert_eq!(program.value("cost"), Some(120.0));
+//!
+//! // only cost reads rate
+//! vars.insert("rate".to_owned(), 5.0);
+//! assert_eq!(program.update(&mut vars, &["rate"]), Ok(1));
+//! assert_eq!(vars["cost"], 60.0);
+//! ```
+use std::collections::BTreeSet;
+use std::mem;
+
+use thiserror::Error;
+
+use crate::analysis::{dependencies, variables_of};
+use crate::evaluator::{self, eval_with};
+use crate::macros::default::AssignParsed;
+use crate::parser::{self, parse_statements, parse_str, ParserToken, StatementError};
+use crate::variables::VariableResolver;
+use crate::Ctx;
+
+/// Represents the error that can occur when the [`Program`](Program) is built or evaluated.
+#[derive(Debug, Error, PartialEq)]
+pub enum Error {
+    /// The expression could not be parsed.
+    #[error("{name}: {error}")]
+    Parse {
+        /// The name of the expression.
+        name: String,
+        /// The error itself.
+        error: parser::Error,
+    },
+    /// Some statements of the input passed to [`Program::parse`](Program::parse) could not be parsed.
+    #[error("{} statements could not be parsed", .0.len())]
+    Statements(Vec<StatementError>),
+    /// The statement of the input passed to [`Program::parse`](Program::parse) is not an assignment.
+    #[error("Statement {statement} is not an assignment")]
+    NotAssignment {
+        /// Index of the statement.
+        statement: usize,
+    },
+    /// The expressions depend on each other, contains their names in the order they were defined.
+    #[error("Circular dependency between {0:?}")]
+    Cycle(Vec<String>),
+    /// The expression could not be evaluated.
+    #[error("{name}: {error}")]
+    Eval {
+        /// The name of the expression.
+        name: String,
+        /// The error itself.
+        error: evaluator::Error,
+    },
+}
+
+/// The set of named expressions that are evaluated together, see the [module](self) documentation.
+///
+/// Each expression assigns its result to the variable with its name,
+/// so the other expressions and the caller read the result from the variables.
+#[derive(Debug)]
+pub struct Program<'a, 'ctx> {
+    ctx: &'ctx Ctx,
+    names: Vec<String>,
+    /// The expressions followed by the assignment to their names
+    statements: Vec<Vec<ParserToken<'a, 'ctx>>>,
+    reads: Vec<BTreeSet<String>>,
+    order: Vec<usize>,
+    values: Vec<Option<f64>>,
+    dirty: Vec<bool>,
+}
+
+impl<'a, 'ctx> Program<'a, 'ctx> {
+    /// Creates the program without expressions.
+    pub fn new(ctx: &'ctx Ctx) -> Self {
+        Self {
+            ctx,
+            names: Vec::new(),
+            statements: Vec::new(),
+            reads: Vec::new(),
+            order: Vec::new(),
+            values: Vec::new(),
+            dirty: Vec::new(),
+        }
+    }
+
+    /// Creates the program from multi-statement input where every statement is an assignment, e.g. `area = pi * r ^ 2`.
+    ///
+    /// The statements are split like by [`parse_statements`](crate::parser::parse_statements)
+    /// and the context needs the [`Assign`](crate::macros::default::Assign) macro.
+    /// If the name is assigned more than once, the last statement wins.
+    pub fn parse(input: &'a str, ctx: &'ctx Ctx) -> Result<Self, Error> {
+        let statements = parse_statements(input, ctx).map_err(Error::Statements)?;
+        let mut program = Self::new(ctx);
+        for (statement, tokens) in statements.into_iter().enumerate() {
+            let name = match tokens.last() {
+                Some(ParserToken::Macro(m)) => m.assigns().map(str::to_owned),
+                _ => None,
+            };
+            let name = name.ok_or(Error::NotAssignment { statement })?;
+            program.insert(name, tokens)?;
+        }
+        Ok(program)
+    }
+
+    /// Defines the expression `name` from the `input`, replacing the previous expression with this name.
+    ///
+    /// The program is left unchanged if the input can't be parsed or the expression makes a cycle.
+    pub fn define(&mut self, name: &'a str, input: &'a str) -> Result<(), Error> {
+        let mut tokens = parse_str(input, self.ctx).map_err(|error| Error::Parse {
+            name: name.to_owned(),
+            error,
+        })?;
+        tokens.push(ParserToken::Macro(Box::new(AssignParsed::new(name))));
+        self.insert(name.to_owned(), tokens)
+    }
+
+    /// Inserts the statement that assigns `name`
+    fn insert(&mut self, name: String, tokens: Vec<ParserToken<'a, 'ctx>>) -> Result<(), Error> {
+        let (index, old) = match self.names.iter().position(|n| *n == name) {
+            Some(index) => (
+                index,
+                Some(mem::replace(&mut self.statements[index], tokens)),
+            ),
+            None => {
+                self.names.push(name);
+                self.statements.push(tokens);
+                self.reads.push(BTreeSet::new());
+                self.values.push(None);
+                self.dirty.push(true);
+                (self.names.len() - 1, None)
+            }
+        };
+        match dependencies(&self.statements, self.ctx).order() {
+            Ok(order) => {
+                self.order = order;
+                self.reads[index] = variables_of(&self.statements[index], self.ctx);
+                self.values[index] = None;
+                self.dirty[index] = true;
+                Ok(())
+            }
+            Err(cycle) => {
+                let names = cycle
+                    .statements
+                    .iter()
+                    .map(|statement| self.names[*statement].clone())
+                    .collect();
+                match old {
+                    Some(old) => self.statements[index] = old,
+                    None => {
+                        self.names.pop();
+                        self.statements.pop();
+                        self.reads.pop();
+                        self.values.pop();
+                        self.dirty.pop();
+                    }
+                }
+                Err(Error::Cycle(names))
+            }
+        }
+    }
+
+    /// The names of the expressions in the order they are evaluated.
+    pub fn names(&self) -> impl Iterator<Item = &str> + '_ {
+        self.order
+            .iter()
+            .map(move |index| self.names[*index].as_str())
+    }
+
+    /// The variables that are read but are not the names of the expressions, they have to be provided by the caller.
+    pub fn inputs(&self) -> BTreeSet<String> {
+        dependencies(&self.statements, self.ctx).inputs
+    }
+
+    /// The last result of the expression `name`.
+    ///
+    /// Returns [`None`](std::option::Option::None) if there is no such expression
+    /// or it was not evaluated since it was defined.
+    pub fn value(&self, name: &str) -> Option<f64> {
+        let index = self.names.iter().position(|n| n == name)?;
+        self.values[index]
+    }
+
+    /// Evaluates every expression with the `variables` and assigns the results to them.
+    ///
+    /// The evaluation stops at the first error, the expressions that are not evaluated
+    /// are evaluated by the next call to [`update`](Program::update).
+    pub fn eval(&mut self, variables: &mut dyn VariableResolver) -> Result<(), Error> {
+        self.dirty.iter_mut().for_each(|dirty| *dirty = true);
+        self.run(variables).map(|_| ())
+    }
+
+    /// Evaluates the expressions affected by the change of the variables `changed`, returns the number of evaluated expressions.
+    ///
+    /// The expression is evaluated if it reads one of the changed variables,
+    /// if the result of the expression it reads has changed, or if it was not evaluated since it was defined.
+    /// The `variables` should be the same as in the previous evaluations, because they hold the results of the expressions.
+    pub fn update(
+        &mut self,
+        variables: &mut dyn VariableResolver,
+        changed: &[&str],
+    ) -> Result<usize, Error> {
+        for var in changed {
+            mark_readers(&self.reads, &mut self.dirty, var);
+        }
+        self.run(variables)
+    }
+
+    fn run(&mut self, variables: &mut dyn VariableResolver) -> Result<usize, Error> {
+        let mut evaluated = 0;
+        for order in 0..self.order.len() {
+            let index = self.order[order];
+            if !self.dirty[index] {
+                continue;
+            }
+            let value =
+                eval_with(&self.statements[index], variables, self.ctx).map_err(|error| {
+                    Error::Eval {
+                        name: self.names[index].clone(),
+                        error,
+                    }
+                })?;
+            evaluated += 1;
+            self.dirty[index] = false;
+            if self.values[index] != Some(value) {
+                self.values[index] = Some(value);
+                mark_readers(&self.reads, &mut self.dirty, &self.names[index]);
+            }
+        }
+        Ok(evaluated)
+    }
+}
+
+/// Marks the expressions that read `var` to be evaluated
+fn mark_readers(reads: &[BTreeSet<String>], dirty: &mut [bool], var: &str) {
+    for (reads, dirty) in reads.iter().zip(dirty) {
+        if reads.contains(var) {
+            *dirty = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let input_expected: &[(&str, &[&str])] = &[
+            ("", &[]),
+            ("b = a * 2; a = 1; c = b + a", &["a", "b", "c"]),
+            ("a = 1; a = b; b = 2", &["b", "a"]),
+        ];
+        let ctx = Ctx::default_with_macros();
+        for (input, expected) in input_expected {
+            let program = Program::parse(input, &ctx).unwrap();
+            assert_eq!(
+                program.names().collect::<Vec<_>>(),
+                *expected,
+                "input was {}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_err() {
+        let input_expected = &[
+            ("a = 1; 2 + a", Error::NotAssignment { statement: 1 }),
+            (
+                "a = b; b = c; c = a",
+                Error::Cycle(vec!["a".to_owned(), "b".to_owned(), "c".to_owned()]),
+            ),
+        ];
+        let ctx = Ctx::default_with_macros();
+        for (input, expected) in input_expected {
+            let result = Program::parse(input, &ctx);
+            assert_eq!(result.err().as_ref(), Some(expected), "input was {}", input);
+        }
+        let result = Program::parse("a = 1 +", &ctx);
+        assert!(matches!(result, Err(Error::Statements(errors)) if errors.len() == 1));
+    }
+
+    #[test]
+    fn test_define() {
+        let ctx = Ctx::default();
+        let mut program = Program::new(&ctx);
+        program.define("total", "net + tax").unwrap();
+        program.define("tax", "net * rate").unwrap();
+        assert_eq!(program.names().collect::<Vec<_>>(), vec!["tax", "total"]);
+        assert_eq!(
+            program.inputs().into_iter().collect::<Vec<_>>(),
+            vec!["net", "rate"]
+        );
+
+        let result = program.define("net", "total - tax");
+        assert_eq!(
+            result,
+            Err(Error::Cycle(vec![
+                "total".to_owned(),
+                "tax".to_owned(),
+                "net".to_owned()
+            ]))
+        );
+        let result = program.define("tax", "total * rate");
+        assert_eq!(
+            result,
+            Err(Error::Cycle(vec!["total".to_owned(), "tax".to_owned()]))
+        );
+        assert!(matches!(
+            program.define("tax", "net *"),
+            Err(Error::Parse { .. })
+        ));
+        // the failed definitions are not applied
+        assert_eq!(program.names().collect::<Vec<_>>(), vec!["tax", "total"]);
+
+        let mut vars = HashMap::new();
+        vars.insert("net".to_owned(), 100.0);
+        assert_eq!(
+            program.eval(&mut vars),
+            Err(Error::Eval {
+                name: "tax".to_owned(),
+                error: evaluator::Error::VarNotFound("rate".to_owned(), None)
+            })
+        );
+        vars.insert("rate".to_owned(), 0.25);
+        assert_eq!(program.update(&mut vars, &[]), Ok(2));
+        assert_eq!(program.value("total"), Some(125.0));
+        assert_eq!(program.value("net"), None);
+    }
+
+    #[test]
+    fn test_update() {
+        let ctx = Ctx::default_with_macros();
+        let input = "a = x + 1; b = y * 0; c = a + b; d = c * 2";
+        let mut program = Program::parse(input, &ctx).unwrap();
+        let mut vars = HashMap::new();
+        vars.insert("x".to_owned(), 1.0);
+        vars.insert("y".to_owned(), 1.0);
+        program.eval(&mut vars).unwrap();
+        assert_eq!(vars["d"], 4.0);
+
+        let input_expected: &[(&str, f64, usize, f64)] = &[
+            // nothing reads z
+            ("z", 1.0, 0, 4.0),
+            ("x", 2.0, 3, 6.0),
+            // b does not change, so c and d are not evaluated
+            ("y", 5.0, 1, 6.0),
+            // a is evaluated, but its result is the same
+            ("x", 2.0, 1, 6.0),
+        ];
+        for (var, value, evaluated, d) in input_expected {
+            vars.insert((*var).to_owned(), *value);
+            let result = program.update(&mut vars, &[var]);
+            assert_eq!(result, Ok(*evaluated), "input was {} = {}", var, value);
+            assert_eq!(
+                program.value("d"),
+                Some(*d),
+                "input was {} = {}",
+                var,
+                value
+            );
+        }
+
+        // the redefined expression is evaluated with its readers
+        program.define("b", "y").unwrap();
+        assert_eq!(program.update(&mut vars, &[]), Ok(3));
+        assert_eq!(vars["d"], 16.0);
+    }
+
+    #[test]
+    fn test_macros() {
+        let ctx = Ctx::default_with_macros();
+        let input =
+            "cost = if(area > 0, area * rate, 0)\narea = k * r\ntotal = let t = cost in t + fee";
+        let mut program = Program::parse(input, &ctx).unwrap();
+        assert_eq!(
+            program.names().collect::<Vec<_>>(),
+            vec!["area", "cost", "total"]
+        );
+        assert_eq!(
+            program.inputs().into_iter().collect::<Vec<_>>(),
+            vec!["fee", "k", "r", "rate"]
+        );
+        let mut vars = HashMap::new();
+        vars.insert("k".to_owned(), 2.0);
+        vars.insert("r".to_owned(), 3.0);
+        vars.insert("rate".to_owned(), 10.0);
+        vars.insert("fee".to_owned(), 1.0);
+        program.eval(&mut vars).unwrap();
+        assert_eq!(program.value("total"), Some(61.0));
+        // rate is read only in the branch of if
+        vars.insert("rate".to_owned(), 5.0);
+        assert_eq!(program.update(&mut vars, &["rate"]), Ok(2));
+        assert_eq!(program.value("total"), Some(31.0));
+    }
+}
@@ -0,0 +1,159 @@
+//! The pseudo random number generators.
+//!
+//! [`Rng`](Rng) is the seedable generator of the random functions of the expressions,
+//! the same generator is used by [`generate`](crate::generate) and [`monte_carlo`](crate::monte_carlo).
+//!
+//! # Example
+//!
+//! ```
+//! use rusty_yard::evaluator::eval_str_with_vars_and_ctx;
+//! use rusty_yard::random::Rng;
+//! use rusty_yard::Ctx;
+//! use std::collections::HashMap;
+//!
+//! let rng = Rng::new(42);
+//! let ctx = Ctx::default().with_random(&rng);
+//! let mut vars = HashMap::new();
+//! let first = eval_str_with_vars_and_ctx("rand_range(1, 6)", &mut vars, &ctx).unwrap();
+//! assert!((1.0..6.0).contains(&first));
+//! // the same seed produces the same numbers
+//! rng.seed(42);
+//! assert_eq!(eval_str_with_vars_and_ctx("rand_range(1, 6)", &mut vars, &ctx), Ok(first));
+//! ```
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::functions::Func;
+
+/// The seedable random number generator of the random functions, see [`functions`](Rng::functions).
+///
+/// The generator is the state of the evaluation and not the global one: the functions share it with the `Rng`
+/// they were created from, so the clones of the `Rng` can [`seed`](Rng::seed) them.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: Arc<Mutex<XorShift>>,
+}
+
+impl Rng {
+    /// Creates the generator, the same `seed` produces the same sequence of the numbers.
+    pub fn new(seed: u64) -> Self {
+        Rng {
+            state: Arc::new(Mutex::new(XorShift::new(seed))),
+        }
+    }
+
+    /// Restarts the sequence of the numbers of the generator and of its clones from the `seed`.
+    pub fn seed(&self, seed: u64) {
+        *self.lock() = XorShift::new(seed);
+    }
+
+    /// The functions that use the generator:
+    ///
+    /// - `rand()`, the number in `[0, 1)`;
+    /// - `rand_range(a, b)`, the number in `[a, b)`;
+    /// - `randn()`, the number from the standard normal distribution.
+    pub fn functions(&self) -> Vec<Func> {
+        let (rand, rand_range, randn) = (self.clone(), self.clone(), self.clone());
+        vec![
+            Func::new("rand", Some(0), move |_| rand.lock().unit()),
+            Func::new("rand_range", Some(2), move |args| {
+                args[0] + (args[1] - args[0]) * rand_range.lock().unit()
+            }),
+            Func::new("randn", Some(0), move |_| randn.lock().normal()),
+        ]
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, XorShift> {
+        // the state is always valid, even if the other thread panicked
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Default for Rng {
+    /// Creates the generator seeded from the current time.
+    fn default() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_nanos() as u64);
+        Rng::new(nanos)
+    }
+}
+
+/// xorshift64* pseudo random number generator
+#[derive(Debug, Clone)]
+pub(crate) struct XorShift(u64);
+
+impl XorShift {
+    pub(crate) fn new(seed: u64) -> Self {
+        // the state must not be 0
+        XorShift((seed ^ 0x9E37_79B9_7F4A_7C15).max(1))
+    }
+
+    pub(crate) fn next(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.0 = x;
+        x.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Returns the number in `0..n`, `n` must not be 0
+    pub(crate) fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    /// Returns the number in `[0, 1)`
+    pub(crate) fn unit(&mut self) -> f64 {
+        // the 53 high bits fill the mantissa
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Returns the number from the standard normal distribution
+    pub(crate) fn normal(&mut self) -> f64 {
+        // Box-Muller transform, 1 - unit is in (0, 1] so the logarithm is finite
+        let radius = (-2.0 * (1.0 - self.unit()).ln()).sqrt();
+        radius * (2.0 * std::f64::consts::PI * self.unit()).cos()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::evaluator::eval_str_with_vars_and_ctx;
+    use crate::Ctx;
+
+    #[test]
+    fn test_random_functions() {
+        let rng = Rng::new(7);
+        let ctx = Ctx::default().with_random(&rng);
+        let mut vars = HashMap::new();
+        let mut eval = |input: &str| eval_str_with_vars_and_ctx(input, &mut vars, &ctx).unwrap();
+        let inputs = &["rand()", "rand * 2", "rand_range(-3, -1)", "randn()"];
+        let first: Vec<f64> = inputs.iter().map(|input| eval(input)).collect();
+        assert!((0.0..1.0).contains(&first[0]), "input was {}", inputs[0]);
+        assert!((0.0..2.0).contains(&first[1]), "input was {}", inputs[1]);
+        assert!((-3.0..-1.0).contains(&first[2]), "input was {}", inputs[2]);
+        assert!(first[3].is_finite(), "input was {}", inputs[3]);
+        // the numbers change, until the generator is seeded again
+        assert_ne!(eval("rand()"), first[0]);
+        rng.clone().seed(7);
+        let again: Vec<f64> = inputs.iter().map(|input| eval(input)).collect();
+        assert_eq!(again, first);
+    }
+
+    #[test]
+    fn test_normal_distribution() {
+        let mut rng = XorShift::new(1);
+        let samples: Vec<f64> = (0..10_000).map(|_| rng.normal()).collect();
+        let mean = samples.iter().sum::<f64>() / samples.len() as f64;
+        let variance =
+            samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / samples.len() as f64;
+        assert!(mean.abs() < 0.05);
+        assert!((variance - 1.0).abs() < 0.05);
+    }
+}
@@ -0,0 +1,266 @@
+//! Evaluation of the expression over evenly spaced points, e.g. to plot it.
+//!
+//! The expression is parsed once and every point reuses the same evaluation stack.
+//!
+//! # Example
+//!
+//! ```
+//! use std::collections::HashMap;
+//! use rusty_yard::expression::Expression;
+//! use rusty_yard::sample::sample;
+//! use rusty_yard::Ctx;
+//!
+//! let ctx = Ctx::default();
+//! let expr = Expression::parse("a * x ^ 2", &ctx).unwrap();
+//! let mut vars = HashMap::new();
+//! vars.insert("a".to_owned(), 2.0);
+//! let points = sample(&expr, "x", 0.0..=2.0, 3, &mut vars).unwrap();
+//! assert_eq!(points, vec![(0.0, 0.0), (1.0, 2.0), (2.0, 8.0)]);
+//! ```
+use std::ops::RangeInclusive;
+
+use crate::evaluator::{self, eval_with_stack};
+use crate::expression::Expression;
+use crate::variables::VariableResolver;
+
+/// The variable and its values for [`sample_grid`](sample_grid).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Axis<'v> {
+    /// The name of the variable.
+    pub var: &'v str,
+    /// The first and the last value of the variable.
+    pub range: RangeInclusive<f64>,
+    /// The number of values.
+    pub n: usize,
+}
+
+/// Returns `n` evenly spaced values from the start to the end of the `range`.
+///
+/// If `n` is 1 this is the start of the range.
+pub fn linspace(range: RangeInclusive<f64>, n: usize) -> impl Iterator<Item = f64> {
+    let (start, end) = range.into_inner();
+    let step = if n > 1 {
+        (end - start) / (n - 1) as f64
+    } else {
+        0.0
+    };
+    (0..n).map(move |i| {
+        if i + 1 == n && n > 1 {
+            end
+        } else {
+            start + step * i as f64
+        }
+    })
+}
+
+/// Evaluates the expression at `n` evenly spaced values of `var` from the `range`,
+/// returns the pairs of the value of `var` and the result.
+///
+/// Other variables are read from `variables`, `var` shadows the variable with the same name.
+/// Results that are `NaN` or infinite are returned as they are,
+/// the evaluation stops at the first [`Error`](crate::evaluator::Error).
+pub fn sample(
+    expr: &Expression,
+    var: &str,
+    range: RangeInclusive<f64>,
+    n: usize,
+    variables: &mut dyn VariableResolver,
+) -> Result<Vec<(f64, f64)>, evaluator::Error> {
+    let mut sampler = Sampler::new(expr, [var], variables);
+    linspace(range, n)
+        .map(|x| Ok((x, sampler.eval([x])?)))
+        .collect()
+}
+
+/// Evaluates the expression at every point of the grid formed by the values of two variables,
+/// returns the triples of the values of the variables and the result.
+///
+/// The points are in row-major order, i.e. the values of `x` change first.
+/// See [`sample`](sample) for the details.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use rusty_yard::expression::Expression;
+/// use rusty_yard::sample::{sample_grid, Axis};
+/// use rusty_yard::Ctx;
+///
+/// let ctx = Ctx::default();
+/// let expr = Expression::parse("x * y", &ctx).unwrap();
+/// let x = Axis { var: "x", range: 1.0..=2.0, n: 2 };
+/// let y = Axis { var: "y", range: 0.0..=10.0, n: 2 };
+/// let points = sample_grid(&expr, x, y, &mut HashMap::new()).unwrap();
+/// assert_eq!(
+///     points,
+///     vec![(1.0, 0.0, 0.0), (2.0, 0.0, 0.0), (1.0, 10.0, 10.0), (2.0, 10.0, 20.0)]
+/// );
+/// ```
+pub fn sample_grid(
+    expr: &Expression,
+    x: Axis,
+    y: Axis,
+    variables: &mut dyn VariableResolver,
+) -> Result<Vec<(f64, f64, f64)>, evaluator::Error> {
+    let mut sampler = Sampler::new(expr, [x.var, y.var], variables);
+    let xs: Vec<f64> = linspace(x.range, x.n).collect();
+    let mut points = Vec::with_capacity(x.n * y.n);
+    for y in linspace(y.range, y.n) {
+        for x in xs.iter().copied() {
+            points.push((x, y, sampler.eval([x, y])?));
+        }
+    }
+    Ok(points)
+}
+
+/// Evaluates the expression for the values of `N` variables, reusing the evaluation stack
+pub(crate) struct Sampler<'e, 'a, 'ctx, 'v, 'r, const N: usize> {
+    expr: &'e Expression<'a, 'ctx>,
+    bound: Bound<'r, [(&'v str, f64); N]>,
+    stack: Vec<f64>,
+}
+
+impl<'e, 'a, 'ctx, 'v, 'r, const N: usize> Sampler<'e, 'a, 'ctx, 'v, 'r, N> {
+    /// `vars` shadow the variables with the same names in `variables`
+    pub(crate) fn new(
+        expr: &'e Expression<'a, 'ctx>,
+        vars: [&'v str; N],
+        variables: &'r mut dyn VariableResolver,
+    ) -> Self {
+        Self {
+            expr,
+            bound: Bound {
+                vars: vars.map(|var| (var, 0.0)),
+                variables,
+            },
+            stack: Vec::new(),
+        }
+    }
+
+    /// Evaluates the expression with the `values` of the variables, in the order they were passed to `new`
+    pub(crate) fn eval(&mut self, values: [f64; N]) -> evaluator::Result {
+        for (var, value) in self.bound.vars.iter_mut().zip(values) {
+            var.1 = value;
+        }
+        let expr = self.expr;
+        eval_with_stack(expr.tokens(), &mut self.bound, expr.ctx(), &mut self.stack)
+    }
+}
+
+/// The resolver where the sampled variables shadow the `variables`
+///
+/// `B` is the array or the vector of the names and the values of the sampled variables
+pub(crate) struct Bound<'r, B> {
+    pub(crate) vars: B,
+    pub(crate) variables: &'r mut dyn VariableResolver,
+}
+
+impl<'v, B> VariableResolver for Bound<'_, B>
+where
+    B: AsRef<[(&'v str, f64)]> + AsMut<[(&'v str, f64)]>,
+{
+    fn get(&self, name: &str) -> Option<f64> {
+        match self.vars.as_ref().iter().find(|(var, _)| *var == name) {
+            Some((_, value)) => Some(*value),
+            None => self.variables.get(name),
+        }
+    }
+
+    fn set(&mut self, name: &str, value: f64) {
+        match self.vars.as_mut().iter_mut().find(|(var, _)| *var == name) {
+            Some((_, old)) => *old = value,
+            None => self.variables.set(name, value),
+        }
+    }
+
+    fn names(&self) -> Vec<String> {
+        let mut names = self.variables.names();
+        names.extend(self.vars.as_ref().iter().map(|(var, _)| (*var).to_owned()));
+        names
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::Ctx;
+
+    #[test]
+    fn test_linspace() {
+        let input_expected: &[(RangeInclusive<f64>, usize, &[f64])] = &[
+            (0.0..=1.0, 0, &[]),
+            (0.0..=1.0, 1, &[0.0]),
+            (0.0..=1.0, 2, &[0.0, 1.0]),
+            (0.0..=1.0, 5, &[0.0, 0.25, 0.5, 0.75, 1.0]),
+            (1.0..=-1.0, 3, &[1.0, 0.0, -1.0]),
+            (0.0..=0.3, 4, &[0.0, 0.1, 0.2, 0.3]),
+        ];
+        for (range, n, expected) in input_expected {
+            let result: Vec<_> = linspace(range.clone(), *n).collect();
+            let close = result.len() == expected.len()
+                && result
+                    .iter()
+                    .zip(*expected)
+                    .all(|(r, e)| (r - e).abs() < 1e-12);
+            assert!(close, "input was {:?} {}, result {:?}", range, n, result);
+            assert_eq!(
+                result.last(),
+                expected.last(),
+                "input was {:?} {}",
+                range,
+                n
+            );
+        }
+    }
+
+    #[test]
+    fn test_sample() {
+        let ctx = Ctx::default_with_macros();
+        let expr = Expression::parse("x = x * 2", &ctx).unwrap();
+        let mut vars = HashMap::new();
+        vars.insert("x".to_owned(), 100.0);
+        let points = sample(&expr, "x", -1.0..=1.0, 3, &mut vars).unwrap();
+        assert_eq!(points, vec![(-1.0, -2.0), (0.0, 0.0), (1.0, 2.0)]);
+        // the sampled variable shadows the outer one
+        assert_eq!(vars.get("x"), Some(&100.0));
+
+        let expr = Expression::parse("x + y", &ctx).unwrap();
+        let result = sample(&expr, "x", 0.0..=1.0, 2, &mut HashMap::new());
+        assert_eq!(
+            result,
+            Err(evaluator::Error::VarNotFound("y".to_owned(), None))
+        );
+    }
+
+    #[test]
+    fn test_sample_grid() {
+        let ctx = Ctx::default();
+        let expr = Expression::parse("x - y + c", &ctx).unwrap();
+        let mut vars = HashMap::new();
+        vars.insert("c".to_owned(), 0.5);
+        let x = Axis {
+            var: "x",
+            range: 0.0..=2.0,
+            n: 3,
+        };
+        let y = Axis {
+            var: "y",
+            range: 0.0..=1.0,
+            n: 2,
+        };
+        let points = sample_grid(&expr, x, y, &mut vars).unwrap();
+        assert_eq!(
+            points,
+            vec![
+                (0.0, 0.0, 0.5),
+                (1.0, 0.0, 1.5),
+                (2.0, 0.0, 2.5),
+                (0.0, 1.0, -0.5),
+                (1.0, 1.0, 0.5),
+                (2.0, 1.0, 1.5),
+            ]
+        );
+    }
+}
@@ -0,0 +1,195 @@
+//! Provides [`Session`](Session), the evaluation state that owns the context, the variables and the options.
+//!
+//! The free functions of the [`evaluator`](crate::evaluator) take the variables and the context on every call,
+//! the session keeps them together, so the assignments of one expression are seen by the next ones.
+//!
+//! # Example
+//!
+//! ```
+//! use rusty_yard::session::Session;
+//! use rusty_yard::Ctx;
+//!
+//! let mut session = Session::new(Ctx::default_with_macros());
+//! session.set("x", 3.0);
+//! assert_eq!(session.eval("a = 1 + 2"), Ok(3.0));
+//! assert_eq!(session.eval("a * x"), Ok(9.0));
+//! assert_eq!(session.get("a"), Some(3.0));
+//!
+//! // the last result is bound to `ans`
+//! assert_eq!(session.eval("ans + 1"), Ok(10.0));
+//! ```
+use std::collections::HashMap;
+
+use crate::evaluator::{self, eval_with_options, EvalOptions};
+use crate::parser::parse_str;
+use crate::Ctx;
+
+/// The variable the result of the last successful evaluation is bound to, see [`eval`](Session::eval).
+pub const ANS: &str = "ans";
+
+/// The context, the variables and the [`EvalOptions`](crate::evaluator::EvalOptions) of the evaluations.
+#[derive(Debug)]
+pub struct Session {
+    ctx: Ctx,
+    vars: HashMap<String, f64>,
+    options: EvalOptions,
+    /// The number of the results bound to `ans1..ansN`
+    history: usize,
+}
+
+impl Session {
+    /// Creates the session without the variables and with the default options.
+    pub fn new(ctx: Ctx) -> Self {
+        Session {
+            ctx,
+            vars: HashMap::new(),
+            options: EvalOptions::default(),
+            history: 0,
+        }
+    }
+
+    /// Keeps the last `depth` results in the variables `ans1..ans{depth}`, `ans1` is the last one.
+    pub fn with_history(mut self, depth: usize) -> Self {
+        self.history = depth;
+        self
+    }
+
+    /// Sets the options of the evaluations.
+    pub fn with_options(mut self, options: EvalOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Parses and evaluates the `input` with the variables of the session.
+    ///
+    /// The macros like `=` change the variables of the session.
+    /// The result is bound to the [`ANS`](ANS) variable, and to the [history](Session::with_history) variables,
+    /// they are not changed if the evaluation fails.
+    pub fn eval(&mut self, input: &str) -> Result<f64, evaluator::Error> {
+        let parsed = parse_str(input, &self.ctx)?;
+        let value = eval_with_options(&parsed, &mut self.vars, &self.ctx, self.options.clone())?;
+        self.bind_result(value);
+        Ok(value)
+    }
+
+    fn bind_result(&mut self, value: f64) {
+        for i in (1..self.history).rev() {
+            if let Some(previous) = self.get(&format!("{}{}", ANS, i)) {
+                self.set(&format!("{}{}", ANS, i + 1), previous);
+            }
+        }
+        if self.history > 0 {
+            self.set(&format!("{}1", ANS), value);
+        }
+        self.set(ANS, value);
+    }
+
+    /// Returns the value of the variable `name`.
+    pub fn get(&self, name: &str) -> Option<f64> {
+        self.vars.get(name).copied()
+    }
+
+    /// Sets the variable `name` to the `value`, returns the previous value.
+    pub fn set(&mut self, name: &str, value: f64) -> Option<f64> {
+        self.vars.insert(name.to_owned(), value)
+    }
+
+    /// Removes the variable `name`, returns its value.
+    pub fn remove(&mut self, name: &str) -> Option<f64> {
+        self.vars.remove(name)
+    }
+
+    /// The variables of the session.
+    pub fn vars(&self) -> &HashMap<String, f64> {
+        &self.vars
+    }
+
+    /// The variables of the session, that can be changed all at once.
+    pub fn vars_mut(&mut self) -> &mut HashMap<String, f64> {
+        &mut self.vars
+    }
+
+    /// The context of the session.
+    pub fn ctx(&self) -> &Ctx {
+        &self.ctx
+    }
+
+    /// The context of the session, e.g. to add the functions.
+    pub fn ctx_mut(&mut self) -> &mut Ctx {
+        &mut self.ctx
+    }
+
+    /// The options of the evaluations.
+    pub fn options_mut(&mut self) -> &mut EvalOptions {
+        &mut self.options
+    }
+
+    /// Returns the context and the variables of the session.
+    pub fn into_parts(self) -> (Ctx, HashMap<String, f64>) {
+        (self.ctx, self.vars)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::functions::Func;
+
+    #[test]
+    fn test_session() {
+        let mut session = Session::new(Ctx::default_with_macros()).with_options(EvalOptions {
+            strict_math: true,
+            ..EvalOptions::default()
+        });
+        session
+            .ctx_mut()
+            .fns
+            .push(Func::new("double", Some(1), |args| args[0] * 2.0));
+        let input_expected = &[
+            ("a = 2", Ok(2.0)),
+            ("b = double(a) + 1", Ok(5.0)),
+            ("a * b", Ok(10.0)),
+            (
+                "1 / (a - 2)",
+                Err(evaluator::Error::DivisionByZero {
+                    func: "/".to_owned(),
+                    position: 4,
+                }),
+            ),
+            (
+                "c",
+                Err(evaluator::Error::VarNotFound("c".to_owned(), None)),
+            ),
+        ];
+        for (input, expected) in input_expected {
+            assert_eq!(session.eval(input), *expected, "input was {}", input);
+        }
+        assert_eq!(session.set("a", 3.0), Some(2.0));
+        assert_eq!(session.eval("a * b"), Ok(15.0));
+        assert_eq!(session.remove("b"), Some(5.0));
+        assert_eq!(session.vars().len(), 2);
+        let (_, vars) = session.into_parts();
+        assert_eq!(vars.get("a"), Some(&3.0));
+    }
+
+    #[test]
+    fn test_session_history() {
+        let mut session = Session::new(Ctx::default()).with_history(2);
+        let input_expected = &[
+            ("1 + 1", Ok(2.0)),
+            ("ans * 3", Ok(6.0)),
+            ("ans1 + ans2", Ok(8.0)),
+            // the failed evaluation keeps the results
+            (
+                "ans / x",
+                Err(evaluator::Error::VarNotFound("x".to_owned(), None)),
+            ),
+            ("ans - ans2", Ok(2.0)),
+        ];
+        for (input, expected) in input_expected {
+            assert_eq!(session.eval(input), *expected, "input was {}", input);
+        }
+        assert_eq!(session.get("ans2"), Some(8.0));
+        assert_eq!(session.get("ans3"), None);
+    }
+}
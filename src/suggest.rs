@@ -0,0 +1,86 @@
+//! The "did you mean" suggestions for the misspelled identifiers.
+
+/// Returns the candidate closest to the `name`, if it is close enough to be the misspelling of it.
+///
+/// The candidate is close enough if the edit distance is at most the third of the length of the `name`,
+/// so the names shorter than 3 characters get no suggestions, `b` is rarely the misspelled `e`.
+/// The ties are resolved alphabetically, so the order of the candidates does not matter.
+pub(crate) fn closest<'c>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'c str>,
+) -> Option<String> {
+    let max_distance = name.chars().count() / 3;
+    candidates
+        .into_iter()
+        .filter(|candidate| *candidate != name)
+        .map(|candidate| (levenshtein(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min()
+        .map(|(_, candidate)| candidate.to_owned())
+}
+
+/// Formats the `suggestion` of the error message, empty if there is none
+pub(crate) fn did_you_mean(suggestion: &Option<String>) -> String {
+    match suggestion {
+        Some(suggestion) => format!(". Did you mean {}?", suggestion),
+        None => String::new(),
+    }
+}
+
+/// The number of the inserted, removed and replaced characters that turn `a` into `b`
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    // the distances from the prefix of `a` to the prefixes of `b`
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, b_char) in b.iter().enumerate() {
+            let replaced = diagonal + usize::from(a_char != *b_char);
+            diagonal = row[j + 1];
+            row[j + 1] = replaced.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_levenshtein() {
+        let input_expected = &[
+            (("", ""), 0),
+            (("pi", "pi"), 0),
+            (("pie", "pi"), 1),
+            (("sqtr", "sqrt"), 2),
+            (("kitten", "sitting"), 3),
+            (("", "abc"), 3),
+            (("ä", "a"), 1),
+        ];
+        for ((a, b), expected) in input_expected {
+            assert_eq!(levenshtein(a, b), *expected, "input was {:?}", (a, b));
+            assert_eq!(levenshtein(b, a), *expected, "input was {:?}", (b, a));
+        }
+    }
+
+    #[test]
+    fn test_closest() {
+        let candidates = &["pi", "e", "sin", "sinh", "sqrt", "rate"];
+        let input_expected = &[
+            ("pie", Some("pi")),
+            ("sinn", Some("sin")),
+            ("sqr", Some("sqrt")),
+            ("rat", Some("rate")),
+            ("b", None),
+            ("pi", None),
+            ("cos", None),
+            ("ratio", None),
+        ];
+        for (input, expected) in input_expected {
+            let result = closest(input, candidates.iter().copied());
+            assert_eq!(result.as_deref(), *expected, "input was {}", input);
+        }
+    }
+}
@@ -0,0 +1,147 @@
+//! [`proptest`](https://docs.rs/proptest) strategies that generate input consistent with the [`context`](crate::Ctx).
+//!
+//! This module is available with `proptest` feature.
+//! It allows you to fuzz your own operators, functions and macros
+//! against the [`parser`](crate::parser) and [`evaluator`](crate::evaluator).
+//!
+//! Generated expressions only use numbers, the variables passed to the strategy,
+//! parentheses, operators and functions from the context.
+//! The token streams can be produced from them using [`tokenize`](crate::tokenizer::tokenize).
+//!
+//! # Example
+//!
+//! ```
+//! use proptest::prelude::*;
+//! use rusty_yard::parser::parse_str;
+//! use rusty_yard::testing::expression;
+//! use rusty_yard::Ctx;
+//!
+//! let ctx = Ctx::default();
+//! proptest!(|(input in expression(&ctx, &["a".to_owned()]))| {
+//!     prop_assert!(parse_str(&input, &ctx).is_ok(), "input: {}", input);
+//! });
+//! ```
+use proptest::prelude::*;
+
+pub use crate::generate::ExprTree;
+use crate::Ctx;
+
+/// Strategy that generates the numbers that can be written as a literal.
+pub fn number() -> impl Strategy<Value = f64> {
+    prop::num::f64::NORMAL | prop::num::f64::ZERO
+}
+
+/// Strategy that generates variable identifiers that do not clash with the items of `ctx`.
+pub fn identifier(ctx: &Ctx) -> impl Strategy<Value = String> {
+    let reserved: Vec<String> = ctx
+        .fns
+        .iter()
+        .map(|f| f.token.clone())
+        .chain(ctx.u_ops.iter().map(|op| op.token.clone()))
+        .chain(ctx.bi_ops.iter().map(|op| op.token.clone()))
+        .collect();
+    let consts: Vec<String> = ctx.consts.iter().map(|c| c.token.clone()).collect();
+    "[a-zA-Z_][a-zA-Z0-9_]{0,7}".prop_filter("identifier clashes with context", move |id| {
+        !reserved
+            .iter()
+            .any(|token| id == token || id.contains(token.as_str()))
+            && !consts.contains(id)
+    })
+}
+
+/// Strategy that generates expression trees over the items of `ctx` and `variables`.
+///
+/// Functions are always called with the right number of arguments, variadic functions get up to 4 arguments.
+pub fn expression_tree(ctx: &Ctx, variables: &[String]) -> BoxedStrategy<ExprTree> {
+    let num = number().prop_map(ExprTree::Num).boxed();
+    let leaf = if variables.is_empty() {
+        num
+    } else {
+        prop_oneof![
+            num,
+            prop::sample::select(variables.to_vec()).prop_map(ExprTree::Var)
+        ]
+        .boxed()
+    };
+    let u_ops: Vec<String> = ctx
+        .u_ops
+        .iter()
+        .filter(|op| !op.postfix)
+        .map(|op| op.token.clone())
+        .collect();
+    let bi_ops: Vec<String> = ctx.bi_ops.iter().map(|op| op.token.clone()).collect();
+    let fns: Vec<(String, Option<usize>)> =
+        ctx.fns.iter().map(|f| (f.token.clone(), f.arity)).collect();
+    leaf.prop_recursive(6, 64, 4, move |inner| {
+        let mut branches = vec![inner
+            .clone()
+            .prop_map(|e| ExprTree::Paren(Box::new(e)))
+            .boxed()];
+        if !u_ops.is_empty() {
+            branches.push(
+                (prop::sample::select(u_ops.clone()), inner.clone())
+                    .prop_map(|(op, e)| ExprTree::UOp(op, Box::new(e)))
+                    .boxed(),
+            );
+        }
+        if !bi_ops.is_empty() {
+            branches.push(
+                (
+                    prop::sample::select(bi_ops.clone()),
+                    inner.clone(),
+                    inner.clone(),
+                )
+                    .prop_map(|(op, l, r)| ExprTree::BiOp(op, Box::new(l), Box::new(r)))
+                    .boxed(),
+            );
+        }
+        for (id, arity) in &fns {
+            let id = id.clone();
+            let args = match arity {
+                Some(arity) => prop::collection::vec(inner.clone(), *arity),
+                None => prop::collection::vec(inner.clone(), 0..=4),
+            };
+            branches.push(
+                args.prop_map(move |args| ExprTree::Func(id.clone(), args))
+                    .boxed(),
+            );
+        }
+        prop::strategy::Union::new(branches)
+    })
+    .boxed()
+}
+
+/// Strategy that generates expression strings over the items of `ctx` and `variables`.
+///
+/// This is [`expression_tree`](expression_tree) formatted using [`Display`](std::fmt::Display).
+pub fn expression(ctx: &Ctx, variables: &[String]) -> impl Strategy<Value = String> {
+    expression_tree(ctx, variables).prop_map(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_str;
+
+    proptest! {
+        #[test]
+        fn test_identifier_is_variable(id in identifier(&Ctx::default())) {
+            let ctx = Ctx::default();
+            let parsed = parse_str(&id, &ctx);
+            prop_assert_eq!(parsed, Ok(vec![crate::parser::ParserToken::Id(&id)]));
+        }
+    }
+
+    #[test]
+    fn test_display() {
+        let tree = ExprTree::BiOp(
+            "+".to_owned(),
+            Box::new(ExprTree::UOp("-".to_owned(), Box::new(ExprTree::Num(1.0)))),
+            Box::new(ExprTree::Func(
+                "max".to_owned(),
+                vec![ExprTree::Var("a".to_owned()), ExprTree::Num(2.5)],
+            )),
+        );
+        assert_eq!(tree.to_string(), "- 1 + max( a, 2.5 )");
+    }
+}
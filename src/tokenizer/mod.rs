@@ -34,15 +34,77 @@
 //! They all are using [`Token::Id`](crate::tokenizer::Token::Id).
 //!
 //! It is the job of the [`parser`](crate::parser) to distinguish different identifiers.
-pub use token::Token;
+//! The only exception are quoted identifiers (`` `unit price` `` or `[unit price]`),
+//! they are always variables and use [`Token::QuotedId`](crate::tokenizer::Token::QuotedId).
+//!
+//! # Array literals
+//!
+//! When the context has [`ARRAY_FUNCTION`](ARRAY_FUNCTION), the brackets with the comma inside, or the empty ones,
+//! are the array literal, `[1, 2, 3]` is tokenized as `array(1, 2, 3)`, see [`array`](crate::value::array).
+//! The brackets without the comma are still the quoted identifier, so the single element array is `array(x)`.
+//!
+//! # Indexing
+//!
+//! When the context has the [`INDEX_OPERATOR`](INDEX_OPERATOR), e.g. [`array_ctx`](crate::value::array::array_ctx),
+//! the identifiers end before `[`, and the brackets right after the identifier, the quoted one,
+//! or the closing paren are the index,
+//! `prices[i + 1]` is tokenized as `[Id("prices"), Id("[]"), OpenParen, Id("i"), Id("+"), Num(1.0), ClosedParen]`.
+//! The brackets after the whitespace are still the quoted identifier or the array literal.
+//!
+//! # Named arguments
+//!
+//! The argument of the call of the function with [`params`](crate::functions::Func::params) can start with
+//! the name of the parameter and `=`, `normal(x, sd = 2)` is tokenized as
+//! `[Id("normal"), OpenParen, Id("x"), Comma, ArgName("sd"), Num(2.0), ClosedParen]`.
+//! The name is matched before the macros, so it is not the assignment.
+//!
+//! # String literals
+//!
+//! When [`Ctx::strings`](crate::Ctx::strings) is set, the text in double quotes is the string literal,
+//! `lookup("steel", t)` is tokenized as `[Id("lookup"), OpenParen, Str("steel"), Comma, Id("t"), ClosedParen]`.
+//! The strings are the arguments of the [functions with strings](crate::functions::Func::strings),
+//! the macros can match them in their input with [`match_string`](match_string).
+//!
+//! # Comments
+//!
+//! When [`Ctx::comments`](crate::Ctx::comments) is set, `#` starts the comment that lasts to the end of the line,
+//! and `/* … */` is the block comment, they are skipped like the whitespace,
+//! `1 + /* the rate */ r # per year` is tokenized as `[Num(1.0), Id("+"), Id("r")]`.
+//! The spans of the tokens are the same as without the comments.
+//!
+//! # Strict identifiers
+//!
+//! When [`Ctx::strict_ids`](crate::Ctx::strict_ids) is set, the identifiers are `[A-Za-z_][A-Za-z0-9_]*`,
+//! any other character that is not the part of an operator, a number or a quoted identifier is the bad token,
+//! so `a$b` is tokenized as `[Id("a"), BadToken("$", InvalidCharacter), Id("b")]` instead of `[Id("a$b")]`.
+//! The quoted identifiers can still contain any characters.
+//!
+//! # Decimal comma
+//!
+//! When the [`decimal_separator`](TokenizeOptions::decimal_separator) of [`Ctx::tokenize_options`](crate::Ctx::tokenize_options)
+//! is [`Comma`](DecimalSeparator::Comma), `1,5` is the number and `;` separates the arguments,
+//! `max(1,5; 2)` is tokenized as `[Id("max"), OpenParen, Num(1.5), Comma, Num(2.0), ClosedParen]`.
+use std::borrow::Cow;
+use std::cmp::Reverse;
+use std::fmt::{Display, Formatter};
 
+pub use options::{DecimalSeparator, TokenizeOptions};
+pub use passes::{apply_passes, apply_passes_with_spans, TokenPass};
+pub use token::{BadTokenReason, Token};
+
+use crate::diagnostics::{locate, Location};
+use crate::functions::Func;
 use crate::macros::Macro;
 
 use super::Ctx;
 use crate::operators::{BiOp, UOp};
 use crate::tokenizer::token::MacroToken;
+use trie::OperatorTrie;
 
+mod options;
+pub mod passes;
 mod token;
+mod trie;
 
 /// Represents a match from one of the match functions
 ///
@@ -58,68 +120,414 @@ pub struct Match<T>(pub T, pub usize);
 ///
 /// Each token reuses memory from the input string when possible.
 ///
-/// # Panics
+/// The input can contain any Unicode characters, see [`match_id`](match_id) for the identifiers.
 ///
-/// This function will panic is input in not an ascii string.\
-/// TODO: add unicode support.
+/// This collects [`tokens`](tokens) into a `Vec`.
 pub fn tokenize<'a, 'ctx>(input: &'a str, ctx: &'ctx Ctx) -> Vec<Token<'a, 'ctx>> {
-    if !input.is_ascii() {
-        panic!("Input contains non ascii characters");
-    }
-    let mut output = Vec::new();
-    let whitespace_to_skip = skip_whitespace(input);
-    let mut text = &input[whitespace_to_skip..];
-    while !text.is_empty() {
-        let (token, consumed) = if text.starts_with('(') {
+    tokens(input, ctx).collect()
+}
+
+/// Represents the range of bytes of the input the token was tokenized from.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Default)]
+pub struct Span {
+    /// The offset of the first byte of the token.
+    pub start: usize,
+    /// The offset after the last byte of the token.
+    pub end: usize,
+}
+
+impl Span {
+    /// The [`Position`](Position) of the start of the span in the `input` it was tokenized from.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rusty_yard::tokenizer::{Position, Span};
+    ///
+    /// let input = "1 +\n  (2 * x)";
+    /// let position = Span { start: 11, end: 12 }.position(input);
+    /// assert_eq!(position, Position { line: 2, column: 8 });
+    /// assert_eq!(position.to_string(), "line 2, col 8");
+    /// ```
+    pub fn position(&self, input: &str) -> Position {
+        let Location { number, column, .. } = locate(input, *self);
+        Position {
+            line: number,
+            column: column + 1,
+        }
+    }
+}
+
+/// Represents the line and the column of the character in the multi-line input.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct Position {
+    /// The line, starting from 1.
+    pub line: usize,
+    /// The column, starting from 1, it counts the characters, not the bytes.
+    pub column: usize,
+}
+
+impl Display for Position {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}, col {}", self.line, self.column)
+    }
+}
+
+/// Same as [`tokenize`](tokenize), but also returns the [`Span`](Span) of each token in the `input`.
+///
+/// # Example
+///
+/// ```
+/// use rusty_yard::tokenizer::{tokenize_with_spans, Span, Token};
+/// use rusty_yard::Ctx;
+///
+/// let ctx = Ctx::default();
+/// let (tokens, spans) = tokenize_with_spans("ab + 1", &ctx);
+/// assert_eq!(tokens, vec![Token::Id("ab"), Token::Id("+"), Token::Num(1.0)]);
+/// assert_eq!(spans[0], Span { start: 0, end: 2 });
+/// assert_eq!(spans[2], Span { start: 5, end: 6 });
+/// ```
+pub fn tokenize_with_spans<'a, 'ctx>(
+    input: &'a str,
+    ctx: &'ctx Ctx,
+) -> (Vec<Token<'a, 'ctx>>, Vec<Span>) {
+    tokens(input, ctx).spanned().unzip()
+}
+
+/// Lazily tokenizes the input string, the tokens are the same as the ones of [`tokenize`](tokenize).
+///
+/// Each token is tokenized when it is requested,
+/// so the tokens of a long input don't have to be in the memory all at once.
+///
+/// # Example
+///
+/// ```
+/// use rusty_yard::tokenizer::{tokens, Token};
+/// use rusty_yard::Ctx;
+///
+/// let ctx = Ctx::default();
+/// let mut iter = tokens("a + 1", &ctx);
+/// assert_eq!(iter.next(), Some(Token::Id("a")));
+/// assert_eq!(iter.count(), 2);
+/// ```
+pub fn tokens<'a, 'ctx>(input: &'a str, ctx: &'ctx Ctx) -> Tokenizer<'a, 'ctx> {
+    Tokenizer {
+        input,
+        text: &input[skip_ignored(input, ctx)..],
+        ctx,
+        trie: OperatorTrie::new(ctx),
+        arrays: ctx.func(ARRAY_FUNCTION).is_some(),
+        indexing: ctx.bi_op(INDEX_OPERATOR).is_some(),
+        index_follows: false,
+        separator: ctx.tokenize_options.decimal_separator.argument_separator(),
+        open_arrays: 0,
+        pending: None,
+        named_args: ctx.fns.iter().any(|func| !func.params.is_empty()),
+        calls: Vec::new(),
+        last_func: None,
+        arg_start: false,
+    }
+}
+
+/// The function the array literals are tokenized into, see [`array literals`](self#array-literals).
+pub const ARRAY_FUNCTION: &str = "array";
+
+/// The binary operator the index brackets are tokenized into, see [`indexing`](self#indexing).
+pub const INDEX_OPERATOR: &str = "[]";
+
+/// The iterator over the tokens of the input, see [`tokens`](tokens)
+pub struct Tokenizer<'a, 'ctx> {
+    input: &'a str,
+    /// The rest of the input that is not tokenized yet, without the leading whitespace
+    text: &'a str,
+    ctx: &'ctx Ctx,
+    trie: OperatorTrie,
+    /// The context has [`ARRAY_FUNCTION`](ARRAY_FUNCTION)
+    arrays: bool,
+    /// The context has [`INDEX_OPERATOR`](INDEX_OPERATOR)
+    indexing: bool,
+    /// The last token is the identifier, or the closing paren, right before `[`, so the bracket is the index
+    index_follows: bool,
+    /// The character that separates the arguments, see [`DecimalSeparator`](DecimalSeparator)
+    separator: char,
+    /// The number of array literals and indices that are not closed yet
+    open_arrays: usize,
+    /// The token that follows the array function
+    pending: Option<(Token<'a, 'ctx>, Span)>,
+    /// The context has the functions with the [`params`](crate::functions::Func::params),
+    /// so the [`named arguments`](self#named-arguments) are tokenized
+    named_args: bool,
+    /// For each open paren, the function with the params it calls
+    calls: Vec<Option<&'ctx Func>>,
+    /// The last token is the function with the params
+    last_func: Option<&'ctx Func>,
+    /// The last token is the paren or the comma, so the argument can start here
+    arg_start: bool,
+}
+
+impl<'a, 'ctx> Tokenizer<'a, 'ctx> {
+    /// Turns this into the iterator over the tokens together with their [`Spans`](Span)
+    pub fn spanned(self) -> impl Iterator<Item = (Token<'a, 'ctx>, Span)> {
+        let mut tokenizer = self;
+        std::iter::from_fn(move || tokenizer.next_spanned())
+    }
+
+    /// Tokenizes the next token
+    fn next_spanned(&mut self) -> Option<(Token<'a, 'ctx>, Span)> {
+        let next = self.next_untracked();
+        if self.named_args {
+            if let Some((token, _)) = &next {
+                self.track_calls(token);
+            }
+        }
+        next
+    }
+
+    /// Remembers the calls of the functions with the params, so their named arguments can be tokenized
+    fn track_calls(&mut self, token: &Token<'a, 'ctx>) {
+        let last_func = self.last_func.take();
+        self.arg_start = matches!(token, Token::OpenParen | Token::Comma);
+        match token {
+            Token::OpenParen => self.calls.push(last_func),
+            Token::ClosedParen => {
+                self.calls.pop();
+            }
+            Token::Id(id) => {
+                self.last_func = self.ctx.func(id).filter(|func| !func.params.is_empty());
+            }
+            _ => {}
+        }
+    }
+
+    /// Tokenizes the next token, without tracking the calls
+    fn next_untracked(&mut self) -> Option<(Token<'a, 'ctx>, Span)> {
+        if let Some(pending) = self.pending.take() {
+            return Some(pending);
+        }
+        let text = self.text;
+        if text.is_empty() {
+            return None;
+        }
+        let ctx = self.ctx;
+        let call = self.calls.last().copied().flatten();
+        let index_follows = std::mem::take(&mut self.index_follows);
+        let (token, consumed) = if let Some(Match(name, c)) = call
+            .filter(|_| self.arg_start)
+            .and_then(|func| match_arg_name(text, func))
+        {
+            (Token::ArgName(name), c)
+        } else if text.starts_with('(') {
             (Token::OpenParen, '('.len_utf8())
         } else if text.starts_with(')') {
             (Token::ClosedParen, ')'.len_utf8())
-        } else if text.starts_with(',') {
-            (Token::Comma, ','.len_utf8())
-        } else if let Some(Match(m, c)) = match_macros(text, &ctx) {
+        } else if index_follows && text.starts_with('[') {
+            self.open_arrays += 1;
+            (Token::Id(INDEX_OPERATOR), '['.len_utf8())
+        } else if self.arrays && text.starts_with('[') && is_array_literal(text, self.separator) {
+            self.open_arrays += 1;
+            (Token::Id(ARRAY_FUNCTION), '['.len_utf8())
+        } else if self.open_arrays > 0 && text.starts_with(']') {
+            self.open_arrays -= 1;
+            (Token::ClosedParen, ']'.len_utf8())
+        } else if text.starts_with(self.separator) {
+            (Token::Comma, self.separator.len_utf8())
+        } else if ctx.comments && text.starts_with("/*") {
+            // the closed comments are skipped with the whitespace
+            (
+                Token::BadToken(text, BadTokenReason::UnterminatedComment),
+                text.len(),
+            )
+        } else if let Some(Match(m, c)) = match_macros(text, ctx) {
             let token = MacroToken {
                 text: &text[..c],
                 definition: m,
             };
             (Token::Macro(token), c)
-        } else if let Some(Match(n, c)) = match_number(text) {
+        } else if ctx.strings && text.starts_with('"') {
+            match match_string(text) {
+                Some(Match(s, c)) => (Token::Str(s), c),
+                None => (
+                    Token::BadToken(text, BadTokenReason::UnterminatedString),
+                    text.len(),
+                ),
+            }
+        } else if text.starts_with(is_open_quote) {
+            match match_quoted_id(text) {
+                Some(Match(id, c)) => (Token::QuotedId(id), c),
+                // the identifier is not closed, so the rest of the input is bad
+                None => (
+                    Token::BadToken(text, BadTokenReason::UnterminatedQuote),
+                    text.len(),
+                ),
+            }
+        } else if let Some(Match(n, c)) =
+            match_number_with(text, ctx.tokenize_options.decimal_separator)
+        {
             (Token::Num(n), c)
-        } else if let Some(Match(id, c)) = match_op(text, ctx).or_else(|| match_id(text, ctx)) {
-            (Token::Id(id), c)
+        } else if let Some(Match(id, c)) = self
+            .trie
+            .match_op(text)
+            .map(|c| Match(&text[..c], c))
+            .or_else(|| match_id_with(text, &self.trie, ctx))
+        {
+            // the comment can follow the identifier without the whitespace
+            let (id, c) = match id.find('#').filter(|end| ctx.comments && *end > 0) {
+                Some(end) => (&id[..end], end),
+                None => (id, c),
+            };
+            // the identifier ends where the array literal does, or where its index starts
+            let end = id
+                .find(']')
+                .filter(|_| self.open_arrays > 0)
+                .into_iter()
+                .chain(id.find('[').filter(|_| self.indexing))
+                .min();
+            match end {
+                Some(end) => (Token::Id(&id[..end]), end),
+                None => (Token::Id(id), c),
+            }
+        } else if ctx.strict_ids && !starts_like_number(text) {
+            // the identifier or the operator can start right after the bad character
+            let c = text.chars().next().map_or(0, char::len_utf8);
+            (
+                Token::BadToken(&text[..c], BadTokenReason::InvalidCharacter),
+                c,
+            )
         } else {
             let c = text
                 .chars()
-                .take_while(|c| !c.is_ascii_whitespace())
+                .take_while(|c| !c.is_whitespace())
                 .map(|c| c.len_utf8())
                 .sum();
-            (Token::BadToken(&text[..c]), c)
+            let bad = &text[..c];
+            let reason = if starts_like_number(bad) {
+                BadTokenReason::MalformedNumber
+            } else {
+                BadTokenReason::InvalidCharacter
+            };
+            (Token::BadToken(bad, reason), c)
+        };
+        let start = self.input.len() - text.len();
+        let rest = &text[consumed..];
+        let ignored = skip_ignored(rest, self.ctx);
+        if self.indexing && ignored == 0 && rest.starts_with('[') {
+            self.index_follows = match token {
+                Token::ClosedParen | Token::QuotedId(_) => true,
+                Token::Id(ARRAY_FUNCTION) | Token::Id(INDEX_OPERATOR) => false,
+                Token::Id(_) => self.trie.match_op(text).is_none(),
+                _ => false,
+            };
+        }
+        self.text = &rest[ignored..];
+        let span = Span {
+            start,
+            end: start + consumed,
         };
-        output.push(token);
-        text = &text[consumed..];
-        let whitespace_to_skip = skip_whitespace(text);
-        text = &text[whitespace_to_skip..];
+        if matches!(token, Token::Id(ARRAY_FUNCTION) | Token::Id(INDEX_OPERATOR))
+            && text.starts_with('[')
+        {
+            // the bracket is both the function, or the operator, and its paren
+            self.pending = Some((Token::OpenParen, span));
+        }
+        Some((token, span))
+    }
+}
+
+/// Matches the name of the parameter of the `func` followed by `=`, the longest name wins
+fn match_arg_name<'a>(text: &'a str, func: &Func) -> Option<Match<&'a str>> {
+    func.params
+        .iter()
+        .filter_map(|param| {
+            let name = text
+                .get(..param.name.len())
+                .filter(|name| *name == param.name)?;
+            let rest = &text[name.len()..];
+            let rest = &rest[skip_whitespace(rest)..];
+            if rest.starts_with('=') && !rest.starts_with("==") {
+                Some(Match(name, text.len() - rest.len() + '='.len_utf8()))
+            } else {
+                None
+            }
+        })
+        .max_by_key(|Match(name, _)| name.len())
+}
+
+/// Returns true if the brackets at the start of the `text` contain the `separator` that is not nested, or nothing
+fn is_array_literal(text: &str, separator: char) -> bool {
+    let mut depth = 0;
+    for ch in text[1..].chars() {
+        match ch {
+            '(' | '[' => depth += 1,
+            ']' if depth == 0 => return text[1..].trim_start().starts_with(']'),
+            ')' | ']' => depth -= 1,
+            ch if ch == separator && depth == 0 => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+impl<'a, 'ctx> Iterator for Tokenizer<'a, 'ctx> {
+    type Item = Token<'a, 'ctx>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_spanned().map(|(token, _)| token)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        // every token is at least one byte long, except the parens of the array literals
+        let pending = usize::from(self.pending.is_some());
+        let per_byte = if self.arrays || self.indexing { 2 } else { 1 };
+        let lower = usize::from(!self.text.is_empty()) + pending;
+        (lower, Some(self.text.len() * per_byte + pending))
     }
-    output
 }
 
 /// Matches the start of the `text` with the definition of id in this crate.
 ///
 /// The definition of *identifier* very relaxed by design
-/// (one or more characters that are `|char| char.is_ascii_graphic()` but not '(', ')', ',').
+/// (one or more characters that are `|char| char.is_ascii_graphic()` or Unicode alphanumeric but not '(', ')', ',').
+/// The identifier can't start with a digit, so `π` and `résultat` are identifiers but `2π` is not.
 ///
 /// Returns [`Some(length of the match)`](std::option::Option::Some) if we matched
 /// and [`None`](std::option::Option::None) when input hasn't matched an identifier.
-#[allow(clippy::while_let_on_iterator)]
 pub fn match_id<'a>(text: &'a str, ctx: &'_ Ctx) -> Option<Match<&'a str>> {
+    let text = ctx_id_candidate(text, ctx)?;
+    // the identifier ends where the first operator starts
+    let u_ops = ctx.u_ops.iter().map(|op| op.token.as_str());
+    let bi_ops = ctx.bi_ops.iter().map(|op| op.token.as_str());
+    let aliases = operator_aliases(ctx);
+    let len = u_ops
+        .chain(bi_ops)
+        .chain(aliases.iter().map(|alias| alias.alias))
+        .filter_map(|token| text.find(token))
+        .min()
+        .unwrap_or(text.len());
+    Some(Match(&text[..len], len))
+}
+
+/// Same as [`match_id`](match_id), but finds the operators using the trie
+fn match_id_with<'a>(text: &'a str, trie: &OperatorTrie, ctx: &Ctx) -> Option<Match<&'a str>> {
+    let text = ctx_id_candidate(text, ctx)?;
+    let len = text
+        .char_indices()
+        .map(|(i, _)| i)
+        .find(|i| trie.starts_with_op(&text[*i..]))
+        .unwrap_or(text.len());
+    Some(Match(&text[..len], len))
+}
+
+/// The longest prefix of `text` that can be an identifier, ignoring the operators
+pub(crate) fn id_candidate(text: &str) -> Option<&str> {
     fn is_disallowed(ch: &char) -> bool {
         const DISALLOWED_CHARS: &[char] = &['(', ')', ','];
         DISALLOWED_CHARS.iter().any(|v| v == ch)
     }
     fn is_valid_first_char(ch: &char) -> bool {
-        ch.is_ascii_graphic() && !ch.is_ascii_digit() && !is_disallowed(ch)
+        (ch.is_ascii_graphic() || ch.is_alphabetic()) && !ch.is_ascii_digit() && !is_disallowed(ch)
     }
     fn is_valid_char(ch: &char) -> bool {
-        ch.is_ascii_graphic() && !is_disallowed(ch)
+        (ch.is_ascii_graphic() || ch.is_alphanumeric()) && !is_disallowed(ch)
     }
 
     let mut iterator = text.chars();
@@ -129,14 +537,94 @@ pub fn match_id<'a>(text: &'a str, ctx: &'_ Ctx) -> Option<Match<&'a str>> {
             .take_while(is_valid_char)
             .map(char::len_utf8)
             .sum::<usize>();
-    let text = &text[..full_len];
-    let len = ctx
-        .u_ops
-        .iter()
-        .find_map(|op| text.find(&op.token))
-        .or_else(|| ctx.bi_ops.iter().find_map(|op| text.find(&op.token)))
-        .unwrap_or(full_len);
-    Some(Match(&text[..len], len))
+    Some(&text[..full_len])
+}
+
+/// The longest prefix of `text` that can be the identifier of the `ctx`, ignoring the operators
+fn ctx_id_candidate<'a>(text: &'a str, ctx: &Ctx) -> Option<&'a str> {
+    let text = if ctx.strict_ids {
+        strict_id_candidate(text)?
+    } else {
+        id_candidate(text)?
+    };
+    // `;` is the part of the identifier, unless it separates the arguments
+    let separator = ctx.tokenize_options.decimal_separator.argument_separator();
+    match text.find(separator) {
+        Some(0) => None,
+        Some(end) => Some(&text[..end]),
+        None => Some(text),
+    }
+}
+
+/// The longest prefix of `text` that can be the [strict identifier](crate::Ctx::strict_ids), ignoring the operators
+pub(crate) fn strict_id_candidate(text: &str) -> Option<&str> {
+    if !text.starts_with(|ch: char| ch.is_ascii_alphabetic() || ch == '_') {
+        return None;
+    }
+    let len = text
+        .find(|ch: char| !ch.is_ascii_alphanumeric() && ch != '_')
+        .unwrap_or(text.len());
+    Some(&text[..len])
+}
+
+fn is_open_quote(ch: char) -> bool {
+    ch == '`' || ch == '['
+}
+
+/// Matches the start of the `text` with the quoted identifier, i.e. `` `unit price` `` or `[unit price]`.
+///
+/// The quoted identifier can contain any characters except the closing quote, but it can't be empty.
+///
+/// Returns [`Some(identifier without quotes, length of the match with quotes)`](std::option::Option::Some) if we matched
+/// and [`None`](std::option::Option::None) when input hasn't matched a quoted identifier.
+pub fn match_quoted_id(text: &str) -> Option<Match<&str>> {
+    let close = match text.chars().next()? {
+        '`' => '`',
+        '[' => ']',
+        _ => return None,
+    };
+    let inner = &text[1..];
+    let len = inner.find(close).filter(|len| *len > 0)?;
+    Some(Match(&inner[..len], len + 2))
+}
+
+/// Matches the start of the `text` with the [string literal](self#string-literals), i.e. `"steel"`.
+///
+/// The string can contain any characters except `"`, there are no escapes, and it can be empty.
+///
+/// Returns [`Some(string without quotes, length of the match with quotes)`](std::option::Option::Some) if we matched
+/// and [`None`](std::option::Option::None) when input hasn't matched a string literal.
+pub fn match_string(text: &str) -> Option<Match<&str>> {
+    let inner = text.strip_prefix('"')?;
+    let len = inner.find('"')?;
+    Some(Match(&inner[..len], len + 2))
+}
+
+/// Returns the identifier in the form that is tokenized back into the same identifier.
+///
+/// Identifiers that consist of letters, digits and `_` and don't start with a digit are returned as they are,
+/// other identifiers are quoted with backticks, or with brackets if they contain a backtick.
+///
+/// # Example
+///
+/// ```
+/// use rusty_yard::tokenizer::quote_id;
+/// assert_eq!(quote_id("price"), "price");
+/// assert_eq!(quote_id("unit price"), "`unit price`");
+/// ```
+pub fn quote_id(id: &str) -> Cow<'_, str> {
+    let plain = id.chars().all(|ch| ch.is_alphanumeric() || ch == '_')
+        && id
+            .chars()
+            .next()
+            .is_some_and(|ch| ch.is_alphabetic() || ch == '_');
+    if plain {
+        Cow::Borrowed(id)
+    } else if id.contains('`') {
+        Cow::Owned(format!("[{}]", id))
+    } else {
+        Cow::Owned(format!("`{}`", id))
+    }
 }
 
 /// Matches one of the macros from 'ctx' against the start of input `text`.
@@ -159,67 +647,162 @@ pub fn match_macros<'a>(text: &str, ctx: &'a Ctx) -> Option<Match<&'a dyn Macro>
 /// # Note
 ///
 /// Binary operators are matched first, then unary.
+/// The [aliases](crate::Ctx::aliases) of the operators are matched together with the operators.
 ///
 /// In most cases this implementation detail does not matter.
 #[inline]
 pub fn match_op<'a>(text: &'a str, ctx: &Ctx) -> Option<Match<&'a str>> {
-    let matched_bi_op = match_bi_op(text, &ctx.bi_ops).map(|m| m.1);
-    let matched_u_op = || match_u_op(text, &ctx.u_ops).map(|m| m.1);
+    let aliases = operator_aliases(ctx);
+    let matched_alias = |is_kind: fn(&OperatorAlias) -> bool| {
+        aliases
+            .iter()
+            .filter(|alias| is_kind(alias) && text.starts_with(alias.alias))
+            .map(|alias| alias.alias.len())
+            .max()
+    };
+    let matched_bi_op = match_bi_op(text, &ctx.bi_ops)
+        .map(|m| m.1)
+        .max(matched_alias(|alias| alias.bi_op));
+    let matched_u_op = || {
+        match_u_op(text, &ctx.u_ops)
+            .map(|m| m.1)
+            .max(matched_alias(|alias| alias.u_op))
+    };
     matched_bi_op
         .or_else(matched_u_op)
         .map(|c| Match(&text[..c], c))
 }
 
+/// The [alias](crate::Ctx::aliases) the tokenizer matches like the operator
+pub(crate) struct OperatorAlias<'c> {
+    pub(crate) alias: &'c str,
+    /// The alias stands for the binary operator
+    pub(crate) bi_op: bool,
+    /// The alias stands for the unary operator
+    pub(crate) u_op: bool,
+}
+
+/// The aliases of the operators, except the ones made of the letters, the digits and `_`,
+/// they are tokenized as the identifiers
+pub(crate) fn operator_aliases(ctx: &Ctx) -> Vec<OperatorAlias<'_>> {
+    ctx.aliases
+        .iter()
+        .filter(|(alias, _)| !alias.is_empty() && !is_word(alias))
+        .map(|(alias, token)| OperatorAlias {
+            alias,
+            bi_op: ctx.bi_ops.iter().any(|op| op.token == *token),
+            u_op: ctx.u_ops.iter().any(|op| op.token == *token),
+        })
+        .filter(|alias| alias.bi_op || alias.u_op)
+        .collect()
+}
+
+/// The token is made of the letters, the digits and `_`
+pub(crate) fn is_word(token: &str) -> bool {
+    token.chars().all(|ch| ch.is_alphanumeric() || ch == '_')
+}
+
 /// Matches the start of the input `text` against one of [BiOps](crate::operators::binary)
 ///
+/// If several operators match, the longest one is returned.
+///
 /// Returns [`Some(length of the match)`](std::option::Option::Some) if we matched
 /// and [`None`](std::option::Option::None) when input hasn't matched any BiOp.
 pub fn match_bi_op<'a>(text: &str, bi_ops: &'a [BiOp]) -> Option<Match<&'a BiOp>> {
+    // the longest operator wins, so that `**` is not matched as `*`
     bi_ops
         .iter()
-        .find(|op| text.starts_with(&op.token))
+        .filter(|op| text.starts_with(&op.token))
+        .min_by_key(|op| Reverse(op.token.len()))
         .map(|op| Match(op, op.token.len()))
 }
 
 /// Matches the start of the input `text` against one of [UOps](crate::operators::unary)
 ///
+/// If several operators match, the longest one is returned.
+///
 /// Returns [`Some(matched macro, length of the match)`](std::option::Option::Some) if we matched
 /// and [`None`](std::option::Option::None) when input hasn't matched any UOp.
 pub fn match_u_op<'a>(text: &str, u_ops: &'a [UOp]) -> Option<Match<&'a UOp>> {
     u_ops
         .iter()
-        .find(|op| text.starts_with(&op.token))
+        .filter(|op| text.starts_with(&op.token))
+        .min_by_key(|op| Reverse(op.token.len()))
         .map(|op| Match(op, op.token.len()))
 }
 
 /// Matches the start of 'text' with the definition of number in this crate.
 ///
+/// The number is the digits with an optional fractional part and an optional exponent,
+/// e.g. `12`, `2.5`, `1e10`, `2.5E-3` or `1e+6`.
+/// The `e` after the digits that doesn't start the exponent is the start of the identifier (`2exp(1)`)
+/// if it is followed by a letter, a digit or `_`, otherwise the number is malformed (`2e`, `2e+`).
+///
 /// Returns [`Some(length of the match)`](std::option::Option::Some) if we matched
 /// and [`None`](std::option::Option::None) when input hasn't a number.
 pub fn match_number(text: &str) -> Option<Match<f64>> {
+    match_number_with(text, DecimalSeparator::Dot)
+}
+
+/// Same as [`match_number`](match_number), but the fraction follows the `separator`, so `1,5` is 1.5 with the comma.
+pub fn match_number_with(text: &str, separator: DecimalSeparator) -> Option<Match<f64>> {
+    let separator = separator.char();
     let mut iterator = text.chars();
     let first_char = iterator.next().filter(char::is_ascii_digit)?;
     let mut index = first_char.len_utf8();
-    let mut seen_dot = false;
+    let mut seen_separator = false;
     for ch in iterator {
         if ch.is_ascii_digit() {
             index += ch.len_utf8();
             continue;
         }
-        if ch == '.' {
-            if seen_dot {
+        if ch == separator {
+            if seen_separator {
                 return None;
             }
-            seen_dot = true;
+            seen_separator = true;
             index += ch.len_utf8();
             continue;
         }
         break;
     }
-    let num: f64 = text[..index].parse().ok()?;
+    if text[index..].starts_with(['e', 'E']) {
+        index += match_exponent(&text[index..])?;
+    }
+    let num: f64 = if separator == '.' {
+        text[..index].parse().ok()?
+    } else {
+        text[..index].replace(separator, ".").parse().ok()?
+    };
     Some(Match(num, index))
 }
 
+/// Whether the `text` starts with the digit or the dot followed by the digit
+fn starts_like_number(text: &str) -> bool {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some('.') => chars.next().is_some_and(|ch| ch.is_ascii_digit()),
+        first => first.is_some_and(|ch| ch.is_ascii_digit()),
+    }
+}
+
+/// The length of the exponent at the start of `text` that starts with `e`,
+/// 0 if the `e` starts the identifier and [`None`](std::option::Option::None) if the exponent is malformed.
+fn match_exponent(text: &str) -> Option<usize> {
+    let mut chars = text.chars().skip(1).peekable();
+    let sign = chars.next_if(|ch| *ch == '+' || *ch == '-').is_some();
+    let digits = chars.take_while(char::is_ascii_digit).count();
+    if digits > 0 {
+        return Some(1 + sign as usize + digits);
+    }
+    let next = text[1..].chars().next();
+    if !sign && next.is_some_and(|ch| ch.is_alphanumeric() || ch == '_') {
+        Some(0)
+    } else {
+        None
+    }
+}
+
 /// Matches the start of 'text' string `str_to_match`.
 ///
 /// Returns [`Some(number_of_chars_matched)`](std::option::Option::Some) if we matched
@@ -232,23 +815,55 @@ pub fn match_str<'a>(text: &'a str, str_to_match: &str) -> Option<Match<&'a str>
         None
     }
 }
-/// Returns the number of whitespaces that are at the beginning of input 'text'
+/// Returns the number of bytes of the whitespace (including Unicode whitespace) at the beginning of input 'text'
 ///
 /// This is useful in implementing your own macros
 #[cfg_attr(tarpaulin, skip)]
 pub fn skip_whitespace(text: &str) -> usize {
     text.chars()
-        .take_while(|ch| ch.is_ascii_whitespace())
+        .take_while(|ch| ch.is_whitespace())
         .map(|ch| ch.len_utf8())
         .sum()
 }
 
+/// Returns the number of bytes of the whitespace and the [comments](self#comments) at the beginning of input 'text'
+///
+/// The block comment that is not closed is not skipped.
+pub fn skip_whitespace_and_comments(text: &str) -> usize {
+    let mut c = 0;
+    loop {
+        c += skip_whitespace(&text[c..]);
+        let rest = &text[c..];
+        if rest.starts_with('#') {
+            c += rest.find('\n').unwrap_or(rest.len());
+        } else if let Some(end) = rest
+            .strip_prefix("/*")
+            .and_then(|comment| comment.find("*/"))
+        {
+            c += "/*".len() + end + "*/".len();
+        } else {
+            return c;
+        }
+    }
+}
+
+/// Skips the whitespace, and the comments if the context has them
+fn skip_ignored(text: &str, ctx: &Ctx) -> usize {
+    if ctx.comments {
+        skip_whitespace_and_comments(text)
+    } else {
+        skip_whitespace(text)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use proptest::prelude::*;
 
+    use super::BadTokenReason::*;
     use super::Token::*;
     use super::*;
+    use crate::operators::binary;
 
     proptest! {
         #[test]
@@ -260,6 +875,45 @@ mod tests {
             prop_assert_eq!(str.len(), res.1);
         }
         #[test]
+        fn test_match_numbers_exponent(f in prop::num::f64::NORMAL) {
+            let str = format!("{:e}", f.abs());
+            let res = match_number(&str);
+            prop_assert_eq!(res.map(|Match(n, c)| (n, c)), Some((f.abs(), str.len())));
+        }
+        #[test]
+        fn test_trie_matches_like_linear_search(s in r#"[ab1*+\-=>.]{0,8}"#) {
+            let mut ctx = Ctx::default();
+            for token in &["**", "+-", "=>", "a*b"] {
+                ctx.bi_ops.push(BiOp {
+                    token: (*token).to_owned(),
+                    ..binary::PLUS.clone()
+                });
+            }
+            ctx.u_ops.push(UOp::new("==", |a| a));
+            ctx.aliases.push(("*+".to_owned(), "**".to_owned()));
+            ctx.aliases.push(("=.".to_owned(), "==".to_owned()));
+            ctx.aliases.push(("ab".to_owned(), "+".to_owned()));
+            let trie = OperatorTrie::new(&ctx);
+            prop_assert_eq!(trie.match_op(&s), match_op(&s, &ctx).map(|m| m.1));
+            let matched = match_id_with(&s, &trie, &ctx).map(|m| m.1);
+            prop_assert_eq!(matched, match_id(&s, &ctx).map(|m| m.1));
+        }
+        #[test]
+        fn test_tokenize_any_input(s in ".{0,16}") {
+            let ctx = Ctx::default_with_macros();
+            let tokens = tokenize(&s, &ctx);
+            prop_assert!(tokens.len() <= s.chars().count());
+        }
+        #[test]
+        fn test_tokens_lazily(s in ".{0,16}") {
+            let ctx = Ctx::default_with_macros();
+            let (tokens, spans) = tokenize_with_spans(&s, &ctx);
+            let lazy: Vec<_> = super::tokens(&s, &ctx).collect();
+            prop_assert_eq!(lazy, tokens);
+            let spanned: Vec<_> = super::tokens(&s, &ctx).spanned().map(|(_, span)| span).collect();
+            prop_assert_eq!(spanned, spans);
+        }
+        #[test]
         fn test_match_ids(s in r#"[a-zA-z](?:[a-zA-Z]|[0-9])*"#) {
             let ctx = &Ctx::empty();
             let res = match_id(&s, ctx);
@@ -285,6 +939,483 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_tokenize_default_ctx() {
+        let ctx = Ctx::default();
+        let input_expected = &[
+            ("a^2+b", vec![Id("a"), Id("^"), Num(2.0), Id("+"), Id("b")]),
+            ("a*b-c", vec![Id("a"), Id("*"), Id("b"), Id("-"), Id("c")]),
+            (
+                "π + résultat * 2",
+                vec![Id("π"), Id("+"), Id("résultat"), Id("*"), Num(2.0)],
+            ),
+            ("数量2-x", vec![Id("数量2"), Id("-"), Id("x")]),
+            (
+                "1\u{a0}+\u{2003}2\u{3000}",
+                vec![Num(1.0), Id("+"), Num(2.0)],
+            ),
+            ("2π", vec![Num(2.0), Id("π")]),
+            (
+                "a € b",
+                vec![Id("a"), BadToken("€", InvalidCharacter), Id("b")],
+            ),
+            (
+                "1.2.3 + 1",
+                vec![BadToken("1.2.3", MalformedNumber), Id("+"), Num(1.0)],
+            ),
+            ("2e+", vec![BadToken("2e+", MalformedNumber)]),
+        ];
+        for (input, expected) in input_expected {
+            let output = tokenize(input, &ctx);
+            assert_eq!(output, *expected, "input was {}", input);
+        }
+    }
+
+    #[test]
+    fn test_tokenize_quoted_id() {
+        let ctx = Ctx::default();
+        let input_expected = &[
+            (
+                "`unit price` * 2",
+                vec![QuotedId("unit price"), Id("*"), Num(2.0)],
+            ),
+            (
+                "[a-b]+[`c`]",
+                vec![QuotedId("a-b"), Id("+"), QuotedId("`c`")],
+            ),
+            (
+                "max(`x`,[y])",
+                vec![
+                    Id("max"),
+                    OpenParen,
+                    QuotedId("x"),
+                    Comma,
+                    QuotedId("y"),
+                    ClosedParen,
+                ],
+            ),
+            (
+                "1 + `a b",
+                vec![Num(1.0), Id("+"), BadToken("`a b", UnterminatedQuote)],
+            ),
+            ("``", vec![BadToken("``", UnterminatedQuote)]),
+        ];
+        for (input, expected) in input_expected {
+            let output = tokenize(input, &ctx);
+            assert_eq!(output, *expected, "input was {}", input);
+        }
+    }
+
+    #[test]
+    fn test_tokenize_strings() {
+        let ctx = Ctx {
+            strings: true,
+            ..Ctx::default()
+        };
+        let input_expected = &[
+            (
+                r#"lookup("steel", 2)"#,
+                vec![
+                    Id("lookup"),
+                    OpenParen,
+                    Str("steel"),
+                    Comma,
+                    Num(2.0),
+                    ClosedParen,
+                ],
+            ),
+            (r#""a b"+"""#, vec![Str("a b"), Id("+"), Str("")]),
+            (
+                r#"1 + "a"#,
+                vec![Num(1.0), Id("+"), BadToken("\"a", UnterminatedString)],
+            ),
+        ];
+        for (input, expected) in input_expected {
+            let output = tokenize(input, &ctx);
+            assert_eq!(output, *expected, "input was {}", input);
+        }
+        // without the strings the quotes are the part of the identifier
+        assert_eq!(tokenize(r#""a""#, &Ctx::default()), vec![Id(r#""a""#)]);
+    }
+
+    #[test]
+    fn test_tokenize_comments() {
+        let ctx = Ctx {
+            comments: true,
+            ..Ctx::default()
+        };
+        let input_expected = &[
+            (
+                "1 + /* the rate */ r # per year",
+                vec![Num(1.0), Id("+"), Id("r")],
+            ),
+            ("# only the comment", vec![]),
+            ("a# b\n* 2", vec![Id("a"), Id("*"), Num(2.0)]),
+            ("a/**/b", vec![Id("a"), Id("b")]),
+            (
+                "2 /*/ 3",
+                vec![Num(2.0), BadToken("/*/ 3", UnterminatedComment)],
+            ),
+        ];
+        for (input, expected) in input_expected {
+            let output = tokenize(input, &ctx);
+            assert_eq!(output, *expected, "input was {}", input);
+        }
+        let (_, spans) = tokenize_with_spans("/* x */ ab # y\n+ 1", &ctx);
+        assert_eq!(spans[0], Span { start: 8, end: 10 });
+        assert_eq!(spans[1], Span { start: 15, end: 16 });
+        // without the comments `#` is the part of the identifier
+        assert_eq!(tokenize("a#b", &Ctx::default()), vec![Id("a#b")]);
+    }
+
+    #[test]
+    fn test_tokenize_strict_ids() {
+        let ctx = Ctx {
+            strict_ids: true,
+            ..Ctx::default()
+        };
+        let input_expected = &[
+            (
+                "a$b",
+                vec![Id("a"), BadToken("$", InvalidCharacter), Id("b")],
+            ),
+            ("x_1 + _y", vec![Id("x_1"), Id("+"), Id("_y")]),
+            ("2x", vec![Num(2.0), Id("x")]),
+            ("1.2.3", vec![BadToken("1.2.3", MalformedNumber)]),
+            (
+                "é+1",
+                vec![BadToken("é", InvalidCharacter), Id("+"), Num(1.0)],
+            ),
+            ("`a$b`", vec![QuotedId("a$b")]),
+            (
+                "max(a,b)",
+                vec![Id("max"), OpenParen, Id("a"), Comma, Id("b"), ClosedParen],
+            ),
+        ];
+        for (input, expected) in input_expected {
+            let output = tokenize(input, &ctx);
+            assert_eq!(output, *expected, "input was {}", input);
+        }
+        assert_eq!(tokenize("a$b", &Ctx::default()), vec![Id("a$b")]);
+    }
+
+    #[test]
+    fn test_tokenize_decimal_comma() {
+        let ctx = Ctx {
+            tokenize_options: TokenizeOptions {
+                decimal_separator: DecimalSeparator::Comma,
+                ..TokenizeOptions::default()
+            },
+            ..Ctx::default()
+        };
+        let input_expected = &[
+            (
+                "max(1,5; 2,5)",
+                vec![Id("max"), OpenParen, Num(1.5), Comma, Num(2.5), ClosedParen],
+            ),
+            ("a;b", vec![Id("a"), Comma, Id("b")]),
+            ("0,25e2", vec![Num(25.0)]),
+            ("1,2,3", vec![BadToken("1,2,3", MalformedNumber)]),
+        ];
+        for (input, expected) in input_expected {
+            let output = tokenize(input, &ctx);
+            assert_eq!(output, *expected, "input was {}", input);
+        }
+        assert_eq!(
+            match_number_with("2,5 + 1", DecimalSeparator::Comma).map(|m| m.1),
+            Some(3)
+        );
+        assert!(match_number_with("2,5,", DecimalSeparator::Comma).is_none());
+    }
+
+    #[test]
+    fn test_span_position() {
+        let input = "a +\r\n  (bé *\n\n  c)";
+        let (_, spans) = tokenize_with_spans(input, &Ctx::default());
+        let positions: Vec<_> = spans
+            .iter()
+            .map(|span| {
+                let Position { line, column } = span.position(input);
+                (line, column)
+            })
+            .collect();
+        assert_eq!(
+            positions,
+            vec![(1, 1), (1, 3), (2, 3), (2, 4), (2, 7), (4, 3), (4, 4)]
+        );
+        // the end of the input
+        let end = Span {
+            start: input.len(),
+            end: input.len(),
+        };
+        assert_eq!(end.position(input), Position { line: 4, column: 5 });
+    }
+
+    #[test]
+    fn test_tokenize_array_literal() {
+        let ctx = crate::value::array::array_ctx();
+        let input_expected = &[
+            (
+                "[1, a]",
+                vec![
+                    Id("array"),
+                    OpenParen,
+                    Num(1.0),
+                    Comma,
+                    Id("a"),
+                    ClosedParen,
+                ],
+            ),
+            ("[ ]", vec![Id("array"), OpenParen, ClosedParen]),
+            ("[a b]", vec![QuotedId("a b")]),
+            ("[max(1, 2)]", vec![QuotedId("max(1, 2)")]),
+            (
+                "[[a], 2]",
+                vec![
+                    Id("array"),
+                    OpenParen,
+                    QuotedId("a"),
+                    Comma,
+                    Num(2.0),
+                    ClosedParen,
+                ],
+            ),
+            ("1]", vec![Num(1.0), Id("]")]),
+        ];
+        for (input, expected) in input_expected {
+            let output = tokenize(input, &ctx);
+            assert_eq!(output, *expected, "input was {}", input);
+        }
+        // without the array function the brackets are the quoted identifier
+        assert_eq!(tokenize("[1, a]", &Ctx::default()), vec![QuotedId("1, a")]);
+        // the bracket is the span of both the function and the paren
+        let (_, spans) = tokenize_with_spans("[1, 2]", &ctx);
+        assert_eq!(spans[0], Span { start: 0, end: 1 });
+        assert_eq!(spans[1], Span { start: 0, end: 1 });
+        assert_eq!(spans[5], Span { start: 5, end: 6 });
+    }
+
+    #[test]
+    fn test_tokenize_index() {
+        let ctx = crate::value::array::array_ctx();
+        let input_expected = &[
+            (
+                "prices[i]",
+                vec![Id("prices"), Id("[]"), OpenParen, Id("i"), ClosedParen],
+            ),
+            (
+                "prices[i + 1]",
+                vec![
+                    Id("prices"),
+                    Id("[]"),
+                    OpenParen,
+                    Id("i"),
+                    Id("+"),
+                    Num(1.0),
+                    ClosedParen,
+                ],
+            ),
+            ("a [i]", vec![Id("a"), QuotedId("i")]),
+            (
+                "f(x)[0]",
+                vec![
+                    Id("f"),
+                    OpenParen,
+                    Id("x"),
+                    ClosedParen,
+                    Id("[]"),
+                    OpenParen,
+                    Num(0.0),
+                    ClosedParen,
+                ],
+            ),
+            (
+                "[1, 2][0]",
+                vec![
+                    Id("array"),
+                    OpenParen,
+                    Num(1.0),
+                    Comma,
+                    Num(2.0),
+                    ClosedParen,
+                    Id("[]"),
+                    OpenParen,
+                    Num(0.0),
+                    ClosedParen,
+                ],
+            ),
+            (
+                "prices[[0, 2]]",
+                vec![
+                    Id("prices"),
+                    Id("[]"),
+                    OpenParen,
+                    Id("array"),
+                    OpenParen,
+                    Num(0.0),
+                    Comma,
+                    Num(2.0),
+                    ClosedParen,
+                    ClosedParen,
+                ],
+            ),
+        ];
+        for (input, expected) in input_expected {
+            let output = tokenize(input, &ctx);
+            assert_eq!(output, *expected, "input was {}", input);
+        }
+        // without the index operator the identifier takes the brackets
+        assert_eq!(tokenize("a[i]", &Ctx::default()), vec![Id("a[i]")]);
+        // the bracket is the span of both the operator and the paren
+        let (_, spans) = tokenize_with_spans("ab[1]", &ctx);
+        assert_eq!(spans[1], Span { start: 2, end: 3 });
+        assert_eq!(spans[2], Span { start: 2, end: 3 });
+        assert_eq!(spans[4], Span { start: 4, end: 5 });
+    }
+
+    #[test]
+    fn test_tokenize_named_args() {
+        use crate::functions::{Func, Param};
+
+        let mut ctx = Ctx::default();
+        ctx.fns
+            .push(Func::new("normal", None, |_| 0.0).with_params(vec![
+                Param::required("x"),
+                Param::optional("mean", 0.0),
+                Param::optional("sd", 1.0),
+            ]));
+        let input_expected = &[
+            (
+                "normal(x, sd = 2)",
+                vec![
+                    Id("normal"),
+                    OpenParen,
+                    Id("x"),
+                    Comma,
+                    ArgName("sd"),
+                    Num(2.0),
+                    ClosedParen,
+                ],
+            ),
+            (
+                "normal(mean=-1)",
+                vec![
+                    Id("normal"),
+                    OpenParen,
+                    ArgName("mean"),
+                    Id("-"),
+                    Num(1.0),
+                    ClosedParen,
+                ],
+            ),
+            (
+                "normal(x == 1)",
+                vec![
+                    Id("normal"),
+                    OpenParen,
+                    Id("x"),
+                    Id("=="),
+                    Num(1.0),
+                    ClosedParen,
+                ],
+            ),
+            (
+                "normal((sd = 1))",
+                vec![
+                    Id("normal"),
+                    OpenParen,
+                    OpenParen,
+                    Id("sd"),
+                    Id("="),
+                    Num(1.0),
+                    ClosedParen,
+                    ClosedParen,
+                ],
+            ),
+            (
+                "max(normal(sd = 1), sd = 1)",
+                vec![
+                    Id("max"),
+                    OpenParen,
+                    Id("normal"),
+                    OpenParen,
+                    ArgName("sd"),
+                    Num(1.0),
+                    ClosedParen,
+                    Comma,
+                    Id("sd"),
+                    Id("="),
+                    Num(1.0),
+                    ClosedParen,
+                ],
+            ),
+        ];
+        for (input, expected) in input_expected {
+            assert_eq!(tokenize(input, &ctx), *expected, "input was {}", input);
+        }
+    }
+
+    #[test]
+    fn test_quote_id() {
+        let ctx = Ctx::default();
+        let inputs = &[
+            "a",
+            "_a1",
+            "1a",
+            "unit price",
+            "a-b",
+            "x`y",
+            "(",
+            "a]",
+            "π",
+            "é1",
+            "€",
+        ];
+        for input in inputs {
+            let quoted = quote_id(input);
+            match tokenize(&quoted, &ctx).as_slice() {
+                [Id(id)] | [QuotedId(id)] => assert_eq!(id, input, "input was {}", input),
+                tokens => panic!("input was {}, tokens {:?}", input, tokens),
+            }
+        }
+    }
+
+    #[test]
+    fn test_match_longest_op() {
+        let mut bi_ops = binary::default_operators();
+        bi_ops.push(BiOp {
+            token: "**".to_owned(),
+            ..binary::POWER.clone()
+        });
+        let matched = match_bi_op("**2", &bi_ops).map(|Match(op, c)| (op.token.as_str(), c));
+        assert_eq!(matched, Some(("**", 2)));
+        let matched = match_bi_op("*2", &bi_ops).map(|Match(op, c)| (op.token.as_str(), c));
+        assert_eq!(matched, Some(("*", 1)));
+    }
+
+    #[test]
+    fn test_match_number() {
+        let input_expected = &[
+            ("12", Some((12.0, 2))),
+            ("2.5", Some((2.5, 3))),
+            ("1e10", Some((1e10, 4))),
+            ("2.5E-3", Some((2.5e-3, 6))),
+            ("1e+6 + 1", Some((1e6, 4))),
+            ("1.e2", Some((100.0, 4))),
+            ("2exp(1)", Some((2.0, 1))),
+            ("2e_", Some((2.0, 1))),
+            ("2e", None),
+            ("2e+", None),
+            ("2e-x", None),
+            ("2e*3", None),
+            ("1.2.3", None),
+        ];
+        for (input, expected) in input_expected {
+            let res = match_number(input).map(|Match(n, c)| (n, c));
+            assert_eq!(res, *expected, "input was {}", input);
+        }
+    }
+
     #[test]
     fn test_match_number_fails() {
         let str = "not a number";
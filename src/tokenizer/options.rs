@@ -0,0 +1,63 @@
+/// The options of the tokenizer, see [`Ctx::tokenize_options`](crate::Ctx::tokenize_options).
+///
+/// Every function that tokenizes the input with the context uses them,
+/// so the parser and the evaluator read the same syntax as [`tokenize`](super::tokenize).
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use rusty_yard::evaluator::eval_str_with_vars_and_ctx;
+/// use rusty_yard::tokenizer::{DecimalSeparator, TokenizeOptions};
+/// use rusty_yard::Ctx;
+///
+/// let ctx = Ctx {
+///     tokenize_options: TokenizeOptions {
+///         decimal_separator: DecimalSeparator::Comma,
+///         case_insensitive: true,
+///     },
+///     ..Ctx::default()
+/// };
+/// let mut vars = HashMap::new();
+/// assert_eq!(eval_str_with_vars_and_ctx("MAX(1,5; 2,5)", &mut vars, &ctx), Ok(2.5));
+/// ```
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub struct TokenizeOptions {
+    /// The character that separates the integer part of the number from the fraction.
+    pub decimal_separator: DecimalSeparator,
+    /// The functions and the constants are found regardless of the case of their tokens, `SIN(PI)` is `sin(pi)`.
+    ///
+    /// The token with the same case wins, the variables and the operators are still case-sensitive.
+    pub case_insensitive: bool,
+}
+
+/// The decimal separator of the numbers, see [`TokenizeOptions`](TokenizeOptions).
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub enum DecimalSeparator {
+    /// `1.5`, the arguments of the functions are separated by `,`.
+    #[default]
+    Dot,
+    /// `1,5`, the arguments of the functions are separated by `;`, like in the spreadsheets of many locales.
+    ///
+    /// The `;` inside the parentheses is then not the statement separator of
+    /// [`split_statements`](crate::parser::split_statements), only the `;` outside of them is.
+    Comma,
+}
+
+impl DecimalSeparator {
+    /// The character of the separator.
+    pub fn char(self) -> char {
+        match self {
+            DecimalSeparator::Dot => '.',
+            DecimalSeparator::Comma => ',',
+        }
+    }
+
+    /// The character that separates the arguments of the functions.
+    pub fn argument_separator(self) -> char {
+        match self {
+            DecimalSeparator::Dot => ',',
+            DecimalSeparator::Comma => ';',
+        }
+    }
+}
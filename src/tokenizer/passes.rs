@@ -0,0 +1,219 @@
+//! Token stream transformations that run between [`tokenize`](crate::tokenizer::tokenize) and [`parse`](crate::parser::parse).
+//!
+//! The passes are registered in [`Ctx::passes`](crate::Ctx::passes) and run in the order they were registered
+//! by every function that parses the string, e.g. [`parse_str`](crate::parser::parse_str).
+//!
+//! # Example
+//!
+//! ```
+//! use rusty_yard::evaluator::eval_str_with;
+//! use rusty_yard::tokenizer::passes::ImplicitMultiplication;
+//! use rusty_yard::Ctx;
+//! use std::collections::HashMap;
+//!
+//! let mut ctx = Ctx::default();
+//! ctx.passes.push(Box::new(ImplicitMultiplication));
+//! let mut vars = HashMap::new();
+//! vars.insert("x".to_owned(), 3.0);
+//! assert_eq!(eval_str_with("2x(x + 1)", &mut vars, &ctx), Ok(24.0));
+//! ```
+use std::fmt::Debug;
+
+use super::{Span, Token};
+use crate::Ctx;
+
+/// Implement this trait (+ [`Debug`](std::fmt::Debug)) to create your own token pass.
+///
+/// Passes are required to be [`Send`](std::marker::Send) and [`Sync`](std::marker::Sync),
+/// so that [`Ctx`](crate::Ctx) can be shared between threads.
+pub trait TokenPass: Debug + Send + Sync {
+    /// Transforms the `tokens` produced by the tokenizer or by the previous pass.
+    fn apply<'a, 'ctx>(&self, tokens: Vec<Token<'a, 'ctx>>, ctx: &'ctx Ctx)
+        -> Vec<Token<'a, 'ctx>>;
+}
+
+/// Runs every pass from the `ctx` over the `tokens`.
+pub fn apply_passes<'a, 'ctx>(
+    tokens: Vec<Token<'a, 'ctx>>,
+    ctx: &'ctx Ctx,
+) -> Vec<Token<'a, 'ctx>> {
+    ctx.passes
+        .iter()
+        .fold(tokens, |tokens, pass| pass.apply(tokens, ctx))
+}
+
+/// Same as [`apply_passes`](apply_passes), but also keeps the `spans` of the tokens in sync.
+///
+/// Passes only see the tokens, so the spans of their output are recovered by comparing it with the input:
+/// the unchanged tokens keep their spans, the replaced tokens get the span of the token they replaced
+/// and the inserted tokens get the empty span before the next token.
+pub fn apply_passes_with_spans<'a, 'ctx>(
+    tokens: Vec<Token<'a, 'ctx>>,
+    spans: Vec<Span>,
+    ctx: &'ctx Ctx,
+) -> (Vec<Token<'a, 'ctx>>, Vec<Span>) {
+    ctx.passes
+        .iter()
+        .fold((tokens, spans), |(tokens, spans), pass| {
+            let end = spans.last().map_or(0, |span| span.end);
+            let original = tokens.clone();
+            let passed = pass.apply(tokens, ctx);
+            let spans = align_spans(&original, &spans, &passed, end);
+            (passed, spans)
+        })
+}
+
+fn align_spans(original: &[Token], spans: &[Span], passed: &[Token], end: usize) -> Vec<Span> {
+    let mut result = Vec::with_capacity(passed.len());
+    let mut i = 0;
+    for (j, token) in passed.iter().enumerate() {
+        // the token was removed by the pass
+        if original.get(i) != Some(token) && original.get(i + 1) == Some(token) {
+            i += 1;
+        }
+        let Some(span) = spans.get(i) else {
+            result.push(Span { start: end, end });
+            continue;
+        };
+        if original[i] == *token || original.get(i + 1) == passed.get(j + 1) {
+            // the token is the same or it is replaced
+            result.push(*span);
+            i += 1;
+        } else {
+            result.push(Span {
+                start: span.start,
+                end: span.start,
+            });
+        }
+    }
+    result
+}
+
+/// The pass that inserts `*` where the multiplication is implied, e.g. `2x`, `2(a + b)`, `(a)(b)` or `x max(a, b)`.
+///
+/// The multiplication is inserted after a number, a variable or `)`
+/// when it is followed by a variable, a function or `(`.
+/// The identifiers that are the operators of the context are never multiplied
+/// and the identifiers that are the functions of the context are never multiplied on the left.
+/// The context should have the `*` binary operator.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct ImplicitMultiplication;
+
+impl TokenPass for ImplicitMultiplication {
+    fn apply<'a, 'ctx>(
+        &self,
+        tokens: Vec<Token<'a, 'ctx>>,
+        ctx: &'ctx Ctx,
+    ) -> Vec<Token<'a, 'ctx>> {
+        let is_op = |id: &str| ctx.bi_op(id).is_some() || ctx.u_ops.iter().any(|op| op.token == id);
+        let ends_operand = |token: &Token| match token {
+            Token::Num(_) | Token::QuotedId(_) | Token::ClosedParen => true,
+            // the operator that is also binary, e.g. `%`, is binary before the operand
+            Token::Id(id) if ctx.postfix_op(id).is_some() => ctx.bi_op(id).is_none(),
+            Token::Id(id) => !is_op(id) && ctx.func(id).is_none(),
+            _ => false,
+        };
+        let starts_operand = |token: &Token| match token {
+            Token::QuotedId(_) | Token::OpenParen => true,
+            Token::Id(id) => !is_op(id),
+            _ => false,
+        };
+        let mut output = Vec::with_capacity(tokens.len());
+        for token in tokens {
+            if output.last().is_some_and(ends_operand) && starts_operand(&token) {
+                output.push(Token::Id("*"));
+            }
+            output.push(token);
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::{tokenize, tokenize_with_spans};
+    use Token::*;
+
+    #[test]
+    fn test_implicit_multiplication() {
+        let input_expected = &[
+            ("2x", vec![Num(2.0), Id("*"), Id("x")]),
+            ("2 * x", vec![Num(2.0), Id("*"), Id("x")]),
+            ("2 3", vec![Num(2.0), Num(3.0)]),
+            ("x - 1", vec![Id("x"), Id("-"), Num(1.0)]),
+            (
+                "(a)(b)",
+                vec![
+                    OpenParen,
+                    Id("a"),
+                    ClosedParen,
+                    Id("*"),
+                    OpenParen,
+                    Id("b"),
+                    ClosedParen,
+                ],
+            ),
+            (
+                "2max(a)",
+                vec![
+                    Num(2.0),
+                    Id("*"),
+                    Id("max"),
+                    OpenParen,
+                    Id("a"),
+                    ClosedParen,
+                ],
+            ),
+            ("a `b c`", vec![Id("a"), Id("*"), QuotedId("b c")]),
+        ];
+        let ctx = Ctx::default();
+        for (input, expected) in input_expected {
+            let tokens = ImplicitMultiplication.apply(tokenize(input, &ctx), &ctx);
+            assert_eq!(tokens, *expected, "input was {}", input);
+        }
+    }
+
+    #[derive(Debug)]
+    struct Replace(&'static str, &'static str);
+
+    impl TokenPass for Replace {
+        fn apply<'a, 'ctx>(
+            &self,
+            tokens: Vec<Token<'a, 'ctx>>,
+            _ctx: &'ctx Ctx,
+        ) -> Vec<Token<'a, 'ctx>> {
+            tokens
+                .into_iter()
+                .map(|token| match token {
+                    Id(id) if id == self.0 => Id(self.1),
+                    token => token,
+                })
+                .collect()
+        }
+    }
+
+    #[test]
+    fn test_apply_passes_with_spans() {
+        let mut ctx = Ctx::default();
+        ctx.passes.push(Box::new(ImplicitMultiplication));
+        ctx.passes.push(Box::new(Replace("a", "b")));
+        let (tokens, spans) = tokenize_with_spans("2a + c", &ctx);
+        let (tokens, spans) = apply_passes_with_spans(tokens, spans, &ctx);
+        assert_eq!(tokens, vec![Num(2.0), Id("*"), Id("b"), Id("+"), Id("c")]);
+        let spans = spans
+            .iter()
+            .map(|span| (span.start, span.end))
+            .collect::<Vec<_>>();
+        assert_eq!(spans, vec![(0, 1), (1, 1), (1, 2), (3, 4), (5, 6)]);
+    }
+
+    #[test]
+    fn test_apply_passes_in_order() {
+        let mut ctx = Ctx::default();
+        ctx.passes.push(Box::new(Replace("a", "b")));
+        ctx.passes.push(Box::new(Replace("b", "c")));
+        let tokens = apply_passes(tokenize("a + b", &ctx), &ctx);
+        assert_eq!(tokens, vec![Id("c"), Id("+"), Id("c")]);
+    }
+}
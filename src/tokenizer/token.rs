@@ -1,14 +1,16 @@
+use std::fmt::{self, Display, Formatter};
+
 use crate::macros::Macro;
 
 /// Represents a macro token, part of [`Token::Macro`](Token::Macro)
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct MacroToken<'a, 'ctx> {
     pub text: &'a str,
     pub definition: &'ctx dyn Macro,
 }
 
 /// Represents tokenizers token, generally produced by [`tokenizer::tokenize`](super::tokenize).
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Token<'a, 'ctx> {
     /// Open parenthesis ('(') token.
     OpenParen,
@@ -18,18 +20,54 @@ pub enum Token<'a, 'ctx> {
     Comma,
     /// Identifier token.
     ///
-    /// The definition is very relaxed by design (one or more characters that are `|char| char.is_ascii_graphic()` but not '(', ')', ',')
+    /// The definition is very relaxed by design (one or more characters that are `|char| char.is_ascii_graphic()` or Unicode alphanumeric but not '(', ')', ',')
     Id(&'a str),
+    /// Quoted identifier token, without the quotes.
+    ///
+    /// The identifier in backticks or brackets (`` `unit price` `` or `[unit price]`), it is always a variable.
+    QuotedId(&'a str),
     /// Primitive (number).
     Num(f64),
+    /// The [string literal](super#string-literals), without the quotes.
+    Str(&'a str),
     /// Represents the bad token, i.e it could not be tokenized by any other rules.
-    BadToken(&'a str),
+    BadToken(&'a str, BadTokenReason),
+    /// The name of the [`named argument`](super#named-arguments) of the call, without `=`.
+    ArgName(&'a str),
     /// Macro token
     ///
     /// Macros are the fist to match, so you can override any default behavior of any other variants using macros.
     Macro(MacroToken<'a, 'ctx>),
 }
 
+/// The reason why the text is the [`BadToken`](Token::BadToken).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BadTokenReason {
+    /// The character can't start any token, e.g. `€`.
+    InvalidCharacter,
+    /// The text starts like the number, but it is not one, e.g. `1.2.3` or `2e+`.
+    MalformedNumber,
+    /// The quoted identifier is not closed, e.g. `` `unit price ``, the bad token is the rest of the input.
+    UnterminatedQuote,
+    /// The [string literal](super#string-literals) is not closed, e.g. `"steel`, the bad token is the rest of the input.
+    UnterminatedString,
+    /// The block [comment](super#comments) is not closed, e.g. `/* rate`, the bad token is the rest of the input.
+    UnterminatedComment,
+}
+
+impl Display for BadTokenReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            BadTokenReason::InvalidCharacter => "invalid character",
+            BadTokenReason::MalformedNumber => "malformed number",
+            BadTokenReason::UnterminatedQuote => "unterminated quoted identifier",
+            BadTokenReason::UnterminatedString => "unterminated string literal",
+            BadTokenReason::UnterminatedComment => "unterminated block comment",
+        };
+        f.write_str(reason)
+    }
+}
+
 impl Token<'_, '_> {
     /// Returns the text representation of the token
     pub fn token_text(&self) -> String {
@@ -38,8 +76,11 @@ impl Token<'_, '_> {
             OpenParen => String::from("("),
             ClosedParen => String::from(")"),
             Id(s) => String::from(*s),
+            QuotedId(s) => format!("`{}`", s),
             Num(n) => n.to_string(),
-            BadToken(s) => format!("<BAD TOKEN>({})", s),
+            Str(s) => format!("\"{}\"", s),
+            BadToken(s, _) => format!("<BAD TOKEN>({})", s),
+            ArgName(s) => format!("{} =", s),
             Comma => String::from(","),
             Macro(MacroToken { text, definition }) => format!("<MACRO {:?}>({})", definition, text),
         }
@@ -55,9 +96,14 @@ impl PartialEq for Token<'_, '_> {
             (ClosedParen, ClosedParen) => true,
             (Comma, Comma) => true,
             (Id(s1), Id(s2)) => s1 == s2,
+            (QuotedId(s1), QuotedId(s2)) => s1 == s2,
             (Num(f1), Num(f2)) => f1 == f2,
-            (BadToken(b1), BadToken(b2)) => b1 == b2,
-            (Macro(_), Macro(_)) => unimplemented!(),
+            (Str(s1), Str(s2)) => s1 == s2,
+            (BadToken(b1, r1), BadToken(b2, r2)) => b1 == b2 && r1 == r2,
+            (ArgName(n1), ArgName(n2)) => n1 == n2,
+            (Macro(m1), Macro(m2)) => {
+                m1.text == m2.text && std::ptr::addr_eq(m1.definition, m2.definition)
+            }
             _ => false,
         }
     }
@@ -0,0 +1,95 @@
+use super::operator_aliases;
+use crate::Ctx;
+
+/// Prefix tree over the tokens of the operators of the context
+///
+/// [`tokenize`](super::tokenize) builds it once per input, so that matching the operators at every position
+/// depends on the length of the operators instead of their number.
+pub(super) struct OperatorTrie {
+    nodes: Vec<TrieNode>,
+}
+
+#[derive(Default)]
+struct TrieNode {
+    children: Vec<(u8, usize)>,
+    /// The token that ends at this node is a binary operator
+    bi_op: bool,
+    /// The token that ends at this node is a unary operator
+    u_op: bool,
+}
+
+impl OperatorTrie {
+    pub(super) fn new(ctx: &Ctx) -> Self {
+        let mut trie = OperatorTrie {
+            nodes: vec![TrieNode::default()],
+        };
+        for op in &ctx.bi_ops {
+            let node = trie.insert(&op.token);
+            trie.nodes[node].bi_op = true;
+        }
+        for op in &ctx.u_ops {
+            let node = trie.insert(&op.token);
+            trie.nodes[node].u_op = true;
+        }
+        for alias in operator_aliases(ctx) {
+            let node = trie.insert(alias.alias);
+            trie.nodes[node].bi_op |= alias.bi_op;
+            trie.nodes[node].u_op |= alias.u_op;
+        }
+        trie
+    }
+
+    fn insert(&mut self, token: &str) -> usize {
+        let mut node = 0;
+        for byte in token.bytes() {
+            node = match self.child(node, byte) {
+                Some(child) => child,
+                None => {
+                    self.nodes.push(TrieNode::default());
+                    let child = self.nodes.len() - 1;
+                    self.nodes[node].children.push((byte, child));
+                    child
+                }
+            };
+        }
+        node
+    }
+
+    fn child(&self, node: usize, byte: u8) -> Option<usize> {
+        self.nodes[node]
+            .children
+            .iter()
+            .find(|(b, _)| *b == byte)
+            .map(|(_, child)| *child)
+    }
+
+    /// The nodes of the tokens that are prefixes of `text`, with the length of the prefix
+    fn prefixes<'t>(&'t self, text: &'t str) -> impl Iterator<Item = (usize, &'t TrieNode)> + 't {
+        let mut node = Some(0);
+        let root = std::iter::once((0, &self.nodes[0]));
+        root.chain(text.bytes().enumerate().map_while(move |(i, byte)| {
+            node = self.child(node?, byte);
+            node.map(|node| (i + 1, &self.nodes[node]))
+        }))
+    }
+
+    /// Same as [`match_op`](super::match_op): the longest binary operator, then the longest unary operator
+    pub(super) fn match_op(&self, text: &str) -> Option<usize> {
+        let mut bi_op = None;
+        let mut u_op = None;
+        for (len, node) in self.prefixes(text) {
+            if node.bi_op {
+                bi_op = Some(len);
+            }
+            if node.u_op {
+                u_op = Some(len);
+            }
+        }
+        bi_op.or(u_op)
+    }
+
+    /// Returns `true` if an operator is the prefix of `text`
+    pub(super) fn starts_with_op(&self, text: &str) -> bool {
+        self.prefixes(text).any(|(_, node)| node.bi_op || node.u_op)
+    }
+}
@@ -0,0 +1,456 @@
+//! Evaluation of the expressions with the numbers of other types than `f64`.
+//!
+//! The parser and the [`Ctx`](crate::Ctx) work with `f64`, the number literals are `f64` as well,
+//! but the parsed expression can be evaluated with any type that implements [`Value`](Value),
+//! e.g. `f32`, fixed-point, complex or arbitrary precision numbers.
+//!
+//! The built-in arithmetic operators (see [`BiOp::builtin`](crate::operators::BiOp::builtin))
+//! and the default unary operators are implemented by the [`Value`](Value) trait,
+//! other operators and functions need the implementation for the type in [`ValueImpls`](ValueImpls).
+//!
+//! The types that represent the decimal fractions exactly can [`parse the literals`](Value::parse_literal) themselves,
+//! so `0.1` is not rounded to `f64` first. With `decimal` feature `rust_decimal::Decimal`
+//! implements [`Value`](Value) this way, so `0.1 + 0.2` is exactly `0.3`, as the money calculations need.
+//!
+//! [`Interval`](interval::Interval) evaluates the expressions with the intervals to propagate the uncertainty of the inputs,
+//! [`Array`](array::Array) evaluates them with the arrays element-wise,
+//! and [`Quantity`](units::Quantity) checks and converts the [`units`](units) of the quantities.
+//!
+//! # Example
+//!
+//! ```
+//! use std::collections::HashMap;
+//! use rusty_yard::value::{eval_str_value, ValueImpls};
+//! use rusty_yard::Ctx;
+//!
+//! let ctx = Ctx::default();
+//! let mut impls = ValueImpls::<f32>::new();
+//! impls.add_func("sqrt", |args| args[0].sqrt());
+//! let mut vars = HashMap::new();
+//! vars.insert("a".to_owned(), 9.0f32);
+//! assert_eq!(eval_str_value("sqrt(a) * 2 ^ 2", &vars, &ctx, &impls), Ok(12.0f32));
+//! ```
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Formatter};
+use std::ops::{Add, Div, Mul, Neg, Sub};
+use std::sync::Arc;
+
+use crate::evaluator::Error;
+use crate::operators::binary::Builtin;
+use crate::operators::unary;
+use crate::parser::{parse, ParserToken};
+use crate::suggest::closest;
+use crate::tokenizer::{apply_passes, tokenize_with_spans, Token, INDEX_OPERATOR};
+use crate::Ctx;
+
+pub mod array;
+#[cfg(feature = "decimal")]
+mod decimal;
+pub mod interval;
+pub mod units;
+
+/// The numeric type the expressions can be evaluated with.
+pub trait Value:
+    Clone
+    + Debug
+    + PartialEq
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+{
+    /// Converts the number literal of the expression to this type.
+    fn from_f64(value: f64) -> Self;
+
+    /// Raises the value to the power of `exponent`, this implements the built-in `^` operator.
+    fn pow(self, exponent: Self) -> Self;
+
+    /// Converts the number literal from its text in the expression, e.g. `0.1` or `1e3`.
+    ///
+    /// With `None`, the default, the literal is converted with [`from_f64`](Value::from_f64) instead.
+    fn parse_literal(_text: &str) -> Option<Self> {
+        None
+    }
+
+    /// Applies the built-in arithmetic operator to the operands.
+    ///
+    /// The default implementation uses the arithmetic operators of the type and [`pow`](Value::pow),
+    /// the types which operators panic on the overflow or on the division by zero should report it instead.
+    fn apply_builtin(op: Builtin, left: Self, right: Self) -> Result<Self, ArithmeticError> {
+        Ok(match op {
+            Builtin::Add => left + right,
+            Builtin::Subtract => left - right,
+            Builtin::Multiply => left * right,
+            Builtin::Divide => left / right,
+            Builtin::Power => left.pow(right),
+        })
+    }
+
+    /// Returns the elements of the value at the `index`, this implements the [indexing](crate::tokenizer#indexing),
+    /// e.g. `prices[i]`.
+    ///
+    /// The default implementation treats the value as the array of one element, so its only index is `0`.
+    fn index(self, index: Self) -> Result<Self, ArithmeticError> {
+        if index == Self::from_f64(0.0) {
+            Ok(self)
+        } else {
+            Err(ArithmeticError::OutOfBounds)
+        }
+    }
+}
+
+/// The reason a built-in arithmetic operator has no result, see [`Value::apply_builtin`](Value::apply_builtin)
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ArithmeticError {
+    /// The divisor is zero, reported as [`Error::DivisionByZero`](Error::DivisionByZero)
+    DivisionByZero,
+    /// The result can't be represented, reported as [`Error::Overflow`](Error::Overflow)
+    Overflow,
+    /// The result is not defined for the operands, e.g. `(-4) ^ 0.5`,
+    /// reported as [`Error::DomainError`](Error::DomainError)
+    Undefined,
+    /// The operands are arrays of different lengths, reported as [`Error::LengthMismatch`](Error::LengthMismatch)
+    LengthMismatch,
+    /// The operands are quantities of incompatible dimensions,
+    /// reported as [`Error::IncompatibleUnits`](Error::IncompatibleUnits)
+    IncompatibleUnits,
+    /// The index is not the index of an element of the value, reported as [`Error::IndexOutOfBounds`](Error::IndexOutOfBounds)
+    OutOfBounds,
+}
+
+impl Value for f64 {
+    fn from_f64(value: f64) -> Self {
+        value
+    }
+
+    fn pow(self, exponent: Self) -> Self {
+        self.powf(exponent)
+    }
+}
+
+impl Value for f32 {
+    fn from_f64(value: f64) -> Self {
+        value as f32
+    }
+
+    fn pow(self, exponent: Self) -> Self {
+        self.powf(exponent)
+    }
+}
+
+/// The implementation of the unary operator for the value type.
+pub type ValueUOpFn<T> = dyn Fn(T) -> T + Send + Sync;
+
+/// The implementation of the binary operator for the value type.
+pub type ValueBiOpFn<T> = dyn Fn(T, T) -> T + Send + Sync;
+
+/// The implementation of the function for the value type.
+pub type ValueFuncFn<T> = dyn Fn(&[T]) -> T + Send + Sync;
+
+/// The implementations of the operators and the functions for the value type `T`, by their tokens.
+///
+/// They take precedence over the implementations provided by the [`Value`](Value) trait.
+#[derive(Clone)]
+pub struct ValueImpls<T> {
+    /// The implementations of the unary operators.
+    pub u_ops: HashMap<String, Arc<ValueUOpFn<T>>>,
+    /// The implementations of the binary operators.
+    pub bi_ops: HashMap<String, Arc<ValueBiOpFn<T>>>,
+    /// The implementations of the functions.
+    pub fns: HashMap<String, Arc<ValueFuncFn<T>>>,
+}
+
+impl<T> ValueImpls<T> {
+    /// Creates the empty implementations.
+    pub fn new() -> Self {
+        ValueImpls {
+            u_ops: HashMap::new(),
+            bi_ops: HashMap::new(),
+            fns: HashMap::new(),
+        }
+    }
+
+    /// Adds the implementation of the unary operator `token`.
+    pub fn add_u_op(
+        &mut self,
+        token: &str,
+        func: impl Fn(T) -> T + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.u_ops.insert(token.to_owned(), Arc::new(func));
+        self
+    }
+
+    /// Adds the implementation of the binary operator `token`.
+    pub fn add_bi_op(
+        &mut self,
+        token: &str,
+        func: impl Fn(T, T) -> T + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.bi_ops.insert(token.to_owned(), Arc::new(func));
+        self
+    }
+
+    /// Adds the implementation of the function `token`.
+    pub fn add_func(
+        &mut self,
+        token: &str,
+        func: impl Fn(&[T]) -> T + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.fns.insert(token.to_owned(), Arc::new(func));
+        self
+    }
+}
+
+impl<T> Default for ValueImpls<T> {
+    fn default() -> Self {
+        ValueImpls::new()
+    }
+}
+
+impl<T> Debug for ValueImpls<T> {
+    #[cfg_attr(tarpaulin, skip)]
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ValueImpls")
+            .field("u_ops", &self.u_ops.keys())
+            .field("bi_ops", &self.bi_ops.keys())
+            .field("fns", &self.fns.keys())
+            .finish()
+    }
+}
+
+/// Evaluates the token stream with the values of type `T`.
+///
+/// The number literals are converted with [`Value::from_f64`](Value::from_f64).
+/// The operators and the functions without the implementation for `T`, and the macros,
+/// are [`Unsupported`](Error::Unsupported).
+pub fn eval_value<T: Value>(
+    tokens: &[ParserToken],
+    variables: &HashMap<String, T>,
+    impls: &ValueImpls<T>,
+) -> Result<T, Error> {
+    eval_value_with_literals(tokens, variables, impls, &HashMap::new())
+}
+
+/// Same as [`eval_value`](eval_value), the `literals` are the numbers parsed by [`Value::parse_literal`](Value::parse_literal),
+/// by the bits of their `f64` values
+fn eval_value_with_literals<T: Value>(
+    tokens: &[ParserToken],
+    variables: &HashMap<String, T>,
+    impls: &ValueImpls<T>,
+    literals: &HashMap<u64, T>,
+) -> Result<T, Error> {
+    let mut eval_stack: Vec<T> = Vec::new();
+    for (position, token) in tokens.iter().enumerate() {
+        let value = match *token {
+            ParserToken::Num(n) => literals
+                .get(&n.to_bits())
+                .cloned()
+                .unwrap_or_else(|| T::from_f64(n)),
+            ParserToken::Id(id) => variables.get(id).cloned().ok_or_else(|| {
+                let names = variables.keys().map(String::as_str);
+                Error::VarNotFound(id.into(), closest(id, names))
+            })?,
+            ParserToken::Str(s) => return Err(Error::UnexpectedString(s.to_owned())),
+            ParserToken::UOp(op) => {
+                let operand = eval_stack.pop().ok_or(Error::EmptyEvalStack)?;
+                match impls.u_ops.get(&op.token) {
+                    Some(func) => func(operand),
+                    None if *op == *unary::NEGATE => -operand,
+                    None if *op == *unary::PLUS => operand,
+                    None if *op == *unary::PERCENT => {
+                        let hundred = T::from_f64(100.0);
+                        T::apply_builtin(Builtin::Divide, operand, hundred)
+                            .map_err(|e| arithmetic_error(e, &op.token, position))?
+                    }
+                    None => return Err(Error::Unsupported(op.token.clone())),
+                }
+            }
+            ParserToken::BiOp(op) => {
+                let right = eval_stack.pop().ok_or(Error::EmptyEvalStack)?;
+                let left = eval_stack.pop().ok_or(Error::EmptyEvalStack)?;
+                match (impls.bi_ops.get(&op.token), op.builtin) {
+                    (Some(func), _) => func(left, right),
+                    (None, Some(builtin)) => T::apply_builtin(builtin, left, right)
+                        .map_err(|e| arithmetic_error(e, &op.token, position))?,
+                    (None, None) if op.token == INDEX_OPERATOR => left
+                        .index(right)
+                        .map_err(|e| arithmetic_error(e, &op.token, position))?,
+                    (None, None) => return Err(Error::Unsupported(op.token.clone())),
+                }
+            }
+            ParserToken::Func(func, call_args) => {
+                if !func.accepts(call_args) {
+                    return Err(Error::ArityMismatch {
+                        id: func.token.clone(),
+                        expected: func.arity_range(),
+                        actual: call_args,
+                    });
+                }
+                let implementation = impls
+                    .fns
+                    .get(&func.token)
+                    .ok_or_else(|| Error::Unsupported(func.token.clone()))?;
+                if eval_stack.len() < call_args {
+                    return Err(Error::EmptyEvalStack);
+                }
+                let mut args = eval_stack.split_off(eval_stack.len() - call_args);
+                args.extend(func.defaults(call_args).map(T::from_f64));
+                implementation(&args)
+            }
+            ParserToken::Macro(ref m) => return Err(Error::Unsupported(m.name().to_owned())),
+        };
+        eval_stack.push(value);
+    }
+    eval_stack.pop().ok_or(Error::Other)
+}
+
+fn arithmetic_error(error: ArithmeticError, func: &str, position: usize) -> Error {
+    let func = func.to_owned();
+    match error {
+        ArithmeticError::DivisionByZero => Error::DivisionByZero { func, position },
+        ArithmeticError::Overflow => Error::Overflow { func, position },
+        ArithmeticError::Undefined => Error::DomainError {
+            func,
+            args: Vec::new(),
+            position,
+        },
+        ArithmeticError::LengthMismatch => Error::LengthMismatch { func, position },
+        ArithmeticError::IncompatibleUnits => Error::IncompatibleUnits { func, position },
+        ArithmeticError::OutOfBounds => Error::IndexOutOfBounds { position },
+    }
+}
+
+/// Parses the `input` using the `ctx` and evaluates it with [`eval_value`](eval_value).
+///
+/// The number literals of the `input` are converted with [`Value::parse_literal`](Value::parse_literal) when it can.
+pub fn eval_str_value<T: Value>(
+    input: &str,
+    variables: &HashMap<String, T>,
+    ctx: &Ctx,
+    impls: &ValueImpls<T>,
+) -> Result<T, Error> {
+    let (tokens, spans) = tokenize_with_spans(input, ctx);
+    let literals = tokens
+        .iter()
+        .zip(&spans)
+        .filter_map(|(token, span)| match token {
+            Token::Num(n) => Some((n.to_bits(), T::parse_literal(&input[span.start..span.end])?)),
+            _ => None,
+        })
+        .collect();
+    let tokens = apply_passes(tokens, ctx);
+    let parsed = parse(&tokens, ctx)?;
+    eval_value_with_literals(&parsed, variables, impls, &literals)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eval_str_value_f32() {
+        let input_expected = &[
+            ("1 + 2 * 3", Ok(7.0f32)),
+            ("-a ^ 2", Ok(4.0)),
+            ("+a / 4", Ok(-0.5)),
+            ("0.1 + 0.2", Ok(0.3)),
+            ("50% * a", Ok(-1.0)),
+            ("max(a, 1)", Ok(1.0)),
+            ("b", Err(Error::VarNotFound("b".to_owned(), None))),
+            ("sum(1, 2)", Err(Error::Unsupported("sum".to_owned()))),
+        ];
+        let ctx = Ctx::default();
+        let mut impls = ValueImpls::<f32>::new();
+        impls.add_func("max", |args| args[0].max(args[1]));
+        let mut vars = HashMap::new();
+        vars.insert("a".to_owned(), -2.0f32);
+        for (input, expected) in input_expected {
+            let result = eval_str_value(input, &vars, &ctx, &impls);
+            assert_eq!(result, *expected, "input was {}", input);
+        }
+    }
+
+    /// The dual number `value + derivative * ε`, where `ε² = 0`
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct Dual(f64, f64);
+
+    impl Add for Dual {
+        type Output = Dual;
+        fn add(self, rhs: Dual) -> Dual {
+            Dual(self.0 + rhs.0, self.1 + rhs.1)
+        }
+    }
+
+    impl Sub for Dual {
+        type Output = Dual;
+        fn sub(self, rhs: Dual) -> Dual {
+            Dual(self.0 - rhs.0, self.1 - rhs.1)
+        }
+    }
+
+    impl Mul for Dual {
+        type Output = Dual;
+        fn mul(self, rhs: Dual) -> Dual {
+            Dual(self.0 * rhs.0, self.0 * rhs.1 + self.1 * rhs.0)
+        }
+    }
+
+    impl Div for Dual {
+        type Output = Dual;
+        fn div(self, rhs: Dual) -> Dual {
+            Dual(
+                self.0 / rhs.0,
+                (self.1 * rhs.0 - self.0 * rhs.1) / (rhs.0 * rhs.0),
+            )
+        }
+    }
+
+    impl Neg for Dual {
+        type Output = Dual;
+        fn neg(self) -> Dual {
+            Dual(-self.0, -self.1)
+        }
+    }
+
+    impl Value for Dual {
+        fn from_f64(value: f64) -> Self {
+            Dual(value, 0.0)
+        }
+
+        fn pow(self, exponent: Self) -> Self {
+            // only constant exponents
+            let value = self.0.powf(exponent.0);
+            Dual(value, exponent.0 * self.0.powf(exponent.0 - 1.0) * self.1)
+        }
+    }
+
+    #[test]
+    fn test_eval_str_value_custom_type() {
+        let ctx = Ctx::default();
+        let mut impls = ValueImpls::new();
+        impls.add_func("sin", |args: &[Dual]| {
+            Dual(args[0].0.sin(), args[0].0.cos() * args[0].1)
+        });
+        let mut vars = HashMap::new();
+        vars.insert("x".to_owned(), Dual(2.0, 1.0));
+        let input_expected = &[
+            ("x ^ 3 - 2 * x", Dual(4.0, 10.0)),
+            ("1 / x", Dual(0.5, -0.25)),
+            ("sin(x - 2)", Dual(0.0, 1.0)),
+        ];
+        for (input, expected) in input_expected {
+            let result = eval_str_value(input, &vars, &ctx, &impls);
+            assert_eq!(result, Ok(*expected), "input was {}", input);
+        }
+    }
+
+    #[test]
+    fn test_eval_value_unsupported() {
+        let ctx = Ctx::default_with_macros();
+        let impls = ValueImpls::<f64>::new();
+        let vars = HashMap::new();
+        let result = eval_str_value("a = 1", &vars, &ctx, &impls);
+        assert_eq!(result, Err(Error::Unsupported("=".to_owned())));
+    }
+}
@@ -0,0 +1,372 @@
+//! Arrays of numbers, the expressions evaluated with [`Arrays`](Array) work element-wise.
+//!
+//! The arithmetic operators and the functions like `sqrt` are applied to each element of the array,
+//! the scalar operand is applied to every element of the other one, so `[1, 2, 3] * 2` is `[2, 4, 6]`.
+//! The reductions `sum`, `prod`, `mean`, `dot` and `len` turn the arrays into the scalars.
+//! The elements are [indexed](crate::tokenizer#indexing) from 0, `prices[i]` is the element of the array,
+//! and the array of the indices, `prices[[0, 2]]`, picks the array of the elements.
+//!
+//! The arrays are written as [`array literals`](crate::tokenizer#array-literals), `[1, 2, 3]`,
+//! when the context has [`array_functions`](array_functions), e.g. [`array_ctx`](array_ctx).
+//!
+//! # Example
+//!
+//! ```
+//! use std::collections::HashMap;
+//! use rusty_yard::value::array::{array_ctx, array_impls, Array};
+//! use rusty_yard::value::eval_str_value;
+//!
+//! let ctx = array_ctx();
+//! let mut vars = HashMap::new();
+//! vars.insert("prices".to_owned(), Array::Vector(vec![10.0, 20.0]));
+//! let total = eval_str_value("dot(prices, [3, 1]) * 2", &vars, &ctx, &array_impls());
+//! assert_eq!(total, Ok(Array::Scalar(100.0)));
+//! let last = eval_str_value("prices[len(prices) - 1]", &vars, &ctx, &array_impls());
+//! assert_eq!(last, Ok(Array::Scalar(20.0)));
+//! ```
+use std::fmt::{self, Display, Formatter};
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+use super::{ArithmeticError, Value, ValueImpls};
+use crate::functions::{round_to, Func};
+use crate::operators::binary::{Associativity, Builtin};
+use crate::operators::BiOp;
+use crate::tokenizer::{ARRAY_FUNCTION, INDEX_OPERATOR};
+use crate::Ctx;
+
+/// The number or the array of numbers.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Array {
+    /// The single number, it is applied to every element of the other operand.
+    Scalar(f64),
+    /// The array of numbers.
+    Vector(Vec<f64>),
+}
+
+impl Array {
+    /// The elements of the array, the scalar is the single element.
+    pub fn elements(&self) -> &[f64] {
+        match self {
+            Array::Scalar(value) => std::slice::from_ref(value),
+            Array::Vector(values) => values,
+        }
+    }
+
+    /// Applies `f` to each element.
+    fn map(self, f: impl Fn(f64) -> f64) -> Array {
+        match self {
+            Array::Scalar(value) => Array::Scalar(f(value)),
+            Array::Vector(values) => Array::Vector(values.into_iter().map(f).collect()),
+        }
+    }
+
+    /// Applies `f` to the pairs of the elements, the extra elements of the longer array are dropped.
+    fn zip_with(self, rhs: Array, f: impl Fn(f64, f64) -> f64) -> Array {
+        match (self, rhs) {
+            (Array::Scalar(a), Array::Scalar(b)) => Array::Scalar(f(a, b)),
+            (Array::Scalar(a), b) => b.map(|b| f(a, b)),
+            (a, Array::Scalar(b)) => a.map(|a| f(a, b)),
+            (Array::Vector(a), Array::Vector(b)) => {
+                Array::Vector(a.into_iter().zip(b).map(|(a, b)| f(a, b)).collect())
+            }
+        }
+    }
+}
+
+impl Display for Array {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Array::Scalar(value) => write!(f, "{}", value),
+            Array::Vector(values) => {
+                let values: Vec<_> = values.iter().map(f64::to_string).collect();
+                write!(f, "[{}]", values.join(", "))
+            }
+        }
+    }
+}
+
+impl Add for Array {
+    type Output = Array;
+    fn add(self, rhs: Array) -> Array {
+        self.zip_with(rhs, |a, b| a + b)
+    }
+}
+
+impl Sub for Array {
+    type Output = Array;
+    fn sub(self, rhs: Array) -> Array {
+        self.zip_with(rhs, |a, b| a - b)
+    }
+}
+
+impl Mul for Array {
+    type Output = Array;
+    fn mul(self, rhs: Array) -> Array {
+        self.zip_with(rhs, |a, b| a * b)
+    }
+}
+
+impl Div for Array {
+    type Output = Array;
+    fn div(self, rhs: Array) -> Array {
+        self.zip_with(rhs, |a, b| a / b)
+    }
+}
+
+impl Neg for Array {
+    type Output = Array;
+    fn neg(self) -> Array {
+        self.map(|a| -a)
+    }
+}
+
+impl Value for Array {
+    fn from_f64(value: f64) -> Self {
+        Array::Scalar(value)
+    }
+
+    fn pow(self, exponent: Self) -> Self {
+        self.zip_with(exponent, f64::powf)
+    }
+
+    fn apply_builtin(op: Builtin, left: Self, right: Self) -> Result<Self, ArithmeticError> {
+        if let (Array::Vector(a), Array::Vector(b)) = (&left, &right) {
+            if a.len() != b.len() {
+                return Err(ArithmeticError::LengthMismatch);
+            }
+        }
+        Ok(match op {
+            Builtin::Add => left + right,
+            Builtin::Subtract => left - right,
+            Builtin::Multiply => left * right,
+            Builtin::Divide => left / right,
+            Builtin::Power => left.pow(right),
+        })
+    }
+
+    fn index(self, index: Self) -> Result<Self, ArithmeticError> {
+        let elements = self.elements();
+        let element = |index: f64| {
+            if index.fract() == 0.0 && index >= 0.0 && index < elements.len() as f64 {
+                Ok(elements[index as usize])
+            } else {
+                Err(ArithmeticError::OutOfBounds)
+            }
+        };
+        match index {
+            Array::Scalar(index) => element(index).map(Array::Scalar),
+            Array::Vector(indices) => indices
+                .into_iter()
+                .map(element)
+                .collect::<Result<_, _>>()
+                .map(Array::Vector),
+        }
+    }
+}
+
+/// Get the functions the arrays need in the [`Ctx`](Ctx): `array`, `len`, `mean` and `dot`.
+///
+/// With `f64` evaluation the numbers are the arrays of one element, and the `array` function fails.
+pub fn array_functions() -> Vec<Func> {
+    vec![
+        Func::fallible(ARRAY_FUNCTION, None, |_| {
+            Err("the arrays are evaluated with value::array::Array".to_owned())
+        }),
+        Func::new("len", Some(1), |_| 1.0),
+        Func::new("mean", None, |args| {
+            args.iter().sum::<f64>() / args.len() as f64
+        }),
+        Func::new("dot", Some(2), |args| args[0] * args[1]),
+    ]
+}
+
+/// Get the [`INDEX_OPERATOR`](INDEX_OPERATOR) the arrays need in the [`Ctx`](Ctx) to be [indexed](crate::tokenizer#indexing).
+///
+/// It binds tighter than any other operator. With `f64` evaluation the number is the array of one element,
+/// so its only index is `0`, the evaluator reports the other indices as [`IndexOutOfBounds`](crate::evaluator::Error::IndexOutOfBounds).
+pub fn index_operator() -> BiOp {
+    BiOp::new(
+        INDEX_OPERATOR,
+        u32::MAX,
+        Associativity::LEFT,
+        |value, index| {
+            if index == 0.0 {
+                value
+            } else {
+                f64::NAN
+            }
+        },
+    )
+}
+
+/// Get the default context with [`array_functions`](array_functions) and [`index_operator`](index_operator).
+pub fn array_ctx() -> Ctx {
+    let mut ctx = Ctx::default();
+    ctx.fns.extend(array_functions());
+    ctx.bi_ops.push(index_operator());
+    ctx
+}
+
+/// Get the array implementations of [`array_functions`](array_functions),
+/// and of the [`default functions`](crate::functions::default_functions).
+///
+/// `array` concatenates its arguments, the reductions take all elements of all their arguments,
+/// and `dot` of the arrays of different lengths is `NaN`.
+/// The other functions are applied element-wise, `atan2`, `hypot` and `log` are not implemented.
+pub fn array_impls() -> ValueImpls<Array> {
+    fn elements(args: &[Array]) -> impl Iterator<Item = f64> + '_ {
+        args.iter().flat_map(|arg| arg.elements().iter().copied())
+    }
+
+    let mut impls = ValueImpls::new();
+    let element_wise = [
+        ("sin", f64::sin as fn(f64) -> f64),
+        ("cos", f64::cos),
+        ("tan", f64::tan),
+        ("asin", f64::asin),
+        ("acos", f64::acos),
+        ("atan", f64::atan),
+        ("sinh", f64::sinh),
+        ("cosh", f64::cosh),
+        ("tanh", f64::tanh),
+        ("ln", f64::ln),
+        ("log10", f64::log10),
+        ("log2", f64::log2),
+        ("exp", f64::exp),
+        ("sqrt", f64::sqrt),
+        ("cbrt", f64::cbrt),
+        ("abs", f64::abs),
+        ("floor", f64::floor),
+        ("ceil", f64::ceil),
+        ("signum", f64::signum),
+    ];
+    for (token, f) in element_wise {
+        impls.add_func(token, move |args: &[Array]| args[0].clone().map(f));
+    }
+    impls
+        .add_func(ARRAY_FUNCTION, |args| {
+            Array::Vector(elements(args).collect())
+        })
+        .add_func("round", |args| {
+            args[0].clone().zip_with(args[1].clone(), round_to)
+        })
+        .add_func("len", |args| Array::Scalar(args[0].elements().len() as f64))
+        .add_func("sum", |args| Array::Scalar(elements(args).sum()))
+        .add_func("prod", |args| Array::Scalar(elements(args).product()))
+        .add_func("mean", |args| {
+            let count = elements(args).count() as f64;
+            Array::Scalar(elements(args).sum::<f64>() / count)
+        })
+        .add_func("dot", |args| match (&args[0], &args[1]) {
+            (Array::Vector(a), Array::Vector(b)) if a.len() != b.len() => Array::Scalar(f64::NAN),
+            (a, b) => Array::Scalar(elements(&[a.clone() * b.clone()]).sum()),
+        })
+        .add_func("sub", |args| args[0].clone() - args[1].clone())
+        .add_func("max", |args| {
+            args[0].clone().zip_with(args[1].clone(), f64::max)
+        })
+        .add_func("min", |args| {
+            args[0].clone().zip_with(args[1].clone(), f64::min)
+        });
+    impls
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::evaluator::{eval_str_with_vars_and_ctx, Error};
+    use crate::value::eval_str_value;
+
+    #[test]
+    fn test_eval_str_value_array() {
+        let ctx = array_ctx();
+        let impls = array_impls();
+        let mut vars = HashMap::new();
+        vars.insert("v".to_owned(), Array::Vector(vec![1.0, 4.0, 9.0]));
+        vars.insert("x".to_owned(), Array::Scalar(2.0));
+        let vector = |values: &[f64]| Ok(Array::Vector(values.to_vec()));
+        let input_expected = &[
+            ("[1, 2, 3]", vector(&[1.0, 2.0, 3.0])),
+            ("[]", vector(&[])),
+            ("[1, 2] + [10, 20]", vector(&[11.0, 22.0])),
+            ("v * x + 1", vector(&[3.0, 9.0, 19.0])),
+            ("2 ^ [1, 2, 3]", vector(&[2.0, 4.0, 8.0])),
+            ("-v", vector(&[-1.0, -4.0, -9.0])),
+            ("v * 50%", vector(&[0.5, 2.0, 4.5])),
+            ("sqrt(v)", vector(&[1.0, 2.0, 3.0])),
+            ("max(v, 5)", vector(&[5.0, 5.0, 9.0])),
+            ("round([1.25, 2.5], 1)", vector(&[1.3, 2.5])),
+            ("round(v / 2)", vector(&[1.0, 2.0, 5.0])),
+            ("[[1, 2], [x, 4]]", vector(&[1.0, 2.0, 2.0, 4.0])),
+            ("[x, x + 1] * [(1 + 2), 3]", vector(&[6.0, 9.0])),
+            ("array(x)", vector(&[2.0])),
+            ("sum(v)", Ok(Array::Scalar(14.0))),
+            ("sum(v, [1, 2])", Ok(Array::Scalar(17.0))),
+            ("mean([1, 2, 3, 6])", Ok(Array::Scalar(3.0))),
+            ("dot(v, [1, 0, 1])", Ok(Array::Scalar(10.0))),
+            ("len(v)", Ok(Array::Scalar(3.0))),
+            ("len(x)", Ok(Array::Scalar(1.0))),
+            ("prod([1, 2, 3])", Ok(Array::Scalar(6.0))),
+            ("v[1]", Ok(Array::Scalar(4.0))),
+            ("v[len(v) - 1] + 1", Ok(Array::Scalar(10.0))),
+            ("v[[0, 2]]", vector(&[1.0, 9.0])),
+            ("[1, 2, 3][x]", Ok(Array::Scalar(3.0))),
+            ("-v[1]%", Ok(Array::Scalar(-0.04))),
+            ("x[0]", Ok(Array::Scalar(2.0))),
+            ("v[3]", Err(Error::IndexOutOfBounds { position: 2 })),
+            ("v[0.5]", Err(Error::IndexOutOfBounds { position: 2 })),
+            ("1 + x[1]", Err(Error::IndexOutOfBounds { position: 3 })),
+            (
+                "v + [1, 2]",
+                Err(Error::LengthMismatch {
+                    func: "+".to_owned(),
+                    position: 4,
+                }),
+            ),
+        ];
+        for (input, expected) in input_expected {
+            let result = eval_str_value(input, &vars, &ctx, &impls);
+            assert_eq!(result, *expected, "input was {}", input);
+        }
+    }
+
+    #[test]
+    fn test_eval_f64() {
+        let ctx = array_ctx();
+        let mut vars = HashMap::new();
+        vars.insert("unit price".to_owned(), 5.0);
+        let input_expected = &[
+            ("[unit price] * 2", Ok(10.0)),
+            ("mean(1, 2, 6)", Ok(3.0)),
+            ("dot(2, 3) + len(7)", Ok(7.0)),
+            ("[unit price][0] + 1", Ok(6.0)),
+            (
+                "[unit price][1]",
+                Err(Error::IndexOutOfBounds { position: 2 }),
+            ),
+            (
+                "2 * [unit price][-1]",
+                Err(Error::IndexOutOfBounds { position: 4 }),
+            ),
+            (
+                "[1, 2]",
+                Err(Error::FunctionError {
+                    id: ARRAY_FUNCTION.to_owned(),
+                    message: "the arrays are evaluated with value::array::Array".to_owned(),
+                }),
+            ),
+        ];
+        for (input, expected) in input_expected {
+            let result = eval_str_with_vars_and_ctx(input, &mut vars, &ctx);
+            assert_eq!(result, *expected, "input was {}", input);
+        }
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Array::Scalar(1.5).to_string(), "1.5");
+        assert_eq!(Array::Vector(vec![1.0, -2.5]).to_string(), "[1, -2.5]");
+    }
+}
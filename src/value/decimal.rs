@@ -0,0 +1,122 @@
+//! [`Value`](Value) implementation for [`Decimal`](Decimal).
+use rust_decimal::prelude::{FromPrimitive, MathematicalOps};
+use rust_decimal::Decimal;
+
+use super::{ArithmeticError, Value};
+use crate::operators::binary::Builtin;
+
+/// The decimal numbers with 28 significant digits, the literals keep all their digits.
+///
+/// `f64` values that are not decimal numbers (e.g. the constants like `nan`) become zero,
+/// the infinite ones become the largest decimal numbers.
+impl Value for Decimal {
+    fn from_f64(value: f64) -> Self {
+        <Decimal as FromPrimitive>::from_f64(value).unwrap_or(if value.is_nan() {
+            Decimal::ZERO
+        } else if value > 0.0 {
+            Decimal::MAX
+        } else {
+            Decimal::MIN
+        })
+    }
+
+    /// Panics on the overflow, [`apply_builtin`](Value::apply_builtin) reports it instead.
+    fn pow(self, exponent: Self) -> Self {
+        self.powd(exponent)
+    }
+
+    fn parse_literal(text: &str) -> Option<Self> {
+        Decimal::from_str_exact(text)
+            .or_else(|_| Decimal::from_scientific(text))
+            .ok()
+    }
+
+    fn apply_builtin(op: Builtin, left: Self, right: Self) -> Result<Self, ArithmeticError> {
+        let is_divisor_zero = match op {
+            Builtin::Divide => right.is_zero(),
+            Builtin::Power => left.is_zero() && right.is_sign_negative() && !right.is_zero(),
+            _ => false,
+        };
+        if is_divisor_zero {
+            return Err(ArithmeticError::DivisionByZero);
+        }
+        if op == Builtin::Power && left.is_sign_negative() && !right.fract().is_zero() {
+            return Err(ArithmeticError::Undefined);
+        }
+        let result = match op {
+            Builtin::Add => left.checked_add(right),
+            Builtin::Subtract => left.checked_sub(right),
+            Builtin::Multiply => left.checked_mul(right),
+            Builtin::Divide => left.checked_div(right),
+            Builtin::Power => left.checked_powd(right),
+        };
+        result.ok_or(ArithmeticError::Overflow)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::evaluator::Error;
+    use crate::value::{eval_str_value, ValueImpls};
+    use crate::Ctx;
+
+    fn dec(text: &str) -> Decimal {
+        Decimal::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn test_eval_str_value_decimal() {
+        let ctx = Ctx::default();
+        let impls = ValueImpls::new();
+        let mut vars = HashMap::new();
+        vars.insert("price".to_owned(), dec("19.99"));
+        let input_expected = &[
+            ("0.1 + 0.2", Ok(dec("0.3"))),
+            ("0.1 * 3 - 0.3", Ok(dec("0"))),
+            ("price * 3", Ok(dec("59.97"))),
+            ("price * 7.5%", Ok(dec("1.49925"))),
+            ("1 / 3 * 3", Ok(dec("0.9999999999999999999999999999"))),
+            ("2 ^ 10", Ok(dec("1024"))),
+            ("2 ^ -2", Ok(dec("0.25"))),
+            ("1.5e3 + 0.001", Ok(dec("1500.001"))),
+            ("-0.1 - 0.2", Ok(dec("-0.3"))),
+            (
+                "1 / (0.1 - 0.1)",
+                Err(Error::DivisionByZero {
+                    func: "/".to_owned(),
+                    position: 4,
+                }),
+            ),
+            (
+                "0 ^ -1",
+                Err(Error::DivisionByZero {
+                    func: "^".to_owned(),
+                    position: 3,
+                }),
+            ),
+            (
+                "(-4) ^ 0.5",
+                Err(Error::DomainError {
+                    func: "^".to_owned(),
+                    args: Vec::new(),
+                    position: 3,
+                }),
+            ),
+            (
+                "10 ^ 30",
+                Err(Error::Overflow {
+                    func: "^".to_owned(),
+                    position: 2,
+                }),
+            ),
+        ];
+        for (input, expected) in input_expected {
+            let result = eval_str_value(input, &vars, &ctx, &impls);
+            assert_eq!(result, *expected, "input was {}", input);
+        }
+    }
+}
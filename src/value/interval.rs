@@ -0,0 +1,390 @@
+//! Interval arithmetic, the expressions evaluated with [`Intervals`](Interval) propagate the uncertainty of their inputs.
+//!
+//! Each operation returns the interval that contains every result of the operation
+//! applied to any values from the intervals of its operands.
+//! The intervals are not narrowed down when the same variable is used several times,
+//! so `x - x` is `[-2, 2]` for `x = [-1, 1]`, not `[0, 0]`.
+//!
+//! # Example
+//!
+//! ```
+//! use std::collections::HashMap;
+//! use rusty_yard::value::eval_str_value;
+//! use rusty_yard::value::interval::{interval_impls, Interval};
+//! use rusty_yard::Ctx;
+//!
+//! let mut vars = HashMap::new();
+//! vars.insert("r".to_owned(), Interval::around(2.0, 0.1));
+//! let area = eval_str_value("3 * r ^ 2", &vars, &Ctx::default(), &interval_impls()).unwrap();
+//! assert!((area.lo - 10.83).abs() < 1e-9 && (area.hi - 13.23).abs() < 1e-9);
+//! ```
+use std::f64::consts::{FRAC_PI_2, PI};
+use std::fmt::{self, Display, Formatter};
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+use super::{ArithmeticError, Value, ValueImpls};
+use crate::operators::binary::Builtin;
+
+/// The closed interval `[lo, hi]` of real numbers.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Interval {
+    /// The lower bound.
+    pub lo: f64,
+    /// The upper bound.
+    pub hi: f64,
+}
+
+impl Interval {
+    /// Creates the interval between `a` and `b`, in any order.
+    pub fn new(a: f64, b: f64) -> Self {
+        Interval {
+            lo: a.min(b),
+            hi: a.max(b),
+        }
+    }
+
+    /// Creates the interval that contains the single `value`.
+    pub fn point(value: f64) -> Self {
+        Interval {
+            lo: value,
+            hi: value,
+        }
+    }
+
+    /// Creates the interval `value ± error`.
+    pub fn around(value: f64, error: f64) -> Self {
+        Interval::new(value - error, value + error)
+    }
+
+    /// The distance between the bounds.
+    pub fn width(&self) -> f64 {
+        self.hi - self.lo
+    }
+
+    /// The middle of the interval.
+    pub fn mid(&self) -> f64 {
+        (self.lo + self.hi) / 2.0
+    }
+
+    /// Returns true if the `value` is in the interval.
+    pub fn contains(&self, value: f64) -> bool {
+        self.lo <= value && value <= self.hi
+    }
+
+    /// The smallest interval that contains all the `values`.
+    fn hull(values: &[f64]) -> Self {
+        let lo = values.iter().copied().fold(f64::INFINITY, f64::min);
+        let hi = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        Interval { lo, hi }
+    }
+
+    /// The integer the interval contains only, if any
+    fn as_integer(&self) -> Option<i32> {
+        let is_integer = self.lo == self.hi && self.lo.fract() == 0.0 && self.lo.abs() <= 1e9;
+        is_integer.then_some(self.lo as i32)
+    }
+
+    /// Applies the increasing function to the bounds.
+    fn increasing(self, f: impl Fn(f64) -> f64) -> Self {
+        Interval {
+            lo: f(self.lo),
+            hi: f(self.hi),
+        }
+    }
+
+    /// Raises the interval to the integer power, the powers of negative numbers are defined as well.
+    fn powi(self, n: i32) -> Self {
+        let (lo, hi) = (self.lo.powi(n), self.hi.powi(n));
+        if n % 2 == 0 && self.contains(0.0) {
+            Interval::new(0.0, lo.max(hi))
+        } else {
+            Interval::new(lo, hi)
+        }
+    }
+
+    fn sin(self) -> Self {
+        if self.width() >= 2.0 * PI {
+            return Interval::new(-1.0, 1.0);
+        }
+        // the extremes are at π/2 + kπ
+        let first = (self.lo / PI - 0.5).ceil();
+        let mut bounds = vec![self.lo.sin(), self.hi.sin()];
+        let mut k = first;
+        while k * PI + FRAC_PI_2 <= self.hi {
+            bounds.push(if k % 2.0 == 0.0 { 1.0 } else { -1.0 });
+            k += 1.0;
+        }
+        Interval::hull(&bounds)
+    }
+
+    fn cos(self) -> Self {
+        (self + Interval::point(FRAC_PI_2)).sin()
+    }
+
+    fn abs(self) -> Self {
+        if self.contains(0.0) {
+            Interval::new(0.0, self.lo.abs().max(self.hi.abs()))
+        } else {
+            Interval::new(self.lo.abs(), self.hi.abs())
+        }
+    }
+}
+
+impl Display for Interval {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}, {}]", self.lo, self.hi)
+    }
+}
+
+impl Add for Interval {
+    type Output = Interval;
+    fn add(self, rhs: Interval) -> Interval {
+        Interval {
+            lo: self.lo + rhs.lo,
+            hi: self.hi + rhs.hi,
+        }
+    }
+}
+
+impl Sub for Interval {
+    type Output = Interval;
+    fn sub(self, rhs: Interval) -> Interval {
+        Interval {
+            lo: self.lo - rhs.hi,
+            hi: self.hi - rhs.lo,
+        }
+    }
+}
+
+impl Mul for Interval {
+    type Output = Interval;
+    fn mul(self, rhs: Interval) -> Interval {
+        Interval::hull(&[
+            self.lo * rhs.lo,
+            self.lo * rhs.hi,
+            self.hi * rhs.lo,
+            self.hi * rhs.hi,
+        ])
+    }
+}
+
+/// The divisor that contains zero gives the whole real line.
+impl Div for Interval {
+    type Output = Interval;
+    fn div(self, rhs: Interval) -> Interval {
+        if rhs.contains(0.0) {
+            return Interval::new(f64::NEG_INFINITY, f64::INFINITY);
+        }
+        self * Interval::new(1.0 / rhs.lo, 1.0 / rhs.hi)
+    }
+}
+
+impl Neg for Interval {
+    type Output = Interval;
+    fn neg(self) -> Interval {
+        Interval {
+            lo: -self.hi,
+            hi: -self.lo,
+        }
+    }
+}
+
+impl Value for Interval {
+    fn from_f64(value: f64) -> Self {
+        Interval::point(value)
+    }
+
+    /// The negative base is allowed with the integer exponents only, otherwise the result is `NaN`.
+    fn pow(self, exponent: Self) -> Self {
+        if let Some(n) = exponent.as_integer() {
+            return self.powi(n);
+        }
+        if self.lo < 0.0 {
+            return Interval::point(f64::NAN);
+        }
+        // `a ^ b` is monotonic in both `a` and `b`, so the extremes are at the corners
+        Interval::hull(&[
+            self.lo.powf(exponent.lo),
+            self.lo.powf(exponent.hi),
+            self.hi.powf(exponent.lo),
+            self.hi.powf(exponent.hi),
+        ])
+    }
+
+    fn apply_builtin(op: Builtin, left: Self, right: Self) -> Result<Self, ArithmeticError> {
+        match op {
+            Builtin::Divide if right.contains(0.0) => Err(ArithmeticError::DivisionByZero),
+            Builtin::Power => {
+                let negative_exponent = match right.as_integer() {
+                    Some(n) => n < 0,
+                    None if left.lo < 0.0 => return Err(ArithmeticError::Undefined),
+                    None => right.lo < 0.0,
+                };
+                if negative_exponent && left.contains(0.0) {
+                    Err(ArithmeticError::DivisionByZero)
+                } else {
+                    Ok(left.pow(right))
+                }
+            }
+            Builtin::Add => Ok(left + right),
+            Builtin::Subtract => Ok(left - right),
+            Builtin::Multiply => Ok(left * right),
+            Builtin::Divide => Ok(left / right),
+        }
+    }
+}
+
+/// Get the interval implementations of the [`default functions`](crate::functions::default_functions).
+///
+/// The functions are evaluated outside of their domain like the `f64` ones, e.g. `sqrt([-1, 4])` is `[NaN, 2]`.
+/// `floor`, `ceil`, `round`, `signum`, `atan2`, `hypot` and `log` are not implemented.
+pub fn interval_impls() -> ValueImpls<Interval> {
+    let mut impls = ValueImpls::new();
+    let increasing = [
+        ("asin", f64::asin as fn(f64) -> f64),
+        ("atan", f64::atan),
+        ("sinh", f64::sinh),
+        ("tanh", f64::tanh),
+        ("ln", f64::ln),
+        ("log10", f64::log10),
+        ("log2", f64::log2),
+        ("exp", f64::exp),
+        ("sqrt", f64::sqrt),
+        ("cbrt", f64::cbrt),
+    ];
+    for (token, f) in increasing {
+        impls.add_func(token, move |args: &[Interval]| args[0].increasing(f));
+    }
+    impls
+        .add_func("acos", |args| {
+            let x = args[0];
+            Interval::new(x.hi.acos(), x.lo.acos())
+        })
+        .add_func("cosh", |args| {
+            let abs = args[0].abs();
+            abs.increasing(f64::cosh)
+        })
+        .add_func("sin", |args| args[0].sin())
+        .add_func("cos", |args| args[0].cos())
+        .add_func("tan", |args| {
+            let x = args[0];
+            // the poles are at π/2 + kπ
+            if (x.lo / PI - 0.5).ceil() * PI + FRAC_PI_2 <= x.hi {
+                Interval::new(f64::NEG_INFINITY, f64::INFINITY)
+            } else {
+                x.increasing(f64::tan)
+            }
+        })
+        .add_func("abs", |args| args[0].abs())
+        .add_func("max", |args| Interval {
+            lo: args[0].lo.max(args[1].lo),
+            hi: args[0].hi.max(args[1].hi),
+        })
+        .add_func("min", |args| Interval {
+            lo: args[0].lo.min(args[1].lo),
+            hi: args[0].hi.min(args[1].hi),
+        })
+        .add_func("sub", |args| args[0] - args[1])
+        .add_func("sum", |args| {
+            args.iter()
+                .fold(Interval::point(0.0), |sum, arg| sum + *arg)
+        })
+        .add_func("prod", |args| {
+            args.iter()
+                .fold(Interval::point(1.0), |prod, arg| prod * *arg)
+        });
+    impls
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::evaluator::Error;
+    use crate::value::eval_str_value;
+    use crate::Ctx;
+
+    #[test]
+    fn test_eval_str_value_interval() {
+        let ctx = Ctx::default();
+        let impls = interval_impls();
+        let mut vars = HashMap::new();
+        vars.insert("x".to_owned(), Interval::new(-1.0, 2.0));
+        vars.insert("y".to_owned(), Interval::new(1.0, 4.0));
+        let input_expected = &[
+            ("x + y", Ok(Interval::new(0.0, 6.0))),
+            ("x - y", Ok(Interval::new(-5.0, 1.0))),
+            ("x * y", Ok(Interval::new(-4.0, 8.0))),
+            ("x / y", Ok(Interval::new(-1.0, 2.0))),
+            ("-x", Ok(Interval::new(-2.0, 1.0))),
+            ("x ^ 2", Ok(Interval::new(0.0, 4.0))),
+            ("x ^ 3", Ok(Interval::new(-1.0, 8.0))),
+            ("y ^ -1", Ok(Interval::new(0.25, 1.0))),
+            ("y ^ 0.5", Ok(Interval::new(1.0, 2.0))),
+            ("2 ^ x", Ok(Interval::new(0.5, 4.0))),
+            ("x - x", Ok(Interval::new(-3.0, 3.0))),
+            ("sqrt(y)", Ok(Interval::new(1.0, 2.0))),
+            ("abs(x)", Ok(Interval::new(0.0, 2.0))),
+            ("max(x, y)", Ok(Interval::new(1.0, 4.0))),
+            ("sum(x, y, 1)", Ok(Interval::new(1.0, 7.0))),
+            ("sin(x * pi)", Ok(Interval::new(-1.0, 1.0))),
+            ("cos(2 * y)", Ok(Interval::new(-1.0, 1.0))),
+            ("50% * y", Ok(Interval::new(0.5, 2.0))),
+            (
+                "y / x",
+                Err(Error::DivisionByZero {
+                    func: "/".to_owned(),
+                    position: 2,
+                }),
+            ),
+            (
+                "x ^ -2",
+                Err(Error::DivisionByZero {
+                    func: "^".to_owned(),
+                    position: 3,
+                }),
+            ),
+            (
+                "x ^ 0.5",
+                Err(Error::DomainError {
+                    func: "^".to_owned(),
+                    args: Vec::new(),
+                    position: 2,
+                }),
+            ),
+            ("floor(x)", Err(Error::Unsupported("floor".to_owned()))),
+        ];
+        for (input, expected) in input_expected {
+            let result = eval_str_value(input, &vars, &ctx, &impls);
+            assert_eq!(result, *expected, "input was {}", input);
+        }
+    }
+
+    #[test]
+    fn test_sin_cos() {
+        let input_expected = &[
+            (Interval::new(0.0, 1.0), Interval::new(0.0, 1f64.sin())),
+            (Interval::new(1.0, 2.0), Interval::new(1f64.sin(), 1.0)),
+            (Interval::new(4.0, 5.0), Interval::new(-1.0, 4f64.sin())),
+            (Interval::new(0.0, 7.0), Interval::new(-1.0, 1.0)),
+            (Interval::point(2.0), Interval::point(2f64.sin())),
+        ];
+        for (input, expected) in input_expected {
+            assert_eq!(input.sin(), *expected, "input was {}", input);
+        }
+        let cos = Interval::new(-0.5, 0.5).cos();
+        assert_eq!(cos.hi, 1.0);
+        assert!((cos.lo - 0.5f64.cos()).abs() < 1e-15);
+    }
+
+    #[test]
+    fn test_interval() {
+        let interval = Interval::around(2.0, 0.5);
+        assert_eq!(interval, Interval::new(2.5, 1.5));
+        assert_eq!(interval.width(), 1.0);
+        assert_eq!(interval.mid(), 2.0);
+        assert!(interval.contains(1.5) && !interval.contains(1.0));
+        assert_eq!(interval.to_string(), "[1.5, 2.5]");
+    }
+}
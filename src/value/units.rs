@@ -0,0 +1,457 @@
+//! Unit-aware calculations, the expressions evaluated with [`Quantities`](Quantity) check the dimensions
+//! and convert the units.
+//!
+//! The units are defined in the [`Units`](Units) registry of the context, [`Ctx::units`](crate::Ctx::units),
+//! as the quantities of the SI base units, e.g. `cm` is `0.01 m` and `N` is `1 kg·m·s^-2`,
+//! so the quantities of the same dimension can be added whatever units they were written in.
+//! The number followed by the unit, `20 cm`, is the quantity in that unit, see [`UnitSuffixes`](UnitSuffixes).
+//!
+//! # Example
+//!
+//! ```
+//! use std::collections::HashMap;
+//! use rusty_yard::value::units::{eval_str_units, units_ctx, Units};
+//!
+//! let ctx = units_ctx(Units::si());
+//! let vars = HashMap::new();
+//! let length = eval_str_units("3 m + 20 cm", &vars, &ctx).unwrap();
+//! assert_eq!(length.to_string(), "3.2 m");
+//! assert_eq!(ctx.units.convert(&length, "cm"), Some(320.0));
+//! let speed = eval_str_units("5 km / 2 h", &vars, &ctx).unwrap();
+//! assert_eq!(speed.dimension.to_string(), "m·s^-1");
+//! assert_eq!(ctx.units.convert(&speed, "kg"), None);
+//! ```
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+use super::{eval_str_value, ArithmeticError, Value, ValueImpls};
+use crate::evaluator::Error;
+use crate::operators::binary::Builtin;
+use crate::tokenizer::{Token, TokenPass};
+use crate::Ctx;
+
+/// The symbols of the SI base units, in the order of the exponents of [`Dimension`](Dimension)
+pub const BASE_UNITS: [&str; 7] = ["m", "kg", "s", "A", "K", "mol", "cd"];
+
+/// The exponents of the [`BASE_UNITS`](BASE_UNITS), e.g. `[1, 0, -1, 0, 0, 0, 0]` is the speed.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct Dimension(pub [i32; 7]);
+
+impl Dimension {
+    /// The dimension of the numbers without units.
+    pub const NONE: Dimension = Dimension([0; 7]);
+
+    /// The dimension of the base unit with the `index` in [`BASE_UNITS`](BASE_UNITS).
+    pub fn base(index: usize) -> Self {
+        let mut exponents = [0; 7];
+        exponents[index] = 1;
+        Dimension(exponents)
+    }
+
+    fn combine(self, other: Dimension, f: impl Fn(i32, i32) -> i32) -> Dimension {
+        let mut exponents = self.0;
+        for (exponent, other) in exponents.iter_mut().zip(other.0.iter()) {
+            *exponent = f(*exponent, *other);
+        }
+        Dimension(exponents)
+    }
+}
+
+/// Formats the dimension as the product of the base units, e.g. `m·s^-1`, the numbers without units are empty.
+impl Display for Dimension {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let units: Vec<_> = BASE_UNITS
+            .iter()
+            .zip(self.0.iter())
+            .filter(|(_, exponent)| **exponent != 0)
+            .map(|(unit, exponent)| match exponent {
+                1 => unit.to_string(),
+                _ => format!("{}^{}", unit, exponent),
+            })
+            .collect();
+        write!(f, "{}", units.join("·"))
+    }
+}
+
+/// The number with the [`Dimension`](Dimension), the value is in the SI base units.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Quantity {
+    /// The value in the SI base units.
+    pub value: f64,
+    /// The dimension of the value.
+    pub dimension: Dimension,
+}
+
+impl Quantity {
+    /// Creates the quantity of the `value` in the SI base units.
+    pub fn new(value: f64, dimension: Dimension) -> Self {
+        Quantity { value, dimension }
+    }
+
+    /// Adds the quantities, fails with [`IncompatibleUnits`](ArithmeticError::IncompatibleUnits)
+    /// if they have different dimensions.
+    pub fn checked_add(self, rhs: Quantity) -> Result<Quantity, ArithmeticError> {
+        if self.dimension != rhs.dimension {
+            return Err(ArithmeticError::IncompatibleUnits);
+        }
+        Ok(Quantity::new(self.value + rhs.value, self.dimension))
+    }
+
+    /// Subtracts the quantities, see [`checked_add`](Quantity::checked_add).
+    pub fn checked_sub(self, rhs: Quantity) -> Result<Quantity, ArithmeticError> {
+        self.checked_add(-rhs)
+    }
+}
+
+/// Formats the value in the SI base units, e.g. `3.2 m`.
+impl Display for Quantity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.dimension == Dimension::NONE {
+            write!(f, "{}", self.value)
+        } else {
+            write!(f, "{} {}", self.value, self.dimension)
+        }
+    }
+}
+
+/// # Panics
+///
+/// Panics if the quantities have different dimensions, see [`checked_add`](Quantity::checked_add).
+impl Add for Quantity {
+    type Output = Quantity;
+    fn add(self, rhs: Quantity) -> Quantity {
+        match self.checked_add(rhs) {
+            Ok(sum) => sum,
+            Err(_) => panic!("adding {} to {}", rhs.dimension, self.dimension),
+        }
+    }
+}
+
+/// # Panics
+///
+/// Panics if the quantities have different dimensions, see [`checked_sub`](Quantity::checked_sub).
+impl Sub for Quantity {
+    type Output = Quantity;
+    fn sub(self, rhs: Quantity) -> Quantity {
+        match self.checked_sub(rhs) {
+            Ok(difference) => difference,
+            Err(_) => panic!("subtracting {} from {}", rhs.dimension, self.dimension),
+        }
+    }
+}
+
+impl Mul for Quantity {
+    type Output = Quantity;
+    fn mul(self, rhs: Quantity) -> Quantity {
+        let dimension = self.dimension.combine(rhs.dimension, i32::add);
+        Quantity::new(self.value * rhs.value, dimension)
+    }
+}
+
+impl Div for Quantity {
+    type Output = Quantity;
+    fn div(self, rhs: Quantity) -> Quantity {
+        let dimension = self.dimension.combine(rhs.dimension, i32::sub);
+        Quantity::new(self.value / rhs.value, dimension)
+    }
+}
+
+impl Neg for Quantity {
+    type Output = Quantity;
+    fn neg(self) -> Quantity {
+        Quantity::new(-self.value, self.dimension)
+    }
+}
+
+impl Value for Quantity {
+    fn from_f64(value: f64) -> Self {
+        Quantity::new(value, Dimension::NONE)
+    }
+
+    /// The quantity with the dimension can be raised to the integer powers only, otherwise the result is `NaN`.
+    fn pow(self, exponent: Self) -> Self {
+        let n = exponent.value;
+        if self.dimension == Dimension::NONE {
+            Quantity::new(self.value.powf(n), Dimension::NONE)
+        } else if n.fract() == 0.0 && n.abs() <= i32::MAX as f64 {
+            let dimension = self.dimension.combine(Dimension::NONE, |a, _| a * n as i32);
+            Quantity::new(self.value.powf(n), dimension)
+        } else {
+            Quantity::new(f64::NAN, self.dimension)
+        }
+    }
+
+    fn apply_builtin(op: Builtin, left: Self, right: Self) -> Result<Self, ArithmeticError> {
+        match op {
+            Builtin::Add => left.checked_add(right),
+            Builtin::Subtract => left.checked_sub(right),
+            Builtin::Power if right.dimension != Dimension::NONE => {
+                Err(ArithmeticError::IncompatibleUnits)
+            }
+            Builtin::Power if left.dimension != Dimension::NONE && right.value.fract() != 0.0 => {
+                Err(ArithmeticError::Undefined)
+            }
+            Builtin::Multiply => Ok(left * right),
+            Builtin::Divide => Ok(left / right),
+            Builtin::Power => Ok(left.pow(right)),
+        }
+    }
+}
+
+/// The registry of the units, by their symbols.
+#[derive(Debug, Clone, Default)]
+pub struct Units {
+    /// The quantity of the SI base units each unit stands for, e.g. `km` is `1000 m`.
+    pub units: HashMap<String, Quantity>,
+}
+
+impl Units {
+    /// Creates the empty registry.
+    pub fn new() -> Self {
+        Units::default()
+    }
+
+    /// Creates the registry of the SI base units, the common SI derived units
+    /// and the multiples of the units of length, mass and time.
+    pub fn si() -> Self {
+        let mut units = Units::new();
+        for (index, unit) in BASE_UNITS.iter().enumerate() {
+            units.define(unit, Quantity::new(1.0, Dimension::base(index)));
+        }
+        let [m, kg, s, ..] = [0, 1, 2].map(|index| Quantity::new(1.0, Dimension::base(index)));
+        let scaled = |factor: f64, unit: Quantity| Quantity::new(factor, Dimension::NONE) * unit;
+        units
+            .define("km", scaled(1e3, m))
+            .define("cm", scaled(1e-2, m))
+            .define("mm", scaled(1e-3, m))
+            .define("g", scaled(1e-3, kg))
+            .define("mg", scaled(1e-6, kg))
+            .define("ms", scaled(1e-3, s))
+            .define("min", scaled(60.0, s))
+            .define("h", scaled(3600.0, s))
+            .define("L", scaled(1e-3, m * m * m))
+            .define("Hz", scaled(1.0, Quantity::from_f64(1.0) / s))
+            .define("N", kg * m / (s * s))
+            .define("J", kg * m * m / (s * s))
+            .define("W", kg * m * m / (s * s * s))
+            .define("Pa", kg / (m * s * s));
+        units
+    }
+
+    /// Defines the `unit` as the `quantity` of the SI base units, replacing the previous definition.
+    pub fn define(&mut self, unit: &str, quantity: Quantity) -> &mut Self {
+        self.units.insert(unit.to_owned(), quantity);
+        self
+    }
+
+    /// Returns the definition of the `unit`.
+    pub fn get(&self, unit: &str) -> Option<&Quantity> {
+        self.units.get(unit)
+    }
+
+    /// Converts the `quantity` to the number of the `unit`,
+    /// returns `None` when the unit is not defined or it has another dimension.
+    pub fn convert(&self, quantity: &Quantity, unit: &str) -> Option<f64> {
+        let unit = self.get(unit)?;
+        (unit.dimension == quantity.dimension).then(|| quantity.value / unit.value)
+    }
+}
+
+/// The pass that binds the [`units`](crate::Ctx::units) of the context to the numbers before them,
+/// so `5 km / 2 h` is `(5 * km) / (2 * h)`.
+///
+/// The unit binds tighter than any operator, so `2 m ^ 2` is `(2 m) ^ 2`, the area of the square.
+/// The units that are the operators, the functions or the constants of the context are never bound.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UnitSuffixes;
+
+impl TokenPass for UnitSuffixes {
+    fn apply<'a, 'ctx>(
+        &self,
+        tokens: Vec<Token<'a, 'ctx>>,
+        ctx: &'ctx Ctx,
+    ) -> Vec<Token<'a, 'ctx>> {
+        let is_unit = |id: &str| {
+            ctx.units.get(id).is_some()
+                && ctx.bi_op(id).is_none()
+                && ctx.u_ops.iter().all(|op| op.token != id)
+                && ctx.func(id).is_none()
+                && ctx.constant(id).is_none()
+        };
+        let mut output = Vec::with_capacity(tokens.len());
+        let mut tokens = tokens.into_iter().peekable();
+        while let Some(token) = tokens.next() {
+            match (token, tokens.peek()) {
+                (Token::Num(n), Some(Token::Id(unit))) if is_unit(unit) => {
+                    output.push(Token::OpenParen);
+                    output.push(Token::Num(n));
+                    output.push(Token::Id("*"));
+                    // unwrap: the unit has been peeked
+                    output.push(tokens.next().unwrap());
+                    output.push(Token::ClosedParen);
+                }
+                (token, _) => output.push(token),
+            }
+        }
+        output
+    }
+}
+
+/// Get the default context with the `units` and the [`UnitSuffixes`](UnitSuffixes) pass.
+pub fn units_ctx(units: Units) -> Ctx {
+    let mut ctx = Ctx {
+        units,
+        ..Ctx::default()
+    };
+    ctx.passes.push(Box::new(UnitSuffixes));
+    ctx
+}
+
+/// Get the implementations of `abs`, `sqrt`, `min` and `max` for the quantities.
+///
+/// `sqrt` of the quantity with the odd exponents, and `min` and `max` of the different dimensions are `NaN`.
+pub fn quantity_impls() -> ValueImpls<Quantity> {
+    fn same_dimension(a: Quantity, b: Quantity, f: fn(f64, f64) -> f64) -> Quantity {
+        let value = if a.dimension == b.dimension {
+            f(a.value, b.value)
+        } else {
+            f64::NAN
+        };
+        Quantity::new(value, a.dimension)
+    }
+
+    let mut impls = ValueImpls::new();
+    impls
+        .add_func("abs", |args: &[Quantity]| {
+            Quantity::new(args[0].value.abs(), args[0].dimension)
+        })
+        .add_func("sqrt", |args| {
+            let Quantity { value, dimension } = args[0];
+            if dimension.0.iter().all(|exponent| exponent % 2 == 0) {
+                let dimension = dimension.combine(Dimension::NONE, |a, _| a / 2);
+                Quantity::new(value.sqrt(), dimension)
+            } else {
+                Quantity::new(f64::NAN, dimension)
+            }
+        })
+        .add_func("max", |args| same_dimension(args[0], args[1], f64::max))
+        .add_func("min", |args| same_dimension(args[0], args[1], f64::min));
+    impls
+}
+
+/// Evaluates the `input` with the quantities, the identifiers that are not the `variables`
+/// are the [`units`](crate::Ctx::units) of the `ctx`.
+///
+/// The `ctx` should have the [`UnitSuffixes`](UnitSuffixes) pass, e.g. [`units_ctx`](units_ctx),
+/// so that the numbers are bound to their units.
+pub fn eval_str_units(
+    input: &str,
+    variables: &HashMap<String, Quantity>,
+    ctx: &Ctx,
+) -> Result<Quantity, Error> {
+    let mut scope = ctx.units.units.clone();
+    scope.extend(variables.iter().map(|(name, value)| (name.clone(), *value)));
+    eval_str_value(input, &scope, ctx, &quantity_impls())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eval_str_units() {
+        let ctx = units_ctx(Units::si());
+        let mut vars = HashMap::new();
+        vars.insert("h".to_owned(), Quantity::new(2.0, Dimension::base(0)));
+        let input_expected = &[
+            ("3 m + 20 cm", Ok("3.2 m")),
+            ("5 km / (2 * 3600 s)", Ok("0.6944444444444444 m·s^-1")),
+            ("10 N * 2 m", Ok("20 m^2·kg·s^-2")),
+            ("2 m ^ 2", Ok("4 m^2")),
+            ("sqrt(16 m * m)", Ok("4 m")),
+            ("500 g * 2", Ok("1 kg")),
+            ("1 km / 1 mm", Ok("1000000")),
+            ("h * 3", Ok("6 m")),
+            (
+                "2 pi m",
+                Err(Error::ParserError(crate::parser::Error::ExpectedOperator)),
+            ),
+            (
+                "1 m + 1 s",
+                Err(Error::IncompatibleUnits {
+                    func: "+".to_owned(),
+                    position: 6,
+                }),
+            ),
+            (
+                "2 ^ (1 m)",
+                Err(Error::IncompatibleUnits {
+                    func: "^".to_owned(),
+                    position: 4,
+                }),
+            ),
+            (
+                "(4 m) ^ 0.5",
+                Err(Error::DomainError {
+                    func: "^".to_owned(),
+                    args: Vec::new(),
+                    position: 4,
+                }),
+            ),
+        ];
+        for (input, expected) in input_expected {
+            let result = eval_str_units(input, &vars, &ctx);
+            let result = result.as_ref().map(|q| q.to_string());
+            let expected = expected.as_ref().map(|q| q.to_string());
+            assert_eq!(result, expected, "input was {}", input);
+        }
+    }
+
+    #[test]
+    fn test_convert() {
+        let mut ctx = units_ctx(Units::si());
+        let speed = eval_str_units("90 km / 1 h", &HashMap::new(), &ctx).unwrap();
+        assert_eq!(speed.to_string(), "25 m·s^-1");
+        let units = &mut ctx.units;
+        units.define("kmh", Quantity::new(1000.0 / 3600.0, speed.dimension));
+        assert_eq!(units.convert(&speed, "kmh"), Some(90.0));
+        assert_eq!(units.convert(&speed, "km"), None);
+        assert_eq!(units.convert(&speed, "parsec"), None);
+    }
+
+    #[test]
+    fn test_checked_add() {
+        let m = Quantity::new(1.0, Dimension::base(0));
+        let s = Quantity::new(1.0, Dimension::base(2));
+        assert_eq!(m.checked_add(m), Ok(Quantity::new(2.0, m.dimension)));
+        assert_eq!(m.checked_sub(m), Ok(Quantity::new(0.0, m.dimension)));
+        assert_eq!(m.checked_add(s), Err(ArithmeticError::IncompatibleUnits));
+        assert_eq!(m.checked_sub(s), Err(ArithmeticError::IncompatibleUnits));
+    }
+
+    #[test]
+    #[should_panic(expected = "adding s to m")]
+    fn test_add_incompatible() {
+        let _ = Quantity::new(1.0, Dimension::base(0)) + Quantity::new(1.0, Dimension::base(2));
+    }
+
+    #[test]
+    fn test_unit_suffixes() {
+        let ctx = units_ctx(Units::si());
+        let tokens =
+            crate::tokenizer::apply_passes(crate::tokenizer::tokenize("2km + x m", &ctx), &ctx);
+        use Token::*;
+        let expected = vec![
+            OpenParen,
+            Num(2.0),
+            Id("*"),
+            Id("km"),
+            ClosedParen,
+            Id("+"),
+            Id("x"),
+            Id("m"),
+        ];
+        assert_eq!(tokens, expected);
+    }
+}
@@ -0,0 +1,776 @@
+//! Provides the abstraction over the storage of variables used by [`evaluator`](crate::evaluator) and macros.
+//!
+//! The main type in this module is [`VariableResolver`](VariableResolver) trait.
+//! It is implemented for `HashMap<String, f64>`, `BTreeMap<String, f64>` and for [`SnapshotVariables`](SnapshotVariables),
+//! the container that supports cheap snapshots and rollbacks.
+//!
+//! The adapters [`CaseInsensitive`](CaseInsensitive), [`EnvVariables`](EnvVariables) and [`Computed`](Computed)
+//! change how the names are resolved, and [`CellReferences`](CellReferences) reads the cells of the spreadsheet-like hosts.
+//!
+//! # Example
+//!
+//! ```
+//! use rusty_yard::evaluator::eval_str_with;
+//! use rusty_yard::variables::SnapshotVariables;
+//! use rusty_yard::Ctx;
+//!
+//! let ctx = Ctx::default_with_macros();
+//! let mut vars = SnapshotVariables::new();
+//! eval_str_with("a = 1", &mut vars, &ctx).unwrap();
+//!
+//! // what if a was 10?
+//! let snapshot = vars.snapshot();
+//! eval_str_with("a = 10", &mut vars, &ctx).unwrap();
+//! assert_eq!(eval_str_with("a * 2", &mut vars, &ctx), Ok(20.0));
+//! vars.rollback(snapshot);
+//! assert_eq!(vars.get("a"), Some(1.0));
+//! ```
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::fmt::{self, Display, Formatter};
+
+/// Represents the storage of variables that the expression can read and write by name.
+pub trait VariableResolver {
+    /// Get the value of variable `name`.
+    ///
+    /// Returns [`None`](std::option::Option::None) if the variable is not defined.
+    fn get(&self, name: &str) -> Option<f64>;
+
+    /// Set the value of variable `name`, defining it if it did not exist.
+    fn set(&mut self, name: &str, value: f64);
+
+    /// The names of the defined variables, if the resolver can list them.
+    ///
+    /// Used to suggest the closest name when the variable is not found, see [`VarNotFound`](crate::evaluator::Error::VarNotFound).
+    /// Returns no names by default.
+    fn names(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+impl VariableResolver for HashMap<String, f64> {
+    #[inline]
+    fn get(&self, name: &str) -> Option<f64> {
+        HashMap::get(self, name).copied()
+    }
+
+    fn set(&mut self, name: &str, value: f64) {
+        // only allocate the key when the variable is new
+        match self.get_mut(name) {
+            Some(old) => *old = value,
+            None => {
+                self.insert(name.into(), value);
+            }
+        }
+    }
+
+    fn names(&self) -> Vec<String> {
+        self.keys().cloned().collect()
+    }
+}
+
+impl VariableResolver for BTreeMap<String, f64> {
+    #[inline]
+    fn get(&self, name: &str) -> Option<f64> {
+        BTreeMap::get(self, name).copied()
+    }
+
+    fn set(&mut self, name: &str, value: f64) {
+        match self.get_mut(name) {
+            Some(old) => *old = value,
+            None => {
+                self.insert(name.into(), value);
+            }
+        }
+    }
+
+    fn names(&self) -> Vec<String> {
+        self.keys().cloned().collect()
+    }
+}
+
+impl<R: VariableResolver + ?Sized> VariableResolver for &mut R {
+    #[inline]
+    #[cfg_attr(tarpaulin, skip)]
+    fn get(&self, name: &str) -> Option<f64> {
+        (**self).get(name)
+    }
+
+    #[inline]
+    #[cfg_attr(tarpaulin, skip)]
+    fn set(&mut self, name: &str, value: f64) {
+        (**self).set(name, value)
+    }
+
+    #[cfg_attr(tarpaulin, skip)]
+    fn names(&self) -> Vec<String> {
+        (**self).names()
+    }
+}
+
+/// The adapter that resolves the names of the variables ignoring their case.
+///
+/// The names are converted to lowercase before they are passed to the inner resolver.
+///
+/// # Example
+///
+/// ```
+/// use rusty_yard::evaluator::eval_str_with;
+/// use rusty_yard::variables::CaseInsensitive;
+/// use rusty_yard::Ctx;
+/// use std::collections::HashMap;
+///
+/// let mut vars = CaseInsensitive::new(HashMap::new());
+/// let ctx = Ctx::default_with_macros();
+/// eval_str_with("Price = 10", &mut vars, &ctx).unwrap();
+/// assert_eq!(eval_str_with("PRICE * price", &mut vars, &ctx), Ok(100.0));
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct CaseInsensitive<R> {
+    inner: R,
+}
+
+impl<R: VariableResolver> CaseInsensitive<R> {
+    /// Wraps the `inner` resolver, its variables should have lowercase names.
+    pub fn new(inner: R) -> Self {
+        Self { inner }
+    }
+
+    /// Consumes the adapter returning the inner resolver.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn key(name: &str) -> Cow<'_, str> {
+        if name.chars().any(char::is_uppercase) {
+            Cow::Owned(name.to_lowercase())
+        } else {
+            Cow::Borrowed(name)
+        }
+    }
+}
+
+impl<R: VariableResolver> VariableResolver for CaseInsensitive<R> {
+    fn get(&self, name: &str) -> Option<f64> {
+        self.inner.get(&Self::key(name))
+    }
+
+    fn set(&mut self, name: &str, value: f64) {
+        self.inner.set(&Self::key(name), value)
+    }
+
+    fn names(&self) -> Vec<String> {
+        self.inner.names()
+    }
+}
+
+/// The variables that are read from the environment of the process.
+///
+/// The variable `name` is the environment variable `{prefix}{name}` parsed as `f64`.
+/// Assigned variables are stored in the container and shadow the environment,
+/// the environment of the process is never changed.
+#[derive(Debug, Default, Clone)]
+pub struct EnvVariables {
+    prefix: String,
+    assigned: HashMap<String, f64>,
+}
+
+impl EnvVariables {
+    /// Creates the variables that read the environment variables starting with `prefix`.
+    pub fn new(prefix: &str) -> Self {
+        Self {
+            prefix: prefix.to_owned(),
+            assigned: HashMap::new(),
+        }
+    }
+
+    /// Returns the variables that were assigned.
+    pub fn assigned(&self) -> &HashMap<String, f64> {
+        &self.assigned
+    }
+}
+
+impl VariableResolver for EnvVariables {
+    fn get(&self, name: &str) -> Option<f64> {
+        if let Some(value) = self.assigned.get(name) {
+            return Some(*value);
+        }
+        let value = env::var(format!("{}{}", self.prefix, name)).ok()?;
+        value.trim().parse().ok()
+    }
+
+    fn set(&mut self, name: &str, value: f64) {
+        VariableResolver::set(&mut self.assigned, name, value)
+    }
+
+    /// The names of the assigned variables, the environment is not listed.
+    fn names(&self) -> Vec<String> {
+        self.assigned.names()
+    }
+}
+
+/// The adapter that computes the variables the inner resolver doesn't have.
+///
+/// `compute` is called every time the variable is read, unless the variable has been assigned.
+///
+/// # Example
+///
+/// ```
+/// use rusty_yard::evaluator::eval_str_with;
+/// use rusty_yard::variables::Computed;
+/// use rusty_yard::Ctx;
+/// use std::collections::HashMap;
+///
+/// // x1, x2, ... are the squares of their indices
+/// let mut vars = Computed::new(HashMap::new(), |name: &str| {
+///     let index: f64 = name.strip_prefix('x')?.parse().ok()?;
+///     Some(index * index)
+/// });
+/// assert_eq!(eval_str_with("x2 + x3", &mut vars, &Ctx::default()), Ok(13.0));
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct Computed<R, F> {
+    inner: R,
+    compute: F,
+}
+
+impl<R: VariableResolver, F: Fn(&str) -> Option<f64>> Computed<R, F> {
+    /// Wraps the `inner` resolver, the variables it doesn't have are computed using `compute`.
+    pub fn new(inner: R, compute: F) -> Self {
+        Self { inner, compute }
+    }
+
+    /// Consumes the adapter returning the inner resolver.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: VariableResolver, F: Fn(&str) -> Option<f64>> VariableResolver for Computed<R, F> {
+    fn get(&self, name: &str) -> Option<f64> {
+        self.inner.get(name).or_else(|| (self.compute)(name))
+    }
+
+    fn set(&mut self, name: &str, value: f64) {
+        self.inner.set(name, value)
+    }
+
+    fn names(&self) -> Vec<String> {
+        self.inner.names()
+    }
+}
+
+/// The adapter that only reads the variables of the shared resolver, the assignments are ignored.
+///
+/// Used by [`eval_readonly`](crate::evaluator::eval_readonly),
+/// which rejects the expressions that write the variables before they are evaluated.
+#[derive(Debug)]
+pub struct ReadOnly<'r, R: ?Sized> {
+    inner: &'r R,
+}
+
+impl<'r, R: VariableResolver + ?Sized> ReadOnly<'r, R> {
+    /// Wraps the `inner` resolver.
+    pub fn new(inner: &'r R) -> Self {
+        Self { inner }
+    }
+}
+
+impl<R: VariableResolver + ?Sized> VariableResolver for ReadOnly<'_, R> {
+    fn get(&self, name: &str) -> Option<f64> {
+        self.inner.get(name)
+    }
+
+    fn set(&mut self, _name: &str, _value: f64) {}
+
+    fn names(&self) -> Vec<String> {
+        self.inner.names()
+    }
+}
+
+/// The reference to the cell of the spreadsheet in the A1 notation, e.g. `B12`.
+///
+/// The column and the row are zero-based, so `A1` is the column 0 and the row 0.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct CellRef {
+    /// The column of the cell, `A` is 0, `Z` is 25 and `AA` is 26.
+    pub column: u32,
+    /// The row of the cell, the row `1` is 0.
+    pub row: u32,
+}
+
+impl CellRef {
+    /// Parses the reference like `B12`: the uppercase letters of the column and the number of the row.
+    ///
+    /// The absolute references like `$B$12` are the same cell.
+    /// Returns [`None`](std::option::Option::None) if the `name` is not the reference.
+    pub fn parse(name: &str) -> Option<Self> {
+        let name = name.strip_prefix('$').unwrap_or(name);
+        let digits = name.find(|ch: char| !ch.is_ascii_uppercase())?;
+        let (letters, row) = name.split_at(digits);
+        let row = row.strip_prefix('$').unwrap_or(row);
+        if letters.is_empty() || row.starts_with('0') || !row.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        let column = letters.bytes().try_fold(0u32, |column, letter| {
+            column
+                .checked_mul(26)?
+                .checked_add(u32::from(letter - b'A') + 1)
+        })?;
+        Some(CellRef {
+            column: column - 1,
+            row: row.parse::<u32>().ok()? - 1,
+        })
+    }
+}
+
+impl Display for CellRef {
+    /// Writes the reference in the A1 notation.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut letters = Vec::new();
+        let mut column = u64::from(self.column) + 1;
+        while column > 0 {
+            letters.push(b'A' + ((column - 1) % 26) as u8);
+            column = (column - 1) / 26;
+        }
+        letters.reverse();
+        // unwrap: the letters are ASCII
+        write!(
+            f,
+            "{}{}",
+            std::str::from_utf8(&letters).unwrap(),
+            u64::from(self.row) + 1
+        )
+    }
+}
+
+/// The cells of the spreadsheet-like host, read by [`CellReferences`](CellReferences).
+///
+/// It is implemented for the closures `Fn(CellRef, &dyn VariableResolver) -> Option<f64>`.
+pub trait CellResolver {
+    /// Get the value of the `cell`.
+    ///
+    /// `variables` resolve the references of the formula of the cell, including the references to the other cells,
+    /// evaluate the formula with [`ReadOnly::new(variables)`](ReadOnly::new) to have the cycles detected.
+    fn resolve(&self, cell: CellRef, variables: &dyn VariableResolver) -> Option<f64>;
+
+    /// Called instead of [`resolve`](CellResolver::resolve) when the cell refers to itself through the other cells.
+    ///
+    /// `cycle` is the chain of the references that starts and ends with the same cell, e.g. `A1 B1 A1`.
+    /// Returns the value the reference is read as, by default `None`, so the evaluation fails with
+    /// [`VarNotFound`](crate::evaluator::Error::VarNotFound) error.
+    fn on_cycle(&self, cycle: &[CellRef]) -> Option<f64> {
+        let _ = cycle;
+        None
+    }
+}
+
+impl<F: Fn(CellRef, &dyn VariableResolver) -> Option<f64>> CellResolver for F {
+    fn resolve(&self, cell: CellRef, variables: &dyn VariableResolver) -> Option<f64> {
+        self(cell, variables)
+    }
+}
+
+/// The adapter that reads the variables that are the [cell references](CellRef), like `A1` or `B12`, from the `cells`.
+///
+/// The other variables are resolved by the inner resolver. The cells are never assigned, the assignments to them are ignored.
+/// The adapter keeps the chain of the cells being resolved, so the cell that refers to itself is reported
+/// to [`on_cycle`](CellResolver::on_cycle) instead of being resolved forever.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use rusty_yard::evaluator::eval_str_with;
+/// use rusty_yard::variables::{CellRef, CellReferences, ReadOnly, VariableResolver};
+/// use rusty_yard::Ctx;
+///
+/// let ctx = Ctx::default();
+/// let mut sheet = HashMap::new();
+/// sheet.insert(CellRef::parse("A1").unwrap(), "2");
+/// sheet.insert(CellRef::parse("A2").unwrap(), "A1 * rate");
+/// sheet.insert(CellRef::parse("B1").unwrap(), "B2 + 1");
+/// sheet.insert(CellRef::parse("B2").unwrap(), "B1 + 1");
+///
+/// let mut inner = HashMap::new();
+/// inner.insert("rate".to_owned(), 1.5);
+/// let mut vars = CellReferences::new(inner, |cell: CellRef, vars: &dyn VariableResolver| {
+///     let formula = sheet.get(&cell)?;
+///     eval_str_with(formula, &mut ReadOnly::new(vars), &ctx).ok()
+/// });
+/// assert_eq!(eval_str_with("A2 + A1", &mut vars, &ctx), Ok(5.0));
+/// assert!(eval_str_with("B1", &mut vars, &ctx).is_err());
+/// ```
+#[derive(Debug, Default)]
+pub struct CellReferences<R, C> {
+    inner: R,
+    cells: C,
+    resolving: RefCell<Vec<CellRef>>,
+}
+
+impl<R: VariableResolver, C: CellResolver> CellReferences<R, C> {
+    /// Wraps the `inner` resolver, the cell references are resolved using `cells`.
+    pub fn new(inner: R, cells: C) -> Self {
+        Self {
+            inner,
+            cells,
+            resolving: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Consumes the adapter returning the inner resolver.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn get_cell(&self, cell: CellRef) -> Option<f64> {
+        let start = self.resolving.borrow().iter().position(|c| *c == cell);
+        if let Some(start) = start {
+            let mut cycle = self.resolving.borrow()[start..].to_vec();
+            cycle.push(cell);
+            return self.cells.on_cycle(&cycle);
+        }
+        // the borrow is not held while the cell is resolved, it reads the other cells
+        self.resolving.borrow_mut().push(cell);
+        let value = self.cells.resolve(cell, self);
+        self.resolving.borrow_mut().pop();
+        value
+    }
+}
+
+impl<R: VariableResolver, C: CellResolver> VariableResolver for CellReferences<R, C> {
+    fn get(&self, name: &str) -> Option<f64> {
+        match CellRef::parse(name) {
+            Some(cell) => self.get_cell(cell),
+            None => self.inner.get(name),
+        }
+    }
+
+    fn set(&mut self, name: &str, value: f64) {
+        if CellRef::parse(name).is_none() {
+            self.inner.set(name, value)
+        }
+    }
+
+    fn names(&self) -> Vec<String> {
+        self.inner.names()
+    }
+}
+
+/// The point [`SnapshotVariables`](SnapshotVariables) can be rolled back to.
+///
+/// Created by [`SnapshotVariables::snapshot`](SnapshotVariables::snapshot).
+#[derive(Debug, Eq, PartialEq)]
+#[must_use = "snapshot should be rolled back or committed"]
+pub struct Snapshot {
+    journal_len: usize,
+}
+
+/// Variables container that supports cheap snapshots and rollbacks.
+///
+/// Taking a snapshot does not copy the variables.
+/// Instead, while there is a snapshot that is not rolled back or committed,
+/// every change records the previous value of the variable in the journal,
+/// and [`rollback`](SnapshotVariables::rollback) restores the previous values from it.
+///
+/// Snapshots can be nested, but they should be rolled back or committed in the reverse order of their creation.
+#[derive(Debug, Default, Clone)]
+pub struct SnapshotVariables {
+    variables: HashMap<String, f64>,
+    journal: Vec<(String, Option<f64>)>,
+    open_snapshots: usize,
+}
+
+impl SnapshotVariables {
+    /// Creates new empty container.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the value of the variable `name`.
+    #[inline]
+    pub fn get(&self, name: &str) -> Option<f64> {
+        self.variables.get(name).copied()
+    }
+
+    /// Returns the variables this container holds.
+    pub fn variables(&self) -> &HashMap<String, f64> {
+        &self.variables
+    }
+
+    /// Consumes the container returning the variables it holds.
+    pub fn into_variables(self) -> HashMap<String, f64> {
+        self.variables
+    }
+
+    /// Take a snapshot of the current variables.
+    pub fn snapshot(&mut self) -> Snapshot {
+        self.open_snapshots += 1;
+        Snapshot {
+            journal_len: self.journal.len(),
+        }
+    }
+
+    /// Restore the variables to the state they were when the `snapshot` has been taken.
+    pub fn rollback(&mut self, snapshot: Snapshot) {
+        while self.journal.len() > snapshot.journal_len {
+            // unwrap: journal is longer than snapshot position
+            let (name, old) = self.journal.pop().unwrap();
+            match old {
+                Some(value) => {
+                    self.variables.insert(name, value);
+                }
+                None => {
+                    self.variables.remove(&name);
+                }
+            }
+        }
+        self.close_snapshot();
+    }
+
+    /// Keep the changes made since the `snapshot` has been taken.
+    pub fn commit(&mut self, _snapshot: Snapshot) {
+        self.close_snapshot();
+    }
+
+    /// Run `f` and roll back all the changes it made to the variables afterwards.
+    ///
+    /// Useful for what-if analysis.
+    pub fn speculate<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        let snapshot = self.snapshot();
+        let result = f(self);
+        self.rollback(snapshot);
+        result
+    }
+
+    /// Run `f` keeping the changes it made to the variables only if it succeeded.
+    pub fn transaction<T, E>(&mut self, f: impl FnOnce(&mut Self) -> Result<T, E>) -> Result<T, E> {
+        let snapshot = self.snapshot();
+        let result = f(self);
+        match result {
+            Ok(_) => self.commit(snapshot),
+            Err(_) => self.rollback(snapshot),
+        }
+        result
+    }
+
+    fn close_snapshot(&mut self) {
+        self.open_snapshots = self.open_snapshots.saturating_sub(1);
+        if self.open_snapshots == 0 {
+            self.journal.clear();
+        }
+    }
+}
+
+impl From<HashMap<String, f64>> for SnapshotVariables {
+    fn from(variables: HashMap<String, f64>) -> Self {
+        Self {
+            variables,
+            ..Default::default()
+        }
+    }
+}
+
+impl VariableResolver for SnapshotVariables {
+    #[inline]
+    fn get(&self, name: &str) -> Option<f64> {
+        SnapshotVariables::get(self, name)
+    }
+
+    fn set(&mut self, name: &str, value: f64) {
+        let old = match self.variables.get_mut(name) {
+            Some(old) => Some(std::mem::replace(old, value)),
+            None => {
+                self.variables.insert(name.into(), value);
+                None
+            }
+        };
+        if self.open_snapshots > 0 {
+            self.journal.push((name.into(), old));
+        }
+    }
+
+    fn names(&self) -> Vec<String> {
+        self.variables.names()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_case_insensitive() {
+        let mut vars = CaseInsensitive::new(BTreeMap::new());
+        vars.set("Price", 1.0);
+        vars.set("PRICE", 2.0);
+        vars.set("Σx", 3.0);
+        let input_expected = &[
+            ("price", Some(2.0)),
+            ("pRiCe", Some(2.0)),
+            ("σX", Some(3.0)),
+            ("qty", None),
+        ];
+        for (input, expected) in input_expected {
+            assert_eq!(vars.get(input), *expected, "input was {}", input);
+        }
+        assert_eq!(vars.into_inner().len(), 2);
+    }
+
+    #[test]
+    fn test_env_variables() {
+        env::set_var("RUSTY_YARD_TEST_A", "2.5");
+        env::set_var("RUSTY_YARD_TEST_B", "not a number");
+        let mut vars = EnvVariables::new("RUSTY_YARD_TEST_");
+        assert_eq!(vars.get("A"), Some(2.5));
+        assert_eq!(vars.get("B"), None);
+        assert_eq!(vars.get("C"), None);
+        vars.set("A", 1.0);
+        assert_eq!(vars.get("A"), Some(1.0));
+        assert_eq!(env::var("RUSTY_YARD_TEST_A").as_deref(), Ok("2.5"));
+    }
+
+    #[test]
+    fn test_computed() {
+        let mut vars = Computed::new(HashMap::new(), |name: &str| name.parse().ok());
+        assert_eq!(vars.get("a"), None);
+        assert_eq!(vars.get("12"), Some(12.0));
+        vars.set("12", 1.0);
+        assert_eq!(vars.get("12"), Some(1.0));
+    }
+
+    #[test]
+    fn test_cell_ref() {
+        let input_expected = &[
+            ("A1", Some((0, 0))),
+            ("B12", Some((1, 11))),
+            ("Z3", Some((25, 2))),
+            ("AA10", Some((26, 9))),
+            ("$C$4", Some((2, 3))),
+            ("XFD1048576", Some((16383, 1048575))),
+            ("A0", None),
+            ("A01", None),
+            ("a1", None),
+            ("A", None),
+            ("12", None),
+            ("A1B", None),
+            ("ZZZZZZZZZ1", None),
+        ];
+        for (input, expected) in input_expected {
+            let cell = CellRef::parse(input);
+            let expected = expected.map(|(column, row)| CellRef { column, row });
+            assert_eq!(cell, expected, "input was {}", input);
+            if let Some(cell) = cell {
+                assert_eq!(
+                    cell.to_string(),
+                    input.replace('$', ""),
+                    "input was {}",
+                    input
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_cell_references() {
+        struct Cells(HashMap<CellRef, Vec<CellRef>>, RefCell<Vec<String>>);
+
+        impl CellResolver for Cells {
+            // the value of the cell is the sum of the cells it refers to plus 1
+            fn resolve(&self, cell: CellRef, variables: &dyn VariableResolver) -> Option<f64> {
+                let refs = self.0.get(&cell)?;
+                refs.iter().try_fold(1.0, |sum, cell| {
+                    Some(sum + variables.get(&cell.to_string())?)
+                })
+            }
+
+            fn on_cycle(&self, cycle: &[CellRef]) -> Option<f64> {
+                let cycle: Vec<_> = cycle.iter().map(CellRef::to_string).collect();
+                self.1.borrow_mut().push(cycle.join(" "));
+                None
+            }
+        }
+
+        let cell = |name| CellRef::parse(name).unwrap();
+        let mut refs = HashMap::new();
+        refs.insert(cell("A1"), vec![]);
+        refs.insert(cell("A2"), vec![cell("A1"), cell("A1")]);
+        refs.insert(cell("B1"), vec![cell("A2"), cell("B2")]);
+        refs.insert(cell("B2"), vec![cell("B3")]);
+        refs.insert(cell("B3"), vec![cell("B1")]);
+        let mut inner = HashMap::new();
+        inner.insert("x".to_owned(), 2.0);
+        let mut vars = CellReferences::new(inner, Cells(refs, RefCell::new(Vec::new())));
+        assert_eq!(vars.get("A2"), Some(3.0));
+        assert_eq!(vars.get("$A$2"), Some(3.0));
+        assert_eq!(vars.get("x"), Some(2.0));
+        assert_eq!(vars.get("C1"), None);
+        assert_eq!(vars.get("B2"), None);
+        assert_eq!(vars.cells.1.borrow().as_slice(), ["B2 B3 B1 B2"]);
+        vars.set("A1", 10.0);
+        vars.set("y", 1.0);
+        assert_eq!(vars.get("A1"), Some(1.0));
+        assert_eq!(vars.into_inner().len(), 2);
+    }
+
+    #[test]
+    fn test_borrowed_resolver() {
+        let mut inner = HashMap::new();
+        let mut vars = CaseInsensitive::new(&mut inner);
+        vars.set("A", 1.0);
+        assert_eq!(inner.get("a"), Some(&1.0));
+    }
+
+    #[test]
+    fn test_rollback() {
+        let mut vars = SnapshotVariables::new();
+        vars.set("a", 1.0);
+        let snapshot = vars.snapshot();
+        vars.set("a", 2.0);
+        vars.set("a", 3.0);
+        vars.set("b", 4.0);
+        vars.rollback(snapshot);
+        assert_eq!(vars.get("a"), Some(1.0));
+        assert_eq!(vars.get("b"), None);
+        assert!(vars.journal.is_empty());
+    }
+
+    #[test]
+    fn test_nested_snapshots() {
+        let mut vars = SnapshotVariables::new();
+        let outer = vars.snapshot();
+        vars.set("a", 1.0);
+        let inner = vars.snapshot();
+        vars.set("a", 2.0);
+        vars.commit(inner);
+        assert_eq!(vars.get("a"), Some(2.0));
+        vars.rollback(outer);
+        assert_eq!(vars.get("a"), None);
+    }
+
+    #[test]
+    fn test_transaction() {
+        let mut vars = SnapshotVariables::new();
+        let ok: Result<(), ()> = vars.transaction(|vars| {
+            vars.set("a", 1.0);
+            Ok(())
+        });
+        assert_eq!(ok, Ok(()));
+        let err: Result<(), ()> = vars.transaction(|vars| {
+            vars.set("a", 2.0);
+            Err(())
+        });
+        assert_eq!(err, Err(()));
+        assert_eq!(vars.get("a"), Some(1.0));
+        assert_eq!(
+            vars.speculate(|vars| {
+                vars.set("a", 3.0);
+                vars.get("a")
+            }),
+            Some(3.0)
+        );
+        assert_eq!(vars.get("a"), Some(1.0));
+    }
+}
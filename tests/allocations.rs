@@ -0,0 +1,53 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rusty_yard::evaluator::eval_with_vars_and_ctx;
+use rusty_yard::parser::parse_str;
+use rusty_yard::Ctx;
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+fn count_allocations(input: &str, vars: &mut HashMap<String, f64>, ctx: &Ctx) -> usize {
+    let tokens = parse_str(input, ctx).expect("input is valid");
+    let before = ALLOCATIONS.load(Ordering::SeqCst);
+    eval_with_vars_and_ctx(&tokens, vars, ctx).expect("input evaluates");
+    ALLOCATIONS.load(Ordering::SeqCst) - before
+}
+
+#[test]
+fn test_variables_do_not_allocate() {
+    let ctx = Ctx::default_with_macros();
+    let mut vars = HashMap::new();
+    for name in &["a", "b", "c", "d", "e"] {
+        vars.insert(name.to_string(), 1.0);
+    }
+    // only the evaluation stack is allocated
+    let baseline = count_allocations("1 + 1 + 1 + 1 + 1", &mut vars, &ctx);
+    assert_eq!(
+        count_allocations("a + b + c + d + e", &mut vars, &ctx),
+        baseline
+    );
+    assert_eq!(
+        count_allocations("a = b + c + d + e", &mut vars, &ctx),
+        baseline
+    );
+    // new variable needs its name to be allocated
+    assert!(count_allocations("f = b + c + d + e", &mut vars, &ctx) > baseline);
+}
@@ -30,8 +30,7 @@ fn func_ctx() -> Ctx {
 
 #[inline]
 fn vars() -> HashMap<String, f64> {
-    let map = HashMap::new();
-    map
+    HashMap::new()
 }
 
 #[test]
@@ -51,6 +50,15 @@ fn test_evaluation_results_bi_ops() {
         ("1 + 1 + 1", Ok(3.0)),
         ("1 * (2 + 2)", Ok(4.0)),
         ("(2 ^ 3) ^ 2", Ok(64.0)),
+        ("1 + 1 == 2", Ok(1.0)),
+        ("1 != 2 - 1", Ok(0.0)),
+        ("2 * 3 > 5", Ok(1.0)),
+        ("2 >= 3", Ok(0.0)),
+        ("1 < 2 == 2 <= 1", Ok(0.0)),
+        ("1 > 0 && 2 < 10", Ok(1.0)),
+        ("0 && 1 || 1", Ok(1.0)),
+        ("1 || 1 && 0", Ok(1.0)),
+        ("(1 || 1) && 0", Ok(0.0)),
         ("", Err(Other)),
         ("1 + ", Err(ParserError(parser::Error::OperatorAtTheEnd))),
         ("+ 1", Err(ParserError(parser::Error::ExpectedExpression))),
@@ -85,6 +93,13 @@ fn test_evaluation_results_u_ops() {
         ("-+1", Ok(-1.0)),
         ("-+-1", Ok(1.0)),
         ("+-+1", Ok(-1.0)),
+        ("!0", Ok(1.0)),
+        ("!2", Ok(0.0)),
+        ("!!2", Ok(1.0)),
+        ("!-0", Ok(1.0)),
+        ("50%", Ok(0.5)),
+        ("-50%%", Ok(-0.005)),
+        ("!50%", Ok(0.0)),
     ];
     for (input, expected) in input_expected_pair {
         let result = eval_str_with_vars_and_ctx(input, &mut vars, &ctx);
@@ -109,6 +124,18 @@ fn test_evaluation_results_funcs() {
         ("prod(1, 1)", Ok(1.0)),
         ("prod(1, 1, 1)", Ok(1.0)),
         ("sub(2, 1)", Ok(1.0)),
+        ("sum(1, 2, 4) + 1", Ok(8.0)),
+        ("sub(5, 1) + sum(1)", Ok(5.0)),
+        ("1 + sub(sum(2, 1), 1) + 1", Ok(4.0)),
+        ("min(1, 2)", Ok(1.0)),
+        ("sin(0) + cos(0) + tan(0)", Ok(1.0)),
+        ("asin(0) + acos(1) + atan(0) + atan2(0, 1)", Ok(0.0)),
+        ("sinh(0) + cosh(0) + tanh(0)", Ok(1.0)),
+        ("ln(1) + log10(1000) + log2(8)", Ok(6.0)),
+        ("log(4, 16)", Ok(2.0)),
+        ("exp(0) + sqrt(16) + cbrt(27)", Ok(8.0)),
+        ("abs(sub(1, 3)) + floor(2.7) + ceil(2.2)", Ok(7.0)),
+        ("round(2.5) + hypot(3, 4) + signum(sub(0, 2))", Ok(7.0)),
         //TODO: v0.3 this should change
         (
             "sum + 10",
@@ -122,7 +149,7 @@ fn test_evaluation_results_funcs() {
             "sub(1)",
             Err(ParserError(parser::Error::ArityMismatch {
                 id: "sub".to_owned(),
-                expected: 2,
+                expected: 2.into(),
                 actual: 1,
             })),
         ),
@@ -149,6 +176,28 @@ fn test_evaluation_results_all() {
         ("+1 + +2 + +3", Ok(6.0)),
         ("+1 + +2 * +3", Ok(7.0)),
         ("-+-1 + +-+2 * -3", Ok(7.0)),
+        ("sum(1, 2, 4) / 2", Ok(3.5)),
+        ("!(1 > 2) && max(1, 2) == 2", Ok(1.0)),
+        ("!1 == 0", Ok(1.0)),
+    ];
+    for (input, expected) in input_expected_pair {
+        let result = eval_str_with_vars_and_ctx(input, &mut vars, &ctx);
+        assert_eq!(result, *expected, "input was: {}", input);
+    }
+}
+
+#[test]
+fn test_comparison_with_macros() {
+    let ctx = Ctx::default_with_macros();
+    let mut vars = vars();
+    vars.insert("x".to_owned(), 5.0);
+    vars.insert("y".to_owned(), 3.0);
+    let input_expected_pair = &[
+        ("x > 0 && y < 10", Ok(1.0)),
+        ("x == 5", Ok(1.0)),
+        ("z = x >= y", Ok(1.0)),
+        ("z != 1", Ok(0.0)),
+        ("let d = x - y in if(d > 0, d, -d)", Ok(2.0)),
     ];
     for (input, expected) in input_expected_pair {
         let result = eval_str_with_vars_and_ctx(input, &mut vars, &ctx);